@@ -0,0 +1,166 @@
+use std::collections::btree_map::{BTreeMap, Entry};
+use std::collections::HashSet;
+use std::error::Error;
+use std::io::{self, Write};
+use std::iter::FromIterator;
+
+use meilidb_core::{Highlight, Index};
+use meilidb_schema::{Schema, SchemaAttr};
+use serde_json::Value;
+use termcolor::{Color, ColorChoice, ColorSpec, StandardStream, WriteColor};
+
+pub fn display_highlights(text: &str, ranges: &[usize]) -> io::Result<()> {
+    let mut stdout = StandardStream::stdout(ColorChoice::Always);
+    let mut highlighted = false;
+
+    for range in ranges.windows(2) {
+        let [start, end] = match range {
+            [start, end] => [*start, *end],
+            _ => unreachable!(),
+        };
+        if highlighted {
+            stdout.set_color(
+                ColorSpec::new()
+                    .set_fg(Some(Color::Yellow))
+                    .set_underline(true),
+            )?;
+        }
+        write!(&mut stdout, "{}", &text[start..end])?;
+        stdout.reset()?;
+        highlighted = !highlighted;
+    }
+
+    Ok(())
+}
+
+fn char_to_byte_range(index: usize, length: usize, text: &str) -> (usize, usize) {
+    let mut byte_index = 0;
+    let mut byte_length = 0;
+
+    for (n, (i, c)) in text.char_indices().enumerate() {
+        if n == index {
+            byte_index = i;
+        }
+
+        if n + 1 == index + length {
+            byte_length = i - byte_index + c.len_utf8();
+            break;
+        }
+    }
+
+    (byte_index, byte_length)
+}
+
+pub fn create_highlight_areas(text: &str, highlights: &[Highlight]) -> Vec<usize> {
+    let mut byte_indexes = BTreeMap::new();
+
+    for highlight in highlights {
+        let char_index = highlight.char_index as usize;
+        let char_length = highlight.char_length as usize;
+        let (byte_index, byte_length) = char_to_byte_range(char_index, char_length, text);
+
+        match byte_indexes.entry(byte_index) {
+            Entry::Vacant(entry) => {
+                entry.insert(byte_length);
+            }
+            Entry::Occupied(mut entry) => {
+                if *entry.get() < byte_length {
+                    entry.insert(byte_length);
+                }
+            }
+        }
+    }
+
+    let mut title_areas = Vec::new();
+    title_areas.push(0);
+    for (byte_index, length) in byte_indexes {
+        title_areas.push(byte_index);
+        title_areas.push(byte_index + length);
+    }
+    title_areas.push(text.len());
+    title_areas.sort_unstable();
+    title_areas
+}
+
+/// note: matches must have been sorted by `char_index` and `char_length` before being passed.
+///
+/// ```no_run
+/// matches.sort_unstable_by_key(|m| (m.char_index, m.char_length));
+///
+/// let matches = matches.matches.iter().filter(|m| SchemaAttr::new(m.attribute) == attr).cloned();
+///
+/// let (text, matches) = crop_text(&text, matches, 35);
+/// ```
+pub fn crop_text(
+    text: &str,
+    highlights: impl IntoIterator<Item = Highlight>,
+    context: usize,
+) -> (String, Vec<Highlight>) {
+    let mut highlights = highlights.into_iter().peekable();
+
+    let char_index = highlights
+        .peek()
+        .map(|m| m.char_index as usize)
+        .unwrap_or(0);
+    let start = char_index.saturating_sub(context);
+    let text = text.chars().skip(start).take(context * 2).collect();
+
+    let highlights = highlights
+        .take_while(|m| (m.char_index as usize) + (m.char_length as usize) <= start + (context * 2))
+        .map(|highlight| Highlight {
+            char_index: highlight.char_index - start as u16,
+            ..highlight
+        })
+        .collect();
+
+    (text, highlights)
+}
+
+/// Prints the fields of a document returned by a query with the matching words highlighted.
+pub fn display_document(
+    index: &Index,
+    reader: &heed::RoTxn,
+    schema: &Schema,
+    fields: Option<&HashSet<&str>>,
+    doc: &meilidb_core::Document,
+    char_context: usize,
+) -> Result<(), Box<dyn Error>> {
+    let mut doc_highlights = doc.highlights.clone();
+    doc_highlights.sort_unstable_by_key(|m| (m.char_index, m.char_length));
+
+    match index.document::<crate::Document>(reader, fields, doc.id)? {
+        Some(document) => {
+            println!("raw-id: {:?}", doc.id);
+            for (name, value) in document {
+                print!("{}: ", name);
+
+                let text = match value {
+                    Value::String(string) => string,
+                    value => value.to_string(),
+                };
+
+                let attr = schema.attribute(&name);
+                let highlights = doc_highlights
+                    .iter()
+                    .filter(|m| Some(SchemaAttr::new(m.attribute)) == attr)
+                    .cloned();
+                let (text, highlights) = crop_text(&text, highlights, char_context);
+                let areas = create_highlight_areas(&text, &highlights);
+                display_highlights(&text, &areas)?;
+                println!();
+            }
+        }
+        None => eprintln!("missing document {:?}", doc.id),
+    }
+
+    let mut matching_attributes = HashSet::new();
+    for highlight in doc_highlights {
+        let attr = SchemaAttr::new(highlight.attribute);
+        matching_attributes.insert(schema.attribute_name(attr));
+    }
+
+    let matching_attributes = Vec::from_iter(matching_attributes);
+    println!("matching in: {:?}", matching_attributes);
+
+    Ok(())
+}
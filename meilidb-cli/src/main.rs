@@ -0,0 +1,731 @@
+use std::collections::HashSet;
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::iter::FromIterator;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use indexmap::IndexMap;
+use meilidb_core::serde::{csv_documents, CsvOptions};
+use meilidb_core::{Database, Filter, Index, IndexSettings, UpdateStatus, VocabularyFormat};
+use meilidb_schema::Schema;
+use serde_json::Value;
+use structopt::StructOpt;
+
+mod display;
+mod repl;
+
+/// The name of the schema file written in a dump directory.
+const DUMP_SCHEMA_FILE: &str = "schema.toml";
+/// The name of the settings file written in a dump directory, the dumps
+/// made by the previous versions do not have one.
+const DUMP_SETTINGS_FILE: &str = "settings.json";
+/// The name of the documents file written in a dump directory, one json document by line.
+const DUMP_DOCUMENTS_FILE: &str = "documents.ndjson";
+
+type Document = IndexMap<String, Value>;
+
+#[derive(Debug, StructOpt)]
+struct CreateIndexCommand {
+    /// The path of the database to work with, created if it doesn't exist.
+    #[structopt(parse(from_os_str))]
+    database_path: PathBuf,
+
+    /// The uid of the index to create.
+    index_uid: String,
+
+    /// The path to the toml schema of the index.
+    #[structopt(long, parse(from_os_str))]
+    schema: PathBuf,
+
+    /// The name of the index, defaults to its uid.
+    #[structopt(long)]
+    name: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum DataFormat {
+    Csv,
+    Ndjson,
+    Json,
+}
+
+impl DataFormat {
+    fn from_path(path: &Path) -> Option<DataFormat> {
+        let extension = path.extension()?.to_str()?;
+        extension.parse().ok()
+    }
+}
+
+impl FromStr for DataFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<DataFormat, String> {
+        match s.to_lowercase().as_str() {
+            "csv" => Ok(DataFormat::Csv),
+            "ndjson" | "jsonl" => Ok(DataFormat::Ndjson),
+            "json" => Ok(DataFormat::Json),
+            _ => Err(format!(
+                "unknown data format {:?}, must be csv, ndjson or json",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Debug, StructOpt)]
+struct ImportCommand {
+    /// The path of the database to work with.
+    #[structopt(parse(from_os_str))]
+    database_path: PathBuf,
+
+    /// The uid of the index in which the documents are imported.
+    index_uid: String,
+
+    /// The file containing the documents to import.
+    #[structopt(parse(from_os_str))]
+    data_path: PathBuf,
+
+    /// The format of the file, `csv`, `ndjson` or `json`,
+    /// inferred from the file extension by default.
+    #[structopt(long)]
+    format: Option<DataFormat>,
+
+    /// The number of documents sent in each update.
+    #[structopt(long, default_value = "10000")]
+    batch_size: usize,
+}
+
+#[derive(Debug, StructOpt)]
+struct SearchCommand {
+    /// The path of the database to work with.
+    #[structopt(parse(from_os_str))]
+    database_path: PathBuf,
+
+    /// The uid of the index to search in.
+    index_uid: String,
+
+    /// The query to search for.
+    query: String,
+
+    /// Timeout after which the search will return results.
+    #[structopt(long)]
+    fetch_timeout_ms: Option<u64>,
+
+    /// The number of documents to skip.
+    #[structopt(long, default_value = "0")]
+    offset: usize,
+
+    /// The number of returned results.
+    #[structopt(short, long, default_value = "10")]
+    limit: usize,
+
+    /// The number of characters before and after the first match.
+    #[structopt(short = "C", long, default_value = "35")]
+    char_context: usize,
+
+    /// A filter expression like `adult = false AND year > 2000`.
+    #[structopt(short, long)]
+    filter: Option<String>,
+
+    /// Fields that must be displayed.
+    displayed_fields: Vec<String>,
+}
+
+#[derive(Debug, StructOpt)]
+struct DumpCommand {
+    /// The path of the database to work with.
+    #[structopt(parse(from_os_str))]
+    database_path: PathBuf,
+
+    /// The uid of the index to dump.
+    index_uid: String,
+
+    /// The directory where the schema, the settings and the documents are written.
+    #[structopt(parse(from_os_str))]
+    dump_path: PathBuf,
+}
+
+#[derive(Debug, StructOpt)]
+struct RestoreCommand {
+    /// The path of the database to work with, created if it doesn't exist.
+    #[structopt(parse(from_os_str))]
+    database_path: PathBuf,
+
+    /// The uid of the index to create from the dump.
+    index_uid: String,
+
+    /// The directory of a dump made by the `dump` subcommand.
+    #[structopt(parse(from_os_str))]
+    dump_path: PathBuf,
+
+    /// The number of documents sent in each update.
+    #[structopt(long, default_value = "10000")]
+    batch_size: usize,
+}
+
+#[derive(Debug, StructOpt)]
+struct BackupCommand {
+    /// The path of the database to work with.
+    #[structopt(parse(from_os_str))]
+    database_path: PathBuf,
+
+    /// The directory where the backup is written, it can be opened as a database.
+    #[structopt(parse(from_os_str))]
+    backup_path: PathBuf,
+}
+
+#[derive(Debug, StructOpt)]
+struct StatsCommand {
+    /// The path of the database to work with.
+    #[structopt(parse(from_os_str))]
+    database_path: PathBuf,
+
+    /// The uid of the index to show, all the indexes by default.
+    index_uid: Option<String>,
+}
+
+#[derive(Debug, StructOpt)]
+struct VerifyCommand {
+    /// The path of the database to work with.
+    #[structopt(parse(from_os_str))]
+    database_path: PathBuf,
+}
+
+#[derive(Debug, StructOpt)]
+struct ReplCommand {
+    /// The path of the database to work with.
+    #[structopt(parse(from_os_str))]
+    database_path: PathBuf,
+
+    /// The uid of the index to search in, can be changed with the `:use` command.
+    #[structopt(long)]
+    index_uid: Option<String>,
+
+    /// The number of characters before and after the first match.
+    #[structopt(short = "C", long, default_value = "35")]
+    char_context: usize,
+}
+
+#[derive(Debug, StructOpt)]
+struct ShowUpdatesCommand {
+    /// The path of the database to work with.
+    #[structopt(parse(from_os_str))]
+    database_path: PathBuf,
+
+    /// The uid of the index to show the updates of.
+    index_uid: String,
+}
+
+#[derive(Debug, StructOpt)]
+struct ExportVocabularyCommand {
+    /// The path of the database to work with.
+    #[structopt(parse(from_os_str))]
+    database_path: PathBuf,
+
+    /// The uid of the index to export the vocabulary of.
+    index_uid: String,
+
+    /// The output format, `csv` or `json`.
+    #[structopt(long, default_value = "csv")]
+    format: VocabularyFormat,
+}
+
+/// Administrates the indexes of a MeiliDB database.
+#[derive(Debug, StructOpt)]
+#[structopt(name = "meilidb")]
+enum Command {
+    /// Creates an index with the given schema.
+    CreateIndex(CreateIndexCommand),
+    /// Imports the documents of a csv, ndjson or json file in an index.
+    Import(ImportCommand),
+    /// Searches the documents of an index.
+    Search(SearchCommand),
+    /// Writes the schema, the settings and the documents of an index in a directory.
+    Dump(DumpCommand),
+    /// Creates an index from a directory made by the `dump` subcommand.
+    Restore(RestoreCommand),
+    /// Copies the whole database in a directory, even while it is being written.
+    Backup(BackupCommand),
+    /// Shows the statistics of the indexes.
+    Stats(StatsCommand),
+    /// Checks the consistency of the database and of its indexes.
+    Verify(VerifyCommand),
+    /// Runs queries interactively, toggles the criteria and adds synonyms for the session.
+    Repl(ReplCommand),
+    /// Shows the status of the updates of an index.
+    ShowUpdates(ShowUpdatesCommand),
+    /// Exports the words of an index with their statistics.
+    ExportVocabulary(ExportVocabularyCommand),
+}
+
+impl Command {
+    fn path(&self) -> &Path {
+        match self {
+            Command::CreateIndex(command) => &command.database_path,
+            Command::Import(command) => &command.database_path,
+            Command::Search(command) => &command.database_path,
+            Command::Dump(command) => &command.database_path,
+            Command::Restore(command) => &command.database_path,
+            Command::Backup(command) => &command.database_path,
+            Command::Stats(command) => &command.database_path,
+            Command::Verify(command) => &command.database_path,
+            Command::Repl(command) => &command.database_path,
+            Command::ShowUpdates(command) => &command.database_path,
+            Command::ExportVocabulary(command) => &command.database_path,
+        }
+    }
+}
+
+fn open_index(database: &Database, index_uid: &str) -> Result<Index, Box<dyn Error>> {
+    match database.open_index(index_uid) {
+        Some(index) => Ok(index),
+        None => Err(format!("index {:?} not found", index_uid).into()),
+    }
+}
+
+/// Blocks until the update has been processed and returns its error if it failed.
+fn wait_for_update(
+    database: &Database,
+    index: &Index,
+    update_id: u64,
+) -> Result<(), Box<dyn Error>> {
+    while !index.wait_for_update(update_id, Duration::from_secs(1)) {}
+
+    let reader = database.env.read_txn()?;
+    match index.update_status(&reader, update_id)? {
+        Some(UpdateStatus::Processed { content }) => match content.error {
+            Some(error) => Err(format!("update {} failed: {}", update_id, error).into()),
+            None => Ok(()),
+        },
+        _ => Err(format!("update {} has not been processed", update_id).into()),
+    }
+}
+
+fn create_index(
+    database: &Database,
+    index_uid: &str,
+    name: &str,
+    schema: Schema,
+) -> Result<Index, Box<dyn Error>> {
+    if let Some(index) = database.open_index(index_uid) {
+        let reader = database.env.read_txn()?;
+        return match index.main.schema(&reader)? {
+            Some(ref current) if *current == schema => Ok(index),
+            Some(_) => Err(meilidb_core::Error::SchemaDiffer.into()),
+            None => Err(format!("index {:?} already exists without a schema", index_uid).into()),
+        };
+    }
+
+    let index = database.create_index(index_uid)?;
+
+    let mut writer = database.env.write_txn()?;
+    index.main.put_name(&mut writer, name)?;
+    index.main.put_created_at(&mut writer)?;
+    index.main.put_updated_at(&mut writer)?;
+    let update_id = index.schema_update(&mut writer, schema)?;
+    writer.commit()?;
+
+    wait_for_update(database, &index, update_id)?;
+
+    Ok(index)
+}
+
+fn read_schema(path: &Path) -> Result<Schema, Box<dyn Error>> {
+    let string = fs::read_to_string(path)?;
+    Ok(toml::from_str(&string)?)
+}
+
+fn read_documents(
+    path: &Path,
+    format: DataFormat,
+) -> Result<Box<dyn Iterator<Item = Result<Document, Box<dyn Error>>>>, Box<dyn Error>> {
+    let file = File::open(path)?;
+
+    match format {
+        DataFormat::Csv => {
+            let documents: Vec<Document> = csv_documents(file, &CsvOptions::default())?;
+            Ok(Box::new(documents.into_iter().map(Ok::<_, Box<dyn Error>>)))
+        }
+        DataFormat::Ndjson => {
+            let lines = BufReader::new(file).lines();
+            let iter = lines
+                .filter(|line| line.as_ref().map_or(true, |l| !l.trim().is_empty()))
+                .map(|line| -> Result<Document, Box<dyn Error>> {
+                    Ok(serde_json::from_str(&line?)?)
+                });
+            Ok(Box::new(iter))
+        }
+        DataFormat::Json => {
+            let documents: Vec<Document> = serde_json::from_reader(BufReader::new(file))?;
+            Ok(Box::new(documents.into_iter().map(Ok::<_, Box<dyn Error>>)))
+        }
+    }
+}
+
+/// Sends the documents by batches and waits for the last update to be processed,
+/// returns the number of documents sent.
+fn import_documents(
+    database: &Database,
+    index: &Index,
+    documents: impl Iterator<Item = Result<Document, Box<dyn Error>>>,
+    batch_size: usize,
+) -> Result<usize, Box<dyn Error>> {
+    let mut documents = documents.peekable();
+    let mut last_update_id = None;
+    let mut count = 0;
+
+    while documents.peek().is_some() {
+        let mut additions = index.documents_addition();
+        for document in documents.by_ref().take(batch_size.max(1)) {
+            additions.update_document(document?);
+            count += 1;
+        }
+
+        let mut writer = database.env.write_txn()?;
+        let update_id = additions.finalize(&mut writer)?;
+        writer.commit()?;
+
+        eprint!("\rsent {} documents", count);
+        last_update_id = Some(update_id);
+    }
+    eprintln!();
+
+    if let Some(update_id) = last_update_id {
+        eprintln!("waiting for update {}", update_id);
+        wait_for_update(database, index, update_id)?;
+    }
+
+    Ok(count)
+}
+
+fn create_index_command(
+    command: CreateIndexCommand,
+    database: Database,
+) -> Result<(), Box<dyn Error>> {
+    let schema = read_schema(&command.schema)?;
+    let name = command.name.as_ref().unwrap_or(&command.index_uid);
+    create_index(&database, &command.index_uid, name, schema)?;
+    println!("index {:?} created", command.index_uid);
+    Ok(())
+}
+
+fn import_command(command: ImportCommand, database: Database) -> Result<(), Box<dyn Error>> {
+    let start = Instant::now();
+    let index = open_index(&database, &command.index_uid)?;
+
+    let format = match command
+        .format
+        .or_else(|| DataFormat::from_path(&command.data_path))
+    {
+        Some(format) => format,
+        None => return Err("cannot infer the format of the file, use the --format option".into()),
+    };
+
+    let documents = read_documents(&command.data_path, format)?;
+    let count = import_documents(&database, &index, documents, command.batch_size)?;
+
+    println!("{} documents imported in {:.2?}", count, start.elapsed());
+    Ok(())
+}
+
+fn search_command(command: SearchCommand, database: Database) -> Result<(), Box<dyn Error>> {
+    let start = Instant::now();
+    let index = open_index(&database, &command.index_uid)?;
+
+    let reader = database.env.read_txn()?;
+    let schema = index
+        .main
+        .schema(&reader)?
+        .ok_or(meilidb_core::Error::SchemaMissing)?;
+
+    let fields = command.displayed_fields.iter().map(String::as_str);
+    let fields = HashSet::from_iter(fields);
+    let fields = if fields.is_empty() {
+        None
+    } else {
+        Some(&fields)
+    };
+
+    let mut builder = index.query_builder();
+    if let Some(timeout) = command.fetch_timeout_ms {
+        builder.with_fetch_timeout(Duration::from_millis(timeout));
+    }
+    if let Some(ref filter) = command.filter {
+        builder.with_filter_expression(filter.parse::<Filter>()?);
+    }
+
+    let range = command.offset..command.offset + command.limit;
+    let documents = builder.query(&reader, &command.query, range)?;
+
+    let number_of_documents = documents.len();
+    for doc in documents {
+        display::display_document(&index, &reader, &schema, fields, &doc, command.char_context)?;
+        println!();
+    }
+
+    eprintln!(
+        "===== Found {} results in {:.2?} =====",
+        number_of_documents,
+        start.elapsed()
+    );
+
+    Ok(())
+}
+
+fn dump_command(command: DumpCommand, database: Database) -> Result<(), Box<dyn Error>> {
+    let start = Instant::now();
+    let index = open_index(&database, &command.index_uid)?;
+
+    let reader = database.env.read_txn()?;
+    let schema = index
+        .main
+        .schema(&reader)?
+        .ok_or(meilidb_core::Error::SchemaMissing)?;
+
+    fs::create_dir_all(&command.dump_path)?;
+    fs::write(
+        command.dump_path.join(DUMP_SCHEMA_FILE),
+        toml::to_string(&schema)?,
+    )?;
+
+    let settings = IndexSettings::read(&reader, &index)?;
+    fs::write(
+        command.dump_path.join(DUMP_SETTINGS_FILE),
+        serde_json::to_string_pretty(&settings)?,
+    )?;
+
+    let file = File::create(command.dump_path.join(DUMP_DOCUMENTS_FILE))?;
+    let mut file = BufWriter::new(file);
+
+    let mut count = 0;
+    for document_id in index.documents_fields_counts.documents_ids(&reader)? {
+        let document_id = document_id?;
+        // the attributes that are not displayed are dumped too
+        if let Some(document) = index.raw_document(&reader, document_id)? {
+            serde_json::to_writer(&mut file, &document)?;
+            file.write_all(b"\n")?;
+            count += 1;
+        }
+    }
+    file.flush()?;
+
+    println!(
+        "{} documents dumped in {:.2?} at: {:?}",
+        count,
+        start.elapsed(),
+        command.dump_path
+    );
+    Ok(())
+}
+
+fn restore_command(command: RestoreCommand, database: Database) -> Result<(), Box<dyn Error>> {
+    let start = Instant::now();
+    if database.open_index(&command.index_uid).is_some() {
+        return Err(format!("index {:?} already exists", command.index_uid).into());
+    }
+
+    let schema = read_schema(&command.dump_path.join(DUMP_SCHEMA_FILE))?;
+    let index = create_index(&database, &command.index_uid, &command.index_uid, schema)?;
+
+    // the settings are written before the documents to be used to index them
+    let settings_path = command.dump_path.join(DUMP_SETTINGS_FILE);
+    if settings_path.exists() {
+        let settings: IndexSettings = serde_json::from_str(&fs::read_to_string(settings_path)?)?;
+        let mut writer = database.env.write_txn()?;
+        settings.write(&mut writer, &index)?;
+        writer.commit()?;
+    }
+
+    let documents_path = command.dump_path.join(DUMP_DOCUMENTS_FILE);
+    let documents = read_documents(&documents_path, DataFormat::Ndjson)?;
+    let count = import_documents(&database, &index, documents, command.batch_size)?;
+
+    println!("{} documents restored in {:.2?}", count, start.elapsed());
+    Ok(())
+}
+
+fn backup_command(command: BackupCommand, database: Database) -> Result<(), Box<dyn Error>> {
+    let start = Instant::now();
+    database.hot_backup(&command.backup_path)?;
+    println!(
+        "database backed up in {:.2?} at: {:?}",
+        start.elapsed(),
+        command.backup_path
+    );
+    Ok(())
+}
+
+fn stats_command(command: StatsCommand, database: Database) -> Result<(), Box<dyn Error>> {
+    let indexes_uids = match command.index_uid {
+        Some(index_uid) => vec![index_uid],
+        None => database.indexes_uids(),
+    };
+
+    let reader = database.env.read_txn()?;
+    for index_uid in indexes_uids {
+        let index = open_index(&database, &index_uid)?;
+
+        let name = index.main.name(&reader)?;
+        let number_of_documents = index.main.number_of_documents(&reader)?;
+        let number_of_words = index.main.words_fst(&reader)?.map_or(0, |fst| fst.len());
+        let tier_stats = index.tier_stats(&reader)?;
+
+        println!("{}", index_uid);
+        println!("  name: {}", name.unwrap_or_default());
+        if let Some(created_at) = index.main.created_at(&reader)? {
+            println!("  created at: {}", created_at);
+        }
+        if let Some(updated_at) = index.main.updated_at(&reader)? {
+            println!("  updated at: {}", updated_at);
+        }
+        println!("  documents: {}", number_of_documents);
+        println!("  words: {}", number_of_words);
+        println!(
+            "  hot documents: {}, cold documents: {} ({} bytes)",
+            tier_stats.hot_documents, tier_stats.cold_documents, tier_stats.cold_size
+        );
+
+        if let (Some(schema), Some(frequency)) = (
+            index.main.schema(&reader)?,
+            index.main.fields_frequency(&reader)?,
+        ) {
+            println!("  fields frequency:");
+            for (name, _, _) in schema.iter() {
+                let count = frequency.get(name).cloned().unwrap_or(0);
+                println!("    {}: {}", name, count);
+            }
+        }
+        println!();
+    }
+
+    Ok(())
+}
+
+fn verify_index(database: &Database, index_uid: &str) -> Result<Vec<String>, Box<dyn Error>> {
+    let mut problems = Vec::new();
+    let index = open_index(database, index_uid)?;
+    let reader = database.env.read_txn()?;
+
+    let schema = index.main.schema(&reader)?;
+    let number_of_documents = index.main.number_of_documents(&reader)?;
+
+    let mut stored_documents = 0;
+    for document_id in index.documents_fields_counts.documents_ids(&reader)? {
+        let document_id = document_id?;
+        stored_documents += 1;
+
+        if schema.is_some() {
+            let document = index.document::<Document>(&reader, None, document_id)?;
+            if document.is_none() {
+                problems.push(format!("document {:?} has no fields", document_id));
+            }
+        }
+    }
+
+    if schema.is_none() && stored_documents != 0 {
+        problems.push(format!(
+            "{} documents are stored but the schema is missing",
+            stored_documents
+        ));
+    }
+
+    if stored_documents != number_of_documents {
+        problems.push(format!(
+            "{} documents are stored but the number of documents is {}",
+            stored_documents, number_of_documents
+        ));
+    }
+
+    Ok(problems)
+}
+
+fn verify_command(_command: VerifyCommand, database: Database) -> Result<(), Box<dyn Error>> {
+    let mut inconsistencies = 0;
+
+    let report = database.startup_report();
+    for index_uid in &report.missing_indexes {
+        println!("{}: some stores of the index are missing", index_uid);
+        inconsistencies += 1;
+    }
+    for store in &report.orphaned_stores {
+        println!("{}: orphaned store, it has been cleared", store);
+        inconsistencies += 1;
+    }
+
+    for index_uid in database.indexes_uids() {
+        if report.missing_indexes.contains(&index_uid) {
+            continue;
+        }
+
+        let problems = verify_index(&database, &index_uid)?;
+        if problems.is_empty() {
+            println!("{}: ok", index_uid);
+        }
+        for problem in &problems {
+            println!("{}: {}", index_uid, problem);
+        }
+        inconsistencies += problems.len();
+    }
+
+    if inconsistencies != 0 {
+        return Err(format!("{} inconsistencies found", inconsistencies).into());
+    }
+
+    Ok(())
+}
+
+fn repl_command(command: ReplCommand, database: Database) -> Result<(), Box<dyn Error>> {
+    let index_uid = command.index_uid.as_ref().map(String::as_str);
+    repl::run(&database, index_uid, command.char_context)
+}
+
+fn show_updates_command(
+    command: ShowUpdatesCommand,
+    database: Database,
+) -> Result<(), Box<dyn Error>> {
+    let index = open_index(&database, &command.index_uid)?;
+
+    let reader = database.env.read_txn()?;
+    let updates = index.all_updates_status(&reader)?;
+    println!("{:#?}", updates);
+
+    Ok(())
+}
+
+fn export_vocabulary_command(
+    command: ExportVocabularyCommand,
+    database: Database,
+) -> Result<(), Box<dyn Error>> {
+    let index = open_index(&database, &command.index_uid)?;
+
+    let reader = database.env.read_txn()?;
+    let stdout = io::stdout();
+    let count = index.export_vocabulary(&reader, stdout.lock(), command.format)?;
+    eprintln!("{} words exported", count);
+
+    Ok(())
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    env_logger::init();
+
+    let opt = Command::from_args();
+    let database = Database::open_or_create(opt.path())?;
+
+    match opt {
+        Command::CreateIndex(command) => create_index_command(command, database),
+        Command::Import(command) => import_command(command, database),
+        Command::Search(command) => search_command(command, database),
+        Command::Dump(command) => dump_command(command, database),
+        Command::Restore(command) => restore_command(command, database),
+        Command::Backup(command) => backup_command(command, database),
+        Command::Stats(command) => stats_command(command, database),
+        Command::Verify(command) => verify_command(command, database),
+        Command::Repl(command) => repl_command(command, database),
+        Command::ShowUpdates(command) => show_updates_command(command, database),
+        Command::ExportVocabulary(command) => export_vocabulary_command(command, database),
+    }
+}
@@ -0,0 +1,298 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error;
+use std::iter::FromIterator;
+use std::time::Instant;
+
+use meilidb_core::criterion::RankingRule;
+use meilidb_core::{Database, Index, QueryResult};
+use rustyline::{Config, Editor};
+
+use crate::display;
+
+const HISTORY_FILE: &str = "query-history.txt";
+
+const HELP: &str = "\
+Type a query to search the selected index or one of the following commands:
+  :use <index>                          selects the index to search in
+  :indexes                              lists the indexes of the database
+  :criteria                             lists the ranking rules and whether they are enabled
+  :toggle <rule>                        enables or disables a ranking rule, e.g. proximity or asc(year)
+  :synonym <word> <alternative>, ...    adds synonyms for the session only
+  :synonyms                             lists the synonyms of the session
+  :clear-synonyms                       removes the synonyms of the session
+  :explain                              shows or hides the ranking explanations of the results
+  :limit <number>                       sets the number of results
+  :help                                 shows this message
+  :quit                                 exits";
+
+/// The state of an interactive session, the criteria and the synonyms
+/// modified in it are never written in the database.
+struct Session<'a> {
+    database: &'a Database,
+    index: Option<Index>,
+    rules: Vec<(RankingRule, bool)>,
+    synonyms: BTreeMap<String, BTreeSet<String>>,
+    explain: bool,
+    limit: usize,
+    char_context: usize,
+}
+
+impl<'a> Session<'a> {
+    fn new(database: &'a Database, char_context: usize) -> Session<'a> {
+        Session {
+            database,
+            index: None,
+            rules: Vec::new(),
+            synonyms: BTreeMap::new(),
+            explain: true,
+            limit: 10,
+            char_context,
+        }
+    }
+
+    fn use_index(&mut self, index_uid: &str) -> Result<(), Box<dyn Error>> {
+        let index = crate::open_index(self.database, index_uid)?;
+
+        let reader = self.database.env.read_txn()?;
+        let rules = index
+            .main
+            .criteria(&reader)?
+            .unwrap_or_else(RankingRule::default_rules);
+
+        self.rules = rules.into_iter().map(|rule| (rule, true)).collect();
+        self.index = Some(index);
+        println!("searching in {:?}", index_uid);
+
+        Ok(())
+    }
+
+    fn toggle_rule(&mut self, rule: &str) -> Result<(), Box<dyn Error>> {
+        let rule: RankingRule = rule.parse()?;
+        match self.rules.iter_mut().find(|(r, _)| *r == rule) {
+            Some((_, enabled)) => *enabled = !*enabled,
+            None => self.rules.push((rule, true)),
+        }
+        self.print_criteria();
+        Ok(())
+    }
+
+    fn print_criteria(&self) {
+        for (rule, enabled) in &self.rules {
+            let mark = if *enabled { 'x' } else { ' ' };
+            println!("[{}] {}", mark, rule);
+        }
+    }
+
+    fn add_synonyms(&mut self, args: &str) -> Result<(), Box<dyn Error>> {
+        let mut iter = args.trim().splitn(2, char::is_whitespace);
+        let word = iter.next().unwrap_or_default().to_lowercase();
+        let alternatives: BTreeSet<_> = iter
+            .next()
+            .unwrap_or_default()
+            .split(',')
+            .map(|alternative| alternative.trim().to_lowercase())
+            .filter(|alternative| !alternative.is_empty())
+            .collect();
+
+        if word.is_empty() || alternatives.is_empty() {
+            return Err("usage: :synonym <word> <alternative>, ...".into());
+        }
+
+        self.synonyms.entry(word).or_default().extend(alternatives);
+        Ok(())
+    }
+
+    fn print_synonyms(&self) {
+        for (word, alternatives) in &self.synonyms {
+            let alternatives = Vec::from_iter(alternatives.iter().map(String::as_str));
+            println!("{}: {}", word, alternatives.join(", "));
+        }
+    }
+
+    fn search(&self, query: &str) -> Result<(), Box<dyn Error>> {
+        let index = match &self.index {
+            Some(index) => index,
+            None => return Err("no index selected, use `:use <index>`".into()),
+        };
+
+        if self.synonyms.is_empty() {
+            let reader = self.database.env.read_txn()?;
+            self.search_with(index, &reader, query)
+        } else {
+            // the synonyms of the session are written in a transaction that is never committed
+            let mut writer = self.database.env.write_txn()?;
+            put_synonyms(index, &mut writer, &self.synonyms)?;
+            let result = self.search_with(index, &writer, query);
+            writer.abort();
+            result
+        }
+    }
+
+    fn search_with(
+        &self,
+        index: &Index,
+        reader: &heed::RoTxn,
+        query: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        let start = Instant::now();
+
+        let schema = index
+            .main
+            .schema(reader)?
+            .ok_or(meilidb_core::Error::SchemaMissing)?;
+        let ranked_map = index.main.ranked_map(reader)?.unwrap_or_default();
+
+        let rules: Vec<_> = self
+            .rules
+            .iter()
+            .filter(|(_, enabled)| *enabled)
+            .map(|(rule, _)| rule.clone())
+            .collect();
+        let criteria = RankingRule::criteria(&rules, &ranked_map, &schema);
+
+        let builder = index.query_builder_with_criteria(criteria);
+        let result = builder.query_with_buckets(reader, query, 0..self.limit)?;
+
+        for (i, doc) in result.documents.iter().enumerate() {
+            display::display_document(index, reader, &schema, None, doc, self.char_context)?;
+            if self.explain {
+                println!("ranking: {}", explanation(&result, i));
+            }
+            println!();
+        }
+
+        eprintln!(
+            "===== Found {} results in {:.2?} =====",
+            result.documents.len(),
+            start.elapsed()
+        );
+
+        Ok(())
+    }
+
+    fn execute(&mut self, line: &str) -> Result<bool, Box<dyn Error>> {
+        let line = line.trim();
+        if !line.starts_with(':') {
+            if !line.is_empty() {
+                self.search(line)?;
+            }
+            return Ok(true);
+        }
+
+        let mut iter = line[1..].splitn(2, char::is_whitespace);
+        let command = iter.next().unwrap_or_default();
+        let args = iter.next().unwrap_or_default().trim();
+
+        match command {
+            "use" => self.use_index(args)?,
+            "indexes" => {
+                for index_uid in self.database.indexes_uids() {
+                    println!("{}", index_uid);
+                }
+            }
+            "criteria" => self.print_criteria(),
+            "toggle" => self.toggle_rule(args)?,
+            "synonym" => self.add_synonyms(args)?,
+            "synonyms" => self.print_synonyms(),
+            "clear-synonyms" => self.synonyms.clear(),
+            "explain" => {
+                self.explain = !self.explain;
+                println!(
+                    "explanations {}",
+                    if self.explain { "shown" } else { "hidden" }
+                );
+            }
+            "limit" => self.limit = args.parse()?,
+            "help" => println!("{}", HELP),
+            "quit" | "exit" => return Ok(false),
+            _ => return Err(format!("unknown command {:?}, type `:help`", command).into()),
+        }
+
+        Ok(true)
+    }
+}
+
+/// Describes why the document is ranked at its position in the results.
+fn explanation(result: &QueryResult, position: usize) -> String {
+    let bucket = result
+        .buckets_starts
+        .iter()
+        .filter(|start| **start <= position)
+        .count();
+
+    let reason = match result.decisive_criteria.get(position) {
+        _ if position == 0 => "first result".to_string(),
+        Some(Some(criterion)) => format!("ranked after the previous result by {}", criterion),
+        _ => "equal to the previous result for all the criteria".to_string(),
+    };
+
+    format!("{} (bucket {})", reason, bucket)
+}
+
+fn put_synonyms(
+    index: &Index,
+    writer: &mut heed::RwTxn,
+    synonyms: &BTreeMap<String, BTreeSet<String>>,
+) -> Result<(), Box<dyn Error>> {
+    let mut words = match index.main.synonyms_fst(writer)? {
+        Some(words) => BTreeSet::from_iter(words.stream().into_strs()?),
+        None => BTreeSet::new(),
+    };
+
+    for (word, alternatives) in synonyms {
+        let mut alternatives = alternatives.clone();
+        if let Some(current) = index.synonyms.synonyms(writer, word.as_bytes())? {
+            alternatives.extend(current.stream().into_strs()?);
+        }
+
+        let alternatives = fst::Set::from_iter(alternatives)?;
+        index
+            .synonyms
+            .put_synonyms(writer, word.as_bytes(), &alternatives)?;
+        words.insert(word.clone());
+    }
+
+    let words = fst::Set::from_iter(words)?;
+    index.main.put_synonyms_fst(writer, &words)?;
+
+    Ok(())
+}
+
+pub fn run(
+    database: &Database,
+    index_uid: Option<&str>,
+    char_context: usize,
+) -> Result<(), Box<dyn Error>> {
+    let mut session = Session::new(database, char_context);
+
+    let indexes_uids = database.indexes_uids();
+    match index_uid {
+        Some(index_uid) => session.use_index(index_uid)?,
+        None if indexes_uids.len() == 1 => session.use_index(&indexes_uids[0])?,
+        None => println!("select an index with `:use <index>`, type `:help` for the commands"),
+    }
+
+    let config = Config::builder().auto_add_history(true).build();
+    let mut readline = Editor::<()>::with_config(config);
+    let _ = readline.load_history(HISTORY_FILE);
+
+    for result in readline.iter("meilidb> ") {
+        let line = match result {
+            Ok(line) => line,
+            Err(err) => {
+                println!("Error: {:?}", err);
+                break;
+            }
+        };
+
+        match session.execute(&line) {
+            Ok(true) => (),
+            Ok(false) => break,
+            Err(e) => eprintln!("Error: {}", e),
+        }
+    }
+
+    readline.save_history(HISTORY_FILE)?;
+
+    Ok(())
+}
@@ -0,0 +1,61 @@
+use std::{error, fmt, io};
+
+use http::StatusCode;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    Http(http::Error),
+    Isahc(isahc::Error),
+    SerdeJson(serde_json::Error),
+    SerdeQs(serde_qs::Error),
+    Response { status: StatusCode, message: String },
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Error {
+        Error::Io(error)
+    }
+}
+
+impl From<http::Error> for Error {
+    fn from(error: http::Error) -> Error {
+        Error::Http(error)
+    }
+}
+
+impl From<isahc::Error> for Error {
+    fn from(error: isahc::Error) -> Error {
+        Error::Isahc(error)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(error: serde_json::Error) -> Error {
+        Error::SerdeJson(error)
+    }
+}
+
+impl From<serde_qs::Error> for Error {
+    fn from(error: serde_qs::Error) -> Error {
+        Error::SerdeQs(error)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::Error::*;
+        match self {
+            Io(e) => write!(f, "{}", e),
+            Http(e) => write!(f, "http; {}", e),
+            Isahc(e) => write!(f, "request; {}", e),
+            SerdeJson(e) => write!(f, "serde json; {}", e),
+            SerdeQs(e) => write!(f, "serde qs; {}", e),
+            Response { status, message } => write!(f, "server returned {}; {}", status, message),
+        }
+    }
+}
+
+impl error::Error for Error {}
@@ -0,0 +1,636 @@
+//! A typed asynchronous client for the MeiliDB HTTP API.
+//!
+//! ```no_run
+//! # async fn run() -> meilidb_client::Result<()> {
+//! use meilidb_client::{Client, SearchQuery};
+//! use serde_json::Value;
+//!
+//! let client = Client::new("http://127.0.0.1:8080")?.with_api_key("masterKey");
+//! let movies = client.index("movies");
+//!
+//! let query = SearchQuery { q: "batman".into(), ..SearchQuery::default() };
+//! let result = movies.search::<Value>(&query).await?;
+//! println!("{} hits", result.hits.len());
+//! # Ok(())
+//! # }
+//! ```
+
+mod error;
+
+pub use self::error::{Error, Result};
+pub use meilidb_dto::*;
+
+use std::collections::BTreeMap;
+use std::io::Write;
+
+use flate2::write::GzEncoder;
+use http::{Method, Request, StatusCode};
+use isahc::prelude::*;
+use isahc::{Body, HttpClient};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+#[derive(Debug, serde::Deserialize)]
+struct ErrorMessage {
+    message: String,
+}
+
+pub struct Client {
+    url: String,
+    api_key: Option<String>,
+    gzip_min_size: Option<usize>,
+    http: HttpClient,
+}
+
+impl Client {
+    pub fn new(url: impl Into<String>) -> Result<Client> {
+        let url = url.into().trim_end_matches('/').to_string();
+        let http = HttpClient::new()?;
+        Ok(Client {
+            url,
+            api_key: None,
+            gzip_min_size: None,
+            http,
+        })
+    }
+
+    pub fn with_api_key(mut self, api_key: impl Into<String>) -> Client {
+        self.api_key = Some(api_key.into());
+        self
+    }
+
+    /// Compresses with gzip the request payloads from the given size in bytes,
+    /// it saves bandwidth when sending large batches of documents.
+    pub fn with_payload_compression(mut self, min_size: usize) -> Client {
+        self.gzip_min_size = Some(min_size);
+        self
+    }
+
+    pub fn index(&self, uid: impl Into<String>) -> Index {
+        Index {
+            client: self,
+            uid: uid.into(),
+        }
+    }
+
+    pub async fn list_indexes(&self) -> Result<Vec<IndexResponse>> {
+        self.request(Method::GET, "/indexes", None::<&()>).await
+    }
+
+    /// The indexes whose metadata matches a `key:value` filter.
+    pub async fn list_indexes_by_metadata(&self, filter: &str) -> Result<Vec<IndexResponse>> {
+        let mut query = std::collections::BTreeMap::new();
+        query.insert("metadata", filter);
+        let path = format!("/indexes?{}", serde_qs::to_string(&query)?);
+        self.request(Method::GET, &path, None::<&()>).await
+    }
+
+    /// The indexes filtered by uid or name prefix and by metadata, sorted by uid.
+    pub async fn search_indexes(&self, query: &IndexesQuery) -> Result<Vec<IndexResponse>> {
+        let path = format!("/indexes?{}", serde_qs::to_string(query)?);
+        self.request(Method::GET, &path, None::<&()>).await
+    }
+
+    pub async fn create_index(&self, request: &IndexCreateRequest) -> Result<IndexCreateResponse> {
+        self.request(Method::POST, "/indexes", Some(request)).await
+    }
+
+    pub async fn clone_index(
+        &self,
+        uid: &str,
+        request: &IndexCloneRequest,
+    ) -> Result<IndexCreateResponse> {
+        let path = format!("/indexes/{}/clone", uid);
+        self.request(Method::POST, &path, Some(request)).await
+    }
+
+    pub async fn delete_index(&self, uid: &str) -> Result<()> {
+        let path = format!("/indexes/{}", uid);
+        self.send(Method::DELETE, &path, None::<&()>).await?;
+        Ok(())
+    }
+
+    /// The number of searches of all the indexes and of all the keys, requires the master key.
+    pub async fn usage(&self) -> Result<UsageResponse> {
+        self.request(Method::GET, "/usage", None::<&()>).await
+    }
+
+    async fn send<B: Serialize + ?Sized>(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<&B>,
+    ) -> Result<String> {
+        let url = format!("{}{}", self.url, path);
+
+        let mut builder = Request::builder();
+        builder
+            .method(method)
+            .uri(url.as_str())
+            .header("Content-Type", "application/json");
+
+        if let Some(api_key) = &self.api_key {
+            builder.header("X-Meili-API-Key", api_key.as_str());
+        }
+
+        let body = match body {
+            Some(body) => {
+                let bytes = serde_json::to_vec(body)?;
+                match self.gzip_min_size {
+                    Some(min_size) if bytes.len() >= min_size => {
+                        let mut encoder =
+                            GzEncoder::new(Vec::new(), flate2::Compression::default());
+                        encoder.write_all(&bytes)?;
+                        builder.header("Content-Encoding", "gzip");
+                        Body::from(encoder.finish()?)
+                    }
+                    _ => Body::from(bytes),
+                }
+            }
+            None => Body::empty(),
+        };
+
+        let request = builder.body(body)?;
+        let mut response = self.http.send_async(request).await?;
+        let status = response.status();
+        let text = response.text_async().await?;
+
+        if status.is_success() {
+            Ok(text)
+        } else {
+            let message = match serde_json::from_str::<ErrorMessage>(&text) {
+                Ok(error) => error.message,
+                Err(_) => text,
+            };
+            Err(Error::Response { status, message })
+        }
+    }
+
+    async fn request<B, T>(&self, method: Method, path: &str, body: Option<&B>) -> Result<T>
+    where
+        B: Serialize + ?Sized,
+        T: DeserializeOwned,
+    {
+        let text = self.send(method, path, body).await?;
+        let value = serde_json::from_str(&text)?;
+        Ok(value)
+    }
+}
+
+pub struct Index<'a> {
+    client: &'a Client,
+    uid: String,
+}
+
+impl Index<'_> {
+    pub fn uid(&self) -> &str {
+        &self.uid
+    }
+
+    fn path(&self, route: &str) -> String {
+        format!("/indexes/{}{}", self.uid, route)
+    }
+
+    pub async fn get(&self) -> Result<IndexResponse> {
+        let path = self.path("");
+        self.client.request(Method::GET, &path, None::<&()>).await
+    }
+
+    pub async fn delete(&self) -> Result<()> {
+        self.client.delete_index(&self.uid).await
+    }
+
+    pub async fn update_status(&self, update_id: u64) -> Result<UpdateStatus> {
+        let path = self.path(&format!("/updates/{}", update_id));
+        self.client.request(Method::GET, &path, None::<&()>).await
+    }
+
+    /// Applies again a processed update on a scratch copy of the index settings and of
+    /// the documents it touched, the index is not modified, requires the admin key and
+    /// the server to record the updates replays.
+    pub async fn replay_update(&self, update_id: u64) -> Result<ProcessedUpdateResult> {
+        let path = self.path(&format!("/updates/{}/replay", update_id));
+        self.client.request(Method::POST, &path, None::<&()>).await
+    }
+
+    pub async fn all_updates_status(&self) -> Result<Vec<UpdateStatus>> {
+        let path = self.path("/updates");
+        self.client.request(Method::GET, &path, None::<&()>).await
+    }
+
+    pub async fn document<T: DeserializeOwned>(&self, identifier: &str) -> Result<Option<T>> {
+        let path = self.path(&format!("/documents/{}", identifier));
+        match self.client.request(Method::GET, &path, None::<&()>).await {
+            Ok(document) => Ok(Some(document)),
+            Err(Error::Response { status, .. }) if status == StatusCode::NOT_FOUND => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub async fn documents<T: DeserializeOwned>(
+        &self,
+        offset: usize,
+        limit: usize,
+    ) -> Result<Vec<T>> {
+        let path = self.path(&format!("/documents?offset={}&limit={}", offset, limit));
+        self.client.request(Method::GET, &path, None::<&()>).await
+    }
+
+    /// Returns the documents in the order of the identifiers, `None` for the missing ones.
+    pub async fn fetch_documents<T: DeserializeOwned>(
+        &self,
+        request: &FetchDocumentsRequest,
+    ) -> Result<Vec<Option<T>>> {
+        let path = self.path("/documents/fetch");
+        self.client
+            .request(Method::POST, &path, Some(request))
+            .await
+    }
+
+    /// Adds the documents, replacing the ones with the same identifiers.
+    pub async fn add_or_replace_documents<T: Serialize>(
+        &self,
+        documents: &[T],
+    ) -> Result<IndexUpdateResponse> {
+        let path = self.path("/documents");
+        self.client
+            .request(Method::POST, &path, Some(documents))
+            .await
+    }
+
+    /// Adds the documents, merging the fields of the ones with the same identifiers.
+    pub async fn add_or_update_documents<T: Serialize>(
+        &self,
+        documents: &[T],
+    ) -> Result<IndexUpdateResponse> {
+        let path = self.path("/documents");
+        self.client
+            .request(Method::PATCH, &path, Some(documents))
+            .await
+    }
+
+    pub async fn delete_documents<I: Serialize>(
+        &self,
+        identifiers: &[I],
+    ) -> Result<IndexUpdateResponse> {
+        let path = self.path("/documents/delete");
+        self.client
+            .request(Method::POST, &path, Some(identifiers))
+            .await
+    }
+
+    /// Deletes the documents whose stored fields match the filter expression.
+    pub async fn delete_documents_by_filter(&self, filter: &str) -> Result<IndexUpdateResponse> {
+        let path = self.path("/documents/delete-by-filter");
+        let request = DeleteByFilterRequest {
+            filter: filter.to_owned(),
+        };
+        self.client
+            .request(Method::POST, &path, Some(&request))
+            .await
+    }
+
+    pub async fn clear_documents(&self) -> Result<IndexUpdateResponse> {
+        let path = self.path("/documents");
+        self.client
+            .request(Method::DELETE, &path, None::<&()>)
+            .await
+    }
+
+    pub async fn search<T: DeserializeOwned>(
+        &self,
+        query: &SearchQuery,
+    ) -> Result<SearchResult<T>> {
+        let query = serde_qs::to_string(query)?;
+        let path = self.path(&format!("/search?{}", query));
+        self.client.request(Method::GET, &path, None::<&()>).await
+    }
+
+    pub async fn settings(&self) -> Result<SettingBody> {
+        let path = self.path("/settings");
+        self.client.request(Method::GET, &path, None::<&()>).await
+    }
+
+    pub async fn update_settings(&self, settings: &SettingBody) -> Result<IndexUpdateResponse> {
+        let path = self.path("/settings");
+        self.client
+            .request(Method::POST, &path, Some(settings))
+            .await
+    }
+
+    /// Changes only the given settings, the stop words and synonyms are added and removed.
+    pub async fn patch_settings(
+        &self,
+        patch: &SettingsPatchRequest,
+    ) -> Result<IndexUpdateResponse> {
+        let path = self.path("/settings");
+        self.client.request(Method::PATCH, &path, Some(patch)).await
+    }
+
+    pub async fn update_limits(&self) -> Result<UpdateLimitsBody> {
+        let path = self.path("/settings/limits");
+        self.client.request(Method::GET, &path, None::<&()>).await
+    }
+
+    /// Replaces the limits enforced on the documents updates, takes effect immediately.
+    pub async fn set_update_limits(&self, limits: &UpdateLimitsBody) -> Result<UpdateLimitsBody> {
+        let path = self.path("/settings/limits");
+        self.client.request(Method::POST, &path, Some(limits)).await
+    }
+
+    pub async fn pagination_settings(&self) -> Result<PaginationSettingsBody> {
+        let path = self.path("/settings/pagination");
+        self.client.request(Method::GET, &path, None::<&()>).await
+    }
+
+    /// Bounds how deep the search results can be paginated, takes effect immediately.
+    pub async fn update_pagination_settings(
+        &self,
+        settings: &PaginationSettingsBody,
+    ) -> Result<PaginationSettingsBody> {
+        let path = self.path("/settings/pagination");
+        self.client
+            .request(Method::POST, &path, Some(settings))
+            .await
+    }
+
+    pub async fn query_limits(&self) -> Result<QueryLimitsBody> {
+        let path = self.path("/settings/query-limits");
+        self.client.request(Method::GET, &path, None::<&()>).await
+    }
+
+    /// Bounds the number of words and automatons of the queries, takes effect immediately.
+    pub async fn update_query_limits(&self, limits: &QueryLimitsBody) -> Result<QueryLimitsBody> {
+        let path = self.path("/settings/query-limits");
+        self.client.request(Method::POST, &path, Some(limits)).await
+    }
+
+    pub async fn typos(&self) -> Result<TyposBody> {
+        let path = self.path("/settings/typos");
+        self.client.request(Method::GET, &path, None::<&()>).await
+    }
+
+    /// Sets the number of typos allowed by word length, takes effect immediately.
+    pub async fn update_typos(&self, typos: &TyposBody) -> Result<TyposBody> {
+        let path = self.path("/settings/typos");
+        self.client.request(Method::POST, &path, Some(typos)).await
+    }
+
+    /// The ranking rules of the default criteria, like `"typo"` or `"dsc(release_date)"`.
+    pub async fn criteria(&self) -> Result<Vec<String>> {
+        let path = self.path("/settings/criteria");
+        self.client.request(Method::GET, &path, None::<&()>).await
+    }
+
+    /// Replaces the ranking rules of the default criteria, takes effect immediately.
+    pub async fn update_criteria(&self, rules: &[&str]) -> Result<Vec<String>> {
+        let path = self.path("/settings/criteria");
+        self.client.request(Method::POST, &path, Some(rules)).await
+    }
+
+    /// The named ranking rules that a search can select with its `ranking_profile`.
+    pub async fn ranking_profiles(&self) -> Result<BTreeMap<String, Vec<String>>> {
+        let path = self.path("/settings/ranking-profiles");
+        self.client.request(Method::GET, &path, None::<&()>).await
+    }
+
+    /// Replaces all of the ranking profiles, takes effect immediately.
+    pub async fn update_ranking_profiles(
+        &self,
+        profiles: &BTreeMap<String, Vec<String>>,
+    ) -> Result<BTreeMap<String, Vec<String>>> {
+        let path = self.path("/settings/ranking-profiles");
+        self.client
+            .request(Method::POST, &path, Some(profiles))
+            .await
+    }
+
+    pub async fn cold_tier(&self) -> Result<ColdTierBody> {
+        let path = self.path("/settings/cold-tier");
+        self.client.request(Method::GET, &path, None::<&()>).await
+    }
+
+    /// Sets how long the documents are not retrieved before being moved to the cold tier,
+    /// the documents are moved by the next tiering of the server.
+    pub async fn update_cold_tier(&self, settings: &ColdTierBody) -> Result<ColdTierBody> {
+        let path = self.path("/settings/cold-tier");
+        self.client
+            .request(Method::POST, &path, Some(settings))
+            .await
+    }
+
+    pub async fn frozen_words(&self) -> Result<Vec<String>> {
+        let path = self.path("/settings/frozen-words");
+        self.client.request(Method::GET, &path, None::<&()>).await
+    }
+
+    /// Replaces the words that are only matched exactly, takes effect immediately.
+    pub async fn update_frozen_words(&self, words: &[&str]) -> Result<Vec<String>> {
+        let path = self.path("/settings/frozen-words");
+        self.client.request(Method::POST, &path, Some(words)).await
+    }
+
+    pub async fn numbers_settings(&self) -> Result<NumbersSettingsBody> {
+        let path = self.path("/settings/numbers");
+        self.client.request(Method::GET, &path, None::<&()>).await
+    }
+
+    pub async fn update_numbers_settings(
+        &self,
+        settings: &NumbersSettingsBody,
+    ) -> Result<NumbersSettingsBody> {
+        let path = self.path("/settings/numbers");
+        self.client
+            .request(Method::POST, &path, Some(settings))
+            .await
+    }
+
+    pub async fn prefix_search_settings(&self) -> Result<PrefixSearchSettingsBody> {
+        let path = self.path("/settings/prefix-search");
+        self.client.request(Method::GET, &path, None::<&()>).await
+    }
+
+    /// Whether the last word of the queries matches the words it is a prefix of by default.
+    pub async fn update_prefix_search_settings(
+        &self,
+        settings: &PrefixSearchSettingsBody,
+    ) -> Result<PrefixSearchSettingsBody> {
+        let path = self.path("/settings/prefix-search");
+        self.client
+            .request(Method::POST, &path, Some(settings))
+            .await
+    }
+
+    pub async fn document_id_charset(&self) -> Result<DocumentIdCharsetBody> {
+        let path = self.path("/settings/document-id-charset");
+        self.client.request(Method::GET, &path, None::<&()>).await
+    }
+
+    /// Replaces the characters allowed in the string identifiers of the next additions.
+    pub async fn update_document_id_charset(
+        &self,
+        charset: &DocumentIdCharsetBody,
+    ) -> Result<DocumentIdCharsetBody> {
+        let path = self.path("/settings/document-id-charset");
+        self.client
+            .request(Method::POST, &path, Some(charset))
+            .await
+    }
+
+    pub async fn highlighting_settings(&self) -> Result<HighlightingSettingsBody> {
+        let path = self.path("/settings/highlighting");
+        self.client.request(Method::GET, &path, None::<&()>).await
+    }
+
+    /// Whether the formatted fields of the search results are HTML-escaped by default.
+    pub async fn update_highlighting_settings(
+        &self,
+        settings: &HighlightingSettingsBody,
+    ) -> Result<HighlightingSettingsBody> {
+        let path = self.path("/settings/highlighting");
+        self.client
+            .request(Method::POST, &path, Some(settings))
+            .await
+    }
+
+    pub async fn facets_normalization(&self) -> Result<FacetsNormalization> {
+        let path = self.path("/settings/facets-normalization");
+        self.client.request(Method::GET, &path, None::<&()>).await
+    }
+
+    /// How the values of the facets are normalized before being counted, by attribute name.
+    pub async fn update_facets_normalization(
+        &self,
+        normalization: &FacetsNormalization,
+    ) -> Result<FacetsNormalization> {
+        let path = self.path("/settings/facets-normalization");
+        self.client
+            .request(Method::POST, &path, Some(normalization))
+            .await
+    }
+
+    pub async fn flattening_settings(&self) -> Result<FlatteningSettingsBody> {
+        let path = self.path("/settings/flattening");
+        self.client.request(Method::GET, &path, None::<&()>).await
+    }
+
+    /// Whether the nested objects of the documents added from now on are flattened.
+    pub async fn update_flattening_settings(
+        &self,
+        settings: &FlatteningSettingsBody,
+    ) -> Result<FlatteningSettingsBody> {
+        let path = self.path("/settings/flattening");
+        self.client
+            .request(Method::POST, &path, Some(settings))
+            .await
+    }
+
+    pub async fn attributes_types(&self) -> Result<AttributesTypes> {
+        let path = self.path("/settings/attributes-types");
+        self.client.request(Method::GET, &path, None::<&()>).await
+    }
+
+    /// Replaces the types of the attributes, the documents are reindexed.
+    pub async fn update_attributes_types(
+        &self,
+        types: &AttributesTypes,
+    ) -> Result<IndexUpdateResponse> {
+        let path = self.path("/settings/attributes-types");
+        self.client.request(Method::POST, &path, Some(types)).await
+    }
+
+    pub async fn words_limits(&self) -> Result<WordsLimits> {
+        let path = self.path("/settings/words-limits");
+        self.client.request(Method::GET, &path, None::<&()>).await
+    }
+
+    /// Replaces the maximum number of words indexed in the attributes, the documents are reindexed.
+    pub async fn update_words_limits(&self, limits: &WordsLimits) -> Result<IndexUpdateResponse> {
+        let path = self.path("/settings/words-limits");
+        self.client.request(Method::POST, &path, Some(limits)).await
+    }
+
+    pub async fn token_filters(&self) -> Result<Vec<TokenFilter>> {
+        let path = self.path("/settings/token-filters");
+        self.client.request(Method::GET, &path, None::<&()>).await
+    }
+
+    /// Replaces the token filters applied to the documents and the queries, the documents
+    /// are reindexed, the custom filters must be registered by the server.
+    pub async fn update_token_filters(
+        &self,
+        filters: &[TokenFilter],
+    ) -> Result<IndexUpdateResponse> {
+        let path = self.path("/settings/token-filters");
+        self.client
+            .request(Method::POST, &path, Some(filters))
+            .await
+    }
+
+    pub async fn searchable_attributes(&self) -> Result<SearchableAttributesBody> {
+        let path = self.path("/settings/searchable-attributes");
+        self.client.request(Method::GET, &path, None::<&()>).await
+    }
+
+    /// Replaces the searchable attributes and their priority, the documents are reindexed.
+    pub async fn update_searchable_attributes(
+        &self,
+        attributes: &SearchableAttributesBody,
+    ) -> Result<IndexUpdateResponse> {
+        let path = self.path("/settings/searchable-attributes");
+        self.client
+            .request(Method::POST, &path, Some(attributes))
+            .await
+    }
+
+    pub async fn displayed_attributes(&self) -> Result<DisplayedAttributesBody> {
+        let path = self.path("/settings/displayed-attributes");
+        self.client.request(Method::GET, &path, None::<&()>).await
+    }
+
+    /// Replaces the attributes returned in the documents, takes effect immediately.
+    pub async fn update_displayed_attributes(
+        &self,
+        attributes: &DisplayedAttributesBody,
+    ) -> Result<DisplayedAttributesBody> {
+        let path = self.path("/settings/displayed-attributes");
+        self.client
+            .request(Method::POST, &path, Some(attributes))
+            .await
+    }
+
+    pub async fn response_format(&self) -> Result<ResponseFormat> {
+        let path = self.path("/settings/response-format");
+        self.client.request(Method::GET, &path, None::<&()>).await
+    }
+
+    /// Replaces the default shape of the search responses, takes effect immediately.
+    pub async fn set_response_format(&self, format: &ResponseFormat) -> Result<ResponseFormat> {
+        let path = self.path("/settings/response-format");
+        self.client.request(Method::POST, &path, Some(format)).await
+    }
+
+    pub async fn query_quota(&self) -> Result<QueryQuotaBody> {
+        let path = self.path("/settings/query-quota");
+        self.client.request(Method::GET, &path, None::<&()>).await
+    }
+
+    /// Replaces the soft limits of the number of searches, takes effect immediately.
+    pub async fn update_query_quota(&self, quota: &QueryQuotaBody) -> Result<QueryQuotaBody> {
+        let path = self.path("/settings/query-quota");
+        self.client.request(Method::POST, &path, Some(quota)).await
+    }
+
+    pub async fn usage(&self) -> Result<IndexUsageResponse> {
+        let path = self.path("/usage");
+        self.client.request(Method::GET, &path, None::<&()>).await
+    }
+
+    /// Returns the components a settings update would change without applying it.
+    pub async fn settings_diff(&self, proposed: &SettingsDiffRequest) -> Result<SettingsDiff> {
+        let path = self.path("/settings/diff");
+        self.client
+            .request(Method::POST, &path, Some(proposed))
+            .await
+    }
+}
@@ -0,0 +1,81 @@
+//! Embeds the engine in the process with the `SearchEngine` facade, without any http server.
+//!
+//! cargo run --example embedded -- <database-path> <query>
+
+use std::env;
+use std::error::Error;
+
+use meilidb_core::SearchEngine;
+use meilidb_schema::Schema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Movie {
+    id: u64,
+    title: String,
+    overview: String,
+}
+
+const SCHEMA: &str = r#"
+    identifier = "id"
+
+    [attributes."id"]
+    displayed = true
+
+    [attributes."title"]
+    displayed = true
+    indexed = true
+
+    [attributes."overview"]
+    displayed = true
+    indexed = true
+"#;
+
+fn movies() -> Vec<Movie> {
+    vec![
+        Movie {
+            id: 1,
+            title: "Carol".to_string(),
+            overview: "In 1950s New York, a department-store clerk falls for a woman.".to_string(),
+        },
+        Movie {
+            id: 2,
+            title: "Wonder Woman".to_string(),
+            overview: "An Amazon princess comes to the world of Man.".to_string(),
+        },
+        Movie {
+            id: 3,
+            title: "Life of Pi".to_string(),
+            overview: "A young man survives a disaster at sea.".to_string(),
+        },
+    ]
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut args = env::args().skip(1);
+    let database_path = args.next().unwrap_or_else(|| "embedded.mdb".to_string());
+    let query = args.next().unwrap_or_else(|| "woman".to_string());
+
+    let engine = SearchEngine::open(&database_path)?;
+
+    if !engine.indexes().iter().any(|uid| uid == "movies") {
+        let schema: Schema = toml::from_str(SCHEMA)?;
+        let result = engine.create_index("movies", schema)?;
+        if let Some(error) = result.error {
+            return Err(error.into());
+        }
+    }
+
+    let result = engine.add_documents("movies", movies())?;
+    if let Some(error) = result.error {
+        return Err(error.into());
+    }
+    println!("documents indexed in {:.02}s", result.duration);
+
+    let found: Vec<Movie> = engine.search("movies", &query, 0..10)?;
+    for movie in found {
+        println!("{} - {}: {}", movie.id, movie.title, movie.overview);
+    }
+
+    Ok(())
+}
@@ -1,48 +1,116 @@
+use std::cmp;
+use std::sync::{Arc, Mutex};
+
+use hashbrown::HashMap;
 use levenshtein_automata::{LevenshteinAutomatonBuilder as LevBuilder, DFA};
 use once_cell::sync::OnceCell;
 
-static LEVDIST0: OnceCell<LevBuilder> = OnceCell::new();
-static LEVDIST1: OnceCell<LevBuilder> = OnceCell::new();
-static LEVDIST2: OnceCell<LevBuilder> = OnceCell::new();
+use super::typos::{TyposTable, MAX_TYPOS};
+
+/// The maximum number of DFAs kept in the cache, it is emptied once full.
+const DFA_CACHE_CAPACITY: usize = 1024;
+
+static LEVDIST: [OnceCell<LevBuilder>; 3] = [OnceCell::new(), OnceCell::new(), OnceCell::new()];
+
+/// The DFAs already built, they only depend on the query word, the prefix
+/// setting and the number of typos allowed, and are therefore reused
+/// across the query words and the queries.
+static DFA_CACHE: OnceCell<Mutex<DfaCache>> = OnceCell::new();
 
-#[derive(Copy, Clone)]
+type DfaCache = HashMap<(String, PrefixSetting, u8), Arc<DFA>>;
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
 enum PrefixSetting {
     Prefix,
     NoPrefix,
 }
 
-fn build_dfa_with_setting(query: &str, setting: PrefixSetting) -> DFA {
+fn build_dfa_with_setting(query: &str, setting: PrefixSetting, typos: u8) -> DFA {
     use PrefixSetting::{NoPrefix, Prefix};
 
-    match query.len() {
-        0..=4 => {
-            let builder = LEVDIST0.get_or_init(|| LevBuilder::new(0, true));
-            match setting {
-                Prefix => builder.build_prefix_dfa(query),
-                NoPrefix => builder.build_dfa(query),
-            }
-        }
-        5..=8 => {
-            let builder = LEVDIST1.get_or_init(|| LevBuilder::new(1, true));
-            match setting {
-                Prefix => builder.build_prefix_dfa(query),
-                NoPrefix => builder.build_dfa(query),
-            }
+    let typos = cmp::min(typos, MAX_TYPOS);
+    let builder = LEVDIST[typos as usize].get_or_init(|| LevBuilder::new(typos, true));
+    match setting {
+        Prefix => builder.build_prefix_dfa(query),
+        NoPrefix => builder.build_dfa(query),
+    }
+}
+
+fn cached_dfa_with_setting(query: &str, setting: PrefixSetting, typos: u8) -> Arc<DFA> {
+    let cache = DFA_CACHE.get_or_init(Default::default);
+    let key = (query.to_owned(), setting, typos);
+
+    if let Ok(cache) = cache.lock() {
+        if let Some(dfa) = cache.get(&key) {
+            return dfa.clone();
         }
-        _ => {
-            let builder = LEVDIST2.get_or_init(|| LevBuilder::new(2, true));
-            match setting {
-                Prefix => builder.build_prefix_dfa(query),
-                NoPrefix => builder.build_dfa(query),
-            }
+    }
+
+    // the DFA is built without holding the lock
+    let dfa = Arc::new(build_dfa_with_setting(query, setting, typos));
+
+    if let Ok(mut cache) = cache.lock() {
+        if cache.len() >= DFA_CACHE_CAPACITY {
+            cache.clear();
         }
+        cache.insert(key, dfa.clone());
     }
+
+    dfa
 }
 
-pub fn build_prefix_dfa(query: &str) -> DFA {
-    build_dfa_with_setting(query, PrefixSetting::Prefix)
+/// Builds a DFA of the query allowing the number of typos the table gives for its length.
+pub fn build_prefix_dfa(query: &str, typos: &TyposTable) -> Arc<DFA> {
+    cached_dfa_with_setting(query, PrefixSetting::Prefix, typos.max_typos(query.len()))
 }
 
-pub fn build_dfa(query: &str) -> DFA {
-    build_dfa_with_setting(query, PrefixSetting::NoPrefix)
+pub fn build_dfa(query: &str, typos: &TyposTable) -> Arc<DFA> {
+    cached_dfa_with_setting(query, PrefixSetting::NoPrefix, typos.max_typos(query.len()))
+}
+
+/// Builds a DFA of the query that only matches the query itself, without typo nor prefix.
+pub fn build_exact_dfa(query: &str) -> Arc<DFA> {
+    cached_dfa_with_setting(query, PrefixSetting::NoPrefix, 0)
+}
+
+/// The number of DFAs currently kept in the cache.
+pub fn dfa_cache_len() -> usize {
+    match DFA_CACHE.get() {
+        Some(cache) => cache.lock().map(|cache| cache.len()).unwrap_or(0),
+        None => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use levenshtein_automata::Distance;
+
+    #[test]
+    fn reuse_cached_dfas() {
+        let typos = TyposTable::default();
+        let first = build_prefix_dfa("hello", &typos);
+        let second = build_prefix_dfa("hello", &typos);
+        assert!(Arc::ptr_eq(&first, &second));
+
+        let other = build_dfa("hello", &typos);
+        assert!(!Arc::ptr_eq(&first, &other));
+    }
+
+    #[test]
+    fn typos_of_the_table() {
+        let strict = TyposTable::new(Vec::new()).unwrap();
+        let dfa = build_dfa("hello", &strict);
+        assert_eq!(dfa.eval("hello"), Distance::Exact(0));
+        assert_eq!(dfa.eval("hallo"), Distance::AtLeast(1));
+
+        let dfa = build_dfa("hello", &TyposTable::default());
+        assert_eq!(dfa.eval("hallo"), Distance::Exact(1));
+
+        // the DFAs built with another table are not reused
+        assert!(!Arc::ptr_eq(
+            &build_dfa("world", &strict),
+            &build_dfa("world", &TyposTable::default())
+        ));
+    }
 }
@@ -0,0 +1,22 @@
+use serde::{Deserialize, Serialize};
+
+/// The limits an index imposes on the queries, they bound the work
+/// done for the very long queries like pasted paragraphs.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueryLimits {
+    /// The words following this number of words are not used to select the
+    /// documents, they are only highlighted in the documents selected.
+    pub max_query_words: usize,
+    /// The least important automatons built from the query words, their ngrams,
+    /// synonyms and splits are dropped, the ones of the words are always kept.
+    pub max_automatons: usize,
+}
+
+impl Default for QueryLimits {
+    fn default() -> QueryLimits {
+        QueryLimits {
+            max_query_words: 32,
+            max_automatons: 256,
+        }
+    }
+}
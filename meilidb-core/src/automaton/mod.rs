@@ -1,24 +1,42 @@
 mod dfa;
+mod limits;
+mod pattern;
 mod query_enhancer;
+mod typos;
 
 use std::cmp::Reverse;
+use std::collections::HashSet;
+use std::ops::Range;
 use std::{cmp, vec};
 
+use fst::raw::Node;
 use fst::{IntoStreamer, Streamer};
-use levenshtein_automata::DFA;
-use meilidb_tokenizer::{is_cjk, split_query_string};
+use meilidb_tokenizer::{
+    is_cjk, normalize_number, split_query_phrases, split_query_string, split_unit,
+};
 
 use crate::error::MResult;
 use crate::store;
+use crate::token_filters::TokenFilterChain;
+use crate::update::MAX_SYNONYM_PENALTY;
 
-use self::dfa::{build_dfa, build_prefix_dfa};
+pub use self::dfa::dfa_cache_len;
+use self::dfa::{build_dfa, build_exact_dfa, build_prefix_dfa};
+pub use self::limits::QueryLimits;
+pub use self::pattern::{Pattern, PatternOptions, WordDfa};
 pub use self::query_enhancer::QueryEnhancer;
 use self::query_enhancer::QueryEnhancerBuilder;
+pub use self::typos::{TyposTable, TyposTableError, TyposThreshold};
 
 const NGRAMS: usize = 3;
 
+// the minimum number of characters of a decompounded word part
+const DECOMPOUNDING_MIN_PART_LEN: usize = 2;
+
 pub struct AutomatonProducer {
     automatons: Vec<AutomatonGroup>,
+    ignored_words: Vec<String>,
+    rewrites: Vec<(Option<usize>, QueryRewrite)>,
 }
 
 impl AutomatonProducer {
@@ -28,16 +46,36 @@ impl AutomatonProducer {
         main_store: store::Main,
         postings_list_store: store::PostingsLists,
         synonyms_store: store::Synonyms,
+        patterns: Option<PatternOptions>,
+        prefix: Option<bool>,
     ) -> MResult<(AutomatonProducer, QueryEnhancer)> {
-        let (automatons, query_enhancer) = generate_automatons(
+        let (automatons, ignored_words, rewrites, query_enhancer) = generate_automatons(
             reader,
             query,
             main_store,
             postings_list_store,
             synonyms_store,
+            patterns,
+            prefix,
         )?;
 
-        Ok((AutomatonProducer { automatons }, query_enhancer))
+        let producer = AutomatonProducer {
+            automatons,
+            ignored_words,
+            rewrites,
+        };
+        Ok((producer, query_enhancer))
+    }
+
+    /// The query words past the limit of words, they are not used to select the documents.
+    pub fn ignored_words(&self) -> &[String] {
+        &self.ignored_words
+    }
+
+    /// The changes made to the query words, along with the index of the first automaton
+    /// searching the rewritten words, the words that are only removed have none.
+    pub fn rewrites(&self) -> &[(Option<usize>, QueryRewrite)] {
+        &self.rewrites
     }
 
     pub fn into_iter(self) -> vec::IntoIter<AutomatonGroup> {
@@ -67,6 +105,48 @@ impl AutomatonGroup {
     }
 }
 
+/// The way the word searched by an automaton has been derived from the query.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AutomatonOrigin {
+    /// A word of the query, it can be a pattern.
+    Query,
+    /// A word of an alternative of a synonym key found in the query.
+    Synonym,
+    /// A part of a compound word of the query found in the decompounding dictionary.
+    Decompounding,
+    /// A part of a word of the query split in two.
+    Split,
+    /// Consecutive words of the query concatenated.
+    Concatenation,
+}
+
+/// A change made to the words of the query to search the documents.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QueryRewrite {
+    /// A query word that is a stop word, the documents are indexed without the stop words.
+    StopWord { word: String },
+    /// A query word removed by the token filters or past the limit of query words.
+    Ignored { word: String },
+    /// Query words also searched as the words of an alternative of their synonym key.
+    Synonym {
+        query: Vec<String>,
+        words: Vec<String>,
+        penalty: u8,
+    },
+    /// A compound query word also searched as the parts it is made of.
+    Decompounding { query: String, words: Vec<String> },
+    /// A query word also searched as the two words it is split in.
+    Split { query: String, words: Vec<String> },
+    /// Consecutive query words also searched as a single word.
+    Concatenation { query: Vec<String>, word: String },
+    /// A word of the index matched by a query word with typos.
+    Typo {
+        query: String,
+        word: String,
+        typos: u8,
+    },
+}
+
 #[derive(Debug)]
 pub struct Automaton {
     pub index: usize,
@@ -75,14 +155,27 @@ pub struct Automaton {
     pub is_exact: bool,
     pub is_prefix: bool,
     pub query: String,
+    pub pattern: Option<Pattern>,
+    /// The penalty of the synonym alternative this automaton comes from,
+    /// it is added to the number of typos of the matches.
+    pub penalty: u8,
+    /// The quoted phrase of the query this word belongs to and its position in it,
+    /// the words of a phrase only match when they follow each other in an attribute.
+    pub phrase: Option<(usize, usize)>,
+    pub origin: AutomatonOrigin,
 }
 
 impl Automaton {
-    pub fn dfa(&self) -> DFA {
-        if self.is_prefix {
-            build_prefix_dfa(&self.query)
+    /// The query words that are frozen words are only matched exactly.
+    pub fn dfa(&self, typos: &TyposTable, frozen_words: &fst::Set) -> WordDfa {
+        if let Some(pattern) = &self.pattern {
+            WordDfa::Pattern(pattern)
+        } else if frozen_words.contains(&self.query) {
+            WordDfa::Levenshtein(build_exact_dfa(&self.query))
+        } else if self.is_prefix {
+            WordDfa::Levenshtein(build_prefix_dfa(&self.query, typos))
         } else {
-            build_dfa(&self.query)
+            WordDfa::Levenshtein(build_dfa(&self.query, typos))
         }
     }
 
@@ -94,6 +187,10 @@ impl Automaton {
             is_exact: true,
             is_prefix: false,
             query: query.to_string(),
+            pattern: None,
+            penalty: 0,
+            phrase: None,
+            origin: AutomatonOrigin::Query,
         }
     }
 
@@ -105,6 +202,25 @@ impl Automaton {
             is_exact: true,
             is_prefix: true,
             query: query.to_string(),
+            pattern: None,
+            penalty: 0,
+            phrase: None,
+            origin: AutomatonOrigin::Query,
+        }
+    }
+
+    fn pattern(index: usize, query: &str, pattern: Pattern) -> Automaton {
+        Automaton {
+            index,
+            ngram: 1,
+            query_len: query.len(),
+            is_exact: true,
+            is_prefix: false,
+            query: query.to_string(),
+            pattern: Some(pattern),
+            penalty: 0,
+            phrase: None,
+            origin: AutomatonOrigin::Query,
         }
     }
 
@@ -116,6 +232,10 @@ impl Automaton {
             is_exact: false,
             is_prefix: false,
             query: query.to_string(),
+            pattern: None,
+            penalty: 0,
+            phrase: None,
+            origin: AutomatonOrigin::Query,
         }
     }
 }
@@ -130,6 +250,21 @@ pub fn normalize_str(string: &str) -> String {
     string
 }
 
+/// Rewrites a query word that is a number the way it is indexed, e.g. "1,000" into "1000",
+/// the unit following a number can be stripped to also match the numbers without it.
+fn normalize_query_number(word: String, strip_units: bool) -> String {
+    if let Some((number, unit)) = split_unit(&word) {
+        let number = normalize_number(number).unwrap_or_else(|| number.to_string());
+        return if strip_units {
+            number
+        } else {
+            format!("{}{}", number, unit)
+        };
+    }
+
+    normalize_number(&word).unwrap_or(word)
+}
+
 fn split_best_frequency<'a>(
     reader: &heed::RoTxn,
     word: &'a str,
@@ -158,19 +293,377 @@ fn split_best_frequency<'a>(
     Ok(best.map(|(_, l, r)| (l, r)))
 }
 
+/// Splits a compound word into the fewest possible number of parts
+/// found in the decompounding dictionary, e.g. "hundehutte" into
+/// "hunde" and "hutte". Returns `None` if the word can not be split.
+fn decompound<'a>(word: &'a str, dictionary: &fst::Set) -> Option<Vec<&'a str>> {
+    if dictionary.is_empty() {
+        return None;
+    }
+
+    let boundaries: Vec<_> = word
+        .char_indices()
+        .map(|(i, _)| i)
+        .chain(Some(word.len()))
+        .collect();
+
+    // the minimum number of parts needed to split the word up to a boundary
+    // along with the boundary where the last part starts
+    let mut best: Vec<Option<(usize, usize)>> = vec![None; boundaries.len()];
+    best[0] = Some((0, 0));
+
+    for end in 1..boundaries.len() {
+        for start in 0..end.saturating_sub(DECOMPOUNDING_MIN_PART_LEN - 1) {
+            let count = match best[start] {
+                Some((count, _)) => count + 1,
+                None => continue,
+            };
+
+            let part = &word[boundaries[start]..boundaries[end]];
+            if best[end].map_or(true, |(c, _)| count < c) && dictionary.contains(part) {
+                best[end] = Some((count, start));
+            }
+        }
+    }
+
+    let last = boundaries.len() - 1;
+    match best[last] {
+        Some((count, _)) if count >= 2 => {
+            let mut parts = Vec::with_capacity(count);
+            let mut end = last;
+            while end != 0 {
+                let (_, start) = best[end].unwrap();
+                parts.push(&word[boundaries[start]..boundaries[end]]);
+                end = start;
+            }
+            parts.reverse();
+            Some(parts)
+        }
+        _ => None,
+    }
+}
+
+/// Splits the query into words, the whitespace separated parts of the
+/// query that are patterns are kept whole instead of being tokenized.
+fn split_query_patterns(query: &str, options: PatternOptions) -> Vec<(String, Option<Pattern>)> {
+    let mut words = Vec::new();
+    let mut count = 0;
+
+    for part in query.split_whitespace() {
+        let part = part.to_lowercase();
+
+        if count < options.max_patterns {
+            if let Some(pattern) = Pattern::parse(&part, options.max_expansions) {
+                words.push((part, Some(pattern)));
+                count += 1;
+                continue;
+            }
+        }
+
+        for word in split_query_string(&part) {
+            words.push((word.to_string(), None));
+        }
+    }
+
+    words
+}
+
+fn lowercase_if(word: &str, lowercase: bool) -> String {
+    if lowercase {
+        word.to_lowercase()
+    } else {
+        word.to_string()
+    }
+}
+
+/// The words of the query along with their pattern and the quoted phrase they belong to
+/// with their position in it, the quoted words are never patterns and a single quoted word
+/// is not a phrase. The words are not lowercased when the token filters of the index do it.
+fn split_query(
+    query: &str,
+    patterns: Option<PatternOptions>,
+    lowercase: bool,
+) -> (
+    Vec<String>,
+    Vec<Option<Pattern>>,
+    Vec<Option<(usize, usize)>>,
+) {
+    let mut words = Vec::new();
+    let mut patterns_found = Vec::new();
+    let mut phrases = Vec::new();
+    let mut phrases_count = 0;
+
+    for (part, is_quoted) in split_query_phrases(query) {
+        if is_quoted {
+            let part_words: Vec<_> = split_query_string(part)
+                .map(|word| lowercase_if(word, lowercase))
+                .collect();
+            let phrase = if part_words.len() > 1 {
+                phrases_count += 1;
+                Some(phrases_count - 1)
+            } else {
+                None
+            };
+
+            for (position, word) in part_words.into_iter().enumerate() {
+                words.push(word);
+                patterns_found.push(None);
+                phrases.push(phrase.map(|phrase| (phrase, position)));
+            }
+            continue;
+        }
+
+        let part_words = match patterns {
+            Some(mut options) => {
+                // the maximum number of patterns is shared by all the parts
+                let count = patterns_found.iter().filter(|p| p.is_some()).count();
+                options.max_patterns = options.max_patterns.saturating_sub(count);
+                split_query_patterns(part, options)
+            }
+            None => split_query_string(part)
+                .map(|word| (lowercase_if(word, lowercase), None))
+                .collect(),
+        };
+
+        for (word, pattern) in part_words {
+            words.push(word);
+            patterns_found.push(pattern);
+            phrases.push(None);
+        }
+    }
+
+    (words, patterns_found, phrases)
+}
+
+/// Pushes the automatons of the alternatives of a synonym key found in the query.
+fn push_synonym_alternatives(
+    reader: &heed::RoTxn,
+    synonym_store: store::Synonyms,
+    base: &str,
+    query_words: &[String],
+    query_range: Range<usize>,
+    automaton_index: &mut usize,
+    automatons: &mut Vec<AutomatonGroup>,
+    rewrites: &mut Vec<(Option<usize>, QueryRewrite)>,
+    enhancer_builder: &mut QueryEnhancerBuilder<String>,
+) -> MResult<()> {
+    let n = query_range.len();
+
+    if let Some(synonyms) = synonym_store.weighted_synonyms(reader, base.as_bytes())? {
+        let mut stream = synonyms.into_stream();
+        while let Some((synonyms, penalty)) = stream.next() {
+            let synonyms = std::str::from_utf8(synonyms).unwrap();
+            let penalty = cmp::min(penalty, u64::from(MAX_SYNONYM_PENALTY)) as u8;
+            let synonyms_words: Vec<_> = split_query_string(synonyms).collect();
+            let nb_synonym_words = synonyms_words.len();
+
+            let real_query_index = *automaton_index;
+            enhancer_builder.declare(query_range.clone(), real_query_index, &synonyms_words);
+
+            let rewrite = QueryRewrite::Synonym {
+                query: query_words[query_range.clone()].to_vec(),
+                words: synonyms_words.iter().map(ToString::to_string).collect(),
+                penalty,
+            };
+            rewrites.push((Some(real_query_index), rewrite));
+
+            for synonym in synonyms_words {
+                let mut automaton = if nb_synonym_words == 1 {
+                    Automaton::exact(*automaton_index, n, synonym)
+                } else {
+                    Automaton::non_exact(*automaton_index, n, synonym)
+                };
+                automaton.penalty = penalty;
+                automaton.origin = AutomatonOrigin::Synonym;
+                *automaton_index += 1;
+                automatons.push(AutomatonGroup::normal(vec![automaton]));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Follows the bytes from the given node of the fst, returns the node reached.
+fn follow_bytes<'f>(fst: &'f fst::raw::Fst, mut node: Node<'f>, bytes: &[u8]) -> Option<Node<'f>> {
+    for &byte in bytes {
+        let i = node.find_input(byte)?;
+        node = fst.node(node.transition_addr(i));
+    }
+    Some(node)
+}
+
+/// Finds the sequences of more than `NGRAMS` query words that are synonym keys,
+/// the shorter ones are matched by the ngrams automatons along with typos.
+///
+/// The synonyms fst is walked as a trie, word by word, a sequence of words
+/// is abandoned as soon as no synonym key starts with it.
+fn find_synonym_phrases(words: &[String], synonyms: &fst::Set) -> Vec<(Range<usize>, String)> {
+    let fst = synonyms.as_fst();
+    let mut phrases = Vec::new();
+
+    for start in 0..words.len() {
+        let mut node = fst.root();
+        let mut phrase = String::new();
+
+        for (end, word) in words.iter().enumerate().skip(start) {
+            if end != start {
+                node = match follow_bytes(fst, node, b" ") {
+                    Some(node) => node,
+                    None => break,
+                };
+                phrase.push(' ');
+            }
+
+            node = match follow_bytes(fst, node, word.as_bytes()) {
+                Some(node) => node,
+                None => break,
+            };
+            phrase.push_str(word);
+
+            let nb_words = end + 1 - start;
+            if nb_words > NGRAMS && node.is_final() {
+                phrases.push((start..end + 1, phrase.clone()));
+            }
+        }
+    }
+
+    phrases
+}
+
+/// Applies the token filters of the index to the query words that are not patterns,
+/// the words dropped by the filters are removed unless they are part of a quoted phrase,
+/// the removed words are pushed to `dropped`.
+fn filter_query_words(
+    token_filters: &TokenFilterChain,
+    words: Vec<String>,
+    patterns: Vec<Option<Pattern>>,
+    phrases: Vec<Option<(usize, usize)>>,
+    dropped: &mut Vec<String>,
+) -> (
+    Vec<String>,
+    Vec<Option<Pattern>>,
+    Vec<Option<(usize, usize)>>,
+) {
+    let mut filtered = (Vec::new(), Vec::new(), Vec::new());
+
+    for ((word, pattern), phrase) in words.into_iter().zip(patterns).zip(phrases) {
+        let word = match (&pattern, token_filters.apply(&word)) {
+            (Some(_), _) => word,
+            (None, Some(filtered)) => filtered,
+            (None, None) if phrase.is_some() => word,
+            (None, None) => {
+                dropped.push(word);
+                continue;
+            }
+        };
+
+        filtered.0.push(word);
+        filtered.1.push(pattern);
+        filtered.2.push(phrase);
+    }
+
+    filtered
+}
+
 fn generate_automatons(
     reader: &heed::RoTxn,
     query: &str,
     main_store: store::Main,
     postings_lists_store: store::PostingsLists,
     synonym_store: store::Synonyms,
-) -> MResult<(Vec<AutomatonGroup>, QueryEnhancer)> {
-    let has_end_whitespace = query.chars().last().map_or(false, char::is_whitespace);
-    let query_words: Vec<_> = split_query_string(query).map(str::to_lowercase).collect();
+    patterns: Option<PatternOptions>,
+    prefix: Option<bool>,
+) -> MResult<(
+    Vec<AutomatonGroup>,
+    Vec<String>,
+    Vec<(Option<usize>, QueryRewrite)>,
+    QueryEnhancer,
+)> {
+    let limits = main_store.query_limits(reader)?.unwrap_or_default();
+    let typos = main_store.typos_table(reader)?.unwrap_or_default();
+    // only the last query word is a prefix, and only when the prefix search is enabled,
+    // the setting of the index is used when the query does not say otherwise
+    let prefix = match prefix {
+        Some(prefix) => prefix,
+        None => main_store.prefix_search(reader)?.unwrap_or(true),
+    };
+    let has_end_whitespace = query.chars().last().map_or(false, char::is_whitespace) || !prefix;
+    let strip_units = main_store.strip_units(reader)?.unwrap_or(false);
+    let token_filters = match main_store.token_filters(reader)? {
+        Some(token_filters) => Some(TokenFilterChain::resolve(&token_filters)?),
+        None => None,
+    };
+    let (query_words, query_patterns, query_phrases) =
+        split_query(query, patterns, token_filters.is_none());
+    let mut dropped_words = Vec::new();
+    let (query_words, mut query_patterns, mut query_phrases) = match &token_filters {
+        Some(token_filters) => filter_query_words(
+            token_filters,
+            query_words,
+            query_patterns,
+            query_phrases,
+            &mut dropped_words,
+        ),
+        None => (query_words, query_patterns, query_phrases),
+    };
+    let mut query_words: Vec<_> = query_words
+        .into_iter()
+        .zip(&query_patterns)
+        .map(|(word, pattern)| match pattern {
+            Some(_) => word,
+            None => normalize_query_number(word, strip_units),
+        })
+        .collect();
+    // the query words were already analyzed by the token filters, the ngrams
+    // and concatenations of these words must not be analyzed a second time
+    let normalize = |word: &str| {
+        let word = match &token_filters {
+            Some(_) => word.to_owned(),
+            None => normalize_str(word),
+        };
+        if word.is_empty() {
+            None
+        } else {
+            Some(word)
+        }
+    };
+
+    // the words past the limit are only highlighted, the last word
+    // kept is followed by other words and is not a prefix
+    let is_truncated = query_words.len() > limits.max_query_words;
+    let has_end_whitespace = has_end_whitespace || is_truncated;
+    let mut ignored_words = Vec::new();
+    if is_truncated {
+        let words = query_words.split_off(limits.max_query_words);
+        let patterns = query_patterns.split_off(limits.max_query_words);
+        query_phrases.truncate(limits.max_query_words);
+        let iter = words.into_iter().zip(patterns);
+        ignored_words.extend(iter.filter(|(_, p)| p.is_none()).map(|(w, _)| w));
+    }
+
+    // the stop words of the queries are searched but never match
+    let stop_words = main_store.stop_words_fst(reader)?.unwrap_or_default();
+    let mut rewrites = Vec::new();
+    for (word, pattern) in query_words.iter().zip(&query_patterns) {
+        if pattern.is_none() && stop_words.contains(word) {
+            rewrites.push((None, QueryRewrite::StopWord { word: word.clone() }));
+        }
+    }
+    for word in dropped_words
+        .into_iter()
+        .chain(ignored_words.iter().cloned())
+    {
+        rewrites.push((None, QueryRewrite::Ignored { word }));
+    }
     let synonyms = match main_store.synonyms_fst(reader)? {
         Some(synonym) => synonym,
         None => fst::Set::default(),
     };
+    let decompounding = match main_store.decompounding_fst(reader)? {
+        Some(decompounding) => decompounding,
+        None => fst::Set::default(),
+    };
 
     let mut automaton_index = 0;
     let mut automatons = Vec::new();
@@ -179,16 +672,27 @@ fn generate_automatons(
     // We must not declare the original words to the query enhancer
     // *but* we need to push them in the automatons list first
     let mut original_automatons = Vec::new();
-    let mut original_words = query_words.iter().peekable();
-    while let Some(word) = original_words.next() {
+    let mut original_words = query_words
+        .iter()
+        .zip(&query_patterns)
+        .zip(&query_phrases)
+        .peekable();
+    while let Some(((word, pattern), phrase)) = original_words.next() {
+        // the words of a closed quoted phrase are complete words
         let has_following_word = original_words.peek().is_some();
-        let not_prefix_dfa = has_following_word || has_end_whitespace || word.chars().all(is_cjk);
-
-        let automaton = if not_prefix_dfa {
+        let not_prefix_dfa = has_following_word
+            || has_end_whitespace
+            || phrase.is_some()
+            || word.chars().all(is_cjk);
+
+        let mut automaton = if let Some(pattern) = pattern {
+            Automaton::pattern(automaton_index, word, pattern.clone())
+        } else if not_prefix_dfa {
             Automaton::exact(automaton_index, 1, word)
         } else {
             Automaton::prefix_exact(automaton_index, 1, word)
         };
+        automaton.phrase = *phrase;
         automaton_index += 1;
         original_automatons.push(automaton);
     }
@@ -199,6 +703,18 @@ fn generate_automatons(
         let mut ngrams = query_words.windows(n).enumerate().peekable();
         while let Some((query_index, ngram_slice)) = ngrams.next() {
             let query_range = query_index..query_index + n;
+
+            // patterns and phrases are only matched as they are written
+            if query_patterns[query_range.clone()]
+                .iter()
+                .any(Option::is_some)
+                || query_phrases[query_range.clone()]
+                    .iter()
+                    .any(Option::is_some)
+            {
+                continue;
+            }
+
             let ngram_nb_words = ngram_slice.len();
             let ngram = ngram_slice.join(" ");
 
@@ -206,15 +722,18 @@ fn generate_automatons(
             let not_prefix_dfa =
                 has_following_word || has_end_whitespace || ngram.chars().all(is_cjk);
 
-            // automaton of synonyms of the ngrams
-            let normalized = normalize_str(&ngram);
+            // automaton of synonyms of the ngrams, an empty ngram would match them all
+            let normalized = match normalize(&ngram) {
+                Some(normalized) => normalized,
+                None => continue,
+            };
             let lev = if not_prefix_dfa {
-                build_dfa(&normalized)
+                build_dfa(&normalized, &typos)
             } else {
-                build_prefix_dfa(&normalized)
+                build_prefix_dfa(&normalized, &typos)
             };
 
-            let mut stream = synonyms.search(&lev).into_stream();
+            let mut stream = synonyms.search(&*lev).into_stream();
             while let Some(base) = stream.next() {
                 // only trigger alternatives when the last word has been typed
                 // i.e. "new " do not but "new yo" triggers alternatives to "new york"
@@ -224,42 +743,57 @@ fn generate_automatons(
                     continue;
                 }
 
-                if let Some(synonyms) = synonym_store.synonyms(reader, base.as_bytes())? {
-                    let mut stream = synonyms.into_stream();
-                    while let Some(synonyms) = stream.next() {
-                        let synonyms = std::str::from_utf8(synonyms).unwrap();
-                        let synonyms_words: Vec<_> = split_query_string(synonyms).collect();
-                        let nb_synonym_words = synonyms_words.len();
-
-                        let real_query_index = automaton_index;
-                        enhancer_builder.declare(
-                            query_range.clone(),
-                            real_query_index,
-                            &synonyms_words,
-                        );
-
-                        for synonym in synonyms_words {
-                            let automaton = if nb_synonym_words == 1 {
-                                Automaton::exact(automaton_index, n, synonym)
-                            } else {
-                                Automaton::non_exact(automaton_index, n, synonym)
-                            };
-                            automaton_index += 1;
-                            automatons.push(AutomatonGroup::normal(vec![automaton]));
-                        }
-                    }
-                }
+                push_synonym_alternatives(
+                    reader,
+                    synonym_store,
+                    base,
+                    &query_words,
+                    query_range.clone(),
+                    &mut automaton_index,
+                    &mut automatons,
+                    &mut rewrites,
+                    &mut enhancer_builder,
+                )?;
             }
 
             if n == 1 {
+                // automaton of the parts of the compound word
+                if let Some(parts) = decompound(&normalized, &decompounding) {
+                    let real_query_index = automaton_index;
+                    enhancer_builder.declare(query_range.clone(), real_query_index, &parts);
+
+                    let rewrite = QueryRewrite::Decompounding {
+                        query: ngram.clone(),
+                        words: parts.iter().map(ToString::to_string).collect(),
+                    };
+                    rewrites.push((Some(real_query_index), rewrite));
+
+                    let mut group = Vec::with_capacity(parts.len());
+                    for part in parts {
+                        let mut automaton = Automaton::exact(automaton_index, 1, part);
+                        automaton.origin = AutomatonOrigin::Decompounding;
+                        group.push(automaton);
+                        automaton_index += 1;
+                    }
+                    automatons.push(AutomatonGroup::phrase_query(group));
+                }
+
                 if let Some((left, right)) =
                     split_best_frequency(reader, &normalized, postings_lists_store)?
                 {
-                    let a = Automaton::exact(automaton_index, 1, left);
+                    let rewrite = QueryRewrite::Split {
+                        query: ngram.clone(),
+                        words: vec![left.to_string(), right.to_string()],
+                    };
+                    rewrites.push((Some(automaton_index), rewrite));
+
+                    let mut a = Automaton::exact(automaton_index, 1, left);
+                    a.origin = AutomatonOrigin::Split;
                     enhancer_builder.declare(query_range.clone(), automaton_index, &[left]);
                     automaton_index += 1;
 
-                    let b = Automaton::exact(automaton_index, 1, right);
+                    let mut b = Automaton::exact(automaton_index, 1, right);
+                    b.origin = AutomatonOrigin::Split;
                     enhancer_builder.declare(query_range.clone(), automaton_index, &[left]);
                     automaton_index += 1;
 
@@ -268,28 +802,167 @@ fn generate_automatons(
             } else {
                 // automaton of concatenation of query words
                 let concat = ngram_slice.concat();
-                let normalized = normalize_str(&concat);
+                let normalized = match normalize(&concat) {
+                    Some(normalized) => normalized,
+                    None => continue,
+                };
 
                 let real_query_index = automaton_index;
                 enhancer_builder.declare(query_range.clone(), real_query_index, &[&normalized]);
 
-                let automaton = Automaton::exact(automaton_index, n, &normalized);
+                let rewrite = QueryRewrite::Concatenation {
+                    query: ngram_slice.to_vec(),
+                    word: normalized.clone(),
+                };
+                rewrites.push((Some(real_query_index), rewrite));
+
+                let mut automaton = Automaton::exact(automaton_index, n, &normalized);
+                automaton.origin = AutomatonOrigin::Concatenation;
                 automaton_index += 1;
                 automatons.push(AutomatonGroup::normal(vec![automaton]));
             }
         }
     }
 
+    // automatons of the synonyms of the query phrases that are too long to be ngrams
+    let normalized_words: Vec<_> = query_words
+        .iter()
+        .map(|w| normalize(w).unwrap_or_default())
+        .collect();
+    for (query_range, base) in find_synonym_phrases(&normalized_words, &synonyms) {
+        // patterns and phrases are only matched as they are written
+        if query_patterns[query_range.clone()]
+            .iter()
+            .any(Option::is_some)
+            || query_phrases[query_range.clone()]
+                .iter()
+                .any(Option::is_some)
+        {
+            continue;
+        }
+
+        push_synonym_alternatives(
+            reader,
+            synonym_store,
+            &base,
+            &query_words,
+            query_range,
+            &mut automaton_index,
+            &mut automatons,
+            &mut rewrites,
+            &mut enhancer_builder,
+        )?;
+    }
+
     // order automatons, the most important first,
     // we keep the original automatons at the front.
     automatons[1..].sort_by_key(|group| {
         let a = group.automatons.first().unwrap();
         (
             Reverse(a.is_exact),
+            a.penalty,
             a.ngram,
             Reverse(group.automatons.len()),
         )
     });
 
-    Ok((automatons, enhancer_builder.build()))
+    // drop the least important automatons, the original words are always kept
+    let mut automatons_count = automatons[0].automatons.len();
+    let mut kept_groups = 1;
+    for group in &automatons[1..] {
+        automatons_count += group.automatons.len();
+        if automatons_count > limits.max_automatons {
+            break;
+        }
+        kept_groups += 1;
+    }
+    automatons.truncate(kept_groups);
+
+    // the rewritten words of the dropped automatons are not searched
+    let kept: HashSet<_> = automatons
+        .iter()
+        .flat_map(|group| &group.automatons)
+        .map(|automaton| automaton.index)
+        .collect();
+    rewrites.retain(|(index, _)| index.map_or(true, |index| kept.contains(&index)));
+
+    Ok((
+        automatons,
+        ignored_words,
+        rewrites,
+        enhancer_builder.build(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decompound_words() {
+        let dictionary = fst::Set::from_iter(vec!["haus", "hunde", "hutte", "tur"]).unwrap();
+
+        assert_eq!(
+            decompound("hundehutte", &dictionary),
+            Some(vec!["hunde", "hutte"])
+        );
+        assert_eq!(
+            decompound("haustur", &dictionary),
+            Some(vec!["haus", "tur"])
+        );
+        assert_eq!(
+            decompound("hundehaustur", &dictionary),
+            Some(vec!["hunde", "haus", "tur"])
+        );
+
+        // a word of the dictionary is not a compound word
+        assert_eq!(decompound("hunde", &dictionary), None);
+        assert_eq!(decompound("hundekatze", &dictionary), None);
+        assert_eq!(decompound("hundehutte", &fst::Set::default()), None);
+    }
+
+    #[test]
+    fn normalize_query_numbers() {
+        let normalize =
+            |word: &str, strip_units| normalize_query_number(word.to_string(), strip_units);
+
+        assert_eq!(normalize("1,000", false), "1000");
+        assert_eq!(normalize("1.5", false), "1.5");
+        assert_eq!(normalize("1,5kg", false), "1.5kg");
+        assert_eq!(normalize("1,5kg", true), "1.5");
+        assert_eq!(normalize("10kg", true), "10");
+        assert_eq!(normalize("kg", true), "kg");
+        assert_eq!(normalize("iphone", true), "iphone");
+    }
+
+    #[test]
+    fn synonym_phrases() {
+        let synonyms = fst::Set::from_iter(vec![
+            "new york",
+            "united states",
+            "united states of america",
+            "united states of america army",
+        ])
+        .unwrap();
+
+        let words: Vec<_> = "the united states of america army"
+            .split(' ')
+            .map(ToString::to_string)
+            .collect();
+
+        assert_eq!(
+            find_synonym_phrases(&words, &synonyms),
+            vec![
+                (1..5, "united states of america".to_string()),
+                (1..6, "united states of america army".to_string()),
+            ]
+        );
+
+        let words: Vec<_> = "united states of europe"
+            .split(' ')
+            .map(ToString::to_string)
+            .collect();
+
+        assert!(find_synonym_phrases(&words, &synonyms).is_empty());
+    }
 }
@@ -0,0 +1,278 @@
+use std::sync::Arc;
+
+use fst::Automaton;
+use levenshtein_automata::DFA;
+
+/// The limits applied to the query words that are patterns.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct PatternOptions {
+    /// The maximum number of words of the index a pattern can match.
+    pub max_expansions: usize,
+    /// The maximum number of patterns in a query, the following
+    /// ones are considered as normal query words.
+    pub max_patterns: usize,
+}
+
+impl Default for PatternOptions {
+    fn default() -> PatternOptions {
+        PatternOptions {
+            max_expansions: 50,
+            max_patterns: 3,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Item {
+    Byte(u8),
+    /// A set of ASCII characters, given as inclusive ranges.
+    Class {
+        ranges: Vec<(u8, u8)>,
+        negated: bool,
+    },
+}
+
+impl Item {
+    fn matches(&self, byte: u8) -> bool {
+        match self {
+            Item::Byte(b) => *b == byte,
+            Item::Class { ranges, negated } => {
+                let found = ranges.iter().any(|(s, e)| *s <= byte && byte <= *e);
+                byte.is_ascii() && found != *negated
+            }
+        }
+    }
+}
+
+/// A query word matching the words of the index by pattern instead of by typo
+/// tolerance, it supports character classes (`[a-z]`, `[^0-9]`, `[abc]`)
+/// which match a single ASCII character and a trailing wildcard (`run*`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pattern {
+    items: Vec<Item>,
+    trailing_wildcard: bool,
+    pub max_expansions: usize,
+}
+
+impl Pattern {
+    /// Parses a query word, returns `None` if it is not a valid pattern
+    /// or if it doesn't contain any wildcard nor character class.
+    pub fn parse(word: &str, max_expansions: usize) -> Option<Pattern> {
+        let mut items = Vec::new();
+        let mut trailing_wildcard = false;
+        let mut chars = word.chars();
+
+        while let Some(c) = chars.next() {
+            if trailing_wildcard {
+                // the wildcard is only supported at the end of the word
+                return None;
+            }
+
+            match c {
+                '*' => trailing_wildcard = true,
+                '[' => items.push(parse_class(&mut chars)?),
+                ']' => return None,
+                c => {
+                    let mut buffer = [0; 4];
+                    let bytes = c.encode_utf8(&mut buffer).bytes();
+                    items.extend(bytes.map(Item::Byte));
+                }
+            }
+        }
+
+        let is_pattern = trailing_wildcard || items.iter().any(|i| !is_byte(i));
+        if items.is_empty() || !is_pattern {
+            return None;
+        }
+
+        Some(Pattern {
+            items,
+            trailing_wildcard,
+            max_expansions,
+        })
+    }
+
+    /// Whether the word is matched without using the trailing wildcard.
+    pub fn is_exact_match(&self, word: &[u8]) -> bool {
+        word.len() == self.items.len()
+    }
+}
+
+fn is_byte(item: &Item) -> bool {
+    match item {
+        Item::Byte(_) => true,
+        Item::Class { .. } => false,
+    }
+}
+
+fn parse_class(chars: &mut std::str::Chars) -> Option<Item> {
+    let mut ranges = Vec::new();
+    let mut negated = false;
+    let mut first = true;
+
+    loop {
+        let c = chars.next()?;
+        match c {
+            ']' if !ranges.is_empty() => break,
+            '^' if first => negated = true,
+            c if c.is_ascii() => {
+                let start = c as u8;
+                let mut lookahead = chars.clone();
+                match (lookahead.next(), lookahead.next()) {
+                    (Some('-'), Some(end)) if end != ']' => {
+                        if !end.is_ascii() || (end as u8) < start {
+                            return None;
+                        }
+                        ranges.push((start, end as u8));
+                        *chars = lookahead;
+                    }
+                    _ => ranges.push((start, start)),
+                }
+            }
+            _ => return None,
+        }
+        first = false;
+    }
+
+    Some(Item::Class { ranges, negated })
+}
+
+impl Automaton for Pattern {
+    type State = Option<usize>;
+
+    fn start(&self) -> Option<usize> {
+        Some(0)
+    }
+
+    fn is_match(&self, state: &Option<usize>) -> bool {
+        *state == Some(self.items.len())
+    }
+
+    fn can_match(&self, state: &Option<usize>) -> bool {
+        state.is_some()
+    }
+
+    fn will_always_match(&self, state: &Option<usize>) -> bool {
+        self.trailing_wildcard && *state == Some(self.items.len())
+    }
+
+    fn accept(&self, state: &Option<usize>, byte: u8) -> Option<usize> {
+        match *state {
+            Some(i) if i < self.items.len() => {
+                if self.items[i].matches(byte) {
+                    Some(i + 1)
+                } else {
+                    None
+                }
+            }
+            Some(i) if self.trailing_wildcard => Some(i),
+            _ => None,
+        }
+    }
+}
+
+/// The automaton used to find the words of the index that a query word matches.
+pub enum WordDfa<'a> {
+    Levenshtein(Arc<DFA>),
+    Pattern(&'a Pattern),
+}
+
+pub enum WordDfaState {
+    Levenshtein(<DFA as Automaton>::State),
+    Pattern(Option<usize>),
+}
+
+impl Automaton for WordDfa<'_> {
+    type State = WordDfaState;
+
+    fn start(&self) -> WordDfaState {
+        match self {
+            WordDfa::Levenshtein(dfa) => WordDfaState::Levenshtein(dfa.start()),
+            WordDfa::Pattern(pattern) => WordDfaState::Pattern(pattern.start()),
+        }
+    }
+
+    fn is_match(&self, state: &WordDfaState) -> bool {
+        match (self, state) {
+            (WordDfa::Levenshtein(dfa), WordDfaState::Levenshtein(s)) => dfa.is_match(s),
+            (WordDfa::Pattern(pattern), WordDfaState::Pattern(s)) => pattern.is_match(s),
+            _ => false,
+        }
+    }
+
+    fn can_match(&self, state: &WordDfaState) -> bool {
+        match (self, state) {
+            (WordDfa::Levenshtein(dfa), WordDfaState::Levenshtein(s)) => dfa.can_match(s),
+            (WordDfa::Pattern(pattern), WordDfaState::Pattern(s)) => pattern.can_match(s),
+            _ => false,
+        }
+    }
+
+    fn will_always_match(&self, state: &WordDfaState) -> bool {
+        match (self, state) {
+            (WordDfa::Levenshtein(dfa), WordDfaState::Levenshtein(s)) => dfa.will_always_match(s),
+            (WordDfa::Pattern(pattern), WordDfaState::Pattern(s)) => pattern.will_always_match(s),
+            _ => false,
+        }
+    }
+
+    fn accept(&self, state: &WordDfaState, byte: u8) -> WordDfaState {
+        match (self, state) {
+            (WordDfa::Levenshtein(dfa), WordDfaState::Levenshtein(s)) => {
+                WordDfaState::Levenshtein(dfa.accept(s, byte))
+            }
+            (WordDfa::Pattern(pattern), WordDfaState::Pattern(s)) => {
+                WordDfaState::Pattern(pattern.accept(s, byte))
+            }
+            _ => unreachable!("the state doesn't come from this automaton"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fst::{IntoStreamer, Set};
+
+    fn search(words: &[&str], pattern: &str) -> Vec<String> {
+        let set = Set::from_iter(words).unwrap();
+        let pattern = Pattern::parse(pattern, 10).unwrap();
+        set.search(&pattern).into_stream().into_strs().unwrap()
+    }
+
+    #[test]
+    fn parse_patterns() {
+        assert!(Pattern::parse("run*", 10).is_some());
+        assert!(Pattern::parse("r[aeiou]n", 10).is_some());
+        assert!(Pattern::parse("[^0-9]x*", 10).is_some());
+
+        // not patterns
+        assert_eq!(Pattern::parse("run", 10), None);
+        assert_eq!(Pattern::parse("*", 10), None);
+        assert_eq!(Pattern::parse("ru*n", 10), None);
+        assert_eq!(Pattern::parse("r[a", 10), None);
+        assert_eq!(Pattern::parse("r[]", 10), None);
+        assert_eq!(Pattern::parse("r[z-a]", 10), None);
+        assert_eq!(Pattern::parse("r]", 10), None);
+    }
+
+    #[test]
+    fn trailing_wildcard() {
+        let words = &["rub", "run", "runner", "running", "ruse"];
+        assert_eq!(search(words, "run*"), vec!["run", "runner", "running"]);
+        assert_eq!(search(words, "runn*"), vec!["runner", "running"]);
+
+        let pattern = Pattern::parse("run*", 10).unwrap();
+        assert!(pattern.is_exact_match(b"run"));
+        assert!(!pattern.is_exact_match(b"running"));
+    }
+
+    #[test]
+    fn character_classes() {
+        let words = &["a1", "a2", "ab", "b1", "b22"];
+        assert_eq!(search(words, "a[0-9]"), vec!["a1", "a2"]);
+        assert_eq!(search(words, "a[^0-9]"), vec!["ab"]);
+        assert_eq!(search(words, "[ab]1"), vec!["a1", "b1"]);
+        assert_eq!(search(words, "[a-b][0-9]*"), vec!["a1", "a2", "b1", "b22"]);
+    }
+}
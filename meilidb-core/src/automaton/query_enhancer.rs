@@ -160,6 +160,12 @@ pub struct QueryEnhancer {
 }
 
 impl QueryEnhancer {
+    /// The number of query indices once the query words are replaced,
+    /// the query indices of the rewritten matches are below it.
+    pub fn query_len(&self) -> usize {
+        self.origins.last().cloned().unwrap_or(0)
+    }
+
     /// Returns the query indices to use to replace this real query index.
     pub fn replacement(&self, real: u32) -> Range<u32> {
         let real = real as usize;
@@ -0,0 +1,161 @@
+use std::{error, fmt};
+
+use serde::{Deserialize, Serialize};
+
+/// The maximum number of typos the DFAs can be built with.
+pub const MAX_TYPOS: u8 = 2;
+
+/// Allows a number of typos to the query words from a word length.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TyposThreshold {
+    /// The length of the words in bytes, like the query length of the automatons.
+    pub min_word_len: usize,
+    pub max_typos: u8,
+}
+
+/// The number of typos allowed in the query words depending on their length,
+/// the words shorter than the first threshold must be typed without typo.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TyposTable {
+    thresholds: Vec<TyposThreshold>,
+}
+
+impl TyposTable {
+    /// Checks that the lengths are strictly increasing and that the
+    /// longer words are never allowed less typos than the shorter ones.
+    pub fn new(thresholds: Vec<TyposThreshold>) -> Result<TyposTable, TyposTableError> {
+        let mut previous: Option<TyposThreshold> = None;
+
+        for threshold in &thresholds {
+            if threshold.max_typos > MAX_TYPOS {
+                return Err(TyposTableError::TooManyTypos {
+                    max_typos: threshold.max_typos,
+                });
+            }
+
+            if let Some(previous) = previous {
+                if threshold.min_word_len <= previous.min_word_len {
+                    return Err(TyposTableError::UnorderedLengths {
+                        min_word_len: threshold.min_word_len,
+                    });
+                }
+                if threshold.max_typos < previous.max_typos {
+                    return Err(TyposTableError::DecreasingTypos {
+                        min_word_len: threshold.min_word_len,
+                    });
+                }
+            }
+
+            previous = Some(*threshold);
+        }
+
+        Ok(TyposTable { thresholds })
+    }
+
+    pub fn thresholds(&self) -> &[TyposThreshold] {
+        &self.thresholds
+    }
+
+    pub fn max_typos(&self, word_len: usize) -> u8 {
+        self.thresholds
+            .iter()
+            .rev()
+            .find(|t| t.min_word_len <= word_len)
+            .map_or(0, |t| t.max_typos)
+    }
+}
+
+impl Default for TyposTable {
+    fn default() -> TyposTable {
+        let threshold = |min_word_len, max_typos| TyposThreshold {
+            min_word_len,
+            max_typos,
+        };
+
+        TyposTable {
+            thresholds: vec![threshold(0, 0), threshold(5, 1), threshold(9, 2)],
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TyposTableError {
+    TooManyTypos { max_typos: u8 },
+    UnorderedLengths { min_word_len: usize },
+    DecreasingTypos { min_word_len: usize },
+}
+
+impl fmt::Display for TyposTableError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::TyposTableError::*;
+        match self {
+            TooManyTypos { max_typos } => write!(
+                f,
+                "{} typos are allowed but the maximum is {}",
+                max_typos, MAX_TYPOS
+            ),
+            UnorderedLengths { min_word_len } => write!(
+                f,
+                "the word length {} is not greater than the previous one",
+                min_word_len
+            ),
+            DecreasingTypos { min_word_len } => write!(
+                f,
+                "the words of {} bytes are allowed less typos than the shorter ones",
+                min_word_len
+            ),
+        }
+    }
+}
+
+impl error::Error for TyposTableError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn threshold(min_word_len: usize, max_typos: u8) -> TyposThreshold {
+        TyposThreshold {
+            min_word_len,
+            max_typos,
+        }
+    }
+
+    #[test]
+    fn default_thresholds() {
+        let table = TyposTable::default();
+        assert_eq!(table.max_typos(0), 0);
+        assert_eq!(table.max_typos(4), 0);
+        assert_eq!(table.max_typos(5), 1);
+        assert_eq!(table.max_typos(8), 1);
+        assert_eq!(table.max_typos(9), 2);
+        assert_eq!(table.max_typos(100), 2);
+    }
+
+    #[test]
+    fn custom_thresholds() {
+        let table = TyposTable::new(vec![threshold(3, 1), threshold(6, 2)]).unwrap();
+        assert_eq!(table.max_typos(2), 0);
+        assert_eq!(table.max_typos(3), 1);
+        assert_eq!(table.max_typos(6), 2);
+
+        let table = TyposTable::new(Vec::new()).unwrap();
+        assert_eq!(table.max_typos(20), 0);
+    }
+
+    #[test]
+    fn invalid_thresholds() {
+        assert_eq!(
+            TyposTable::new(vec![threshold(0, 3)]),
+            Err(TyposTableError::TooManyTypos { max_typos: 3 })
+        );
+        assert_eq!(
+            TyposTable::new(vec![threshold(5, 1), threshold(5, 2)]),
+            Err(TyposTableError::UnorderedLengths { min_word_len: 5 })
+        );
+        assert_eq!(
+            TyposTable::new(vec![threshold(5, 2), threshold(9, 1)]),
+            Err(TyposTableError::DecreasingTypos { min_word_len: 9 })
+        );
+    }
+}
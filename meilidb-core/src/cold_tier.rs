@@ -0,0 +1,106 @@
+use std::mem;
+use std::sync::{Arc, Mutex};
+
+use hashbrown::{HashMap, HashSet};
+use serde::{Deserialize, Serialize};
+
+use crate::store::Index;
+use crate::{DocumentId, MResult};
+
+/// The documents that have not been retrieved for `idle_secs` seconds are moved
+/// to the cold tier, where their fields are compressed and slower to retrieve.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ColdTierSettings {
+    pub idle_secs: u64,
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TierStats {
+    pub hot_documents: u64,
+    pub cold_documents: u64,
+    /// The size in bytes of the compressed fields of the cold documents.
+    pub cold_size: u64,
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TieringReport {
+    pub moved_to_cold: u64,
+    pub moved_to_hot: u64,
+}
+
+/// The last retrievals of the documents, they are kept in memory until the next
+/// tiering to not open a write transaction each time a document is retrieved.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct AccessLog {
+    accesses: Arc<Mutex<HashMap<DocumentId, u64>>>,
+}
+
+impl AccessLog {
+    pub fn record(&self, document_id: DocumentId, timestamp: u64) {
+        let mut accesses = self.accesses.lock().unwrap();
+        let last = accesses.entry(document_id).or_insert(timestamp);
+        *last = (*last).max(timestamp);
+    }
+
+    pub fn take(&self) -> HashMap<DocumentId, u64> {
+        mem::replace(&mut *self.accesses.lock().unwrap(), HashMap::new())
+    }
+}
+
+pub(crate) fn tier_documents(
+    writer: &mut heed::RwTxn,
+    index: &Index,
+    now: u64,
+) -> MResult<TieringReport> {
+    let documents_fields = index.documents_fields;
+    let settings = index.main.cold_tier(writer)?;
+
+    // the accesses are only tracked while the cold tier is enabled
+    // and there is nothing to move back when it is disabled
+    if settings.is_none() && !documents_fields.has_cold_documents(writer)? {
+        index.accesses.take();
+        return Ok(TieringReport::default());
+    }
+
+    let mut documents_ids = HashSet::new();
+    for result in index.documents_fields_counts.documents_ids(writer)? {
+        documents_ids.insert(result?);
+    }
+
+    // the retrievals of the deleted documents are ignored
+    for (document_id, timestamp) in index.accesses.take() {
+        if documents_ids.contains(&document_id) {
+            documents_fields.put_last_access(writer, document_id, timestamp)?;
+        }
+    }
+
+    let mut report = TieringReport::default();
+
+    for document_id in documents_ids {
+        // the documents never retrieved are considered retrieved
+        // the first time they are seen by the tiering
+        let last_access = match documents_fields.last_access(writer, document_id)? {
+            Some(timestamp) => timestamp,
+            None => {
+                documents_fields.put_last_access(writer, document_id, now)?;
+                now
+            }
+        };
+
+        // all the documents are moved back to the hot tier when the cold tier is disabled
+        let is_idle = match settings {
+            Some(settings) => now.saturating_sub(last_access) >= settings.idle_secs,
+            None => false,
+        };
+
+        if is_idle {
+            if documents_fields.freeze_document(writer, document_id)? {
+                report.moved_to_cold += 1;
+            }
+        } else if documents_fields.thaw_document(writer, document_id)? {
+            report.moved_to_hot += 1;
+        }
+    }
+
+    Ok(report)
+}
@@ -1,5 +1,5 @@
 use crate::criterion::Criterion;
-use crate::RawDocument;
+use crate::{Number, RawDocument};
 use std::cmp::Ordering;
 
 #[derive(Debug, Clone, Copy)]
@@ -13,4 +13,8 @@ impl Criterion for DocumentId {
     fn name(&self) -> &str {
         "DocumentId"
     }
+
+    fn score(&self, document: &RawDocument) -> Option<Number> {
+        Some(Number::Unsigned(document.id.0))
+    }
 }
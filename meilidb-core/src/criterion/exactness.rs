@@ -0,0 +1,165 @@
+use std::cmp::{self, Ordering};
+
+use meilidb_schema::SchemaAttr;
+use sdset::Set;
+use slice_group_by::GroupBy;
+
+use crate::criterion::Criterion;
+use crate::{Number, RawDocument};
+
+/// The number of words of the longest attribute that is exactly the query words,
+/// all the query words are matched without typos in the order of the query and
+/// the attribute contains no other word, zero when there is no such attribute.
+#[inline]
+fn exact_attribute_words(
+    query_words: usize,
+    query_index: &[u32],
+    attribute: &[u16],
+    word_index: &[u16],
+    is_exact: &[bool],
+    fields_counts: &Set<(SchemaAttr, u64)>,
+) -> usize {
+    let mut matches: Vec<_> = (0..query_index.len())
+        .filter(|&i| is_exact[i])
+        .map(|i| (attribute[i], word_index[i], query_index[i]))
+        .collect();
+    matches.sort_unstable();
+    matches.dedup_by_key(|(attribute, word_index, _)| (*attribute, *word_index));
+
+    let mut words = 0;
+    for group in matches.linear_group_by(|a, b| a.0 == b.0) {
+        let attribute = group[0].0;
+        let count = match fields_counts.binary_search_by_key(&attribute, |(a, _)| a.0) {
+            Ok(pos) => fields_counts[pos].1,
+            Err(_) => continue,
+        };
+
+        let in_query_order = group.windows(2).all(|w| w[0].2 < w[1].2);
+        let whole_query = group.len() == query_words;
+        if in_query_order && whole_query && group.len() as u64 == count {
+            words = cmp::max(words, group.len());
+        }
+    }
+
+    words
+}
+
+fn document_exact_words(document: &RawDocument) -> usize {
+    exact_attribute_words(
+        document.query_words,
+        document.query_index(),
+        document.attribute(),
+        document.word_index(),
+        document.is_exact(),
+        &document.fields_counts,
+    )
+}
+
+/// Ranks first the documents with an attribute equal to the query, e.g. the title
+/// "new york" is placed before the title "new york city" for the query "new york".
+#[derive(Debug, Clone, Copy)]
+pub struct Exactness;
+
+impl Criterion for Exactness {
+    fn evaluate(&self, lhs: &RawDocument, rhs: &RawDocument) -> Ordering {
+        let lhs = document_exact_words(lhs);
+        let rhs = document_exact_words(rhs);
+
+        lhs.cmp(&rhs).reverse()
+    }
+
+    fn name(&self) -> &str {
+        "Exactness"
+    }
+
+    fn score(&self, document: &RawDocument) -> Option<Number> {
+        Some(Number::Unsigned(document_exact_words(document) as u64))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // typing: "new york"
+    //
+    // doc0: { 0. "new york" }
+    // doc1: { 0. "new york city" }
+    #[test]
+    fn whole_attribute() {
+        let doc0 = {
+            let query_index = &[0, 1];
+            let attribute = &[0, 0];
+            let word_index = &[0, 1];
+            let is_exact = &[true, true];
+            let fields_counts = Set::new(&[(SchemaAttr(0), 2)]).unwrap();
+
+            exact_attribute_words(
+                2,
+                query_index,
+                attribute,
+                word_index,
+                is_exact,
+                fields_counts,
+            )
+        };
+
+        let doc1 = {
+            let query_index = &[0, 1];
+            let attribute = &[0, 0];
+            let word_index = &[0, 1];
+            let is_exact = &[true, true];
+            let fields_counts = Set::new(&[(SchemaAttr(0), 3)]).unwrap();
+
+            exact_attribute_words(
+                2,
+                query_index,
+                attribute,
+                word_index,
+                is_exact,
+                fields_counts,
+            )
+        };
+
+        assert_eq!(doc0, 2);
+        assert_eq!(doc1, 0);
+        assert_eq!(doc0.cmp(&doc1).reverse(), Ordering::Less);
+    }
+
+    // typing: "new york"
+    //
+    // doc0: { 0. "york new" }
+    // doc1: { 0. "new yorkk" }
+    // doc2: { 0. "new", 1. "york" }
+    #[test]
+    fn not_the_query() {
+        let fields_counts = Set::new(&[(SchemaAttr(0), 2)]).unwrap();
+        let words =
+            exact_attribute_words(2, &[0, 1], &[0, 0], &[1, 0], &[true, true], fields_counts);
+        assert_eq!(words, 0);
+
+        let words =
+            exact_attribute_words(2, &[0, 1], &[0, 0], &[0, 1], &[true, false], fields_counts);
+        assert_eq!(words, 0);
+
+        // each attribute is only a part of the query
+        let fields_counts = Set::new(&[(SchemaAttr(0), 1), (SchemaAttr(1), 1)]).unwrap();
+        let words =
+            exact_attribute_words(2, &[0, 1], &[0, 1], &[0, 0], &[true, true], fields_counts);
+        assert_eq!(words, 0);
+    }
+
+    // typing: "new york"
+    //
+    // doc0: { 0. "york" }
+    // doc1: { 0. "new" }
+    #[test]
+    fn missing_query_words() {
+        let fields_counts = Set::new(&[(SchemaAttr(0), 1)]).unwrap();
+        let words = exact_attribute_words(2, &[1], &[0], &[0], &[true], fields_counts);
+        assert_eq!(words, 0);
+
+        let words = exact_attribute_words(2, &[0], &[0], &[0], &[true], fields_counts);
+        assert_eq!(words, 0);
+    }
+}
@@ -1,27 +1,39 @@
 mod document_id;
 mod exact;
+mod exactness;
 mod number_of_words;
+mod ranking_rule;
 mod sort_by_attr;
+mod sort_by_distance;
 mod sum_of_typos;
 mod sum_of_words_attribute;
 mod sum_of_words_position;
 mod words_proximity;
 
-use crate::RawDocument;
+use crate::{Number, RawDocument};
 use std::cmp::Ordering;
 
 pub use self::{
-    document_id::DocumentId, exact::Exact, number_of_words::NumberOfWords,
-    sort_by_attr::SortByAttr, sum_of_typos::SumOfTypos,
+    document_id::DocumentId, exact::Exact, exactness::Exactness, number_of_words::NumberOfWords,
+    sort_by_attr::SortByAttr, sort_by_distance::SortByDistance, sum_of_typos::SumOfTypos,
     sum_of_words_attribute::SumOfWordsAttribute, sum_of_words_position::SumOfWordsPosition,
     words_proximity::WordsProximity,
 };
 
+pub use self::ranking_rule::{ParseRankingRuleError, RankingProfiles, RankingRule};
+
 pub trait Criterion: Send + Sync {
     fn evaluate(&self, lhs: &RawDocument, rhs: &RawDocument) -> Ordering;
 
     fn name(&self) -> &str;
 
+    /// The value the criterion compares the documents with, it is only used to
+    /// explain the ranking of the documents, see `QueryBuilder::explain`.
+    #[inline]
+    fn score(&self, _document: &RawDocument) -> Option<Number> {
+        None
+    }
+
     #[inline]
     fn eq(&self, lhs: &RawDocument, rhs: &RawDocument) -> bool {
         self.evaluate(lhs, rhs) == Ordering::Equal
@@ -37,6 +49,10 @@ impl<'a, T: Criterion + ?Sized + Send + Sync> Criterion for &'a T {
         (**self).name()
     }
 
+    fn score(&self, document: &RawDocument) -> Option<Number> {
+        (**self).score(document)
+    }
+
     fn eq(&self, lhs: &RawDocument, rhs: &RawDocument) -> bool {
         (**self).eq(lhs, rhs)
     }
@@ -51,6 +67,10 @@ impl<T: Criterion + ?Sized> Criterion for Box<T> {
         (**self).name()
     }
 
+    fn score(&self, document: &RawDocument) -> Option<Number> {
+        (**self).score(document)
+    }
+
     fn eq(&self, lhs: &RawDocument, rhs: &RawDocument) -> bool {
         (**self).eq(lhs, rhs)
     }
@@ -0,0 +1,275 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+use log::warn;
+use meilidb_schema::Schema;
+use serde::{Deserialize, Serialize};
+
+use crate::criterion::{
+    Criteria, CriteriaBuilder, DocumentId, Exact, Exactness, NumberOfWords, SortByAttr, SumOfTypos,
+    SumOfWordsAttribute, SumOfWordsPosition, WordsProximity,
+};
+use crate::RankedMap;
+
+/// The named lists of ranking rules of an index, a search can select one of them
+/// to sort the documents differently, e.g. `"newest"` or `"cheapest"`.
+pub type RankingProfiles = BTreeMap<String, Vec<RankingRule>>;
+
+/// A rule of the ranking criteria of an index settings, written like `"typo"`,
+/// `"words"`, `"proximity"`, `"attribute"`, `"wordsPosition"`, `"exact"`,
+/// `"exactness"`, `"asc(release_date)"` or `"dsc(release_date)"`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RankingRule {
+    Typo,
+    Words,
+    Proximity,
+    Attribute,
+    WordsPosition,
+    Exact,
+    Asc(String),
+    Dsc(String),
+    /// Ranks first the documents with an attribute equal to the query.
+    Exactness,
+}
+
+impl RankingRule {
+    /// The rules of the default criteria.
+    pub fn default_rules() -> Vec<RankingRule> {
+        vec![
+            RankingRule::Typo,
+            RankingRule::Words,
+            RankingRule::Proximity,
+            RankingRule::Attribute,
+            RankingRule::WordsPosition,
+            RankingRule::Exact,
+        ]
+    }
+
+    /// The attribute sorted by an `asc` or a `dsc` rule.
+    pub fn attribute(&self) -> Option<&str> {
+        match self {
+            RankingRule::Asc(name) | RankingRule::Dsc(name) => Some(name),
+            _ => None,
+        }
+    }
+
+    /// Builds the criteria of the rules followed by the document id criterion,
+    /// the rules on attributes that are not ranked in the schema are skipped.
+    pub fn criteria<'a>(
+        rules: &[RankingRule],
+        ranked_map: &'a RankedMap,
+        schema: &Schema,
+    ) -> Criteria<'a> {
+        let mut builder = RankingRule::criteria_builder(rules, ranked_map, schema);
+        builder.push(DocumentId);
+        builder.build()
+    }
+
+    /// The criteria of the rules without the document id criterion,
+    /// more criteria can be pushed before building them.
+    pub fn criteria_builder<'a>(
+        rules: &[RankingRule],
+        ranked_map: &'a RankedMap,
+        schema: &Schema,
+    ) -> CriteriaBuilder<'a> {
+        let mut builder = CriteriaBuilder::with_capacity(rules.len() + 2);
+
+        for rule in rules {
+            match rule {
+                RankingRule::Typo => builder.push(SumOfTypos),
+                RankingRule::Words => builder.push(NumberOfWords),
+                RankingRule::Proximity => builder.push(WordsProximity),
+                RankingRule::Attribute => builder.push(SumOfWordsAttribute),
+                RankingRule::WordsPosition => builder.push(SumOfWordsPosition),
+                RankingRule::Exact => builder.push(Exact),
+                RankingRule::Exactness => builder.push(Exactness),
+                RankingRule::Asc(name) | RankingRule::Dsc(name) => {
+                    let result = match rule {
+                        RankingRule::Asc(_) => {
+                            SortByAttr::lower_is_better(ranked_map, schema, name)
+                        }
+                        _ => SortByAttr::higher_is_better(ranked_map, schema, name),
+                    };
+
+                    match result {
+                        Ok(criterion) => builder.push(criterion),
+                        Err(e) => warn!("the ranking rule {} is ignored; {}", rule, e),
+                    }
+                }
+            }
+        }
+
+        builder
+    }
+}
+
+impl fmt::Display for RankingRule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RankingRule::Typo => f.write_str("typo"),
+            RankingRule::Words => f.write_str("words"),
+            RankingRule::Proximity => f.write_str("proximity"),
+            RankingRule::Attribute => f.write_str("attribute"),
+            RankingRule::WordsPosition => f.write_str("wordsPosition"),
+            RankingRule::Exact => f.write_str("exact"),
+            RankingRule::Exactness => f.write_str("exactness"),
+            RankingRule::Asc(name) => write!(f, "asc({})", name),
+            RankingRule::Dsc(name) => write!(f, "dsc({})", name),
+        }
+    }
+}
+
+impl FromStr for RankingRule {
+    type Err = ParseRankingRuleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let rule = match s {
+            "typo" => RankingRule::Typo,
+            "words" => RankingRule::Words,
+            "proximity" => RankingRule::Proximity,
+            "attribute" => RankingRule::Attribute,
+            "wordsPosition" => RankingRule::WordsPosition,
+            "exact" => RankingRule::Exact,
+            "exactness" => RankingRule::Exactness,
+            _ => {
+                let (order, rest) = match s.find('(') {
+                    Some(index) => s.split_at(index),
+                    None => return Err(ParseRankingRuleError::UnknownRule(s.to_owned())),
+                };
+
+                let name = match (rest.starts_with('('), rest.ends_with(')')) {
+                    (true, true) if rest.len() > 2 => rest[1..rest.len() - 1].trim(),
+                    _ => return Err(ParseRankingRuleError::InvalidAttribute(s.to_owned())),
+                };
+
+                if name.is_empty() {
+                    return Err(ParseRankingRuleError::InvalidAttribute(s.to_owned()));
+                }
+
+                match order.trim() {
+                    "asc" => RankingRule::Asc(name.to_owned()),
+                    "dsc" => RankingRule::Dsc(name.to_owned()),
+                    _ => return Err(ParseRankingRuleError::UnknownRule(s.to_owned())),
+                }
+            }
+        };
+
+        Ok(rule)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseRankingRuleError {
+    UnknownRule(String),
+    InvalidAttribute(String),
+}
+
+impl fmt::Display for ParseRankingRuleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use ParseRankingRuleError::*;
+        match self {
+            UnknownRule(rule) => write!(f, "unknown ranking rule {:?}", rule),
+            InvalidAttribute(rule) => {
+                write!(
+                    f,
+                    "the ranking rule {:?} must be like \"asc(attribute)\"",
+                    rule
+                )
+            }
+        }
+    }
+}
+
+impl Error for ParseRankingRuleError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_rules() {
+        let rules = [
+            "typo",
+            "words",
+            "proximity",
+            "attribute",
+            "exact",
+            "exactness",
+            "dsc(release_date)",
+        ];
+        let rules: Result<Vec<RankingRule>, _> = rules.iter().map(|r| r.parse()).collect();
+        assert_eq!(
+            rules.unwrap(),
+            vec![
+                RankingRule::Typo,
+                RankingRule::Words,
+                RankingRule::Proximity,
+                RankingRule::Attribute,
+                RankingRule::Exact,
+                RankingRule::Exactness,
+                RankingRule::Dsc("release_date".to_string()),
+            ]
+        );
+
+        assert_eq!(
+            " asc( price ) ".parse(),
+            Ok(RankingRule::Asc("price".to_string()))
+        );
+        assert_eq!(
+            "typos".parse::<RankingRule>(),
+            Err(ParseRankingRuleError::UnknownRule("typos".to_string()))
+        );
+        assert_eq!(
+            "desc(price)".parse::<RankingRule>(),
+            Err(ParseRankingRuleError::UnknownRule(
+                "desc(price)".to_string()
+            ))
+        );
+        assert_eq!(
+            "asc()".parse::<RankingRule>(),
+            Err(ParseRankingRuleError::InvalidAttribute("asc()".to_string()))
+        );
+        assert_eq!(
+            "asc(price".parse::<RankingRule>(),
+            Err(ParseRankingRuleError::InvalidAttribute(
+                "asc(price".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn display_roundtrip() {
+        let mut rules = RankingRule::default_rules();
+        rules.push(RankingRule::Exactness);
+        rules.push(RankingRule::Asc("price".to_string()));
+
+        for rule in rules {
+            assert_eq!(rule.to_string().parse(), Ok(rule));
+        }
+    }
+
+    #[test]
+    fn criteria_of_rules() {
+        use crate::criterion::Criterion;
+        use meilidb_schema::{SchemaBuilder, DISPLAYED, RANKED};
+
+        let mut builder = SchemaBuilder::with_identifier("id");
+        builder.new_attribute("price", DISPLAYED | RANKED);
+        let schema = builder.build();
+        let ranked_map = RankedMap::default();
+
+        let rules = vec![
+            RankingRule::Exact,
+            RankingRule::Dsc("price".to_string()),
+            RankingRule::Asc("missing".to_string()),
+            RankingRule::Typo,
+        ];
+
+        let criteria = RankingRule::criteria(&rules, &ranked_map, &schema);
+        let names: Vec<_> = criteria.as_ref().iter().map(|c| c.name()).collect();
+        assert_eq!(names, ["Exact", "SortByAttr", "SumOfTypos", "DocumentId"]);
+    }
+}
@@ -3,7 +3,7 @@ use std::error::Error;
 use std::fmt;
 
 use crate::criterion::Criterion;
-use crate::{RankedMap, RawDocument};
+use crate::{Number, RankedMap, RawDocument};
 use meilidb_schema::{Schema, SchemaAttr};
 
 /// An helper struct that permit to sort documents by
@@ -11,13 +11,13 @@ use meilidb_schema::{Schema, SchemaAttr};
 ///
 /// # Note
 ///
-/// If a document cannot be deserialized it will be considered [`None`][].
+/// The documents that have no value for the attribute in the ranked map, because
+/// the field is missing, is not a number or the ranked map has not been rebuilt
+/// since the attribute is ranked, are always placed after the ones that have one,
+/// whatever the order, and are considered equal between them. Use
+/// [`RankedMap::attribute_len`] to know how many documents are concerned.
 ///
-/// Deserialized documents are compared like `Some(doc0).cmp(&Some(doc1))`,
-/// so you must check the [`Ord`] of `Option` implementation.
-///
-/// [`None`]: https://doc.rust-lang.org/std/option/enum.Option.html#variant.None
-/// [`Ord`]: https://doc.rust-lang.org/std/option/enum.Option.html#impl-Ord
+/// [`RankedMap::attribute_len`]: ../struct.RankedMap.html#method.attribute_len
 ///
 /// # Example
 ///
@@ -109,6 +109,10 @@ impl<'a> Criterion for SortByAttr<'a> {
     fn name(&self) -> &str {
         "SortByAttr"
     }
+
+    fn score(&self, document: &RawDocument) -> Option<Number> {
+        self.ranked_map.get(document.id, self.attr)
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -0,0 +1,42 @@
+use std::cmp::Ordering;
+
+use crate::criterion::Criterion;
+use crate::{GeoIndex, GeoPoint, Number, RawDocument};
+
+/// Sorts the documents from the closest to the farthest of a point, the distances are
+/// compared in whole meters. The documents without a location are placed after the
+/// other ones and are considered equal between them.
+pub struct SortByDistance<'a> {
+    geo_index: &'a GeoIndex,
+    origin: GeoPoint,
+}
+
+impl<'a> SortByDistance<'a> {
+    pub fn new(geo_index: &'a GeoIndex, origin: GeoPoint) -> SortByDistance<'a> {
+        SortByDistance { geo_index, origin }
+    }
+
+    fn distance(&self, document: &RawDocument) -> Option<u64> {
+        let point = self.geo_index.get(document.id)?;
+        Some(self.origin.distance(point).round() as u64)
+    }
+}
+
+impl<'a> Criterion for SortByDistance<'a> {
+    fn evaluate(&self, lhs: &RawDocument, rhs: &RawDocument) -> Ordering {
+        match (self.distance(lhs), self.distance(rhs)) {
+            (Some(lhs), Some(rhs)) => lhs.cmp(&rhs),
+            (None, Some(_)) => Ordering::Greater,
+            (Some(_), None) => Ordering::Less,
+            (None, None) => Ordering::Equal,
+        }
+    }
+
+    fn name(&self) -> &str {
+        "SortByDistance"
+    }
+
+    fn score(&self, document: &RawDocument) -> Option<Number> {
+        self.distance(document).map(Number::Unsigned)
+    }
+}
@@ -3,7 +3,7 @@ use std::cmp::Ordering;
 use slice_group_by::GroupBy;
 
 use crate::criterion::Criterion;
-use crate::RawDocument;
+use crate::{Number, RawDocument};
 
 // This function is a wrong logarithmic 10 function.
 // It is safe to panic on input number higher than 3,
@@ -57,6 +57,17 @@ impl Criterion for SumOfTypos {
     fn name(&self) -> &str {
         "SumOfTypos"
     }
+
+    fn score(&self, document: &RawDocument) -> Option<Number> {
+        // the number of typos of the best match of each query word
+        let mut sum_typos = 0;
+        let mut index = 0;
+        for group in document.query_index().linear_group() {
+            sum_typos += u64::from(document.distance()[index]);
+            index += group.len();
+        }
+        Some(Number::Unsigned(sum_typos))
+    }
 }
 
 #[cfg(test)]
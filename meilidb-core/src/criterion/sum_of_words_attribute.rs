@@ -1,5 +1,5 @@
 use crate::criterion::Criterion;
-use crate::RawDocument;
+use crate::{Number, RawDocument};
 use slice_group_by::GroupBy;
 use std::cmp::Ordering;
 
@@ -39,6 +39,11 @@ impl Criterion for SumOfWordsAttribute {
     fn name(&self) -> &str {
         "SumOfWordsAttribute"
     }
+
+    fn score(&self, document: &RawDocument) -> Option<Number> {
+        let sum = sum_matches_attributes(document.query_index(), document.attribute());
+        Some(Number::Unsigned(sum as u64))
+    }
 }
 
 #[cfg(test)]
@@ -1,5 +1,5 @@
 use crate::criterion::Criterion;
-use crate::RawDocument;
+use crate::{Number, RawDocument};
 use slice_group_by::GroupBy;
 use std::cmp::Ordering;
 
@@ -39,6 +39,11 @@ impl Criterion for SumOfWordsPosition {
     fn name(&self) -> &str {
         "SumOfWordsPosition"
     }
+
+    fn score(&self, document: &RawDocument) -> Option<Number> {
+        let sum = sum_matches_attribute_index(document.query_index(), document.word_index());
+        Some(Number::Unsigned(sum as u64))
+    }
 }
 
 #[cfg(test)]
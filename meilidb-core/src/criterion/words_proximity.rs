@@ -1,5 +1,5 @@
 use crate::criterion::Criterion;
-use crate::RawDocument;
+use crate::{Number, RawDocument};
 use slice_group_by::GroupBy;
 use std::cmp::{self, Ordering};
 
@@ -107,6 +107,16 @@ impl Criterion for WordsProximity {
     fn name(&self) -> &str {
         "WordsProximity"
     }
+
+    fn score(&self, document: &RawDocument) -> Option<Number> {
+        let proximity = matches_proximity(
+            document.query_index(),
+            document.distance(),
+            document.attribute(),
+            document.word_index(),
+        );
+        Some(Number::Unsigned(u64::from(proximity)))
+    }
 }
 
 #[cfg(test)]
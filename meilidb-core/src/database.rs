@@ -1,25 +1,58 @@
 use std::collections::hash_map::{Entry, HashMap};
+use std::collections::{BTreeMap, HashSet};
 use std::fs::File;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, RwLock};
-use std::{fs, thread};
+use std::{fs, str, thread};
 
 use crossbeam_channel::{Receiver, Sender};
-use heed::types::{Str, Unit};
+use heed::types::{ByteSlice, Str, Unit};
 use heed::{CompactionOption, Result as ZResult};
 use log::debug;
 
+use crate::operations_log::{LoggedOperation, Operation, OperationsLog};
+use crate::update::{
+    ArcSwapFn, CallbacksDispatcher, CustomsMerge, CustomsRename, SettingsPatchContent,
+};
 use crate::{store, update, Index, MResult};
 
+/// The maximum size of the memory map of the environment, the database cannot grow beyond it.
+pub const MAP_SIZE: usize = 10 * 1024 * 1024 * 1024; // 10GB
+
 pub type BoxUpdateFn = Box<dyn Fn(&str, update::ProcessedUpdateResult) + Send + Sync + 'static>;
-type ArcSwapFn = arc_swap::ArcSwapOption<BoxUpdateFn>;
 
 pub struct Database {
     pub env: heed::Env,
     common_store: heed::PolyDatabase,
     indexes_store: heed::Database<Str, Unit>,
+    operations_log: OperationsLog,
     indexes: RwLock<HashMap<String, (Index, thread::JoinHandle<MResult<()>>)>>,
     update_fn: Arc<ArcSwapFn>,
+    callbacks: CallbacksDispatcher,
+    customs_merge: CustomsMerge,
+    customs_rename: CustomsRename,
+    record_replays: Arc<AtomicBool>,
+    read_only: Arc<AtomicBool>,
+    startup_report: StartupReport,
+}
+
+/// The inconsistencies found between the registered indexes
+/// and the stores present when the database was opened.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct StartupReport {
+    /// The indexes that are registered but have some of their stores missing,
+    /// these indexes have not been opened.
+    pub missing_indexes: Vec<String>,
+    /// The non-empty stores that do not belong to any registered index, they are
+    /// left by interrupted index deletions and have been cleared.
+    pub orphaned_stores: Vec<String>,
+}
+
+impl StartupReport {
+    pub fn is_consistent(&self) -> bool {
+        self.missing_indexes.is_empty() && self.orphaned_stores.is_empty()
+    }
 }
 
 macro_rules! r#break_try {
@@ -42,16 +75,75 @@ pub enum UpdateEvent {
 pub type UpdateEvents = Receiver<UpdateEvent>;
 pub type UpdateEventsEmitter = Sender<UpdateEvent>;
 
+/// Clears the stores that do not belong to any of the given indexes and returns the names
+/// of the ones that were not empty. LMDB does not allow to remove a named database from
+/// the environment, the emptied stores are reused if an index with the same uid is created.
+fn clear_orphaned_stores(env: &heed::Env, indexes_uids: &[String]) -> MResult<Vec<String>> {
+    let known_stores: HashSet<_> = indexes_uids
+        .iter()
+        .flat_map(|uid| store::stores_names(uid))
+        .collect();
+
+    // the unnamed database contains the names of all the named ones
+    let unnamed = match env.open_poly_database(None)? {
+        Some(unnamed) => unnamed,
+        None => return Ok(Vec::new()),
+    };
+
+    let mut unknown_stores = Vec::new();
+    let reader = env.read_txn()?;
+    for result in unnamed.iter::<ByteSlice, ByteSlice>(&reader)? {
+        let (name, _) = result?;
+        if let Ok(name) = str::from_utf8(name) {
+            if name.starts_with("store-") && !known_stores.contains(name) {
+                unknown_stores.push(name.to_owned());
+            }
+        }
+    }
+    reader.abort();
+
+    let mut stores = Vec::with_capacity(unknown_stores.len());
+    for name in unknown_stores {
+        if let Some(store) = env.open_poly_database(Some(&name))? {
+            stores.push((name, store));
+        }
+    }
+
+    let mut orphaned_stores = Vec::new();
+    let mut writer = env.write_txn()?;
+    for (name, store) in stores {
+        let is_empty = store
+            .iter::<ByteSlice, ByteSlice>(&writer)?
+            .next()
+            .is_none();
+        if !is_empty {
+            store.clear(&mut writer)?;
+            orphaned_stores.push(name);
+        }
+    }
+    writer.commit()?;
+
+    Ok(orphaned_stores)
+}
+
 fn update_awaiter(
     receiver: UpdateEvents,
     env: heed::Env,
     index_uid: &str,
-    update_fn: Arc<ArcSwapFn>,
+    callbacks: CallbacksDispatcher,
+    read_only: Arc<AtomicBool>,
+    operations_log: OperationsLog,
     index: Index,
 ) -> MResult<()> {
     let mut receiver = receiver.into_iter();
     while let Some(UpdateEvent::NewUpdate) = receiver.next() {
         loop {
+            // the enqueued updates are kept until the database is writable again
+            if read_only.load(Ordering::SeqCst) {
+                debug!("database is read-only, updates are postponed");
+                break;
+            }
+
             // instantiate a main/parent transaction
             let mut writer = break_try!(env.write_txn(), "LMDB write transaction begin failed");
 
@@ -66,13 +158,39 @@ fn update_awaiter(
                 }
             };
 
+            // keep what is needed to replay the update, before it modifies the index
+            if index.record_replays.load(Ordering::SeqCst) {
+                let result = update::UpdateReplay::new(&writer, &index, update.clone());
+                let replay = break_try!(result, "update replay recording failed");
+                let result = index
+                    .updates_replays
+                    .push_replay(&mut writer, update_id, &replay);
+                break_try!(result, "update replay recording failed");
+            }
+
             // instantiate a nested transaction
             let result = env.nested_write_txn(&mut writer);
             let mut nested_writer = break_try!(result, "LMDB nested write transaction failed");
 
+            #[cfg(feature = "fault-injection")]
+            break_try!(
+                index.fault_injector.before_update(update_id),
+                "update task failed"
+            );
+
+            // the operation is logged only once the update is known to be successful,
+            // the identifiers of the deleted documents are only known before it is applied
+            let result = operations_log.is_enabled(&nested_writer);
+            let operation = if break_try!(result, "operations log retrieval failed") {
+                let result = Operation::from_update(&nested_writer, &index, update.data());
+                Some(break_try!(result, "operations log retrieval failed"))
+            } else {
+                None
+            };
+
             // try to apply the update to the database using the nested transaction
             let result = update::update_task(&mut nested_writer, index.clone(), update_id, update);
-            let status = break_try!(result, "update task failed");
+            let (status, documents_ids) = break_try!(result, "update task failed");
 
             // commit the nested transaction if the update was successful, abort it otherwise
             if status.error.is_none() {
@@ -81,18 +199,63 @@ fn update_awaiter(
                 nested_writer.abort()
             }
 
+            // a failure here must also roll back the already committed nested transaction
+            #[cfg(feature = "fault-injection")]
+            break_try!(
+                index.fault_injector.before_update_result(update_id),
+                "update result store commit failed"
+            );
+
             // write the result of the update in the updates-results store
             let updates_results = index.updates_results;
             let result = updates_results.put_update_result(&mut writer, update_id, &status);
 
             // always commit the main/parent transaction, even if the update was unsuccessful
             break_try!(result, "update result store commit failed");
+
+            if let (None, Some(operation)) = (&status.error, operation) {
+                let result = operation.with_stored_documents(&writer, &index, &documents_ids);
+                let operation = break_try!(result, "operations log append failed");
+                let result =
+                    operations_log.append(&mut writer, index_uid, Some(update_id), operation);
+                break_try!(result, "operations log append failed");
+            }
+
+            #[cfg(feature = "fault-injection")]
+            index.fault_injector.before_commit(update_id);
+
+            let schema_updated = match status.update_type {
+                update::UpdateType::Schema | update::UpdateType::Migration { .. } => {
+                    status.error.is_none()
+                }
+                // the flattening of the documents can append attributes to the schema
+                update::UpdateType::DocumentsAddition { .. }
+                | update::UpdateType::DocumentsPartial { .. }
+                    if status.error.is_none() =>
+                {
+                    let result = index.main.schema(&writer);
+                    let schema = break_try!(result, "schema retrieval failed");
+                    schema.as_ref() != index.schema_snapshot().schema()
+                }
+                _ => false,
+            };
+
             break_try!(writer.commit(), "update parent transaction failed");
 
-            // call the user callback when the update and the result are written consistently
-            if let Some(ref callback) = *update_fn.load() {
-                (callback)(index_uid, status);
+            // the new schema must be visible before the waiters are woken up
+            if schema_updated {
+                let reader = break_try!(env.read_txn(), "LMDB read transaction begin failed");
+                let schema = break_try!(index.main.schema(&reader), "schema retrieval failed");
+                reader.abort();
+                index.schema_snapshots.replace(schema);
             }
+
+            // wake up the waiters once the update result is readable
+            index.processed_updates.notify(update_id);
+
+            // call the user callbacks when the update and the result are written consistently,
+            // on the callbacks thread to not wait for them before processing the next update
+            callbacks.dispatch(index_uid, &index.update_callback, status);
         }
     }
 
@@ -112,13 +275,22 @@ impl Database {
         fs::create_dir_all(path.as_ref())?;
 
         let env = heed::EnvOpenOptions::new()
-            .map_size(10 * 1024 * 1024 * 1024) // 10GB
+            .map_size(MAP_SIZE)
             .max_dbs(3000)
             .open(path)?;
 
         let common_store = env.create_poly_database(Some("common"))?;
         let indexes_store = env.create_database::<Str, Unit>(Some("indexes"))?;
+        let operations_log = OperationsLog {
+            operations: env.create_database(Some("operations"))?,
+            common: common_store,
+        };
         let update_fn = Arc::new(ArcSwapFn::empty());
+        let callbacks = CallbacksDispatcher::spawn(update_fn.clone());
+        let customs_merge = CustomsMerge::default();
+        let customs_rename = CustomsRename::default();
+        let record_replays = Arc::new(AtomicBool::new(false));
+        let read_only = Arc::new(AtomicBool::new(false));
 
         // list all indexes that needs to be opened
         let mut must_open = Vec::new();
@@ -130,32 +302,53 @@ impl Database {
 
         reader.abort();
 
+        let orphaned_stores = clear_orphaned_stores(&env, &must_open)?;
+        for name in &orphaned_stores {
+            log::warn!("the orphaned store {} has been cleared", name);
+        }
+
         // open the previously aggregated indexes
         let mut indexes = HashMap::new();
+        let mut missing_indexes = Vec::new();
         for index_uid in must_open {
             let (sender, receiver) = crossbeam_channel::bounded(100);
-            let index = match store::open(&env, &index_uid, sender.clone())? {
+            let mut index = match store::open(&env, &index_uid, sender.clone())? {
                 Some(index) => index,
                 None => {
                     log::warn!(
                         "the index {} doesn't exist or has not all the databases",
                         index_uid
                     );
+                    missing_indexes.push(index_uid);
                     continue;
                 }
             };
 
+            index.customs_merge = customs_merge.clone();
+            index.customs_rename = customs_rename.clone();
+            index.record_replays = record_replays.clone();
+
+            // the updates processed before the index has been opened
+            let reader = env.read_txn()?;
+            if let Some((update_id, _)) = index.updates_results.last_update_id(&reader)? {
+                index.processed_updates.notify(update_id);
+            }
+            reader.abort();
+
             let env_clone = env.clone();
             let index_clone = index.clone();
             let name_clone = index_uid.clone();
-            let update_fn_clone = update_fn.clone();
+            let callbacks_clone = callbacks.clone();
+            let read_only_clone = read_only.clone();
 
             let handle = thread::spawn(move || {
                 update_awaiter(
                     receiver,
                     env_clone,
                     &name_clone,
-                    update_fn_clone,
+                    callbacks_clone,
+                    read_only_clone,
+                    operations_log,
                     index_clone,
                 )
             });
@@ -175,8 +368,18 @@ impl Database {
             env,
             common_store,
             indexes_store,
+            operations_log,
             indexes: RwLock::new(indexes),
             update_fn,
+            callbacks,
+            customs_merge,
+            customs_rename,
+            record_replays,
+            read_only,
+            startup_report: StartupReport {
+                missing_indexes,
+                orphaned_stores,
+            },
         })
     }
 
@@ -190,28 +393,40 @@ impl Database {
 
     pub fn create_index(&self, name: impl AsRef<str>) -> MResult<Index> {
         let name = name.as_ref();
+        if self.is_read_only() {
+            return Err(crate::Error::ReadOnly);
+        }
+
         let mut indexes_lock = self.indexes.write().unwrap();
 
         match indexes_lock.entry(name.to_owned()) {
             Entry::Occupied(_) => Err(crate::Error::IndexAlreadyExists),
             Entry::Vacant(entry) => {
                 let (sender, receiver) = crossbeam_channel::bounded(100);
-                let index = store::create(&self.env, name, sender)?;
+                let mut index = store::create(&self.env, name, sender)?;
+                index.customs_merge = self.customs_merge.clone();
+                index.customs_rename = self.customs_rename.clone();
+                index.record_replays = self.record_replays.clone();
 
                 let mut writer = self.env.write_txn()?;
                 self.indexes_store.put(&mut writer, name, &())?;
+                let operations_log = self.operations_log;
+                operations_log.append(&mut writer, name, None, Operation::IndexCreated)?;
 
                 let env_clone = self.env.clone();
                 let index_clone = index.clone();
                 let name_clone = name.to_owned();
-                let update_fn_clone = self.update_fn.clone();
+                let callbacks_clone = self.callbacks.clone();
+                let read_only_clone = self.read_only.clone();
 
                 let handle = thread::spawn(move || {
                     update_awaiter(
                         receiver,
                         env_clone,
                         &name_clone,
-                        update_fn_clone,
+                        callbacks_clone,
+                        read_only_clone,
+                        operations_log,
                         index_clone,
                     )
                 });
@@ -224,6 +439,18 @@ impl Database {
         }
     }
 
+    /// Creates an index without a schema, the schema is inferred from the first documents
+    /// added and the attributes of the next ones are appended to it when they are new.
+    pub fn create_schemaless_index(&self, name: impl AsRef<str>) -> MResult<Index> {
+        let index = self.create_index(name)?;
+
+        let mut writer = self.env.write_txn()?;
+        index.main.put_attributes_discovery(&mut writer, true)?;
+        writer.commit()?;
+
+        Ok(index)
+    }
+
     pub fn delete_index(&self, name: impl AsRef<str>) -> MResult<bool> {
         let name = name.as_ref();
         let mut indexes_lock = self.indexes.write().unwrap();
@@ -234,6 +461,9 @@ impl Database {
                 // and clear all the LMDB dbi
                 let mut writer = self.env.write_txn()?;
                 self.indexes_store.delete(&mut writer, &name)?;
+                let operation = Operation::IndexDeleted;
+                self.operations_log
+                    .append(&mut writer, &name, None, operation)?;
                 writer.commit()?;
 
                 // send a stop event to the update loop of the index
@@ -250,6 +480,94 @@ impl Database {
         }
     }
 
+    /// Creates a new empty index with the settings of the source index, its schema,
+    /// synonyms, stop words and ranking configuration, the documents are not copied.
+    pub fn clone_index_settings(
+        &self,
+        src: impl AsRef<str>,
+        dst: impl AsRef<str>,
+    ) -> MResult<Index> {
+        let dst = dst.as_ref();
+        let src_index = self.open_index(src).ok_or(crate::Error::IndexNotFound)?;
+        let dst_index = self.create_index(dst)?;
+
+        let mut writer = self.env.write_txn()?;
+        if let Err(e) = store::copy_settings(&mut writer, &src_index, &dst_index) {
+            // the new index must not be left without its settings
+            writer.abort();
+            self.delete_index(dst)?;
+            return Err(e);
+        }
+        writer.commit()?;
+
+        let reader = self.env.read_txn()?;
+        let schema = dst_index.main.schema(&reader)?;
+        reader.abort();
+        dst_index.schema_snapshots.replace(schema);
+
+        Ok(dst_index)
+    }
+
+    /// Enqueues the same settings patch to all the given indexes and returns
+    /// the update id of each of them, by index uid.
+    ///
+    /// The indexes are all checked before anything is enqueued and the updates are
+    /// written in a single transaction, either every index receives the patch or none.
+    pub fn apply_settings_to<S: AsRef<str>>(
+        &self,
+        indexes: &[S],
+        settings: &SettingsPatchContent,
+    ) -> MResult<BTreeMap<String, u64>> {
+        if self.is_read_only() {
+            return Err(crate::Error::ReadOnly);
+        }
+
+        let mut targets = BTreeMap::new();
+        for uid in indexes {
+            let uid = uid.as_ref();
+            let index = self.open_index(uid).ok_or(crate::Error::IndexNotFound)?;
+            targets.insert(uid.to_owned(), index);
+        }
+
+        let mut updates_ids = BTreeMap::new();
+        let mut writer = self.env.write_txn()?;
+        for (uid, index) in &targets {
+            let update_id = update::push_settings_patch(
+                &mut writer,
+                index.updates,
+                index.updates_results,
+                settings.clone(),
+            )?;
+            updates_ids.insert(uid.clone(), update_id);
+        }
+        writer.commit()?;
+
+        for index in targets.values() {
+            let _ = index.updates_notifier.send(UpdateEvent::NewUpdate);
+        }
+
+        Ok(updates_ids)
+    }
+
+    /// Switches the database to a read-only mode where no index can be created
+    /// and the enqueued updates are not processed, the searches are still served.
+    ///
+    /// The postponed updates are processed once the database is writable again.
+    pub fn set_read_only(&self, read_only: bool) {
+        let was_read_only = self.read_only.swap(read_only, Ordering::SeqCst);
+
+        if was_read_only && !read_only {
+            let indexes = self.indexes.read().unwrap();
+            for (index, _) in indexes.values() {
+                let _ = index.updates_notifier.send(UpdateEvent::NewUpdate);
+            }
+        }
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.read_only.load(Ordering::SeqCst)
+    }
+
     pub fn set_update_callback(&self, update_fn: BoxUpdateFn) {
         let update_fn = Some(Arc::new(update_fn));
         self.update_fn.swap(update_fn);
@@ -259,10 +577,73 @@ impl Database {
         self.update_fn.swap(None);
     }
 
+    /// Sets how the customs of the settings patches are merged into the current customs
+    /// of the indexes when the patches are processed, they are replaced by default.
+    pub fn set_customs_merge(&self, merge_fn: update::BoxCustomsMergeFn) {
+        self.customs_merge.set(merge_fn);
+    }
+
+    /// Sets how the attributes named in the customs of the indexes are renamed
+    /// by the migrations, the customs are kept as they are by default.
+    pub fn set_customs_rename(&self, rename_fn: update::BoxCustomsRenameFn) {
+        self.customs_rename.set(rename_fn);
+    }
+
+    /// Starts or stops recording the payloads of the processed updates along with the
+    /// documents they modify, to be able to replay them. It is disabled by default
+    /// as every recorded update is stored again, only the last ones of each index are kept.
+    pub fn set_updates_replays(&self, enabled: bool) {
+        self.record_replays.store(enabled, Ordering::SeqCst);
+    }
+
+    pub fn is_updates_replays_enabled(&self) -> bool {
+        self.record_replays.load(Ordering::SeqCst)
+    }
+
+    /// The number of processed updates for which the callbacks have not been called because
+    /// they were too slow, more than `UPDATE_CALLBACKS_QUEUE_LEN` updates of an index behind,
+    /// the callbacks were called with a following update of the same index instead.
+    pub fn coalesced_update_callbacks(&self) -> usize {
+        self.callbacks.coalesced()
+    }
+
     pub fn copy_and_compact_to_path<P: AsRef<Path>>(&self, path: P) -> ZResult<File> {
         self.env.copy_to_path(path, CompactionOption::Enabled)
     }
 
+    /// Copies the environment to the path while the updates are being processed, the copy
+    /// is made from a read transaction and written in a temporary file that is synced and
+    /// renamed once complete, an interrupted backup never leaves a corrupted file at the path.
+    pub fn backup_to_path<P: AsRef<Path>>(&self, path: P) -> MResult<()> {
+        let path = path.as_ref();
+        let mut tmp_path = path.as_os_str().to_owned();
+        tmp_path.push(".tmp");
+        let tmp_path = PathBuf::from(tmp_path);
+
+        let copy = || -> MResult<()> {
+            self.env
+                .copy_to_path(&tmp_path, CompactionOption::Enabled)?;
+            File::open(&tmp_path)?.sync_all()?;
+            fs::rename(&tmp_path, path)?;
+            Ok(())
+        };
+
+        let result = copy();
+
+        if result.is_err() {
+            let _ = fs::remove_file(&tmp_path);
+        }
+
+        result
+    }
+
+    /// Backs up the environment in the directory while the updates are being processed,
+    /// the directory can then be opened as a database with [`Database::open_or_create`].
+    pub fn hot_backup<P: AsRef<Path>>(&self, dir: P) -> MResult<()> {
+        fs::create_dir_all(dir.as_ref())?;
+        self.backup_to_path(dir.as_ref().join("data.mdb"))
+    }
+
     pub fn indexes_uids(&self) -> Vec<String> {
         let indexes = self.indexes.read().unwrap();
         indexes.keys().cloned().collect()
@@ -271,17 +652,70 @@ impl Database {
     pub fn common_store(&self) -> heed::PolyDatabase {
         self.common_store
     }
+
+    /// Starts or stops logging the operations applied to the indexes,
+    /// the log is disabled by default and only records the operations that follow.
+    pub fn set_operations_log(&self, enabled: bool) -> MResult<()> {
+        let mut writer = self.env.write_txn()?;
+        self.operations_log.set_enabled(&mut writer, enabled)?;
+        writer.commit()?;
+        Ok(())
+    }
+
+    pub fn is_operations_log_enabled(&self) -> MResult<bool> {
+        let reader = self.env.read_txn()?;
+        Ok(self.operations_log.is_enabled(&reader)?)
+    }
+
+    /// The operations logged after the given sequence number, in order and at most `limit`
+    /// of them, a consumer starts from zero and then gives the last sequence number it has seen.
+    pub fn operations_since(&self, sequence: u64, limit: usize) -> MResult<Vec<LoggedOperation>> {
+        let reader = self.env.read_txn()?;
+        Ok(self.operations_log.since(&reader, sequence, limit)?)
+    }
+
+    /// Removes the logged operations up to the given sequence number included,
+    /// the sequence numbers of the next operations are not reused.
+    pub fn truncate_operations(&self, sequence: u64) -> MResult<usize> {
+        let mut writer = self.env.write_txn()?;
+        let removed = self.operations_log.truncate(&mut writer, sequence)?;
+        writer.commit()?;
+        Ok(removed)
+    }
+
+    /// The inconsistencies found and repaired when the database was opened.
+    pub fn startup_report(&self) -> &StartupReport {
+        &self.startup_report
+    }
+}
+
+impl Drop for Database {
+    fn drop(&mut self) {
+        // the callbacks of the processed updates are called before the database is gone
+        self.callbacks.close();
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    use crate::criterion::{self, CriteriaBuilder};
-    use crate::update::{ProcessedUpdateResult, UpdateStatus};
-    use crate::{Document, DocumentId};
+    use crate::criterion::{self, CriteriaBuilder, RankingRule};
+    use crate::serde::compute_document_id;
+    use crate::store::Posting;
+    use crate::update::{
+        DocumentCoercions, DuplicateDocument, DuplicatesPolicy, IdentifierCollision,
+        IdentifiersGeneration, IdentifiersVerification, ProcessedUpdateResult, UpdateLimits,
+        UpdateStatus, UpdateType,
+    };
+    use crate::{
+        ColdTierSettings, Document, DocumentId, DocumentRetrieval, Error, GeoPoint, LimitExceeded,
+        Number, TieringReport,
+    };
+    use meilidb_schema::SchemaAttr;
     use serde::de::IgnoredAny;
     use std::sync::mpsc;
+    use std::time::Duration;
 
     #[test]
     fn valid_updates() {
@@ -842,93 +1276,101 @@ mod tests {
     }
 
     #[test]
-    fn delete_index() {
+    fn update_limits_enforced_at_enqueue() {
         let dir = tempfile::tempdir().unwrap();
 
-        let database = Arc::new(Database::open_or_create(dir.path()).unwrap());
+        let database = Database::open_or_create(dir.path()).unwrap();
         let env = &database.env;
-
-        let (sender, receiver) = mpsc::sync_channel(100);
-        let db_cloned = database.clone();
-        let update_fn = move |name: &str, update: ProcessedUpdateResult| {
-            // try to open index to trigger a lock
-            let _ = db_cloned.open_index(name);
-            sender.send(update.update_id).unwrap()
-        };
-
-        // create the index
         let index = database.create_index("test").unwrap();
 
-        database.set_update_callback(Box::new(update_fn));
-
-        let schema = {
-            let data = r#"
-                identifier = "id"
-
-                [attributes."name"]
-                displayed = true
-                indexed = true
-
-                [attributes."description"]
-                displayed = true
-                indexed = true
-            "#;
-            toml::from_str(data).unwrap()
+        let limits = UpdateLimits {
+            max_documents_per_update: Some(2),
+            ..UpdateLimits::default()
         };
 
-        // add a schema to the index
         let mut writer = env.write_txn().unwrap();
-        let _update_id = index.schema_update(&mut writer, schema).unwrap();
+        index.main.put_update_limits(&mut writer, &limits).unwrap();
         writer.commit().unwrap();
 
-        // add documents to the index
         let mut additions = index.documents_addition();
+        additions.update_document(serde_json::json!({ "id": 1, "name": "Marvin" }));
+        additions.update_document(serde_json::json!({ "id": 2, "name": "Kevin" }));
+        additions.update_document(serde_json::json!({ "id": 3, "name": "Bob" }));
 
-        let doc1 = serde_json::json!({
-            "id": 123,
-            "name": "Marvin",
-            "description": "My name is Marvin",
-        });
+        let mut writer = env.write_txn().unwrap();
+        let result = additions.finalize(&mut writer);
+        assert_matches!(
+            result,
+            Err(Error::LimitExceeded(LimitExceeded::TooManyDocuments {
+                limit: 2,
+                found: 3
+            }))
+        );
+        writer.abort();
 
-        let doc2 = serde_json::json!({
-            "id": 234,
-            "name": "Kevin",
-            "description": "My name is Kevin",
-        });
+        let reader = env.read_txn().unwrap();
+        let result = index.update_status(&reader, 0).unwrap();
+        assert_matches!(result, None);
+    }
 
-        additions.update_document(doc1);
-        additions.update_document(doc2);
+    #[test]
+    fn idempotency_keys() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let database = Database::open_or_create(dir.path()).unwrap();
+        let env = &database.env;
+        let index = database.create_index("test").unwrap();
 
         let mut writer = env.write_txn().unwrap();
-        let update_id = additions.finalize(&mut writer).unwrap();
+        let update_id = index.clear_all(&mut writer).unwrap();
+        index
+            .idempotency_keys
+            .put_update_id(&mut writer, "my-key", update_id)
+            .unwrap();
         writer.commit().unwrap();
 
-        // delete the index
-        let deleted = database.delete_index("test").unwrap();
-        assert!(deleted);
+        let reader = env.read_txn().unwrap();
+        let result = index.idempotency_keys.update_id(&reader, "my-key").unwrap();
+        assert_eq!(result, Some(update_id));
 
-        // block until the transaction is processed
-        let _ = receiver.into_iter().find(|id| *id == update_id);
+        let result = index
+            .idempotency_keys
+            .update_id(&reader, "other-key")
+            .unwrap();
+        assert_eq!(result, None);
+        drop(reader);
 
-        let result = database.open_index("test");
-        assert!(result.is_none());
+        // the keys are removed once expired, when another key is inserted
+        let now = chrono::Utc::now().timestamp() as u64;
+        let later = now + crate::store::IDEMPOTENCY_KEYS_TTL + 1;
+        let mut writer = env.write_txn().unwrap();
+        index
+            .idempotency_keys
+            .put_update_id_at(&mut writer, "later-key", update_id + 1, later)
+            .unwrap();
+        writer.commit().unwrap();
+
+        let reader = env.read_txn().unwrap();
+        let result = index.idempotency_keys.update_id(&reader, "my-key").unwrap();
+        assert_eq!(result, None);
+
+        let result = index
+            .idempotency_keys
+            .update_id(&reader, "later-key")
+            .unwrap();
+        assert_eq!(result, Some(update_id + 1));
     }
 
     #[test]
-    fn check_number_ordering() {
+    fn indexing_history() {
+        use crate::store::INDEXING_HISTORY_LEN;
+
         let dir = tempfile::tempdir().unwrap();
 
         let database = Database::open_or_create(dir.path()).unwrap();
         let env = &database.env;
-
-        let (sender, receiver) = mpsc::sync_channel(100);
-        let update_fn = move |_name: &str, update: ProcessedUpdateResult| {
-            sender.send(update.update_id).unwrap()
-        };
         let index = database.create_index("test").unwrap();
 
-        database.set_update_callback(Box::new(update_fn));
-
         let schema = {
             let data = r#"
                 identifier = "id"
@@ -936,76 +1378,3544 @@ mod tests {
                 [attributes."name"]
                 displayed = true
                 indexed = true
-
-                [attributes."release_date"]
-                displayed = true
-                ranked = true
             "#;
             toml::from_str(data).unwrap()
         };
 
         let mut writer = env.write_txn().unwrap();
-        let _update_id = index.schema_update(&mut writer, schema).unwrap();
+        index.schema_update(&mut writer, schema).unwrap();
         writer.commit().unwrap();
 
         let mut additions = index.documents_addition();
+        additions.update_document(serde_json::json!({ "id": 1, "name": "Marvin" }));
+        additions.update_document(serde_json::json!({ "id": 2, "name": "Kevin" }));
 
-        // DocumentId(7900334843754999545)
-        let doc1 = serde_json::json!({
-            "id": 123,
-            "name": "Kevin the first",
-            "release_date": -10000,
-        });
+        let mut writer = env.write_txn().unwrap();
+        let addition_id = additions.finalize(&mut writer).unwrap();
+        let mut deletion = index.documents_deletion();
+        deletion.delete_document_by_id(compute_document_id("1"));
+        let deletion_id = deletion.finalize(&mut writer).unwrap();
+        writer.commit().unwrap();
+        assert!(index.wait_for_update(deletion_id, Duration::from_secs(10)));
 
-        // DocumentId(8367468610878465872)
-        let doc2 = serde_json::json!({
-            "id": 234,
-            "name": "Kevin the second",
-            "release_date": 10000,
-        });
+        // the schema update is not a documents update, it is not in the history
+        let reader = env.read_txn().unwrap();
+        let history = index.indexing_history(&reader).unwrap();
+        assert_eq!(history.len(), 2);
+
+        assert_eq!(history[0].update_id, addition_id);
+        assert_eq!(history[0].number_of_documents, 2);
+        assert!(history[0].bytes_indexed > 0);
+        assert_eq!(history[1].update_id, deletion_id);
+        assert_eq!(history[1].number_of_documents, 1);
+        assert_eq!(history[1].bytes_indexed, 0);
+        drop(reader);
+
+        // the oldest statistics are removed once the history is full
+        let mut writer = env.write_txn().unwrap();
+        for update_id in 10..10 + INDEXING_HISTORY_LEN as u64 {
+            let mut stats = history[0].clone();
+            stats.update_id = update_id;
+            index
+                .indexing_history
+                .push_stats(&mut writer, update_id, &stats)
+                .unwrap();
+        }
+        writer.commit().unwrap();
 
-        additions.update_document(doc1);
-        additions.update_document(doc2);
+        let reader = env.read_txn().unwrap();
+        let history = index.indexing_history(&reader).unwrap();
+        assert_eq!(history.len(), INDEXING_HISTORY_LEN);
+        assert_eq!(history[0].update_id, 10);
+    }
+
+    #[test]
+    fn wait_for_update() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let database = Database::open_or_create(dir.path()).unwrap();
+        let env = &database.env;
+        let index = database.create_index("test").unwrap();
 
         let mut writer = env.write_txn().unwrap();
-        let update_id = additions.finalize(&mut writer).unwrap();
+        let update_id = index.clear_all(&mut writer).unwrap();
         writer.commit().unwrap();
 
-        // block until the transaction is processed
-        let _ = receiver.into_iter().find(|id| *id == update_id);
+        assert!(index.wait_for_update(update_id, Duration::from_secs(10)));
+        assert!(!index.wait_for_update(update_id + 1, Duration::from_millis(10)));
 
         let reader = env.read_txn().unwrap();
+        let result = index.update_status(&reader, update_id).unwrap();
+        assert_matches!(result, Some(UpdateStatus::Processed { content }) if content.error.is_none());
+    }
 
-        let schema = index.main.schema(&reader).unwrap().unwrap();
-        let ranked_map = index.main.ranked_map(&reader).unwrap().unwrap();
+    #[test]
+    fn settings_patch() {
+        let dir = tempfile::tempdir().unwrap();
 
-        let criteria = CriteriaBuilder::new()
-            .add(
-                criterion::SortByAttr::lower_is_better(&ranked_map, &schema, "release_date")
-                    .unwrap(),
-            )
-            .add(criterion::DocumentId)
-            .build();
+        let database = Database::open_or_create(dir.path()).unwrap();
+        let env = &database.env;
+        let index = database.create_index("test").unwrap();
 
-        let builder = index.query_builder_with_criteria(criteria);
+        let mut stop_words = index.stop_words_addition();
+        stop_words.add_stop_word("the");
+        stop_words.add_stop_word("a");
 
-        let results = builder.query(&reader, "Kevin", 0..20).unwrap();
-        let mut iter = results.into_iter();
+        let mut synonyms = index.synonyms_addition();
+        synonyms.add_synonym("nyc", vec!["new york"]);
 
-        assert_matches!(
-            iter.next(),
-            Some(Document {
-                id: DocumentId(7900334843754999545),
-                ..
+        let mut writer = env.write_txn().unwrap();
+        stop_words.finalize(&mut writer).unwrap();
+        synonyms.finalize(&mut writer).unwrap();
+        writer.commit().unwrap();
+
+        let mut patch = index.settings_patch();
+        patch.add_stop_word("of");
+        patch.delete_stop_word("a");
+        patch.add_synonym("nyc", vec!["big apple"]);
+
+        let mut writer = env.write_txn().unwrap();
+        let update_id = patch.finalize(&mut writer).unwrap();
+        writer.commit().unwrap();
+        assert!(index.wait_for_update(update_id, Duration::from_secs(10)));
+
+        let reader = env.read_txn().unwrap();
+        let result = index.update_status(&reader, update_id).unwrap();
+        assert_matches!(result, Some(UpdateStatus::Processed { content }) if content.error.is_none());
+
+        let stop_words = index.main.stop_words_fst(&reader).unwrap().unwrap();
+        assert_eq!(stop_words.stream().into_strs().unwrap(), vec!["of", "the"]);
+
+        // the alternatives are added to the existing ones
+        let alternatives = index.synonyms.synonyms(&reader, b"nyc").unwrap().unwrap();
+        assert_eq!(
+            alternatives.stream().into_strs().unwrap(),
+            vec!["big apple", "new york"]
+        );
+    }
+
+    #[test]
+    fn settings_patch_merged_when_processed() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let database = Database::open_or_create(dir.path()).unwrap();
+        let env = &database.env;
+        database.set_customs_merge(Box::new(|current, patch| {
+            let mut merged = current.unwrap_or_default().to_vec();
+            merged.extend_from_slice(patch);
+            Ok(merged)
+        }));
+        let index = database.create_index("test").unwrap();
+
+        let mut synonyms = index.synonyms_addition();
+        synonyms.add_synonym("nyc", vec!["new york"]);
+
+        let mut writer = env.write_txn().unwrap();
+        index.main.put_customs(&mut writer, b"a").unwrap();
+        synonyms.finalize(&mut writer).unwrap();
+        writer.commit().unwrap();
+
+        // both patches are enqueued before any of them is processed
+        let mut first = index.settings_patch();
+        first.merge_customs(b"b".to_vec());
+        first.add_weighted_synonym("nyc", vec![("new york", 2)]);
+
+        let mut second = index.settings_patch();
+        second.merge_customs(b"c".to_vec());
+
+        let mut writer = env.write_txn().unwrap();
+        first.finalize(&mut writer).unwrap();
+        let update_id = second.finalize(&mut writer).unwrap();
+        writer.commit().unwrap();
+        assert!(index.wait_for_update(update_id, Duration::from_secs(10)));
+
+        let reader = env.read_txn().unwrap();
+        assert_eq!(index.main.customs(&reader).unwrap(), Some(&b"abc"[..]));
+
+        // the penalty of an existing alternative can be raised
+        let alternatives = index
+            .synonyms
+            .weighted_synonyms(&reader, b"nyc")
+            .unwrap()
+            .unwrap();
+        assert_eq!(alternatives.get("new york"), Some(2));
+        assert_eq!(alternatives.len(), 1);
+    }
+
+    #[test]
+    fn attributes_types() {
+        use crate::serde::{AttributeType, AttributesTypes};
+
+        let dir = tempfile::tempdir().unwrap();
+
+        let database = Database::open_or_create(dir.path()).unwrap();
+        let env = &database.env;
+        let index = database.create_index("test").unwrap();
+
+        let schema = {
+            let data = r#"
+                identifier = "id"
+
+                [attributes."name"]
+                displayed = true
+                indexed = true
+
+                [attributes."released"]
+                displayed = true
+            "#;
+            toml::from_str(data).unwrap()
+        };
+
+        let mut attributes_types = AttributesTypes::new();
+        attributes_types.insert("released".to_string(), AttributeType::Date);
+
+        let mut writer = env.write_txn().unwrap();
+        index.schema_update(&mut writer, schema).unwrap();
+        index
+            .attributes_types_update(&mut writer, attributes_types)
+            .unwrap();
+        writer.commit().unwrap();
+
+        let mut additions = index.documents_addition();
+        additions.update_document(serde_json::json!({
+            "id": 123,
+            "name": "Marvin",
+            "released": "2019-11-04",
+        }));
+        additions.update_document(serde_json::json!({
+            "id": 234,
+            "name": "Kevin",
+            "released": "sometime",
+        }));
+
+        let mut writer = env.write_txn().unwrap();
+        let update_id = additions.finalize(&mut writer).unwrap();
+        writer.commit().unwrap();
+        assert!(index.wait_for_update(update_id, Duration::from_secs(10)));
+
+        let reader = env.read_txn().unwrap();
+        let result = index.update_status(&reader, update_id).unwrap();
+        assert_matches!(result, Some(UpdateStatus::Processed { content }) if content.error.is_none());
+
+        let schema = index.main.schema(&reader).unwrap().unwrap();
+        let released = schema.attribute("released").unwrap();
+        let ranked_map = index.main.ranked_map(&reader).unwrap().unwrap();
+
+        let marvin = compute_document_id("123");
+        let kevin = compute_document_id("234");
+        let date = crate::Number::Signed(1572825600);
+        assert_eq!(ranked_map.get(marvin, released), Some(date));
+        assert_eq!(ranked_map.get(kevin, released), None);
+
+        // the original text is kept for display
+        let value: Option<String> = index.document_attribute(&reader, marvin, released).unwrap();
+        assert_eq!(value.as_ref().map(String::as_str), Some("2019-11-04"));
+        reader.abort();
+
+        // removing the type removes the typed values
+        let mut writer = env.write_txn().unwrap();
+        let update_id = index
+            .attributes_types_update(&mut writer, AttributesTypes::new())
+            .unwrap();
+        writer.commit().unwrap();
+        assert!(index.wait_for_update(update_id, Duration::from_secs(10)));
+
+        let reader = env.read_txn().unwrap();
+        let ranked_map = index.main.ranked_map(&reader).unwrap().unwrap();
+        assert_eq!(ranked_map.get(marvin, released), None);
+    }
+
+    #[test]
+    fn words_limits() {
+        use crate::raw_indexer::WordsLimits;
+
+        let dir = tempfile::tempdir().unwrap();
+
+        let database = Database::open_or_create(dir.path()).unwrap();
+        let env = &database.env;
+        let index = database.create_index("test").unwrap();
+
+        let schema = {
+            let data = r#"
+                identifier = "id"
+
+                [attributes."title"]
+                displayed = true
+                indexed = true
+
+                [attributes."body"]
+                displayed = true
+                indexed = true
+            "#;
+            toml::from_str(data).unwrap()
+        };
+
+        let mut writer = env.write_txn().unwrap();
+        index.schema_update(&mut writer, schema).unwrap();
+        writer.commit().unwrap();
+
+        let mut additions = index.documents_addition();
+        additions.update_document(serde_json::json!({
+            "id": 123,
+            "title": "The Hitchhiker's Guide",
+            "body": "Marvin the paranoid android is depressed",
+        }));
+
+        let mut writer = env.write_txn().unwrap();
+        let update_id = additions.finalize(&mut writer).unwrap();
+        writer.commit().unwrap();
+        assert!(index.wait_for_update(update_id, Duration::from_secs(10)));
+
+        let reader = env.read_txn().unwrap();
+        assert_eq!(index.word_postings(&reader, "android").unwrap().count(), 1);
+        reader.abort();
+
+        let mut words_limits = WordsLimits::new();
+        words_limits.insert("body".to_string(), 2);
+
+        let mut writer = env.write_txn().unwrap();
+        let update_id = index
+            .words_limits_update(&mut writer, words_limits)
+            .unwrap();
+        writer.commit().unwrap();
+        assert!(index.wait_for_update(update_id, Duration::from_secs(10)));
+
+        let reader = env.read_txn().unwrap();
+        let result = index.update_status(&reader, update_id).unwrap();
+        assert_matches!(result, Some(UpdateStatus::Processed { content }) if content.error.is_none());
+
+        // the words past the limit are not searchable anymore but are still stored
+        assert_eq!(index.word_postings(&reader, "marvin").unwrap().count(), 1);
+        assert_eq!(index.word_postings(&reader, "android").unwrap().count(), 0);
+        assert_eq!(index.word_postings(&reader, "guide").unwrap().count(), 1);
+
+        let schema = index.main.schema(&reader).unwrap().unwrap();
+        let body = schema.attribute("body").unwrap();
+        let document_id = compute_document_id("123");
+        let value: Option<String> = index
+            .document_attribute(&reader, document_id, body)
+            .unwrap();
+        assert_eq!(
+            value.as_ref().map(String::as_str),
+            Some("Marvin the paranoid android is depressed")
+        );
+    }
+
+    #[test]
+    fn token_filters() {
+        use crate::token_filters::{register_token_filter, TokenFilter};
+
+        register_token_filter("database-strip-plural", |word| {
+            Some(word.trim_end_matches('s').to_owned())
+        });
+
+        let dir = tempfile::tempdir().unwrap();
+
+        let database = Database::open_or_create(dir.path()).unwrap();
+        let env = &database.env;
+        let index = database.create_index("test").unwrap();
+
+        let schema = {
+            let data = r#"
+                identifier = "id"
+
+                [attributes."title"]
+                displayed = true
+                indexed = true
+            "#;
+            toml::from_str(data).unwrap()
+        };
+
+        let mut writer = env.write_txn().unwrap();
+        index.schema_update(&mut writer, schema).unwrap();
+        writer.commit().unwrap();
+
+        let mut additions = index.documents_addition();
+        additions.update_document(serde_json::json!({
+            "id": 123,
+            "title": "The Dogs bark",
+        }));
+
+        let mut writer = env.write_txn().unwrap();
+        let update_id = additions.finalize(&mut writer).unwrap();
+        writer.commit().unwrap();
+        assert!(index.wait_for_update(update_id, Duration::from_secs(10)));
+
+        let token_filters = vec![
+            TokenFilter::Lowercase,
+            TokenFilter::Length { min: 4, max: None },
+            TokenFilter::Custom("database-strip-plural".to_string()),
+        ];
+
+        let mut writer = env.write_txn().unwrap();
+        let update_id = index
+            .token_filters_update(&mut writer, token_filters)
+            .unwrap();
+        writer.commit().unwrap();
+        assert!(index.wait_for_update(update_id, Duration::from_secs(10)));
+
+        let reader = env.read_txn().unwrap();
+        let result = index.update_status(&reader, update_id).unwrap();
+        assert_matches!(result, Some(UpdateStatus::Processed { content }) if content.error.is_none());
+
+        // the documents are reindexed with the new filters
+        assert_eq!(index.word_postings(&reader, "dog").unwrap().count(), 1);
+        assert_eq!(index.word_postings(&reader, "dogs").unwrap().count(), 0);
+        assert_eq!(index.word_postings(&reader, "the").unwrap().count(), 0);
+
+        // the query words are analyzed the same way, the dropped ones are ignored
+        let results = index
+            .query_builder()
+            .query(&reader, "THE DOGS ", 0..20)
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        reader.abort();
+
+        // the filters are not replaced by ones that are not registered
+        let token_filters = vec![TokenFilter::Custom("unknown".to_string())];
+
+        let mut writer = env.write_txn().unwrap();
+        let update_id = index
+            .token_filters_update(&mut writer, token_filters)
+            .unwrap();
+        writer.commit().unwrap();
+        assert!(index.wait_for_update(update_id, Duration::from_secs(10)));
+
+        let reader = env.read_txn().unwrap();
+        let result = index.update_status(&reader, update_id).unwrap();
+        assert_matches!(result, Some(UpdateStatus::Processed { content }) if content.error.is_some());
+        assert_eq!(index.word_postings(&reader, "dog").unwrap().count(), 1);
+    }
+
+    #[test]
+    fn token_filters_applied_once_to_ngrams() {
+        use crate::token_filters::TokenFilter;
+
+        let dir = tempfile::tempdir().unwrap();
+
+        let database = Database::open_or_create(dir.path()).unwrap();
+        let env = &database.env;
+        let index = database.create_index("test").unwrap();
+
+        let schema = {
+            let data = r#"
+                identifier = "id"
+
+                [attributes."title"]
+                displayed = true
+                indexed = true
+            "#;
+            toml::from_str(data).unwrap()
+        };
+
+        let mut writer = env.write_txn().unwrap();
+        index.schema_update(&mut writer, schema).unwrap();
+        writer.commit().unwrap();
+
+        let token_filters = vec![
+            TokenFilter::Lowercase,
+            TokenFilter::Length {
+                min: 0,
+                max: Some(5),
+            },
+        ];
+
+        let mut writer = env.write_txn().unwrap();
+        let update_id = index
+            .token_filters_update(&mut writer, token_filters)
+            .unwrap();
+        writer.commit().unwrap();
+        assert!(index.wait_for_update(update_id, Duration::from_secs(10)));
+
+        let mut synonyms = index.synonyms_addition();
+        synonyms.add_synonym("big house", vec!["manor"]);
+
+        let mut additions = index.documents_addition();
+        additions.update_document(serde_json::json!({
+            "id": 123,
+            "title": "An old manor",
+        }));
+
+        let mut writer = env.write_txn().unwrap();
+        synonyms.finalize(&mut writer).unwrap();
+        let update_id = additions.finalize(&mut writer).unwrap();
+        writer.commit().unwrap();
+        assert!(index.wait_for_update(update_id, Duration::from_secs(10)));
+
+        let reader = env.read_txn().unwrap();
+
+        // the ngram "sun glass" is longer than the filter allows, it must
+        // neither be dropped nor turned into an empty word matching every synonym
+        let results = index
+            .query_builder()
+            .query(&reader, "sun glass", 0..20)
+            .unwrap();
+        assert!(results.is_empty());
+
+        let results = index
+            .query_builder()
+            .query(&reader, "big house", 0..20)
+            .unwrap();
+        assert_eq!(results.len(), 1);
+    }
+
+    #[test]
+    fn resolve_documents_ids() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let database = Database::open_or_create(dir.path()).unwrap();
+        let env = &database.env;
+        let index = database.create_index("test").unwrap();
+
+        let schema = {
+            let data = r#"
+                identifier = "id"
+
+                [attributes."id"]
+                displayed = true
+
+                [attributes."title"]
+                displayed = true
+                indexed = true
+            "#;
+            toml::from_str(data).unwrap()
+        };
+
+        let mut writer = env.write_txn().unwrap();
+        index.schema_update(&mut writer, schema).unwrap();
+        writer.commit().unwrap();
+
+        let mut additions = index.documents_addition();
+        additions.update_document(serde_json::json!({ "id": 1, "title": "Alien" }));
+        additions.update_document(serde_json::json!({ "id": "abc", "title": "Aliens" }));
+        additions.update_document(serde_json::json!({
+            "id": "67E55044-10B1-426F-9247-BB680E5FE0C8",
+            "title": "Alien 3",
+        }));
+
+        let mut writer = env.write_txn().unwrap();
+        let update_id = additions.finalize(&mut writer).unwrap();
+        writer.commit().unwrap();
+        assert!(index.wait_for_update(update_id, Duration::from_secs(10)));
+
+        let uuid = "67e55044-10b1-426f-9247-bb680e5fe0c8";
+        let reader = env.read_txn().unwrap();
+        let internal_ids = index
+            .resolve_external_ids(&reader, &["1", "abc", "missing", uuid])
+            .unwrap();
+        assert_eq!(
+            internal_ids,
+            vec![
+                Some(compute_document_id("1")),
+                Some(compute_document_id("abc")),
+                None,
+                Some(compute_document_id(uuid)),
+            ]
+        );
+
+        let internal_ids = [
+            compute_document_id(uuid),
+            compute_document_id("missing"),
+            compute_document_id("1"),
+        ];
+        let external_ids = index.resolve_internal_ids(&reader, &internal_ids).unwrap();
+        assert_eq!(
+            external_ids,
+            vec![Some(uuid.to_string()), None, Some("1".to_string())]
+        );
+        reader.abort();
+
+        // the documents indexed before the mapping existed are resolved from their fields
+        let mut writer = env.write_txn().unwrap();
+        index.documents_ids.clear(&mut writer).unwrap();
+        writer.commit().unwrap();
+
+        let reader = env.read_txn().unwrap();
+        let internal_ids = index.resolve_external_ids(&reader, &["abc"]).unwrap();
+        assert_eq!(internal_ids, vec![Some(compute_document_id("abc"))]);
+        let external_ids = index
+            .resolve_internal_ids(&reader, &[compute_document_id("abc")])
+            .unwrap();
+        assert_eq!(external_ids, vec![Some("abc".to_string())]);
+        reader.abort();
+
+        let mut writer = env.write_txn().unwrap();
+        let mut deletion = index.documents_deletion();
+        deletion.delete_document_by_id(compute_document_id("abc"));
+        let update_id = deletion.finalize(&mut writer).unwrap();
+        writer.commit().unwrap();
+        assert!(index.wait_for_update(update_id, Duration::from_secs(10)));
+
+        let reader = env.read_txn().unwrap();
+        let internal_ids = index.resolve_external_ids(&reader, &["abc"]).unwrap();
+        assert_eq!(internal_ids, vec![None]);
+    }
+
+    #[test]
+    fn searchable_and_displayed_attributes() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let database = Database::open_or_create(dir.path()).unwrap();
+        let env = &database.env;
+        let index = database.create_index("test").unwrap();
+
+        let schema = {
+            let data = r#"
+                identifier = "id"
+
+                [attributes."id"]
+                displayed = true
+
+                [attributes."title"]
+                displayed = true
+                indexed = true
+
+                [attributes."overview"]
+                displayed = true
+                indexed = true
+            "#;
+            toml::from_str(data).unwrap()
+        };
+
+        let mut writer = env.write_txn().unwrap();
+        index.schema_update(&mut writer, schema).unwrap();
+        writer.commit().unwrap();
+
+        let documents = vec![
+            serde_json::json!({ "id": 1, "title": "alien", "overview": "space" }),
+            serde_json::json!({ "id": 2, "title": "space", "overview": "alien" }),
+        ];
+
+        let mut writer = env.write_txn().unwrap();
+        let update_id = index
+            .documents_addition_from_values(&mut writer, documents)
+            .unwrap();
+        writer.commit().unwrap();
+        assert!(index.wait_for_update(update_id, Duration::from_secs(10)));
+
+        let first = compute_document_id("1");
+        let second = compute_document_id("2");
+
+        let reader = env.read_txn().unwrap();
+        let results = index
+            .query_builder()
+            .query(&reader, "alien", 0..20)
+            .unwrap();
+        let ids: Vec<_> = results.iter().map(|d| d.id).collect();
+        assert_eq!(ids, vec![first, second]);
+        reader.abort();
+
+        // the order of the searchable attributes defines their priority
+        let attributes = vec!["overview".to_string(), "title".to_string()];
+
+        let mut writer = env.write_txn().unwrap();
+        let update_id = index
+            .searchable_attributes_update(&mut writer, Some(attributes))
+            .unwrap();
+        writer.commit().unwrap();
+        assert!(index.wait_for_update(update_id, Duration::from_secs(10)));
+
+        let reader = env.read_txn().unwrap();
+        let result = index.update_status(&reader, update_id).unwrap();
+        assert_matches!(result, Some(UpdateStatus::Processed { content }) if content.error.is_none());
+
+        let results = index
+            .query_builder()
+            .query(&reader, "alien", 0..20)
+            .unwrap();
+        let ids: Vec<_> = results.iter().map(|d| d.id).collect();
+        assert_eq!(ids, vec![second, first]);
+        reader.abort();
+
+        // the attributes that are not searchable are not indexed anymore
+        let mut writer = env.write_txn().unwrap();
+        let update_id = index
+            .searchable_attributes_update(&mut writer, Some(vec!["title".to_string()]))
+            .unwrap();
+        writer.commit().unwrap();
+        assert!(index.wait_for_update(update_id, Duration::from_secs(10)));
+
+        let reader = env.read_txn().unwrap();
+        let results = index
+            .query_builder()
+            .query(&reader, "space", 0..20)
+            .unwrap();
+        let ids: Vec<_> = results.iter().map(|d| d.id).collect();
+        assert_eq!(ids, vec![second]);
+        assert_eq!(index.word_postings(&reader, "space").unwrap().count(), 1);
+
+        let document: Option<serde_json::Value> = index.document(&reader, None, first).unwrap();
+        let expected = serde_json::json!({ "id": 1, "title": "alien", "overview": "space" });
+        assert_eq!(document, Some(expected));
+        reader.abort();
+
+        // the attributes that are not displayed are never returned
+        let mut writer = env.write_txn().unwrap();
+        let displayed = vec!["title".to_string()];
+        index
+            .main
+            .put_displayed_attributes(&mut writer, &displayed)
+            .unwrap();
+        writer.commit().unwrap();
+
+        let reader = env.read_txn().unwrap();
+        let document: Option<serde_json::Value> = index.document(&reader, None, first).unwrap();
+        assert_eq!(document, Some(serde_json::json!({ "title": "alien" })));
+
+        let attributes: HashSet<_> = vec!["id", "title"].into_iter().collect();
+        let document: Option<serde_json::Value> =
+            index.document(&reader, Some(&attributes), first).unwrap();
+        assert_eq!(document, Some(serde_json::json!({ "title": "alien" })));
+    }
+
+    #[test]
+    fn distinct_attribute() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let database = Database::open_or_create(dir.path()).unwrap();
+        let env = &database.env;
+        let index = database.create_index("test").unwrap();
+
+        let schema = {
+            let data = r#"
+                identifier = "id"
+
+                [attributes."id"]
+                displayed = true
+
+                [attributes."name"]
+                displayed = true
+                indexed = true
+
+                [attributes."sku_group"]
+                displayed = true
+            "#;
+            toml::from_str(data).unwrap()
+        };
+
+        let mut writer = env.write_txn().unwrap();
+        index.schema_update(&mut writer, schema).unwrap();
+        writer.commit().unwrap();
+
+        let documents = vec![
+            serde_json::json!({ "id": 1, "name": "red shirt", "sku_group": "shirt" }),
+            serde_json::json!({ "id": 2, "name": "blue shirt", "sku_group": "shirt" }),
+            serde_json::json!({ "id": 3, "name": "shirt pin", "sku_group": "pin" }),
+            serde_json::json!({ "id": 4, "name": "shirt" }),
+            serde_json::json!({ "id": 5, "name": "plain shirt" }),
+        ];
+
+        let mut writer = env.write_txn().unwrap();
+        let update_id = index
+            .documents_addition_from_values(&mut writer, documents)
+            .unwrap();
+        writer.commit().unwrap();
+        assert!(index.wait_for_update(update_id, Duration::from_secs(10)));
+
+        let reader = env.read_txn().unwrap();
+        let schema = index.main.schema(&reader).unwrap().unwrap();
+        let sku_group = schema.attribute("sku_group").unwrap();
+
+        let results = index
+            .query_builder()
+            .query(&reader, "shirt", 0..20)
+            .unwrap();
+        assert_eq!(results.len(), 5);
+
+        // a single variant of the shirts is kept, the documents without a group are all kept
+        let mut builder = index.query_builder();
+        builder.with_distinct_attribute(sku_group, 1);
+        let results = builder.query(&reader, "shirt", 0..20).unwrap();
+        let ids: HashSet<_> = results.iter().map(|d| d.id).collect();
+        assert_eq!(ids.len(), 4);
+
+        let red = ids.contains(&compute_document_id("1"));
+        let blue = ids.contains(&compute_document_id("2"));
+        assert!(red ^ blue);
+
+        let mut builder = index.query_builder();
+        builder.with_distinct_attribute(sku_group, 2);
+        let results = builder.query(&reader, "shirt", 0..20).unwrap();
+        assert_eq!(results.len(), 5);
+    }
+
+    #[test]
+    fn operations_log() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let database = Database::open_or_create(dir.path()).unwrap();
+        let env = &database.env;
+
+        // the operations are not logged until the log is enabled
+        let _ = database.create_index("ignored").unwrap();
+        assert!(!database.is_operations_log_enabled().unwrap());
+        database.set_operations_log(true).unwrap();
+
+        let index = database.create_index("test").unwrap();
+
+        let schema = {
+            let data = r#"
+                identifier = "id"
+
+                [attributes."name"]
+                displayed = true
+                indexed = true
+            "#;
+            toml::from_str(data).unwrap()
+        };
+
+        let mut writer = env.write_txn().unwrap();
+        index.schema_update(&mut writer, schema).unwrap();
+        writer.commit().unwrap();
+
+        let documents = vec![serde_json::json!({ "id": 1, "name": "Marvin" })];
+        let mut writer = env.write_txn().unwrap();
+        let addition_id = index
+            .documents_addition_from_values(&mut writer, documents)
+            .unwrap();
+        writer.commit().unwrap();
+
+        // a failed update is not logged
+        let documents = vec![serde_json::json!({ "name": "no identifier" })];
+        let mut writer = env.write_txn().unwrap();
+        let update_id = index
+            .documents_addition_from_values(&mut writer, documents)
+            .unwrap();
+        writer.commit().unwrap();
+
+        let mut deletion = index.documents_deletion();
+        deletion.delete_document_by_id(compute_document_id("1"));
+        let mut writer = env.write_txn().unwrap();
+        let deletion_id = deletion.finalize(&mut writer).unwrap();
+        writer.commit().unwrap();
+        assert!(index.wait_for_update(deletion_id, Duration::from_secs(10)));
+
+        let reader = env.read_txn().unwrap();
+        let status = index.update_status(&reader, update_id).unwrap();
+        assert_matches!(status, Some(UpdateStatus::Failed { .. }));
+        reader.abort();
+
+        let operations = database.operations_since(0, 100).unwrap();
+        let sequences: Vec<_> = operations.iter().map(|o| o.sequence).collect();
+        assert_eq!(sequences, vec![1, 2, 3, 4]);
+        assert!(operations.iter().all(|o| o.index_uid == "test"));
+
+        assert_matches!(operations[0].operation, Operation::IndexCreated);
+        assert_eq!(operations[0].update_id, None);
+        assert_matches!(
+            operations[1].operation,
+            Operation::SettingsChanged {
+                update_type: UpdateType::Schema
+            }
+        );
+        match &operations[2].operation {
+            Operation::DocumentsAdded { documents } => {
+                assert_eq!(documents.len(), 1);
+                assert_eq!(documents[0]["name"], "Marvin");
+            }
+            operation => panic!("unexpected operation {:?}", operation),
+        }
+        assert_eq!(operations[2].update_id, Some(addition_id));
+        match &operations[3].operation {
+            Operation::DocumentsDeleted {
+                identifiers,
+                documents_ids,
+            } => {
+                assert_eq!(identifiers, &["1".to_string()]);
+                assert_eq!(documents_ids, &[compute_document_id("1").0]);
+            }
+            operation => panic!("unexpected operation {:?}", operation),
+        }
+
+        // a consumer resumes after the last sequence it has seen
+        let operations = database.operations_since(2, 1).unwrap();
+        assert_eq!(operations.len(), 1);
+        assert_eq!(operations[0].sequence, 3);
+
+        // the sequence numbers are not reused after a truncation
+        assert_eq!(database.truncate_operations(4).unwrap(), 4);
+        assert!(database.operations_since(0, 100).unwrap().is_empty());
+
+        assert!(database.delete_index("test").unwrap());
+        let operations = database.operations_since(0, 100).unwrap();
+        assert_eq!(operations.len(), 1);
+        assert_eq!(operations[0].sequence, 5);
+        assert_matches!(operations[0].operation, Operation::IndexDeleted);
+    }
+
+    #[test]
+    fn migrate_to() {
+        use crate::update::FieldMapping;
+
+        let dir = tempfile::tempdir().unwrap();
+
+        let database = Database::open_or_create(dir.path()).unwrap();
+        let env = &database.env;
+        let index = database.create_index("test").unwrap();
+
+        let schema = {
+            let data = r#"
+                identifier = "id"
+
+                [attributes."id"]
+                displayed = true
+
+                [attributes."title"]
+                displayed = true
+                indexed = true
+
+                [attributes."author"]
+                displayed = true
+                indexed = true
+            "#;
+            toml::from_str(data).unwrap()
+        };
+
+        let mut writer = env.write_txn().unwrap();
+        index.schema_update(&mut writer, schema).unwrap();
+        writer.commit().unwrap();
+
+        let documents = vec![
+            serde_json::json!({ "id": 1, "title": "Foundation", "author": "Asimov, Isaac" }),
+            serde_json::json!({ "id": 2, "title": "Dune", "author": "Herbert, Frank" }),
+        ];
+
+        let mut writer = env.write_txn().unwrap();
+        let update_id = index
+            .documents_addition_from_values(&mut writer, documents)
+            .unwrap();
+        writer.commit().unwrap();
+        assert!(index.wait_for_update(update_id, Duration::from_secs(10)));
+
+        // the settings naming the renamed attributes follow them
+        database.set_customs_rename(Box::new(|customs, renames| {
+            let customs = String::from_utf8(customs.to_vec()).map_err(|e| e.to_string())?;
+            let renamed = renames.get(&customs).cloned().unwrap_or(customs);
+            Ok(renamed.into_bytes())
+        }));
+
+        let mut writer = env.write_txn().unwrap();
+        let rules = vec![RankingRule::Typo, RankingRule::Dsc("title".to_string())];
+        index.main.put_criteria(&mut writer, &rules).unwrap();
+        let mut words_limits = crate::raw_indexer::WordsLimits::new();
+        words_limits.insert("title".to_string(), 10);
+        index
+            .main
+            .put_words_limits(&mut writer, &words_limits)
+            .unwrap();
+        let mut normalization = crate::FacetsNormalization::new();
+        normalization.insert("title".to_string(), crate::FacetNormalization::Lowercase);
+        index
+            .main
+            .put_facets_normalization(&mut writer, &normalization)
+            .unwrap();
+        index.main.put_customs(&mut writer, b"title").unwrap();
+        writer.commit().unwrap();
+
+        // the title is renamed and the author is split, the attributes are reordered
+        let new_schema = {
+            let data = r#"
+                identifier = "id"
+
+                [attributes."id"]
+                displayed = true
+
+                [attributes."last_name"]
+                displayed = true
+                indexed = true
+
+                [attributes."name"]
+                displayed = true
+                indexed = true
+
+                [attributes."first_name"]
+                displayed = true
+
+                [attributes."language"]
+                displayed = true
+            "#;
+            toml::from_str(data).unwrap()
+        };
+
+        let mapping: FieldMapping = serde_json::from_value(serde_json::json!({
+            "renames": { "title": "name" },
+            "splits": { "author": { "separator": ",", "into": ["last_name", "first_name"] } },
+            "defaults": { "language": "en" },
+        }))
+        .unwrap();
+
+        // a mapping to an attribute that is not in the new schema is refused
+        let mut unknown = mapping.clone();
+        unknown
+            .renames
+            .insert("title".to_string(), "label".to_string());
+
+        let mut writer = env.write_txn().unwrap();
+        let failed_id = index
+            .migrate_to(&mut writer, new_schema.clone(), unknown)
+            .unwrap();
+        let update_id = index.migrate_to(&mut writer, new_schema, mapping).unwrap();
+        writer.commit().unwrap();
+        assert!(index.wait_for_update(update_id, Duration::from_secs(10)));
+
+        let reader = env.read_txn().unwrap();
+        let status = index.update_status(&reader, failed_id).unwrap();
+        assert_matches!(status, Some(UpdateStatus::Failed { .. }));
+        let status = index.update_status(&reader, update_id).unwrap();
+        assert_matches!(status, Some(UpdateStatus::Processed { content }) if content.error.is_none());
+
+        let snapshot = index.schema_snapshot();
+        let schema = snapshot.schema().unwrap();
+        assert!(schema.attribute("title").is_none());
+        assert!(schema.attribute("name").is_some());
+
+        let document: Option<serde_json::Value> = index
+            .document(&reader, None, compute_document_id("1"))
+            .unwrap();
+        let expected = serde_json::json!({
+            "id": 1,
+            "last_name": "Asimov",
+            "name": "Foundation",
+            "first_name": "Isaac",
+            "language": "en",
+        });
+        assert_eq!(document, Some(expected));
+
+        let results = index
+            .query_builder()
+            .query(&reader, "herbert", 0..20)
+            .unwrap();
+        let ids: Vec<_> = results.iter().map(|d| d.id).collect();
+        assert_eq!(ids, vec![compute_document_id("2")]);
+
+        // the first name is not indexed anymore
+        let results = index
+            .query_builder()
+            .query(&reader, "isaac", 0..20)
+            .unwrap();
+        assert!(results.is_empty());
+
+        assert_eq!(index.main.number_of_documents(&reader).unwrap(), 2);
+
+        let rules = index.main.criteria(&reader).unwrap().unwrap();
+        assert_eq!(
+            rules,
+            vec![RankingRule::Typo, RankingRule::Dsc("name".to_string())]
+        );
+        let words_limits = index.main.words_limits(&reader).unwrap().unwrap();
+        assert_eq!(words_limits.get("name"), Some(&10));
+        assert!(words_limits.get("title").is_none());
+        let normalization = index.main.facets_normalization(&reader).unwrap().unwrap();
+        assert_eq!(
+            normalization.get("name"),
+            Some(&crate::FacetNormalization::Lowercase)
+        );
+        assert_eq!(index.main.customs(&reader).unwrap(), Some(&b"name"[..]));
+    }
+
+    #[test]
+    fn misbehaving_update_callbacks() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let database = Database::open_or_create(dir.path()).unwrap();
+        let env = &database.env;
+        let index = database.create_index("test").unwrap();
+
+        let add_document = |id: u64| {
+            let documents = vec![serde_json::json!({ "id": id, "name": "Marvin" })];
+            let mut writer = env.write_txn().unwrap();
+            let update_id = index
+                .documents_addition_from_values(&mut writer, documents)
+                .unwrap();
+            writer.commit().unwrap();
+            update_id
+        };
+
+        // a panicking callback does not stop the update loop
+        let update_fn = |_: &str, _: ProcessedUpdateResult| panic!("callback failure");
+        database.set_update_callback(Box::new(update_fn));
+
+        let schema = {
+            let data = r#"
+                identifier = "id"
+
+                [attributes."name"]
+                displayed = true
+                indexed = true
+            "#;
+            toml::from_str(data).unwrap()
+        };
+
+        let mut writer = env.write_txn().unwrap();
+        let update_id = index.schema_update(&mut writer, schema).unwrap();
+        writer.commit().unwrap();
+        assert!(index.wait_for_update(update_id, Duration::from_secs(10)));
+
+        let update_id = add_document(1);
+        assert!(index.wait_for_update(update_id, Duration::from_secs(10)));
+
+        // neither does a blocked one
+        let (release, blocked) = mpsc::sync_channel::<()>(0);
+        let blocked = std::sync::Mutex::new(blocked);
+        let update_fn = move |_: &str, _: ProcessedUpdateResult| {
+            let _ = blocked.lock().unwrap().recv();
+        };
+        database.set_update_callback(Box::new(update_fn));
+
+        let update_id = add_document(2);
+        assert!(index.wait_for_update(update_id, Duration::from_secs(10)));
+        let update_id = add_document(3);
+        assert!(index.wait_for_update(update_id, Duration::from_secs(10)));
+
+        drop(release);
+        database.unset_update_callback();
+        assert_eq!(database.coalesced_update_callbacks(), 0);
+
+        // the callback of the index is called until it is cleared
+        let (sender, receiver) = mpsc::sync_channel(100);
+        let update_fn = move |name: &str, update: ProcessedUpdateResult| {
+            sender.send((name.to_owned(), update.update_id)).unwrap()
+        };
+        index.set_update_callback(Box::new(update_fn));
+
+        let update_id = add_document(4);
+        let notified = receiver.iter().find(|(_, id)| *id == update_id);
+        assert_eq!(notified, Some(("test".to_owned(), update_id)));
+
+        index.clear_update_callback();
+
+        let update_id = add_document(5);
+        assert!(index.wait_for_update(update_id, Duration::from_secs(10)));
+        assert!(receiver.iter().all(|(_, id)| id != update_id));
+    }
+
+    #[test]
+    fn pre_tokenized_attributes() {
+        use crate::serde::{AttributeType, AttributesTypes};
+
+        let dir = tempfile::tempdir().unwrap();
+
+        let database = Database::open_or_create(dir.path()).unwrap();
+        let env = &database.env;
+        let index = database.create_index("test").unwrap();
+
+        let schema = {
+            let data = r#"
+                identifier = "id"
+
+                [attributes."name"]
+                displayed = true
+                indexed = true
+
+                [attributes."citation"]
+                displayed = true
+                indexed = true
+            "#;
+            toml::from_str(data).unwrap()
+        };
+
+        let mut attributes_types = AttributesTypes::new();
+        attributes_types.insert("citation".to_string(), AttributeType::Tokens);
+
+        let mut writer = env.write_txn().unwrap();
+        index.schema_update(&mut writer, schema).unwrap();
+        index
+            .attributes_types_update(&mut writer, attributes_types)
+            .unwrap();
+        writer.commit().unwrap();
+
+        let mut additions = index.documents_addition();
+        additions.update_document(serde_json::json!({
+            "id": 123,
+            "name": "Marvin v. Kevin",
+            "citation": [
+                { "word": "42-USC-1983", "position": 0, "start": 0, "end": 11 },
+                { "word": "appeal", "position": 1, "start": 12, "end": 18 },
+            ],
+        }));
+
+        let mut writer = env.write_txn().unwrap();
+        let update_id = additions.finalize(&mut writer).unwrap();
+        writer.commit().unwrap();
+        assert!(index.wait_for_update(update_id, Duration::from_secs(10)));
+
+        let reader = env.read_txn().unwrap();
+        let result = index.update_status(&reader, update_id).unwrap();
+        assert_matches!(result, Some(UpdateStatus::Processed { content }) if content.error.is_none());
+
+        let schema = index.main.schema(&reader).unwrap().unwrap();
+        let citation = schema.attribute("citation").unwrap();
+
+        // the words are not split by the tokenizer
+        let postings: Vec<_> = index
+            .word_postings(&reader, "42-usc-1983")
+            .unwrap()
+            .collect();
+        assert_eq!(
+            postings,
+            vec![Posting {
+                document_id: compute_document_id("123"),
+                attribute: citation,
+                word_indexes: vec![0],
+            }]
+        );
+        assert_eq!(index.word_postings(&reader, "usc").unwrap().count(), 0);
+
+        // the other attributes are tokenized as usual
+        assert_eq!(index.word_postings(&reader, "kevin").unwrap().count(), 1);
+    }
+
+    #[test]
+    fn clone_index_settings() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let database = Database::open_or_create(dir.path()).unwrap();
+        let env = &database.env;
+        let index = database.create_index("golden").unwrap();
+
+        let schema = {
+            let data = r#"
+                identifier = "id"
+
+                [attributes."name"]
+                displayed = true
+                indexed = true
+            "#;
+            toml::from_str(data).unwrap()
+        };
+
+        let stop_words = fst::Set::from_iter(vec!["the"]).unwrap();
+
+        let mut writer = env.write_txn().unwrap();
+        index.main.put_schema(&mut writer, &schema).unwrap();
+        index
+            .main
+            .put_stop_words_fst(&mut writer, &stop_words)
+            .unwrap();
+        index.main.put_customs(&mut writer, b"customs").unwrap();
+        index
+            .main
+            .put_number_of_documents(&mut writer, |_| 12)
+            .unwrap();
+        writer.commit().unwrap();
+
+        let result = database.clone_index_settings("unknown", "tenant");
+        assert_matches!(result.err(), Some(Error::IndexNotFound));
+        assert!(database.open_index("tenant").is_none());
+
+        let tenant = database.clone_index_settings("golden", "tenant").unwrap();
+
+        let reader = env.read_txn().unwrap();
+        assert_eq!(tenant.main.schema(&reader).unwrap(), Some(schema));
+        assert_eq!(tenant.main.customs(&reader).unwrap(), Some(&b"customs"[..]));
+        assert_eq!(tenant.main.number_of_documents(&reader).unwrap(), 0);
+
+        let stop_words = tenant.main.stop_words_fst(&reader).unwrap().unwrap();
+        assert_eq!(stop_words.stream().into_strs().unwrap(), vec!["the"]);
+
+        let result = database.clone_index_settings("golden", "tenant");
+        assert_matches!(result.err(), Some(Error::IndexAlreadyExists));
+    }
+
+    #[test]
+    fn dumped_index_settings_and_documents() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let database = Database::open_or_create(dir.path()).unwrap();
+        let env = &database.env;
+        let index = database.create_index("source").unwrap();
+
+        // the identifier is not displayed
+        let schema: meilidb_schema::Schema = {
+            let data = r#"
+                identifier = "id"
+
+                [attributes."id"]
+
+                [attributes."name"]
+                displayed = true
+                indexed = true
+            "#;
+            toml::from_str(data).unwrap()
+        };
+
+        let mut writer = env.write_txn().unwrap();
+        let update_id = index.schema_update(&mut writer, schema.clone()).unwrap();
+        writer.commit().unwrap();
+        assert!(index.wait_for_update(update_id, Duration::from_secs(10)));
+
+        let mut alternatives = BTreeMap::new();
+        alternatives.insert("new york".to_string(), 0);
+        alternatives.insert("big apple".to_string(), 2);
+        let mut synonyms = crate::WeightedSynonyms::new();
+        synonyms.insert("nyc".to_string(), alternatives);
+
+        let settings = crate::IndexSettings {
+            customs: Some(b"customs".to_vec()),
+            stop_words: Some(vec!["the".to_string()]),
+            synonyms,
+            html_escaping: Some(true),
+            max_total_hits: Some(100),
+            displayed_attributes: Some(vec!["name".to_string()]),
+            ..crate::IndexSettings::default()
+        };
+
+        let mut writer = env.write_txn().unwrap();
+        settings.write(&mut writer, &index).unwrap();
+        writer.commit().unwrap();
+
+        let mut additions = index.documents_addition();
+        additions.update_document(serde_json::json!({ "id": 1, "name": "Marvin" }));
+        let mut writer = env.write_txn().unwrap();
+        let update_id = additions.finalize(&mut writer).unwrap();
+        writer.commit().unwrap();
+        assert!(index.wait_for_update(update_id, Duration::from_secs(10)));
+
+        let reader = env.read_txn().unwrap();
+        let dumped = crate::IndexSettings::read(&reader, &index).unwrap();
+        assert_eq!(dumped, settings);
+
+        let json = serde_json::to_string(&dumped).unwrap();
+        let restored: crate::IndexSettings = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored, settings);
+
+        // the identifier is only part of the raw document
+        let document_id = compute_document_id("1");
+        let document: Option<serde_json::Value> =
+            index.document(&reader, None, document_id).unwrap();
+        assert_eq!(document, Some(serde_json::json!({ "name": "Marvin" })));
+
+        let document = index.raw_document(&reader, document_id).unwrap().unwrap();
+        assert_eq!(
+            serde_json::Value::Object(document),
+            serde_json::json!({ "id": 1, "name": "Marvin" })
+        );
+        drop(reader);
+
+        // the settings are written into another index before its documents
+        let target = database.create_index("target").unwrap();
+        let mut writer = env.write_txn().unwrap();
+        let update_id = target.schema_update(&mut writer, schema).unwrap();
+        restored.write(&mut writer, &target).unwrap();
+        writer.commit().unwrap();
+        assert!(target.wait_for_update(update_id, Duration::from_secs(10)));
+
+        let reader = env.read_txn().unwrap();
+        let copied = crate::IndexSettings::read(&reader, &target).unwrap();
+        assert_eq!(copied, settings);
+
+        let alternatives = target
+            .synonyms
+            .weighted_synonyms(&reader, b"nyc")
+            .unwrap()
+            .unwrap();
+        assert_eq!(alternatives.get("big apple"), Some(2));
+    }
+
+    #[test]
+    fn duplicates_detection() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let database = Database::open_or_create(dir.path()).unwrap();
+        let env = &database.env;
+
+        let (sender, receiver) = mpsc::sync_channel(100);
+        let update_fn = move |_name: &str, update: ProcessedUpdateResult| {
+            sender.send(update.update_id).unwrap()
+        };
+        let index = database.create_index("test").unwrap();
+
+        database.set_update_callback(Box::new(update_fn));
+
+        let schema = {
+            let data = r#"
+                identifier = "id"
+
+                [attributes."id"]
+                displayed = true
+
+                [attributes."name"]
+                displayed = true
+                indexed = true
+            "#;
+            toml::from_str(data).unwrap()
+        };
+
+        let mut writer = env.write_txn().unwrap();
+        let _update_id = index.schema_update(&mut writer, schema).unwrap();
+        writer.commit().unwrap();
+
+        let mut additions = index.documents_addition();
+        additions.update_document(serde_json::json!({ "id": 1, "name": "Marvin" }));
+
+        let mut writer = env.write_txn().unwrap();
+        let update_id = additions.finalize(&mut writer).unwrap();
+        writer.commit().unwrap();
+
+        // block until the transaction is processed
+        let _ = receiver.iter().find(|id| *id == update_id);
+
+        let mut additions = index.documents_addition();
+        additions.duplicates_policy(DuplicatesPolicy::Skip);
+        additions.update_document(serde_json::json!({ "id": 2, "name": "Marvin" }));
+        additions.update_document(serde_json::json!({ "id": 3, "name": "Kevin" }));
+        additions.update_document(serde_json::json!({ "id": 4, "name": "Kevin" }));
+
+        let mut writer = env.write_txn().unwrap();
+        let update_id = additions.finalize(&mut writer).unwrap();
+        writer.commit().unwrap();
+
+        // block until the transaction is processed
+        let _ = receiver.iter().find(|id| *id == update_id);
+
+        let reader = env.read_txn().unwrap();
+        let result = index.update_status(&reader, update_id).unwrap();
+        let content = match result {
+            Some(UpdateStatus::Processed { content }) => content,
+            otherwise => panic!("unexpected update status {:?}", otherwise),
+        };
+
+        assert!(content.error.is_none());
+        assert_eq!(
+            content.duplicates,
+            vec![
+                DuplicateDocument {
+                    identifier: String::from("2"),
+                    duplicate_of: String::from("1"),
+                },
+                DuplicateDocument {
+                    identifier: String::from("4"),
+                    duplicate_of: String::from("3"),
+                },
+            ]
+        );
+
+        for (id, exists) in &[("1", true), ("2", false), ("3", true), ("4", false)] {
+            let document_id = compute_document_id(id.to_string());
+            let document: Option<IgnoredAny> = index.document(&reader, None, document_id).unwrap();
+            assert_eq!(document.is_some(), *exists, "document {}", id);
+        }
+    }
+
+    #[test]
+    fn identifiers_verification() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let database = Database::open_or_create(dir.path()).unwrap();
+        let env = &database.env;
+
+        let (sender, receiver) = mpsc::sync_channel(100);
+        let update_fn = move |_name: &str, update: ProcessedUpdateResult| {
+            sender.send(update.update_id).unwrap()
+        };
+        let index = database.create_index("test").unwrap();
+
+        database.set_update_callback(Box::new(update_fn));
+
+        let schema = {
+            let data = r#"
+                identifier = "id"
+
+                [attributes."id"]
+                displayed = true
+
+                [attributes."name"]
+                displayed = true
+                indexed = true
+            "#;
+            toml::from_str(data).unwrap()
+        };
+
+        let mut writer = env.write_txn().unwrap();
+        let _update_id = index.schema_update(&mut writer, schema).unwrap();
+        writer.commit().unwrap();
+
+        let mut additions = index.documents_addition();
+        additions.update_document(serde_json::json!({ "id": 1, "name": "Marvin" }));
+
+        let mut writer = env.write_txn().unwrap();
+        let update_id = additions.finalize(&mut writer).unwrap();
+        writer.commit().unwrap();
+
+        // block until the transaction is processed
+        let _ = receiver.iter().find(|id| *id == update_id);
+
+        let verifications = vec![
+            (IdentifiersVerification::Ignore, vec![]),
+            (
+                IdentifiersVerification::Addition,
+                vec![IdentifierCollision {
+                    identifier: String::from("2"),
+                    occurrences: 2,
+                    indexed: false,
+                }],
+            ),
+            (
+                IdentifiersVerification::Indexed,
+                vec![
+                    IdentifierCollision {
+                        identifier: String::from("1"),
+                        occurrences: 1,
+                        indexed: true,
+                    },
+                    IdentifierCollision {
+                        identifier: String::from("2"),
+                        occurrences: 2,
+                        indexed: true,
+                    },
+                    IdentifierCollision {
+                        identifier: String::from("3"),
+                        occurrences: 1,
+                        indexed: true,
+                    },
+                ],
+            ),
+        ];
+
+        for (verification, expected) in verifications {
+            let mut additions = index.documents_addition();
+            additions.identifiers_verification(verification);
+            additions.update_document(serde_json::json!({ "id": 1, "name": "Kevin" }));
+            additions.update_document(serde_json::json!({ "id": 2, "name": "Bob" }));
+            additions.update_document(serde_json::json!({ "id": 3, "name": "Stuart" }));
+            additions.update_document(serde_json::json!({ "id": 2, "name": "Dave" }));
+
+            let mut writer = env.write_txn().unwrap();
+            let update_id = additions.finalize(&mut writer).unwrap();
+            writer.commit().unwrap();
+
+            // block until the transaction is processed
+            let _ = receiver.iter().find(|id| *id == update_id);
+
+            let reader = env.read_txn().unwrap();
+            let result = index.update_status(&reader, update_id).unwrap();
+            let content = match result {
+                Some(UpdateStatus::Processed { content }) => content,
+                otherwise => panic!("unexpected update status {:?}", otherwise),
+            };
+
+            assert!(content.error.is_none());
+            assert_eq!(content.collisions, expected, "{:?}", verification);
+        }
+
+        // the last document with a shared identifier is the one kept
+        let reader = env.read_txn().unwrap();
+        let document_id = compute_document_id("2".to_string());
+        let document: Option<serde_json::Value> =
+            index.document(&reader, None, document_id).unwrap();
+        assert_eq!(document.unwrap()["name"], "Dave");
+    }
+
+    #[test]
+    fn identifiers_generation() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let database = Database::open_or_create(dir.path()).unwrap();
+        let env = &database.env;
+
+        let (sender, receiver) = mpsc::sync_channel(100);
+        let update_fn = move |_name: &str, update: ProcessedUpdateResult| {
+            sender.send(update.update_id).unwrap()
+        };
+        let index = database.create_index("test").unwrap();
+
+        database.set_update_callback(Box::new(update_fn));
+
+        let schema = {
+            let data = r#"
+                identifier = "id"
+
+                [attributes."id"]
+                displayed = true
+
+                [attributes."name"]
+                displayed = true
+                indexed = true
+            "#;
+            toml::from_str(data).unwrap()
+        };
+
+        let mut writer = env.write_txn().unwrap();
+        let _update_id = index.schema_update(&mut writer, schema).unwrap();
+        writer.commit().unwrap();
+
+        let generations = vec![
+            (IdentifiersGeneration::Disabled, true, 0),
+            (IdentifiersGeneration::Content, false, 2),
+            (IdentifiersGeneration::Content, false, 2),
+            (IdentifiersGeneration::Uuid, false, 5),
+        ];
+
+        for (generation, failed, number_of_documents) in generations {
+            let mut additions = index.documents_addition();
+            additions.identifiers_generation(generation);
+            additions.update_document(serde_json::json!({ "name": "Marvin" }));
+            additions.update_document(serde_json::json!({ "name": "Marvin" }));
+            additions.update_document(serde_json::json!({ "id": null, "name": "Kevin" }));
+
+            let mut writer = env.write_txn().unwrap();
+            let update_id = additions.finalize(&mut writer).unwrap();
+            writer.commit().unwrap();
+
+            // block until the transaction is processed
+            let _ = receiver.iter().find(|id| *id == update_id);
+
+            let reader = env.read_txn().unwrap();
+            let result = index.update_status(&reader, update_id).unwrap();
+            let content = match result {
+                Some(UpdateStatus::Processed { content }) => content,
+                otherwise => panic!("unexpected update status {:?}", otherwise),
+            };
+
+            assert_eq!(content.error.is_some(), failed, "{:?}", generation);
+            let count = index.main.number_of_documents(&reader).unwrap();
+            assert_eq!(count, number_of_documents, "{:?}", generation);
+        }
+    }
+
+    #[test]
+    fn flattened_documents() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let database = Database::open_or_create(dir.path()).unwrap();
+        let env = &database.env;
+
+        let (sender, receiver) = mpsc::sync_channel(100);
+        let update_fn = move |_name: &str, update: ProcessedUpdateResult| {
+            sender.send(update.update_id).unwrap()
+        };
+        let index = database.create_index("test").unwrap();
+
+        database.set_update_callback(Box::new(update_fn));
+
+        let schema = {
+            let data = r#"
+                identifier = "id"
+
+                [attributes."id"]
+                displayed = true
+
+                [attributes."address"]
+                displayed = true
+                indexed = true
+            "#;
+            toml::from_str(data).unwrap()
+        };
+
+        let mut writer = env.write_txn().unwrap();
+        let _update_id = index.schema_update(&mut writer, schema).unwrap();
+        index.main.put_flatten_documents(&mut writer, true).unwrap();
+        writer.commit().unwrap();
+
+        let mut additions = index.documents_addition();
+        additions.update_document(serde_json::json!({
+            "id": 1,
+            "address": { "city": "Paris", "streets": [{ "name": "Rivoli" }] },
+            "author": { "name": "Kevin" },
+        }));
+
+        let mut writer = env.write_txn().unwrap();
+        let update_id = additions.finalize(&mut writer).unwrap();
+        writer.commit().unwrap();
+
+        // block until the transaction is processed
+        let _ = receiver.iter().find(|id| *id == update_id);
+
+        let reader = env.read_txn().unwrap();
+        let result = index.update_status(&reader, update_id).unwrap();
+        assert_matches!(result, Some(UpdateStatus::Processed { content }) if content.error.is_none());
+
+        let snapshot = index.schema_snapshot();
+        let schema = snapshot.schema().unwrap();
+        assert!(schema.attribute("address.city").is_some());
+        assert!(schema.attribute("address.streets[].name").is_some());
+        assert!(schema.attribute("author.name").is_none());
+
+        let results = index
+            .query_builder()
+            .query(&reader, "rivoli", 0..20)
+            .unwrap();
+        assert_eq!(results.len(), 1);
+
+        let document: Option<serde_json::Value> =
+            index.document(&reader, None, results[0].id).unwrap();
+        let expected = serde_json::json!({
+            "id": 1,
+            "address.city": "Paris",
+            "address.streets[].name": ["Rivoli"],
+        });
+        assert_eq!(document, Some(expected));
+    }
+
+    #[test]
+    fn schemaless_index() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let database = Database::open_or_create(dir.path()).unwrap();
+        let env = &database.env;
+
+        let (sender, receiver) = mpsc::sync_channel(100);
+        let update_fn = move |_name: &str, update: ProcessedUpdateResult| {
+            sender.send(update.update_id).unwrap()
+        };
+        let index = database.create_schemaless_index("test").unwrap();
+
+        database.set_update_callback(Box::new(update_fn));
+
+        let batches = vec![
+            serde_json::json!({ "movie_id": 1, "title": "Alien" }),
+            serde_json::json!({ "movie_id": 2, "title": "Blade Runner", "director": "Scott" }),
+        ];
+
+        for document in batches {
+            let mut additions = index.documents_addition();
+            additions.update_document(document);
+
+            let mut writer = env.write_txn().unwrap();
+            let update_id = additions.finalize(&mut writer).unwrap();
+            writer.commit().unwrap();
+
+            // block until the transaction is processed
+            let _ = receiver.iter().find(|id| *id == update_id);
+
+            let reader = env.read_txn().unwrap();
+            let result = index.update_status(&reader, update_id).unwrap();
+            assert_matches!(result, Some(UpdateStatus::Processed { content }) if content.error.is_none());
+        }
+
+        let reader = env.read_txn().unwrap();
+        let schema = index.main.schema(&reader).unwrap().unwrap();
+        assert_eq!(schema.identifier_name(), "movie_id");
+        assert_eq!(schema.number_of_attributes(), 3);
+        assert_eq!(index.schema_snapshot().schema(), Some(&schema));
+
+        let results = index
+            .query_builder()
+            .query(&reader, "scott", 0..20)
+            .unwrap();
+        assert_eq!(results.len(), 1);
+    }
+
+    #[test]
+    fn retrieve_deleted_document() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let database = Database::open_or_create(dir.path()).unwrap();
+        let env = &database.env;
+
+        let (sender, receiver) = mpsc::sync_channel(100);
+        let update_fn = move |_name: &str, update: ProcessedUpdateResult| {
+            sender.send(update.update_id).unwrap()
+        };
+        let index = database.create_index("test").unwrap();
+
+        database.set_update_callback(Box::new(update_fn));
+
+        let schema = {
+            let data = r#"
+                identifier = "id"
+
+                [attributes."id"]
+
+                [attributes."name"]
+                indexed = true
+
+                [attributes."description"]
+                displayed = true
+                indexed = true
+            "#;
+            toml::from_str(data).unwrap()
+        };
+
+        let mut writer = env.write_txn().unwrap();
+        let _update_id = index.schema_update(&mut writer, schema).unwrap();
+        writer.commit().unwrap();
+
+        let mut additions = index.documents_addition();
+        additions.update_document(serde_json::json!({ "id": 1, "name": "Marvin" }));
+        additions.update_document(serde_json::json!({ "id": 2, "name": "Kevin" }));
+
+        let mut writer = env.write_txn().unwrap();
+        let update_id = additions.finalize(&mut writer).unwrap();
+        writer.commit().unwrap();
+
+        // block until the transaction is processed
+        let _ = receiver.iter().find(|id| *id == update_id);
+
+        // the query is made before the deletion of the documents
+        let query_reader = env.read_txn().unwrap();
+        let results = index
+            .query_builder()
+            .query(&query_reader, "marvin", 0..20)
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        let document_id = results[0].id;
+
+        let mut deletion = index.documents_deletion();
+        deletion.delete_document_by_id(document_id);
+
+        let mut writer = env.write_txn().unwrap();
+        let update_id = deletion.finalize(&mut writer).unwrap();
+        writer.commit().unwrap();
+
+        // block until the transaction is processed
+        let _ = receiver.iter().find(|id| *id == update_id);
+
+        // the document has no displayed attributes but is found with the reader of the query
+        let snapshot = index.schema_snapshot();
+        let retrieval: DocumentRetrieval<serde_json::Value> = index
+            .retrieve_document(&query_reader, &snapshot, None, document_id)
+            .unwrap();
+        assert_eq!(retrieval, DocumentRetrieval::Found(serde_json::json!({})));
+
+        let reader = env.read_txn().unwrap();
+        let retrieval: DocumentRetrieval<serde_json::Value> = index
+            .retrieve_document(&reader, &snapshot, None, document_id)
+            .unwrap();
+        assert_eq!(retrieval, DocumentRetrieval::Gone);
+    }
+
+    #[test]
+    fn delete_index() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let database = Arc::new(Database::open_or_create(dir.path()).unwrap());
+        let env = &database.env;
+
+        let (sender, receiver) = mpsc::sync_channel(100);
+        let db_cloned = database.clone();
+        let update_fn = move |name: &str, update: ProcessedUpdateResult| {
+            // try to open index to trigger a lock
+            let _ = db_cloned.open_index(name);
+            sender.send(update.update_id).unwrap()
+        };
+
+        // create the index
+        let index = database.create_index("test").unwrap();
+
+        database.set_update_callback(Box::new(update_fn));
+
+        let schema = {
+            let data = r#"
+                identifier = "id"
+
+                [attributes."name"]
+                displayed = true
+                indexed = true
+
+                [attributes."description"]
+                displayed = true
+                indexed = true
+            "#;
+            toml::from_str(data).unwrap()
+        };
+
+        // add a schema to the index
+        let mut writer = env.write_txn().unwrap();
+        let _update_id = index.schema_update(&mut writer, schema).unwrap();
+        writer.commit().unwrap();
+
+        // add documents to the index
+        let mut additions = index.documents_addition();
+
+        let doc1 = serde_json::json!({
+            "id": 123,
+            "name": "Marvin",
+            "description": "My name is Marvin",
+        });
+
+        let doc2 = serde_json::json!({
+            "id": 234,
+            "name": "Kevin",
+            "description": "My name is Kevin",
+        });
+
+        additions.update_document(doc1);
+        additions.update_document(doc2);
+
+        let mut writer = env.write_txn().unwrap();
+        let update_id = additions.finalize(&mut writer).unwrap();
+        writer.commit().unwrap();
+
+        // delete the index
+        let deleted = database.delete_index("test").unwrap();
+        assert!(deleted);
+
+        // block until the transaction is processed
+        let _ = receiver.into_iter().find(|id| *id == update_id);
+
+        let result = database.open_index("test");
+        assert!(result.is_none());
+    }
+
+    #[test]
+    fn orphaned_stores() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let database = Database::open_or_create(dir.path()).unwrap();
+        let env = &database.env;
+        assert!(database.startup_report().is_consistent());
+
+        // the stores of an index whose deletion has been interrupted
+        let (sender, _receiver) = crossbeam_channel::bounded(100);
+        let orphan = store::create(env, "orphan", sender).unwrap();
+        let index = database.create_index("test").unwrap();
+
+        let mut writer = env.write_txn().unwrap();
+        orphan.main.put_name(&mut writer, "orphan").unwrap();
+        index.main.put_name(&mut writer, "test").unwrap();
+        writer.commit().unwrap();
+
+        let indexes_uids = database.indexes_uids();
+        let orphaned_stores = clear_orphaned_stores(env, &indexes_uids).unwrap();
+        assert_eq!(orphaned_stores, vec!["store-orphan".to_string()]);
+
+        let reader = env.read_txn().unwrap();
+        assert_eq!(orphan.main.name(&reader).unwrap(), None);
+        assert_eq!(index.main.name(&reader).unwrap(), Some("test".to_string()));
+        reader.abort();
+
+        // the emptied stores are not reported anymore
+        let orphaned_stores = clear_orphaned_stores(env, &indexes_uids).unwrap();
+        assert!(orphaned_stores.is_empty());
+    }
+
+    #[test]
+    fn check_number_ordering() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let database = Database::open_or_create(dir.path()).unwrap();
+        let env = &database.env;
+
+        let (sender, receiver) = mpsc::sync_channel(100);
+        let update_fn = move |_name: &str, update: ProcessedUpdateResult| {
+            sender.send(update.update_id).unwrap()
+        };
+        let index = database.create_index("test").unwrap();
+
+        database.set_update_callback(Box::new(update_fn));
+
+        let schema = {
+            let data = r#"
+                identifier = "id"
+
+                [attributes."name"]
+                displayed = true
+                indexed = true
+
+                [attributes."release_date"]
+                displayed = true
+                ranked = true
+            "#;
+            toml::from_str(data).unwrap()
+        };
+
+        let mut writer = env.write_txn().unwrap();
+        let _update_id = index.schema_update(&mut writer, schema).unwrap();
+        writer.commit().unwrap();
+
+        let mut additions = index.documents_addition();
+
+        // DocumentId(7900334843754999545)
+        let doc1 = serde_json::json!({
+            "id": 123,
+            "name": "Kevin the first",
+            "release_date": -10000,
+        });
+
+        // DocumentId(8367468610878465872)
+        let doc2 = serde_json::json!({
+            "id": 234,
+            "name": "Kevin the second",
+            "release_date": 10000,
+        });
+
+        additions.update_document(doc1);
+        additions.update_document(doc2);
+
+        let mut writer = env.write_txn().unwrap();
+        let update_id = additions.finalize(&mut writer).unwrap();
+        writer.commit().unwrap();
+
+        // block until the transaction is processed
+        let _ = receiver.into_iter().find(|id| *id == update_id);
+
+        let reader = env.read_txn().unwrap();
+
+        let schema = index.main.schema(&reader).unwrap().unwrap();
+        let ranked_map = index.main.ranked_map(&reader).unwrap().unwrap();
+
+        let criteria = CriteriaBuilder::new()
+            .add(
+                criterion::SortByAttr::lower_is_better(&ranked_map, &schema, "release_date")
+                    .unwrap(),
+            )
+            .add(criterion::DocumentId)
+            .build();
+
+        let builder = index.query_builder_with_criteria(criteria);
+
+        let results = builder.query(&reader, "Kevin", 0..20).unwrap();
+        let mut iter = results.into_iter();
+
+        assert_matches!(
+            iter.next(),
+            Some(Document {
+                id: DocumentId(7900334843754999545),
+                ..
+            })
+        );
+        assert_matches!(
+            iter.next(),
+            Some(Document {
+                id: DocumentId(8367468610878465872),
+                ..
             })
         );
-        assert_matches!(
-            iter.next(),
-            Some(Document {
-                id: DocumentId(8367468610878465872),
-                ..
-            })
+        assert_matches!(iter.next(), None);
+    }
+
+    fn movies_schema() -> meilidb_schema::Schema {
+        let data = r#"
+            identifier = "id"
+
+            [attributes."name"]
+            displayed = true
+            indexed = true
+        "#;
+        toml::from_str(data).unwrap()
+    }
+
+    #[test]
+    fn read_only_database() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let database = Database::open_or_create(dir.path()).unwrap();
+        let env = &database.env;
+        let index = database.create_index("test").unwrap();
+
+        database.set_read_only(true);
+        assert!(database.is_read_only());
+        assert_matches!(database.create_index("other").err(), Some(Error::ReadOnly));
+
+        let mut writer = env.write_txn().unwrap();
+        let update_id = index.schema_update(&mut writer, movies_schema()).unwrap();
+        writer.commit().unwrap();
+
+        // the update is postponed until the database is writable again
+        assert!(!index.wait_for_update(update_id, Duration::from_millis(100)));
+
+        database.set_read_only(false);
+        assert!(index.wait_for_update(update_id, Duration::from_secs(10)));
+
+        let reader = env.read_txn().unwrap();
+        assert!(index.main.schema(&reader).unwrap().is_some());
+    }
+
+    #[test]
+    fn schema_snapshots() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let database = Database::open_or_create(dir.path()).unwrap();
+        let env = &database.env;
+        let index = database.create_index("test").unwrap();
+
+        let before = index.schema_snapshot();
+        assert_eq!(before.version(), 0);
+        assert!(before.schema().is_none());
+
+        let mut writer = env.write_txn().unwrap();
+        let update_id = index.schema_update(&mut writer, movies_schema()).unwrap();
+        writer.commit().unwrap();
+        assert!(index.wait_for_update(update_id, Duration::from_secs(10)));
+
+        let after = index.schema_snapshot();
+        assert_eq!(after.version(), 1);
+        assert_eq!(after.schema(), Some(&movies_schema()));
+        assert!(before.schema().is_none());
+
+        // the snapshot is shared by the clones of the index
+        let reopened = database.open_index("test").unwrap();
+        assert_eq!(reopened.schema_snapshot().version(), 1);
+    }
+
+    #[test]
+    fn word_postings() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let database = Database::open_or_create(dir.path()).unwrap();
+        let env = &database.env;
+        let index = database.create_index("test").unwrap();
+
+        let mut writer = env.write_txn().unwrap();
+        index.schema_update(&mut writer, movies_schema()).unwrap();
+        writer.commit().unwrap();
+
+        let mut additions = index.documents_addition();
+        additions.update_document(serde_json::json!({ "id": 123, "name": "Marvin and Marvin" }));
+        additions.update_document(serde_json::json!({ "id": 234, "name": "Kevin" }));
+
+        let mut writer = env.write_txn().unwrap();
+        let update_id = additions.finalize(&mut writer).unwrap();
+        writer.commit().unwrap();
+        assert!(index.wait_for_update(update_id, Duration::from_secs(10)));
+
+        let reader = env.read_txn().unwrap();
+        let postings: Vec<_> = index.word_postings(&reader, "MARVIN").unwrap().collect();
+        assert_eq!(
+            postings,
+            vec![Posting {
+                document_id: compute_document_id("123"),
+                attribute: SchemaAttr(0),
+                word_indexes: vec![0, 2],
+            }]
+        );
+
+        let postings = index.word_postings(&reader, "unknown").unwrap();
+        assert_eq!(postings.count(), 0);
+    }
+
+    #[test]
+    fn settings_broadcast() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let database = Database::open_or_create(dir.path()).unwrap();
+        let env = &database.env;
+        let movies = database.create_index("movies").unwrap();
+        let books = database.create_index("books").unwrap();
+
+        let mut settings = SettingsPatchContent::default();
+        settings.add_stop_word("The");
+        settings.add_synonym("Movie", &["film"]);
+
+        let result = database.apply_settings_to(&["movies", "unknown"], &settings);
+        assert_matches!(result, Err(Error::IndexNotFound));
+
+        // nothing is enqueued when one of the indexes does not exist
+        let reader = env.read_txn().unwrap();
+        assert!(movies.all_updates_status(&reader).unwrap().is_empty());
+        reader.abort();
+
+        let updates_ids = database
+            .apply_settings_to(&["movies", "books"], &settings)
+            .unwrap();
+        assert_eq!(updates_ids.len(), 2);
+
+        for (index, uid) in &[(&movies, "movies"), (&books, "books")] {
+            let update_id = updates_ids[*uid];
+            assert!(index.wait_for_update(update_id, Duration::from_secs(10)));
+
+            let reader = env.read_txn().unwrap();
+            let result = index.update_status(&reader, update_id).unwrap();
+            assert_matches!(result, Some(UpdateStatus::Processed { content }) if content.error.is_none());
+
+            let stop_words = index.main.stop_words_fst(&reader).unwrap().unwrap();
+            assert!(stop_words.contains("the"));
+
+            let synonyms = index.synonyms.synonyms(&reader, b"movie").unwrap().unwrap();
+            assert!(synonyms.contains("film"));
+        }
+    }
+
+    #[test]
+    fn documents_addition_coercions() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let database = Database::open_or_create(dir.path()).unwrap();
+        let env = &database.env;
+        let index = database.create_index("test").unwrap();
+
+        let schema = {
+            let data = r#"
+                identifier = "id"
+
+                [attributes."id"]
+                displayed = true
+
+                [attributes."name"]
+                displayed = true
+                indexed = true
+
+                [attributes."rank"]
+                displayed = true
+                ranked = true
+            "#;
+            toml::from_str(data).unwrap()
+        };
+
+        let mut writer = env.write_txn().unwrap();
+        index.schema_update(&mut writer, schema).unwrap();
+        writer.commit().unwrap();
+
+        let documents = vec![
+            serde_json::json!({ "id": 1, "name": "word ".repeat(1001), "rank": "12", "unknown": true }),
+            serde_json::json!({ "id": 2, "name": "Kevin", "rank": 3 }),
+        ];
+
+        let mut writer = env.write_txn().unwrap();
+        let update_id = index
+            .documents_addition_from_values(&mut writer, documents)
+            .unwrap();
+        writer.commit().unwrap();
+        assert!(index.wait_for_update(update_id, Duration::from_secs(10)));
+
+        let reader = env.read_txn().unwrap();
+        let content = match index.update_status(&reader, update_id).unwrap() {
+            Some(UpdateStatus::Processed { content }) => content,
+            _ => panic!("update not processed"),
+        };
+        assert!(content.error.is_none());
+
+        let report = content.coercions.unwrap();
+        assert_eq!(
+            report.documents,
+            vec![DocumentCoercions {
+                identifier: "1".to_string(),
+                coerced: vec!["rank".to_string()],
+                dropped: vec!["unknown".to_string()],
+                truncated: vec!["name".to_string()],
+            }]
+        );
+        assert_eq!(report.dropped.get("unknown"), Some(&1));
+    }
+
+    #[test]
+    fn attributes_lengths() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let database = Database::open_or_create(dir.path()).unwrap();
+        let env = &database.env;
+        let index = database.create_index("test").unwrap();
+
+        let schema = {
+            let data = r#"
+                identifier = "id"
+
+                [attributes."id"]
+                displayed = true
+
+                [attributes."name"]
+                displayed = true
+                indexed = true
+            "#;
+            toml::from_str(data).unwrap()
+        };
+
+        let mut writer = env.write_txn().unwrap();
+        index.schema_update(&mut writer, schema).unwrap();
+        writer.commit().unwrap();
+
+        let documents = vec![
+            serde_json::json!({ "id": 1, "name": "word ".repeat(1200) }),
+            serde_json::json!({ "id": 2, "name": "Kevin" }),
+        ];
+
+        let mut writer = env.write_txn().unwrap();
+        let update_id = index
+            .documents_addition_from_values(&mut writer, documents)
+            .unwrap();
+        writer.commit().unwrap();
+        assert!(index.wait_for_update(update_id, Duration::from_secs(10)));
+
+        let reader = env.read_txn().unwrap();
+        let content = match index.update_status(&reader, update_id).unwrap() {
+            Some(UpdateStatus::Processed { content }) => content,
+            _ => panic!("update not processed"),
+        };
+        reader.abort();
+
+        let report = content.coercions.unwrap();
+        assert_eq!(report.warnings.len(), 1);
+        assert!(report.warnings[0].contains("limit of 1000 words"));
+
+        let documents = vec![serde_json::json!({ "id": 3, "name": "Kevin Bacon" })];
+
+        let mut writer = env.write_txn().unwrap();
+        let update_id = index
+            .documents_addition_from_values(&mut writer, documents)
+            .unwrap();
+        writer.commit().unwrap();
+        assert!(index.wait_for_update(update_id, Duration::from_secs(10)));
+
+        // the lengths of the values are aggregated over the updates
+        let reader = env.read_txn().unwrap();
+        let lengths = index.main.attributes_lengths(&reader).unwrap().unwrap();
+        let name = &lengths["name"];
+        assert_eq!(name.values, 3);
+        assert_eq!(name.total_words, 1203);
+        assert_eq!(name.max_words, 1200);
+        assert_eq!(name.truncated, 1);
+        assert_eq!(name.buckets, vec![2, 0, 0, 1]);
+        reader.abort();
+
+        let mut writer = env.write_txn().unwrap();
+        let update_id = index.clear_all(&mut writer).unwrap();
+        writer.commit().unwrap();
+        assert!(index.wait_for_update(update_id, Duration::from_secs(10)));
+
+        let reader = env.read_txn().unwrap();
+        let lengths = index.main.attributes_lengths(&reader).unwrap().unwrap();
+        assert!(lengths.is_empty());
+    }
+
+    #[cfg(feature = "fault-injection")]
+    #[test]
+    fn injected_write_failure() {
+        use crate::fault_injection::{FaultConfig, FaultKind};
+
+        let dir = tempfile::tempdir().unwrap();
+
+        let database = Database::open_or_create(dir.path()).unwrap();
+        let env = &database.env;
+        let index = database.create_index("test").unwrap();
+
+        index.fault_injector().configure(FaultConfig {
+            write_failure_rate: 1.0,
+            ..FaultConfig::default()
+        });
+
+        let mut writer = env.write_txn().unwrap();
+        let update_id = index.schema_update(&mut writer, movies_schema()).unwrap();
+        writer.commit().unwrap();
+
+        assert!(!index.wait_for_update(update_id, Duration::from_millis(100)));
+        assert_eq!(
+            index.fault_injector().injected(),
+            vec![(update_id, FaultKind::WriteFailure)]
+        );
+
+        // the failed update is still enqueued and processed on the next notification
+        let reader = env.read_txn().unwrap();
+        let result = index.update_status(&reader, update_id).unwrap();
+        assert_matches!(result, Some(UpdateStatus::Enqueued { .. }));
+        reader.abort();
+
+        index.fault_injector().disable();
+        index.updates_notifier.send(UpdateEvent::NewUpdate).unwrap();
+        assert!(index.wait_for_update(update_id, Duration::from_secs(10)));
+
+        let reader = env.read_txn().unwrap();
+        let result = index.update_status(&reader, update_id).unwrap();
+        assert_matches!(result, Some(UpdateStatus::Processed { content }) if content.error.is_none());
+        assert!(index.main.schema(&reader).unwrap().is_some());
+    }
+
+    #[cfg(feature = "fault-injection")]
+    #[test]
+    fn injected_torn_update() {
+        use crate::fault_injection::{FaultConfig, FaultKind};
+
+        let dir = tempfile::tempdir().unwrap();
+
+        let database = Database::open_or_create(dir.path()).unwrap();
+        let env = &database.env;
+        let index = database.create_index("test").unwrap();
+
+        let mut writer = env.write_txn().unwrap();
+        let update_id = index.schema_update(&mut writer, movies_schema()).unwrap();
+        writer.commit().unwrap();
+        assert!(index.wait_for_update(update_id, Duration::from_secs(10)));
+
+        index.fault_injector().configure(FaultConfig {
+            torn_update_rate: 1.0,
+            ..FaultConfig::default()
+        });
+
+        let mut additions = index.documents_addition();
+        additions.update_document(serde_json::json!({ "id": 123, "name": "Marvin" }));
+
+        let mut writer = env.write_txn().unwrap();
+        let update_id = additions.finalize(&mut writer).unwrap();
+        writer.commit().unwrap();
+
+        assert!(!index.wait_for_update(update_id, Duration::from_millis(100)));
+        assert_eq!(
+            index.fault_injector().injected(),
+            vec![(update_id, FaultKind::TornUpdate)]
+        );
+
+        // the documents applied before the fault must have been rolled back
+        let reader = env.read_txn().unwrap();
+        assert_eq!(index.main.number_of_documents(&reader).unwrap(), 0);
+        let result = index.update_status(&reader, update_id).unwrap();
+        assert_matches!(result, Some(UpdateStatus::Enqueued { .. }));
+        reader.abort();
+
+        index.fault_injector().disable();
+        index.updates_notifier.send(UpdateEvent::NewUpdate).unwrap();
+        assert!(index.wait_for_update(update_id, Duration::from_secs(10)));
+
+        let reader = env.read_txn().unwrap();
+        assert_eq!(index.main.number_of_documents(&reader).unwrap(), 1);
+        let result = index.update_status(&reader, update_id).unwrap();
+        assert_matches!(result, Some(UpdateStatus::Processed { content }) if content.error.is_none());
+    }
+
+    #[cfg(feature = "fault-injection")]
+    #[test]
+    fn injected_commit_delay() {
+        use crate::fault_injection::{FaultConfig, FaultKind};
+
+        let dir = tempfile::tempdir().unwrap();
+
+        let database = Database::open_or_create(dir.path()).unwrap();
+        let env = &database.env;
+        let index = database.create_index("test").unwrap();
+
+        index.fault_injector().configure(FaultConfig {
+            commit_delay: Some(Duration::from_millis(200)),
+            ..FaultConfig::default()
+        });
+
+        let mut writer = env.write_txn().unwrap();
+        let update_id = index.clear_all(&mut writer).unwrap();
+        writer.commit().unwrap();
+
+        assert!(!index.wait_for_update(update_id, Duration::from_millis(10)));
+        assert!(index.wait_for_update(update_id, Duration::from_secs(10)));
+        assert_eq!(
+            index.fault_injector().injected(),
+            vec![(update_id, FaultKind::DelayedCommit)]
+        );
+    }
+
+    #[test]
+    fn filter_expression() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let database = Database::open_or_create(dir.path()).unwrap();
+        let env = &database.env;
+        let index = database.create_index("test").unwrap();
+
+        let schema = {
+            let data = r#"
+                identifier = "id"
+
+                [attributes."title"]
+                displayed = true
+                indexed = true
+
+                [attributes."genre"]
+                displayed = true
+
+                [attributes."year"]
+                displayed = true
+                ranked = true
+            "#;
+            toml::from_str(data).unwrap()
+        };
+
+        let mut writer = env.write_txn().unwrap();
+        index.schema_update(&mut writer, schema).unwrap();
+        writer.commit().unwrap();
+
+        let mut additions = index.documents_addition();
+        additions.update_document(serde_json::json!({ "id": 1, "title": "night shift", "genre": "Thriller", "year": 1998 }));
+        additions.update_document(serde_json::json!({ "id": 2, "title": "night train", "genre": "thriller", "year": 2004 }));
+        additions.update_document(
+            serde_json::json!({ "id": 3, "title": "night music", "genre": "drama", "year": 2010 }),
+        );
+
+        let mut writer = env.write_txn().unwrap();
+        let update_id = additions.finalize(&mut writer).unwrap();
+        writer.commit().unwrap();
+        assert!(index.wait_for_update(update_id, Duration::from_secs(10)));
+
+        let reader = env.read_txn().unwrap();
+        let search = |filter: &str| -> Vec<DocumentId> {
+            let mut builder = index.query_builder();
+            builder.with_filter_expression(filter.parse().unwrap());
+            let documents = builder.query(&reader, "night", 0..20).unwrap();
+            let mut ids: Vec<_> = documents.into_iter().map(|d| d.id).collect();
+            ids.sort();
+            ids
+        };
+
+        let mut expected = vec![compute_document_id("2")];
+        assert_eq!(search(r#"genre = "thriller" AND year > 2000"#), expected);
+
+        expected.push(compute_document_id("3"));
+        expected.sort();
+        assert_eq!(search("year > 2000"), expected);
+
+        let mut expected = vec![compute_document_id("1"), compute_document_id("3")];
+        expected.sort();
+        assert_eq!(search("genre = drama OR year < 2000"), expected);
+        assert_eq!(search("NOT (genre = thriller AND year >= 2004)"), expected);
+
+        let mut builder = index.query_builder();
+        builder.with_filter_expression("rating > 2".parse().unwrap());
+        assert_matches!(
+            builder.query(&reader, "night", 0..20),
+            Err(Error::Filter(crate::FilterError::UnknownAttribute(_)))
+        );
+    }
+
+    #[test]
+    fn number_range() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let database = Database::open_or_create(dir.path()).unwrap();
+        let env = &database.env;
+        let index = database.create_index("test").unwrap();
+
+        let schema = {
+            let data = r#"
+                identifier = "id"
+
+                [attributes."name"]
+                displayed = true
+                indexed = true
+
+                [attributes."price"]
+                displayed = true
+                ranked = true
+            "#;
+            toml::from_str(data).unwrap()
+        };
+
+        let mut writer = env.write_txn().unwrap();
+        index.schema_update(&mut writer, schema).unwrap();
+        writer.commit().unwrap();
+
+        let mut additions = index.documents_addition();
+        additions.update_document(serde_json::json!({ "id": 1, "name": "red shoes", "price": 5 }));
+        additions
+            .update_document(serde_json::json!({ "id": 2, "name": "blue shoes", "price": 10 }));
+        additions
+            .update_document(serde_json::json!({ "id": 3, "name": "green shoes", "price": 35 }));
+        additions
+            .update_document(serde_json::json!({ "id": 4, "name": "white shoes", "price": 50 }));
+        additions.update_document(serde_json::json!({ "id": 5, "name": "black shoes" }));
+
+        let mut writer = env.write_txn().unwrap();
+        let update_id = additions.finalize(&mut writer).unwrap();
+        writer.commit().unwrap();
+        assert!(index.wait_for_update(update_id, Duration::from_secs(10)));
+
+        let reader = env.read_txn().unwrap();
+        let schema = index.main.schema(&reader).unwrap().unwrap();
+        let price = schema.attribute("price").unwrap();
+
+        let mut builder = index.query_builder();
+        builder.with_number_range(price, Number::Unsigned(10)..Number::Unsigned(50));
+        let documents = builder.query(&reader, "shoes", 0..20).unwrap();
+        let mut ids: Vec<_> = documents.into_iter().map(|d| d.id).collect();
+        ids.sort();
+
+        let mut expected = vec![compute_document_id("2"), compute_document_id("3")];
+        expected.sort();
+        assert_eq!(ids, expected);
+
+        // the ranges are combined with the filter expression
+        let mut builder = index.query_builder();
+        builder.with_number_range(price, Number::Unsigned(0)..Number::Unsigned(100));
+        builder.with_filter_expression("name != red".parse().unwrap());
+        let documents = builder.query(&reader, "shoes", 0..20).unwrap();
+        assert_eq!(documents.len(), 3);
+    }
+
+    #[test]
+    fn required_attribute() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let database = Database::open_or_create(dir.path()).unwrap();
+        let env = &database.env;
+        let index = database.create_index("test").unwrap();
+
+        let schema = {
+            let data = r#"
+                identifier = "id"
+
+                [attributes."title"]
+                displayed = true
+                indexed = true
+
+                [attributes."description"]
+                displayed = true
+                indexed = true
+            "#;
+            toml::from_str(data).unwrap()
+        };
+
+        let mut writer = env.write_txn().unwrap();
+        index.schema_update(&mut writer, schema).unwrap();
+        writer.commit().unwrap();
+
+        let mut additions = index.documents_addition();
+        additions.update_document(
+            serde_json::json!({ "id": 1, "title": "dune", "description": "a desert planet" }),
+        );
+        additions.update_document(serde_json::json!({ "id": 2, "title": "foundation", "description": "a galactic empire" }));
+        additions.update_document(serde_json::json!({ "id": 3, "title": "hyperion", "description": "the desert of the time tombs" }));
+
+        let mut writer = env.write_txn().unwrap();
+        let update_id = additions.finalize(&mut writer).unwrap();
+        writer.commit().unwrap();
+        assert!(index.wait_for_update(update_id, Duration::from_secs(10)));
+
+        let reader = env.read_txn().unwrap();
+        let schema = index.main.schema(&reader).unwrap().unwrap();
+        let title = schema.attribute("title").unwrap();
+
+        let documents = index
+            .query_builder()
+            .query(&reader, "dune desert", 0..20)
+            .unwrap();
+        assert_eq!(documents.len(), 2);
+
+        // hyperion only matches "desert" in its description
+        let mut builder = index.query_builder();
+        builder.with_required_attribute(title);
+        let documents = builder.query(&reader, "dune desert", 0..20).unwrap();
+        let ids: Vec<_> = documents.into_iter().map(|d| d.id).collect();
+        assert_eq!(ids, vec![compute_document_id("1")]);
+    }
+
+    #[test]
+    fn geo_search() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let database = Database::open_or_create(dir.path()).unwrap();
+        let env = &database.env;
+        let index = database.create_index("test").unwrap();
+
+        // the locations are indexed even if the geo field is not in the schema
+        let schema = {
+            let data = r#"
+                identifier = "id"
+
+                [attributes."name"]
+                displayed = true
+                indexed = true
+            "#;
+            toml::from_str(data).unwrap()
+        };
+
+        let mut writer = env.write_txn().unwrap();
+        index.schema_update(&mut writer, schema).unwrap();
+        writer.commit().unwrap();
+
+        let mut additions = index.documents_addition();
+        additions.update_document(serde_json::json!({ "id": 1, "name": "louvre museum", "_geo": { "lat": 48.8606, "lng": 2.3376 } }));
+        additions.update_document(serde_json::json!({ "id": 2, "name": "orsay museum", "_geo": { "lat": 48.8600, "lng": 2.3266 } }));
+        additions.update_document(serde_json::json!({ "id": 3, "name": "british museum", "_geo": { "lat": 51.5194, "lng": -0.1270 } }));
+        additions.update_document(serde_json::json!({ "id": 4, "name": "unknown museum" }));
+
+        let mut writer = env.write_txn().unwrap();
+        let update_id = additions.finalize(&mut writer).unwrap();
+        writer.commit().unwrap();
+        assert!(index.wait_for_update(update_id, Duration::from_secs(10)));
+
+        let reader = env.read_txn().unwrap();
+        let search = |meters: f64| -> Vec<DocumentId> {
+            let mut builder = index.query_builder();
+            builder.within_radius(48.8584, 2.2945, meters);
+            let documents = builder.query(&reader, "museum", 0..20).unwrap();
+            let mut ids: Vec<_> = documents.into_iter().map(|d| d.id).collect();
+            ids.sort();
+            ids
+        };
+
+        let mut expected = vec![compute_document_id("1"), compute_document_id("2")];
+        expected.sort();
+        assert_eq!(search(5_000.0), expected);
+        assert_eq!(search(500_000.0).len(), 3);
+
+        // the documents are sorted by distance, the ones without location are the last ones
+        let geo_index = index.main.geo_index(&reader).unwrap().unwrap();
+        let london = GeoPoint::new(51.5007, -0.1246).unwrap();
+        let criteria = CriteriaBuilder::new()
+            .add(criterion::SortByDistance::new(&geo_index, london))
+            .add(criterion::DocumentId)
+            .build();
+        let documents = index
+            .query_builder_with_criteria(criteria)
+            .query(&reader, "museum", 0..20)
+            .unwrap();
+        let ids: Vec<_> = documents.into_iter().map(|d| d.id).collect();
+        assert_eq!(ids[0], compute_document_id("3"));
+        assert_eq!(ids[3], compute_document_id("4"));
+        drop(reader);
+
+        // the partial updates keep the location and the deletions remove it
+        let mut partial_additions = index.documents_partial_addition();
+        partial_additions
+            .update_document(serde_json::json!({ "id": 1, "name": "louvre palace museum" }));
+        let mut writer = env.write_txn().unwrap();
+        partial_additions.finalize(&mut writer).unwrap();
+        let mut deletion = index.documents_deletion();
+        deletion.delete_document_by_id(compute_document_id("2"));
+        let update_id = deletion.finalize(&mut writer).unwrap();
+        writer.commit().unwrap();
+        assert!(index.wait_for_update(update_id, Duration::from_secs(10)));
+
+        let reader = env.read_txn().unwrap();
+        let geo_index = index.main.geo_index(&reader).unwrap().unwrap();
+        assert!(geo_index.get(compute_document_id("1")).is_some());
+        assert!(geo_index.get(compute_document_id("2")).is_none());
+        assert_eq!(geo_index.len(), 2);
+    }
+
+    #[test]
+    fn cold_tier() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let database = Database::open_or_create(dir.path()).unwrap();
+        let env = &database.env;
+        let index = database.create_index("test").unwrap();
+
+        let schema = {
+            let data = r#"
+                identifier = "id"
+
+                [attributes."title"]
+                displayed = true
+                indexed = true
+            "#;
+            toml::from_str(data).unwrap()
+        };
+
+        let mut writer = env.write_txn().unwrap();
+        index.schema_update(&mut writer, schema).unwrap();
+        writer.commit().unwrap();
+
+        let mut additions = index.documents_addition();
+        additions.update_document(serde_json::json!({ "id": 1, "title": "dune" }));
+        additions.update_document(serde_json::json!({ "id": 2, "title": "foundation" }));
+        additions.update_document(serde_json::json!({ "id": 3, "title": "hyperion" }));
+
+        let mut writer = env.write_txn().unwrap();
+        let update_id = additions.finalize(&mut writer).unwrap();
+        writer.commit().unwrap();
+        assert!(index.wait_for_update(update_id, Duration::from_secs(10)));
+
+        let now = chrono::Utc::now().timestamp() as u64;
+        let settings = ColdTierSettings { idle_secs: 3600 };
+
+        // nothing is written while the cold tier is disabled
+        let mut writer = env.write_txn().unwrap();
+        let report = index.tier_documents(&mut writer, now - 7200).unwrap();
+        assert_eq!(report, TieringReport::default());
+        let last_access = index
+            .documents_fields
+            .last_access(&writer, compute_document_id("1"))
+            .unwrap();
+        assert_eq!(last_access, None);
+        writer.commit().unwrap();
+
+        // the documents are first seen two hours ago and never retrieved since
+        let mut writer = env.write_txn().unwrap();
+        index.main.put_cold_tier(&mut writer, &settings).unwrap();
+        let report = index.tier_documents(&mut writer, now - 7200).unwrap();
+        assert_eq!(report, TieringReport::default());
+        let report = index.tier_documents(&mut writer, now).unwrap();
+        assert_eq!(report.moved_to_cold, 3);
+        writer.commit().unwrap();
+
+        let reader = env.read_txn().unwrap();
+        let stats = index.tier_stats(&reader).unwrap();
+        assert_eq!(stats.hot_documents, 0);
+        assert_eq!(stats.cold_documents, 3);
+        assert!(stats.cold_size > 0);
+
+        // the cold documents are searchable and retrievable
+        let documents = index.query_builder().query(&reader, "dune", 0..20).unwrap();
+        assert_eq!(documents.len(), 1);
+
+        let document: Option<serde_json::Value> = index
+            .document(&reader, None, compute_document_id("1"))
+            .unwrap();
+        assert_eq!(
+            document,
+            Some(serde_json::json!({ "id": 1, "title": "dune" }))
+        );
+        drop(reader);
+
+        // the retrieved document is moved back to the hot tier
+        let mut writer = env.write_txn().unwrap();
+        let report = index.tier_documents(&mut writer, now + 60).unwrap();
+        assert_eq!(report.moved_to_hot, 1);
+        assert_eq!(report.moved_to_cold, 0);
+        writer.commit().unwrap();
+
+        let reader = env.read_txn().unwrap();
+        assert!(!index
+            .documents_fields
+            .is_cold(&reader, compute_document_id("1"))
+            .unwrap());
+        assert_eq!(index.tier_stats(&reader).unwrap().cold_documents, 2);
+        drop(reader);
+
+        // the cold documents are deleted like the hot ones
+        let mut deletion = index.documents_deletion();
+        deletion.delete_document_by_id(compute_document_id("2"));
+        let mut writer = env.write_txn().unwrap();
+        let update_id = deletion.finalize(&mut writer).unwrap();
+        writer.commit().unwrap();
+        assert!(index.wait_for_update(update_id, Duration::from_secs(10)));
+
+        let reader = env.read_txn().unwrap();
+        let stats = index.tier_stats(&reader).unwrap();
+        assert_eq!(stats.hot_documents, 1);
+        assert_eq!(stats.cold_documents, 1);
+        drop(reader);
+
+        // disabling the cold tier moves the documents back to the hot tier
+        let mut writer = env.write_txn().unwrap();
+        index.main.delete_cold_tier(&mut writer).unwrap();
+        let report = index.tier_documents(&mut writer, now + 120).unwrap();
+        assert_eq!(report.moved_to_hot, 1);
+        writer.commit().unwrap();
+    }
+
+    #[test]
+    fn criteria_settings() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let database = Database::open_or_create(dir.path()).unwrap();
+        let env = &database.env;
+        let index = database.create_index("test").unwrap();
+
+        let schema = {
+            let data = r#"
+                identifier = "id"
+
+                [attributes."name"]
+                displayed = true
+                indexed = true
+
+                [attributes."release_date"]
+                displayed = true
+                ranked = true
+            "#;
+            toml::from_str(data).unwrap()
+        };
+
+        let mut writer = env.write_txn().unwrap();
+        index.schema_update(&mut writer, schema).unwrap();
+        writer.commit().unwrap();
+
+        let mut additions = index.documents_addition();
+        additions
+            .update_document(serde_json::json!({ "id": 1, "name": "alien", "release_date": 1979 }));
+        additions.update_document(
+            serde_json::json!({ "id": 2, "name": "aliens", "release_date": 1986 }),
+        );
+        additions.update_document(
+            serde_json::json!({ "id": 3, "name": "alien resurrection", "release_date": 1997 }),
+        );
+
+        let mut writer = env.write_txn().unwrap();
+        let update_id = additions.finalize(&mut writer).unwrap();
+        writer.commit().unwrap();
+        assert!(index.wait_for_update(update_id, Duration::from_secs(10)));
+
+        let ids = |reader: &heed::RoTxn| -> Vec<DocumentId> {
+            let documents = index.query_builder().query(reader, "alien", 0..20).unwrap();
+            documents.into_iter().map(|d| d.id).collect()
+        };
+
+        // the exact matches come first with the default criteria
+        let reader = env.read_txn().unwrap();
+        let default_ids = ids(&reader);
+        assert_ne!(default_ids[0], compute_document_id("2"));
+        drop(reader);
+
+        // the release date comes before the typos and the exactness
+        let rules: Vec<RankingRule> = ["dsc(release_date)", "typo", "words", "exact"]
+            .iter()
+            .map(|r| r.parse().unwrap())
+            .collect();
+        let mut writer = env.write_txn().unwrap();
+        index.main.put_criteria(&mut writer, &rules).unwrap();
+        writer.commit().unwrap();
+
+        let reader = env.read_txn().unwrap();
+        assert_eq!(
+            ids(&reader),
+            vec![
+                compute_document_id("3"),
+                compute_document_id("2"),
+                compute_document_id("1"),
+            ]
+        );
+
+        // the rules on attributes that are not ranked are ignored
+        drop(reader);
+        let mut writer = env.write_txn().unwrap();
+        let rules = vec![RankingRule::Asc("name".to_string())];
+        index.main.put_criteria(&mut writer, &rules).unwrap();
+        writer.commit().unwrap();
+
+        let reader = env.read_txn().unwrap();
+        assert_eq!(ids(&reader).len(), 3);
+
+        // the given criteria take precedence over the settings
+        let ranked_map = index.main.ranked_map(&reader).unwrap().unwrap();
+        let schema = index.main.schema(&reader).unwrap().unwrap();
+        let release_date =
+            criterion::SortByAttr::lower_is_better(&ranked_map, &schema, "release_date").unwrap();
+        let criteria = CriteriaBuilder::new().add(release_date).build();
+        let documents = index
+            .query_builder_with_criteria(criteria)
+            .query(&reader, "alien", 0..20)
+            .unwrap();
+        assert_eq!(documents[0].id, compute_document_id("1"));
+    }
+
+    #[test]
+    fn replay_update() {
+        use crate::store::UPDATES_REPLAYS_LEN;
+        use crate::update::UpdateType;
+
+        let dir = tempfile::tempdir().unwrap();
+
+        let database = Database::open_or_create(dir.path()).unwrap();
+        database.set_updates_replays(true);
+        let env = &database.env;
+        let index = database.create_index("test").unwrap();
+
+        let schema = {
+            let data = r#"
+                identifier = "id"
+
+                [attributes."name"]
+                displayed = true
+                indexed = true
+            "#;
+            toml::from_str(data).unwrap()
+        };
+
+        let mut writer = env.write_txn().unwrap();
+        index.schema_update(&mut writer, schema).unwrap();
+        writer.commit().unwrap();
+
+        let mut additions = index.documents_addition();
+        additions.update_document(serde_json::json!({ "id": 1, "name": "marvin" }));
+        additions.update_document(serde_json::json!({ "id": 2, "name": "kevin" }));
+
+        let mut writer = env.write_txn().unwrap();
+        let update_id = additions.finalize(&mut writer).unwrap();
+        writer.commit().unwrap();
+        assert!(index.wait_for_update(update_id, Duration::from_secs(10)));
+
+        let reader = env.read_txn().unwrap();
+        let replay = index.updates_replays.replay(&reader, update_id).unwrap();
+        assert!(replay.unwrap().documents.is_empty());
+
+        // the update is applied again with the same result
+        let result = index.replay_update(&reader, update_id).unwrap().unwrap();
+        assert_eq!(result.update_id, update_id);
+        assert!(result.error.is_none());
+        match result.update_type {
+            UpdateType::DocumentsAddition { number } => assert_eq!(number, 2),
+            other => panic!("unexpected update type {:?}", other),
+        }
+
+        // the replay does not modify the index
+        assert_eq!(index.main.number_of_documents(&reader).unwrap(), 2);
+        assert!(index
+            .replay_update(&reader, update_id + 1)
+            .unwrap()
+            .is_none());
+        drop(reader);
+
+        // the replay is applied on the documents indexed before the update
+        let mut additions = index.documents_addition();
+        additions.duplicates_policy(DuplicatesPolicy::Report);
+        additions.identifiers_verification(IdentifiersVerification::Indexed);
+        additions.update_document(serde_json::json!({ "id": 1, "name": "marvin the second" }));
+        additions.update_document(serde_json::json!({ "id": 3, "name": "kevin" }));
+
+        let mut writer = env.write_txn().unwrap();
+        let update_id = additions.finalize(&mut writer).unwrap();
+        writer.commit().unwrap();
+        assert!(index.wait_for_update(update_id, Duration::from_secs(10)));
+
+        let reader = env.read_txn().unwrap();
+        let replay = index.updates_replays.replay(&reader, update_id).unwrap();
+        assert_eq!(replay.unwrap().documents.len(), 2);
+
+        let status = index.update_status(&reader, update_id).unwrap();
+        let processed = match status {
+            Some(UpdateStatus::Processed { content }) => content,
+            other => panic!("unexpected update status {:?}", other),
+        };
+        assert_eq!(processed.collisions.len(), 1);
+        assert_eq!(processed.duplicates.len(), 1);
+
+        let result = index.replay_update(&reader, update_id).unwrap().unwrap();
+        assert_eq!(result.collisions, processed.collisions);
+        assert_eq!(result.duplicates, processed.duplicates);
+        drop(reader);
+
+        // the deleted documents are replayed as they were before the deletion
+        let mut deletion = index.documents_deletion();
+        deletion.delete_document_by_id(compute_document_id("2"));
+        let mut writer = env.write_txn().unwrap();
+        let update_id = deletion.finalize(&mut writer).unwrap();
+        writer.commit().unwrap();
+        assert!(index.wait_for_update(update_id, Duration::from_secs(10)));
+
+        let reader = env.read_txn().unwrap();
+        let replay = index.updates_replays.replay(&reader, update_id).unwrap();
+        let documents = replay.unwrap().documents;
+        assert_eq!(documents.len(), 1);
+        assert_eq!(documents[0]["name"], "kevin");
+        assert!(index
+            .replay_update(&reader, update_id)
+            .unwrap()
+            .unwrap()
+            .error
+            .is_none());
+        drop(reader);
+
+        // only the last updates can be replayed
+        let mut last_update_id = update_id;
+        for i in 0..UPDATES_REPLAYS_LEN {
+            let mut deletion = index.documents_deletion();
+            deletion.delete_document_by_id(compute_document_id(&i.to_string()));
+            let mut writer = env.write_txn().unwrap();
+            last_update_id = deletion.finalize(&mut writer).unwrap();
+            writer.commit().unwrap();
+        }
+        assert!(index.wait_for_update(last_update_id, Duration::from_secs(10)));
+
+        let reader = env.read_txn().unwrap();
+        assert!(index.replay_update(&reader, update_id).unwrap().is_none());
+        let result = index
+            .replay_update(&reader, last_update_id)
+            .unwrap()
+            .unwrap();
+        assert!(result.error.is_none());
+    }
+
+    #[test]
+    fn replay_update_invalid_identifier() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let database = Database::open_or_create(dir.path()).unwrap();
+        database.set_updates_replays(true);
+        let env = &database.env;
+        let index = database.create_index("test").unwrap();
+
+        let schema = {
+            let data = r#"
+                identifier = "id"
+
+                [attributes."name"]
+                displayed = true
+                indexed = true
+            "#;
+            toml::from_str(data).unwrap()
+        };
+
+        let mut writer = env.write_txn().unwrap();
+        index.schema_update(&mut writer, schema).unwrap();
+        writer.commit().unwrap();
+
+        let mut additions = index.documents_addition();
+        additions.update_document(serde_json::json!({ "id": 1.5, "name": "marvin" }));
+
+        let mut writer = env.write_txn().unwrap();
+        let update_id = additions.finalize(&mut writer).unwrap();
+        writer.commit().unwrap();
+        assert!(index.wait_for_update(update_id, Duration::from_secs(10)));
+
+        // the invalid identifier does not prevent the update from being processed
+        let reader = env.read_txn().unwrap();
+        let result = index.update_status(&reader, update_id).unwrap();
+        assert_matches!(result, Some(UpdateStatus::Processed { content }) if content.error.is_some());
+    }
+
+    #[test]
+    fn delete_by_filter() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let database = Database::open_or_create(dir.path()).unwrap();
+        let env = &database.env;
+        let index = database.create_index("test").unwrap();
+
+        let schema = {
+            let data = r#"
+                identifier = "id"
+
+                [attributes."name"]
+                displayed = true
+                indexed = true
+
+                [attributes."genre"]
+                displayed = true
+            "#;
+            toml::from_str(data).unwrap()
+        };
+
+        let mut writer = env.write_txn().unwrap();
+        let _update_id = index.schema_update(&mut writer, schema).unwrap();
+        writer.commit().unwrap();
+
+        let mut additions = index.documents_addition();
+        additions
+            .update_document(serde_json::json!({ "id": 1, "name": "alien", "genre": "horror" }));
+        additions.update_document(serde_json::json!({ "id": 2, "name": "heat", "genre": "crime" }));
+        additions.update_document(
+            serde_json::json!({ "id": 3, "name": "the thing", "genre": "horror" }),
+        );
+
+        let mut writer = env.write_txn().unwrap();
+        let update_id = additions.finalize(&mut writer).unwrap();
+        writer.commit().unwrap();
+        assert!(index.wait_for_update(update_id, Duration::from_secs(10)));
+
+        let filter = r#"genre = "horror""#.parse().unwrap();
+        let mut deletion = index.documents_deletion();
+        let mut writer = env.write_txn().unwrap();
+        let mut matching = deletion.delete_by_filter(&writer, &filter).unwrap();
+        let update_id = deletion.finalize(&mut writer).unwrap();
+        writer.commit().unwrap();
+        assert!(index.wait_for_update(update_id, Duration::from_secs(10)));
+
+        matching.sort_unstable();
+        let mut expected = vec![compute_document_id("1"), compute_document_id("3")];
+        expected.sort_unstable();
+        assert_eq!(matching, expected);
+
+        let reader = env.read_txn().unwrap();
+        assert_eq!(index.main.number_of_documents(&reader).unwrap(), 1);
+        let ids: Vec<_> = index
+            .documents_fields_counts
+            .documents_ids(&reader)
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(ids, vec![compute_document_id("2")]);
+
+        // the attributes that are not in the schema are rejected
+        let filter = "rating > 3".parse().unwrap();
+        let mut deletion = index.documents_deletion();
+        assert!(deletion.delete_by_filter(&reader, &filter).is_err());
+    }
+
+    #[test]
+    fn hot_backup_during_writes() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let database = Database::open_or_create(dir.path().join("database")).unwrap();
+        let env = &database.env;
+        let index = database.create_index("test").unwrap();
+
+        let schema = {
+            let data = r#"
+                identifier = "id"
+
+                [attributes."name"]
+                displayed = true
+                indexed = true
+            "#;
+            toml::from_str(data).unwrap()
+        };
+
+        let mut writer = env.write_txn().unwrap();
+        let update_id = index.schema_update(&mut writer, schema).unwrap();
+        writer.commit().unwrap();
+        assert!(index.wait_for_update(update_id, Duration::from_secs(10)));
+
+        // enqueues documents additions while the backups are taken
+        let writer_env = env.clone();
+        let writer_index = index.clone();
+        let handle = thread::spawn(move || {
+            let mut last_update_id = 0;
+            for batch in 0..20 {
+                let mut additions = writer_index.documents_addition();
+                for i in 0..100 {
+                    let id = batch * 100 + i;
+                    let name = format!("document number {}", id);
+                    additions.update_document(serde_json::json!({ "id": id, "name": name }));
+                }
+
+                let mut writer = writer_env.write_txn().unwrap();
+                last_update_id = additions.finalize(&mut writer).unwrap();
+                writer.commit().unwrap();
+            }
+            last_update_id
+        });
+
+        let mut backups = Vec::new();
+        for i in 0..3 {
+            let path = dir.path().join(format!("backup-{}", i));
+            database.hot_backup(&path).unwrap();
+            assert!(!path.join("data.mdb.tmp").exists());
+            backups.push(path);
+        }
+
+        let last_update_id = handle.join().unwrap();
+        assert!(index.wait_for_update(last_update_id, Duration::from_secs(60)));
+
+        for path in backups {
+            let backup = Database::open_or_create(&path).unwrap();
+            assert!(backup.startup_report().is_consistent());
+
+            let index = backup.open_index("test").unwrap();
+            let reader = backup.env.read_txn().unwrap();
+            assert!(index.main.schema(&reader).unwrap().is_some());
+
+            let documents_ids = index
+                .documents_fields_counts
+                .documents_ids(&reader)
+                .unwrap()
+                .count();
+            let number_of_documents = index.main.number_of_documents(&reader).unwrap();
+            assert_eq!(documents_ids as u64, number_of_documents);
+        }
+    }
+
+    #[test]
+    fn clear_all_documents() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let database = Database::open_or_create(dir.path()).unwrap();
+        let env = &database.env;
+        let index = database.create_index("test").unwrap();
+
+        let schema = {
+            let data = r#"
+                identifier = "id"
+
+                [attributes."name"]
+                displayed = true
+                indexed = true
+
+                [attributes."year"]
+                displayed = true
+                ranked = true
+            "#;
+            toml::from_str(data).unwrap()
+        };
+
+        let mut writer = env.write_txn().unwrap();
+        let _update_id = index.schema_update(&mut writer, schema).unwrap();
+        writer.commit().unwrap();
+
+        let mut additions = index.documents_addition();
+        additions.update_document(serde_json::json!({ "id": 1, "name": "alien", "year": 1979 }));
+        additions.update_document(serde_json::json!({ "id": 2, "name": "heat", "year": 1995 }));
+
+        let mut writer = env.write_txn().unwrap();
+        let update_id = additions.finalize(&mut writer).unwrap();
+        writer.commit().unwrap();
+        assert!(index.wait_for_update(update_id, Duration::from_secs(10)));
+
+        let mut writer = env.write_txn().unwrap();
+        let update_id = index.clear_all(&mut writer).unwrap();
+        writer.commit().unwrap();
+        assert!(index.wait_for_update(update_id, Duration::from_secs(10)));
+
+        let reader = env.read_txn().unwrap();
+        assert_eq!(index.main.number_of_documents(&reader).unwrap(), 0);
+        assert_eq!(
+            index.main.words_fst(&reader).unwrap().map(|fst| fst.len()),
+            Some(0)
         );
-        assert_matches!(iter.next(), None);
+        assert!(index.main.ranked_map(&reader).unwrap().unwrap().is_empty());
+        assert_eq!(
+            index
+                .documents_fields_counts
+                .documents_ids(&reader)
+                .unwrap()
+                .count(),
+            0
+        );
+        let docs_words = index
+            .docs_words
+            .doc_words(&reader, compute_document_id("1"));
+        assert!(docs_words.unwrap().is_none());
+
+        let documents = index
+            .query_builder()
+            .query(&reader, "alien", 0..20)
+            .unwrap();
+        assert!(documents.is_empty());
+
+        // the schema is kept, the index can be filled again
+        assert!(index.main.schema(&reader).unwrap().is_some());
+    }
+
+    #[test]
+    fn boolean_attributes() {
+        use crate::serde::{AttributeType, AttributesTypes};
+
+        let dir = tempfile::tempdir().unwrap();
+
+        let database = Database::open_or_create(dir.path()).unwrap();
+        let env = &database.env;
+        let index = database.create_index("test").unwrap();
+
+        let schema = {
+            let data = r#"
+                identifier = "id"
+
+                [attributes."title"]
+                displayed = true
+                indexed = true
+
+                [attributes."is_published"]
+                displayed = true
+                ranked = true
+            "#;
+            toml::from_str(data).unwrap()
+        };
+
+        let mut attributes_types = AttributesTypes::new();
+        attributes_types.insert("is_published".to_string(), AttributeType::Boolean);
+
+        let mut writer = env.write_txn().unwrap();
+        index.schema_update(&mut writer, schema).unwrap();
+        index
+            .attributes_types_update(&mut writer, attributes_types)
+            .unwrap();
+        writer.commit().unwrap();
+
+        let mut additions = index.documents_addition();
+        additions.update_document(
+            serde_json::json!({ "id": 1, "title": "night shift", "is_published": true }),
+        );
+        additions.update_document(
+            serde_json::json!({ "id": 2, "title": "night train", "is_published": "yes" }),
+        );
+        additions.update_document(
+            serde_json::json!({ "id": 3, "title": "night music", "is_published": false }),
+        );
+        additions.update_document(serde_json::json!({ "id": 4, "title": "night owl" }));
+
+        let mut writer = env.write_txn().unwrap();
+        let update_id = additions.finalize(&mut writer).unwrap();
+        writer.commit().unwrap();
+        assert!(index.wait_for_update(update_id, Duration::from_secs(10)));
+
+        let reader = env.read_txn().unwrap();
+
+        // the booleans are not sortable, they are not stored in the ranked map
+        let schema = index.main.schema(&reader).unwrap().unwrap();
+        let is_published = schema.attribute("is_published").unwrap();
+        let ranked_map = index.main.ranked_map(&reader).unwrap().unwrap();
+        assert_eq!(ranked_map.attribute_len(is_published), 0);
+
+        let search = |filter: &str| -> Vec<DocumentId> {
+            let mut builder = index.query_builder();
+            builder.with_filter_expression(filter.parse().unwrap());
+            let documents = builder.query(&reader, "night", 0..20).unwrap();
+            let mut ids: Vec<_> = documents.into_iter().map(|d| d.id).collect();
+            ids.sort();
+            ids
+        };
+
+        let mut expected = vec![compute_document_id("1"), compute_document_id("2")];
+        expected.sort();
+        assert_eq!(search("is_published = true"), expected);
+        assert_eq!(search("is_published = YES"), expected);
+
+        assert_eq!(
+            search("is_published = false"),
+            vec![compute_document_id("3")]
+        );
+
+        let mut expected = vec![compute_document_id("3"), compute_document_id("4")];
+        expected.sort();
+        assert_eq!(search("is_published != true"), expected);
+    }
+
+    #[test]
+    fn aggregated_attributes() {
+        use crate::serde::{Aggregation, AttributeType, AttributesTypes};
+
+        let dir = tempfile::tempdir().unwrap();
+
+        let database = Database::open_or_create(dir.path()).unwrap();
+        let env = &database.env;
+        let index = database.create_index("test").unwrap();
+
+        let schema = {
+            let data = r#"
+                identifier = "id"
+
+                [attributes."title"]
+                displayed = true
+                indexed = true
+
+                [attributes."ratings"]
+                displayed = true
+                ranked = true
+            "#;
+            toml::from_str(data).unwrap()
+        };
+
+        let mut attributes_types = AttributesTypes::new();
+        attributes_types.insert(
+            "ratings".to_string(),
+            AttributeType::Aggregated(Aggregation::Avg),
+        );
+
+        let mut writer = env.write_txn().unwrap();
+        index.schema_update(&mut writer, schema).unwrap();
+        index
+            .attributes_types_update(&mut writer, attributes_types)
+            .unwrap();
+        writer.commit().unwrap();
+
+        let mut additions = index.documents_addition();
+        additions.update_document(
+            serde_json::json!({ "id": 1, "title": "night shift", "ratings": [2, 3, 4] }),
+        );
+        additions.update_document(
+            serde_json::json!({ "id": 2, "title": "night train", "ratings": [5, "4"] }),
+        );
+        additions
+            .update_document(serde_json::json!({ "id": 3, "title": "night owl", "ratings": 1 }));
+        additions
+            .update_document(serde_json::json!({ "id": 4, "title": "night music", "ratings": [] }));
+
+        let mut writer = env.write_txn().unwrap();
+        let update_id = additions.finalize(&mut writer).unwrap();
+        writer.commit().unwrap();
+        assert!(index.wait_for_update(update_id, Duration::from_secs(10)));
+
+        let reader = env.read_txn().unwrap();
+        let result = index.update_status(&reader, update_id).unwrap();
+        assert_matches!(result, Some(UpdateStatus::Processed { content }) if content.error.is_none());
+
+        let schema = index.main.schema(&reader).unwrap().unwrap();
+        let ratings = schema.attribute("ratings").unwrap();
+        let ranked_map = index.main.ranked_map(&reader).unwrap().unwrap();
+
+        let average = |n| Some(crate::Number::Float(ordered_float::OrderedFloat(n)));
+        assert_eq!(
+            ranked_map.get(compute_document_id("1"), ratings),
+            average(3.0)
+        );
+        assert_eq!(
+            ranked_map.get(compute_document_id("2"), ratings),
+            average(4.5)
+        );
+        assert_eq!(
+            ranked_map.get(compute_document_id("3"), ratings),
+            Some(crate::Number::Unsigned(1))
+        );
+        assert_eq!(ranked_map.get(compute_document_id("4"), ratings), None);
+
+        // the aggregated values are sorted on
+        let criteria = CriteriaBuilder::new()
+            .add(criterion::SortByAttr::higher_is_better(&ranked_map, &schema, "ratings").unwrap())
+            .add(criterion::DocumentId)
+            .build();
+
+        let builder = index.query_builder_with_criteria(criteria);
+        let documents = builder.query(&reader, "night", 0..20).unwrap();
+        let ids: Vec<_> = documents.into_iter().map(|d| d.id).collect();
+        let expected = vec![
+            compute_document_id("2"),
+            compute_document_id("1"),
+            compute_document_id("3"),
+            compute_document_id("4"),
+        ];
+        assert_eq!(ids, expected);
+
+        // and filtered on
+        let mut builder = index.query_builder();
+        builder.with_filter_expression("ratings > 2.5".parse().unwrap());
+        let documents = builder.query(&reader, "night", 0..20).unwrap();
+        let mut ids: Vec<_> = documents.into_iter().map(|d| d.id).collect();
+        ids.sort();
+        let mut expected = vec![compute_document_id("1"), compute_document_id("2")];
+        expected.sort();
+        assert_eq!(ids, expected);
+    }
+
+    #[test]
+    fn nothing_searchable() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let database = Database::open_or_create(dir.path()).unwrap();
+        let env = &database.env;
+        let index = database.create_index("test").unwrap();
+
+        let schema: meilidb_schema::Schema = {
+            let data = r#"
+                identifier = "id"
+
+                [attributes."id"]
+                displayed = true
+
+                [attributes."title"]
+                displayed = true
+            "#;
+            toml::from_str(data).unwrap()
+        };
+
+        // a schema without indexed attributes is refused
+        let mut writer = env.write_txn().unwrap();
+        let update_id = index.schema_update(&mut writer, schema.clone()).unwrap();
+        writer.commit().unwrap();
+        assert!(index.wait_for_update(update_id, Duration::from_secs(10)));
+
+        let reader = env.read_txn().unwrap();
+        let result = index.update_status(&reader, update_id).unwrap();
+        assert_matches!(result, Some(UpdateStatus::Processed { content }) => {
+            let error = content.error.unwrap();
+            assert!(error.contains("nothing can be searched"), "{}", error);
+            assert!(error.contains("id, title"), "{}", error);
+        });
+        assert!(index.main.schema(&reader).unwrap().is_none());
+        reader.abort();
+
+        // the indexes that already have such a schema refuse the queries
+        let mut writer = env.write_txn().unwrap();
+        index.main.put_schema(&mut writer, &schema).unwrap();
+        writer.commit().unwrap();
+
+        let reader = env.read_txn().unwrap();
+        let result = index.query_builder().query(&reader, "hello", 0..20);
+        assert_matches!(result, Err(Error::NothingSearchable(attributes)) => {
+            assert_eq!(attributes, vec!["id".to_string(), "title".to_string()]);
+        });
+        reader.abort();
+
+        let schema = {
+            let data = r#"
+                identifier = "id"
+
+                [attributes."id"]
+                displayed = true
+
+                [attributes."title"]
+                displayed = true
+                indexed = true
+            "#;
+            toml::from_str(data).unwrap()
+        };
+
+        let mut writer = env.write_txn().unwrap();
+        let update_id = index.schema_update(&mut writer, schema).unwrap();
+        writer.commit().unwrap();
+        assert!(index.wait_for_update(update_id, Duration::from_secs(10)));
+
+        let reader = env.read_txn().unwrap();
+        let result = index.update_status(&reader, update_id).unwrap();
+        assert_matches!(result, Some(UpdateStatus::Processed { content }) if content.error.is_none());
+        assert!(index.query_builder().query(&reader, "hello", 0..20).is_ok());
+        reader.abort();
+
+        // the searchable attributes must keep an indexed attribute
+        let mut writer = env.write_txn().unwrap();
+        let update_id = index
+            .searchable_attributes_update(&mut writer, Some(vec!["id".to_string()]))
+            .unwrap();
+        writer.commit().unwrap();
+        assert!(index.wait_for_update(update_id, Duration::from_secs(10)));
+
+        let reader = env.read_txn().unwrap();
+        let result = index.update_status(&reader, update_id).unwrap();
+        assert_matches!(result, Some(UpdateStatus::Processed { content }) if content.error.is_some());
+        assert_eq!(index.main.searchable_attributes(&reader).unwrap(), None);
     }
 }
@@ -0,0 +1,106 @@
+use std::collections::VecDeque;
+
+/// Reorders the documents to avoid consecutive ones sharing the same key,
+/// when a document shares the key of the previous one, the first of the
+/// following `strength` documents with a different key is promoted instead.
+///
+/// The documents without key are never considered as repeating the
+/// previous one and the relative order of the documents is otherwise kept.
+///
+/// # Note
+///
+/// The first `n` reordered documents only depend on the
+/// first `n + strength` documents given to this function.
+pub fn diversify<T, K, F>(documents: Vec<T>, key: F, strength: usize) -> Vec<T>
+where
+    F: Fn(&T) -> Option<K>,
+    K: PartialEq,
+{
+    if strength == 0 {
+        return documents;
+    }
+
+    let mut output = Vec::with_capacity(documents.len());
+    let mut remaining = VecDeque::from(documents);
+    let mut last_key = None;
+
+    while !remaining.is_empty() {
+        let position = match &last_key {
+            Some(last_key) => remaining
+                .iter()
+                .take(strength.saturating_add(1))
+                .position(|document| key(document).as_ref() != Some(last_key))
+                .unwrap_or(0),
+            None => 0,
+        };
+
+        // the position comes from the remaining documents
+        if let Some(document) = remaining.remove(position) {
+            last_key = key(&document);
+            output.push(document);
+        }
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn brand(document: &(u32, Option<char>)) -> Option<char> {
+        document.1
+    }
+
+    #[test]
+    fn no_strength() {
+        let documents = vec![(0, Some('a')), (1, Some('a')), (2, Some('b'))];
+        assert_eq!(diversify(documents.clone(), brand, 0), documents);
+    }
+
+    #[test]
+    fn promote_different_keys() {
+        let documents = vec![
+            (0, Some('a')),
+            (1, Some('a')),
+            (2, Some('a')),
+            (3, Some('b')),
+            (4, Some('b')),
+            (5, Some('c')),
+        ];
+
+        let ids: Vec<_> = diversify(documents.clone(), brand, 1)
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect();
+        assert_eq!(ids, vec![0, 1, 3, 2, 4, 5]);
+
+        let ids: Vec<_> = diversify(documents, brand, 3)
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect();
+        assert_eq!(ids, vec![0, 3, 1, 4, 2, 5]);
+    }
+
+    #[test]
+    fn documents_without_key() {
+        let documents = vec![(0, None), (1, None), (2, Some('a')), (3, Some('a'))];
+
+        let ids: Vec<_> = diversify(documents, brand, 2)
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect();
+        assert_eq!(ids, vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn unbounded_strength() {
+        let documents = vec![(0, Some('a')), (1, Some('a')), (2, Some('b'))];
+
+        let ids: Vec<_> = diversify(documents, brand, usize::max_value())
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect();
+        assert_eq!(ids, vec![0, 2, 1]);
+    }
+}
@@ -0,0 +1,298 @@
+use std::collections::HashSet;
+use std::io;
+use std::ops::Range;
+use std::path::Path;
+use std::time::Duration;
+
+use meilidb_schema::Schema;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::serde::DocumentKey;
+use crate::{Database, DocumentId, Error, Index, MResult, ProcessedUpdateResult, UpdateStatus};
+
+const DEFAULT_UPDATE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// A database embedded in the process, the updates are blocking and return
+/// once they have been processed by the update loop of the index.
+///
+/// The updates that fail are not returned as errors, the reason of the failure
+/// is in the `error` field of the returned [`ProcessedUpdateResult`].
+pub struct SearchEngine {
+    database: Database,
+    update_timeout: Duration,
+}
+
+impl SearchEngine {
+    pub fn open(path: impl AsRef<Path>) -> MResult<SearchEngine> {
+        let database = Database::open_or_create(path)?;
+        Ok(SearchEngine {
+            database,
+            update_timeout: DEFAULT_UPDATE_TIMEOUT,
+        })
+    }
+
+    /// The maximum time waited for an update to be processed, one minute by default.
+    pub fn with_update_timeout(mut self, timeout: Duration) -> SearchEngine {
+        self.update_timeout = timeout;
+        self
+    }
+
+    /// The underlying database, for the operations this facade does not cover.
+    pub fn database(&self) -> &Database {
+        &self.database
+    }
+
+    pub fn indexes(&self) -> Vec<String> {
+        self.database.indexes_uids()
+    }
+
+    /// Creates an index and sets its schema, the index is deleted
+    /// if the schema could not be set.
+    pub fn create_index(&self, uid: &str, schema: Schema) -> MResult<ProcessedUpdateResult> {
+        let index = self.database.create_index(uid)?;
+
+        let result = self.set_schema(&index, schema);
+        let failed = match &result {
+            Ok(result) => result.error.is_some(),
+            Err(_) => true,
+        };
+
+        if failed {
+            self.database.delete_index(uid)?;
+        }
+
+        result
+    }
+
+    /// Returns `false` if there was no index with this uid.
+    pub fn delete_index(&self, uid: &str) -> MResult<bool> {
+        self.database.delete_index(uid)
+    }
+
+    /// Adds the documents or replaces the ones with the same identifiers.
+    pub fn add_documents<D, I>(&self, uid: &str, documents: I) -> MResult<ProcessedUpdateResult>
+    where
+        D: Serialize,
+        I: IntoIterator<Item = D>,
+    {
+        let index = self.index(uid)?;
+
+        let mut addition = index.documents_addition();
+        for document in documents {
+            addition.update_document(document);
+        }
+
+        let mut writer = self.database.env.write_txn()?;
+        let update_id = addition.finalize(&mut writer)?;
+        writer.commit()?;
+
+        self.wait_for_update(&index, update_id)
+    }
+
+    /// Deletes the documents by the values of their identifier attribute,
+    /// the integer `12` and the string `"12"` identify the same document.
+    pub fn delete_documents<K, I>(
+        &self,
+        uid: &str,
+        identifiers: I,
+    ) -> MResult<ProcessedUpdateResult>
+    where
+        K: Serialize,
+        I: IntoIterator<Item = K>,
+    {
+        let index = self.index(uid)?;
+
+        let mut deletion = index.documents_deletion();
+        for identifier in identifiers {
+            let document_id = document_id(identifier)?;
+            deletion.delete_document_by_id(document_id);
+        }
+
+        let mut writer = self.database.env.write_txn()?;
+        let update_id = deletion.finalize(&mut writer)?;
+        writer.commit()?;
+
+        self.wait_for_update(&index, update_id)
+    }
+
+    /// The documents matching the query in the given range of the results,
+    /// all of them are read with the same version of the schema.
+    pub fn search<D: DeserializeOwned>(
+        &self,
+        uid: &str,
+        query: &str,
+        range: Range<usize>,
+    ) -> MResult<Vec<D>> {
+        let index = self.index(uid)?;
+        let reader = self.database.env.read_txn()?;
+
+        let snapshot = index.schema_snapshot();
+        let documents_ids = index.query_builder().query(&reader, query, range)?;
+
+        let mut documents = Vec::with_capacity(documents_ids.len());
+        for document in documents_ids {
+            let fetched = index.document_with_snapshot(&reader, &snapshot, None, document.id)?;
+            if let Some(fetched) = fetched {
+                documents.push(fetched);
+            }
+        }
+
+        Ok(documents)
+    }
+
+    /// The document with the given identifier value, only the attributes
+    /// listed are returned when some are given.
+    pub fn document<D: DeserializeOwned, K: Serialize>(
+        &self,
+        uid: &str,
+        identifier: K,
+        attributes: Option<&HashSet<&str>>,
+    ) -> MResult<Option<D>> {
+        let index = self.index(uid)?;
+        let reader = self.database.env.read_txn()?;
+        let document_id = document_id(identifier)?;
+        index.document(&reader, attributes, document_id)
+    }
+
+    fn index(&self, uid: &str) -> MResult<Index> {
+        self.database.open_index(uid).ok_or(Error::IndexNotFound)
+    }
+
+    fn set_schema(&self, index: &Index, schema: Schema) -> MResult<ProcessedUpdateResult> {
+        let mut writer = self.database.env.write_txn()?;
+        let update_id = index.schema_update(&mut writer, schema)?;
+        writer.commit()?;
+
+        self.wait_for_update(index, update_id)
+    }
+
+    fn wait_for_update(&self, index: &Index, update_id: u64) -> MResult<ProcessedUpdateResult> {
+        if !index.wait_for_update(update_id, self.update_timeout) {
+            let message = format!("update {} has not been processed in time", update_id);
+            return Err(Error::Io(io::Error::new(io::ErrorKind::TimedOut, message)));
+        }
+
+        let reader = self.database.env.read_txn()?;
+        match index.update_status(&reader, update_id)? {
+            Some(UpdateStatus::Processed { content }) => Ok(content),
+            _ => {
+                let message = format!("update {} has no processed status", update_id);
+                Err(Error::Io(io::Error::new(io::ErrorKind::Other, message)))
+            }
+        }
+    }
+}
+
+/// Resolves an identifier value the same way the identifiers of the documents are.
+fn document_id<K: Serialize>(identifier: K) -> MResult<DocumentId> {
+    let value = serde_json::to_value(identifier)?;
+    let key = DocumentKey::from_value(&value)?;
+    Ok(key.document_id())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Movie {
+        id: u64,
+        name: String,
+    }
+
+    fn movies_schema() -> Schema {
+        let data = r#"
+            identifier = "id"
+
+            [attributes."id"]
+            displayed = true
+
+            [attributes."name"]
+            displayed = true
+            indexed = true
+        "#;
+        toml::from_str(data).unwrap()
+    }
+
+    #[test]
+    fn embedded_search() {
+        let dir = tempfile::tempdir().unwrap();
+        let engine = SearchEngine::open(dir.path()).unwrap();
+
+        let result = engine.create_index("movies", movies_schema()).unwrap();
+        assert!(result.error.is_none());
+        assert_eq!(engine.indexes(), vec!["movies".to_string()]);
+
+        let movies = vec![
+            Movie {
+                id: 1,
+                name: "Carol".into(),
+            },
+            Movie {
+                id: 2,
+                name: "Wonder Woman".into(),
+            },
+        ];
+        let result = engine.add_documents("movies", movies).unwrap();
+        assert!(result.error.is_none());
+
+        let found: Vec<Movie> = engine.search("movies", "carol", 0..20).unwrap();
+        assert_eq!(
+            found,
+            vec![Movie {
+                id: 1,
+                name: "Carol".into()
+            }]
+        );
+
+        // the integer and string identifiers resolve to the same document
+        let found: Option<Movie> = engine.document("movies", 1, None).unwrap();
+        assert!(found.is_some());
+
+        let result = engine.delete_documents("movies", &["1"]).unwrap();
+        assert!(result.error.is_none());
+
+        let found: Option<Movie> = engine.document("movies", "1", None).unwrap();
+        assert!(found.is_none());
+
+        let found: Vec<Movie> = engine.search("movies", "carol", 0..20).unwrap();
+        assert!(found.is_empty());
+
+        assert_matches!(
+            engine.search::<Movie>("unknown", "carol", 0..20),
+            Err(Error::IndexNotFound)
+        );
+
+        assert_matches!(
+            engine.document::<Movie, _>("movies", 1.5, None),
+            Err(Error::Serializer(_))
+        );
+    }
+
+    #[test]
+    fn index_deleted_on_schema_failure() {
+        let dir = tempfile::tempdir().unwrap();
+        let engine = SearchEngine::open(dir.path()).unwrap();
+
+        // none of the attributes is indexed, the schema is refused
+        let schema = {
+            let data = r#"
+                identifier = "id"
+
+                [attributes."name"]
+                displayed = true
+            "#;
+            toml::from_str(data).unwrap()
+        };
+
+        let result = engine.create_index("movies", schema);
+        let failed = match result {
+            Ok(result) => result.error.is_some(),
+            Err(_) => true,
+        };
+        assert!(failed);
+        assert!(engine.indexes().is_empty());
+    }
+}
@@ -1,3 +1,4 @@
+use crate::filters::FilterError;
 use crate::serde::{DeserializerError, SerializerError};
 use serde_json::Error as SerdeJsonError;
 use std::{error, fmt, io};
@@ -8,6 +9,8 @@ pub type MResult<T> = Result<T, Error>;
 pub enum Error {
     Io(io::Error),
     IndexAlreadyExists,
+    IndexNotFound,
+    ReadOnly,
     SchemaDiffer,
     SchemaMissing,
     WordIndexMissing,
@@ -19,6 +22,15 @@ pub enum Error {
     Serializer(SerializerError),
     Deserializer(DeserializerError),
     UnsupportedOperation(UnsupportedOperation),
+    LimitExceeded(LimitExceeded),
+    Filter(FilterError),
+    /// None of the attributes of the schema is both indexed and searchable,
+    /// the attributes of the schema are given.
+    NothingSearchable(Vec<String>),
+    /// The customs of a settings patch could not be merged into the current ones.
+    CustomsMerge(String),
+    /// The attributes of the customs could not be renamed by a migration.
+    CustomsRename(String),
 }
 
 impl From<io::Error> for Error {
@@ -69,12 +81,26 @@ impl From<UnsupportedOperation> for Error {
     }
 }
 
+impl From<LimitExceeded> for Error {
+    fn from(limit: LimitExceeded) -> Error {
+        Error::LimitExceeded(limit)
+    }
+}
+
+impl From<FilterError> for Error {
+    fn from(error: FilterError) -> Error {
+        Error::Filter(error)
+    }
+}
+
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         use self::Error::*;
         match self {
             Io(e) => write!(f, "{}", e),
             IndexAlreadyExists => write!(f, "index already exists"),
+            IndexNotFound => write!(f, "index not found"),
+            ReadOnly => write!(f, "the database is read-only"),
             SchemaDiffer => write!(f, "schemas differ"),
             SchemaMissing => write!(f, "this index does not have a schema"),
             WordIndexMissing => write!(f, "this index does not have a word index"),
@@ -86,6 +112,16 @@ impl fmt::Display for Error {
             Serializer(e) => write!(f, "serializer error; {}", e),
             Deserializer(e) => write!(f, "deserializer error; {}", e),
             UnsupportedOperation(op) => write!(f, "unsupported operation; {}", op),
+            LimitExceeded(limit) => write!(f, "limit exceeded; {}", limit),
+            Filter(e) => write!(f, "filter error; {}", e),
+            NothingSearchable(attributes) => write!(
+                f,
+                "nothing can be searched, none of the attributes is indexed and searchable; \
+                 the attributes are {}",
+                attributes.join(", ")
+            ),
+            CustomsMerge(e) => write!(f, "customs merge error; {}", e),
+            CustomsRename(e) => write!(f, "customs rename error; {}", e),
         }
     }
 }
@@ -99,6 +135,8 @@ pub enum UnsupportedOperation {
     CannotReorderSchemaAttribute,
     CanOnlyIntroduceNewSchemaAttributesAtEnd,
     CannotRemoveSchemaAttribute,
+    CannotMigrateToUnknownAttribute(String),
+    UnknownTokenFilter(String),
 }
 
 impl fmt::Display for UnsupportedOperation {
@@ -112,6 +150,80 @@ impl fmt::Display for UnsupportedOperation {
                 write!(f, "Can only introduce new attributes at end of a schema")
             }
             CannotRemoveSchemaAttribute => write!(f, "Cannot remove attributes from a schema"),
+            CannotMigrateToUnknownAttribute(name) => write!(
+                f,
+                "Cannot migrate the documents to the attribute {} which is not in the new schema",
+                name
+            ),
+            UnknownTokenFilter(name) => {
+                write!(
+                    f,
+                    "Cannot use the token filter {} which is not registered",
+                    name
+                )
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum LimitExceeded {
+    TooManyDocuments {
+        limit: usize,
+        found: usize,
+    },
+    PayloadTooLarge {
+        limit: usize,
+        found: usize,
+    },
+    FieldTooLarge {
+        limit: usize,
+        found: usize,
+        field: String,
+    },
+    TotalHitsExceeded {
+        limit: usize,
+        offset: usize,
+    },
+    TooManyAttributes {
+        limit: usize,
+        found: usize,
+    },
+}
+
+impl fmt::Display for LimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::LimitExceeded::*;
+        match self {
+            TooManyDocuments { limit, found } => write!(
+                f,
+                "The update contains {} documents, the index accepts at most {}",
+                found, limit
+            ),
+            PayloadTooLarge { limit, found } => write!(
+                f,
+                "The update weighs {} bytes, the index accepts at most {}",
+                found, limit
+            ),
+            FieldTooLarge {
+                limit,
+                found,
+                field,
+            } => write!(
+                f,
+                "The {} field weighs {} bytes, the index accepts at most {}",
+                field, found, limit
+            ),
+            TotalHitsExceeded { limit, offset } => write!(
+                f,
+                "The search starts at the hit {}, the index returns at most {} hits",
+                offset, limit
+            ),
+            TooManyAttributes { limit, found } => write!(
+                f,
+                "The schema would have {} attributes, the index accepts at most {}",
+                found, limit
+            ),
         }
     }
 }
@@ -0,0 +1,187 @@
+use std::cmp;
+
+use fst::{IntoStreamer, Streamer};
+use meilidb_schema::SchemaAttr;
+
+use crate::automaton::{AutomatonGroup, AutomatonOrigin, QueryRewrite, WordDfa};
+use crate::criterion::Criteria;
+use crate::reordered_attrs::ReorderedAttrs;
+use crate::{store, DocIndex, DocumentId, MResult, Number, RawDocument};
+
+/// The value a criterion compares a document with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CriterionScore {
+    pub name: String,
+    /// The criteria that do not expose the value they compare have none.
+    pub score: Option<Number>,
+}
+
+/// A word of a document matched by a word searched for the query.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MatchExplanation {
+    /// The word searched, it is a word of the query or a word derived from it.
+    pub query: String,
+    pub origin: AutomatonOrigin,
+    /// The word of the document.
+    pub word: String,
+    pub typos: u8,
+    /// The penalty of the synonym alternative the searched word comes from.
+    pub penalty: u8,
+    pub attribute: SchemaAttr,
+    pub word_index: u16,
+    pub is_exact: bool,
+}
+
+/// Why a document has been returned and ranked where it is.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Explanation {
+    /// The values of the criteria for the document, in the order of the criteria.
+    pub criteria: Vec<CriterionScore>,
+    /// The matches of the document ordered by attribute and word position, the
+    /// words of a phrase are listed wherever they appear in the document.
+    pub matches: Vec<MatchExplanation>,
+}
+
+/// Explains the ranking of the returned documents, the matches are found again
+/// by searching the words of the automatons in the postings of these documents.
+pub(crate) fn explain_documents(
+    reader: &heed::RoTxn,
+    criteria: &Criteria,
+    automatons_groups: &[AutomatonGroup],
+    searchables: Option<&ReorderedAttrs>,
+    main_store: store::Main,
+    postings_lists_store: store::PostingsLists,
+    documents: &[RawDocument],
+) -> MResult<Vec<Explanation>> {
+    let mut explanations: Vec<_> = documents
+        .iter()
+        .map(|document| {
+            let criteria = criteria.as_ref().iter().map(|criterion| CriterionScore {
+                name: criterion.name().to_string(),
+                score: criterion.score(document),
+            });
+
+            Explanation {
+                criteria: criteria.collect(),
+                matches: Vec::new(),
+            }
+        })
+        .collect();
+
+    let words = match main_store.words_fst(reader)? {
+        Some(words) if !documents.is_empty() => words,
+        _ => return Ok(explanations),
+    };
+    let typos = main_store.typos_table(reader)?.unwrap_or_default();
+    let frozen_words = main_store.frozen_words_fst(reader)?.unwrap_or_default();
+
+    let automatons = automatons_groups.iter().flat_map(|group| &group.automatons);
+    for automaton in automatons {
+        let dfa = automaton.dfa(&typos, &frozen_words);
+
+        let mut expansions = 0;
+        let mut stream = words.search(&dfa).into_stream();
+        while let Some(input) = stream.next() {
+            let (typos, is_exact) = match &dfa {
+                WordDfa::Levenshtein(dfa) => {
+                    let distance = dfa.eval(input).to_u8();
+                    if distance != 0 && frozen_words.contains(input) {
+                        continue;
+                    }
+                    let is_exact =
+                        automaton.is_exact && distance == 0 && input.len() == automaton.query_len;
+                    (distance, is_exact)
+                }
+                WordDfa::Pattern(pattern) => {
+                    if expansions == pattern.max_expansions {
+                        break;
+                    }
+                    expansions += 1;
+                    (0, pattern.is_exact_match(input))
+                }
+            };
+
+            let doc_indexes = match postings_lists_store.postings_list(reader, input)? {
+                Some(doc_indexes) => doc_indexes,
+                None => continue,
+            };
+            let word = String::from_utf8_lossy(input);
+
+            for (document, explanation) in documents.iter().zip(&mut explanations) {
+                let start = first_posting(&doc_indexes, document.id);
+                let iter = doc_indexes[start..]
+                    .iter()
+                    .take_while(|di| di.document_id == document.id)
+                    .filter(|di| searchables.map_or(true, |r| r.get(di.attribute).is_some()))
+                    .map(|di| MatchExplanation {
+                        query: automaton.query.clone(),
+                        origin: automaton.origin,
+                        word: word.to_string(),
+                        typos,
+                        penalty: automaton.penalty,
+                        attribute: SchemaAttr(di.attribute),
+                        word_index: di.word_index,
+                        is_exact: is_exact && automaton.penalty == 0,
+                    });
+
+                explanation.matches.extend(iter);
+            }
+        }
+    }
+
+    for explanation in &mut explanations {
+        explanation
+            .matches
+            .sort_by_key(|m| (m.attribute, m.word_index, m.typos + m.penalty));
+    }
+
+    Ok(explanations)
+}
+
+/// Lists the rewrites of the query words searched by the automatons, along with
+/// the words of the index matched with typos in the explained documents.
+pub(crate) fn query_rewrites(
+    rewrites: &[(Option<usize>, QueryRewrite)],
+    automatons_groups: &[AutomatonGroup],
+    explanations: &[Explanation],
+) -> Vec<QueryRewrite> {
+    let searched = |index: usize| {
+        automatons_groups
+            .iter()
+            .flat_map(|group| &group.automatons)
+            .any(|automaton| automaton.index == index)
+    };
+
+    let mut query_rewrites: Vec<_> = rewrites
+        .iter()
+        .filter(|(index, _)| index.map_or(true, searched))
+        .map(|(_, rewrite)| rewrite.clone())
+        .collect();
+
+    let matches = explanations.iter().flat_map(|e| &e.matches);
+    for m in matches.filter(|m| m.origin == AutomatonOrigin::Query && m.typos != 0) {
+        let rewrite = QueryRewrite::Typo {
+            query: m.query.clone(),
+            word: m.word.clone(),
+            typos: m.typos,
+        };
+        if !query_rewrites.contains(&rewrite) {
+            query_rewrites.push(rewrite);
+        }
+    }
+
+    query_rewrites
+}
+
+/// The position of the first posting of the document.
+fn first_posting(doc_indexes: &[DocIndex], id: DocumentId) -> usize {
+    doc_indexes
+        .binary_search_by(|di| {
+            if di.document_id < id {
+                cmp::Ordering::Less
+            } else {
+                cmp::Ordering::Greater
+            }
+        })
+        .unwrap_err()
+}
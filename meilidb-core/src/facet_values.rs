@@ -0,0 +1,175 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::automaton::normalize_str;
+
+/// How the raw values of a facet are normalized before being counted,
+/// each policy also applies the ones listed before it, except `Verbatim`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FacetNormalization {
+    /// The values are counted as they are given.
+    Verbatim,
+    /// The leading and trailing whitespaces are removed.
+    Trim,
+    /// The values are trimmed and lowercased.
+    Lowercase,
+    /// The values are trimmed, lowercased and their accents are removed,
+    /// like the words of the queries.
+    UnicodeFold,
+}
+
+/// The normalization policies of the facets, by attribute name,
+/// the values of the other attributes are counted verbatim.
+pub type FacetsNormalization = BTreeMap<String, FacetNormalization>;
+
+impl Default for FacetNormalization {
+    fn default() -> FacetNormalization {
+        FacetNormalization::Verbatim
+    }
+}
+
+impl FacetNormalization {
+    pub fn normalize(self, value: &str) -> String {
+        match self {
+            FacetNormalization::Verbatim => value.to_owned(),
+            FacetNormalization::Trim => value.trim().to_owned(),
+            FacetNormalization::Lowercase => value.trim().to_lowercase(),
+            FacetNormalization::UnicodeFold => normalize_str(value.trim()),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+struct FacetValue {
+    count: usize,
+    originals: BTreeMap<String, usize>,
+}
+
+impl FacetValue {
+    /// The most frequent original value, the smallest one in case of a tie,
+    /// the label does not depend on the order in which the values are counted.
+    fn label(&self) -> &str {
+        let mut label: Option<(&String, usize)> = None;
+        for (original, count) in &self.originals {
+            match label {
+                Some((_, best)) if best >= *count => (),
+                _ => label = Some((original, *count)),
+            }
+        }
+
+        label.map_or("", |(original, _)| original.as_str())
+    }
+}
+
+/// Counts the values of a facet, the values that are equal once normalized are counted
+/// together and are displayed under a single label chosen among their original values.
+#[derive(Debug, Default, Clone)]
+pub struct FacetValues {
+    normalization: FacetNormalization,
+    values: BTreeMap<String, FacetValue>,
+}
+
+impl FacetValues {
+    pub fn new(normalization: FacetNormalization) -> FacetValues {
+        FacetValues {
+            normalization,
+            values: BTreeMap::new(),
+        }
+    }
+
+    pub fn insert(&mut self, value: &str) {
+        let normalized = self.normalization.normalize(value);
+        let facet_value = self.values.entry(normalized).or_default();
+        facet_value.count += 1;
+        *facet_value.originals.entry(value.to_owned()).or_insert(0) += 1;
+    }
+
+    /// Counts the value of a document field, the booleans are counted under `true` and
+    /// `false`, each value of an array is counted and the nulls and objects are ignored.
+    pub fn insert_value(&mut self, value: &Value) {
+        match value {
+            Value::String(string) => self.insert(string),
+            Value::Number(number) => self.insert(&number.to_string()),
+            Value::Bool(boolean) => self.insert(if *boolean { "true" } else { "false" }),
+            Value::Array(values) => values.iter().for_each(|value| self.insert_value(value)),
+            Value::Null | Value::Object(_) => (),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// The count of a value, the given value is normalized first.
+    pub fn count(&self, value: &str) -> usize {
+        let normalized = self.normalization.normalize(value);
+        self.values.get(&normalized).map_or(0, |v| v.count)
+    }
+
+    /// Returns the labels of the values with their counts, ordered by normalized value.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, usize)> {
+        self.values.values().map(|v| (v.label(), v.count))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizations() {
+        let value = " Émeraude ";
+        assert_eq!(FacetNormalization::Verbatim.normalize(value), " Émeraude ");
+        assert_eq!(FacetNormalization::Trim.normalize(value), "Émeraude");
+        assert_eq!(FacetNormalization::Lowercase.normalize(value), "émeraude");
+        assert_eq!(FacetNormalization::UnicodeFold.normalize(value), "emeraude");
+    }
+
+    #[test]
+    fn stable_labels() {
+        let values = &["red", "Red", "RED ", "Red", "blue", "Blue"];
+
+        let mut facet_values = FacetValues::new(FacetNormalization::Lowercase);
+        for value in values {
+            facet_values.insert(value);
+        }
+
+        let mut reversed = FacetValues::new(FacetNormalization::Lowercase);
+        for value in values.iter().rev() {
+            reversed.insert(value);
+        }
+
+        let counts: Vec<_> = facet_values.iter().collect();
+        assert_eq!(counts, vec![("Blue", 2), ("Red", 4)]);
+        assert_eq!(counts, reversed.iter().collect::<Vec<_>>());
+        assert_eq!(facet_values.count("rEd"), 4);
+    }
+
+    #[test]
+    fn boolean_values() {
+        let mut facet_values = FacetValues::default();
+        facet_values.insert_value(&serde_json::json!(true));
+        facet_values.insert_value(&serde_json::json!([false, true]));
+        facet_values.insert_value(&serde_json::json!(null));
+
+        let counts: Vec<_> = facet_values.iter().collect();
+        assert_eq!(counts, vec![("false", 1), ("true", 2)]);
+    }
+
+    #[test]
+    fn verbatim_values() {
+        let mut facet_values = FacetValues::default();
+        facet_values.insert("red");
+        facet_values.insert("Red");
+
+        assert_eq!(facet_values.len(), 2);
+        assert_eq!(facet_values.count("red"), 1);
+    }
+}
@@ -0,0 +1,166 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use std::{io, thread};
+
+use crate::MResult;
+
+/// The faults that can be injected in the update loop of an index.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FaultKind {
+    /// The update fails before anything has been written.
+    WriteFailure,
+    /// The commit of the update is delayed.
+    DelayedCommit,
+    /// The update has been applied but its result has not been written,
+    /// like if the process was stopped in the middle of the update.
+    TornUpdate,
+}
+
+/// Describes the faults to inject, the failures are drawn from a seeded
+/// pseudo-random generator, the same seed always gives the same faults.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FaultConfig {
+    pub seed: u64,
+    /// The probability, between 0 and 1, that an update fails before being applied.
+    pub write_failure_rate: f64,
+    /// The probability, between 0 and 1, that an update is torn.
+    pub torn_update_rate: f64,
+    pub commit_delay: Option<Duration>,
+}
+
+struct State {
+    config: FaultConfig,
+    rng: u64,
+    injected: Vec<(u64, FaultKind)>,
+}
+
+impl State {
+    fn new(config: FaultConfig) -> State {
+        // xorshift generators must not be seeded with zero
+        let rng = config.seed | 1;
+        State {
+            config,
+            rng,
+            injected: Vec::new(),
+        }
+    }
+
+    fn next_f64(&mut self) -> f64 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 7;
+        self.rng ^= self.rng << 17;
+        (self.rng >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn roll(&mut self, rate: f64) -> bool {
+        rate > 0.0 && self.next_f64() < rate
+    }
+}
+
+impl Default for State {
+    fn default() -> State {
+        State::new(FaultConfig::default())
+    }
+}
+
+/// Injects faults in the update loop of an index, it is only available
+/// with the `fault-injection` feature and must only be used in tests.
+#[derive(Clone, Default)]
+pub struct FaultInjector {
+    inner: Arc<Mutex<State>>,
+}
+
+impl FaultInjector {
+    /// Replaces the current configuration and resets the pseudo-random generator.
+    pub fn configure(&self, config: FaultConfig) {
+        *self.inner.lock().unwrap() = State::new(config);
+    }
+
+    pub fn disable(&self) {
+        self.configure(FaultConfig::default());
+    }
+
+    /// The faults injected since the last configuration, with the id of the affected update.
+    pub fn injected(&self) -> Vec<(u64, FaultKind)> {
+        self.inner.lock().unwrap().injected.clone()
+    }
+
+    fn fail(&self, update_id: u64, kind: FaultKind) -> MResult<()> {
+        let mut state = self.inner.lock().unwrap();
+        let rate = match kind {
+            FaultKind::WriteFailure => state.config.write_failure_rate,
+            FaultKind::TornUpdate => state.config.torn_update_rate,
+            FaultKind::DelayedCommit => return Ok(()),
+        };
+
+        if state.roll(rate) {
+            state.injected.push((update_id, kind));
+            let message = format!("injected fault {:?} on update {}", kind, update_id);
+            return Err(io::Error::new(io::ErrorKind::Other, message).into());
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn before_update(&self, update_id: u64) -> MResult<()> {
+        self.fail(update_id, FaultKind::WriteFailure)
+    }
+
+    pub(crate) fn before_update_result(&self, update_id: u64) -> MResult<()> {
+        self.fail(update_id, FaultKind::TornUpdate)
+    }
+
+    pub(crate) fn before_commit(&self, update_id: u64) {
+        let delay = {
+            let mut state = self.inner.lock().unwrap();
+            let delay = state.config.commit_delay;
+            if delay.is_some() {
+                state.injected.push((update_id, FaultKind::DelayedCommit));
+            }
+            delay
+        };
+
+        if let Some(delay) = delay {
+            thread::sleep(delay);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deterministic_faults() {
+        let config = FaultConfig {
+            seed: 42,
+            write_failure_rate: 0.5,
+            ..FaultConfig::default()
+        };
+
+        let injector = FaultInjector::default();
+        let mut runs = Vec::new();
+        for _ in 0..2 {
+            injector.configure(config.clone());
+            for update_id in 0..100 {
+                let _ = injector.before_update(update_id);
+            }
+            runs.push(injector.injected());
+        }
+
+        assert_eq!(runs[0], runs[1]);
+        assert!(!runs[0].is_empty() && runs[0].len() < 100);
+    }
+
+    #[test]
+    fn disabled_faults() {
+        let injector = FaultInjector::default();
+        for update_id in 0..100 {
+            assert!(injector.before_update(update_id).is_ok());
+            assert!(injector.before_update_result(update_id).is_ok());
+            injector.before_commit(update_id);
+        }
+
+        assert!(injector.injected().is_empty());
+    }
+}
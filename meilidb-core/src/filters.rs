@@ -0,0 +1,694 @@
+use std::ops::Range;
+use std::str::FromStr;
+use std::{error, fmt};
+
+use meilidb_schema::SchemaAttr;
+use serde_json::Value;
+
+use hashbrown::HashSet;
+
+use crate::serde::AttributeType;
+use crate::{store, DocumentId, Error, GeoArea, Highlight, MResult, Number, RankedMap};
+
+/// A filter expression like `genre = "thriller" AND year > 2000`, the `AND`
+/// operator has precedence over the `OR` one and parentheses group conditions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Filter {
+    Condition(Condition),
+    And(Box<Filter>, Box<Filter>),
+    Or(Box<Filter>, Box<Filter>),
+    Not(Box<Filter>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Condition {
+    pub attribute: String,
+    pub operator: Operator,
+    pub value: String,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Operator {
+    Equal,
+    NotEqual,
+    Greater,
+    GreaterOrEqual,
+    Lower,
+    LowerOrEqual,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Word(String),
+    Quoted(String),
+    Operator(Operator),
+    LeftParen,
+    RightParen,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Token::Word(word) => write!(f, "{}", word),
+            Token::Quoted(value) => write!(f, "{:?}", value),
+            Token::Operator(operator) => write!(f, "{}", operator.as_str()),
+            Token::LeftParen => write!(f, "("),
+            Token::RightParen => write!(f, ")"),
+        }
+    }
+}
+
+impl Operator {
+    fn as_str(self) -> &'static str {
+        match self {
+            Operator::Equal => "=",
+            Operator::NotEqual => "!=",
+            Operator::Greater => ">",
+            Operator::GreaterOrEqual => ">=",
+            Operator::Lower => "<",
+            Operator::LowerOrEqual => "<=",
+        }
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '-' || c == '.'
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, FilterError> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            '(' => Token::LeftParen,
+            ')' => Token::RightParen,
+            '=' => Token::Operator(Operator::Equal),
+            '!' if chars.peek() == Some(&'=') => {
+                chars.next();
+                Token::Operator(Operator::NotEqual)
+            }
+            '>' | '<' => {
+                let or_equal = chars.peek() == Some(&'=');
+                if or_equal {
+                    chars.next();
+                }
+                let operator = match (c, or_equal) {
+                    ('>', false) => Operator::Greater,
+                    ('>', true) => Operator::GreaterOrEqual,
+                    ('<', false) => Operator::Lower,
+                    _ => Operator::LowerOrEqual,
+                };
+                Token::Operator(operator)
+            }
+            '"' => {
+                let mut value = String::new();
+                loop {
+                    match chars.next() {
+                        Some('\\') => match chars.next() {
+                            Some(c) => value.push(c),
+                            None => return Err(FilterError::UnclosedQuote),
+                        },
+                        Some('"') => break,
+                        Some(c) => value.push(c),
+                        None => return Err(FilterError::UnclosedQuote),
+                    }
+                }
+                Token::Quoted(value)
+            }
+            c if is_word_char(c) => {
+                let mut word = c.to_string();
+                while let Some(&c) = chars.peek() {
+                    if !is_word_char(c) {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+                Token::Word(word)
+            }
+            c => return Err(FilterError::UnexpectedChar(c)),
+        };
+
+        tokens.push(token);
+    }
+
+    Ok(tokens)
+}
+
+/// The maximum number of nested parentheses and negations of a filter,
+/// the filter is parsed and evaluated recursively.
+pub const MAX_FILTER_DEPTH: usize = 100;
+
+/// The maximum number of conditions of a filter, the conditions joined by
+/// `AND` and `OR` are chained in a tree as deep as their number.
+pub const MAX_FILTER_CONDITIONS: usize = 1000;
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    depth: usize,
+    conditions: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Result<Token, FilterError> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token.ok_or(FilterError::UnexpectedEnd)
+    }
+
+    fn next_is_keyword(&self, keyword: &str) -> bool {
+        match self.peek() {
+            Some(Token::Word(word)) => word.eq_ignore_ascii_case(keyword),
+            _ => false,
+        }
+    }
+
+    fn nest(&mut self) -> Result<(), FilterError> {
+        self.depth += 1;
+        if self.depth > MAX_FILTER_DEPTH {
+            return Err(FilterError::TooDeep(MAX_FILTER_DEPTH));
+        }
+        Ok(())
+    }
+
+    fn parse_or(&mut self) -> Result<Filter, FilterError> {
+        let mut filter = self.parse_and()?;
+        while self.next_is_keyword("OR") {
+            self.position += 1;
+            let right = self.parse_and()?;
+            filter = Filter::Or(Box::new(filter), Box::new(right));
+        }
+        Ok(filter)
+    }
+
+    fn parse_and(&mut self) -> Result<Filter, FilterError> {
+        let mut filter = self.parse_not()?;
+        while self.next_is_keyword("AND") {
+            self.position += 1;
+            let right = self.parse_not()?;
+            filter = Filter::And(Box::new(filter), Box::new(right));
+        }
+        Ok(filter)
+    }
+
+    fn parse_not(&mut self) -> Result<Filter, FilterError> {
+        if self.next_is_keyword("NOT") {
+            self.position += 1;
+            self.nest()?;
+            let filter = self.parse_not()?;
+            self.depth -= 1;
+            return Ok(Filter::Not(Box::new(filter)));
+        }
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> Result<Filter, FilterError> {
+        match self.next()? {
+            Token::LeftParen => {
+                self.nest()?;
+                let filter = self.parse_or()?;
+                self.depth -= 1;
+                match self.next()? {
+                    Token::RightParen => Ok(filter),
+                    token => Err(FilterError::UnexpectedToken(token.to_string())),
+                }
+            }
+            Token::Word(attribute) | Token::Quoted(attribute) => {
+                self.conditions += 1;
+                if self.conditions > MAX_FILTER_CONDITIONS {
+                    return Err(FilterError::TooManyConditions(MAX_FILTER_CONDITIONS));
+                }
+                let operator = match self.next()? {
+                    Token::Operator(operator) => operator,
+                    token => return Err(FilterError::UnexpectedToken(token.to_string())),
+                };
+                let value = match self.next()? {
+                    Token::Word(value) | Token::Quoted(value) => value,
+                    token => return Err(FilterError::UnexpectedToken(token.to_string())),
+                };
+                Ok(Filter::Condition(Condition {
+                    attribute,
+                    operator,
+                    value,
+                }))
+            }
+            token => Err(FilterError::UnexpectedToken(token.to_string())),
+        }
+    }
+}
+
+impl FromStr for Filter {
+    type Err = FilterError;
+
+    fn from_str(s: &str) -> Result<Filter, FilterError> {
+        let tokens = tokenize(s)?;
+        let mut parser = Parser {
+            tokens,
+            position: 0,
+            depth: 0,
+            conditions: 0,
+        };
+
+        let filter = parser.parse_or()?;
+        match parser.peek() {
+            Some(token) => Err(FilterError::UnexpectedToken(token.to_string())),
+            None => Ok(filter),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FilterError {
+    UnexpectedChar(char),
+    UnexpectedToken(String),
+    UnexpectedEnd,
+    UnclosedQuote,
+    UnknownAttribute(String),
+    TooDeep(usize),
+    TooManyConditions(usize),
+}
+
+impl fmt::Display for FilterError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::FilterError::*;
+        match self {
+            UnexpectedChar(c) => write!(f, "unexpected character {:?}", c),
+            UnexpectedToken(token) => write!(f, "unexpected {}", token),
+            UnexpectedEnd => write!(f, "unexpected end of the filter"),
+            UnclosedQuote => write!(f, "a quoted value is not closed"),
+            UnknownAttribute(name) => write!(f, "the attribute {} is not in the schema", name),
+            TooDeep(max) => write!(f, "the filter nests more than {} expressions", max),
+            TooManyConditions(max) => write!(f, "the filter has more than {} conditions", max),
+        }
+    }
+}
+
+impl error::Error for FilterError {}
+
+#[derive(Debug)]
+enum ResolvedFilter {
+    Condition {
+        attribute: SchemaAttr,
+        operator: Operator,
+        value: String,
+        number: Option<Number>,
+        attribute_type: Option<AttributeType>,
+    },
+    And(Box<ResolvedFilter>, Box<ResolvedFilter>),
+    Or(Box<ResolvedFilter>, Box<ResolvedFilter>),
+    Not(Box<ResolvedFilter>),
+}
+
+/// Evaluates a filter against the stored attributes of the candidate documents,
+/// the typed attributes are compared with the values of the ranked map, or with
+/// the typed values of the stored attributes for the ones that are not sortable.
+pub struct CandidatesFilter<'r> {
+    filter: Option<ResolvedFilter>,
+    number_ranges: Vec<(SchemaAttr, Range<Number>)>,
+    required_attribute: Option<SchemaAttr>,
+    geo_candidates: Option<HashSet<DocumentId>>,
+    allowed_documents: Option<HashSet<DocumentId>>,
+    reader: &'r heed::RoTxn,
+    documents_fields: store::DocumentsFields,
+    ranked_map: RankedMap,
+}
+
+impl<'r> CandidatesFilter<'r> {
+    pub fn new(
+        reader: &'r heed::RoTxn,
+        filter: Option<&Filter>,
+        number_ranges: Vec<(SchemaAttr, Range<Number>)>,
+        required_attribute: Option<SchemaAttr>,
+        geo_area: Option<GeoArea>,
+        allowed_documents: Option<HashSet<DocumentId>>,
+        main_store: store::Main,
+        documents_fields: store::DocumentsFields,
+    ) -> MResult<CandidatesFilter<'r>> {
+        let schema = match main_store.schema(reader)? {
+            Some(schema) => schema,
+            None => return Err(Error::SchemaMissing),
+        };
+        let ranked_map = main_store.ranked_map(reader)?.unwrap_or_default();
+        let attributes_types = main_store.attributes_types(reader)?.unwrap_or_default();
+
+        let resolve = |condition: &Condition| -> Result<ResolvedFilter, FilterError> {
+            let name = condition.attribute.as_str();
+            let attribute = match schema.attribute(name) {
+                Some(attribute) => attribute,
+                None => return Err(FilterError::UnknownAttribute(name.to_owned())),
+            };
+
+            // the typed attributes are compared with their canonical values
+            let attribute_type = attributes_types.get(name).cloned();
+            let number = match attribute_type {
+                Some(attribute_type) => attribute_type.parse_str(&condition.value),
+                None => condition.value.parse().ok(),
+            };
+
+            Ok(ResolvedFilter::Condition {
+                attribute,
+                operator: condition.operator,
+                value: condition.value.to_lowercase(),
+                number,
+                attribute_type,
+            })
+        };
+
+        let filter = match filter {
+            Some(filter) => Some(resolve_filter(filter, &resolve)?),
+            None => None,
+        };
+
+        // the documents of the area are found once with the geohashes
+        let geo_candidates = match geo_area {
+            Some(area) => {
+                let geo_index = main_store.geo_index(reader)?.unwrap_or_default();
+                Some(geo_index.documents_in(&area))
+            }
+            None => None,
+        };
+
+        Ok(CandidatesFilter {
+            filter,
+            number_ranges,
+            required_attribute,
+            geo_candidates,
+            allowed_documents,
+            reader,
+            documents_fields,
+            ranked_map,
+        })
+    }
+
+    /// Whether the document is allowed, passes the filter, has its ranked values in the number
+    /// ranges and is located in the area, the documents whose attributes cannot be read are rejected.
+    pub fn accepts(&self, document_id: DocumentId) -> bool {
+        let is_allowed = self
+            .allowed_documents
+            .as_ref()
+            .map_or(true, |documents| documents.contains(&document_id));
+        if !is_allowed {
+            return false;
+        }
+
+        let in_area = self
+            .geo_candidates
+            .as_ref()
+            .map_or(true, |candidates| candidates.contains(&document_id));
+        if !in_area {
+            return false;
+        }
+
+        let in_ranges = self.number_ranges.iter().all(|(attribute, range)| {
+            self.ranked_map
+                .get(document_id, *attribute)
+                .map_or(false, |number| range.contains(&number))
+        });
+
+        in_ranges
+            && self
+                .filter
+                .as_ref()
+                .map_or(true, |filter| self.evaluate(filter, document_id))
+    }
+
+    /// Whether one of the query words matched in the required attribute,
+    /// the highlights give the attributes in which the words matched.
+    pub fn accepts_highlights(&self, highlights: &[Highlight]) -> bool {
+        match self.required_attribute {
+            Some(attribute) => highlights.iter().any(|h| h.attribute == attribute.0),
+            None => true,
+        }
+    }
+
+    fn evaluate(&self, filter: &ResolvedFilter, document_id: DocumentId) -> bool {
+        match filter {
+            ResolvedFilter::Condition {
+                attribute,
+                operator: Operator::NotEqual,
+                value,
+                number,
+                attribute_type,
+            } => !self.condition(
+                document_id,
+                *attribute,
+                Operator::Equal,
+                value,
+                *number,
+                *attribute_type,
+            ),
+            ResolvedFilter::Condition {
+                attribute,
+                operator,
+                value,
+                number,
+                attribute_type,
+            } => self.condition(
+                document_id,
+                *attribute,
+                *operator,
+                value,
+                *number,
+                *attribute_type,
+            ),
+            ResolvedFilter::And(left, right) => {
+                self.evaluate(left, document_id) && self.evaluate(right, document_id)
+            }
+            ResolvedFilter::Or(left, right) => {
+                self.evaluate(left, document_id) || self.evaluate(right, document_id)
+            }
+            ResolvedFilter::Not(filter) => !self.evaluate(filter, document_id),
+        }
+    }
+
+    fn condition(
+        &self,
+        document_id: DocumentId,
+        attribute: SchemaAttr,
+        operator: Operator,
+        value: &str,
+        number: Option<Number>,
+        attribute_type: Option<AttributeType>,
+    ) -> bool {
+        if let (Some(found), Some(number)) = (self.ranked_map.get(document_id, attribute), number) {
+            return compare(operator, found, number);
+        }
+
+        let bytes = self
+            .documents_fields
+            .document_attribute(self.reader, document_id, attribute);
+
+        let found = match bytes {
+            Ok(Some(bytes)) => match serde_json::from_slice(&bytes) {
+                Ok(found) => found,
+                Err(_) => return false,
+            },
+            _ => return false,
+        };
+
+        // the values of the types that are not in the ranked map are typed here
+        match attribute_type {
+            Some(attribute_type) if !attribute_type.is_sortable() => {
+                match (attribute_type.parse_value(&found), number) {
+                    (Some(found), Some(number)) => compare(operator, found, number),
+                    _ => false,
+                }
+            }
+            _ => value_matches(&found, operator, value, number),
+        }
+    }
+}
+
+fn resolve_filter<F>(filter: &Filter, resolve: &F) -> Result<ResolvedFilter, FilterError>
+where
+    F: Fn(&Condition) -> Result<ResolvedFilter, FilterError>,
+{
+    let resolved = match filter {
+        Filter::Condition(condition) => resolve(condition)?,
+        Filter::And(left, right) => ResolvedFilter::And(
+            Box::new(resolve_filter(left, resolve)?),
+            Box::new(resolve_filter(right, resolve)?),
+        ),
+        Filter::Or(left, right) => ResolvedFilter::Or(
+            Box::new(resolve_filter(left, resolve)?),
+            Box::new(resolve_filter(right, resolve)?),
+        ),
+        Filter::Not(filter) => ResolvedFilter::Not(Box::new(resolve_filter(filter, resolve)?)),
+    };
+
+    Ok(resolved)
+}
+
+fn compare<T: Ord>(operator: Operator, found: T, expected: T) -> bool {
+    match operator {
+        Operator::Equal => found == expected,
+        Operator::NotEqual => found != expected,
+        Operator::Greater => found > expected,
+        Operator::GreaterOrEqual => found >= expected,
+        Operator::Lower => found < expected,
+        Operator::LowerOrEqual => found <= expected,
+    }
+}
+
+/// The strings are compared case insensitively, as numbers if both sides are numbers,
+/// an array matches if one of its values matches.
+fn value_matches(found: &Value, operator: Operator, value: &str, number: Option<Number>) -> bool {
+    match found {
+        Value::Array(values) => values
+            .iter()
+            .any(|found| value_matches(found, operator, value, number)),
+        Value::Number(found) => match (found.to_string().parse::<Number>(), number) {
+            (Ok(found), Some(number)) => compare(operator, found, number),
+            _ => false,
+        },
+        Value::String(found) => match (found.trim().parse::<Number>(), number) {
+            (Ok(found), Some(number)) => compare(operator, found, number),
+            _ => compare(operator, found.to_lowercase().as_str(), value),
+        },
+        Value::Bool(found) => match operator {
+            Operator::Equal => found.to_string() == value,
+            _ => false,
+        },
+        Value::Null | Value::Object(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn condition(attribute: &str, operator: Operator, value: &str) -> Filter {
+        Filter::Condition(Condition {
+            attribute: attribute.to_owned(),
+            operator,
+            value: value.to_owned(),
+        })
+    }
+
+    #[test]
+    fn parse_filters() {
+        let filter: Filter = r#"genre = "thriller" AND year > 2000"#.parse().unwrap();
+        assert_eq!(
+            filter,
+            Filter::And(
+                Box::new(condition("genre", Operator::Equal, "thriller")),
+                Box::new(condition("year", Operator::Greater, "2000")),
+            )
+        );
+
+        // the AND operator has precedence over the OR one
+        let filter: Filter = "a = 1 or b <= 2 and not c != 3".parse().unwrap();
+        assert_eq!(
+            filter,
+            Filter::Or(
+                Box::new(condition("a", Operator::Equal, "1")),
+                Box::new(Filter::And(
+                    Box::new(condition("b", Operator::LowerOrEqual, "2")),
+                    Box::new(Filter::Not(Box::new(condition(
+                        "c",
+                        Operator::NotEqual,
+                        "3"
+                    )))),
+                )),
+            )
+        );
+
+        let filter: Filter = r#"(a >= -1.5 OR b < "x \"y\"") AND c = d"#.parse().unwrap();
+        assert_eq!(
+            filter,
+            Filter::And(
+                Box::new(Filter::Or(
+                    Box::new(condition("a", Operator::GreaterOrEqual, "-1.5")),
+                    Box::new(condition("b", Operator::Lower, "x \"y\"")),
+                )),
+                Box::new(condition("c", Operator::Equal, "d")),
+            )
+        );
+    }
+
+    #[test]
+    fn invalid_filters() {
+        let parse = |s: &str| s.parse::<Filter>().unwrap_err();
+
+        assert_eq!(parse("genre ="), FilterError::UnexpectedEnd);
+        assert_eq!(parse(r#"genre = "thri"#), FilterError::UnclosedQuote);
+        assert_eq!(parse("genre ~ x"), FilterError::UnexpectedChar('~'));
+        assert_eq!(parse("(a = 1"), FilterError::UnexpectedEnd);
+        assert_eq!(
+            parse("a = 1 b = 2"),
+            FilterError::UnexpectedToken("b".to_owned())
+        );
+        assert_eq!(
+            parse("a = = 1"),
+            FilterError::UnexpectedToken("=".to_owned())
+        );
+
+        let nested = format!("{}a = 1{}", "(".repeat(5000), ")".repeat(5000));
+        assert_eq!(parse(&nested), FilterError::TooDeep(MAX_FILTER_DEPTH));
+        let negated = format!("{}a = 1", "NOT ".repeat(5000));
+        assert_eq!(parse(&negated), FilterError::TooDeep(MAX_FILTER_DEPTH));
+
+        let nested = format!("{}a = 1{}", "(".repeat(50), ")".repeat(50));
+        assert!(nested.parse::<Filter>().is_ok());
+
+        let chained = vec!["a = 1"; 100_000].join(" AND ");
+        assert_eq!(
+            parse(&chained),
+            FilterError::TooManyConditions(MAX_FILTER_CONDITIONS)
+        );
+        let chained = vec!["a = 1"; 100_000].join(" OR ");
+        assert_eq!(
+            parse(&chained),
+            FilterError::TooManyConditions(MAX_FILTER_CONDITIONS)
+        );
+
+        let chained = vec!["a = 1"; MAX_FILTER_CONDITIONS].join(" OR ");
+        assert!(chained.parse::<Filter>().is_ok());
+    }
+
+    #[test]
+    fn matching_values() {
+        let number = Some(Number::Unsigned(2000));
+        assert!(value_matches(
+            &json!(2005),
+            Operator::Greater,
+            "2000",
+            number
+        ));
+        assert!(!value_matches(
+            &json!(1999),
+            Operator::Greater,
+            "2000",
+            number
+        ));
+        assert!(value_matches(
+            &json!("2000"),
+            Operator::Equal,
+            "2000",
+            number
+        ));
+
+        assert!(value_matches(
+            &json!("Thriller"),
+            Operator::Equal,
+            "thriller",
+            None
+        ));
+        assert!(value_matches(
+            &json!(["drama", "thriller"]),
+            Operator::Equal,
+            "thriller",
+            None
+        ));
+        assert!(value_matches(&json!(true), Operator::Equal, "true", None));
+        assert!(!value_matches(&json!(null), Operator::Equal, "null", None));
+    }
+}
@@ -0,0 +1,327 @@
+use std::collections::BTreeSet;
+
+use hashbrown::{HashMap, HashSet};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::DocumentId;
+
+/// The field of the documents giving their location, like `{ "lat": 48.85, "lng": 2.35 }`.
+pub const GEO_FIELD: &str = "_geo";
+
+const EARTH_RADIUS_METERS: f64 = 6_371_000.0;
+
+/// The number of bits of each coordinate in the geohashes, the cells
+/// of the deepest level of the tree are around a centimeter wide.
+const COORDINATE_BITS: u32 = 32;
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GeoPoint {
+    pub lat: f64,
+    pub lng: f64,
+}
+
+impl GeoPoint {
+    /// A point with a latitude in `-90..=90` and a longitude in `-180..=180`.
+    pub fn new(lat: f64, lng: f64) -> Option<GeoPoint> {
+        let valid = lat.is_finite()
+            && lng.is_finite()
+            && lat >= -90.0
+            && lat <= 90.0
+            && lng >= -180.0
+            && lng <= 180.0;
+
+        if valid {
+            Some(GeoPoint { lat, lng })
+        } else {
+            None
+        }
+    }
+
+    /// Reads a point from an object with `lat` and `lng` numbers or numeric strings.
+    pub fn from_value(value: &Value) -> Option<GeoPoint> {
+        let coordinate = |name: &str| match value.get(name)? {
+            Value::Number(number) => number.as_f64(),
+            Value::String(string) => string.trim().parse().ok(),
+            _ => None,
+        };
+
+        GeoPoint::new(coordinate("lat")?, coordinate("lng")?)
+    }
+
+    pub fn to_value(self) -> Value {
+        serde_json::json!({ "lat": self.lat, "lng": self.lng })
+    }
+
+    /// The great-circle distance in meters between the two points.
+    pub fn distance(self, other: GeoPoint) -> f64 {
+        let (lat1, lat2) = (self.lat.to_radians(), other.lat.to_radians());
+        let dlat = (other.lat - self.lat).to_radians();
+        let dlng = (other.lng - self.lng).to_radians();
+
+        let a = (dlat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (dlng / 2.0).sin().powi(2);
+        2.0 * EARTH_RADIUS_METERS * a.sqrt().asin()
+    }
+}
+
+/// A box delimited by its south-west and north-east corners,
+/// the boxes crossing the antimeridian are not supported.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct BoundingBox {
+    pub south_west: GeoPoint,
+    pub north_east: GeoPoint,
+}
+
+impl BoundingBox {
+    pub fn contains(&self, point: GeoPoint) -> bool {
+        point.lat >= self.south_west.lat
+            && point.lat <= self.north_east.lat
+            && point.lng >= self.south_west.lng
+            && point.lng <= self.north_east.lng
+    }
+
+    fn world() -> BoundingBox {
+        BoundingBox {
+            south_west: GeoPoint {
+                lat: -90.0,
+                lng: -180.0,
+            },
+            north_east: GeoPoint {
+                lat: 90.0,
+                lng: 180.0,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum GeoArea {
+    Radius { center: GeoPoint, meters: f64 },
+    BoundingBox(BoundingBox),
+}
+
+impl GeoArea {
+    pub fn contains(&self, point: GeoPoint) -> bool {
+        match self {
+            GeoArea::Radius { center, meters } => center.distance(point) <= *meters,
+            GeoArea::BoundingBox(bbox) => bbox.contains(point),
+        }
+    }
+
+    /// A box containing the whole area, the circles reaching a pole
+    /// or the antimeridian are bounded by the whole world.
+    fn bounding_box(&self) -> BoundingBox {
+        match *self {
+            GeoArea::Radius { center, meters } => {
+                let dlat = (meters / EARTH_RADIUS_METERS).to_degrees();
+                let south = center.lat - dlat;
+                let north = center.lat + dlat;
+                if south < -90.0 || north > 90.0 {
+                    return BoundingBox::world();
+                }
+
+                let widest_lat = center.lat.abs() + dlat;
+                let dlng = dlat / widest_lat.to_radians().cos();
+                let west = center.lng - dlng;
+                let east = center.lng + dlng;
+                if !dlng.is_finite() || west < -180.0 || east > 180.0 {
+                    return BoundingBox::world();
+                }
+
+                BoundingBox {
+                    south_west: GeoPoint {
+                        lat: south,
+                        lng: west,
+                    },
+                    north_east: GeoPoint {
+                        lat: north,
+                        lng: east,
+                    },
+                }
+            }
+            GeoArea::BoundingBox(bbox) => bbox,
+        }
+    }
+}
+
+fn quantize(value: f64, min: f64, max: f64) -> u32 {
+    let ratio = (value - min) / (max - min);
+    let cells = f64::from(u32::max_value()) + 1.0;
+    (ratio * cells).max(0.0).min(f64::from(u32::max_value())) as u32
+}
+
+/// Interleaves the bits of the coordinates, the points sharing a prefix are in the same cell.
+fn interleave(lat: u32, lng: u32) -> u64 {
+    let mut geohash = 0;
+    for bit in (0..COORDINATE_BITS).rev() {
+        geohash = (geohash << 1) | u64::from((lng >> bit) & 1);
+        geohash = (geohash << 1) | u64::from((lat >> bit) & 1);
+    }
+    geohash
+}
+
+fn geohash(point: GeoPoint) -> u64 {
+    let lat = quantize(point.lat, -90.0, 90.0);
+    let lng = quantize(point.lng, -180.0, 180.0);
+    interleave(lat, lng)
+}
+
+/// The locations of the documents, ordered by geohash to find
+/// the documents of an area by scanning the cells covering it.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GeoIndex {
+    points: HashMap<DocumentId, GeoPoint>,
+    cells: BTreeSet<(u64, DocumentId)>,
+}
+
+impl GeoIndex {
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    pub fn insert(&mut self, document: DocumentId, point: GeoPoint) {
+        self.remove(document);
+        self.cells.insert((geohash(point), document));
+        self.points.insert(document, point);
+    }
+
+    pub fn remove(&mut self, document: DocumentId) {
+        if let Some(point) = self.points.remove(&document) {
+            self.cells.remove(&(geohash(point), document));
+        }
+    }
+
+    pub fn get(&self, document: DocumentId) -> Option<GeoPoint> {
+        self.points.get(&document).cloned()
+    }
+
+    /// The documents located in the area.
+    pub fn documents_in(&self, area: &GeoArea) -> HashSet<DocumentId> {
+        let bbox = area.bounding_box();
+        let (south, west) = (bbox.south_west.lat, bbox.south_west.lng);
+        let (north, east) = (bbox.north_east.lat, bbox.north_east.lng);
+
+        // the deepest level at which the cells are larger than the box,
+        // the box is then covered by at most two cells in each direction
+        let lat_level = (180.0 / (north - south)).log2().floor();
+        let lng_level = (360.0 / (east - west)).log2().floor();
+        let level = lat_level
+            .min(lng_level)
+            .max(0.0)
+            .min(f64::from(COORDINATE_BITS)) as u32;
+        let shift = COORDINATE_BITS - level;
+
+        let cell = |value: u32| u64::from(value) >> shift;
+        let lats = cell(quantize(south, -90.0, 90.0))..=cell(quantize(north, -90.0, 90.0));
+        let lngs = cell(quantize(west, -180.0, 180.0))..=cell(quantize(east, -180.0, 180.0));
+
+        let mut documents = HashSet::new();
+        for lat in lats {
+            for lng in lngs.clone() {
+                let prefix = interleave((lat << shift) as u32, (lng << shift) as u32);
+                let last = match 2 * shift {
+                    64 => u64::max_value(),
+                    bits => prefix | ((1 << bits) - 1),
+                };
+
+                let start = (prefix, DocumentId(0));
+                let end = (last, DocumentId(u64::max_value()));
+                for (_, document) in self.cells.range(start..=end) {
+                    if self
+                        .points
+                        .get(document)
+                        .map_or(false, |p| area.contains(*p))
+                    {
+                        documents.insert(*document);
+                    }
+                }
+            }
+        }
+
+        documents
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(lat: f64, lng: f64) -> GeoPoint {
+        GeoPoint::new(lat, lng).unwrap()
+    }
+
+    #[test]
+    fn parse_points() {
+        let value = serde_json::json!({ "lat": 48.85, "lng": "2.35" });
+        assert_eq!(GeoPoint::from_value(&value), Some(point(48.85, 2.35)));
+
+        let value = serde_json::json!({ "lat": 91, "lng": 2 });
+        assert_eq!(GeoPoint::from_value(&value), None);
+
+        let value = serde_json::json!({ "latitude": 48.85, "lng": 2.35 });
+        assert_eq!(GeoPoint::from_value(&value), None);
+    }
+
+    #[test]
+    fn distances() {
+        let paris = point(48.8566, 2.3522);
+        let london = point(51.5074, -0.1278);
+
+        let distance = paris.distance(london);
+        assert!((distance - 343_500.0).abs() < 1_000.0, "{}", distance);
+        assert_eq!(paris.distance(paris), 0.0);
+    }
+
+    #[test]
+    fn documents_in_areas() {
+        let paris = point(48.8566, 2.3522);
+        let versailles = point(48.8049, 2.1204);
+        let london = point(51.5074, -0.1278);
+        let sydney = point(-33.8688, 151.2093);
+
+        let mut index = GeoIndex::default();
+        index.insert(DocumentId(0), paris);
+        index.insert(DocumentId(1), versailles);
+        index.insert(DocumentId(2), london);
+        index.insert(DocumentId(3), sydney);
+
+        let ids = |area| {
+            let mut ids: Vec<_> = index.documents_in(&area).into_iter().collect();
+            ids.sort();
+            ids
+        };
+
+        let around_paris = |meters| GeoArea::Radius {
+            center: paris,
+            meters,
+        };
+        assert_eq!(ids(around_paris(1_000.0)), vec![DocumentId(0)]);
+        assert_eq!(
+            ids(around_paris(20_000.0)),
+            vec![DocumentId(0), DocumentId(1)]
+        );
+        assert_eq!(ids(around_paris(400_000.0)).len(), 3);
+        assert_eq!(ids(around_paris(20_000_000.0)).len(), 4);
+
+        let bbox = GeoArea::BoundingBox(BoundingBox {
+            south_west: point(48.0, -1.0),
+            north_east: point(52.0, 2.2),
+        });
+        assert_eq!(ids(bbox), vec![DocumentId(1), DocumentId(2)]);
+
+        // a moved document is only found at its new location
+        index.insert(DocumentId(3), point(48.86, 2.35));
+        assert_eq!(
+            ids(around_paris(1_000.0)),
+            vec![DocumentId(0), DocumentId(3)]
+        );
+
+        index.remove(DocumentId(0));
+        assert_eq!(ids(around_paris(1_000.0)), vec![DocumentId(3)]);
+        assert_eq!(index.len(), 3);
+    }
+}
@@ -0,0 +1,233 @@
+use std::collections::BTreeMap;
+
+use fst::Streamer;
+use serde::{Deserialize, Serialize};
+
+use crate::cold_tier::ColdTierSettings;
+use crate::criterion::{RankingProfiles, RankingRule};
+use crate::facet_values::FacetsNormalization;
+use crate::raw_indexer::WordsLimits;
+use crate::serde::{AttributesTypes, DocumentIdCharset};
+use crate::store::Index;
+use crate::token_filters::TokenFilter;
+use crate::update::UpdateLimits;
+use crate::{MResult, QueryLimits, TyposTable};
+
+/// The alternatives of the synonyms along with their penalties, by synonym.
+pub type WeightedSynonyms = BTreeMap<String, BTreeMap<String, u64>>;
+
+/// Every setting of an index apart from its schema, in a serializable form, to dump
+/// the settings of an index along with its documents and write them into another index.
+/// The settings that are not defined keep their default value.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexSettings {
+    pub customs: Option<Vec<u8>>,
+    pub stop_words: Option<Vec<String>>,
+    pub decompounding: Option<Vec<String>>,
+    pub frozen_words: Option<Vec<String>>,
+    pub synonyms: WeightedSynonyms,
+    pub strip_units: Option<bool>,
+    pub prefix_search: Option<bool>,
+    pub attributes_types: Option<AttributesTypes>,
+    pub words_limits: Option<WordsLimits>,
+    pub facets_normalization: Option<FacetsNormalization>,
+    pub token_filters: Option<Vec<TokenFilter>>,
+    pub searchable_attributes: Option<Vec<String>>,
+    pub displayed_attributes: Option<Vec<String>>,
+    pub update_limits: Option<UpdateLimits>,
+    pub max_attributes: Option<u64>,
+    pub max_total_hits: Option<u64>,
+    pub html_escaping: Option<bool>,
+    pub flatten_documents: Option<bool>,
+    pub attributes_discovery: Option<bool>,
+    pub query_limits: Option<QueryLimits>,
+    pub typos_table: Option<TyposTable>,
+    pub criteria: Option<Vec<RankingRule>>,
+    pub ranking_profiles: Option<RankingProfiles>,
+    pub cold_tier: Option<ColdTierSettings>,
+    pub document_id_charset: Option<DocumentIdCharset>,
+}
+
+fn words(set: Option<fst::Set>) -> MResult<Option<Vec<String>>> {
+    match set {
+        Some(set) => Ok(Some(set.stream().into_strs()?)),
+        None => Ok(None),
+    }
+}
+
+fn words_set(words: &[String]) -> MResult<fst::Set> {
+    let mut words = words.to_vec();
+    words.sort_unstable();
+    words.dedup();
+    Ok(fst::Set::from_iter(words)?)
+}
+
+impl IndexSettings {
+    pub fn read(reader: &heed::RoTxn, index: &Index) -> MResult<IndexSettings> {
+        let main = index.main;
+
+        let mut synonyms = WeightedSynonyms::new();
+        if let Some(synonyms_fst) = main.synonyms_fst(reader)? {
+            for word in synonyms_fst.stream().into_strs()? {
+                let alternatives =
+                    match index.synonyms.weighted_synonyms(reader, word.as_bytes())? {
+                        Some(alternatives) => alternatives,
+                        None => continue,
+                    };
+
+                let mut weighted = BTreeMap::new();
+                let mut stream = alternatives.stream();
+                while let Some((alternative, penalty)) = stream.next() {
+                    let alternative = String::from_utf8_lossy(alternative).into_owned();
+                    weighted.insert(alternative, penalty);
+                }
+                synonyms.insert(word, weighted);
+            }
+        }
+
+        Ok(IndexSettings {
+            customs: main.customs(reader)?.map(<[u8]>::to_vec),
+            stop_words: words(main.stop_words_fst(reader)?)?,
+            decompounding: words(main.decompounding_fst(reader)?)?,
+            frozen_words: words(main.frozen_words_fst(reader)?)?,
+            synonyms,
+            strip_units: main.strip_units(reader)?,
+            prefix_search: main.prefix_search(reader)?,
+            attributes_types: main.attributes_types(reader)?,
+            words_limits: main.words_limits(reader)?,
+            facets_normalization: main.facets_normalization(reader)?,
+            token_filters: main.token_filters(reader)?,
+            searchable_attributes: main.searchable_attributes(reader)?,
+            displayed_attributes: main.displayed_attributes(reader)?,
+            update_limits: main.update_limits(reader)?,
+            max_attributes: main.max_attributes(reader)?,
+            max_total_hits: main.max_total_hits(reader)?,
+            html_escaping: main.html_escaping(reader)?,
+            flatten_documents: main.flatten_documents(reader)?,
+            attributes_discovery: main.attributes_discovery(reader)?,
+            query_limits: main.query_limits(reader)?,
+            typos_table: main.typos_table(reader)?,
+            criteria: main.criteria(reader)?,
+            ranking_profiles: main.ranking_profiles(reader)?,
+            cold_tier: main.cold_tier(reader)?,
+            document_id_charset: main.document_id_charset(reader)?,
+        })
+    }
+
+    /// Writes the settings into the index as they are, the documents are not reindexed,
+    /// the settings must therefore be written before any document is added to the index.
+    pub fn write(&self, writer: &mut heed::RwTxn, index: &Index) -> MResult<()> {
+        let main = index.main;
+
+        if let Some(customs) = &self.customs {
+            main.put_customs(writer, customs)?;
+        }
+
+        if let Some(stop_words) = &self.stop_words {
+            main.put_stop_words_fst(writer, &words_set(stop_words)?)?;
+        }
+
+        if let Some(decompounding) = &self.decompounding {
+            main.put_decompounding_fst(writer, &words_set(decompounding)?)?;
+        }
+
+        if let Some(frozen_words) = &self.frozen_words {
+            main.put_frozen_words_fst(writer, &words_set(frozen_words)?)?;
+        }
+
+        if !self.synonyms.is_empty() {
+            for (word, alternatives) in &self.synonyms {
+                let alternatives = fst::Map::from_iter(alternatives.clone())?;
+                index
+                    .synonyms
+                    .put_weighted_synonyms(writer, word.as_bytes(), &alternatives)?;
+            }
+
+            let synonyms_fst = fst::Set::from_iter(self.synonyms.keys())?;
+            main.put_synonyms_fst(writer, &synonyms_fst)?;
+        }
+
+        if let Some(strip_units) = self.strip_units {
+            main.put_strip_units(writer, strip_units)?;
+        }
+
+        if let Some(prefix) = self.prefix_search {
+            main.put_prefix_search(writer, prefix)?;
+        }
+
+        if let Some(attributes_types) = &self.attributes_types {
+            main.put_attributes_types(writer, attributes_types)?;
+        }
+
+        if let Some(words_limits) = &self.words_limits {
+            main.put_words_limits(writer, words_limits)?;
+        }
+
+        if let Some(normalization) = &self.facets_normalization {
+            main.put_facets_normalization(writer, normalization)?;
+        }
+
+        if let Some(token_filters) = &self.token_filters {
+            main.put_token_filters(writer, token_filters)?;
+        }
+
+        if let Some(attributes) = &self.searchable_attributes {
+            main.put_searchable_attributes(writer, attributes)?;
+        }
+
+        if let Some(attributes) = &self.displayed_attributes {
+            main.put_displayed_attributes(writer, attributes)?;
+        }
+
+        if let Some(limits) = &self.update_limits {
+            main.put_update_limits(writer, limits)?;
+        }
+
+        if let Some(max_attributes) = self.max_attributes {
+            main.put_max_attributes(writer, max_attributes)?;
+        }
+
+        if let Some(max_total_hits) = self.max_total_hits {
+            main.put_max_total_hits(writer, max_total_hits)?;
+        }
+
+        if let Some(escape_html) = self.html_escaping {
+            main.put_html_escaping(writer, escape_html)?;
+        }
+
+        if let Some(flatten) = self.flatten_documents {
+            main.put_flatten_documents(writer, flatten)?;
+        }
+
+        if let Some(discovery) = self.attributes_discovery {
+            main.put_attributes_discovery(writer, discovery)?;
+        }
+
+        if let Some(limits) = &self.query_limits {
+            main.put_query_limits(writer, limits)?;
+        }
+
+        if let Some(table) = &self.typos_table {
+            main.put_typos_table(writer, table)?;
+        }
+
+        if let Some(rules) = &self.criteria {
+            main.put_criteria(writer, rules)?;
+        }
+
+        if let Some(profiles) = &self.ranking_profiles {
+            main.put_ranking_profiles(writer, profiles)?;
+        }
+
+        if let Some(settings) = &self.cold_tier {
+            main.put_cold_tier(writer, settings)?;
+        }
+
+        if let Some(charset) = &self.document_id_charset {
+            main.put_document_id_charset(writer, charset)?;
+        }
+
+        Ok(())
+    }
+}
@@ -3,28 +3,68 @@
 extern crate assert_matches;
 
 mod automaton;
+mod cold_tier;
 pub mod criterion;
 mod database;
 mod distinct_map;
+mod diversity;
+mod engine;
 mod error;
+mod explain;
+mod facet_values;
+#[cfg(feature = "fault-injection")]
+pub mod fault_injection;
+mod filters;
+mod geo;
+mod index_settings;
 mod levenshtein;
 mod number;
+mod operations_log;
 mod query_builder;
 mod ranked_map;
 mod raw_document;
 pub mod raw_indexer;
 mod reordered_attrs;
+mod schema_snapshot;
 pub mod serde;
+mod spill;
 pub mod store;
+mod token_filters;
 mod update;
+mod vocabulary;
 
-pub use self::database::{BoxUpdateFn, Database};
-pub use self::error::{Error, MResult};
+pub use self::automaton::{
+    dfa_cache_len, normalize_str, AutomatonOrigin, PatternOptions, QueryLimits, QueryRewrite,
+    TyposTable, TyposTableError, TyposThreshold,
+};
+pub use self::cold_tier::{ColdTierSettings, TierStats, TieringReport};
+pub use self::database::{BoxUpdateFn, Database, StartupReport, MAP_SIZE};
+pub use self::engine::SearchEngine;
+pub use self::error::{Error, LimitExceeded, MResult, UnsupportedOperation};
+pub use self::explain::{CriterionScore, Explanation, MatchExplanation};
+pub use self::facet_values::{FacetNormalization, FacetValues, FacetsNormalization};
+pub use self::filters::{Condition, Filter, FilterError, Operator};
+pub use self::geo::{BoundingBox, GeoArea, GeoIndex, GeoPoint, GEO_FIELD};
+pub use self::index_settings::{IndexSettings, WeightedSynonyms};
 pub use self::number::{Number, ParseNumberError};
+pub use self::operations_log::{LoggedOperation, Operation, OperationsLog};
+pub use self::query_builder::{QueryResult, QueryTimings};
 pub use self::ranked_map::RankedMap;
 pub use self::raw_document::RawDocument;
-pub use self::store::Index;
-pub use self::update::{EnqueuedUpdateResult, ProcessedUpdateResult, UpdateStatus, UpdateType};
+pub use self::schema_snapshot::SchemaSnapshot;
+pub use self::store::{DocumentRetrieval, Index};
+pub use self::token_filters::{
+    default_token_filters, register_token_filter, registered_token_filters, CustomTokenFilter,
+    TokenFilter, TokenFilterChain,
+};
+pub use self::update::{
+    check_searchable, BoxCustomsMergeFn, BoxCustomsRenameFn, CoercionReport, DocumentCoercions,
+    DuplicateDocument, DuplicatesPolicy, EnqueuedUpdateResult, FieldMapping, FieldMerge,
+    FieldSplit, IdentifierCollision, IdentifiersGeneration, IdentifiersVerification, IndexingStats,
+    ProcessedUpdateResult, SettingsPatch, SettingsPatchContent, UpdateLimits, UpdateProcessed,
+    UpdateStatus, UpdateType, MAX_SYNONYM_PENALTY, UPDATE_CALLBACKS_QUEUE_LEN,
+};
+pub use self::vocabulary::{PostingsStats, VocabularyFormat, WordPostings, WordStats};
 pub use meilidb_types::{DocIndex, DocumentId, Highlight};
 
 #[doc(hidden)]
@@ -0,0 +1,263 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use heed::types::{OwnedType, SerdeBincode, SerdeJson, Str};
+use heed::Result as ZResult;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::serde::Deserializer;
+use crate::store::{self, BEU64};
+use crate::update::{FieldMapping, UpdateData, UpdateType};
+use crate::{DocumentId, Error, MResult};
+
+const OPERATIONS_LOG_ENABLED_KEY: &str = "operations-log-enabled";
+const TRUNCATED_UP_TO_KEY: &str = "operations-log-truncated-up-to";
+
+/// A change of the database as seen by the external consumers of the operations log,
+/// the format of the operations is stable and new variants are only ever appended.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum Operation {
+    IndexCreated,
+    IndexDeleted,
+    /// The documents added or replaced, as they are stored once the update is processed,
+    /// along with their generated identifiers.
+    DocumentsAdded {
+        documents: Vec<HashMap<String, Value>>,
+    },
+    /// The documents updated, as they are stored once their fields are updated.
+    DocumentsUpdated {
+        documents: Vec<HashMap<String, Value>>,
+    },
+    /// The identifiers of the documents deleted, as they were sent, and their internal ids
+    /// computed with `serde::compute_document_id`. The identifiers of the documents indexed
+    /// before they were kept are unknown, these documents are only listed by their ids.
+    DocumentsDeleted {
+        #[serde(default)]
+        identifiers: Vec<String>,
+        documents_ids: Vec<u64>,
+    },
+    DocumentsCleared,
+    /// A change of the schema or of the settings of the index, the documents
+    /// are not listed even when they are reindexed.
+    SettingsChanged {
+        update_type: UpdateType,
+    },
+    /// The documents were all rewritten for a new schema with this mapping of their fields.
+    Migration {
+        mapping: FieldMapping,
+    },
+}
+
+impl Operation {
+    /// The operation of an update, it must be created before the update is applied
+    /// for the identifiers of the deleted documents to still be known.
+    pub(crate) fn from_update(
+        reader: &heed::RoTxn,
+        index: &store::Index,
+        data: &UpdateData,
+    ) -> MResult<Operation> {
+        let operation = match data {
+            UpdateData::DocumentsAddition { .. } => Operation::DocumentsAdded {
+                documents: Vec::new(),
+            },
+            UpdateData::DocumentsPartial { .. } => Operation::DocumentsUpdated {
+                documents: Vec::new(),
+            },
+            UpdateData::DocumentsDeletion(ids) => {
+                let mut identifiers = Vec::with_capacity(ids.len());
+                for id in ids {
+                    if let Some(identifier) = index.documents_ids.external_id(reader, *id)? {
+                        identifiers.push(identifier.to_owned());
+                    }
+                }
+
+                Operation::DocumentsDeleted {
+                    identifiers,
+                    documents_ids: ids.iter().map(|id| id.0).collect(),
+                }
+            }
+            UpdateData::ClearAll => Operation::DocumentsCleared,
+            UpdateData::Migration(_, mapping) => Operation::Migration {
+                mapping: mapping.clone(),
+            },
+            data => Operation::SettingsChanged {
+                update_type: data.update_type(),
+            },
+        };
+
+        Ok(operation)
+    }
+
+    /// Fills the documents added or updated by the operation with the stored ones,
+    /// it must be called once the update that stored these documents is applied.
+    pub(crate) fn with_stored_documents(
+        self,
+        reader: &heed::RoTxn,
+        index: &store::Index,
+        documents_ids: &[DocumentId],
+    ) -> MResult<Operation> {
+        match self {
+            Operation::DocumentsAdded { .. } => Ok(Operation::DocumentsAdded {
+                documents: stored_documents(reader, index, documents_ids)?,
+            }),
+            Operation::DocumentsUpdated { .. } => Ok(Operation::DocumentsUpdated {
+                documents: stored_documents(reader, index, documents_ids)?,
+            }),
+            operation => Ok(operation),
+        }
+    }
+}
+
+pub(crate) fn stored_documents(
+    reader: &heed::RoTxn,
+    index: &store::Index,
+    documents_ids: &[DocumentId],
+) -> MResult<Vec<HashMap<String, Value>>> {
+    let schema = match index.main.schema(reader)? {
+        Some(schema) => schema,
+        None => return Err(Error::SchemaMissing),
+    };
+
+    let mut documents = Vec::with_capacity(documents_ids.len());
+    for document_id in documents_ids {
+        let mut deserializer = Deserializer {
+            document_id: *document_id,
+            reader,
+            documents_fields: index.documents_fields,
+            schema: &schema,
+            attributes: None,
+        };
+
+        if let Some(document) = Option::<HashMap<String, Value>>::deserialize(&mut deserializer)? {
+            documents.push(document);
+        }
+    }
+
+    Ok(documents)
+}
+
+/// An operation of the log along with its position, the sequence numbers
+/// are strictly increasing across all the indexes of the database.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoggedOperation {
+    pub sequence: u64,
+    pub index_uid: String,
+    /// The update that applied the operation, none for the creations and deletions of indexes.
+    pub update_id: Option<u64>,
+    pub operation: Operation,
+    pub logged_at: DateTime<Utc>,
+}
+
+/// The persistent log of the operations applied to the database, for the external consumers
+/// building a change data capture, the operations are only logged once it is enabled and
+/// are written in the same transaction as the updates that apply them.
+#[derive(Copy, Clone)]
+pub struct OperationsLog {
+    pub(crate) operations: heed::Database<OwnedType<BEU64>, SerdeJson<LoggedOperation>>,
+    pub(crate) common: heed::PolyDatabase,
+}
+
+impl OperationsLog {
+    pub fn is_enabled(self, reader: &heed::RoTxn) -> ZResult<bool> {
+        let enabled = self
+            .common
+            .get::<Str, SerdeBincode<bool>>(reader, OPERATIONS_LOG_ENABLED_KEY)?;
+        Ok(enabled.unwrap_or(false))
+    }
+
+    /// The already logged operations are kept when the log is disabled.
+    pub fn set_enabled(self, writer: &mut heed::RwTxn, enabled: bool) -> ZResult<()> {
+        self.common
+            .put::<Str, SerdeBincode<bool>>(writer, OPERATIONS_LOG_ENABLED_KEY, &enabled)
+    }
+
+    pub fn last_sequence(self, reader: &heed::RoTxn) -> ZResult<Option<u64>> {
+        match self.operations.last(reader)? {
+            Some((key, _)) => Ok(Some(key.get())),
+            None => Ok(None),
+        }
+    }
+
+    /// Appends the operation to the log if it is enabled and returns its sequence number.
+    pub(crate) fn append(
+        self,
+        writer: &mut heed::RwTxn,
+        index_uid: &str,
+        update_id: Option<u64>,
+        operation: Operation,
+    ) -> ZResult<Option<u64>> {
+        if !self.is_enabled(writer)? {
+            return Ok(None);
+        }
+
+        // the sequence numbers start at one and are never reused, even after a truncation
+        let sequence = self.last_sequence(writer)?.map_or(1, |n| n + 1);
+        let sequence = match self.truncated_up_to(writer)? {
+            Some(truncated) if truncated >= sequence => truncated + 1,
+            _ => sequence,
+        };
+
+        let logged = LoggedOperation {
+            sequence,
+            index_uid: index_uid.to_owned(),
+            update_id,
+            operation,
+            logged_at: Utc::now(),
+        };
+
+        self.operations
+            .put(writer, &BEU64::new(sequence), &logged)?;
+        Ok(Some(sequence))
+    }
+
+    /// The operations following the given sequence number, at most `limit` of them,
+    /// a consumer gives the sequence number of the last operation it has processed.
+    pub fn since(
+        self,
+        reader: &heed::RoTxn,
+        sequence: u64,
+        limit: usize,
+    ) -> ZResult<Vec<LoggedOperation>> {
+        let start = BEU64::new(sequence.saturating_add(1));
+        let mut operations = Vec::new();
+        for result in self.operations.range(reader, &(start..))?.take(limit) {
+            let (_, operation) = result?;
+            operations.push(operation);
+        }
+        Ok(operations)
+    }
+
+    /// Removes the operations up to the given sequence number, once all the consumers
+    /// processed them, and returns the number of operations removed.
+    pub fn truncate(self, writer: &mut heed::RwTxn, sequence: u64) -> ZResult<usize> {
+        let mut sequences = Vec::new();
+        for result in self.operations.range(writer, &(..=BEU64::new(sequence)))? {
+            let (key, _) = result?;
+            sequences.push(key.get());
+        }
+
+        for sequence in &sequences {
+            self.operations.delete(writer, &BEU64::new(*sequence))?;
+        }
+
+        let truncated = self.truncated_up_to(writer)?;
+        if truncated.map_or(true, |truncated| truncated < sequence) {
+            self.common.put::<Str, OwnedType<BEU64>>(
+                writer,
+                TRUNCATED_UP_TO_KEY,
+                &BEU64::new(sequence),
+            )?;
+        }
+
+        Ok(sequences.len())
+    }
+
+    fn truncated_up_to(self, reader: &heed::RoTxn) -> ZResult<Option<u64>> {
+        let truncated = self
+            .common
+            .get::<Str, OwnedType<BEU64>>(reader, TRUNCATED_UP_TO_KEY)?;
+        Ok(truncated.map(|n| n.get()))
+    }
+}
@@ -1,29 +1,88 @@
-use hashbrown::HashMap;
+use hashbrown::{HashMap, HashSet};
+use std::borrow::Cow;
+use std::collections::hash_map::DefaultHasher;
 use std::convert::TryFrom;
+use std::hash::{Hash, Hasher};
 use std::ops::Range;
 use std::rc::Rc;
 use std::time::{Duration, Instant};
 use std::{cmp, mem};
 
 use fst::{IntoStreamer, Streamer};
+use log::debug;
+use meilidb_schema::SchemaAttr;
 use sdset::SetBuf;
 use slice_group_by::{GroupBy, GroupByMut};
 
-use crate::automaton::{Automaton, AutomatonGroup, AutomatonProducer, QueryEnhancer};
+use crate::automaton::{
+    Automaton, AutomatonGroup, AutomatonProducer, PatternOptions, QueryEnhancer, QueryRewrite,
+    WordDfa,
+};
+use crate::criterion::{Criteria, RankingRule};
 use crate::distinct_map::{BufferedDistinctMap, DistinctMap};
+use crate::diversity::diversify;
+use crate::explain::{explain_documents, query_rewrites, Explanation};
+use crate::filters::{CandidatesFilter, Filter};
 use crate::levenshtein::prefix_damerau_levenshtein;
-use crate::raw_document::{raw_documents_from, RawDocument};
-use crate::{criterion::Criteria, Document, DocumentId, Highlight, TmpMatch};
-use crate::{reordered_attrs::ReorderedAttrs, store, MResult};
+use crate::raw_document::{raw_documents_from, DocumentsMatches, MatchesStream, RawDocument};
+use crate::spill::MatchesSpiller;
+use crate::update::check_searchable;
+use crate::{reordered_attrs::ReorderedAttrs, store, Error, LimitExceeded, MResult};
+use crate::{BoundingBox, GeoArea, GeoPoint};
+use crate::{DocIndex, Document, DocumentId, Highlight, Number, TmpMatch};
+
+/// The time spent in each phase of a query.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct QueryTimings {
+    /// The time spent to build the automatons of the query words.
+    pub parse: Duration,
+    /// The time spent to retrieve the documents matching the automatons.
+    pub candidates: Duration,
+    /// The time spent to sort and select the documents.
+    pub ranking: Duration,
+}
+
+/// The documents returned by a query along with the ranking buckets they belong to.
+#[derive(Debug, Default, Clone)]
+pub struct QueryResult {
+    pub documents: Vec<Document>,
+    /// The offsets in the documents at which a ranking bucket starts, a bucket
+    /// is a group of documents considered equal by all of the criteria.
+    pub buckets_starts: Vec<usize>,
+    /// The name of the first criterion that ranks each document after the previous
+    /// one, the first document and the documents in the same bucket have none.
+    pub decisive_criteria: Vec<Option<String>>,
+    /// The explanations of the ranking of the documents, in the order of the
+    /// documents, they are only computed when the query builder explains.
+    pub explanations: Vec<Explanation>,
+    /// The changes made to the words of the query to search the documents,
+    /// they are only listed when the query builder explains.
+    pub rewrites: Vec<QueryRewrite>,
+    pub timings: QueryTimings,
+}
 
 pub struct QueryBuilder<'c, 'f, 'd> {
-    criteria: Criteria<'c>,
+    /// The criteria of the index settings are used when none are given.
+    criteria: Option<Criteria<'c>>,
     searchable_attrs: Option<ReorderedAttrs>,
+    restricted_attrs: Option<Vec<SchemaAttr>>,
     filter: Option<Box<dyn Fn(DocumentId) -> bool + 'f>>,
+    filter_expression: Option<Filter>,
+    number_ranges: Vec<(SchemaAttr, Range<Number>)>,
+    required_attribute: Option<SchemaAttr>,
+    geo_area: Option<GeoArea>,
+    allowed_documents: Option<HashSet<DocumentId>>,
     distinct: Option<(Box<dyn Fn(DocumentId) -> Option<u64> + 'd>, usize)>,
+    distinct_attribute: Option<(SchemaAttr, usize)>,
+    diversity: Option<(Box<dyn Fn(DocumentId) -> Option<u64> + 'd>, usize)>,
     timeout: Option<Duration>,
+    memory_limit: Option<usize>,
+    patterns: Option<PatternOptions>,
+    prefix: Option<bool>,
+    explain: bool,
     main_store: store::Main,
     postings_lists_store: store::PostingsLists,
+    documents_fields_store: store::DocumentsFields,
     documents_fields_counts_store: store::DocumentsFieldsCounts,
     synonyms_store: store::Synonyms,
 }
@@ -138,170 +197,320 @@ fn multiword_rewrite_matches(
     SetBuf::new_unchecked(padded_matches)
 }
 
-fn fetch_raw_documents(
-    reader: &heed::RoTxn,
+/// Keeps the matches of the words of a phrase that follow each other in the same attribute,
+/// the matches are given with the position of their word in the phrase.
+fn following_matches(
+    mut matches: Vec<(DocumentId, usize, TmpMatch, Highlight)>,
+    phrase_len: usize,
+) -> Vec<(DocumentId, TmpMatch, Highlight)> {
+    let mut phrase_matches = Vec::new();
+
+    matches.sort_unstable_by_key(|(id, _, m, _)| (*id, m.attribute, m.word_index));
+    for group in matches.linear_group_by_key(|(id, _, m, _)| (*id, m.attribute)) {
+        for (i, (_, position, first, _)) in group.iter().enumerate() {
+            if *position != 0 {
+                continue;
+            }
+
+            let mut phrase = Vec::with_capacity(phrase_len);
+            phrase.push(group[i]);
+
+            for next in 1..phrase_len {
+                let word_index = match first.word_index.checked_add(next as u16) {
+                    Some(word_index) => word_index,
+                    None => break,
+                };
+
+                let following = group[i..]
+                    .iter()
+                    .take_while(|(_, _, m, _)| m.word_index <= word_index)
+                    .find(|(_, p, m, _)| *p == next && m.word_index == word_index);
+
+                match following {
+                    Some(following) => phrase.push(*following),
+                    None => break,
+                }
+            }
+
+            if phrase.len() == phrase_len {
+                phrase_matches.extend(phrase.into_iter().map(|(id, _, m, h)| (id, m, h)));
+            }
+        }
+    }
+
+    // a match can be part of multiple phrases, e.g. "la la" in "la la la"
+    phrase_matches.sort_unstable();
+    phrase_matches.dedup();
+    phrase_matches
+}
+
+fn fetch_raw_documents<'a>(
+    reader: &'a heed::RoTxn,
     automatons_groups: &[AutomatonGroup],
     query_enhancer: &QueryEnhancer,
-    searchables: Option<&ReorderedAttrs>,
+    searchables: Option<&'a ReorderedAttrs>,
+    candidates: Option<&'a CandidatesFilter<'a>>,
+    memory_limit: Option<usize>,
     main_store: store::Main,
     postings_lists_store: store::PostingsLists,
     documents_fields_counts_store: store::DocumentsFieldsCounts,
 ) -> MResult<Vec<RawDocument>> {
-    let mut matches = Vec::new();
-    let mut highlights = Vec::new();
+    // the matches of each query word are streamed from the postings
+    // lists which are already ordered by document id
+    let mut streams: Vec<MatchesStream> = Vec::new();
+    // the borrowed postings lists are read from the memory-mapped database, the ones
+    // decoded in memory are spilled one by one to disk when exceeding the memory limit
+    let mut spiller = memory_limit.map(MatchesSpiller::new);
+    let mut dfas_build_time = Duration::default();
+    let typos = main_store.typos_table(reader)?.unwrap_or_default();
+    let frozen_words = main_store.frozen_words_fst(reader)?.unwrap_or_default();
 
     for group in automatons_groups {
         let AutomatonGroup {
             is_phrase_query,
             automatons,
         } = group;
-        let phrase_query_len = automatons.len();
 
-        let mut tmp_matches = Vec::new();
-        for (id, automaton) in automatons.into_iter().enumerate() {
+        // the matches of the words that must follow each other by phrase, the automatons
+        // of a phrase query group form a single phrase, the quoted words form their own ones
+        let mut phrases_lens = HashMap::new();
+        let mut phrases_matches = HashMap::new();
+
+        for (id, automaton) in automatons.iter().enumerate() {
             let Automaton {
                 index,
                 is_exact,
                 query_len,
                 query,
+                penalty,
+                phrase,
                 ..
             } = automaton;
-            let dfa = automaton.dfa();
+
+            let phrase = if *is_phrase_query {
+                Some((0, id))
+            } else {
+                *phrase
+            };
+            if let Some((phrase, _)) = phrase {
+                *phrases_lens.entry(phrase).or_insert(0) += 1;
+            }
+
+            let start = Instant::now();
+            let dfa = automaton.dfa(&typos, &frozen_words);
+            dfas_build_time += start.elapsed();
 
             let words = match main_store.words_fst(reader)? {
                 Some(words) => words,
                 None => return Ok(Vec::new()),
             };
 
+            let mut expansions = 0;
             let mut stream = words.search(&dfa).into_stream();
             while let Some(input) = stream.next() {
-                let distance = dfa.eval(input).to_u8();
-                let is_exact = *is_exact && distance == 0 && input.len() == *query_len;
+                let (distance, is_exact, covered_area) = match &dfa {
+                    WordDfa::Levenshtein(dfa) => {
+                        let distance = dfa.eval(input).to_u8();
 
-                let covered_area = if *query_len > input.len() {
-                    input.len()
-                } else {
-                    prefix_damerau_levenshtein(query.as_bytes(), input).1
+                        // the frozen words are never matched with typos
+                        if distance != 0 && frozen_words.contains(input) {
+                            continue;
+                        }
+                        let is_exact = *is_exact && distance == 0 && input.len() == *query_len;
+
+                        let covered_area = if *query_len > input.len() {
+                            input.len()
+                        } else {
+                            prefix_damerau_levenshtein(query.as_bytes(), input).1
+                        };
+
+                        (distance, is_exact, covered_area)
+                    }
+                    WordDfa::Pattern(pattern) => {
+                        if expansions == pattern.max_expansions {
+                            break;
+                        }
+                        expansions += 1;
+
+                        (0, pattern.is_exact_match(input), input.len())
+                    }
                 };
 
+                // the matches of a penalized synonym alternative are never exact and
+                // considered as having more typos, the typos criterion supports up to 3
+                let distance = cmp::min(distance + *penalty, 3);
+                let is_exact = is_exact && *penalty == 0;
+
                 let doc_indexes = match postings_lists_store.postings_list(reader, input)? {
                     Some(doc_indexes) => doc_indexes,
                     None => continue,
                 };
 
-                tmp_matches.reserve(doc_indexes.len());
+                let query_index = *index as u32;
+                let covered_area = u16::try_from(covered_area).unwrap_or(u16::max_value());
 
-                for di in doc_indexes.as_ref() {
-                    let attribute = searchables.map_or(Some(di.attribute), |r| r.get(di.attribute));
-                    if let Some(attribute) = attribute {
-                        let match_ = TmpMatch {
-                            query_index: *index as u32,
-                            distance,
-                            attribute,
-                            word_index: di.word_index,
-                            is_exact,
-                        };
+                let to_match = move |di: &DocIndex| {
+                    let attribute =
+                        searchables.map_or(Some(di.attribute), |r| r.get(di.attribute))?;
+                    let match_ = TmpMatch {
+                        query_index,
+                        distance,
+                        attribute,
+                        word_index: di.word_index,
+                        is_exact,
+                    };
 
-                        let covered_area = u16::try_from(covered_area).unwrap_or(u16::max_value());
-                        let covered_area = cmp::min(covered_area, di.char_length);
+                    let highlight = Highlight {
+                        attribute: di.attribute,
+                        char_index: di.char_index,
+                        char_length: cmp::min(covered_area, di.char_length),
+                    };
 
-                        let highlight = Highlight {
-                            attribute: di.attribute,
-                            char_index: di.char_index,
-                            char_length: covered_area,
-                        };
+                    Some((di.document_id, match_, highlight))
+                };
 
-                        tmp_matches.push((di.document_id, id, match_, highlight));
-                    }
+                if let Some((phrase, position)) = phrase {
+                    let matches = phrases_matches.entry(phrase).or_insert_with(Vec::new);
+                    matches.reserve(doc_indexes.len());
+                    let iter = doc_indexes.as_ref().iter().filter_map(to_match);
+                    matches.extend(iter.map(|(di, m, h)| (di, position, m, h)));
+                } else {
+                    let stream: MatchesStream = match doc_indexes {
+                        Cow::Borrowed(doc_indexes) => {
+                            Box::new(doc_indexes.as_ref().iter().filter_map(to_match))
+                        }
+                        Cow::Owned(doc_indexes) => match spiller.as_mut() {
+                            Some(spiller) => {
+                                spiller
+                                    .push_run(doc_indexes.as_ref().iter().filter_map(to_match))?;
+                                continue;
+                            }
+                            None => {
+                                let doc_indexes = doc_indexes.into_vec();
+                                let iter = doc_indexes.into_iter();
+                                Box::new(iter.filter_map(move |di| to_match(&di)))
+                            }
+                        },
+                    };
+                    streams.push(stream);
                 }
             }
         }
 
-        if *is_phrase_query {
-            tmp_matches.sort_unstable_by_key(|(id, _, m, _)| (*id, m.attribute, m.word_index));
-            for group in tmp_matches.linear_group_by_key(|(id, _, m, _)| (*id, m.attribute)) {
-                for window in group.windows(2) {
-                    let (ida, ia, ma, ha) = window[0];
-                    let (idb, ib, mb, hb) = window[1];
-
-                    debug_assert_eq!(ida, idb);
-
-                    // if matches must follow and actually follows themselves
-                    if ia + 1 == ib && ma.word_index + 1 == mb.word_index {
-                        // TODO we must make it work for phrase query longer than 2
-                        // if the second match is the last phrase query word
-                        if ib + 1 == phrase_query_len {
-                            // insert first match
-                            matches.push((ida, ma));
-                            highlights.push((ida, ha));
-
-                            // insert second match
-                            matches.push((idb, mb));
-                            highlights.push((idb, hb));
-                        }
-                    }
-                }
-            }
-        } else {
-            for (id, _, match_, highlight) in tmp_matches {
-                matches.push((id, match_));
-                highlights.push((id, highlight));
+        for (phrase, matches) in phrases_matches {
+            let phrase_matches = following_matches(matches, phrases_lens[&phrase]);
+
+            // the phrase matches are ordered by document id
+            match spiller.as_mut() {
+                Some(spiller) => spiller.push_run(phrase_matches)?,
+                None => streams.push(Box::new(phrase_matches.into_iter())),
             }
         }
     }
 
-    let matches = multiword_rewrite_matches(matches, &query_enhancer);
-    let highlights = {
-        highlights.sort_unstable_by_key(|(id, _)| *id);
-        SetBuf::new_unchecked(highlights)
+    if let Some(spiller) = spiller {
+        debug!(
+            "matches spilled in {} runs, at most {} matches buffered",
+            spiller.spilled_runs(),
+            spiller.peak_buffered()
+        );
+        streams.extend(spiller.into_streams()?);
+    }
+
+    debug!("automatons DFAs built in {:.02?}", dfas_build_time);
+
+    // the documents rejected by the filter or without a match
+    // in the required attribute never reach the criteria
+    let documents = match candidates {
+        Some(candidates) => DocumentsMatches::new(streams).with_filter(candidates),
+        None => DocumentsMatches::new(streams),
     };
+    let documents = documents.map(|(id, matches, highlights)| -> MResult<_> {
+        let matches = matches.into_iter().map(|m| (id, m)).collect();
+        let matches = multiword_rewrite_matches(matches, &query_enhancer);
 
-    let fields_counts = {
+        // the fields counts are compared with the matches, by reordered attribute
         let mut fields_counts = Vec::new();
-        for group in matches.linear_group_by_key(|(id, ..)| *id) {
-            let id = group[0].0;
-            for result in documents_fields_counts_store.document_fields_counts(reader, id)? {
-                let (attr, count) = result?;
-                fields_counts.push((id, attr, count));
+        for result in documents_fields_counts_store.document_fields_counts(reader, id)? {
+            let (attribute, count) = result?;
+            let attribute = searchables.map_or(Some(attribute.0), |r| r.get(attribute.0));
+            if let Some(attribute) = attribute {
+                fields_counts.push((SchemaAttr(attribute), count));
             }
         }
-        SetBuf::new(fields_counts).unwrap()
-    };
+        let fields_counts = SetBuf::from_dirty(fields_counts);
 
-    Ok(raw_documents_from(matches, highlights, fields_counts))
+        Ok((id, matches, highlights, fields_counts))
+    });
+
+    raw_documents_from(documents, query_enhancer.query_len())
 }
 
 impl<'c, 'f, 'd> QueryBuilder<'c, 'f, 'd> {
     pub fn new(
         main: store::Main,
         postings_lists: store::PostingsLists,
+        documents_fields: store::DocumentsFields,
         documents_fields_counts: store::DocumentsFieldsCounts,
         synonyms: store::Synonyms,
     ) -> QueryBuilder<'c, 'f, 'd> {
-        QueryBuilder::with_criteria(
+        QueryBuilder::with_optional_criteria(
             main,
             postings_lists,
+            documents_fields,
             documents_fields_counts,
             synonyms,
-            Criteria::default(),
+            None,
         )
     }
 
     pub fn with_criteria(
         main: store::Main,
         postings_lists: store::PostingsLists,
+        documents_fields: store::DocumentsFields,
         documents_fields_counts: store::DocumentsFieldsCounts,
         synonyms: store::Synonyms,
         criteria: Criteria<'c>,
+    ) -> QueryBuilder<'c, 'f, 'd> {
+        QueryBuilder::with_optional_criteria(
+            main,
+            postings_lists,
+            documents_fields,
+            documents_fields_counts,
+            synonyms,
+            Some(criteria),
+        )
+    }
+
+    fn with_optional_criteria(
+        main: store::Main,
+        postings_lists: store::PostingsLists,
+        documents_fields: store::DocumentsFields,
+        documents_fields_counts: store::DocumentsFieldsCounts,
+        synonyms: store::Synonyms,
+        criteria: Option<Criteria<'c>>,
     ) -> QueryBuilder<'c, 'f, 'd> {
         QueryBuilder {
             criteria,
             searchable_attrs: None,
+            restricted_attrs: None,
             filter: None,
+            filter_expression: None,
+            number_ranges: Vec::new(),
+            required_attribute: None,
+            geo_area: None,
+            allowed_documents: None,
             distinct: None,
+            distinct_attribute: None,
+            diversity: None,
             timeout: None,
+            memory_limit: None,
+            patterns: None,
+            prefix: None,
+            explain: false,
             main_store: main,
             postings_lists_store: postings_lists,
+            documents_fields_store: documents_fields,
             documents_fields_counts_store: documents_fields_counts,
             synonyms_store: synonyms,
         }
@@ -316,10 +525,72 @@ impl<'c, 'f, 'd> QueryBuilder<'c, 'f, 'd> {
         self.filter = Some(Box::new(function))
     }
 
+    /// Evaluates the filter against the stored attributes of the documents matching
+    /// the query, the documents rejected are removed before being ranked.
+    pub fn with_filter_expression(&mut self, filter: Filter) {
+        self.filter_expression = Some(filter)
+    }
+
+    /// Keeps only the documents whose ranked value of the attribute is in the range,
+    /// the documents without a value are removed before being ranked.
+    pub fn with_number_range(&mut self, attribute: SchemaAttr, range: Range<Number>) {
+        self.number_ranges.push((attribute, range))
+    }
+
+    /// Keeps only the documents where at least one of the query words matches in the
+    /// attribute, the other query words can match in any of the searchable attributes.
+    pub fn with_required_attribute(&mut self, attribute: SchemaAttr) {
+        self.required_attribute = Some(attribute)
+    }
+
+    /// Keeps only the documents located less than the distance in meters from the point,
+    /// the locations are given by the `_geo` field of the documents.
+    pub fn within_radius(&mut self, lat: f64, lng: f64, meters: f64) {
+        let center = GeoPoint { lat, lng };
+        self.geo_area = Some(GeoArea::Radius { center, meters })
+    }
+
+    /// Keeps only the documents located in the box.
+    pub fn within_bounding_box(&mut self, bbox: BoundingBox) {
+        self.geo_area = Some(GeoArea::BoundingBox(bbox))
+    }
+
+    /// Searches only in the given documents, the other documents are skipped before
+    /// being ranked, calling it again intersects the given documents with the previous ones.
+    pub fn restrict_documents<I>(&mut self, documents_ids: I)
+    where
+        I: IntoIterator<Item = DocumentId>,
+    {
+        let documents_ids: HashSet<_> = documents_ids.into_iter().collect();
+        self.allowed_documents = match self.allowed_documents.take() {
+            Some(allowed) => Some(allowed.intersection(&documents_ids).cloned().collect()),
+            None => Some(documents_ids),
+        };
+    }
+
     pub fn with_fetch_timeout(&mut self, timeout: Duration) {
         self.timeout = Some(timeout)
     }
 
+    /// Bounds the memory used to buffer the matches of the query words to `bytes`,
+    /// the matches exceeding it are written to a temporary file and merged from disk.
+    /// The matches of the documents that reach the ranking are still kept in memory.
+    pub fn with_memory_limit(&mut self, bytes: usize) {
+        self.memory_limit = Some(bytes)
+    }
+
+    /// Enables the matching of the query words that are patterns,
+    /// words with a trailing wildcard or character classes.
+    pub fn with_patterns(&mut self, options: PatternOptions) {
+        self.patterns = Some(options)
+    }
+
+    /// Whether the last query word matches the words it is a prefix of, the other
+    /// words are always complete words, the setting of the index is used when not given.
+    pub fn with_prefix(&mut self, prefix: bool) {
+        self.prefix = Some(prefix)
+    }
+
     pub fn with_distinct<F, K>(&mut self, function: F, size: usize)
     where
         F: Fn(DocumentId) -> Option<u64> + 'd,
@@ -327,6 +598,28 @@ impl<'c, 'f, 'd> QueryBuilder<'c, 'f, 'd> {
         self.distinct = Some((Box::new(function), size))
     }
 
+    /// Keeps at most `size` documents for each value of the attribute, the best ranked ones,
+    /// the documents without this attribute are all kept. A distinct function takes precedence.
+    pub fn with_distinct_attribute(&mut self, attribute: SchemaAttr, size: usize) {
+        self.distinct_attribute = Some((attribute, size))
+    }
+
+    /// Reorders the results, once sorted by the criteria, to avoid consecutive
+    /// ones sharing the same key, the strength is the number of following
+    /// results that can be promoted over a result repeating the previous key.
+    pub fn with_diversity<F>(&mut self, function: F, strength: usize)
+    where
+        F: Fn(DocumentId) -> Option<u64> + 'd,
+    {
+        self.diversity = Some((Box::new(function), strength))
+    }
+
+    /// Explains the ranking of the returned documents with the values of the criteria
+    /// and the words of the documents matched along with the query words they come from.
+    pub fn explain(&mut self) {
+        self.explain = true;
+    }
+
     pub fn add_searchable_attribute(&mut self, attribute: u16) {
         let reorders = self
             .searchable_attrs
@@ -334,23 +627,220 @@ impl<'c, 'f, 'd> QueryBuilder<'c, 'f, 'd> {
         reorders.insert_attribute(attribute);
     }
 
+    /// Only the words of these attributes match the query words and are highlighted,
+    /// the attributes keep the priority the searchable attributes give them in the ranking.
+    pub fn restrict_searchable_attributes<I>(&mut self, attributes: I)
+    where
+        I: IntoIterator<Item = SchemaAttr>,
+    {
+        let restricted = self.restricted_attrs.get_or_insert_with(Vec::new);
+        restricted.extend(attributes);
+    }
+
     pub fn query(
         self,
         reader: &heed::RoTxn,
         query: &str,
         range: Range<usize>,
     ) -> MResult<Vec<Document>> {
-        match self.distinct {
+        self.query_with_timings(reader, query, range)
+            .map(|(documents, _)| documents)
+    }
+
+    /// Executes the query and returns the time spent in each phase along with the documents.
+    pub fn query_with_timings(
+        self,
+        reader: &heed::RoTxn,
+        query: &str,
+        range: Range<usize>,
+    ) -> MResult<(Vec<Document>, QueryTimings)> {
+        self.query_with_buckets(reader, query, range)
+            .map(|result| (result.documents, result.timings))
+    }
+
+    /// Executes the query and returns the boundaries of the ranking buckets along with the
+    /// documents. The diversity reorders the documents across the buckets, every document
+    /// is then considered to be in its own bucket.
+    pub fn query_with_buckets(
+        mut self,
+        reader: &heed::RoTxn,
+        query: &str,
+        range: Range<usize>,
+    ) -> MResult<QueryResult> {
+        let max_total_hits = self.main_store.max_total_hits(reader)?;
+        let range = match max_total_hits {
+            Some(limit) => clamp_range(range, limit as usize)?,
+            None => range,
+        };
+
+        match self.diversity.take() {
+            Some((diversity, strength)) => {
+                // the documents following the requested range can be promoted,
+                // they must be sorted along with the documents preceding it
+                let sorted_range = 0..range.end.saturating_add(strength);
+                let sorted_range = match max_total_hits {
+                    Some(limit) => 0..cmp::min(sorted_range.end, limit as usize),
+                    None => sorted_range,
+                };
+                let result = self.sorted_query(reader, query, sorted_range)?;
+                let mut timings = result.timings;
+
+                let start = Instant::now();
+                // the explanations follow their documents
+                let mut explanations = result.explanations.into_iter();
+                let documents: Vec<_> = result
+                    .documents
+                    .into_iter()
+                    .map(|document| (document, explanations.next()))
+                    .collect();
+                let documents = diversify(
+                    documents,
+                    |(document, _)| (diversity)(document.id),
+                    strength,
+                );
+                let iter = documents.into_iter().skip(range.start).take(range.len());
+                let (documents, explanations): (Vec<_>, Vec<_>) = iter.unzip();
+                let explanations = explanations.into_iter().flatten().collect();
+                timings.ranking += start.elapsed();
+
+                let buckets_starts = (0..documents.len()).collect();
+                let decisive_criteria = vec![None; documents.len()];
+                Ok(QueryResult {
+                    documents,
+                    buckets_starts,
+                    decisive_criteria,
+                    explanations,
+                    rewrites: result.rewrites,
+                    timings,
+                })
+            }
+            None => self.sorted_query(reader, query, range),
+        }
+    }
+
+    fn sorted_query(
+        self,
+        reader: &heed::RoTxn,
+        query: &str,
+        range: Range<usize>,
+    ) -> MResult<QueryResult> {
+        let candidates = if self.filter_expression.is_some()
+            || !self.number_ranges.is_empty()
+            || self.required_attribute.is_some()
+            || self.geo_area.is_some()
+            || self.allowed_documents.is_some()
+        {
+            Some(CandidatesFilter::new(
+                reader,
+                self.filter_expression.as_ref(),
+                self.number_ranges,
+                self.required_attribute,
+                self.geo_area,
+                self.allowed_documents,
+                self.main_store,
+                self.documents_fields_store,
+            )?)
+        } else {
+            None
+        };
+
+        // the documents of an index without searchable attributes can never be found
+        if let Some(schema) = self.main_store.schema(reader)? {
+            let searchable_attributes = self.main_store.searchable_attributes(reader)?;
+            check_searchable(&schema, searchable_attributes.as_ref().map(Vec::as_slice))?;
+        }
+
+        // the criteria of the settings sort by the ranked map of the reader
+        let ranked_map;
+        let criteria = match self.criteria {
+            Some(criteria) => criteria,
+            None => match (
+                self.main_store.criteria(reader)?,
+                self.main_store.schema(reader)?,
+            ) {
+                (Some(rules), Some(schema)) => {
+                    ranked_map = self.main_store.ranked_map(reader)?.unwrap_or_default();
+                    RankingRule::criteria(&rules, &ranked_map, &schema)
+                }
+                _ => Criteria::default(),
+            },
+        };
+
+        // the order of the searchable attributes of the settings
+        // defines the priority of the attributes in the ranking
+        let searchable_attrs = match self.searchable_attrs {
+            Some(searchable_attrs) => Some(searchable_attrs),
+            None => match (
+                self.main_store.searchable_attributes(reader)?,
+                self.main_store.schema(reader)?,
+            ) {
+                (Some(names), Some(schema)) => {
+                    let mut searchable_attrs = ReorderedAttrs::new();
+                    for attribute in names.iter().filter_map(|name| schema.attribute(name)) {
+                        searchable_attrs.insert_attribute(attribute.0);
+                    }
+                    Some(searchable_attrs)
+                }
+                _ => None,
+            },
+        };
+
+        // the matches of the attributes that are not restricted to are dropped
+        // before the documents are built, they neither rank nor highlight them
+        let searchable_attrs = match (searchable_attrs, self.restricted_attrs) {
+            (Some(searchable_attrs), Some(restricted)) => {
+                Some(searchable_attrs.restrict(|a| restricted.contains(&SchemaAttr(a))))
+            }
+            (None, Some(mut restricted)) => {
+                restricted.sort_unstable();
+                restricted.dedup();
+                let mut searchable_attrs = ReorderedAttrs::new();
+                for attribute in restricted {
+                    searchable_attrs.insert_attribute(attribute.0);
+                }
+                Some(searchable_attrs)
+            }
+            (searchable_attrs, None) => searchable_attrs,
+        };
+
+        // the distinct attribute is read from the stored fields of the reader
+        let documents_fields_store = self.documents_fields_store;
+        let distinct: Option<(Box<dyn Fn(DocumentId) -> Option<u64> + '_>, usize)> =
+            match (self.distinct, self.distinct_attribute) {
+                (Some((distinct, distinct_size)), _) => Some((distinct, distinct_size)),
+                (None, Some((attribute, distinct_size))) => {
+                    let distinct = move |id| match documents_fields_store
+                        .document_attribute(reader, id, attribute)
+                    {
+                        Ok(Some(bytes)) => {
+                            let mut hasher = DefaultHasher::new();
+                            bytes.hash(&mut hasher);
+                            Some(hasher.finish())
+                        }
+                        _ => None,
+                    };
+                    let distinct = Box::new(distinct) as Box<dyn Fn(DocumentId) -> Option<u64>>;
+                    Some((distinct, distinct_size))
+                }
+                (None, None) => None,
+            };
+
+        match distinct {
             Some((distinct, distinct_size)) => raw_query_with_distinct(
                 reader,
                 query,
                 range,
                 self.filter,
+                candidates.as_ref(),
                 distinct,
                 distinct_size,
                 self.timeout,
-                self.criteria,
-                self.searchable_attrs,
+                self.memory_limit,
+                self.patterns,
+                self.prefix,
+                self.explain,
+                criteria,
+                searchable_attrs,
                 self.main_store,
                 self.postings_lists_store,
                 self.documents_fields_counts_store,
@@ -361,9 +851,14 @@ impl<'c, 'f, 'd> QueryBuilder<'c, 'f, 'd> {
                 query,
                 range,
                 self.filter,
+                candidates.as_ref(),
                 self.timeout,
-                self.criteria,
-                self.searchable_attrs,
+                self.memory_limit,
+                self.patterns,
+                self.prefix,
+                self.explain,
+                criteria,
+                searchable_attrs,
                 self.main_store,
                 self.postings_lists_store,
                 self.documents_fields_counts_store,
@@ -373,6 +868,115 @@ impl<'c, 'f, 'd> QueryBuilder<'c, 'f, 'd> {
     }
 }
 
+/// Bounds the requested range to the maximum number of hits an index returns,
+/// a range starting after this bound cannot return anything and is refused.
+fn clamp_range(range: Range<usize>, limit: usize) -> MResult<Range<usize>> {
+    if range.start >= limit && !range.is_empty() {
+        let exceeded = LimitExceeded::TotalHitsExceeded {
+            limit,
+            offset: range.start,
+        };
+        return Err(Error::LimitExceeded(exceeded));
+    }
+
+    Ok(range.start..cmp::min(range.end, limit))
+}
+
+/// The offsets at which the returned documents enter a new group of the sorted documents,
+/// the documents are given by their positions in the sorted documents, in ascending order.
+fn buckets_starts<I>(groups_lens: &[usize], positions: I) -> Vec<usize>
+where
+    I: IntoIterator<Item = usize>,
+{
+    let mut starts = Vec::new();
+    let mut groups_lens = groups_lens.iter();
+    let mut group_end = 0;
+
+    for (offset, position) in positions.into_iter().enumerate() {
+        let mut new_bucket = offset == 0;
+        while position >= group_end {
+            match groups_lens.next() {
+                Some(len) => group_end += len,
+                // the document has not been sorted, it cannot be compared to the others
+                None => group_end = position + 1,
+            }
+            new_bucket = true;
+        }
+
+        if new_bucket {
+            starts.push(offset);
+        }
+    }
+
+    starts
+}
+
+/// The name of the first criterion that ranks each document after the previous one.
+fn decisive_criteria(criteria: &Criteria, documents: &[RawDocument]) -> Vec<Option<String>> {
+    let mut decisive = Vec::with_capacity(documents.len());
+    if !documents.is_empty() {
+        decisive.push(None);
+    }
+
+    for pair in documents.windows(2) {
+        let criterion = criteria
+            .as_ref()
+            .iter()
+            .find(|criterion| criterion.evaluate(&pair[0], &pair[1]) != cmp::Ordering::Equal);
+        decisive.push(criterion.map(|criterion| criterion.name().to_string()));
+    }
+
+    decisive
+}
+
+/// Highlights the query words that were not used to select the documents,
+/// they are only matched as they are written in the returned documents.
+fn highlight_ignored_words(
+    reader: &heed::RoTxn,
+    words: &[String],
+    searchables: Option<&ReorderedAttrs>,
+    postings_lists_store: store::PostingsLists,
+    documents: &mut [Document],
+) -> MResult<()> {
+    if documents.is_empty() {
+        return Ok(());
+    }
+
+    for word in words {
+        let doc_indexes = match postings_lists_store.postings_list(reader, word.as_bytes())? {
+            Some(doc_indexes) => doc_indexes,
+            None => continue,
+        };
+
+        for document in documents.iter_mut() {
+            // the position of the first posting of the document
+            let start = doc_indexes
+                .binary_search_by(|di| {
+                    if di.document_id < document.id {
+                        cmp::Ordering::Less
+                    } else {
+                        cmp::Ordering::Greater
+                    }
+                })
+                .unwrap_err();
+
+            let iter = doc_indexes[start..]
+                .iter()
+                .take_while(|di| di.document_id == document.id)
+                .filter(|di| searchables.map_or(true, |r| r.get(di.attribute).is_some()))
+                .map(|di| Highlight {
+                    attribute: di.attribute,
+                    char_index: di.char_index,
+                    char_length: di.char_length,
+                });
+
+            document.highlights.extend(iter);
+        }
+    }
+
+    Ok(())
+}
+
 fn raw_query<'c, FI>(
     reader: &heed::RoTxn,
 
@@ -380,7 +984,12 @@ fn raw_query<'c, FI>(
     range: Range<usize>,
 
     filter: Option<FI>,
+    candidates: Option<&CandidatesFilter>,
     timeout: Option<Duration>,
+    memory_limit: Option<usize>,
+    patterns: Option<PatternOptions>,
+    prefix: Option<bool>,
+    explain: bool,
 
     criteria: Criteria<'c>,
     searchable_attrs: Option<ReorderedAttrs>,
@@ -389,7 +998,7 @@ fn raw_query<'c, FI>(
     postings_lists_store: store::PostingsLists,
     documents_fields_counts_store: store::DocumentsFieldsCounts,
     synonyms_store: store::Synonyms,
-) -> MResult<Vec<Document>>
+) -> MResult<QueryResult>
 where
     FI: Fn(DocumentId) -> bool,
 {
@@ -403,9 +1012,14 @@ where
             query,
             range,
             filter,
+            candidates,
             distinct,
             distinct_size,
             timeout,
+            memory_limit,
+            patterns,
+            prefix,
+            explain,
             criteria,
             searchable_attrs,
             main_store,
@@ -416,7 +1030,9 @@ where
     }
 
     let start_processing = Instant::now();
-    let mut raw_documents_processed = Vec::with_capacity(range.len());
+    let mut timings = QueryTimings::default();
+    let mut raw_documents_processed = Vec::new();
+    let mut buckets = Vec::new();
 
     let (automaton_producer, query_enhancer) = AutomatonProducer::new(
         reader,
@@ -424,10 +1040,18 @@ where
         main_store,
         postings_lists_store,
         synonyms_store,
+        patterns,
+        prefix,
     )?;
 
+    timings.parse = start_processing.elapsed();
+    debug!("automatons generated in {:.02?}", timings.parse);
+
+    let ignored_words = automaton_producer.ignored_words().to_vec();
+    let rewrites = automaton_producer.rewrites().to_vec();
     let automaton_producer = automaton_producer.into_iter();
     let mut automatons = Vec::new();
+    let mut automatons_used = 0;
 
     // aggregate automatons groups by groups after time
     for auts in automaton_producer {
@@ -435,15 +1059,19 @@ where
 
         // we must retrieve the documents associated
         // with the current automatons
+        let start = Instant::now();
         let mut raw_documents = fetch_raw_documents(
             reader,
             &automatons,
             &query_enhancer,
             searchable_attrs.as_ref(),
+            candidates,
+            memory_limit,
             main_store,
             postings_lists_store,
             documents_fields_counts_store,
         )?;
+        timings.candidates += start.elapsed();
 
         // stop processing when time is running out
         if let Some(timeout) = timeout {
@@ -452,6 +1080,7 @@ where
             }
         }
 
+        let start = Instant::now();
         let mut groups = vec![raw_documents.as_mut_slice()];
 
         'criteria: for criterion in criteria.as_ref() {
@@ -482,6 +1111,8 @@ where
             }
         }
 
+        let groups_lens: Vec<_> = groups.iter().map(|group| group.len()).collect();
+
         // once we classified the documents related to the current
         // automatons we save that as the next valid result
         let iter = raw_documents
@@ -491,6 +1122,12 @@ where
         raw_documents_processed.clear();
         raw_documents_processed.extend(iter);
 
+        let positions = range.start..range.start + raw_documents_processed.len();
+        buckets = buckets_starts(&groups_lens, positions);
+        automatons_used = automatons.len();
+
+        timings.ranking += start.elapsed();
+
         // stop processing when time is running out
         if let Some(timeout) = timeout {
             if start_processing.elapsed() > timeout {
@@ -499,14 +1136,50 @@ where
         }
     }
 
+    let decisive_criteria = decisive_criteria(&criteria, &raw_documents_processed);
+    let explanations = if explain {
+        // only the automatons that selected the documents explain them
+        explain_documents(
+            reader,
+            &criteria,
+            &automatons[..automatons_used],
+            searchable_attrs.as_ref(),
+            main_store,
+            postings_lists_store,
+            &raw_documents_processed,
+        )?
+    } else {
+        Vec::new()
+    };
+    let rewrites = if explain {
+        query_rewrites(&rewrites, &automatons[..automatons_used], &explanations)
+    } else {
+        Vec::new()
+    };
+
     // make real documents now that we know
     // those must be returned
-    let documents = raw_documents_processed
+    let mut documents: Vec<_> = raw_documents_processed
         .into_iter()
         .map(Document::from_raw)
         .collect();
 
-    Ok(documents)
+    highlight_ignored_words(
+        reader,
+        &ignored_words,
+        searchable_attrs.as_ref(),
+        postings_lists_store,
+        &mut documents,
+    )?;
+
+    Ok(QueryResult {
+        documents,
+        buckets_starts: buckets,
+        decisive_criteria,
+        explanations,
+        rewrites,
+        timings,
+    })
 }
 
 fn raw_query_with_distinct<'c, FI, FD>(
@@ -516,10 +1189,15 @@ fn raw_query_with_distinct<'c, FI, FD>(
     range: Range<usize>,
 
     filter: Option<FI>,
+    candidates: Option<&CandidatesFilter>,
 
     distinct: FD,
     distinct_size: usize,
     timeout: Option<Duration>,
+    memory_limit: Option<usize>,
+    patterns: Option<PatternOptions>,
+    prefix: Option<bool>,
+    explain: bool,
 
     criteria: Criteria<'c>,
     searchable_attrs: Option<ReorderedAttrs>,
@@ -528,13 +1206,15 @@ fn raw_query_with_distinct<'c, FI, FD>(
     postings_lists_store: store::PostingsLists,
     documents_fields_counts_store: store::DocumentsFieldsCounts,
     synonyms_store: store::Synonyms,
-) -> MResult<Vec<Document>>
+) -> MResult<QueryResult>
 where
     FI: Fn(DocumentId) -> bool,
     FD: Fn(DocumentId) -> Option<u64>,
 {
     let start_processing = Instant::now();
+    let mut timings = QueryTimings::default();
     let mut raw_documents_processed = Vec::new();
+    let mut buckets = Vec::new();
 
     let (automaton_producer, query_enhancer) = AutomatonProducer::new(
         reader,
@@ -542,10 +1222,18 @@ where
         main_store,
         postings_lists_store,
         synonyms_store,
+        patterns,
+        prefix,
     )?;
 
+    timings.parse = start_processing.elapsed();
+    debug!("automatons generated in {:.02?}", timings.parse);
+
+    let ignored_words = automaton_producer.ignored_words().to_vec();
+    let rewrites = automaton_producer.rewrites().to_vec();
     let automaton_producer = automaton_producer.into_iter();
     let mut automatons = Vec::new();
+    let mut automatons_used = 0;
 
     // aggregate automatons groups by groups after time
     for auts in automaton_producer {
@@ -553,15 +1241,19 @@ where
 
         // we must retrieve the documents associated
         // with the current automatons
+        let start = Instant::now();
         let mut raw_documents = fetch_raw_documents(
             reader,
             &automatons,
             &query_enhancer,
             searchable_attrs.as_ref(),
+            candidates,
+            memory_limit,
             main_store,
             postings_lists_store,
             documents_fields_counts_store,
         )?;
+        timings.candidates += start.elapsed();
 
         // stop processing when time is running out
         if let Some(timeout) = timeout {
@@ -570,6 +1262,7 @@ where
             }
         }
 
+        let start = Instant::now();
         let mut groups = vec![raw_documents.as_mut_slice()];
         let mut key_cache = HashMap::new();
 
@@ -642,12 +1335,19 @@ where
             }
         }
 
+        let groups_lens: Vec<_> = groups.iter().map(|group| group.len()).collect();
+
         // once we classified the documents related to the current
         // automatons we save that as the next valid result
         let mut seen = BufferedDistinctMap::new(&mut distinct_map);
+        let mut positions = Vec::new();
         raw_documents_processed.clear();
 
-        for document in raw_documents.into_iter().skip(distinct_raw_offset) {
+        let iter = raw_documents
+            .into_iter()
+            .enumerate()
+            .skip(distinct_raw_offset);
+        for (position, document) in iter {
             let filter_accepted = match &filter {
                 Some(_) => filter_map.remove(&document.id).unwrap(),
                 None => true,
@@ -661,6 +1361,7 @@ where
                 };
 
                 if distinct_accepted && seen.len() > range.start {
+                    positions.push(position);
                     raw_documents_processed.push(document);
                     if raw_documents_processed.len() == range.len() {
                         break;
@@ -669,6 +1370,11 @@ where
             }
         }
 
+        buckets = buckets_starts(&groups_lens, positions);
+        automatons_used = automatons.len();
+
+        timings.ranking += start.elapsed();
+
         // stop processing when time is running out
         if let Some(timeout) = timeout {
             if start_processing.elapsed() > timeout {
@@ -677,14 +1383,50 @@ where
         }
     }
 
+    let decisive_criteria = decisive_criteria(&criteria, &raw_documents_processed);
+    let explanations = if explain {
+        // only the automatons that selected the documents explain them
+        explain_documents(
+            reader,
+            &criteria,
+            &automatons[..automatons_used],
+            searchable_attrs.as_ref(),
+            main_store,
+            postings_lists_store,
+            &raw_documents_processed,
+        )?
+    } else {
+        Vec::new()
+    };
+    let rewrites = if explain {
+        query_rewrites(&rewrites, &automatons[..automatons_used], &explanations)
+    } else {
+        Vec::new()
+    };
+
     // make real documents now that we know
     // those must be returned
-    let documents = raw_documents_processed
+    let mut documents: Vec<_> = raw_documents_processed
         .into_iter()
         .map(Document::from_raw)
         .collect();
 
-    Ok(documents)
+    highlight_ignored_words(
+        reader,
+        &ignored_words,
+        searchable_attrs.as_ref(),
+        postings_lists_store,
+        &mut documents,
+    )?;
+
+    Ok(QueryResult {
+        documents,
+        buckets_starts: buckets,
+        decisive_criteria,
+        explanations,
+        rewrites,
+        timings,
+    })
 }
 
 #[cfg(test)]
@@ -695,14 +1437,15 @@ mod tests {
     use std::iter::FromIterator;
 
     use fst::{IntoStreamer, Set};
-    use meilidb_schema::SchemaAttr;
     use sdset::SetBuf;
     use tempfile::TempDir;
 
     use crate::automaton::normalize_str;
+    use crate::criterion::{self, CriteriaBuilder};
     use crate::database::Database;
     use crate::store::Index;
-    use crate::DocIndex;
+    use crate::{AutomatonOrigin, MatchExplanation};
+    use crate::{DocIndex, QueryLimits, TyposTable, TyposThreshold};
 
     fn set_from_stream<'f, I, S>(stream: I) -> Set
     where
@@ -733,160 +1476,834 @@ mod tests {
         builder.into_inner().and_then(Set::from_bytes).unwrap()
     }
 
-    const fn doc_index(document_id: u64, word_index: u16) -> DocIndex {
-        DocIndex {
-            document_id: DocumentId(document_id),
-            attribute: 0,
-            word_index,
-            char_index: 0,
-            char_length: 0,
-        }
-    }
+    const fn doc_index(document_id: u64, word_index: u16) -> DocIndex {
+        DocIndex {
+            document_id: DocumentId(document_id),
+            attribute: 0,
+            word_index,
+            char_index: 0,
+            char_length: 0,
+        }
+    }
+
+    const fn doc_char_index(document_id: u64, word_index: u16, char_index: u16) -> DocIndex {
+        DocIndex {
+            document_id: DocumentId(document_id),
+            attribute: 0,
+            word_index,
+            char_index,
+            char_length: 0,
+        }
+    }
+
+    pub struct TempDatabase {
+        database: Database,
+        index: Index,
+        _tempdir: TempDir,
+    }
+
+    impl TempDatabase {
+        pub fn query_builder(&self) -> QueryBuilder {
+            self.index.query_builder()
+        }
+
+        pub fn add_synonym(&mut self, word: &str, new: SetBuf<&str>) {
+            let env = &self.database.env;
+            let mut writer = env.write_txn().unwrap();
+
+            let word = word.to_lowercase();
+
+            let alternatives = match self
+                .index
+                .synonyms
+                .synonyms(&writer, word.as_bytes())
+                .unwrap()
+            {
+                Some(alternatives) => alternatives,
+                None => fst::Set::default(),
+            };
+
+            let new = sdset_into_fstset(&new);
+            let new_alternatives =
+                set_from_stream(alternatives.op().add(new.into_stream()).r#union());
+            self.index
+                .synonyms
+                .put_synonyms(&mut writer, word.as_bytes(), &new_alternatives)
+                .unwrap();
+
+            let synonyms = match self.index.main.synonyms_fst(&writer).unwrap() {
+                Some(synonyms) => synonyms,
+                None => fst::Set::default(),
+            };
+
+            let synonyms_fst = insert_key(&synonyms, word.as_bytes());
+            self.index
+                .main
+                .put_synonyms_fst(&mut writer, &synonyms_fst)
+                .unwrap();
+
+            writer.commit().unwrap();
+        }
+
+        pub fn add_weighted_synonym(&mut self, word: &str, new: &[(&str, u8)]) {
+            let env = &self.database.env;
+            let mut writer = env.write_txn().unwrap();
+
+            let word = word.to_lowercase();
+
+            let mut new = new.to_vec();
+            new.sort_unstable();
+            let new = new.into_iter().map(|(a, p)| (a, u64::from(p)));
+            let new_alternatives = fst::Map::from_iter(new).unwrap();
+            self.index
+                .synonyms
+                .put_weighted_synonyms(&mut writer, word.as_bytes(), &new_alternatives)
+                .unwrap();
+
+            let synonyms = match self.index.main.synonyms_fst(&writer).unwrap() {
+                Some(synonyms) => synonyms,
+                None => fst::Set::default(),
+            };
+
+            let synonyms_fst = insert_key(&synonyms, word.as_bytes());
+            self.index
+                .main
+                .put_synonyms_fst(&mut writer, &synonyms_fst)
+                .unwrap();
+
+            writer.commit().unwrap();
+        }
+    }
+
+    impl<'a> FromIterator<(&'a str, &'a [DocIndex])> for TempDatabase {
+        fn from_iter<I: IntoIterator<Item = (&'a str, &'a [DocIndex])>>(iter: I) -> Self {
+            let tempdir = TempDir::new().unwrap();
+            let database = Database::open_or_create(&tempdir).unwrap();
+            let index = database.create_index("default").unwrap();
+
+            let env = &database.env;
+            let mut writer = env.write_txn().unwrap();
+
+            let mut words_fst = BTreeSet::new();
+            let mut postings_lists = HashMap::new();
+            let mut fields_counts = HashMap::<_, u64>::new();
+
+            for (word, indexes) in iter {
+                let word = word.to_lowercase().into_bytes();
+                words_fst.insert(word.clone());
+                postings_lists
+                    .entry(word)
+                    .or_insert_with(Vec::new)
+                    .extend_from_slice(indexes);
+                for idx in indexes {
+                    fields_counts.insert((idx.document_id, idx.attribute, idx.word_index), 1);
+                }
+            }
+
+            let words_fst = Set::from_iter(words_fst).unwrap();
+
+            index.main.put_words_fst(&mut writer, &words_fst).unwrap();
+
+            for (word, postings_list) in postings_lists {
+                let postings_list = SetBuf::from_dirty(postings_list);
+                index
+                    .postings_lists
+                    .put_postings_list(&mut writer, &word, &postings_list)
+                    .unwrap();
+            }
+
+            for ((docid, attr, _), count) in fields_counts {
+                let prev = index
+                    .documents_fields_counts
+                    .document_field_count(&mut writer, docid, SchemaAttr(attr))
+                    .unwrap();
+
+                let prev = prev.unwrap_or(0);
+
+                index
+                    .documents_fields_counts
+                    .put_document_field_count(&mut writer, docid, SchemaAttr(attr), prev + count)
+                    .unwrap();
+            }
+
+            writer.commit().unwrap();
+
+            TempDatabase {
+                database,
+                index,
+                _tempdir: tempdir,
+            }
+        }
+    }
+
+    #[test]
+    fn simple() {
+        let store = TempDatabase::from_iter(vec![
+            ("iphone", &[doc_char_index(0, 0, 0)][..]),
+            ("from", &[doc_char_index(0, 1, 1)][..]),
+            ("apple", &[doc_char_index(0, 2, 2)][..]),
+        ]);
+
+        let env = &store.database.env;
+        let reader = env.read_txn().unwrap();
+
+        let builder = store.query_builder();
+        let results = builder.query(&reader, "iphone from apple", 0..20).unwrap();
+        let mut iter = results.into_iter();
+
+        assert_matches!(iter.next(), Some(Document { id: DocumentId(0), matches, .. }) => {
+            let mut matches = matches.into_iter();
+            assert_matches!(matches.next(), Some(TmpMatch { query_index: 0, word_index: 0, .. }));
+            assert_matches!(matches.next(), Some(TmpMatch { query_index: 1, word_index: 1, .. }));
+            assert_matches!(matches.next(), Some(TmpMatch { query_index: 2, word_index: 2, .. }));
+            assert_matches!(matches.next(), None);
+        });
+        assert_matches!(iter.next(), None);
+    }
+
+    #[test]
+    fn diversity() {
+        let store = TempDatabase::from_iter(vec![(
+            "iphone",
+            &[
+                doc_char_index(0, 0, 0),
+                doc_char_index(1, 0, 0),
+                doc_char_index(2, 0, 0),
+                doc_char_index(3, 0, 0),
+            ][..],
+        )]);
+
+        let env = &store.database.env;
+        let reader = env.read_txn().unwrap();
+
+        // the documents 0 and 1 share the same brand, as do the 2 and 3
+        let brand = |id: DocumentId| Some(id.0 / 2);
+
+        let builder = store.query_builder();
+        let results = builder.query(&reader, "iphone", 0..20).unwrap();
+        let ids: Vec<_> = results.into_iter().map(|d| d.id.0).collect();
+        assert_eq!(ids, vec![0, 1, 2, 3]);
+
+        let mut builder = store.query_builder();
+        builder.with_diversity(brand, 1);
+        let results = builder.query(&reader, "iphone", 0..20).unwrap();
+        let ids: Vec<_> = results.into_iter().map(|d| d.id.0).collect();
+        assert_eq!(ids, vec![0, 2, 1, 3]);
+
+        let mut builder = store.query_builder();
+        builder.with_diversity(brand, 1);
+        let results = builder.query(&reader, "iphone", 1..3).unwrap();
+        let ids: Vec<_> = results.into_iter().map(|d| d.id.0).collect();
+        assert_eq!(ids, vec![2, 1]);
+
+        let mut builder = store.query_builder();
+        builder.with_diversity(brand, usize::max_value());
+        let results = builder.query(&reader, "iphone", 0..20).unwrap();
+        let ids: Vec<_> = results.into_iter().map(|d| d.id.0).collect();
+        assert_eq!(ids, vec![0, 2, 1, 3]);
+    }
+
+    #[test]
+    fn memory_limit() {
+        let store = TempDatabase::from_iter(vec![
+            (
+                "iphone",
+                &[doc_index(0, 0), doc_index(1, 1), doc_index(2, 0)][..],
+            ),
+            (
+                "case",
+                &[doc_index(0, 1), doc_index(2, 2), doc_index(3, 0)][..],
+            ),
+        ]);
+
+        let env = &store.database.env;
+        let reader = env.read_txn().unwrap();
+
+        let builder = store.query_builder();
+        let expected = builder.query(&reader, "iphone case", 0..20).unwrap();
+
+        // a limit smaller than a single match spills every match
+        let mut builder = store.query_builder();
+        builder.with_memory_limit(1);
+        let results = builder.query(&reader, "iphone case", 0..20).unwrap();
+        assert_eq!(results, expected);
+    }
+
+    #[test]
+    fn restrict_documents() {
+        let store = TempDatabase::from_iter(vec![(
+            "iphone",
+            &[
+                doc_char_index(0, 0, 0),
+                doc_char_index(1, 0, 0),
+                doc_char_index(2, 0, 0),
+                doc_char_index(3, 0, 0),
+            ][..],
+        )]);
+
+        let env = &store.database.env;
+        let reader = env.read_txn().unwrap();
+
+        let mut builder = store.query_builder();
+        builder.restrict_documents(vec![DocumentId(1), DocumentId(3), DocumentId(7)]);
+        let results = builder.query(&reader, "iphone", 0..20).unwrap();
+        let ids: Vec<_> = results.into_iter().map(|d| d.id.0).collect();
+        assert_eq!(ids, vec![1, 3]);
+
+        // the pagination applies to the allowed documents
+        let mut builder = store.query_builder();
+        builder.restrict_documents(vec![DocumentId(1), DocumentId(3), DocumentId(7)]);
+        let results = builder.query(&reader, "iphone", 1..2).unwrap();
+        let ids: Vec<_> = results.into_iter().map(|d| d.id.0).collect();
+        assert_eq!(ids, vec![3]);
+
+        // the restrictions are intersected
+        let mut builder = store.query_builder();
+        builder.restrict_documents(vec![DocumentId(1), DocumentId(3)]);
+        builder.restrict_documents(vec![DocumentId(0), DocumentId(3)]);
+        let results = builder.query(&reader, "iphone", 0..20).unwrap();
+        let ids: Vec<_> = results.into_iter().map(|d| d.id.0).collect();
+        assert_eq!(ids, vec![3]);
+    }
+
+    #[test]
+    fn max_total_hits() {
+        let store = TempDatabase::from_iter(vec![(
+            "iphone",
+            &[
+                doc_char_index(0, 0, 0),
+                doc_char_index(1, 0, 0),
+                doc_char_index(2, 0, 0),
+                doc_char_index(3, 0, 0),
+            ][..],
+        )]);
+
+        let env = &store.database.env;
+        let mut writer = env.write_txn().unwrap();
+        store.index.main.put_max_total_hits(&mut writer, 3).unwrap();
+        writer.commit().unwrap();
+
+        let reader = env.read_txn().unwrap();
+
+        let builder = store.query_builder();
+        let results = builder.query(&reader, "iphone", 0..20).unwrap();
+        let ids: Vec<_> = results.into_iter().map(|d| d.id.0).collect();
+        assert_eq!(ids, vec![0, 1, 2]);
+
+        let builder = store.query_builder();
+        let results = builder.query(&reader, "iphone", 2..4).unwrap();
+        let ids: Vec<_> = results.into_iter().map(|d| d.id.0).collect();
+        assert_eq!(ids, vec![2]);
+
+        let builder = store.query_builder();
+        let result = builder.query(&reader, "iphone", 3..5);
+        assert_matches!(
+            result,
+            Err(Error::LimitExceeded(LimitExceeded::TotalHitsExceeded {
+                limit: 3,
+                offset: 3
+            }))
+        );
+    }
+
+    #[test]
+    fn ranking_buckets() {
+        let store = TempDatabase::from_iter(vec![(
+            "iphone",
+            &[
+                doc_char_index(0, 0, 0),
+                doc_char_index(1, 0, 0),
+                doc_char_index(2, 5, 0),
+                doc_char_index(3, 5, 0),
+            ][..],
+        )]);
+
+        let env = &store.database.env;
+        let reader = env.read_txn().unwrap();
+
+        let criteria = || {
+            CriteriaBuilder::new()
+                .add(criterion::SumOfWordsPosition)
+                .build()
+        };
+
+        let builder = store.index.query_builder_with_criteria(criteria());
+        let result = builder
+            .query_with_buckets(&reader, "iphone", 0..20)
+            .unwrap();
+        assert_eq!(result.documents.len(), 4);
+        assert_eq!(result.buckets_starts, vec![0, 2]);
+
+        let builder = store.index.query_builder_with_criteria(criteria());
+        let result = builder.query_with_buckets(&reader, "iphone", 1..4).unwrap();
+        assert_eq!(result.documents.len(), 3);
+        assert_eq!(result.buckets_starts, vec![0, 1]);
+
+        // the default criteria ends with the document id, all the documents differ
+        let builder = store.query_builder();
+        let result = builder
+            .query_with_buckets(&reader, "iphone", 0..20)
+            .unwrap();
+        assert_eq!(result.buckets_starts, vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn decisive_criteria() {
+        let store = TempDatabase::from_iter(vec![(
+            "iphone",
+            &[
+                doc_char_index(0, 0, 0),
+                doc_char_index(1, 0, 0),
+                doc_char_index(2, 5, 0),
+                doc_char_index(3, 5, 0),
+            ][..],
+        )]);
+
+        let env = &store.database.env;
+        let reader = env.read_txn().unwrap();
+
+        let criteria = CriteriaBuilder::new()
+            .add(criterion::SumOfWordsPosition)
+            .add(criterion::DocumentId)
+            .build();
+
+        let builder = store.index.query_builder_with_criteria(criteria);
+        let result = builder
+            .query_with_buckets(&reader, "iphone", 0..20)
+            .unwrap();
+
+        let decisive: Vec<_> = result
+            .decisive_criteria
+            .iter()
+            .map(|name| name.as_ref().map(String::as_str))
+            .collect();
+        assert_eq!(
+            decisive,
+            vec![
+                None,
+                Some("DocumentId"),
+                Some("SumOfWordsPosition"),
+                Some("DocumentId"),
+            ]
+        );
+    }
+
+    #[test]
+    fn restrict_searchable_attributes() {
+        let doc_attr_index = |document_id, attribute| DocIndex {
+            document_id: DocumentId(document_id),
+            attribute,
+            word_index: 0,
+            char_index: 0,
+            char_length: 5,
+        };
+        let hello = vec![
+            doc_attr_index(0, 0),
+            doc_attr_index(1, 1),
+            doc_attr_index(2, 0),
+            doc_attr_index(2, 1),
+        ];
+        let store = TempDatabase::from_iter(vec![("hello", &hello[..])]);
+
+        let env = &store.database.env;
+        let reader = env.read_txn().unwrap();
+
+        let mut builder = store.query_builder();
+        builder.restrict_searchable_attributes(vec![SchemaAttr(1)]);
+        let results = builder.query(&reader, "hello", 0..20).unwrap();
+        let mut iter = results.into_iter();
+
+        // the match of the second document in the first attribute is ignored
+        assert_matches!(iter.next(), Some(Document { id: DocumentId(1), highlights, .. }) => {
+            assert_eq!(highlights, vec![Highlight { attribute: 1, char_index: 0, char_length: 5 }]);
+        });
+        assert_matches!(iter.next(), Some(Document { id: DocumentId(2), highlights, .. }) => {
+            assert_eq!(highlights, vec![Highlight { attribute: 1, char_index: 0, char_length: 5 }]);
+        });
+        assert_matches!(iter.next(), None);
+    }
+
+    #[test]
+    fn explain() {
+        let mut store = TempDatabase::from_iter(vec![
+            ("hello", &[doc_index(0, 0)][..]),
+            ("bonjoor", &[doc_index(1, 2)][..]),
+        ]);
+
+        store.add_synonym("bonjour", SetBuf::from_dirty(vec!["hello"]));
+
+        let env = &store.database.env;
+        let reader = env.read_txn().unwrap();
+
+        let mut builder = store.query_builder();
+        builder.explain();
+        let result = builder
+            .query_with_buckets(&reader, "bonjour ", 0..20)
+            .unwrap();
+
+        let ids: Vec<_> = result.documents.iter().map(|d| d.id.0).collect();
+        assert_eq!(ids, vec![0, 1]);
+        assert_eq!(result.explanations.len(), 2);
+
+        let explanation = &result.explanations[0];
+        assert_eq!(explanation.criteria.len(), 7);
+        assert_eq!(explanation.criteria[0].name, "SumOfTypos");
+        assert_eq!(explanation.criteria[0].score, Some(Number::Unsigned(0)));
+        assert_eq!(
+            explanation.matches,
+            vec![MatchExplanation {
+                query: "hello".to_string(),
+                origin: AutomatonOrigin::Synonym,
+                word: "hello".to_string(),
+                typos: 0,
+                penalty: 0,
+                attribute: SchemaAttr(0),
+                word_index: 0,
+                is_exact: true,
+            }]
+        );
+
+        let explanation = &result.explanations[1];
+        assert_eq!(explanation.criteria[0].score, Some(Number::Unsigned(1)));
+        assert_matches!(
+            &explanation.matches[..],
+            [MatchExplanation {
+                origin: AutomatonOrigin::Query,
+                typos: 1,
+                word_index: 2,
+                is_exact: false,
+                ..
+            }]
+        );
+        assert_eq!(explanation.matches[0].word, "bonjoor");
+
+        // nothing is explained by default
+        let builder = store.query_builder();
+        let result = builder
+            .query_with_buckets(&reader, "bonjour ", 0..20)
+            .unwrap();
+        assert!(result.explanations.is_empty());
+    }
+
+    #[test]
+    fn query_rewrites() {
+        let mut store = TempDatabase::from_iter(vec![
+            ("hello", &[doc_index(0, 0)][..]),
+            ("bonjoor", &[doc_index(1, 2)][..]),
+        ]);
+
+        store.add_synonym("bonjour", SetBuf::from_dirty(vec!["hello"]));
+
+        let env = &store.database.env;
+        let mut writer = env.write_txn().unwrap();
+        let stop_words = fst::Set::from_iter(vec!["the"]).unwrap();
+        store
+            .index
+            .main
+            .put_stop_words_fst(&mut writer, &stop_words)
+            .unwrap();
+        writer.commit().unwrap();
+
+        let reader = env.read_txn().unwrap();
+
+        let mut builder = store.query_builder();
+        builder.explain();
+        let result = builder
+            .query_with_buckets(&reader, "the bonjour ", 0..20)
+            .unwrap();
 
-    const fn doc_char_index(document_id: u64, word_index: u16, char_index: u16) -> DocIndex {
-        DocIndex {
-            document_id: DocumentId(document_id),
-            attribute: 0,
-            word_index,
-            char_index,
-            char_length: 0,
-        }
-    }
+        assert_eq!(
+            result.rewrites,
+            vec![
+                QueryRewrite::StopWord {
+                    word: "the".to_string()
+                },
+                QueryRewrite::Synonym {
+                    query: vec!["bonjour".to_string()],
+                    words: vec!["hello".to_string()],
+                    penalty: 0,
+                },
+                QueryRewrite::Concatenation {
+                    query: vec!["the".to_string(), "bonjour".to_string()],
+                    word: "thebonjour".to_string(),
+                },
+                QueryRewrite::Typo {
+                    query: "bonjour".to_string(),
+                    word: "bonjoor".to_string(),
+                    typos: 1,
+                },
+            ]
+        );
 
-    pub struct TempDatabase {
-        database: Database,
-        index: Index,
-        _tempdir: TempDir,
+        // the rewrites are only listed along with the explanations
+        let builder = store.query_builder();
+        let result = builder
+            .query_with_buckets(&reader, "the bonjour ", 0..20)
+            .unwrap();
+        assert!(result.rewrites.is_empty());
     }
 
-    impl TempDatabase {
-        pub fn query_builder(&self) -> QueryBuilder {
-            self.index.query_builder()
-        }
+    #[test]
+    fn query_words_limit() {
+        let store = TempDatabase::from_iter(vec![
+            ("iphone", &[doc_char_index(0, 0, 0)][..]),
+            ("from", &[doc_char_index(0, 1, 1)][..]),
+            (
+                "apple",
+                &[doc_char_index(0, 2, 2), doc_char_index(1, 0, 0)][..],
+            ),
+        ]);
 
-        pub fn add_synonym(&mut self, word: &str, new: SetBuf<&str>) {
-            let env = &self.database.env;
-            let mut writer = env.write_txn().unwrap();
+        let env = &store.database.env;
+        let mut writer = env.write_txn().unwrap();
+        let limits = QueryLimits {
+            max_query_words: 1,
+            ..QueryLimits::default()
+        };
+        store
+            .index
+            .main
+            .put_query_limits(&mut writer, &limits)
+            .unwrap();
+        writer.commit().unwrap();
 
-            let word = word.to_lowercase();
+        let reader = env.read_txn().unwrap();
 
-            let alternatives = match self
-                .index
-                .synonyms
-                .synonyms(&writer, word.as_bytes())
-                .unwrap()
-            {
-                Some(alternatives) => alternatives,
-                None => fst::Set::default(),
-            };
+        // the ignored words do not select documents but are highlighted
+        let builder = store.query_builder();
+        let results = builder.query(&reader, "iphone from apple", 0..20).unwrap();
+        let mut iter = results.into_iter();
+        assert_matches!(iter.next(), Some(Document { id: DocumentId(0), highlights, .. }) => {
+            assert_eq!(highlights.len(), 3);
+            assert!(highlights.contains(&Highlight { attribute: 0, char_index: 1, char_length: 0 }));
+            assert!(highlights.contains(&Highlight { attribute: 0, char_index: 2, char_length: 0 }));
+        });
+        assert_matches!(iter.next(), None);
 
-            let new = sdset_into_fstset(&new);
-            let new_alternatives =
-                set_from_stream(alternatives.op().add(new.into_stream()).r#union());
-            self.index
-                .synonyms
-                .put_synonyms(&mut writer, word.as_bytes(), &new_alternatives)
-                .unwrap();
+        // the last word kept is followed by other words, it is not a prefix
+        let builder = store.query_builder();
+        let results = builder.query(&reader, "ipho apple", 0..20).unwrap();
+        assert!(results.is_empty());
+    }
 
-            let synonyms = match self.index.main.synonyms_fst(&writer).unwrap() {
-                Some(synonyms) => synonyms,
-                None => fst::Set::default(),
-            };
+    #[test]
+    fn prefix_search() {
+        let store = TempDatabase::from_iter(vec![
+            ("car", &[doc_index(0, 0)][..]),
+            ("cart", &[doc_index(1, 0)][..]),
+        ]);
 
-            let synonyms_fst = insert_key(&synonyms, word.as_bytes());
-            self.index
-                .main
-                .put_synonyms_fst(&mut writer, &synonyms_fst)
-                .unwrap();
+        let env = &store.database.env;
+        let search = |prefix: Option<bool>, query: &str| -> Vec<u64> {
+            let reader = env.read_txn().unwrap();
+            let mut builder = store.query_builder();
+            if let Some(prefix) = prefix {
+                builder.with_prefix(prefix);
+            }
+            let results = builder.query(&reader, query, 0..20).unwrap();
+            results.into_iter().map(|d| d.id.0).collect()
+        };
 
-            writer.commit().unwrap();
-        }
+        // the last word is a prefix unless it is followed by a space
+        assert_eq!(search(None, "car"), vec![0, 1]);
+        assert_eq!(search(None, "car "), vec![0]);
+        assert_eq!(search(Some(false), "car"), vec![0]);
+
+        // the requests override the setting of the index
+        let mut writer = env.write_txn().unwrap();
+        store
+            .index
+            .main
+            .put_prefix_search(&mut writer, false)
+            .unwrap();
+        writer.commit().unwrap();
+
+        assert_eq!(search(None, "car"), vec![0]);
+        assert_eq!(search(Some(true), "car"), vec![0, 1]);
     }
 
-    impl<'a> FromIterator<(&'a str, &'a [DocIndex])> for TempDatabase {
-        fn from_iter<I: IntoIterator<Item = (&'a str, &'a [DocIndex])>>(iter: I) -> Self {
-            let tempdir = TempDir::new().unwrap();
-            let database = Database::open_or_create(&tempdir).unwrap();
-            let index = database.create_index("default").unwrap();
+    #[test]
+    fn typos_table() {
+        let store = TempDatabase::from_iter(vec![
+            ("iphone", &[doc_char_index(0, 0, 0)][..]),
+            ("apple", &[doc_char_index(1, 0, 0)][..]),
+        ]);
 
-            let env = &database.env;
-            let mut writer = env.write_txn().unwrap();
+        let env = &store.database.env;
+        let reader = env.read_txn().unwrap();
 
-            let mut words_fst = BTreeSet::new();
-            let mut postings_lists = HashMap::new();
-            let mut fields_counts = HashMap::<_, u64>::new();
+        // the default table allows one typo to the words of 5 to 8 bytes
+        let builder = store.query_builder();
+        let results = builder.query(&reader, "iphome ", 0..20).unwrap();
+        assert_matches!(
+            results.first(),
+            Some(Document {
+                id: DocumentId(0),
+                ..
+            })
+        );
 
-            for (word, indexes) in iter {
-                let word = word.to_lowercase().into_bytes();
-                words_fst.insert(word.clone());
-                postings_lists
-                    .entry(word)
-                    .or_insert_with(Vec::new)
-                    .extend_from_slice(indexes);
-                for idx in indexes {
-                    fields_counts.insert((idx.document_id, idx.attribute, idx.word_index), 1);
-                }
-            }
+        let builder = store.query_builder();
+        let results = builder.query(&reader, "appel ", 0..20).unwrap();
+        assert_matches!(
+            results.first(),
+            Some(Document {
+                id: DocumentId(1),
+                ..
+            })
+        );
+        drop(reader);
+
+        let mut writer = env.write_txn().unwrap();
+        let table = TyposTable::new(vec![
+            TyposThreshold {
+                min_word_len: 0,
+                max_typos: 0,
+            },
+            TyposThreshold {
+                min_word_len: 6,
+                max_typos: 1,
+            },
+        ])
+        .unwrap();
+        store
+            .index
+            .main
+            .put_typos_table(&mut writer, &table)
+            .unwrap();
+        writer.commit().unwrap();
 
-            let words_fst = Set::from_iter(words_fst).unwrap();
+        let reader = env.read_txn().unwrap();
 
-            index.main.put_words_fst(&mut writer, &words_fst).unwrap();
+        let builder = store.query_builder();
+        let results = builder.query(&reader, "iphome ", 0..20).unwrap();
+        assert_matches!(
+            results.first(),
+            Some(Document {
+                id: DocumentId(0),
+                ..
+            })
+        );
 
-            for (word, postings_list) in postings_lists {
-                let postings_list = SetBuf::from_dirty(postings_list);
-                index
-                    .postings_lists
-                    .put_postings_list(&mut writer, &word, &postings_list)
-                    .unwrap();
-            }
+        let builder = store.query_builder();
+        let results = builder.query(&reader, "appel ", 0..20).unwrap();
+        assert!(
+            results.is_empty(),
+            "the words of 5 bytes must be typed without typo"
+        );
+    }
 
-            for ((docid, attr, _), count) in fields_counts {
-                let prev = index
-                    .documents_fields_counts
-                    .document_field_count(&mut writer, docid, SchemaAttr(attr))
-                    .unwrap();
+    #[test]
+    fn frozen_words() {
+        let store = TempDatabase::from_iter(vec![
+            ("zyrtec", &[doc_char_index(0, 0, 0)][..]),
+            ("zyrtek", &[doc_char_index(1, 0, 0)][..]),
+            ("claritin", &[doc_char_index(2, 0, 0)][..]),
+        ]);
 
-                let prev = prev.unwrap_or(0);
+        let env = &store.database.env;
+        let mut writer = env.write_txn().unwrap();
+        let words = BTreeSet::from_iter(vec!["Zyrtec".to_string()]);
+        store.index.put_frozen_words(&mut writer, words).unwrap();
+        writer.commit().unwrap();
 
-                index
-                    .documents_fields_counts
-                    .put_document_field_count(&mut writer, docid, SchemaAttr(attr), prev + count)
-                    .unwrap();
-            }
+        let reader = env.read_txn().unwrap();
+        assert_eq!(store.index.frozen_words(&reader).unwrap(), vec!["zyrtec"]);
 
-            writer.commit().unwrap();
+        // the frozen word is only matched exactly
+        let builder = store.query_builder();
+        let results = builder.query(&reader, "zyrtec ", 0..20).unwrap();
+        let ids: Vec<_> = results.into_iter().map(|d| d.id).collect();
+        assert_eq!(ids, vec![DocumentId(0)]);
 
-            TempDatabase {
-                database,
-                index,
-                _tempdir: tempdir,
-            }
-        }
+        // and is not matched with typos by the other words
+        let builder = store.query_builder();
+        let results = builder.query(&reader, "zyrtek ", 0..20).unwrap();
+        let ids: Vec<_> = results.into_iter().map(|d| d.id).collect();
+        assert_eq!(ids, vec![DocumentId(1)]);
+
+        // the other words are still matched with typos
+        let builder = store.query_builder();
+        let results = builder.query(&reader, "claritim ", 0..20).unwrap();
+        let ids: Vec<_> = results.into_iter().map(|d| d.id).collect();
+        assert_eq!(ids, vec![DocumentId(2)]);
     }
 
     #[test]
-    fn simple() {
+    fn buckets_starts_of_positions() {
+        assert_eq!(buckets_starts(&[2, 3, 1], 0..6), vec![0, 2, 5]);
+        assert_eq!(buckets_starts(&[2, 3, 1], 3..6), vec![0, 2]);
+        assert_eq!(buckets_starts(&[2, 3, 1], vec![1, 4, 5]), vec![0, 1, 2]);
+        assert_eq!(buckets_starts(&[2], vec![0, 3, 4]), vec![0, 1, 2]);
+        assert!(buckets_starts(&[2], 0..0).is_empty());
+    }
+
+    #[test]
+    fn patterns() {
         let store = TempDatabase::from_iter(vec![
-            ("iphone", &[doc_char_index(0, 0, 0)][..]),
-            ("from", &[doc_char_index(0, 1, 1)][..]),
-            ("apple", &[doc_char_index(0, 2, 2)][..]),
+            ("run", &[doc_index(0, 0)][..]),
+            ("runner", &[doc_index(1, 0)][..]),
+            ("running", &[doc_index(2, 0)][..]),
+            ("rust", &[doc_index(3, 0)][..]),
+            ("v8", &[doc_index(4, 0)][..]),
         ]);
 
         let env = &store.database.env;
         let reader = env.read_txn().unwrap();
 
+        // patterns are not enabled by default
         let builder = store.query_builder();
-        let results = builder.query(&reader, "iphone from apple", 0..20).unwrap();
+        let results = builder.query(&reader, "v[0-9] ", 0..20).unwrap();
+        assert_matches!(results.into_iter().next(), None);
+
+        let mut builder = store.query_builder();
+        builder.with_patterns(PatternOptions::default());
+        let results = builder.query(&reader, "runn* ", 0..20).unwrap();
         let mut iter = results.into_iter();
 
-        assert_matches!(iter.next(), Some(Document { id: DocumentId(0), matches, .. }) => {
+        assert_matches!(iter.next(), Some(Document { id: DocumentId(1), matches, .. }) => {
             let mut matches = matches.into_iter();
-            assert_matches!(matches.next(), Some(TmpMatch { query_index: 0, word_index: 0, .. }));
-            assert_matches!(matches.next(), Some(TmpMatch { query_index: 1, word_index: 1, .. }));
-            assert_matches!(matches.next(), Some(TmpMatch { query_index: 2, word_index: 2, .. }));
+            assert_matches!(matches.next(), Some(TmpMatch { query_index: 0, distance: 0, is_exact: false, .. }));
+            assert_matches!(matches.next(), None);
+        });
+        assert_matches!(
+            iter.next(),
+            Some(Document {
+                id: DocumentId(2),
+                ..
+            })
+        );
+        assert_matches!(iter.next(), None);
+
+        let mut builder = store.query_builder();
+        builder.with_patterns(PatternOptions::default());
+        let results = builder.query(&reader, "v[0-9]", 0..20).unwrap();
+        let mut iter = results.into_iter();
+
+        assert_matches!(iter.next(), Some(Document { id: DocumentId(4), matches, .. }) => {
+            let mut matches = matches.into_iter();
+            assert_matches!(matches.next(), Some(TmpMatch { query_index: 0, is_exact: true, .. }));
             assert_matches!(matches.next(), None);
         });
         assert_matches!(iter.next(), None);
+
+        // the number of words a pattern can match is limited
+        let mut builder = store.query_builder();
+        builder.with_patterns(PatternOptions {
+            max_expansions: 1,
+            ..PatternOptions::default()
+        });
+        let results = builder.query(&reader, "ru*", 0..20).unwrap();
+        let mut iter = results.into_iter();
+
+        assert_matches!(
+            iter.next(),
+            Some(Document {
+                id: DocumentId(0),
+                ..
+            })
+        );
+        assert_matches!(iter.next(), None);
     }
 
     #[test]
@@ -921,6 +2338,26 @@ mod tests {
         assert_matches!(iter.next(), None);
     }
 
+    #[test]
+    fn weighted_synonyms() {
+        let mut store = TempDatabase::from_iter(vec![
+            ("mobile", &[doc_index(0, 0)][..]),
+            ("cellphone", &[doc_index(1, 0)][..]),
+            ("phone", &[doc_index(2, 0)][..]),
+        ]);
+
+        store.add_weighted_synonym("phone", &[("cellphone", 0), ("mobile", 1)]);
+
+        let env = &store.database.env;
+        let reader = env.read_txn().unwrap();
+
+        // the weak alternative ranks after the original word and the strong alternative
+        let builder = store.query_builder();
+        let results = builder.query(&reader, "phone", 0..20).unwrap();
+        let ids: Vec<_> = results.into_iter().map(|d| d.id.0).collect();
+        assert_eq!(ids, vec![1, 2, 0]);
+    }
+
     #[test]
     fn prefix_synonyms() {
         let mut store = TempDatabase::from_iter(vec![("hello", &[doc_index(0, 0)][..])]);
@@ -1267,6 +2704,31 @@ mod tests {
         assert_matches!(iter.next(), None);
     }
 
+    #[test]
+    fn synonym_key_longer_than_ngrams() {
+        let mut store = TempDatabase::from_iter(vec![
+            ("usa", &[doc_char_index(0, 0, 0)][..]),
+            ("united", &[doc_char_index(1, 0, 0)][..]),
+            ("states", &[doc_char_index(1, 1, 1)][..]),
+            ("of", &[doc_char_index(1, 2, 2)][..]),
+            ("america", &[doc_char_index(1, 3, 3)][..]),
+        ]);
+
+        store.add_synonym("united states of america", SetBuf::from_dirty(vec!["usa"]));
+
+        let env = &store.database.env;
+        let reader = env.read_txn().unwrap();
+
+        let builder = store.query_builder();
+        let results = builder
+            .query(&reader, "united states of america", 0..20)
+            .unwrap();
+
+        let mut documents_ids: Vec<_> = results.into_iter().map(|d| d.id).collect();
+        documents_ids.sort_unstable();
+        assert_eq!(documents_ids, vec![DocumentId(0), DocumentId(1)]);
+    }
+
     #[test]
     /// Unique word has multi-word synonyms
     fn harder_unique_to_multiword_synonyms_one() {
@@ -1822,4 +3284,72 @@ mod tests {
         });
         assert_matches!(iter.next(), None);
     }
+
+    #[test]
+    fn quoted_phrase_query() {
+        let store = TempDatabase::from_iter(vec![
+            ("new", &[doc_index(0, 0)][..]),
+            ("york", &[doc_index(0, 1)][..]),
+            ("city", &[doc_index(0, 2)][..]),
+            ("new", &[doc_index(1, 0)][..]),
+            ("shiny", &[doc_index(1, 1)][..]),
+            ("york", &[doc_index(1, 2)][..]),
+            ("york", &[doc_index(2, 0)][..]),
+            ("new", &[doc_index(2, 1)][..]),
+        ]);
+
+        let env = &store.database.env;
+        let reader = env.read_txn().unwrap();
+
+        // the words are matched anywhere when not quoted
+        let builder = store.query_builder();
+        let results = builder.query(&reader, "new york", 0..20).unwrap();
+        assert_eq!(results.len(), 3);
+
+        // the quoted words must follow each other
+        let builder = store.query_builder();
+        let results = builder.query(&reader, "\"new york city\"", 0..20).unwrap();
+        let mut iter = results.into_iter();
+
+        assert_matches!(iter.next(), Some(Document { id: DocumentId(0), matches, .. }) => {
+            let mut iter = matches.into_iter();
+            assert_matches!(iter.next(), Some(TmpMatch { query_index: 0, word_index: 0, .. })); // new
+            assert_matches!(iter.next(), Some(TmpMatch { query_index: 1, word_index: 1, .. })); // york
+            assert_matches!(iter.next(), Some(TmpMatch { query_index: 2, word_index: 2, .. })); // city
+            assert_matches!(iter.next(), None);
+        });
+        assert_matches!(iter.next(), None);
+
+        // an unclosed quote is ignored
+        let builder = store.query_builder();
+        let results = builder.query(&reader, "\"new york", 0..20).unwrap();
+        assert_eq!(results.len(), 3);
+    }
+
+    #[test]
+    fn exactness_criterion() {
+        let store = TempDatabase::from_iter(vec![
+            ("new", &[doc_index(0, 0)][..]),
+            ("york", &[doc_index(0, 1)][..]),
+            ("city", &[doc_index(0, 2)][..]),
+            ("new", &[doc_index(1, 0)][..]),
+            ("york", &[doc_index(1, 1)][..]),
+            ("york", &[doc_index(2, 0)][..]),
+            ("new", &[doc_index(2, 1)][..]),
+        ]);
+
+        let env = &store.database.env;
+        let reader = env.read_txn().unwrap();
+
+        let criteria = CriteriaBuilder::new()
+            .add(criterion::Exactness)
+            .add(criterion::DocumentId)
+            .build();
+
+        // only the attribute of the document 1 is exactly the query
+        let builder = store.index.query_builder_with_criteria(criteria);
+        let results = builder.query(&reader, "new york", 0..20).unwrap();
+        let ids: Vec<_> = results.into_iter().map(|d| d.id.0).collect();
+        assert_eq!(ids, vec![1, 0, 2]);
+    }
 }
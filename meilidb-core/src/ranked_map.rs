@@ -31,6 +31,11 @@ impl RankedMap {
         self.0.get(&(document, attribute)).cloned()
     }
 
+    /// The number of documents that have a value for this attribute.
+    pub fn attribute_len(&self, attribute: SchemaAttr) -> usize {
+        self.0.keys().filter(|(_, attr)| *attr == attribute).count()
+    }
+
     pub fn read_from_bin<R: Read>(reader: R) -> bincode::Result<RankedMap> {
         bincode::deserialize_from(reader).map(RankedMap)
     }
@@ -39,3 +44,20 @@ impl RankedMap {
         bincode::serialize_into(writer, &self.0)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn attribute_len() {
+        let mut ranked_map = RankedMap::default();
+        ranked_map.insert(DocumentId(0), SchemaAttr(0), Number::Unsigned(1));
+        ranked_map.insert(DocumentId(1), SchemaAttr(0), Number::Unsigned(2));
+        ranked_map.insert(DocumentId(1), SchemaAttr(1), Number::Unsigned(3));
+
+        assert_eq!(ranked_map.attribute_len(SchemaAttr(0)), 2);
+        assert_eq!(ranked_map.attribute_len(SchemaAttr(1)), 1);
+        assert_eq!(ranked_map.attribute_len(SchemaAttr(2)), 0);
+    }
+}
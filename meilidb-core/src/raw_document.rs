@@ -1,11 +1,14 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
 use std::fmt;
+use std::iter::Peekable;
 use std::sync::Arc;
 
 use meilidb_schema::SchemaAttr;
 use sdset::SetBuf;
-use slice_group_by::GroupBy;
 
-use crate::{DocumentId, Highlight, TmpMatch};
+use crate::filters::CandidatesFilter;
+use crate::{DocumentId, Highlight, MResult, TmpMatch};
 
 #[derive(Clone)]
 pub struct RawDocument {
@@ -13,6 +16,8 @@ pub struct RawDocument {
     pub matches: SharedMatches,
     pub highlights: Vec<Highlight>,
     pub fields_counts: SetBuf<(SchemaAttr, u64)>,
+    /// The number of words of the query, once rewritten, the document was matched with.
+    pub query_words: usize,
 }
 
 impl RawDocument {
@@ -98,34 +103,133 @@ impl fmt::Debug for RawDocument {
     }
 }
 
-pub fn raw_documents_from(
-    matches: SetBuf<(DocumentId, TmpMatch)>,
-    highlights: SetBuf<(DocumentId, Highlight)>,
-    fields_counts: SetBuf<(DocumentId, SchemaAttr, u64)>,
-) -> Vec<RawDocument> {
-    let mut docs_ranges: Vec<(_, Range, _, _)> = Vec::new();
-    let mut matches2 = Matches::with_capacity(matches.len());
+/// The matches of a query word along with their highlights,
+/// they must be yielded in the documents ids order.
+pub type MatchesStream<'a> = Box<dyn Iterator<Item = (DocumentId, TmpMatch, Highlight)> + 'a>;
+
+/// A k-way merge of the matches streams of the query words, it yields
+/// the matches and highlights of the documents one document after the other,
+/// in the documents ids order, without gathering all the matches beforehand.
+pub struct DocumentsMatches<'a> {
+    streams: Vec<Peekable<MatchesStream<'a>>>,
+    heap: BinaryHeap<Reverse<(DocumentId, usize)>>,
+    filter: Option<&'a CandidatesFilter<'a>>,
+}
+
+impl<'a> DocumentsMatches<'a> {
+    pub fn new(streams: Vec<MatchesStream<'a>>) -> DocumentsMatches<'a> {
+        let mut streams: Vec<_> = streams.into_iter().map(Iterator::peekable).collect();
+        let mut heap = BinaryHeap::with_capacity(streams.len());
+
+        for (i, stream) in streams.iter_mut().enumerate() {
+            if let Some((id, ..)) = stream.peek() {
+                heap.push(Reverse((*id, i)));
+            }
+        }
+
+        DocumentsMatches {
+            streams,
+            heap,
+            filter: None,
+        }
+    }
+
+    /// Skips the documents the filter rejects, their matches are
+    /// drained without being gathered and they are never ranked.
+    pub fn with_filter(mut self, filter: &'a CandidatesFilter<'a>) -> DocumentsMatches<'a> {
+        self.filter = Some(filter);
+        self
+    }
+
+    fn drain_document(
+        &mut self,
+        document_id: DocumentId,
+        mut matches: Option<(&mut Vec<TmpMatch>, &mut Vec<Highlight>)>,
+    ) {
+        // drain every stream currently pointing on this document
+        while let Some(Reverse((id, i))) = self.heap.peek().cloned() {
+            if id != document_id {
+                break;
+            }
+            self.heap.pop();
+
+            let stream = &mut self.streams[i];
+            loop {
+                match stream.peek() {
+                    Some((id, ..)) if *id == document_id => (),
+                    Some((id, ..)) => {
+                        self.heap.push(Reverse((*id, i)));
+                        break;
+                    }
+                    None => break,
+                }
+
+                if let Some((_, match_, highlight)) = stream.next() {
+                    if let Some((matches, highlights)) = matches.as_mut() {
+                        matches.push(match_);
+                        highlights.push(highlight);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Iterator for DocumentsMatches<'_> {
+    type Item = (DocumentId, Vec<TmpMatch>, Vec<Highlight>);
 
-    let matches = matches.linear_group_by_key(|(id, _)| *id);
-    let highlights = highlights.linear_group_by_key(|(id, _)| *id);
-    let fields_counts = fields_counts.linear_group_by_key(|(id, _, _)| *id);
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let Reverse((document_id, _)) = *self.heap.peek()?;
 
-    for ((mgroup, hgroup), fgroup) in matches.zip(highlights).zip(fields_counts) {
-        debug_assert_eq!(mgroup[0].0, hgroup[0].0);
-        debug_assert_eq!(mgroup[0].0, fgroup[0].0);
+            if let Some(filter) = self.filter {
+                if !filter.accepts(document_id) {
+                    self.drain_document(document_id, None);
+                    continue;
+                }
+            }
+
+            let mut matches = Vec::new();
+            let mut highlights = Vec::new();
+            self.drain_document(document_id, Some((&mut matches, &mut highlights)));
+
+            if let Some(filter) = self.filter {
+                if !filter.accepts_highlights(&highlights) {
+                    continue;
+                }
+            }
+
+            return Some((document_id, matches, highlights));
+        }
+    }
+}
+
+pub fn raw_documents_from<I>(documents: I, query_words: usize) -> MResult<Vec<RawDocument>>
+where
+    I: IntoIterator<
+        Item = MResult<(
+            DocumentId,
+            SetBuf<(DocumentId, TmpMatch)>,
+            Vec<Highlight>,
+            SetBuf<(SchemaAttr, u64)>,
+        )>,
+    >,
+{
+    let mut docs_ranges: Vec<(_, Range, _, _)> = Vec::new();
+    let mut matches = Matches::with_capacity(0);
+
+    for result in documents {
+        let (document_id, document_matches, highlights, fields_counts) = result?;
 
-        let document_id = mgroup[0].0;
         let start = docs_ranges.last().map(|(_, r, _, _)| r.end).unwrap_or(0);
-        let end = start + mgroup.len();
-        let highlights = hgroup.iter().map(|(_, h)| *h).collect();
-        let fields_counts = SetBuf::new(fgroup.iter().map(|(_, a, c)| (*a, *c)).collect()).unwrap();
+        let end = start + document_matches.len();
 
         docs_ranges.push((document_id, Range { start, end }, highlights, fields_counts));
-        matches2.extend_from_slice(mgroup);
+        matches.extend_from_slice(&document_matches);
     }
 
-    let matches = Arc::new(matches2);
-    docs_ranges
+    let matches = Arc::new(matches);
+    let documents = docs_ranges
         .into_iter()
         .map(|(id, range, highlights, fields_counts)| {
             let matches = SharedMatches {
@@ -137,9 +241,12 @@ pub fn raw_documents_from(
                 matches,
                 highlights,
                 fields_counts,
+                query_words,
             }
         })
-        .collect()
+        .collect();
+
+    Ok(documents)
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -184,3 +291,47 @@ impl Matches {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stream(ids: &[u64], query_index: u32) -> MatchesStream<'static> {
+        let matches: Vec<_> = ids
+            .iter()
+            .map(|id| {
+                let match_ = TmpMatch {
+                    query_index,
+                    distance: 0,
+                    attribute: 0,
+                    word_index: 0,
+                    is_exact: true,
+                };
+                let highlight = Highlight {
+                    attribute: 0,
+                    char_index: 0,
+                    char_length: 0,
+                };
+                (DocumentId(*id), match_, highlight)
+            })
+            .collect();
+
+        Box::new(matches.into_iter())
+    }
+
+    #[test]
+    fn merge_streams_by_document() {
+        let streams = vec![stream(&[1, 3, 3, 5], 0), stream(&[], 1), stream(&[2, 3], 2)];
+
+        let documents: Vec<_> = DocumentsMatches::new(streams)
+            .map(|(id, matches, highlights)| {
+                assert_eq!(matches.len(), highlights.len());
+                let query_indexes: Vec<_> = matches.iter().map(|m| m.query_index).collect();
+                (id.0, query_indexes)
+            })
+            .collect();
+
+        let expected = vec![(1, vec![0]), (2, vec![2]), (3, vec![0, 0, 2]), (5, vec![0])];
+        assert_eq!(documents, expected);
+    }
+}
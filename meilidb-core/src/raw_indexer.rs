@@ -1,21 +1,99 @@
-use std::collections::{BTreeMap, HashMap};
+use std::cmp;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::convert::TryFrom;
 
+use crate::token_filters::TokenFilterChain;
 use crate::{DocIndex, DocumentId};
 use deunicode::deunicode_with_tofu;
 use meilidb_schema::SchemaAttr;
-use meilidb_tokenizer::{is_cjk, SeqTokenizer, Token, Tokenizer};
+use meilidb_tokenizer::{is_cjk, normalize_number, split_unit, SeqTokenizer, Token, Tokenizer};
 use sdset::SetBuf;
+use serde::{Deserialize, Serialize};
 
 const WORD_LENGTH_LIMIT: usize = 80;
 
 type Word = Vec<u8>; // TODO make it be a SmallVec
 
+/// The maximum number of words indexed in the attributes, by attribute name,
+/// the words past the limit are stored for display but not searchable.
+pub type WordsLimits = BTreeMap<String, usize>;
+
+/// The distribution of the number of words in the values of an attribute,
+/// the words past the limit of the attribute are counted.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LengthStats {
+    pub values: u64,
+    pub total_words: u64,
+    pub max_words: u64,
+    /// The number of values with more words than the limit of the attribute.
+    pub truncated: u64,
+    /// The number of values by order of magnitude of their number of words,
+    /// the first bucket counts the values of less than 10 words, the second
+    /// the ones of less than 100 words and so on.
+    pub buckets: Vec<u64>,
+}
+
+impl LengthStats {
+    pub fn record(&mut self, number_of_words: usize, truncated: bool) {
+        let number_of_words = number_of_words as u64;
+        self.values += 1;
+        self.total_words += number_of_words;
+        self.max_words = cmp::max(self.max_words, number_of_words);
+        if truncated {
+            self.truncated += 1;
+        }
+
+        let mut bucket = 0;
+        let mut magnitude = number_of_words / 10;
+        while magnitude > 0 {
+            bucket += 1;
+            magnitude /= 10;
+        }
+
+        if self.buckets.len() <= bucket {
+            self.buckets.resize(bucket + 1, 0);
+        }
+        self.buckets[bucket] += 1;
+    }
+
+    pub fn merge(&mut self, other: &LengthStats) {
+        self.values += other.values;
+        self.total_words += other.total_words;
+        self.max_words = cmp::max(self.max_words, other.max_words);
+        self.truncated += other.truncated;
+
+        if self.buckets.len() < other.buckets.len() {
+            self.buckets.resize(other.buckets.len(), 0);
+        }
+        for (bucket, count) in self.buckets.iter_mut().zip(&other.buckets) {
+            *bucket += count;
+        }
+    }
+}
+
+/// The lengths of the values indexed in the attributes, by attribute name.
+pub type AttributesLengths = BTreeMap<String, LengthStats>;
+
 pub struct RawIndexer {
     word_limit: usize, // the maximum number of indexed words
+    attributes_word_limits: HashMap<SchemaAttr, usize>,
+    searchable_attributes: Option<HashSet<SchemaAttr>>,
     stop_words: fst::Set,
+    token_filters: Option<TokenFilterChain>,
     words_doc_indexes: BTreeMap<Word, Vec<DocIndex>>,
     docs_words: HashMap<DocumentId, Vec<Word>>,
+    truncated: Vec<(DocumentId, SchemaAttr)>,
+    lengths: HashMap<SchemaAttr, LengthStats>,
+}
+
+/// A word of a field tokenized by the caller, the offsets are counted in chars,
+/// like the ones of the tokenizer, in the original text of the field.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct PreToken {
+    pub word: String,
+    pub position: usize,
+    pub start: usize,
+    pub end: usize,
 }
 
 pub struct Indexed {
@@ -31,22 +109,81 @@ impl RawIndexer {
     pub fn with_word_limit(stop_words: fst::Set, limit: usize) -> RawIndexer {
         RawIndexer {
             word_limit: limit,
+            attributes_word_limits: HashMap::new(),
+            searchable_attributes: None,
             stop_words,
+            token_filters: None,
             words_doc_indexes: BTreeMap::new(),
             docs_words: HashMap::new(),
+            truncated: Vec::new(),
+            lengths: HashMap::new(),
         }
     }
 
+    /// Indexes fewer words in this attribute than in the others,
+    /// a limit higher than the one of the indexer is ignored.
+    pub fn limit_attribute_words(&mut self, attr: SchemaAttr, limit: usize) {
+        self.attributes_word_limits.insert(attr, limit);
+    }
+
+    /// Analyzes the words with the token filters of the index instead of the default
+    /// normalization, which also indexes the words with their accents.
+    pub fn set_token_filters(&mut self, token_filters: TokenFilterChain) {
+        self.token_filters = Some(token_filters);
+    }
+
+    /// Only indexes the given attributes, the other ones are stored but not searchable.
+    pub fn restrict_searchable_attributes<I>(&mut self, attributes: I)
+    where
+        I: IntoIterator<Item = SchemaAttr>,
+    {
+        self.searchable_attributes = Some(attributes.into_iter().collect());
+    }
+
+    pub fn is_searchable(&self, attr: SchemaAttr) -> bool {
+        self.searchable_attributes
+            .as_ref()
+            .map_or(true, |attributes| attributes.contains(&attr))
+    }
+
+    pub fn attribute_word_limit(&self, attr: SchemaAttr) -> usize {
+        match self.attributes_word_limits.get(&attr) {
+            Some(limit) => cmp::min(*limit, self.word_limit),
+            None => self.word_limit,
+        }
+    }
+
+    /// The attributes of the documents whose words past the limit have not been indexed.
+    pub fn truncated(&self) -> &[(DocumentId, SchemaAttr)] {
+        &self.truncated
+    }
+
+    /// The lengths of the values indexed, by attribute.
+    pub fn lengths(&self) -> &HashMap<SchemaAttr, LengthStats> {
+        &self.lengths
+    }
+
+    fn record_length(&mut self, attr: SchemaAttr, number_of_words: usize, truncated: bool) {
+        self.lengths
+            .entry(attr)
+            .or_default()
+            .record(number_of_words, truncated);
+    }
+
     pub fn index_text(&mut self, id: DocumentId, attr: SchemaAttr, text: &str) -> usize {
         let mut number_of_words = 0;
+        let mut truncated = false;
+        let word_limit = self.attribute_word_limit(attr);
 
-        for token in Tokenizer::new(text) {
+        let mut tokens = Tokenizer::new(text);
+        for token in &mut tokens {
             let must_continue = index_token(
                 token,
                 id,
                 attr,
-                self.word_limit,
+                word_limit,
                 &self.stop_words,
+                self.token_filters.as_ref(),
                 &mut self.words_doc_indexes,
                 &mut self.docs_words,
             );
@@ -54,10 +191,16 @@ impl RawIndexer {
             number_of_words += 1;
 
             if !must_continue {
+                self.truncated.push((id, attr));
+                truncated = true;
                 break;
             }
         }
 
+        // the words past the limit are only counted in the length of the value
+        let length = number_of_words + tokens.count();
+        self.record_length(attr, length, truncated);
+
         number_of_words
     }
 
@@ -65,22 +208,86 @@ impl RawIndexer {
     where
         I: IntoIterator<Item = &'a str>,
     {
+        let mut number_of_words = 0;
+        let mut truncated = false;
+        let word_limit = self.attribute_word_limit(attr);
         let iter = iter.into_iter();
-        for token in SeqTokenizer::new(iter) {
+
+        let mut tokens = SeqTokenizer::new(iter);
+        for token in &mut tokens {
             let must_continue = index_token(
                 token,
                 id,
                 attr,
-                self.word_limit,
+                word_limit,
                 &self.stop_words,
+                self.token_filters.as_ref(),
                 &mut self.words_doc_indexes,
                 &mut self.docs_words,
             );
 
+            number_of_words += 1;
+
             if !must_continue {
+                self.truncated.push((id, attr));
+                truncated = true;
                 break;
             }
         }
+
+        let length = number_of_words + tokens.count();
+        self.record_length(attr, length, truncated);
+    }
+
+    /// Indexes the words as they are given, only lowercased or analyzed by the token
+    /// filters of the index, and returns the number of words
+    /// of the field. The char offsets of the words are used as their positions in the text.
+    pub fn index_tokens(&mut self, id: DocumentId, attr: SchemaAttr, tokens: &[PreToken]) -> usize {
+        let mut number_of_words = 0;
+        let mut truncated = false;
+        let word_limit = self.attribute_word_limit(attr);
+
+        let length = tokens.iter().map(|t| t.position + 1).max().unwrap_or(0);
+        self.record_length(attr, length, length > word_limit);
+
+        for token in tokens {
+            if token.position >= word_limit {
+                truncated = true;
+                continue;
+            }
+
+            number_of_words = cmp::max(number_of_words, token.position + 1);
+
+            let word = match &self.token_filters {
+                Some(token_filters) => match token_filters.apply(&token.word) {
+                    Some(word) => word,
+                    None => continue,
+                },
+                None => token.word.to_lowercase(),
+            };
+            if word.is_empty() || word.len() > WORD_LENGTH_LIMIT || self.stop_words.contains(&word)
+            {
+                continue;
+            }
+
+            if let Some(docindex) = pre_token_to_docindex(id, attr, token) {
+                let word = word.into_bytes();
+                self.words_doc_indexes
+                    .entry(word.clone())
+                    .or_insert_with(Vec::new)
+                    .push(docindex);
+                self.docs_words
+                    .entry(id)
+                    .or_insert_with(Vec::new)
+                    .push(word);
+            }
+        }
+
+        if truncated {
+            self.truncated.push((id, attr));
+        }
+
+        number_of_words
     }
 
     pub fn build(self) -> Indexed {
@@ -113,6 +320,7 @@ fn index_token(
     attr: SchemaAttr,
     word_limit: usize,
     stop_words: &fst::Set,
+    token_filters: Option<&TokenFilterChain>,
     words_doc_indexes: &mut BTreeMap<Word, Vec<DocIndex>>,
     docs_words: &mut HashMap<DocumentId, Vec<Word>>,
 ) -> bool {
@@ -120,14 +328,29 @@ fn index_token(
         return false;
     }
 
-    let lower = token.word.to_lowercase();
+    // the words dropped by the token filters keep their position
+    let (lower, index_unidecoded) = match token_filters {
+        Some(token_filters) => match token_filters.apply(token.word) {
+            Some(word) => (word, false),
+            None => return true,
+        },
+        None => (token.word.to_lowercase(), true),
+    };
+    // the highlights of the filtered words cover the words as they are written
+    let original = token;
     let token = Token {
         word: &lower,
         ..token
     };
 
     if !stop_words.contains(&token.word) {
-        match token_to_docindex(id, attr, token) {
+        let docindex = if index_unidecoded {
+            token_to_docindex(id, attr, token)
+        } else {
+            token_to_docindex(id, attr, original)
+        };
+
+        match docindex {
             Some(docindex) => {
                 let word = Vec::from(token.word);
 
@@ -138,7 +361,7 @@ fn index_token(
                         .push(docindex);
                     docs_words.entry(id).or_insert_with(Vec::new).push(word);
 
-                    if !lower.contains(is_cjk) {
+                    if index_unidecoded && !lower.contains(is_cjk) {
                         let unidecoded = deunicode_with_tofu(&lower, "");
                         if unidecoded != lower && !unidecoded.is_empty() {
                             let word = Vec::from(unidecoded);
@@ -151,6 +374,15 @@ fn index_token(
                             }
                         }
                     }
+
+                    for number in number_variants(&lower) {
+                        let word = Vec::from(number);
+                        words_doc_indexes
+                            .entry(word.clone())
+                            .or_insert_with(Vec::new)
+                            .push(docindex);
+                        docs_words.entry(id).or_insert_with(Vec::new).push(word);
+                    }
                 }
             }
             None => return false,
@@ -160,6 +392,24 @@ fn index_token(
     true
 }
 
+/// The other forms under which a number is indexed, "1,000kg"
+/// is also indexed as "1000kg" and as "1000".
+fn number_variants(word: &str) -> Vec<String> {
+    let mut variants = Vec::new();
+
+    match split_unit(word) {
+        Some((number, unit)) => {
+            let number = normalize_number(number).unwrap_or_else(|| number.to_string());
+            variants.push(format!("{}{}", number, unit));
+            variants.push(number);
+        }
+        None => variants.extend(normalize_number(word)),
+    }
+
+    variants.retain(|variant| variant != word);
+    variants
+}
+
 fn token_to_docindex(id: DocumentId, attr: SchemaAttr, token: Token) -> Option<DocIndex> {
     let word_index = u16::try_from(token.word_index).ok()?;
     let char_index = u16::try_from(token.char_index).ok()?;
@@ -176,6 +426,22 @@ fn token_to_docindex(id: DocumentId, attr: SchemaAttr, token: Token) -> Option<D
     Some(docindex)
 }
 
+fn pre_token_to_docindex(id: DocumentId, attr: SchemaAttr, token: &PreToken) -> Option<DocIndex> {
+    let word_index = u16::try_from(token.position).ok()?;
+    let char_index = u16::try_from(token.start).ok()?;
+    let char_length = u16::try_from(token.end.checked_sub(token.start)?).ok()?;
+
+    let docindex = DocIndex {
+        document_id: id,
+        attribute: attr.0,
+        word_index,
+        char_index,
+        char_length,
+    };
+
+    Some(docindex)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -268,4 +534,157 @@ mod tests {
             .get(&"🇯🇵".to_owned().into_bytes())
             .is_some());
     }
+
+    #[test]
+    fn numbers_variants() {
+        let mut indexer = RawIndexer::new(fst::Set::default());
+
+        let docid = DocumentId(0);
+        let attr = SchemaAttr(0);
+        let text = "Weighs 1,000 kg or 2.500KG, page 8,10";
+        indexer.index_text(docid, attr, text);
+
+        let Indexed {
+            words_doc_indexes, ..
+        } = indexer.build();
+
+        assert!(words_doc_indexes.get(&b"1,000"[..]).is_some());
+        assert!(words_doc_indexes.get(&b"1000"[..]).is_some());
+        assert!(words_doc_indexes.get(&b"kg"[..]).is_some());
+        assert!(words_doc_indexes.get(&b"2.500kg"[..]).is_some());
+        assert!(words_doc_indexes.get(&b"2500kg"[..]).is_some());
+        assert!(words_doc_indexes.get(&b"2500"[..]).is_some());
+
+        // the numbers that are not thousands groups are indexed apart
+        assert!(words_doc_indexes.get(&b"8,10"[..]).is_none());
+        assert!(words_doc_indexes.get(&b"810"[..]).is_none());
+        assert!(words_doc_indexes.get(&b"10"[..]).is_some());
+    }
+
+    #[test]
+    fn pre_tokenized_words() {
+        let stop_words = fst::Set::from_iter(vec!["of"]).unwrap();
+        let mut indexer = RawIndexer::with_word_limit(stop_words, 3);
+
+        let docid = DocumentId(0);
+        let attr = SchemaAttr(0);
+        let token = |word: &str, position, start, end| PreToken {
+            word: word.to_string(),
+            position,
+            start,
+            end,
+        };
+        let tokens = vec![
+            token("42-USC-1983", 0, 0, 11),
+            token("of", 1, 12, 14),
+            token("C6H12O6", 2, 15, 22),
+            token("ignored", 3, 23, 30),
+        ];
+        let number_of_words = indexer.index_tokens(docid, attr, &tokens);
+        assert_eq!(number_of_words, 3);
+        assert_eq!(indexer.truncated(), &[(docid, attr)]);
+
+        let Indexed {
+            words_doc_indexes, ..
+        } = indexer.build();
+
+        let citation = &words_doc_indexes[&b"42-usc-1983"[..]];
+        assert_eq!(citation[0].char_index, 0);
+        assert_eq!(citation[0].char_length, 11);
+
+        assert!(words_doc_indexes.get(&b"c6h12o6"[..]).is_some());
+        assert!(words_doc_indexes.get(&b"of"[..]).is_none());
+        assert!(words_doc_indexes.get(&b"ignored"[..]).is_none());
+        assert!(words_doc_indexes.get(&b"42"[..]).is_none());
+    }
+
+    #[test]
+    fn pre_tokenized_char_offsets() {
+        let mut indexer = RawIndexer::new(fst::Set::default());
+
+        let docid = DocumentId(0);
+        let attr = SchemaAttr(0);
+        let token = |word: &str, position, start, end| PreToken {
+            word: word.to_string(),
+            position,
+            start,
+            end,
+        };
+        // "état ℞-42 café"
+        let tokens = vec![
+            token("état", 0, 0, 4),
+            token("℞-42", 1, 5, 9),
+            token("café", 2, 10, 14),
+        ];
+        indexer.index_tokens(docid, attr, &tokens);
+
+        let Indexed {
+            words_doc_indexes, ..
+        } = indexer.build();
+
+        let prescription = &words_doc_indexes["℞-42".as_bytes()];
+        assert_eq!(prescription[0].char_index, 5);
+        assert_eq!(prescription[0].char_length, 4);
+
+        let cafe = &words_doc_indexes["café".as_bytes()];
+        assert_eq!(cafe[0].char_index, 10);
+        assert_eq!(cafe[0].char_length, 4);
+    }
+
+    #[test]
+    fn attribute_word_limit() {
+        let mut indexer = RawIndexer::new(fst::Set::default());
+        let title = SchemaAttr(0);
+        let body = SchemaAttr(1);
+        indexer.limit_attribute_words(body, 2);
+
+        let docid = DocumentId(0);
+        let text = "le petit chat est sur le tapis";
+        indexer.index_text(docid, title, text);
+        indexer.index_text(docid, body, text);
+        assert_eq!(indexer.truncated(), &[(docid, body)]);
+
+        let Indexed {
+            words_doc_indexes, ..
+        } = indexer.build();
+
+        let attributes = |word: &[u8]| -> Vec<u16> {
+            words_doc_indexes[word]
+                .iter()
+                .map(|d| d.attribute)
+                .collect()
+        };
+
+        assert_eq!(attributes(b"petit"), vec![0, 1]);
+        assert_eq!(attributes(b"chat"), vec![0]);
+        assert_eq!(attributes(b"tapis"), vec![0]);
+    }
+
+    #[test]
+    fn lengths_past_the_limit() {
+        let mut indexer = RawIndexer::new(fst::Set::default());
+        let body = SchemaAttr(0);
+        indexer.limit_attribute_words(body, 2);
+
+        let docid = DocumentId(0);
+        indexer.index_text(docid, body, "le chat");
+        indexer.index_text(docid, body, "le petit chat est sur le tapis");
+        let long_text = vec!["mot"; 150].join(" ");
+        indexer.index_text_seq(docid, body, vec![long_text.as_str()]);
+
+        let expected = LengthStats {
+            values: 3,
+            total_words: 159,
+            max_words: 150,
+            truncated: 2,
+            buckets: vec![2, 0, 1],
+        };
+        assert_eq!(indexer.lengths()[&body], expected);
+
+        let mut merged = LengthStats::default();
+        merged.record(12, false);
+        merged.merge(&expected);
+        assert_eq!(merged.values, 4);
+        assert_eq!(merged.buckets, vec![2, 1, 1]);
+    }
 }
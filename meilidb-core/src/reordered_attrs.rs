@@ -13,11 +13,34 @@ impl ReorderedAttrs {
     }
 
     pub fn insert_attribute(&mut self, attribute: u16) {
-        self.reorders.resize(attribute as usize + 1, None);
+        if self.reorders.len() <= attribute as usize {
+            self.reorders.resize(attribute as usize + 1, None);
+        }
         self.reorders[attribute as usize] = Some(self.count as u16);
         self.count += 1;
     }
 
+    /// Keeps only the attributes accepted by the predicate, in the same order.
+    pub fn restrict<F>(&self, mut predicate: F) -> ReorderedAttrs
+    where
+        F: FnMut(u16) -> bool,
+    {
+        let mut attributes: Vec<_> = self
+            .reorders
+            .iter()
+            .enumerate()
+            .filter_map(|(attribute, reorder)| reorder.map(|r| (r, attribute as u16)))
+            .filter(|(_, attribute)| predicate(*attribute))
+            .collect();
+        attributes.sort_unstable();
+
+        let mut restricted = ReorderedAttrs::new();
+        for (_, attribute) in attributes {
+            restricted.insert_attribute(attribute);
+        }
+        restricted
+    }
+
     pub fn get(&self, attribute: u16) -> Option<u16> {
         match self.reorders.get(attribute as usize) {
             Some(Some(attribute)) => Some(*attribute),
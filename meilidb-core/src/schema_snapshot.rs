@@ -0,0 +1,83 @@
+use std::sync::Arc;
+
+use arc_swap::ArcSwap;
+use meilidb_schema::Schema;
+
+/// The schema of an index as it was at a given version, a snapshot is never modified,
+/// a schema update replaces it by a new one with a greater version.
+#[derive(Debug, Default)]
+pub struct SchemaSnapshot {
+    version: u64,
+    schema: Option<Schema>,
+}
+
+impl SchemaSnapshot {
+    /// The number of times the schema has been replaced since the index has been opened.
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    pub fn schema(&self) -> Option<&Schema> {
+        self.schema.as_ref()
+    }
+}
+
+/// The current schema snapshot of an index, shared by all the clones of the index.
+#[derive(Clone)]
+pub(crate) struct SchemaSnapshots {
+    current: Arc<ArcSwap<SchemaSnapshot>>,
+}
+
+impl SchemaSnapshots {
+    pub(crate) fn new(schema: Option<Schema>) -> SchemaSnapshots {
+        let snapshot = SchemaSnapshot { version: 0, schema };
+        SchemaSnapshots {
+            current: Arc::new(ArcSwap::from_pointee(snapshot)),
+        }
+    }
+
+    pub(crate) fn load(&self) -> Arc<SchemaSnapshot> {
+        self.current.load_full()
+    }
+
+    /// Atomically replaces the current snapshot, the readers that already
+    /// loaded the previous one keep it until they drop it.
+    pub(crate) fn replace(&self, schema: Option<Schema>) {
+        self.current.rcu(|current| SchemaSnapshot {
+            version: current.version + 1,
+            schema: schema.clone(),
+        });
+    }
+}
+
+impl Default for SchemaSnapshots {
+    fn default() -> SchemaSnapshots {
+        SchemaSnapshots::new(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use meilidb_schema::SchemaBuilder;
+
+    #[test]
+    fn replaced_snapshots() {
+        let snapshots = SchemaSnapshots::default();
+
+        let before = snapshots.load();
+        assert_eq!(before.version(), 0);
+        assert!(before.schema().is_none());
+
+        let schema = SchemaBuilder::with_identifier("id").build();
+        snapshots.replace(Some(schema.clone()));
+
+        let after = snapshots.load();
+        assert_eq!(after.version(), 1);
+        assert_eq!(after.schema(), Some(&schema));
+
+        // the snapshot loaded before the update is left untouched
+        assert_eq!(before.version(), 0);
+        assert!(before.schema().is_none());
+    }
+}
@@ -0,0 +1,116 @@
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+use serde_json::Value;
+use siphasher::sip::SipHasher;
+
+/// Computes a hash of the content of a document, the identifier field is ignored
+/// therefore the same content found under two identifiers gives the same hash.
+///
+/// Objects are hashed with their keys sorted, the order in which
+/// the fields were given does not change the resulting hash.
+pub fn compute_content_hash(identifier: &str, document: &HashMap<String, Value>) -> u64 {
+    let mut fields: Vec<_> = document
+        .iter()
+        .filter(|(name, _)| name.as_str() != identifier)
+        .collect();
+    fields.sort_unstable_by_key(|(name, _)| name.as_str());
+
+    let mut hasher = SipHasher::new();
+    fields.len().hash(&mut hasher);
+    for (name, value) in fields {
+        name.hash(&mut hasher);
+        hash_value(value, &mut hasher);
+    }
+
+    hasher.finish()
+}
+
+fn hash_value<H: Hasher>(value: &Value, hasher: &mut H) {
+    match value {
+        Value::Null => 0u8.hash(hasher),
+        Value::Bool(boolean) => {
+            1u8.hash(hasher);
+            boolean.hash(hasher);
+        }
+        Value::Number(number) => {
+            2u8.hash(hasher);
+            number.to_string().hash(hasher);
+        }
+        Value::String(string) => {
+            3u8.hash(hasher);
+            string.hash(hasher);
+        }
+        Value::Array(values) => {
+            4u8.hash(hasher);
+            values.len().hash(hasher);
+            for value in values {
+                hash_value(value, hasher);
+            }
+        }
+        Value::Object(object) => {
+            5u8.hash(hasher);
+            let mut entries: Vec<_> = object.iter().collect();
+            entries.sort_unstable_by_key(|(key, _)| key.as_str());
+
+            entries.len().hash(hasher);
+            for (key, value) in entries {
+                key.hash(hasher);
+                hash_value(value, hasher);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn document(value: Value) -> HashMap<String, Value> {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn identifier_is_ignored() {
+        let a = document(json!({ "id": 1, "title": "Carol", "tags": ["a", "b"] }));
+        let b = document(json!({ "id": "two", "tags": ["a", "b"], "title": "Carol" }));
+
+        assert_eq!(
+            compute_content_hash("id", &a),
+            compute_content_hash("id", &b)
+        );
+    }
+
+    #[test]
+    fn nested_objects_order_is_ignored() {
+        let a = document(json!({ "id": 1, "author": { "first": "Ada", "last": "Lovelace" } }));
+        let b = document(json!({ "id": 2, "author": { "last": "Lovelace", "first": "Ada" } }));
+
+        assert_eq!(
+            compute_content_hash("id", &a),
+            compute_content_hash("id", &b)
+        );
+    }
+
+    #[test]
+    fn different_contents() {
+        let a = document(json!({ "id": 1, "title": "Carol" }));
+        let b = document(json!({ "id": 1, "title": "carol" }));
+        let c = document(json!({ "id": 1, "title": ["Carol"] }));
+        let d = document(json!({ "id": 1, "name": "Carol" }));
+
+        let hashes = vec![
+            compute_content_hash("id", &a),
+            compute_content_hash("id", &b),
+            compute_content_hash("id", &c),
+            compute_content_hash("id", &d),
+        ];
+
+        for (i, x) in hashes.iter().enumerate() {
+            for y in &hashes[i + 1..] {
+                assert_ne!(x, y);
+            }
+        }
+    }
+}
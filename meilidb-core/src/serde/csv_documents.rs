@@ -0,0 +1,245 @@
+use std::collections::HashMap;
+use std::iter::FromIterator;
+use std::str::FromStr;
+use std::{error, fmt, io};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// The type the values of a CSV column are converted to.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CsvColumnType {
+    String,
+    Number,
+}
+
+impl FromStr for CsvColumnType {
+    type Err = CsvError;
+
+    fn from_str(s: &str) -> Result<CsvColumnType, CsvError> {
+        match s.trim().to_lowercase().as_str() {
+            "string" => Ok(CsvColumnType::String),
+            "number" => Ok(CsvColumnType::Number),
+            _ => Err(CsvError::UnknownColumnType(s.to_string())),
+        }
+    }
+}
+
+/// How a CSV file is read, the columns without a type hint are numbers if all of
+/// their values are numbers, strings otherwise. The values of a number column
+/// that are not numbers are kept as strings.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CsvOptions {
+    pub delimiter: u8,
+    pub types: HashMap<String, CsvColumnType>,
+}
+
+impl Default for CsvOptions {
+    fn default() -> CsvOptions {
+        CsvOptions {
+            delimiter: b',',
+            types: HashMap::new(),
+        }
+    }
+}
+
+impl CsvOptions {
+    /// Parses the type hints written as a list of `column:type`, e.g. `id:string,price:number`.
+    pub fn parse_types(hints: &str) -> Result<HashMap<String, CsvColumnType>, CsvError> {
+        let mut types = HashMap::new();
+        for hint in hints.split(',').filter(|hint| !hint.trim().is_empty()) {
+            let mut iter = hint.rsplitn(2, ':');
+            let (kind, column) = match (iter.next(), iter.next()) {
+                (Some(kind), Some(column)) => (kind, column.trim()),
+                _ => return Err(CsvError::InvalidTypeHint(hint.to_string())),
+            };
+            types.insert(column.to_string(), kind.parse()?);
+        }
+        Ok(types)
+    }
+}
+
+#[derive(Debug)]
+pub enum CsvError {
+    Csv(csv::Error),
+    UnknownColumn(String),
+    UnknownColumnType(String),
+    InvalidTypeHint(String),
+}
+
+impl fmt::Display for CsvError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CsvError::Csv(e) => write!(f, "invalid csv: {}", e),
+            CsvError::UnknownColumn(column) => {
+                write!(
+                    f,
+                    "the type hint column {:?} is not in the csv header",
+                    column
+                )
+            }
+            CsvError::UnknownColumnType(kind) => {
+                write!(
+                    f,
+                    "unknown column type {:?}, expected string or number",
+                    kind
+                )
+            }
+            CsvError::InvalidTypeHint(hint) => {
+                write!(f, "invalid type hint {:?}, expected column:type", hint)
+            }
+        }
+    }
+}
+
+impl error::Error for CsvError {}
+
+impl From<csv::Error> for CsvError {
+    fn from(error: csv::Error) -> CsvError {
+        CsvError::Csv(error)
+    }
+}
+
+/// Converts the records of a CSV file, with a header, into documents.
+///
+/// The empty cells are not part of the documents and the numbers with leading zeros,
+/// often identifiers or codes, are considered to be strings when inferring the types.
+pub fn csv_documents<R, D>(reader: R, options: &CsvOptions) -> Result<Vec<D>, CsvError>
+where
+    R: io::Read,
+    D: FromIterator<(String, Value)>,
+{
+    let mut rdr = csv::ReaderBuilder::new()
+        .delimiter(options.delimiter)
+        .from_reader(reader);
+
+    let headers: Vec<String> = rdr.headers()?.iter().map(str::to_string).collect();
+    for column in options.types.keys() {
+        if !headers.contains(column) {
+            return Err(CsvError::UnknownColumn(column.clone()));
+        }
+    }
+
+    let records = rdr.into_records().collect::<Result<Vec<_>, _>>()?;
+
+    let types: Vec<_> = headers
+        .iter()
+        .enumerate()
+        .map(|(i, header)| match options.types.get(header) {
+            Some(kind) => *kind,
+            None => {
+                let mut values = records.iter().filter_map(|r| r.get(i)).map(str::trim);
+                if values.all(|v| v.is_empty() || parse_number(v).is_some()) {
+                    CsvColumnType::Number
+                } else {
+                    CsvColumnType::String
+                }
+            }
+        })
+        .collect();
+
+    let mut documents = Vec::with_capacity(records.len());
+    for record in &records {
+        let document = headers
+            .iter()
+            .zip(&types)
+            .zip(record.iter())
+            .filter(|(_, value)| !value.trim().is_empty())
+            .map(|((header, kind), value)| {
+                let value = match kind {
+                    CsvColumnType::Number => match parse_number(value.trim()) {
+                        Some(number) => Value::Number(number),
+                        None => Value::String(value.to_string()),
+                    },
+                    CsvColumnType::String => Value::String(value.to_string()),
+                };
+                (header.clone(), value)
+            })
+            .collect();
+        documents.push(document);
+    }
+
+    Ok(documents)
+}
+
+fn parse_number(s: &str) -> Option<serde_json::Number> {
+    let digits = s.trim_start_matches('-');
+    if digits.len() > 1 && digits.starts_with('0') && !digits.starts_with("0.") {
+        return None;
+    }
+
+    if let Ok(n) = s.parse::<u64>() {
+        return Some(n.into());
+    }
+
+    if let Ok(n) = s.parse::<i64>() {
+        return Some(n.into());
+    }
+
+    s.parse::<f64>().ok().and_then(serde_json::Number::from_f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    type Document = Vec<(String, Value)>;
+
+    #[test]
+    fn infer_columns_types() {
+        let data = "id,title,price,code\n1,Carol,12.5,007\n2,,-3,10\n";
+        let documents: Vec<Document> = csv_documents(data.as_bytes(), &Default::default()).unwrap();
+
+        let expected = vec![
+            vec![
+                ("id".to_string(), json!(1)),
+                ("title".to_string(), json!("Carol")),
+                ("price".to_string(), json!(12.5)),
+                ("code".to_string(), json!("007")),
+            ],
+            vec![
+                ("id".to_string(), json!(2)),
+                ("price".to_string(), json!(-3)),
+                ("code".to_string(), json!("10")),
+            ],
+        ];
+
+        assert_eq!(documents, expected);
+    }
+
+    #[test]
+    fn delimiter_and_types_hints() {
+        let options = CsvOptions {
+            delimiter: b';',
+            types: CsvOptions::parse_types("id:string, year : number").unwrap(),
+        };
+
+        let data = "id;year\n1;2019\n2;unknown\n";
+        let documents: Vec<Document> = csv_documents(data.as_bytes(), &options).unwrap();
+
+        let expected = vec![
+            vec![
+                ("id".to_string(), json!("1")),
+                ("year".to_string(), json!(2019)),
+            ],
+            vec![
+                ("id".to_string(), json!("2")),
+                ("year".to_string(), json!("unknown")),
+            ],
+        ];
+
+        assert_eq!(documents, expected);
+
+        let options = CsvOptions {
+            types: CsvOptions::parse_types("price:number").unwrap(),
+            ..CsvOptions::default()
+        };
+        let result: Result<Vec<Document>, _> = csv_documents(data.as_bytes(), &options);
+        assert!(result.is_err());
+
+        assert!(CsvOptions::parse_types("price").is_err());
+        assert!(CsvOptions::parse_types("price:date").is_err());
+    }
+}
@@ -95,7 +95,7 @@ impl<'de, 'a, 'b> de::Deserializer<'de> for &'b mut Deserializer<'a> {
                 if is_displayed && self.attributes.map_or(true, |f| f.contains(&attr)) {
                     let attribute_name = self.schema.attribute_name(attr);
 
-                    let cursor = Cursor::new(value.to_owned());
+                    let cursor = Cursor::new(value.into_owned());
                     let ioread = SerdeJsonIoRead::new(cursor);
                     let value = Value(SerdeJsonDeserializer::new(ioread));
 
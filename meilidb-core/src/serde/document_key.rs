@@ -0,0 +1,234 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::{compute_document_id, SerializerError};
+use crate::DocumentId;
+
+/// The maximum length, in bytes, of a string document identifier.
+pub const MAX_DOCUMENT_KEY_LENGTH: usize = 512;
+
+/// The characters allowed in string document identifiers.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DocumentIdCharset {
+    /// Any character that is not a control character.
+    Any,
+    /// ASCII alphanumeric characters along with the extra characters given.
+    Alphanumeric { extra: String },
+}
+
+impl Default for DocumentIdCharset {
+    fn default() -> DocumentIdCharset {
+        DocumentIdCharset::Any
+    }
+}
+
+impl DocumentIdCharset {
+    pub fn contains(&self, c: char) -> bool {
+        match self {
+            DocumentIdCharset::Any => !c.is_control(),
+            DocumentIdCharset::Alphanumeric { extra } => {
+                c.is_ascii_alphanumeric() || extra.contains(c)
+            }
+        }
+    }
+}
+
+/// The user facing identifier of a document, as found in the identifier field.
+///
+/// Document identifiers are resolved in two phases: the raw value is first
+/// turned into a `DocumentKey`, rejecting the unsupported types, then the
+/// canonical representation of this key is hashed into a `DocumentId`.
+///
+/// Integers and strings share the same canonical representation, the integer
+/// `12` and the string `"12"` therefore identify the same document, this way
+/// an identifier found in an url always resolves to the right document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DocumentKey {
+    Integer(i128),
+    String(String),
+    /// A string in the hyphenated UUID format, it is hashed as given like the other
+    /// strings to keep resolving the documents indexed by the previous versions.
+    Uuid(String),
+    /// A floating point number, its string representation is hashed
+    /// to keep resolving the documents indexed by the previous versions.
+    Float(String),
+}
+
+impl DocumentKey {
+    pub fn from_value(value: &Value) -> Result<DocumentKey, SerializerError> {
+        match value {
+            Value::Number(number) => {
+                let integer = number
+                    .as_i64()
+                    .map(i128::from)
+                    .or_else(|| number.as_u64().map(i128::from));
+
+                match integer {
+                    Some(integer) => Ok(DocumentKey::Integer(integer)),
+                    None => Ok(DocumentKey::Float(number.to_string())),
+                }
+            }
+            Value::String(string) if is_uuid(string) => Ok(DocumentKey::Uuid(string.clone())),
+            Value::String(string) => Ok(DocumentKey::String(string.clone())),
+            _ => Err(SerializerError::InvalidDocumentIdType),
+        }
+    }
+
+    /// Checks that the key is not empty, not too long and only
+    /// contains characters that are part of the given charset.
+    pub fn validate(&self, charset: &DocumentIdCharset) -> Result<(), SerializerError> {
+        let string = match self {
+            DocumentKey::String(string) => string,
+            // numbers and UUIDs are always well-formed
+            DocumentKey::Integer(_) | DocumentKey::Uuid(_) | DocumentKey::Float(_) => return Ok(()),
+        };
+
+        let invalid = |reason: String| SerializerError::InvalidDocumentId {
+            id: string.clone(),
+            reason,
+        };
+
+        if string.is_empty() {
+            return Err(invalid(String::from("it is empty")));
+        }
+
+        if string.len() > MAX_DOCUMENT_KEY_LENGTH {
+            let reason = format!("it is longer than {} bytes", MAX_DOCUMENT_KEY_LENGTH);
+            return Err(invalid(reason));
+        }
+
+        if let Some(c) = string.chars().find(|c| !charset.contains(*c)) {
+            return Err(invalid(format!("the {:?} character is not allowed", c)));
+        }
+
+        Ok(())
+    }
+
+    pub fn document_id(&self) -> DocumentId {
+        compute_document_id(self.to_string())
+    }
+
+    /// Returns the key as it was given by the user.
+    pub fn to_value(&self) -> Value {
+        match self {
+            DocumentKey::Integer(integer) => {
+                let number = if *integer < 0 {
+                    serde_json::Number::from(*integer as i64)
+                } else {
+                    serde_json::Number::from(*integer as u64)
+                };
+                Value::Number(number)
+            }
+            DocumentKey::String(string) | DocumentKey::Uuid(string) => {
+                Value::String(string.clone())
+            }
+            DocumentKey::Float(string) => string
+                .parse()
+                .ok()
+                .and_then(serde_json::Number::from_f64)
+                .map_or(Value::Null, Value::Number),
+        }
+    }
+}
+
+impl fmt::Display for DocumentKey {
+    /// Writes the canonical representation of the key.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DocumentKey::Integer(integer) => write!(f, "{}", integer),
+            DocumentKey::String(string)
+            | DocumentKey::Uuid(string)
+            | DocumentKey::Float(string) => f.write_str(string),
+        }
+    }
+}
+
+fn is_uuid(s: &str) -> bool {
+    s.len() == 36
+        && s.char_indices().all(|(i, c)| match i {
+            8 | 13 | 18 | 23 => c == '-',
+            _ => c.is_ascii_hexdigit(),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn integers_and_strings_resolve_uniformly() {
+        let integer = DocumentKey::from_value(&json!(12)).unwrap();
+        let string = DocumentKey::from_value(&json!("12")).unwrap();
+
+        assert_eq!(integer, DocumentKey::Integer(12));
+        assert_eq!(string, DocumentKey::String(String::from("12")));
+        assert_eq!(integer.document_id(), string.document_id());
+
+        // the previous hashing scheme is preserved
+        assert_eq!(integer.document_id(), compute_document_id("12".to_string()));
+
+        assert_eq!(integer.to_value(), json!(12));
+        assert_eq!(string.to_value(), json!("12"));
+    }
+
+    #[test]
+    fn uuids_keep_the_previous_hashing() {
+        let lower =
+            DocumentKey::from_value(&json!("67e55044-10b1-426f-9247-bb680e5fe0c8")).unwrap();
+        let upper =
+            DocumentKey::from_value(&json!("67E55044-10B1-426F-9247-BB680E5FE0C8")).unwrap();
+
+        assert_matches!(lower, DocumentKey::Uuid(_));
+        assert_eq!(
+            upper.document_id(),
+            compute_document_id("67E55044-10B1-426F-9247-BB680E5FE0C8".to_string())
+        );
+        assert_ne!(lower.document_id(), upper.document_id());
+        assert_eq!(
+            upper.to_value(),
+            json!("67E55044-10B1-426F-9247-BB680E5FE0C8")
+        );
+    }
+
+    #[test]
+    fn floats_keep_the_previous_hashing() {
+        let float = DocumentKey::from_value(&json!(1.5)).unwrap();
+
+        assert_eq!(float, DocumentKey::Float(String::from("1.5")));
+        assert_eq!(float.document_id(), compute_document_id("1.5".to_string()));
+        assert!(float.validate(&DocumentIdCharset::default()).is_ok());
+        assert_eq!(float.to_value(), json!(1.5));
+    }
+
+    #[test]
+    fn invalid_keys() {
+        assert_matches!(
+            DocumentKey::from_value(&json!(true)),
+            Err(SerializerError::InvalidDocumentIdType)
+        );
+        assert_matches!(
+            DocumentKey::from_value(&json!(null)),
+            Err(SerializerError::InvalidDocumentIdType)
+        );
+
+        let charset = DocumentIdCharset::Alphanumeric {
+            extra: String::from("-_"),
+        };
+
+        let valid = DocumentKey::String(String::from("movie_12-b"));
+        assert!(valid.validate(&charset).is_ok());
+
+        let empty = DocumentKey::String(String::new());
+        assert!(empty.validate(&charset).is_err());
+
+        let spaced = DocumentKey::String(String::from("movie 12"));
+        assert!(spaced.validate(&charset).is_err());
+        assert!(spaced.validate(&DocumentIdCharset::Any).is_ok());
+
+        let control = DocumentKey::String(String::from("movie\n12"));
+        assert!(control.validate(&DocumentIdCharset::Any).is_err());
+    }
+}
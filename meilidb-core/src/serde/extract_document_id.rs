@@ -5,12 +5,14 @@ use serde::{ser, Serialize};
 use serde_json::Value;
 use siphasher::sip::SipHasher;
 
-use super::{ConvertToString, SerializerError};
+use super::{ConvertToString, DocumentKey, SerializerError};
 
-pub fn extract_document_id<D>(
+/// Extracts the key of the document found in the identifier field,
+/// the key is resolved but not validated against any charset.
+pub fn extract_document_key<D>(
     identifier: &str,
     document: &D,
-) -> Result<Option<DocumentId>, SerializerError>
+) -> Result<Option<DocumentKey>, SerializerError>
 where
     D: serde::Serialize,
 {
@@ -18,6 +20,17 @@ where
     document.serialize(serializer)
 }
 
+pub fn extract_document_id<D>(
+    identifier: &str,
+    document: &D,
+) -> Result<Option<DocumentId>, SerializerError>
+where
+    D: serde::Serialize,
+{
+    let key = extract_document_key(identifier, document)?;
+    Ok(key.map(|key| key.document_id()))
+}
+
 pub fn value_to_string(value: &Value) -> Option<String> {
     match value {
         Value::Null => None,
@@ -41,7 +54,7 @@ struct ExtractDocumentId<'a> {
 }
 
 impl<'a> ser::Serializer for ExtractDocumentId<'a> {
-    type Ok = Option<DocumentId>;
+    type Ok = Option<DocumentKey>;
     type Error = SerializerError;
     type SerializeSeq = ser::Impossible<Self::Ok, Self::Error>;
     type SerializeTuple = ser::Impossible<Self::Ok, Self::Error>;
@@ -174,7 +187,7 @@ impl<'a> ser::Serializer for ExtractDocumentId<'a> {
     fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
         let serializer = ExtractDocumentIdMapSerializer {
             identifier: self.identifier,
-            document_id: None,
+            document_key: None,
             current_key_name: None,
         };
 
@@ -188,7 +201,7 @@ impl<'a> ser::Serializer for ExtractDocumentId<'a> {
     ) -> Result<Self::SerializeStruct, Self::Error> {
         let serializer = ExtractDocumentIdStructSerializer {
             identifier: self.identifier,
-            document_id: None,
+            document_key: None,
         };
 
         Ok(serializer)
@@ -209,12 +222,12 @@ impl<'a> ser::Serializer for ExtractDocumentId<'a> {
 
 pub struct ExtractDocumentIdMapSerializer<'a> {
     identifier: &'a str,
-    document_id: Option<DocumentId>,
+    document_key: Option<DocumentKey>,
     current_key_name: Option<String>,
 }
 
 impl<'a> ser::SerializeMap for ExtractDocumentIdMapSerializer<'a> {
-    type Ok = Option<DocumentId>;
+    type Ok = Option<DocumentKey>;
     type Error = SerializerError;
 
     fn serialize_key<T: ?Sized>(&mut self, key: &T) -> Result<(), Self::Error>
@@ -247,27 +260,24 @@ impl<'a> ser::SerializeMap for ExtractDocumentIdMapSerializer<'a> {
 
         if self.identifier == key {
             let value = serde_json::to_string(value).and_then(|s| serde_json::from_str(&s))?;
-            match value_to_string(&value).map(|s| compute_document_id(&s)) {
-                Some(document_id) => self.document_id = Some(document_id),
-                None => return Err(SerializerError::InvalidDocumentIdType),
-            }
+            self.document_key = Some(DocumentKey::from_value(&value)?);
         }
 
         Ok(())
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
-        Ok(self.document_id)
+        Ok(self.document_key)
     }
 }
 
 pub struct ExtractDocumentIdStructSerializer<'a> {
     identifier: &'a str,
-    document_id: Option<DocumentId>,
+    document_key: Option<DocumentKey>,
 }
 
 impl<'a> ser::SerializeStruct for ExtractDocumentIdStructSerializer<'a> {
-    type Ok = Option<DocumentId>;
+    type Ok = Option<DocumentKey>;
     type Error = SerializerError;
 
     fn serialize_field<T: ?Sized>(
@@ -280,16 +290,13 @@ impl<'a> ser::SerializeStruct for ExtractDocumentIdStructSerializer<'a> {
     {
         if self.identifier == key {
             let value = serde_json::to_string(value).and_then(|s| serde_json::from_str(&s))?;
-            match value_to_string(&value).map(compute_document_id) {
-                Some(document_id) => self.document_id = Some(document_id),
-                None => return Err(SerializerError::InvalidDocumentIdType),
-            }
+            self.document_key = Some(DocumentKey::from_value(&value)?);
         }
 
         Ok(())
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
-        Ok(self.document_id)
+        Ok(self.document_key)
     }
 }
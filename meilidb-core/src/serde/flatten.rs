@@ -0,0 +1,186 @@
+use std::collections::{BTreeMap, HashMap};
+
+use meilidb_schema::{Schema, SchemaProps};
+use serde_json::Value;
+
+use crate::error::LimitExceeded;
+
+/// Flattens the nested objects of a document into dotted attributes,
+/// `{ "address": { "city": "Paris" } }` becomes `{ "address.city": "Paris" }`.
+///
+/// The arrays of objects are flattened into arrays of the values of their fields under
+/// `name[].field`, the values of these arrays that are not objects are listed under `name[]`.
+/// The arrays without objects are kept as they are.
+pub fn flatten_document(document: HashMap<String, Value>) -> HashMap<String, Value> {
+    let mut flattened = HashMap::with_capacity(document.len());
+    for (name, value) in document {
+        flatten_value(name, value, &mut flattened);
+    }
+    flattened
+}
+
+fn flatten_value(name: String, value: Value, output: &mut HashMap<String, Value>) {
+    match value {
+        Value::Object(object) => {
+            for (key, value) in object {
+                flatten_value(format!("{}.{}", name, key), value, output);
+            }
+        }
+        Value::Array(values) if values.iter().any(Value::is_object) => {
+            let name = format!("{}[]", name);
+            for value in values {
+                let mut fields = HashMap::new();
+                flatten_value(name.clone(), value, &mut fields);
+                for (key, value) in fields {
+                    let values = output
+                        .entry(key)
+                        .or_insert_with(|| Value::Array(Vec::new()));
+                    if let Value::Array(values) = values {
+                        match value {
+                            Value::Array(array) => values.extend(array),
+                            value => values.push(value),
+                        }
+                    }
+                }
+            }
+        }
+        value => {
+            output.insert(name, value);
+        }
+    }
+}
+
+/// The name of the attribute a flattened attribute comes from, `address` for `address.city`.
+pub fn root_attribute(name: &str) -> &str {
+    match name.find(|c| c == '.' || c == '[') {
+        Some(index) => &name[..index],
+        None => name,
+    }
+}
+
+/// Appends to the schema the flattened attributes of the documents it does not know about,
+/// they are displayed and indexed like their root attribute but never ranked. The attributes
+/// whose root is not in the schema are ignored like any unknown attribute.
+///
+/// Returns `None` if the schema does not need to be extended and an error if
+/// the extended schema would have more than `max_attributes` attributes.
+pub fn extend_schema<'a, I>(
+    schema: &Schema,
+    documents: I,
+    max_attributes: usize,
+) -> Result<Option<Schema>, LimitExceeded>
+where
+    I: IntoIterator<Item = &'a HashMap<String, Value>>,
+{
+    // the attributes are appended in a deterministic order
+    let mut new_attributes = BTreeMap::new();
+    for document in documents {
+        for name in document.keys() {
+            if schema.attribute(name).is_some() || new_attributes.contains_key(name) {
+                continue;
+            }
+
+            let root = root_attribute(name);
+            if let Some(attribute) = schema.attribute(root) {
+                let props = schema.props(attribute);
+                let props = SchemaProps {
+                    ranked: false,
+                    ..props
+                };
+                new_attributes.insert(name.clone(), props);
+            }
+        }
+    }
+
+    let found = schema.number_of_attributes() + new_attributes.len();
+    if found > max_attributes {
+        let limit = max_attributes;
+        return Err(LimitExceeded::TooManyAttributes { limit, found });
+    }
+
+    if new_attributes.is_empty() {
+        Ok(None)
+    } else {
+        Ok(Some(schema.with_new_attributes(new_attributes)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use meilidb_schema::{SchemaBuilder, DISPLAYED, INDEXED, RANKED};
+    use serde_json::json;
+
+    fn document(value: Value) -> HashMap<String, Value> {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn flatten_nested_objects_and_arrays() {
+        let nested = document(json!({
+            "id": 1,
+            "address": { "city": "Paris", "geo": { "zip": "75001" } },
+            "tags": ["rust", "search"],
+            "authors": [
+                { "name": "Kero", "roles": ["dev"] },
+                { "name": "Qtn", "roles": ["dev", "ops"] },
+                "anonymous",
+            ],
+        }));
+
+        let expected = document(json!({
+            "id": 1,
+            "address.city": "Paris",
+            "address.geo.zip": "75001",
+            "tags": ["rust", "search"],
+            "authors[].name": ["Kero", "Qtn"],
+            "authors[].roles": ["dev", "dev", "ops"],
+            "authors[]": ["anonymous"],
+        }));
+
+        assert_eq!(flatten_document(nested), expected);
+    }
+
+    #[test]
+    fn extend_schema_with_flattened_attributes() {
+        let mut builder = SchemaBuilder::with_identifier("id");
+        builder.new_attribute("id", DISPLAYED);
+        builder.new_attribute("address", DISPLAYED | INDEXED | RANKED);
+        builder.new_attribute("address.zip", DISPLAYED);
+        let schema = builder.build();
+
+        let documents = vec![
+            document(json!({ "id": 1, "address.zip": "75001", "address.city": "Paris" })),
+            document(json!({ "id": 2, "address.city": "Lyon", "unknown.field": true })),
+            document(json!({ "id": 3, "address.country": "France" })),
+        ];
+
+        let extended = extend_schema(&schema, &documents, 10).unwrap().unwrap();
+        assert_eq!(extended.number_of_attributes(), 5);
+
+        let city = extended.attribute("address.city").unwrap();
+        assert_eq!(extended.props(city), DISPLAYED | INDEXED);
+        let country = extended.attribute("address.country").unwrap();
+        assert!(city < country);
+        assert!(extended.attribute("unknown.field").is_none());
+
+        assert!(extend_schema(&extended, &documents, 10).unwrap().is_none());
+    }
+
+    #[test]
+    fn extend_schema_over_the_limit() {
+        let mut builder = SchemaBuilder::with_identifier("id");
+        builder.new_attribute("id", DISPLAYED);
+        builder.new_attribute("tags", DISPLAYED | INDEXED);
+        let schema = builder.build();
+
+        let document = document(json!({ "id": 1, "tags.a": 1, "tags.b": 2, "tags.c": 3 }));
+        let documents = vec![document.clone(), document];
+
+        assert!(extend_schema(&schema, &documents, 5).unwrap().is_some());
+        match extend_schema(&schema, &documents, 4) {
+            Err(LimitExceeded::TooManyAttributes { limit: 4, found: 5 }) => (),
+            other => panic!("unexpected result {:?}", other.map(|s| s.is_some())),
+        }
+    }
+}
@@ -8,19 +8,35 @@ macro_rules! forward_to_unserializable_type {
     }
 }
 
+mod content_hash;
 mod convert_to_number;
 mod convert_to_string;
+#[cfg(feature = "csv-documents")]
+mod csv_documents;
 mod deserializer;
+mod document_key;
 mod extract_document_id;
+mod flatten;
 mod indexer;
+mod schema_inference;
 mod serializer;
+mod typed_value;
 
+pub use self::content_hash::compute_content_hash;
 pub use self::convert_to_number::ConvertToNumber;
 pub use self::convert_to_string::ConvertToString;
+#[cfg(feature = "csv-documents")]
+pub use self::csv_documents::{csv_documents, CsvColumnType, CsvError, CsvOptions};
 pub use self::deserializer::{Deserializer, DeserializerError};
-pub use self::extract_document_id::{compute_document_id, extract_document_id, value_to_string};
+pub use self::document_key::{DocumentIdCharset, DocumentKey, MAX_DOCUMENT_KEY_LENGTH};
+pub use self::extract_document_id::{
+    compute_document_id, extract_document_id, extract_document_key, value_to_string,
+};
+pub use self::flatten::{extend_schema, flatten_document, root_attribute};
 pub use self::indexer::Indexer;
+pub use self::schema_inference::{discover_attributes, infer_schema};
 pub use self::serializer::{serialize_value, Serializer};
+pub use self::typed_value::{Aggregation, AttributeType, AttributesTypes};
 
 use std::{error::Error, fmt};
 
@@ -33,6 +49,7 @@ use crate::ParseNumberError;
 pub enum SerializerError {
     DocumentIdNotFound,
     InvalidDocumentIdType,
+    InvalidDocumentId { id: String, reason: String },
     Zlmdb(heed::Error),
     SerdeJson(SerdeJsonError),
     ParseNumber(ParseNumberError),
@@ -57,6 +74,9 @@ impl fmt::Display for SerializerError {
             SerializerError::InvalidDocumentIdType => {
                 f.write_str("document identifier can only be of type string or number")
             }
+            SerializerError::InvalidDocumentId { id, reason } => {
+                write!(f, "document identifier {:?} is invalid: {}", id, reason)
+            }
             SerializerError::Zlmdb(e) => write!(f, "heed related error: {}", e),
             SerializerError::SerdeJson(e) => write!(f, "serde json error: {}", e),
             SerializerError::ParseNumber(e) => {
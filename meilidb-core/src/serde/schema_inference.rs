@@ -0,0 +1,155 @@
+use std::collections::{BTreeSet, HashMap};
+
+use meilidb_schema::{Schema, SchemaBuilder, DISPLAYED, INDEXED};
+use serde_json::Value;
+
+use crate::error::LimitExceeded;
+
+/// Whether the attribute name is an identifier of another thing, like `movie_id` or `movieId`.
+fn is_suffixed_identifier(name: &str) -> bool {
+    let lowercase = name.to_lowercase();
+    let snake_case = lowercase.ends_with("_id");
+    let camel_case = name.len() > 2 && name.ends_with("Id");
+    snake_case || camel_case
+}
+
+/// Infers a schema from the attributes of a document, the identifier is the `id` attribute
+/// whatever its case, else the first attribute named like `movie_id` or `movieId`, else the
+/// default identifier. All the attributes are displayed and indexed.
+pub fn infer_schema<'a, I>(attributes: I, default_identifier: Option<&str>) -> Option<Schema>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let attributes: Vec<_> = attributes.into_iter().collect();
+
+    let identifier = attributes
+        .iter()
+        .find(|name| name.eq_ignore_ascii_case("id"))
+        .or_else(|| attributes.iter().find(|name| is_suffixed_identifier(name)))
+        .cloned()
+        .or(default_identifier)?;
+
+    let mut builder = SchemaBuilder::with_identifier(identifier);
+    if !attributes.contains(&identifier) {
+        builder.new_attribute(identifier, DISPLAYED | INDEXED);
+    }
+    for name in attributes {
+        builder.new_attribute(name, DISPLAYED | INDEXED);
+    }
+    Some(builder.build())
+}
+
+/// Appends to the schema the attributes of the documents it does not know about,
+/// they are displayed and indexed. Returns `None` if the schema does not need to be extended
+/// and an error if the extended schema would have more than `max_attributes` attributes.
+pub fn discover_attributes<'a, I>(
+    schema: &Schema,
+    documents: I,
+    max_attributes: usize,
+) -> Result<Option<Schema>, LimitExceeded>
+where
+    I: IntoIterator<Item = &'a HashMap<String, Value>>,
+{
+    // the attributes are appended in a deterministic order
+    let new_attributes: BTreeSet<_> = documents
+        .into_iter()
+        .flat_map(|document| document.keys())
+        .filter(|name| schema.attribute(name).is_none())
+        .collect();
+
+    let found = schema.number_of_attributes() + new_attributes.len();
+    if found > max_attributes {
+        let limit = max_attributes;
+        return Err(LimitExceeded::TooManyAttributes { limit, found });
+    }
+
+    if new_attributes.is_empty() {
+        Ok(None)
+    } else {
+        let new_attributes = new_attributes
+            .into_iter()
+            .map(|name| (name.as_str(), DISPLAYED | INDEXED));
+        Ok(Some(schema.with_new_attributes(new_attributes)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn infer_schema_identifier() {
+        assert!(infer_schema(vec!["title"], None).is_none());
+
+        let schema = infer_schema(vec!["title"], Some("id")).unwrap();
+        assert_eq!(schema.identifier_name(), "id");
+        assert_eq!(schema.number_of_attributes(), 2);
+
+        let schema = infer_schema(vec!["title", "movie_id", "author_id"], Some("id")).unwrap();
+        assert_eq!(schema.identifier_name(), "movie_id");
+        assert!(schema.attribute("id").is_none());
+        assert_eq!(
+            schema.props(schema.attribute("title").unwrap()),
+            DISPLAYED | INDEXED
+        );
+    }
+
+    #[test]
+    fn infer_schema_exact_identifier_first() {
+        // the attributes of the first document are given in alphabetical order
+        let schema = infer_schema(vec!["category_id", "id"], None).unwrap();
+        assert_eq!(schema.identifier_name(), "id");
+
+        let schema = infer_schema(vec!["ID", "movieId"], None).unwrap();
+        assert_eq!(schema.identifier_name(), "ID");
+
+        let schema = infer_schema(vec!["hidden", "id"], None).unwrap();
+        assert_eq!(schema.identifier_name(), "id");
+
+        let schema = infer_schema(vec!["hidden", "movieId", "title"], None).unwrap();
+        assert_eq!(schema.identifier_name(), "movieId");
+    }
+
+    #[test]
+    fn infer_schema_no_substring_identifier() {
+        assert!(infer_schema(vec!["hidden", "video", "width"], None).is_none());
+        assert!(infer_schema(vec!["Idaho", "paid", "valid"], None).is_none());
+
+        let schema = infer_schema(vec!["Idaho", "_id"], None).unwrap();
+        assert_eq!(schema.identifier_name(), "_id");
+
+        let schema = infer_schema(vec!["hidden", "title"], Some("id")).unwrap();
+        assert_eq!(schema.identifier_name(), "id");
+        assert!(schema.attribute("hidden").is_some());
+    }
+
+    #[test]
+    fn discover_new_attributes() {
+        let schema = infer_schema(vec!["id", "title"], None).unwrap();
+
+        let documents: Vec<HashMap<String, Value>> = vec![
+            serde_json::from_value(json!({ "id": 1, "title": "Alien", "year": 1979 })).unwrap(),
+            serde_json::from_value(json!({ "id": 2, "director": "Scott", "year": 1982 })).unwrap(),
+        ];
+
+        let extended = discover_attributes(&schema, &documents, 4)
+            .unwrap()
+            .unwrap();
+        assert_eq!(extended.number_of_attributes(), 4);
+
+        let director = extended.attribute("director").unwrap();
+        let year = extended.attribute("year").unwrap();
+        assert!(director < year);
+        assert_eq!(extended.props(year), DISPLAYED | INDEXED);
+
+        assert!(discover_attributes(&extended, &documents, 4)
+            .unwrap()
+            .is_none());
+
+        match discover_attributes(&schema, &documents, 3) {
+            Err(LimitExceeded::TooManyAttributes { limit: 3, found: 4 }) => (),
+            other => panic!("unexpected result {:?}", other.map(|s| s.is_some())),
+        }
+    }
+}
@@ -1,15 +1,18 @@
 use meilidb_schema::{Schema, SchemaAttr, SchemaProps};
 use serde::ser;
 
-use crate::raw_indexer::RawIndexer;
+use crate::raw_indexer::{PreToken, RawIndexer};
 use crate::store::{DocumentsFields, DocumentsFieldsCounts};
 use crate::{DocumentId, RankedMap};
 
-use super::{ConvertToNumber, ConvertToString, Indexer, SerializerError};
+use super::{
+    AttributeType, AttributesTypes, ConvertToNumber, ConvertToString, Indexer, SerializerError,
+};
 
 pub struct Serializer<'a, 'b> {
     pub txn: &'a mut heed::RwTxn<'b>,
     pub schema: &'a Schema,
+    pub attributes_types: &'a AttributesTypes,
     pub document_store: DocumentsFields,
     pub document_fields_counts: DocumentsFieldsCounts,
     pub indexer: &'a mut RawIndexer,
@@ -152,6 +155,7 @@ impl<'a, 'b> ser::Serializer for Serializer<'a, 'b> {
         Ok(MapSerializer {
             txn: self.txn,
             schema: self.schema,
+            attributes_types: self.attributes_types,
             document_id: self.document_id,
             document_store: self.document_store,
             document_fields_counts: self.document_fields_counts,
@@ -169,6 +173,7 @@ impl<'a, 'b> ser::Serializer for Serializer<'a, 'b> {
         Ok(StructSerializer {
             txn: self.txn,
             schema: self.schema,
+            attributes_types: self.attributes_types,
             document_id: self.document_id,
             document_store: self.document_store,
             document_fields_counts: self.document_fields_counts,
@@ -193,6 +198,7 @@ impl<'a, 'b> ser::Serializer for Serializer<'a, 'b> {
 pub struct MapSerializer<'a, 'b> {
     txn: &'a mut heed::RwTxn<'b>,
     schema: &'a Schema,
+    attributes_types: &'a AttributesTypes,
     document_id: DocumentId,
     document_store: DocumentsFields,
     document_fields_counts: DocumentsFieldsCounts,
@@ -237,6 +243,7 @@ impl<'a, 'b> ser::SerializeMap for MapSerializer<'a, 'b> {
                 self.txn,
                 attribute,
                 self.schema.props(attribute),
+                self.attributes_types.get(&key).cloned(),
                 self.document_id,
                 self.document_store,
                 self.document_fields_counts,
@@ -256,6 +263,7 @@ impl<'a, 'b> ser::SerializeMap for MapSerializer<'a, 'b> {
 pub struct StructSerializer<'a, 'b> {
     txn: &'a mut heed::RwTxn<'b>,
     schema: &'a Schema,
+    attributes_types: &'a AttributesTypes,
     document_id: DocumentId,
     document_store: DocumentsFields,
     document_fields_counts: DocumentsFieldsCounts,
@@ -280,6 +288,7 @@ impl<'a, 'b> ser::SerializeStruct for StructSerializer<'a, 'b> {
                 self.txn,
                 attribute,
                 self.schema.props(attribute),
+                self.attributes_types.get(key).cloned(),
                 self.document_id,
                 self.document_store,
                 self.document_fields_counts,
@@ -300,6 +309,7 @@ pub fn serialize_value<T: ?Sized>(
     txn: &mut heed::RwTxn,
     attribute: SchemaAttr,
     props: SchemaProps,
+    attribute_type: Option<AttributeType>,
     document_id: DocumentId,
     document_store: DocumentsFields,
     documents_fields_counts: DocumentsFieldsCounts,
@@ -313,13 +323,23 @@ where
     let serialized = serde_json::to_vec(value)?;
     document_store.put_document_field(txn, document_id, attribute, &serialized)?;
 
-    if props.is_indexed() {
-        let indexer = Indexer {
-            attribute,
-            indexer,
-            document_id,
+    if props.is_indexed() && indexer.is_searchable(attribute) {
+        let number_of_words = match attribute_type {
+            Some(AttributeType::Tokens) => {
+                let tokens: Vec<PreToken> = serde_json::from_slice(&serialized)?;
+                Some(indexer.index_tokens(document_id, attribute, &tokens))
+            }
+            _ => {
+                let indexer = Indexer {
+                    attribute,
+                    indexer,
+                    document_id,
+                };
+                value.serialize(indexer)?
+            }
         };
-        if let Some(number_of_words) = value.serialize(indexer)? {
+
+        if let Some(number_of_words) = number_of_words {
             documents_fields_counts.put_document_field_count(
                 txn,
                 document_id,
@@ -329,9 +349,22 @@ where
         }
     }
 
-    if props.is_ranked() {
-        let number = value.serialize(ConvertToNumber)?;
-        ranked_map.insert(document_id, attribute, number);
+    // the typed value replaces the ranked one, the fields that are
+    // not of the attribute type are not sortable nor filterable
+    match attribute_type {
+        Some(attribute_type) if !attribute_type.is_sortable() => (),
+        Some(attribute_type) => {
+            let value = serde_json::to_value(value)?;
+            if let Some(number) = attribute_type.parse_value(&value) {
+                ranked_map.insert(document_id, attribute, number);
+            }
+        }
+        None => {
+            if props.is_ranked() {
+                let number = value.serialize(ConvertToNumber)?;
+                ranked_map.insert(document_id, attribute, number);
+            }
+        }
     }
 
     Ok(())
@@ -0,0 +1,239 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+use ordered_float::OrderedFloat;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::Number;
+
+/// The types of the attributes, by attribute name.
+pub type AttributesTypes = BTreeMap<String, AttributeType>;
+
+/// The type with which the values of an attribute are interpreted.
+///
+/// The typed value is stored in the ranked map to be sorted and filtered on, apart from
+/// the booleans that are only filtered on, the original value is kept untouched for display.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AttributeType {
+    /// ISO 8601 dates and date times, stored as a number of seconds since the Unix epoch,
+    /// date times without an offset are considered to be UTC ones.
+    Date,
+    /// Booleans and `true`/`false`, `yes`/`no` or `1`/`0` strings, compared as 1 or 0
+    /// by the filters, they are not stored in the ranked map and cannot be sorted on.
+    Boolean,
+    /// Arrays of words already tokenized by the caller, indexed as they are given
+    /// instead of with the built-in tokenizer, they are neither sorted nor filtered on.
+    Tokens,
+    /// Numbers or arrays of numbers, numeric strings included, the numbers of an array
+    /// are aggregated into the single value sorted and filtered on, an empty array has none.
+    Aggregated(Aggregation),
+}
+
+/// How the numbers of a multi-valued attribute are reduced to the value stored in the ranked map.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Aggregation {
+    Min,
+    Max,
+    Sum,
+    /// The mean of the numbers, always a float.
+    Avg,
+}
+
+impl Aggregation {
+    /// Aggregates the numbers, `None` if there is none.
+    pub fn aggregate(self, numbers: &[Number]) -> Option<Number> {
+        if numbers.is_empty() {
+            return None;
+        }
+
+        match self {
+            Aggregation::Min => numbers.iter().min().cloned(),
+            Aggregation::Max => numbers.iter().max().cloned(),
+            Aggregation::Sum => Some(sum(numbers)),
+            Aggregation::Avg => {
+                let sum: f64 = numbers.iter().map(|n| as_f64(*n)).sum();
+                Some(Number::Float(OrderedFloat(sum / numbers.len() as f64)))
+            }
+        }
+    }
+}
+
+/// Sums the numbers, the sum stays an integer as long as all
+/// the numbers are integers and it does not overflow.
+fn sum(numbers: &[Number]) -> Number {
+    let unsigned = numbers.iter().try_fold(0u64, |sum, n| match n {
+        Number::Unsigned(n) => sum.checked_add(*n),
+        _ => None,
+    });
+
+    let signed = || {
+        numbers.iter().try_fold(0i64, |sum, n| match n {
+            Number::Unsigned(n) if *n <= i64::max_value() as u64 => sum.checked_add(*n as i64),
+            Number::Signed(n) => sum.checked_add(*n),
+            _ => None,
+        })
+    };
+
+    match unsigned {
+        Some(sum) => Number::Unsigned(sum),
+        None => match signed() {
+            Some(sum) => Number::Signed(sum),
+            None => Number::Float(OrderedFloat(numbers.iter().map(|n| as_f64(*n)).sum())),
+        },
+    }
+}
+
+fn as_f64(number: Number) -> f64 {
+    match number {
+        Number::Unsigned(n) => n as f64,
+        Number::Signed(n) => n as f64,
+        Number::Float(n) => n.into_inner(),
+    }
+}
+
+impl AttributeType {
+    /// Whether the typed values are stored in the ranked map to sort the documents.
+    pub fn is_sortable(self) -> bool {
+        match self {
+            AttributeType::Date | AttributeType::Aggregated(_) => true,
+            AttributeType::Boolean | AttributeType::Tokens => false,
+        }
+    }
+
+    /// Returns the typed value of a document field, `None` if it is not of this type.
+    pub fn parse_value(self, value: &Value) -> Option<Number> {
+        match (self, value) {
+            (AttributeType::Aggregated(aggregation), Value::Array(values)) => {
+                let numbers: Option<Vec<_>> = values.iter().map(parse_number).collect();
+                aggregation.aggregate(&numbers?)
+            }
+            (AttributeType::Aggregated(_), value) => parse_number(value),
+            (_, Value::String(s)) => self.parse_str(s),
+            (AttributeType::Date, Value::Number(n)) => n.as_i64().map(Number::Signed),
+            (AttributeType::Boolean, Value::Bool(b)) => Some(Number::Unsigned(u64::from(*b))),
+            (_, _) => None,
+        }
+    }
+
+    pub fn parse_str(self, s: &str) -> Option<Number> {
+        let s = s.trim();
+        match self {
+            AttributeType::Date => parse_date(s).map(Number::Signed),
+            AttributeType::Boolean => match s.to_lowercase().as_str() {
+                "true" | "yes" | "1" => Some(Number::Unsigned(1)),
+                "false" | "no" | "0" => Some(Number::Unsigned(0)),
+                _ => None,
+            },
+            AttributeType::Tokens => None,
+            AttributeType::Aggregated(_) => s.parse().ok(),
+        }
+    }
+}
+
+/// The number or the numeric string, an array of
+/// numbers is only aggregated when none is invalid.
+fn parse_number(value: &Value) -> Option<Number> {
+    match value {
+        Value::Number(n) => n.to_string().parse().ok(),
+        Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+fn parse_date(s: &str) -> Option<i64> {
+    if let Ok(datetime) = DateTime::parse_from_rfc3339(s) {
+        return Some(datetime.timestamp());
+    }
+
+    if let Ok(datetime) = NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S") {
+        return Some(datetime.timestamp());
+    }
+
+    match NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        Ok(date) => Some(date.and_hms(0, 0, 0).timestamp()),
+        Err(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn parse_dates() {
+        let date = AttributeType::Date;
+
+        let midnight = Number::Signed(1572825600);
+        assert_eq!(date.parse_value(&json!("2019-11-04")), Some(midnight));
+        assert_eq!(
+            date.parse_value(&json!("2019-11-04T00:00:00")),
+            Some(midnight)
+        );
+        assert_eq!(
+            date.parse_value(&json!("2019-11-04T01:00:00+01:00")),
+            Some(midnight)
+        );
+        assert_eq!(date.parse_value(&json!(1572825600)), Some(midnight));
+
+        assert_eq!(date.parse_value(&json!("04/11/2019")), None);
+        assert_eq!(date.parse_value(&json!(true)), None);
+    }
+
+    #[test]
+    fn parse_booleans() {
+        let boolean = AttributeType::Boolean;
+
+        assert_eq!(boolean.parse_value(&json!(true)), Some(Number::Unsigned(1)));
+        assert_eq!(
+            boolean.parse_value(&json!("Yes")),
+            Some(Number::Unsigned(1))
+        );
+        assert_eq!(
+            boolean.parse_value(&json!("false")),
+            Some(Number::Unsigned(0))
+        );
+        assert_eq!(
+            boolean.parse_value(&json!(" 0 ")),
+            Some(Number::Unsigned(0))
+        );
+
+        assert_eq!(boolean.parse_value(&json!("maybe")), None);
+        assert_eq!(boolean.parse_value(&json!(1)), None);
+    }
+
+    #[test]
+    fn aggregate_arrays() {
+        let min = AttributeType::Aggregated(Aggregation::Min);
+        let max = AttributeType::Aggregated(Aggregation::Max);
+        let sum = AttributeType::Aggregated(Aggregation::Sum);
+        let avg = AttributeType::Aggregated(Aggregation::Avg);
+
+        let ratings = json!([4, 2, "5", 3]);
+        assert_eq!(min.parse_value(&ratings), Some(Number::Unsigned(2)));
+        assert_eq!(max.parse_value(&ratings), Some(Number::Unsigned(5)));
+        assert_eq!(sum.parse_value(&ratings), Some(Number::Unsigned(14)));
+        assert_eq!(
+            avg.parse_value(&ratings),
+            Some(Number::Float(OrderedFloat(3.5)))
+        );
+
+        assert_eq!(sum.parse_value(&json!([3, -5])), Some(Number::Signed(-2)));
+        assert_eq!(
+            sum.parse_value(&json!([1.5, 2])),
+            Some(Number::Float(OrderedFloat(3.5)))
+        );
+
+        assert_eq!(max.parse_value(&json!(12)), Some(Number::Unsigned(12)));
+        assert_eq!(
+            max.parse_value(&json!(" 12.5 ")),
+            Some(Number::Float(OrderedFloat(12.5)))
+        );
+        assert_eq!(max.parse_value(&json!([])), None);
+        assert_eq!(max.parse_value(&json!([1, "cheap"])), None);
+        assert_eq!(max.parse_value(&json!(true)), None);
+    }
+}
@@ -0,0 +1,327 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::ops::Range;
+use std::path::PathBuf;
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::{cmp, mem};
+
+use byteorder::{BigEndian, ByteOrder, WriteBytesExt};
+use memmap::Mmap;
+
+use crate::raw_document::MatchesStream;
+use crate::{DocumentId, Highlight, TmpMatch};
+
+/// The size of a match once written in a spill file.
+const RECORD_SIZE: usize = 24;
+
+static SPILL_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+type Match = (DocumentId, TmpMatch, Highlight);
+
+/// Buffers the matches of a query, the buffer is written to a temporary file each time
+/// it would exceed the memory limit. The runs of matches that do not fit in the limit,
+/// like the ones of a huge posting list, are written as they are produced without being buffered.
+///
+/// All the runs of a query are appended to the same file, it is memory-mapped once and
+/// the runs are merged by document id when read, along with the last buffer.
+pub(crate) struct MatchesSpiller {
+    limit: usize,
+    buffer: Vec<Match>,
+    file: Option<(PathBuf, BufWriter<File>)>,
+    runs: Vec<Range<usize>>,
+    records: usize,
+    peak_buffered: usize,
+}
+
+impl MatchesSpiller {
+    /// Creates a spiller buffering at most `memory_limit` bytes of matches.
+    pub fn new(memory_limit: usize) -> MatchesSpiller {
+        let limit = cmp::max(memory_limit / mem::size_of::<Match>(), 1);
+        MatchesSpiller {
+            limit,
+            buffer: Vec::new(),
+            file: None,
+            runs: Vec::new(),
+            records: 0,
+            peak_buffered: 0,
+        }
+    }
+
+    /// Pushes matches ordered by document id, like the ones of a posting list, they are
+    /// buffered when they fit in the limit and written to the file as a run of their own otherwise.
+    pub fn push_run<I>(&mut self, matches: I) -> io::Result<()>
+    where
+        I: IntoIterator<Item = Match>,
+    {
+        let matches = matches.into_iter();
+        match matches.size_hint().1 {
+            Some(len) if len <= self.limit => {
+                if self.buffer.len() + len > self.limit {
+                    self.spill()?;
+                }
+                self.buffer.extend(matches);
+                self.peak_buffered = cmp::max(self.peak_buffered, self.buffer.len());
+                Ok(())
+            }
+            _ => self.write_run(matches),
+        }
+    }
+
+    fn write_run<I>(&mut self, matches: I) -> io::Result<()>
+    where
+        I: Iterator<Item = Match>,
+    {
+        // the file is only created for the runs that yield matches,
+        // an empty file cannot be memory-mapped
+        let mut matches = matches.peekable();
+        if matches.peek().is_none() {
+            return Ok(());
+        }
+
+        let start = self.records;
+        let writer = self.writer()?;
+
+        let mut written = 0;
+        for match_ in matches {
+            encode_match(writer, match_)?;
+            written += 1;
+        }
+
+        if written != 0 {
+            self.records += written;
+            self.runs.push(start..self.records);
+        }
+
+        Ok(())
+    }
+
+    /// The number of runs of matches written to the file.
+    pub fn spilled_runs(&self) -> usize {
+        self.runs.len()
+    }
+
+    /// The maximum number of matches that were buffered in memory at the same time.
+    pub fn peak_buffered(&self) -> usize {
+        self.peak_buffered
+    }
+
+    /// The streams of matches, each one is ordered by document id.
+    pub fn into_streams(mut self) -> io::Result<Vec<MatchesStream<'static>>> {
+        let mut streams: Vec<MatchesStream> = Vec::with_capacity(self.runs.len() + 1);
+
+        if let Some((path, writer)) = self.file.take() {
+            let file = writer.into_inner().map_err(|e| e.into_error())?;
+
+            // the file is removed as soon as it is mapped, it is freed once unmapped
+            let mmap = unsafe { Mmap::map(&file) };
+            drop(file);
+            fs::remove_file(&path)?;
+            let mmap = Arc::new(mmap?);
+
+            for run in mem::replace(&mut self.runs, Vec::new()) {
+                let mmap = mmap.clone();
+                let iter = run.map(move |i| {
+                    let offset = i * RECORD_SIZE;
+                    decode_match(&mmap[offset..offset + RECORD_SIZE])
+                });
+                streams.push(Box::new(iter));
+            }
+        }
+
+        let mut buffer = mem::replace(&mut self.buffer, Vec::new());
+        buffer.sort_by_key(|(id, _, _)| *id);
+        streams.push(Box::new(buffer.into_iter()));
+
+        Ok(streams)
+    }
+
+    /// The writer of the file of the query, created on the first spill.
+    fn writer(&mut self) -> io::Result<&mut BufWriter<File>> {
+        if self.file.is_none() {
+            let count = SPILL_COUNT.fetch_add(1, Ordering::SeqCst);
+            let name = format!("meilidb-spill-{}-{}", process::id(), count);
+            let path = std::env::temp_dir().join(name);
+
+            let file = OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create_new(true)
+                .open(&path)?;
+
+            self.file = Some((path, BufWriter::new(file)));
+        }
+
+        match self.file.as_mut() {
+            Some((_, writer)) => Ok(writer),
+            None => unreachable!(),
+        }
+    }
+
+    fn spill(&mut self) -> io::Result<()> {
+        let mut buffer = mem::replace(&mut self.buffer, Vec::new());
+        buffer.sort_by_key(|(id, _, _)| *id);
+        self.write_run(buffer.drain(..))?;
+
+        // the allocation is reused for the next matches
+        self.buffer = buffer;
+        Ok(())
+    }
+}
+
+impl Drop for MatchesSpiller {
+    fn drop(&mut self) {
+        // the file of a query that failed before being read
+        if let Some((path, _)) = self.file.take() {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+fn encode_match<W: Write>(writer: &mut W, match_: Match) -> io::Result<()> {
+    let (document_id, match_, highlight) = match_;
+    writer.write_u64::<BigEndian>(document_id.0)?;
+    writer.write_u32::<BigEndian>(match_.query_index)?;
+    writer.write_u8(match_.distance)?;
+    writer.write_u16::<BigEndian>(match_.attribute)?;
+    writer.write_u16::<BigEndian>(match_.word_index)?;
+    writer.write_u8(match_.is_exact as u8)?;
+    writer.write_u16::<BigEndian>(highlight.attribute)?;
+    writer.write_u16::<BigEndian>(highlight.char_index)?;
+    writer.write_u16::<BigEndian>(highlight.char_length)
+}
+
+fn decode_match(bytes: &[u8]) -> Match {
+    let document_id = DocumentId(BigEndian::read_u64(&bytes[0..8]));
+    let match_ = TmpMatch {
+        query_index: BigEndian::read_u32(&bytes[8..12]),
+        distance: bytes[12],
+        attribute: BigEndian::read_u16(&bytes[13..15]),
+        word_index: BigEndian::read_u16(&bytes[15..17]),
+        is_exact: bytes[17] != 0,
+    };
+    let highlight = Highlight {
+        attribute: BigEndian::read_u16(&bytes[18..20]),
+        char_index: BigEndian::read_u16(&bytes[20..22]),
+        char_length: BigEndian::read_u16(&bytes[22..24]),
+    };
+    (document_id, match_, highlight)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::raw_document::DocumentsMatches;
+
+    fn match_(id: u64, query_index: u32, word_index: u16) -> Match {
+        let match_ = TmpMatch {
+            query_index,
+            distance: 1,
+            attribute: 2,
+            word_index,
+            is_exact: true,
+        };
+        let highlight = Highlight {
+            attribute: 2,
+            char_index: word_index * 5,
+            char_length: 4,
+        };
+        (DocumentId(id), match_, highlight)
+    }
+
+    #[test]
+    fn spilled_matches_roundtrip() {
+        let mut bytes = Vec::new();
+        encode_match(&mut bytes, match_(42, 3, 7)).unwrap();
+        assert_eq!(bytes.len(), RECORD_SIZE);
+        assert_eq!(decode_match(&bytes), match_(42, 3, 7));
+    }
+
+    #[test]
+    fn spilled_matches_are_merged_by_document() {
+        let size = mem::size_of::<Match>();
+        let mut spiller = MatchesSpiller::new(3 * size);
+
+        let ids = [9, 4, 7, 1, 4, 8, 2, 9, 3, 5];
+        for (i, id) in ids.iter().enumerate() {
+            spiller.push_run(Some(match_(*id, i as u32, 0))).unwrap();
+        }
+        assert_eq!(spiller.spilled_runs(), 3);
+
+        let documents: Vec<_> = DocumentsMatches::new(spiller.into_streams().unwrap())
+            .map(|(id, matches, _)| (id.0, matches.len()))
+            .collect();
+
+        assert_eq!(
+            documents,
+            vec![
+                (1, 1),
+                (2, 1),
+                (3, 1),
+                (4, 2),
+                (5, 1),
+                (7, 1),
+                (8, 1),
+                (9, 2)
+            ]
+        );
+    }
+
+    #[test]
+    fn spilled_matches_are_bounded() {
+        let size = mem::size_of::<Match>();
+        let mut spiller = MatchesSpiller::new(3 * size);
+
+        // a huge posting list is never buffered
+        spiller
+            .push_run((0..1000).map(|id| match_(id, 0, 0)))
+            .unwrap();
+        assert_eq!(spiller.peak_buffered(), 0);
+
+        // a small one is buffered and spilled when the next one does not fit
+        spiller
+            .push_run((0..2).map(|id| match_(id * 2, 1, 0)))
+            .unwrap();
+        spiller
+            .push_run((0..2).map(|id| match_(id * 3, 2, 0)))
+            .unwrap();
+        for id in (0..500).rev() {
+            spiller.push_run(Some(match_(id, 3, 1))).unwrap();
+        }
+        assert!(spiller.peak_buffered() <= 3);
+
+        let streams = spiller.into_streams().unwrap();
+        let documents: Vec<_> = DocumentsMatches::new(streams)
+            .map(|(id, matches, _)| (id.0, matches.len()))
+            .collect();
+
+        assert_eq!(documents.len(), 1000);
+        assert_eq!(documents[0], (0, 4));
+        assert_eq!(documents[2], (2, 3));
+        assert_eq!(documents[3], (3, 3));
+        assert_eq!(documents[600], (600, 1));
+    }
+
+    #[test]
+    fn empty_spilled_runs() {
+        let size = mem::size_of::<Match>();
+        let mut spiller = MatchesSpiller::new(3 * size);
+
+        // a huge posting list whose matches are all filtered out
+        spiller
+            .push_run((0..1000).map(|id| match_(id, 0, 0)).filter(|_| false))
+            .unwrap();
+        assert_eq!(spiller.spilled_runs(), 0);
+        assert!(spiller.file.is_none());
+
+        spiller.push_run(Some(match_(4, 1, 0))).unwrap();
+
+        let streams = spiller.into_streams().unwrap();
+        let documents: Vec<_> = DocumentsMatches::new(streams)
+            .map(|(id, matches, _)| (id.0, matches.len()))
+            .collect();
+
+        assert_eq!(documents, vec![(4, 1)]);
+    }
+}
@@ -1,13 +1,25 @@
+use std::borrow::Cow;
+use std::io::{Read, Write};
+use std::vec;
+
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
 use heed::types::{ByteSlice, OwnedType};
 use heed::Result as ZResult;
 use meilidb_schema::SchemaAttr;
 
-use super::DocumentAttrKey;
+use super::{DocumentAttrKey, BEU64};
 use crate::DocumentId;
 
+/// The stored fields of the documents, the documents that are not retrieved anymore
+/// can be moved to the cold tier where all their fields are compressed together.
+/// The fields of the cold documents are decompressed when read.
 #[derive(Copy, Clone)]
 pub struct DocumentsFields {
     pub(crate) documents_fields: heed::Database<OwnedType<DocumentAttrKey>, ByteSlice>,
+    pub(crate) cold_documents: heed::Database<OwnedType<BEU64>, ByteSlice>,
+    pub(crate) documents_accesses: heed::Database<OwnedType<BEU64>, OwnedType<BEU64>>,
 }
 
 impl DocumentsFields {
@@ -22,6 +34,8 @@ impl DocumentsFields {
         self.documents_fields.put(writer, &key, value)
     }
 
+    /// Deletes the fields of the document from both tiers along with its last access,
+    /// returns the number of entries deleted, a cold document is a single entry.
     pub fn del_all_document_fields(
         self,
         writer: &mut heed::RwTxn,
@@ -29,11 +43,19 @@ impl DocumentsFields {
     ) -> ZResult<usize> {
         let start = DocumentAttrKey::new(document_id, SchemaAttr::min());
         let end = DocumentAttrKey::new(document_id, SchemaAttr::max());
-        self.documents_fields.delete_range(writer, &(start..=end))
+        let hot = self.documents_fields.delete_range(writer, &(start..=end))?;
+
+        let key = BEU64::new(document_id.0);
+        let cold = self.cold_documents.delete(writer, &key)?;
+        self.documents_accesses.delete(writer, &key)?;
+
+        Ok(hot + cold as usize)
     }
 
     pub fn clear(self, writer: &mut heed::RwTxn) -> ZResult<()> {
-        self.documents_fields.clear(writer)
+        self.documents_fields.clear(writer)?;
+        self.cold_documents.clear(writer)?;
+        self.documents_accesses.clear(writer)
     }
 
     pub fn document_attribute<'txn>(
@@ -41,9 +63,19 @@ impl DocumentsFields {
         reader: &'txn heed::RoTxn,
         document_id: DocumentId,
         attribute: SchemaAttr,
-    ) -> ZResult<Option<&'txn [u8]>> {
+    ) -> ZResult<Option<Cow<'txn, [u8]>>> {
         let key = DocumentAttrKey::new(document_id, attribute);
-        self.documents_fields.get(reader, &key)
+        if let Some(bytes) = self.documents_fields.get(reader, &key)? {
+            return Ok(Some(Cow::Borrowed(bytes)));
+        }
+
+        match self.cold_fields(reader, document_id)? {
+            Some(fields) => {
+                let value = fields.into_iter().find(|(attr, _)| *attr == attribute);
+                Ok(value.map(|(_, bytes)| Cow::Owned(bytes)))
+            }
+            None => Ok(None),
+        }
     }
 
     pub fn document_fields<'txn>(
@@ -51,28 +83,173 @@ impl DocumentsFields {
         reader: &'txn heed::RoTxn,
         document_id: DocumentId,
     ) -> ZResult<DocumentFieldsIter<'txn>> {
+        if let Some(fields) = self.cold_fields(reader, document_id)? {
+            let inner = FieldsIter::Cold(fields.into_iter());
+            return Ok(DocumentFieldsIter { inner });
+        }
+
         let start = DocumentAttrKey::new(document_id, SchemaAttr::min());
         let end = DocumentAttrKey::new(document_id, SchemaAttr::max());
         let iter = self.documents_fields.range(reader, &(start..=end))?;
-        Ok(DocumentFieldsIter { iter })
+        let inner = FieldsIter::Hot(iter);
+        Ok(DocumentFieldsIter { inner })
+    }
+
+    pub fn is_cold(self, reader: &heed::RoTxn, document_id: DocumentId) -> ZResult<bool> {
+        let key = BEU64::new(document_id.0);
+        self.cold_documents
+            .get(reader, &key)
+            .map(|bytes| bytes.is_some())
+    }
+
+    /// Moves the fields of the document to the cold tier,
+    /// returns `false` if the document has no field in the hot tier.
+    pub fn freeze_document(
+        self,
+        writer: &mut heed::RwTxn,
+        document_id: DocumentId,
+    ) -> ZResult<bool> {
+        if self.is_cold(writer, document_id)? {
+            return Ok(false);
+        }
+
+        let mut fields = Vec::new();
+        for result in self.document_fields(writer, document_id)? {
+            let (attr, bytes) = result?;
+            fields.push((attr.0, bytes.into_owned()));
+        }
+
+        if fields.is_empty() {
+            return Ok(false);
+        }
+
+        let compressed = compress_fields(&fields)?;
+        let key = BEU64::new(document_id.0);
+        self.cold_documents.put(writer, &key, &compressed)?;
+
+        let start = DocumentAttrKey::new(document_id, SchemaAttr::min());
+        let end = DocumentAttrKey::new(document_id, SchemaAttr::max());
+        self.documents_fields.delete_range(writer, &(start..=end))?;
+
+        Ok(true)
+    }
+
+    /// Moves the fields of the document back to the hot tier,
+    /// returns `false` if the document is not in the cold tier.
+    pub fn thaw_document(self, writer: &mut heed::RwTxn, document_id: DocumentId) -> ZResult<bool> {
+        let fields = match self.cold_fields(writer, document_id)? {
+            Some(fields) => fields,
+            None => return Ok(false),
+        };
+
+        for (attr, bytes) in fields {
+            self.put_document_field(writer, document_id, attr, &bytes)?;
+        }
+
+        let key = BEU64::new(document_id.0);
+        self.cold_documents.delete(writer, &key)?;
+
+        Ok(true)
+    }
+
+    pub fn has_cold_documents(self, reader: &heed::RoTxn) -> ZResult<bool> {
+        self.cold_documents
+            .first(reader)
+            .map(|entry| entry.is_some())
+    }
+
+    /// The number of documents in the cold tier and the size of their compressed fields.
+    pub fn cold_documents_stats(self, reader: &heed::RoTxn) -> ZResult<(u64, u64)> {
+        let mut count = 0;
+        let mut size = 0;
+        for result in self.cold_documents.iter(reader)? {
+            let (_, bytes) = result?;
+            count += 1;
+            size += bytes.len() as u64;
+        }
+        Ok((count, size))
+    }
+
+    pub fn put_last_access(
+        self,
+        writer: &mut heed::RwTxn,
+        document_id: DocumentId,
+        timestamp: u64,
+    ) -> ZResult<()> {
+        let key = BEU64::new(document_id.0);
+        self.documents_accesses
+            .put(writer, &key, &BEU64::new(timestamp))
+    }
+
+    /// The Unix timestamp in seconds of the last retrieval of the document.
+    pub fn last_access(
+        self,
+        reader: &heed::RoTxn,
+        document_id: DocumentId,
+    ) -> ZResult<Option<u64>> {
+        let key = BEU64::new(document_id.0);
+        let timestamp = self.documents_accesses.get(reader, &key)?;
+        Ok(timestamp.map(|t| t.get()))
+    }
+
+    fn cold_fields(
+        self,
+        reader: &heed::RoTxn,
+        document_id: DocumentId,
+    ) -> ZResult<Option<Vec<(SchemaAttr, Vec<u8>)>>> {
+        let key = BEU64::new(document_id.0);
+        match self.cold_documents.get(reader, &key)? {
+            Some(bytes) => decompress_fields(bytes).map(Some),
+            None => Ok(None),
+        }
     }
 }
 
+fn compress_fields(fields: &[(u16, Vec<u8>)]) -> ZResult<Vec<u8>> {
+    let bytes = bincode::serialize(fields).map_err(|_| heed::Error::Encoding)?;
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(&bytes).map_err(heed::Error::Io)?;
+    encoder.finish().map_err(heed::Error::Io)
+}
+
+fn decompress_fields(bytes: &[u8]) -> ZResult<Vec<(SchemaAttr, Vec<u8>)>> {
+    let mut decoder = DeflateDecoder::new(bytes);
+    let mut buffer = Vec::new();
+    decoder.read_to_end(&mut buffer).map_err(heed::Error::Io)?;
+
+    let fields: Vec<(u16, Vec<u8>)> =
+        bincode::deserialize(&buffer).map_err(|_| heed::Error::Decoding)?;
+    Ok(fields
+        .into_iter()
+        .map(|(attr, bytes)| (SchemaAttr(attr), bytes))
+        .collect())
+}
+
+enum FieldsIter<'txn> {
+    Hot(heed::RoRange<'txn, OwnedType<DocumentAttrKey>, ByteSlice>),
+    Cold(vec::IntoIter<(SchemaAttr, Vec<u8>)>),
+}
+
 pub struct DocumentFieldsIter<'txn> {
-    iter: heed::RoRange<'txn, OwnedType<DocumentAttrKey>, ByteSlice>,
+    inner: FieldsIter<'txn>,
 }
 
 impl<'txn> Iterator for DocumentFieldsIter<'txn> {
-    type Item = ZResult<(SchemaAttr, &'txn [u8])>;
+    type Item = ZResult<(SchemaAttr, Cow<'txn, [u8]>)>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        match self.iter.next() {
-            Some(Ok((key, bytes))) => {
-                let attr = SchemaAttr(key.attr.get());
-                Some(Ok((attr, bytes)))
-            }
-            Some(Err(e)) => Some(Err(e)),
-            None => None,
+        match &mut self.inner {
+            FieldsIter::Hot(iter) => match iter.next() {
+                Some(Ok((key, bytes))) => {
+                    let attr = SchemaAttr(key.attr.get());
+                    Some(Ok((attr, Cow::Borrowed(bytes))))
+                }
+                Some(Err(e)) => Some(Err(e)),
+                None => None,
+            },
+            FieldsIter::Cold(iter) => iter
+                .next()
+                .map(|(attr, bytes)| Ok((attr, Cow::Owned(bytes)))),
         }
     }
 }
@@ -0,0 +1,95 @@
+use super::BEU64;
+use crate::DocumentId;
+use heed::types::{OwnedType, Unit};
+use heed::Result as ZResult;
+use zerocopy::{AsBytes, FromBytes};
+
+#[derive(Debug, Copy, Clone, AsBytes, FromBytes)]
+#[repr(C)]
+pub struct HashDocumentKey {
+    hash: BEU64,
+    docid: BEU64,
+}
+
+impl HashDocumentKey {
+    fn new(hash: u64, docid: DocumentId) -> HashDocumentKey {
+        HashDocumentKey {
+            hash: BEU64::new(hash),
+            docid: BEU64::new(docid.0),
+        }
+    }
+}
+
+/// The content hashes of the documents, indexed both by document
+/// and by hash to find the documents sharing the same content.
+#[derive(Copy, Clone)]
+pub struct DocumentsHashes {
+    pub(crate) documents_hashes: heed::Database<OwnedType<BEU64>, OwnedType<BEU64>>,
+    pub(crate) hashes_documents: heed::Database<OwnedType<HashDocumentKey>, Unit>,
+}
+
+impl DocumentsHashes {
+    pub fn put_document_hash(
+        self,
+        writer: &mut heed::RwTxn,
+        document_id: DocumentId,
+        hash: u64,
+    ) -> ZResult<()> {
+        self.del_document_hash(writer, document_id)?;
+
+        let key = HashDocumentKey::new(hash, document_id);
+        self.hashes_documents.put(writer, &key, &())?;
+
+        let document_id = BEU64::new(document_id.0);
+        self.documents_hashes
+            .put(writer, &document_id, &BEU64::new(hash))
+    }
+
+    pub fn del_document_hash(
+        self,
+        writer: &mut heed::RwTxn,
+        document_id: DocumentId,
+    ) -> ZResult<bool> {
+        let hash = match self.document_hash(writer, document_id)? {
+            Some(hash) => hash,
+            None => return Ok(false),
+        };
+
+        let key = HashDocumentKey::new(hash, document_id);
+        self.hashes_documents.delete(writer, &key)?;
+
+        let document_id = BEU64::new(document_id.0);
+        self.documents_hashes.delete(writer, &document_id)
+    }
+
+    pub fn clear(self, writer: &mut heed::RwTxn) -> ZResult<()> {
+        self.documents_hashes.clear(writer)?;
+        self.hashes_documents.clear(writer)
+    }
+
+    pub fn document_hash(
+        self,
+        reader: &heed::RoTxn,
+        document_id: DocumentId,
+    ) -> ZResult<Option<u64>> {
+        let document_id = BEU64::new(document_id.0);
+        match self.documents_hashes.get(reader, &document_id)? {
+            Some(hash) => Ok(Some(hash.get())),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the ids of the documents having the given content hash.
+    pub fn documents_with_hash(self, reader: &heed::RoTxn, hash: u64) -> ZResult<Vec<DocumentId>> {
+        let start = HashDocumentKey::new(hash, DocumentId(u64::min_value()));
+        let end = HashDocumentKey::new(hash, DocumentId(u64::max_value()));
+
+        let mut documents_ids = Vec::new();
+        for result in self.hashes_documents.range(reader, &(start..=end))? {
+            let (key, ()) = result?;
+            documents_ids.push(DocumentId(key.docid.get()));
+        }
+
+        Ok(documents_ids)
+    }
+}
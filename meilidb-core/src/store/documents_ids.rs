@@ -0,0 +1,69 @@
+use super::BEU64;
+use crate::DocumentId;
+use heed::types::{OwnedType, Str};
+use heed::Result as ZResult;
+
+/// The identifiers of the documents, as they were sent, associated with the internal
+/// ids of the documents in both directions. The documents indexed before this mapping
+/// existed are not in it until they are indexed again.
+#[derive(Copy, Clone)]
+pub struct DocumentsIds {
+    pub(crate) externals_internals: heed::Database<Str, OwnedType<BEU64>>,
+    pub(crate) internals_externals: heed::Database<OwnedType<BEU64>, Str>,
+}
+
+impl DocumentsIds {
+    pub fn put_document_id(
+        self,
+        writer: &mut heed::RwTxn,
+        external_id: &str,
+        document_id: DocumentId,
+    ) -> ZResult<()> {
+        let internal_id = BEU64::new(document_id.0);
+        self.externals_internals
+            .put(writer, external_id, &internal_id)?;
+        self.internals_externals
+            .put(writer, &internal_id, external_id)
+    }
+
+    pub fn del_document_id(
+        self,
+        writer: &mut heed::RwTxn,
+        document_id: DocumentId,
+    ) -> ZResult<bool> {
+        let external_id = match self.external_id(writer, document_id)? {
+            Some(external_id) => external_id.to_owned(),
+            None => return Ok(false),
+        };
+
+        self.externals_internals.delete(writer, &external_id)?;
+
+        let internal_id = BEU64::new(document_id.0);
+        self.internals_externals.delete(writer, &internal_id)
+    }
+
+    pub fn clear(self, writer: &mut heed::RwTxn) -> ZResult<()> {
+        self.externals_internals.clear(writer)?;
+        self.internals_externals.clear(writer)
+    }
+
+    pub fn internal_id(
+        self,
+        reader: &heed::RoTxn,
+        external_id: &str,
+    ) -> ZResult<Option<DocumentId>> {
+        match self.externals_internals.get(reader, external_id)? {
+            Some(internal_id) => Ok(Some(DocumentId(internal_id.get()))),
+            None => Ok(None),
+        }
+    }
+
+    pub fn external_id<'txn>(
+        self,
+        reader: &'txn heed::RoTxn,
+        document_id: DocumentId,
+    ) -> ZResult<Option<&'txn str>> {
+        let internal_id = BEU64::new(document_id.0);
+        self.internals_externals.get(reader, &internal_id)
+    }
+}
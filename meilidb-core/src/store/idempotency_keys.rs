@@ -0,0 +1,91 @@
+use super::BEU64;
+use chrono::Utc;
+use heed::types::{OwnedType, Str};
+use heed::Result as ZResult;
+use zerocopy::{AsBytes, FromBytes};
+
+/// The time in seconds an idempotency key is kept after the update it was enqueued with.
+pub const IDEMPOTENCY_KEYS_TTL: u64 = 24 * 60 * 60;
+
+#[derive(Debug, Copy, Clone, AsBytes, FromBytes)]
+#[repr(C)]
+pub struct KeyTime {
+    inserted_at: BEU64,
+    update_id: BEU64,
+}
+
+impl KeyTime {
+    fn new(inserted_at: u64, update_id: u64) -> KeyTime {
+        KeyTime {
+            inserted_at: BEU64::new(inserted_at),
+            update_id: BEU64::new(update_id),
+        }
+    }
+}
+
+/// The ids of the updates enqueued with an idempotency key, used to
+/// return the original update when a client sends it a second time.
+/// The keys are also indexed by insertion time to remove the expired ones.
+#[derive(Copy, Clone)]
+pub struct IdempotencyKeys {
+    pub(crate) idempotency_keys: heed::Database<Str, OwnedType<BEU64>>,
+    pub(crate) idempotency_keys_times: heed::Database<OwnedType<KeyTime>, Str>,
+}
+
+impl IdempotencyKeys {
+    pub fn put_update_id(self, writer: &mut heed::RwTxn, key: &str, update_id: u64) -> ZResult<()> {
+        let now = Utc::now().timestamp() as u64;
+        self.put_update_id_at(writer, key, update_id, now)
+    }
+
+    /// Associates the key with the update at the given time, in seconds,
+    /// the keys inserted more than the time to live before are removed.
+    pub(crate) fn put_update_id_at(
+        self,
+        writer: &mut heed::RwTxn,
+        key: &str,
+        update_id: u64,
+        now: u64,
+    ) -> ZResult<()> {
+        self.remove_inserted_before(writer, now.saturating_sub(IDEMPOTENCY_KEYS_TTL))?;
+
+        self.idempotency_keys
+            .put(writer, key, &BEU64::new(update_id))?;
+        self.idempotency_keys_times
+            .put(writer, &KeyTime::new(now, update_id), key)
+    }
+
+    fn remove_inserted_before(self, writer: &mut heed::RwTxn, time: u64) -> ZResult<()> {
+        let mut expired = Vec::new();
+        for result in self.idempotency_keys_times.iter(writer)? {
+            let (key_time, key) = result?;
+            if key_time.inserted_at.get() >= time {
+                break;
+            }
+            expired.push((key_time, key.to_owned()));
+        }
+
+        for (key_time, key) in expired {
+            self.idempotency_keys_times.delete(writer, &key_time)?;
+
+            // the key may have been associated with a more recent update since
+            if self.update_id(writer, &key)? == Some(key_time.update_id.get()) {
+                self.idempotency_keys.delete(writer, &key)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn update_id(self, reader: &heed::RoTxn, key: &str) -> ZResult<Option<u64>> {
+        match self.idempotency_keys.get(reader, key)? {
+            Some(update_id) => Ok(Some(update_id.get())),
+            None => Ok(None),
+        }
+    }
+
+    pub fn clear(self, writer: &mut heed::RwTxn) -> ZResult<()> {
+        self.idempotency_keys.clear(writer)?;
+        self.idempotency_keys_times.clear(writer)
+    }
+}
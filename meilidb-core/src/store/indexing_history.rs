@@ -0,0 +1,49 @@
+use super::BEU64;
+use crate::update::IndexingStats;
+use heed::types::{OwnedType, SerdeJson};
+use heed::Result as ZResult;
+
+/// The number of processed updates whose statistics are kept.
+pub const INDEXING_HISTORY_LEN: usize = 100;
+
+/// The statistics of the last documents updates processed, by update id,
+/// the oldest ones are removed once the history is full.
+#[derive(Copy, Clone)]
+pub struct IndexingHistory {
+    pub(crate) indexing_history: heed::Database<OwnedType<BEU64>, SerdeJson<IndexingStats>>,
+}
+
+impl IndexingHistory {
+    pub fn push_stats(
+        self,
+        writer: &mut heed::RwTxn,
+        update_id: u64,
+        stats: &IndexingStats,
+    ) -> ZResult<()> {
+        let key = BEU64::new(update_id);
+        self.indexing_history.put(writer, &key, stats)?;
+
+        let len = self.indexing_history.iter(writer)?.count();
+        for _ in INDEXING_HISTORY_LEN..len {
+            if let Some((key, _)) = self.indexing_history.first(writer)? {
+                self.indexing_history.delete(writer, &key)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The statistics ordered from the oldest update to the most recent one.
+    pub fn stats(self, reader: &heed::RoTxn) -> ZResult<Vec<IndexingStats>> {
+        let mut history = Vec::new();
+        for result in self.indexing_history.iter(reader)? {
+            let (_, stats) = result?;
+            history.push(stats);
+        }
+        Ok(history)
+    }
+
+    pub fn clear(self, writer: &mut heed::RwTxn) -> ZResult<()> {
+        self.indexing_history.clear(writer)
+    }
+}
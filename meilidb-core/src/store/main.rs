@@ -1,4 +1,12 @@
-use crate::RankedMap;
+use crate::cold_tier::ColdTierSettings;
+use crate::criterion::{RankingProfiles, RankingRule};
+use crate::facet_values::FacetsNormalization;
+use crate::geo::GeoIndex;
+use crate::raw_indexer::{AttributesLengths, WordsLimits};
+use crate::serde::{AttributesTypes, DocumentIdCharset};
+use crate::token_filters::TokenFilter;
+use crate::update::UpdateLimits;
+use crate::{MResult, QueryLimits, RankedMap, TyposTable};
 use chrono::{DateTime, Utc};
 use heed::types::{ByteSlice, OwnedType, SerdeBincode, Str};
 use heed::Result as ZResult;
@@ -6,17 +14,42 @@ use meilidb_schema::Schema;
 use std::collections::HashMap;
 use std::sync::Arc;
 
+const ATTRIBUTES_DISCOVERY_KEY: &str = "attributes-discovery";
+const ATTRIBUTES_LENGTHS_KEY: &str = "attributes-lengths";
+const ATTRIBUTES_TYPES_KEY: &str = "attributes-types";
+const COLD_TIER_KEY: &str = "cold-tier";
+const CRITERIA_KEY: &str = "criteria";
 const CREATED_AT_KEY: &str = "created-at";
 const CUSTOMS_KEY: &str = "customs-key";
+const DECOMPOUNDING_KEY: &str = "decompounding";
+const DISPLAYED_ATTRIBUTES_KEY: &str = "displayed-attributes";
+const DOCUMENT_ID_CHARSET_KEY: &str = "document-id-charset";
+const FACETS_NORMALIZATION_KEY: &str = "facets-normalization";
 const FIELDS_FREQUENCY_KEY: &str = "fields-frequency";
+const FLATTEN_DOCUMENTS_KEY: &str = "flatten-documents";
+const FROZEN_WORDS_KEY: &str = "frozen-words";
+const GEO_INDEX_KEY: &str = "geo-index";
+const HTML_ESCAPING_KEY: &str = "html-escaping";
+const MAX_ATTRIBUTES_KEY: &str = "max-attributes";
+const MAX_TOTAL_HITS_KEY: &str = "max-total-hits";
+const METADATA_KEY: &str = "metadata";
 const NAME_KEY: &str = "name";
 const NUMBER_OF_DOCUMENTS_KEY: &str = "number-of-documents";
+const PREFIX_SEARCH_KEY: &str = "prefix-search";
+const QUERY_LIMITS_KEY: &str = "query-limits";
 const RANKED_MAP_KEY: &str = "ranked-map";
+const RANKING_PROFILES_KEY: &str = "ranking-profiles";
 const SCHEMA_KEY: &str = "schema";
+const SEARCHABLE_ATTRIBUTES_KEY: &str = "searchable-attributes";
 const STOP_WORDS_KEY: &str = "stop-words";
+const STRIP_UNITS_KEY: &str = "strip-units";
 const SYNONYMS_KEY: &str = "synonyms";
+const TOKEN_FILTERS_KEY: &str = "token-filters";
+const TYPOS_TABLE_KEY: &str = "typos-table";
 const UPDATED_AT_KEY: &str = "updated-at";
+const UPDATE_LIMITS_KEY: &str = "update-limits";
 const WORDS_KEY: &str = "words";
+const WORDS_LIMITS_KEY: &str = "words-limits";
 
 pub type FreqsMap = HashMap<String, usize>;
 type SerdeFreqsMap = SerdeBincode<FreqsMap>;
@@ -43,6 +76,27 @@ impl Main {
             .map(|name| name.to_owned()))
     }
 
+    /// Stores the free-form metadata of the index, serialized in JSON.
+    pub fn put_metadata(
+        self,
+        writer: &mut heed::RwTxn,
+        metadata: &serde_json::Map<String, serde_json::Value>,
+    ) -> MResult<()> {
+        let json = serde_json::to_string(metadata)?;
+        self.main.put::<Str, Str>(writer, METADATA_KEY, &json)?;
+        Ok(())
+    }
+
+    pub fn metadata(
+        self,
+        reader: &heed::RoTxn,
+    ) -> MResult<Option<serde_json::Map<String, serde_json::Value>>> {
+        match self.main.get::<Str, Str>(reader, METADATA_KEY)? {
+            Some(json) => Ok(Some(serde_json::from_str(json)?)),
+            None => Ok(None),
+        }
+    }
+
     pub fn put_created_at(self, writer: &mut heed::RwTxn) -> ZResult<()> {
         self.main
             .put::<Str, SerdeDatetime>(writer, CREATED_AT_KEY, &Utc::now())
@@ -88,6 +142,332 @@ impl Main {
             .get::<Str, SerdeBincode<Schema>>(reader, SCHEMA_KEY)
     }
 
+    pub fn put_document_id_charset(
+        self,
+        writer: &mut heed::RwTxn,
+        charset: &DocumentIdCharset,
+    ) -> ZResult<()> {
+        self.main.put::<Str, SerdeBincode<DocumentIdCharset>>(
+            writer,
+            DOCUMENT_ID_CHARSET_KEY,
+            charset,
+        )
+    }
+
+    pub fn document_id_charset(self, reader: &heed::RoTxn) -> ZResult<Option<DocumentIdCharset>> {
+        self.main
+            .get::<Str, SerdeBincode<DocumentIdCharset>>(reader, DOCUMENT_ID_CHARSET_KEY)
+    }
+
+    pub fn put_update_limits(self, writer: &mut heed::RwTxn, limits: &UpdateLimits) -> ZResult<()> {
+        self.main
+            .put::<Str, SerdeBincode<UpdateLimits>>(writer, UPDATE_LIMITS_KEY, limits)
+    }
+
+    pub fn update_limits(self, reader: &heed::RoTxn) -> ZResult<Option<UpdateLimits>> {
+        self.main
+            .get::<Str, SerdeBincode<UpdateLimits>>(reader, UPDATE_LIMITS_KEY)
+    }
+
+    pub fn put_criteria(self, writer: &mut heed::RwTxn, rules: &[RankingRule]) -> ZResult<()> {
+        self.main
+            .put::<Str, SerdeBincode<Vec<RankingRule>>>(writer, CRITERIA_KEY, &rules.to_vec())
+    }
+
+    pub fn delete_criteria(self, writer: &mut heed::RwTxn) -> ZResult<bool> {
+        self.main.delete::<Str>(writer, CRITERIA_KEY)
+    }
+
+    /// The rules of the criteria used by the query builders
+    /// that are not given criteria, see [`Index::query_builder`].
+    ///
+    /// [`Index::query_builder`]: ../struct.Index.html#method.query_builder
+    pub fn criteria(self, reader: &heed::RoTxn) -> ZResult<Option<Vec<RankingRule>>> {
+        self.main
+            .get::<Str, SerdeBincode<Vec<RankingRule>>>(reader, CRITERIA_KEY)
+    }
+
+    pub fn put_ranking_profiles(
+        self,
+        writer: &mut heed::RwTxn,
+        profiles: &RankingProfiles,
+    ) -> ZResult<()> {
+        self.main
+            .put::<Str, SerdeBincode<RankingProfiles>>(writer, RANKING_PROFILES_KEY, profiles)
+    }
+
+    pub fn delete_ranking_profiles(self, writer: &mut heed::RwTxn) -> ZResult<bool> {
+        self.main.delete::<Str>(writer, RANKING_PROFILES_KEY)
+    }
+
+    /// The named rules that the searches can select instead of the criteria of the index.
+    pub fn ranking_profiles(self, reader: &heed::RoTxn) -> ZResult<Option<RankingProfiles>> {
+        self.main
+            .get::<Str, SerdeBincode<RankingProfiles>>(reader, RANKING_PROFILES_KEY)
+    }
+
+    pub fn put_query_limits(self, writer: &mut heed::RwTxn, limits: &QueryLimits) -> ZResult<()> {
+        self.main
+            .put::<Str, SerdeBincode<QueryLimits>>(writer, QUERY_LIMITS_KEY, limits)
+    }
+
+    pub fn query_limits(self, reader: &heed::RoTxn) -> ZResult<Option<QueryLimits>> {
+        self.main
+            .get::<Str, SerdeBincode<QueryLimits>>(reader, QUERY_LIMITS_KEY)
+    }
+
+    pub fn put_typos_table(self, writer: &mut heed::RwTxn, table: &TyposTable) -> ZResult<()> {
+        self.main
+            .put::<Str, SerdeBincode<TyposTable>>(writer, TYPOS_TABLE_KEY, table)
+    }
+
+    pub fn typos_table(self, reader: &heed::RoTxn) -> ZResult<Option<TyposTable>> {
+        self.main
+            .get::<Str, SerdeBincode<TyposTable>>(reader, TYPOS_TABLE_KEY)
+    }
+
+    pub fn put_cold_tier(
+        self,
+        writer: &mut heed::RwTxn,
+        settings: &ColdTierSettings,
+    ) -> ZResult<()> {
+        self.main
+            .put::<Str, SerdeBincode<ColdTierSettings>>(writer, COLD_TIER_KEY, settings)
+    }
+
+    pub fn delete_cold_tier(self, writer: &mut heed::RwTxn) -> ZResult<bool> {
+        self.main.delete::<Str>(writer, COLD_TIER_KEY)
+    }
+
+    /// The documents are moved between the tiers only when there are cold tier settings.
+    pub fn cold_tier(self, reader: &heed::RoTxn) -> ZResult<Option<ColdTierSettings>> {
+        self.main
+            .get::<Str, SerdeBincode<ColdTierSettings>>(reader, COLD_TIER_KEY)
+    }
+
+    pub fn put_max_attributes(self, writer: &mut heed::RwTxn, max_attributes: u64) -> ZResult<()> {
+        self.main
+            .put::<Str, OwnedType<u64>>(writer, MAX_ATTRIBUTES_KEY, &max_attributes)
+    }
+
+    pub fn delete_max_attributes(self, writer: &mut heed::RwTxn) -> ZResult<bool> {
+        self.main.delete::<Str>(writer, MAX_ATTRIBUTES_KEY)
+    }
+
+    /// How many attributes the schema can have once extended with
+    /// the attributes discovered or flattened from the documents.
+    pub fn max_attributes(self, reader: &heed::RoTxn) -> ZResult<Option<u64>> {
+        self.main
+            .get::<Str, OwnedType<u64>>(reader, MAX_ATTRIBUTES_KEY)
+    }
+
+    pub fn put_max_total_hits(self, writer: &mut heed::RwTxn, max_total_hits: u64) -> ZResult<()> {
+        self.main
+            .put::<Str, OwnedType<u64>>(writer, MAX_TOTAL_HITS_KEY, &max_total_hits)
+    }
+
+    pub fn delete_max_total_hits(self, writer: &mut heed::RwTxn) -> ZResult<bool> {
+        self.main.delete::<Str>(writer, MAX_TOTAL_HITS_KEY)
+    }
+
+    /// How deep the search results can be paginated, the hits
+    /// following this number of hits are never returned.
+    pub fn max_total_hits(self, reader: &heed::RoTxn) -> ZResult<Option<u64>> {
+        self.main
+            .get::<Str, OwnedType<u64>>(reader, MAX_TOTAL_HITS_KEY)
+    }
+
+    pub fn put_strip_units(self, writer: &mut heed::RwTxn, strip_units: bool) -> ZResult<()> {
+        self.main
+            .put::<Str, SerdeBincode<bool>>(writer, STRIP_UNITS_KEY, &strip_units)
+    }
+
+    pub fn strip_units(self, reader: &heed::RoTxn) -> ZResult<Option<bool>> {
+        self.main
+            .get::<Str, SerdeBincode<bool>>(reader, STRIP_UNITS_KEY)
+    }
+
+    pub fn put_prefix_search(self, writer: &mut heed::RwTxn, prefix: bool) -> ZResult<()> {
+        self.main
+            .put::<Str, SerdeBincode<bool>>(writer, PREFIX_SEARCH_KEY, &prefix)
+    }
+
+    /// Whether the last word of the queries matches the words it is a prefix of.
+    pub fn prefix_search(self, reader: &heed::RoTxn) -> ZResult<Option<bool>> {
+        self.main
+            .get::<Str, SerdeBincode<bool>>(reader, PREFIX_SEARCH_KEY)
+    }
+
+    pub fn put_html_escaping(self, writer: &mut heed::RwTxn, escape_html: bool) -> ZResult<()> {
+        self.main
+            .put::<Str, SerdeBincode<bool>>(writer, HTML_ESCAPING_KEY, &escape_html)
+    }
+
+    /// Whether the formatted fields of the search results are HTML-escaped
+    /// before the highlight tags are inserted, by default.
+    pub fn html_escaping(self, reader: &heed::RoTxn) -> ZResult<Option<bool>> {
+        self.main
+            .get::<Str, SerdeBincode<bool>>(reader, HTML_ESCAPING_KEY)
+    }
+
+    pub fn put_flatten_documents(self, writer: &mut heed::RwTxn, flatten: bool) -> ZResult<()> {
+        self.main
+            .put::<Str, SerdeBincode<bool>>(writer, FLATTEN_DOCUMENTS_KEY, &flatten)
+    }
+
+    /// Whether the nested objects of the added documents are flattened into dotted attributes.
+    pub fn flatten_documents(self, reader: &heed::RoTxn) -> ZResult<Option<bool>> {
+        self.main
+            .get::<Str, SerdeBincode<bool>>(reader, FLATTEN_DOCUMENTS_KEY)
+    }
+
+    pub fn put_attributes_discovery(
+        self,
+        writer: &mut heed::RwTxn,
+        discovery: bool,
+    ) -> ZResult<()> {
+        self.main
+            .put::<Str, SerdeBincode<bool>>(writer, ATTRIBUTES_DISCOVERY_KEY, &discovery)
+    }
+
+    /// Whether the attributes of the added documents that are not in the schema are appended
+    /// to it, the schema is also inferred from the documents when there is none.
+    pub fn attributes_discovery(self, reader: &heed::RoTxn) -> ZResult<Option<bool>> {
+        self.main
+            .get::<Str, SerdeBincode<bool>>(reader, ATTRIBUTES_DISCOVERY_KEY)
+    }
+
+    pub fn put_attributes_types(
+        self,
+        writer: &mut heed::RwTxn,
+        attributes_types: &AttributesTypes,
+    ) -> ZResult<()> {
+        self.main.put::<Str, SerdeBincode<AttributesTypes>>(
+            writer,
+            ATTRIBUTES_TYPES_KEY,
+            attributes_types,
+        )
+    }
+
+    pub fn attributes_types(self, reader: &heed::RoTxn) -> ZResult<Option<AttributesTypes>> {
+        self.main
+            .get::<Str, SerdeBincode<AttributesTypes>>(reader, ATTRIBUTES_TYPES_KEY)
+    }
+
+    pub fn put_facets_normalization(
+        self,
+        writer: &mut heed::RwTxn,
+        normalization: &FacetsNormalization,
+    ) -> ZResult<()> {
+        self.main.put::<Str, SerdeBincode<FacetsNormalization>>(
+            writer,
+            FACETS_NORMALIZATION_KEY,
+            normalization,
+        )
+    }
+
+    /// How the values of the facets are normalized before being counted, by attribute name.
+    pub fn facets_normalization(
+        self,
+        reader: &heed::RoTxn,
+    ) -> ZResult<Option<FacetsNormalization>> {
+        self.main
+            .get::<Str, SerdeBincode<FacetsNormalization>>(reader, FACETS_NORMALIZATION_KEY)
+    }
+
+    pub fn put_words_limits(
+        self,
+        writer: &mut heed::RwTxn,
+        words_limits: &WordsLimits,
+    ) -> ZResult<()> {
+        self.main
+            .put::<Str, SerdeBincode<WordsLimits>>(writer, WORDS_LIMITS_KEY, words_limits)
+    }
+
+    pub fn words_limits(self, reader: &heed::RoTxn) -> ZResult<Option<WordsLimits>> {
+        self.main
+            .get::<Str, SerdeBincode<WordsLimits>>(reader, WORDS_LIMITS_KEY)
+    }
+
+    pub fn put_token_filters(
+        self,
+        writer: &mut heed::RwTxn,
+        token_filters: &[TokenFilter],
+    ) -> ZResult<()> {
+        self.main.put::<Str, SerdeBincode<Vec<TokenFilter>>>(
+            writer,
+            TOKEN_FILTERS_KEY,
+            &token_filters.to_vec(),
+        )
+    }
+
+    /// The token filters of the index, none if they never were defined.
+    pub fn token_filters(self, reader: &heed::RoTxn) -> ZResult<Option<Vec<TokenFilter>>> {
+        self.main
+            .get::<Str, SerdeBincode<Vec<TokenFilter>>>(reader, TOKEN_FILTERS_KEY)
+    }
+
+    pub fn put_attributes_lengths(
+        self,
+        writer: &mut heed::RwTxn,
+        attributes_lengths: &AttributesLengths,
+    ) -> ZResult<()> {
+        self.main.put::<Str, SerdeBincode<AttributesLengths>>(
+            writer,
+            ATTRIBUTES_LENGTHS_KEY,
+            attributes_lengths,
+        )
+    }
+
+    pub fn attributes_lengths(self, reader: &heed::RoTxn) -> ZResult<Option<AttributesLengths>> {
+        self.main
+            .get::<Str, SerdeBincode<AttributesLengths>>(reader, ATTRIBUTES_LENGTHS_KEY)
+    }
+
+    pub fn put_searchable_attributes(
+        self,
+        writer: &mut heed::RwTxn,
+        attributes: &[String],
+    ) -> ZResult<()> {
+        let attributes = attributes.to_vec();
+        self.main.put::<Str, SerdeBincode<Vec<String>>>(
+            writer,
+            SEARCHABLE_ATTRIBUTES_KEY,
+            &attributes,
+        )
+    }
+
+    pub fn searchable_attributes(self, reader: &heed::RoTxn) -> ZResult<Option<Vec<String>>> {
+        self.main
+            .get::<Str, SerdeBincode<Vec<String>>>(reader, SEARCHABLE_ATTRIBUTES_KEY)
+    }
+
+    pub fn delete_searchable_attributes(self, writer: &mut heed::RwTxn) -> ZResult<bool> {
+        self.main.delete::<Str>(writer, SEARCHABLE_ATTRIBUTES_KEY)
+    }
+
+    pub fn put_displayed_attributes(
+        self,
+        writer: &mut heed::RwTxn,
+        attributes: &[String],
+    ) -> ZResult<()> {
+        let attributes = attributes.to_vec();
+        self.main.put::<Str, SerdeBincode<Vec<String>>>(
+            writer,
+            DISPLAYED_ATTRIBUTES_KEY,
+            &attributes,
+        )
+    }
+
+    pub fn displayed_attributes(self, reader: &heed::RoTxn) -> ZResult<Option<Vec<String>>> {
+        self.main
+            .get::<Str, SerdeBincode<Vec<String>>>(reader, DISPLAYED_ATTRIBUTES_KEY)
+    }
+
+    pub fn delete_displayed_attributes(self, writer: &mut heed::RwTxn) -> ZResult<bool> {
+        self.main.delete::<Str>(writer, DISPLAYED_ATTRIBUTES_KEY)
+    }
+
     pub fn put_ranked_map(self, writer: &mut heed::RwTxn, ranked_map: &RankedMap) -> ZResult<()> {
         self.main
             .put::<Str, SerdeBincode<RankedMap>>(writer, RANKED_MAP_KEY, &ranked_map)
@@ -98,6 +478,16 @@ impl Main {
             .get::<Str, SerdeBincode<RankedMap>>(reader, RANKED_MAP_KEY)
     }
 
+    pub fn put_geo_index(self, writer: &mut heed::RwTxn, geo_index: &GeoIndex) -> ZResult<()> {
+        self.main
+            .put::<Str, SerdeBincode<GeoIndex>>(writer, GEO_INDEX_KEY, geo_index)
+    }
+
+    pub fn geo_index(self, reader: &heed::RoTxn) -> ZResult<Option<GeoIndex>> {
+        self.main
+            .get::<Str, SerdeBincode<GeoIndex>>(reader, GEO_INDEX_KEY)
+    }
+
     pub fn put_synonyms_fst(self, writer: &mut heed::RwTxn, fst: &fst::Set) -> ZResult<()> {
         let bytes = fst.as_fst().as_bytes();
         self.main.put::<Str, ByteSlice>(writer, SYNONYMS_KEY, bytes)
@@ -133,6 +523,42 @@ impl Main {
         }
     }
 
+    pub fn put_frozen_words_fst(self, writer: &mut heed::RwTxn, fst: &fst::Set) -> ZResult<()> {
+        let bytes = fst.as_fst().as_bytes();
+        self.main
+            .put::<Str, ByteSlice>(writer, FROZEN_WORDS_KEY, bytes)
+    }
+
+    pub fn frozen_words_fst(self, reader: &heed::RoTxn) -> ZResult<Option<fst::Set>> {
+        match self.main.get::<Str, ByteSlice>(reader, FROZEN_WORDS_KEY)? {
+            Some(bytes) => {
+                let len = bytes.len();
+                let bytes = Arc::new(bytes.to_owned());
+                let fst = fst::raw::Fst::from_shared_bytes(bytes, 0, len).unwrap();
+                Ok(Some(fst::Set::from(fst)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    pub fn put_decompounding_fst(self, writer: &mut heed::RwTxn, fst: &fst::Set) -> ZResult<()> {
+        let bytes = fst.as_fst().as_bytes();
+        self.main
+            .put::<Str, ByteSlice>(writer, DECOMPOUNDING_KEY, bytes)
+    }
+
+    pub fn decompounding_fst(self, reader: &heed::RoTxn) -> ZResult<Option<fst::Set>> {
+        match self.main.get::<Str, ByteSlice>(reader, DECOMPOUNDING_KEY)? {
+            Some(bytes) => {
+                let len = bytes.len();
+                let bytes = Arc::new(bytes.to_owned());
+                let fst = fst::raw::Fst::from_shared_bytes(bytes, 0, len).unwrap();
+                Ok(Some(fst::Set::from(fst)))
+            }
+            None => Ok(None),
+        }
+    }
+
     pub fn put_number_of_documents<F>(self, writer: &mut heed::RwTxn, f: F) -> ZResult<u64>
     where
         F: Fn(u64) -> u64,
@@ -1,10 +1,15 @@
 mod docs_words;
 mod documents_fields;
 mod documents_fields_counts;
+mod documents_hashes;
+mod documents_ids;
+mod idempotency_keys;
+mod indexing_history;
 mod main;
 mod postings_lists;
 mod synonyms;
 mod updates;
+mod updates_replays;
 mod updates_results;
 
 pub use self::docs_words::DocsWords;
@@ -12,25 +17,45 @@ pub use self::documents_fields::{DocumentFieldsIter, DocumentsFields};
 pub use self::documents_fields_counts::{
     DocumentFieldsCountsIter, DocumentsFieldsCounts, DocumentsIdsIter,
 };
+pub use self::documents_hashes::DocumentsHashes;
+pub use self::documents_ids::DocumentsIds;
+pub use self::idempotency_keys::{IdempotencyKeys, IDEMPOTENCY_KEYS_TTL};
+pub use self::indexing_history::{IndexingHistory, INDEXING_HISTORY_LEN};
 pub use self::main::Main;
-pub use self::postings_lists::PostingsLists;
+pub use self::postings_lists::{Posting, PostingsIter, PostingsLists};
 pub use self::synonyms::Synonyms;
 pub use self::updates::Updates;
+pub use self::updates_replays::{UpdatesReplays, UPDATES_REPLAYS_LEN};
 pub use self::updates_results::UpdatesResults;
 
-use std::collections::HashSet;
+use std::collections::{BTreeSet, HashSet};
+use std::io::Write;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use std::time::Duration;
 
+use chrono::Utc;
 use heed::Result as ZResult;
 use meilidb_schema::{Schema, SchemaAttr};
 use serde::de::{self, Deserialize};
 use zerocopy::{AsBytes, FromBytes};
 
+use crate::cold_tier::{self, AccessLog, TierStats, TieringReport};
 use crate::criterion::Criteria;
-use crate::database::{UpdateEvent, UpdateEventsEmitter};
-use crate::serde::Deserializer;
-use crate::{query_builder::QueryBuilder, update, DocumentId, Error, MResult};
+use crate::database::{BoxUpdateFn, UpdateEvent, UpdateEventsEmitter};
+use crate::raw_indexer::WordsLimits;
+use crate::schema_snapshot::{SchemaSnapshot, SchemaSnapshots};
+use crate::serde::{AttributesTypes, Deserializer, DocumentKey};
+use crate::token_filters::TokenFilter;
+use crate::update::{
+    CustomsMerge, CustomsRename, IndexCallback, ProcessedUpdates, UpdateProcessed,
+};
+use crate::vocabulary::{self, PostingsStats, VocabularyFormat};
+use crate::{
+    normalize_str, query_builder::QueryBuilder, update, DocumentId, Error, IndexSettings, MResult,
+};
 
-type BEU64 = zerocopy::U64<byteorder::BigEndian>;
+pub(crate) type BEU64 = zerocopy::U64<byteorder::BigEndian>;
 type BEU16 = zerocopy::U16<byteorder::BigEndian>;
 
 #[derive(Debug, Copy, Clone, AsBytes, FromBytes)]
@@ -61,6 +86,14 @@ fn documents_fields_name(name: &str) -> String {
     format!("store-{}-documents-fields", name)
 }
 
+fn cold_documents_name(name: &str) -> String {
+    format!("store-{}-cold-documents", name)
+}
+
+fn documents_accesses_name(name: &str) -> String {
+    format!("store-{}-documents-accesses", name)
+}
+
 fn documents_fields_counts_name(name: &str) -> String {
     format!("store-{}-documents-fields-counts", name)
 }
@@ -73,6 +106,30 @@ fn docs_words_name(name: &str) -> String {
     format!("store-{}-docs-words", name)
 }
 
+fn documents_hashes_name(name: &str) -> String {
+    format!("store-{}-documents-hashes", name)
+}
+
+fn hashes_documents_name(name: &str) -> String {
+    format!("store-{}-hashes-documents", name)
+}
+
+fn externals_internals_name(name: &str) -> String {
+    format!("store-{}-externals-internals", name)
+}
+
+fn internals_externals_name(name: &str) -> String {
+    format!("store-{}-internals-externals", name)
+}
+
+fn idempotency_keys_name(name: &str) -> String {
+    format!("store-{}-idempotency-keys", name)
+}
+
+fn idempotency_keys_times_name(name: &str) -> String {
+    format!("store-{}-idempotency-keys-times", name)
+}
+
 fn updates_name(name: &str) -> String {
     format!("store-{}-updates", name)
 }
@@ -81,6 +138,46 @@ fn updates_results_name(name: &str) -> String {
     format!("store-{}-updates-results", name)
 }
 
+fn updates_replays_name(name: &str) -> String {
+    format!("store-{}-updates-replays", name)
+}
+
+fn indexing_history_name(name: &str) -> String {
+    format!("store-{}-indexing-history", name)
+}
+
+/// The names of all the stores an index can have.
+pub(crate) fn stores_names(name: &str) -> Vec<String> {
+    vec![
+        main_name(name),
+        postings_lists_name(name),
+        documents_fields_name(name),
+        cold_documents_name(name),
+        documents_accesses_name(name),
+        documents_fields_counts_name(name),
+        synonyms_name(name),
+        docs_words_name(name),
+        documents_hashes_name(name),
+        hashes_documents_name(name),
+        externals_internals_name(name),
+        internals_externals_name(name),
+        idempotency_keys_name(name),
+        idempotency_keys_times_name(name),
+        updates_name(name),
+        updates_results_name(name),
+        updates_replays_name(name),
+        indexing_history_name(name),
+    ]
+}
+
+/// A document returned by a query, retrieved with the reader of the query.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DocumentRetrieval<T> {
+    Found(T),
+    /// The document has been deleted from the version of the index seen by the reader.
+    Gone,
+}
+
 #[derive(Clone)]
 pub struct Index {
     pub main: Main,
@@ -89,23 +186,55 @@ pub struct Index {
     pub documents_fields_counts: DocumentsFieldsCounts,
     pub synonyms: Synonyms,
     pub docs_words: DocsWords,
+    pub documents_hashes: DocumentsHashes,
+    pub documents_ids: DocumentsIds,
 
     pub updates: Updates,
     pub updates_results: UpdatesResults,
+    pub updates_replays: UpdatesReplays,
+    pub idempotency_keys: IdempotencyKeys,
+    pub indexing_history: IndexingHistory,
     pub(crate) updates_notifier: UpdateEventsEmitter,
+    pub(crate) processed_updates: ProcessedUpdates,
+    pub(crate) update_callback: IndexCallback,
+    pub(crate) customs_merge: CustomsMerge,
+    pub(crate) customs_rename: CustomsRename,
+    pub(crate) record_replays: Arc<AtomicBool>,
+    pub(crate) schema_snapshots: SchemaSnapshots,
+    pub(crate) accesses: AccessLog,
+    #[cfg(feature = "fault-injection")]
+    pub(crate) fault_injector: crate::fault_injection::FaultInjector,
 }
 
 impl Index {
+    /// The current schema of the index, it is cheap to clone and is not modified
+    /// by the schema updates processed after it has been retrieved.
+    pub fn schema_snapshot(&self) -> Arc<SchemaSnapshot> {
+        self.schema_snapshots.load()
+    }
+
     pub fn document<T: de::DeserializeOwned>(
         &self,
         reader: &heed::RoTxn,
         attributes: Option<&HashSet<&str>>,
         document_id: DocumentId,
     ) -> MResult<Option<T>> {
-        let schema = self.main.schema(reader)?;
-        let schema = schema.ok_or(Error::SchemaMissing)?;
+        let snapshot = self.schema_snapshot();
+        self.document_with_snapshot(reader, &snapshot, attributes, document_id)
+    }
+
+    /// Reconstructs a document with the given schema snapshot, the one captured
+    /// at the start of a query for all of the documents it returns.
+    pub fn document_with_snapshot<T: de::DeserializeOwned>(
+        &self,
+        reader: &heed::RoTxn,
+        snapshot: &SchemaSnapshot,
+        attributes: Option<&HashSet<&str>>,
+        document_id: DocumentId,
+    ) -> MResult<Option<T>> {
+        let schema = snapshot.schema().ok_or(Error::SchemaMissing)?;
 
-        let attributes = match attributes {
+        let mut attributes: Option<HashSet<SchemaAttr>> = match attributes {
             Some(attributes) => attributes
                 .iter()
                 .map(|name| schema.attribute(name))
@@ -113,15 +242,79 @@ impl Index {
             None => None,
         };
 
+        // the attributes that are not displayed by the settings are never returned
+        if let Some(displayed) = self.main.displayed_attributes(reader)? {
+            let displayed = displayed.iter().filter_map(|name| schema.attribute(name));
+            attributes = Some(match attributes {
+                Some(attributes) => displayed.filter(|a| attributes.contains(a)).collect(),
+                None => displayed.collect(),
+            });
+        }
+
         let mut deserializer = Deserializer {
             document_id,
             reader,
             documents_fields: self.documents_fields,
-            schema: &schema,
+            schema,
             attributes: attributes.as_ref(),
         };
 
-        Ok(Option::<T>::deserialize(&mut deserializer)?)
+        let document = Option::<T>::deserialize(&mut deserializer)?;
+        if document.is_some() {
+            self.accesses
+                .record(document_id, Utc::now().timestamp() as u64);
+        }
+
+        Ok(document)
+    }
+
+    /// Retrieves a document returned by a query, with the reader and the schema snapshot of
+    /// the query. The document is `Gone` when it is not in the reader, which only happens when
+    /// the query has been made with another reader, a document without any of the displayed
+    /// or asked attributes is found but empty.
+    pub fn retrieve_document<T: de::DeserializeOwned>(
+        &self,
+        reader: &heed::RoTxn,
+        snapshot: &SchemaSnapshot,
+        attributes: Option<&HashSet<&str>>,
+        document_id: DocumentId,
+    ) -> MResult<DocumentRetrieval<T>> {
+        let document = self.document_with_snapshot(reader, snapshot, attributes, document_id)?;
+        if let Some(document) = document {
+            return Ok(DocumentRetrieval::Found(document));
+        }
+
+        let mut fields = self.documents_fields.document_fields(reader, document_id)?;
+        if fields.next().is_some() {
+            let empty = serde_json::Value::Object(serde_json::Map::new());
+            Ok(DocumentRetrieval::Found(serde_json::from_value(empty)?))
+        } else {
+            Ok(DocumentRetrieval::Gone)
+        }
+    }
+
+    /// Reconstructs a document with all of its stored fields, the ones that are not
+    /// displayed included, to dump it or to copy it into another index.
+    /// The retrieval is not recorded as an access to the document.
+    pub fn raw_document(
+        &self,
+        reader: &heed::RoTxn,
+        document_id: DocumentId,
+    ) -> MResult<Option<serde_json::Map<String, serde_json::Value>>> {
+        let schema = self.main.schema(reader)?.ok_or(Error::SchemaMissing)?;
+
+        let mut document = serde_json::Map::new();
+        for result in self.documents_fields.document_fields(reader, document_id)? {
+            let (attr, bytes) = result?;
+            let value = serde_json::from_slice(&bytes)?;
+            document.insert(schema.attribute_name(attr).to_owned(), value);
+        }
+
+        if document.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(document))
+        }
     }
 
     pub fn document_attribute<T: de::DeserializeOwned>(
@@ -134,11 +327,92 @@ impl Index {
             .documents_fields
             .document_attribute(reader, document_id, attribute)?;
         match bytes {
-            Some(bytes) => Ok(Some(serde_json::from_slice(bytes)?)),
+            Some(bytes) => Ok(Some(serde_json::from_slice(&bytes)?)),
             None => Ok(None),
         }
     }
 
+    /// Resolves the identifiers of documents, as they were sent, into the ids of the documents
+    /// in the same order, none for the identifiers of the documents that are not indexed.
+    /// All the identifiers are resolved in the given read transaction.
+    pub fn resolve_external_ids<S: AsRef<str>>(
+        &self,
+        reader: &heed::RoTxn,
+        external_ids: &[S],
+    ) -> MResult<Vec<Option<DocumentId>>> {
+        let mut internal_ids = Vec::with_capacity(external_ids.len());
+
+        for external_id in external_ids {
+            let value = serde_json::Value::String(external_id.as_ref().to_owned());
+            let key = DocumentKey::from_value(&value)?;
+
+            let internal_id = match self.documents_ids.internal_id(reader, &key.to_string())? {
+                Some(internal_id) => Some(internal_id),
+                None => {
+                    // the documents indexed before the mapping existed are not in it
+                    let document_id = key.document_id();
+                    let mut counts = self
+                        .documents_fields_counts
+                        .document_fields_counts(reader, document_id)?;
+                    match counts.next() {
+                        Some(result) => result.map(|_| Some(document_id))?,
+                        None => None,
+                    }
+                }
+            };
+
+            internal_ids.push(internal_id);
+        }
+
+        Ok(internal_ids)
+    }
+
+    /// Resolves the ids of documents into the identifiers of the documents in the same
+    /// order, none for the ids of the documents that are not indexed. All the ids are
+    /// resolved in the given read transaction.
+    pub fn resolve_internal_ids(
+        &self,
+        reader: &heed::RoTxn,
+        internal_ids: &[DocumentId],
+    ) -> MResult<Vec<Option<String>>> {
+        let identifier = match self.main.schema(reader)? {
+            Some(schema) => schema.attribute(schema.identifier_name()),
+            None => None,
+        };
+
+        let mut external_ids = Vec::with_capacity(internal_ids.len());
+
+        for document_id in internal_ids {
+            let external_id = match self.documents_ids.external_id(reader, *document_id)? {
+                Some(external_id) => Some(external_id.to_owned()),
+                None => match identifier {
+                    // the documents indexed before the mapping existed are not in it
+                    Some(identifier) => self
+                        .document_attribute::<serde_json::Value>(reader, *document_id, identifier)?
+                        .and_then(|value| DocumentKey::from_value(&value).ok())
+                        .map(|key| key.to_string()),
+                    None => None,
+                },
+            };
+
+            external_ids.push(external_id);
+        }
+
+        Ok(external_ids)
+    }
+
+    /// The documents and attributes in which the word appears along with its positions,
+    /// the word is lowercased like at indexing time. The postings are the ones visible
+    /// in the read transaction, the updates processed after it was opened are not seen.
+    pub fn word_postings<'txn>(
+        &self,
+        reader: &'txn heed::RoTxn,
+        word: &str,
+    ) -> MResult<PostingsIter<'txn>> {
+        let word = word.to_lowercase();
+        Ok(self.postings_lists.postings(reader, word.as_bytes())?)
+    }
+
     pub fn schema_update(&self, writer: &mut heed::RwTxn, schema: Schema) -> MResult<u64> {
         let _ = self.updates_notifier.send(UpdateEvent::NewUpdate);
         update::push_schema_update(writer, self.updates, self.updates_results, schema)
@@ -151,6 +425,7 @@ impl Index {
 
     pub fn documents_addition<D>(&self) -> update::DocumentsAddition<D> {
         update::DocumentsAddition::new(
+            self.main,
             self.updates,
             self.updates_results,
             self.updates_notifier.clone(),
@@ -159,14 +434,33 @@ impl Index {
 
     pub fn documents_partial_addition<D>(&self) -> update::DocumentsAddition<D> {
         update::DocumentsAddition::new_partial(
+            self.main,
             self.updates,
             self.updates_results,
             self.updates_notifier.clone(),
         )
     }
 
+    /// Enqueues the addition of a stream of JSON documents, the fields coerced, dropped
+    /// or truncated according to the schema are reported in the update status.
+    pub fn documents_addition_from_values<I>(
+        &self,
+        writer: &mut heed::RwTxn,
+        documents: I,
+    ) -> MResult<u64>
+    where
+        I: IntoIterator<Item = serde_json::Value>,
+    {
+        let mut addition = self.documents_addition();
+        addition.extend(documents);
+        addition.finalize(writer)
+    }
+
     pub fn documents_deletion(&self) -> update::DocumentsDeletion {
         update::DocumentsDeletion::new(
+            self.main,
+            self.documents_fields,
+            self.documents_fields_counts,
             self.updates,
             self.updates_results,
             self.updates_notifier.clone(),
@@ -210,6 +504,91 @@ impl Index {
         )
     }
 
+    pub fn settings_patch(&self) -> update::SettingsPatch {
+        update::SettingsPatch::new(
+            self.updates,
+            self.updates_results,
+            self.updates_notifier.clone(),
+        )
+    }
+
+    pub fn decompounding_update(
+        &self,
+        writer: &mut heed::RwTxn,
+        dictionary: BTreeSet<String>,
+    ) -> MResult<u64> {
+        let _ = self.updates_notifier.send(UpdateEvent::NewUpdate);
+        update::push_decompounding_update(writer, self.updates, self.updates_results, dictionary)
+    }
+
+    pub fn attributes_types_update(
+        &self,
+        writer: &mut heed::RwTxn,
+        attributes_types: AttributesTypes,
+    ) -> MResult<u64> {
+        let _ = self.updates_notifier.send(UpdateEvent::NewUpdate);
+        update::push_attributes_types_update(
+            writer,
+            self.updates,
+            self.updates_results,
+            attributes_types,
+        )
+    }
+
+    pub fn words_limits_update(
+        &self,
+        writer: &mut heed::RwTxn,
+        words_limits: WordsLimits,
+    ) -> MResult<u64> {
+        let _ = self.updates_notifier.send(UpdateEvent::NewUpdate);
+        update::push_words_limits_update(writer, self.updates, self.updates_results, words_limits)
+    }
+
+    /// Replaces the token filters analyzing the words of the documents and of the queries,
+    /// the documents are reindexed when the filters change.
+    pub fn token_filters_update(
+        &self,
+        writer: &mut heed::RwTxn,
+        token_filters: Vec<TokenFilter>,
+    ) -> MResult<u64> {
+        let _ = self.updates_notifier.send(UpdateEvent::NewUpdate);
+        update::push_token_filters_update(writer, self.updates, self.updates_results, token_filters)
+    }
+
+    /// Replaces the searchable attributes, their order defines the priority of the attributes
+    /// in the ranking, `None` makes all the indexed attributes searchable again.
+    pub fn searchable_attributes_update(
+        &self,
+        writer: &mut heed::RwTxn,
+        attributes: Option<Vec<String>>,
+    ) -> MResult<u64> {
+        let _ = self.updates_notifier.send(UpdateEvent::NewUpdate);
+        update::push_searchable_attributes_update(
+            writer,
+            self.updates,
+            self.updates_results,
+            attributes,
+        )
+    }
+
+    /// Rewrites all the documents for the new schema, the attributes can be renamed, removed,
+    /// reordered, split or merged, unlike with a schema update which can only append attributes.
+    pub fn migrate_to(
+        &self,
+        writer: &mut heed::RwTxn,
+        new_schema: Schema,
+        mapping: update::FieldMapping,
+    ) -> MResult<u64> {
+        let _ = self.updates_notifier.send(UpdateEvent::NewUpdate);
+        update::push_migration(
+            writer,
+            self.updates,
+            self.updates_results,
+            new_schema,
+            mapping,
+        )
+    }
+
     pub fn current_update_id(&self, reader: &heed::RoTxn) -> MResult<Option<u64>> {
         match self.updates.last_update_id(reader)? {
             Some((id, _)) => Ok(Some(id)),
@@ -217,6 +596,50 @@ impl Index {
         }
     }
 
+    /// Blocks until the update has been processed by the update loop or the timeout is
+    /// reached, returns `false` if the timeout has been reached first.
+    pub fn wait_for_update(&self, update_id: u64, timeout: Duration) -> bool {
+        self.processed_updates.wait(update_id, timeout)
+    }
+
+    /// Returns a future that resolves once the update has been processed by the update loop.
+    pub fn await_update(&self, update_id: u64) -> UpdateProcessed {
+        self.processed_updates.processed(update_id)
+    }
+
+    /// Sets the callback called with the result of each update processed by this index,
+    /// after the callback of the database and on the same thread.
+    pub fn set_update_callback(&self, update_fn: BoxUpdateFn) {
+        self.update_callback.set(update_fn);
+    }
+
+    pub fn clear_update_callback(&self) {
+        self.update_callback.clear();
+    }
+
+    /// The faults injected in the update loop of this index.
+    #[cfg(feature = "fault-injection")]
+    pub fn fault_injector(&self) -> &crate::fault_injection::FaultInjector {
+        &self.fault_injector
+    }
+
+    /// Writes the words of the index with their document frequency and average position,
+    /// see [`VocabularyFormat`] for the layouts. Returns the number of words written.
+    pub fn export_vocabulary<W: Write>(
+        &self,
+        reader: &heed::RoTxn,
+        writer: W,
+        format: VocabularyFormat,
+    ) -> MResult<usize> {
+        vocabulary::export_vocabulary(reader, self, writer, format)
+    }
+
+    /// The number, sizes and largest postings lists of the words matching the pattern,
+    /// a `*` matches any sequence of characters and a pattern without one is a prefix.
+    pub fn postings_stats(&self, reader: &heed::RoTxn, pattern: &str) -> MResult<PostingsStats> {
+        vocabulary::postings_stats(reader, self, pattern)
+    }
+
     pub fn update_status(
         &self,
         reader: &heed::RoTxn,
@@ -253,10 +676,69 @@ impl Index {
         Ok(updates)
     }
 
+    /// The statistics of the last documents updates processed, from the oldest to the most
+    /// recent one, to follow how the indexing speed evolves with the data and the settings.
+    pub fn indexing_history(&self, reader: &heed::RoTxn) -> MResult<Vec<update::IndexingStats>> {
+        Ok(self.indexing_history.stats(reader)?)
+    }
+
+    /// Protects the words from the typos, they are only matched by the query words
+    /// equal to them and the query words equal to them only match them. The words
+    /// are normalized like the query words and take effect immediately.
+    pub fn put_frozen_words(
+        &self,
+        writer: &mut heed::RwTxn,
+        words: BTreeSet<String>,
+    ) -> MResult<()> {
+        let words: BTreeSet<_> = words.iter().map(|w| normalize_str(w.trim())).collect();
+        let fst = fst::Set::from_iter(words.iter().filter(|w| !w.is_empty()))?;
+        self.main.put_frozen_words_fst(writer, &fst)?;
+        Ok(())
+    }
+
+    pub fn frozen_words(&self, reader: &heed::RoTxn) -> MResult<Vec<String>> {
+        let fst = self.main.frozen_words_fst(reader)?.unwrap_or_default();
+        Ok(fst.stream().into_strs()?)
+    }
+
+    /// Moves the documents that have not been retrieved for the period of the cold tier
+    /// settings to the cold tier and the ones retrieved since back to the hot tier,
+    /// `now` is a Unix timestamp in seconds. All the documents are moved back to the
+    /// hot tier when the cold tier is disabled.
+    pub fn tier_documents(&self, writer: &mut heed::RwTxn, now: u64) -> MResult<TieringReport> {
+        cold_tier::tier_documents(writer, self, now)
+    }
+
+    pub fn tier_stats(&self, reader: &heed::RoTxn) -> MResult<TierStats> {
+        let number_of_documents = self.main.number_of_documents(reader)?;
+        let (cold_documents, cold_size) = self.documents_fields.cold_documents_stats(reader)?;
+
+        Ok(TierStats {
+            hot_documents: number_of_documents.saturating_sub(cold_documents),
+            cold_documents,
+            cold_size,
+        })
+    }
+
+    /// Applies again a processed update, among the last ones, on a scratch index built
+    /// from the state the update was applied on and returns the result it would have,
+    /// the index is not modified. Returns `None` if the update cannot be replayed anymore
+    /// or was processed while the recording of the replays was disabled.
+    pub fn replay_update(
+        &self,
+        reader: &heed::RoTxn,
+        update_id: u64,
+    ) -> MResult<Option<update::ProcessedUpdateResult>> {
+        update::replay_update(reader, self, update_id)
+    }
+
+    /// A query builder sorting the documents with the criteria of the ranking rules
+    /// of the index settings, or with the default criteria when there are none.
     pub fn query_builder(&self) -> QueryBuilder {
         QueryBuilder::new(
             self.main,
             self.postings_lists,
+            self.documents_fields,
             self.documents_fields_counts,
             self.synonyms,
         )
@@ -269,6 +751,7 @@ impl Index {
         QueryBuilder::with_criteria(
             self.main,
             self.postings_lists,
+            self.documents_fields,
             self.documents_fields_counts,
             self.synonyms,
             criteria,
@@ -285,34 +768,81 @@ pub fn create(
     let main_name = main_name(name);
     let postings_lists_name = postings_lists_name(name);
     let documents_fields_name = documents_fields_name(name);
+    let cold_documents_name = cold_documents_name(name);
+    let documents_accesses_name = documents_accesses_name(name);
     let documents_fields_counts_name = documents_fields_counts_name(name);
     let synonyms_name = synonyms_name(name);
     let docs_words_name = docs_words_name(name);
+    let documents_hashes_name = documents_hashes_name(name);
+    let hashes_documents_name = hashes_documents_name(name);
+    let externals_internals_name = externals_internals_name(name);
+    let internals_externals_name = internals_externals_name(name);
     let updates_name = updates_name(name);
     let updates_results_name = updates_results_name(name);
+    let updates_replays_name = updates_replays_name(name);
+    let idempotency_keys_name = idempotency_keys_name(name);
+    let idempotency_keys_times_name = idempotency_keys_times_name(name);
+    let indexing_history_name = indexing_history_name(name);
 
     // open all the stores
     let main = env.create_poly_database(Some(&main_name))?;
     let postings_lists = env.create_database(Some(&postings_lists_name))?;
     let documents_fields = env.create_database(Some(&documents_fields_name))?;
+    let cold_documents = env.create_database(Some(&cold_documents_name))?;
+    let documents_accesses = env.create_database(Some(&documents_accesses_name))?;
     let documents_fields_counts = env.create_database(Some(&documents_fields_counts_name))?;
     let synonyms = env.create_database(Some(&synonyms_name))?;
     let docs_words = env.create_database(Some(&docs_words_name))?;
+    let documents_hashes = env.create_database(Some(&documents_hashes_name))?;
+    let hashes_documents = env.create_database(Some(&hashes_documents_name))?;
+    let externals_internals = env.create_database(Some(&externals_internals_name))?;
+    let internals_externals = env.create_database(Some(&internals_externals_name))?;
     let updates = env.create_database(Some(&updates_name))?;
     let updates_results = env.create_database(Some(&updates_results_name))?;
+    let updates_replays = env.create_database(Some(&updates_replays_name))?;
+    let idempotency_keys = env.create_database(Some(&idempotency_keys_name))?;
+    let idempotency_keys_times = env.create_database(Some(&idempotency_keys_times_name))?;
+    let indexing_history = env.create_database(Some(&indexing_history_name))?;
 
     Ok(Index {
         main: Main { main },
         postings_lists: PostingsLists { postings_lists },
-        documents_fields: DocumentsFields { documents_fields },
+        documents_fields: DocumentsFields {
+            documents_fields,
+            cold_documents,
+            documents_accesses,
+        },
         documents_fields_counts: DocumentsFieldsCounts {
             documents_fields_counts,
         },
         synonyms: Synonyms { synonyms },
         docs_words: DocsWords { docs_words },
+        documents_hashes: DocumentsHashes {
+            documents_hashes,
+            hashes_documents,
+        },
+        documents_ids: DocumentsIds {
+            externals_internals,
+            internals_externals,
+        },
         updates: Updates { updates },
         updates_results: UpdatesResults { updates_results },
+        updates_replays: UpdatesReplays { updates_replays },
+        idempotency_keys: IdempotencyKeys {
+            idempotency_keys,
+            idempotency_keys_times,
+        },
+        indexing_history: IndexingHistory { indexing_history },
         updates_notifier,
+        processed_updates: ProcessedUpdates::default(),
+        update_callback: IndexCallback::default(),
+        customs_merge: CustomsMerge::default(),
+        customs_rename: CustomsRename::default(),
+        record_replays: Arc::new(AtomicBool::new(false)),
+        schema_snapshots: SchemaSnapshots::default(),
+        accesses: AccessLog::default(),
+        #[cfg(feature = "fault-injection")]
+        fault_injector: Default::default(),
     })
 }
 
@@ -325,11 +855,21 @@ pub fn open(
     let main_name = main_name(name);
     let postings_lists_name = postings_lists_name(name);
     let documents_fields_name = documents_fields_name(name);
+    let cold_documents_name = cold_documents_name(name);
+    let documents_accesses_name = documents_accesses_name(name);
     let documents_fields_counts_name = documents_fields_counts_name(name);
     let synonyms_name = synonyms_name(name);
     let docs_words_name = docs_words_name(name);
+    let documents_hashes_name = documents_hashes_name(name);
+    let hashes_documents_name = hashes_documents_name(name);
+    let externals_internals_name = externals_internals_name(name);
+    let internals_externals_name = internals_externals_name(name);
     let updates_name = updates_name(name);
     let updates_results_name = updates_results_name(name);
+    let updates_replays_name = updates_replays_name(name);
+    let idempotency_keys_name = idempotency_keys_name(name);
+    let idempotency_keys_times_name = idempotency_keys_times_name(name);
+    let indexing_history_name = indexing_history_name(name);
 
     // open all the stores
     let main = match env.open_poly_database(Some(&main_name))? {
@@ -344,6 +884,15 @@ pub fn open(
         Some(documents_fields) => documents_fields,
         None => return Ok(None),
     };
+    // the cold tier stores did not exist in the first versions
+    let cold_documents = match env.open_database(Some(&cold_documents_name))? {
+        Some(cold_documents) => cold_documents,
+        None => env.create_database(Some(&cold_documents_name))?,
+    };
+    let documents_accesses = match env.open_database(Some(&documents_accesses_name))? {
+        Some(documents_accesses) => documents_accesses,
+        None => env.create_database(Some(&documents_accesses_name))?,
+    };
     let documents_fields_counts = match env.open_database(Some(&documents_fields_counts_name))? {
         Some(documents_fields_counts) => documents_fields_counts,
         None => return Ok(None),
@@ -356,6 +905,25 @@ pub fn open(
         Some(docs_words) => docs_words,
         None => return Ok(None),
     };
+    // the content hashes stores did not exist in the first versions,
+    // they are created when opening an index that lacks them
+    let documents_hashes = match env.open_database(Some(&documents_hashes_name))? {
+        Some(documents_hashes) => documents_hashes,
+        None => env.create_database(Some(&documents_hashes_name))?,
+    };
+    let hashes_documents = match env.open_database(Some(&hashes_documents_name))? {
+        Some(hashes_documents) => hashes_documents,
+        None => env.create_database(Some(&hashes_documents_name))?,
+    };
+    // the documents ids stores did not exist in the first versions
+    let externals_internals = match env.open_database(Some(&externals_internals_name))? {
+        Some(externals_internals) => externals_internals,
+        None => env.create_database(Some(&externals_internals_name))?,
+    };
+    let internals_externals = match env.open_database(Some(&internals_externals_name))? {
+        Some(internals_externals) => internals_externals,
+        None => env.create_database(Some(&internals_externals_name))?,
+    };
     let updates = match env.open_database(Some(&updates_name))? {
         Some(updates) => updates,
         None => return Ok(None),
@@ -364,22 +932,84 @@ pub fn open(
         Some(updates_results) => updates_results,
         None => return Ok(None),
     };
+    // the idempotency keys store did not exist in the first versions
+    let idempotency_keys = match env.open_database(Some(&idempotency_keys_name))? {
+        Some(idempotency_keys) => idempotency_keys,
+        None => env.create_database(Some(&idempotency_keys_name))?,
+    };
+    let idempotency_keys_times = match env.open_database(Some(&idempotency_keys_times_name))? {
+        Some(idempotency_keys_times) => idempotency_keys_times,
+        None => env.create_database(Some(&idempotency_keys_times_name))?,
+    };
+    // the updates replays store did not exist in the first versions
+    let updates_replays = match env.open_database(Some(&updates_replays_name))? {
+        Some(updates_replays) => updates_replays,
+        None => env.create_database(Some(&updates_replays_name))?,
+    };
+    // the indexing history store did not exist in the first versions
+    let indexing_history = match env.open_database(Some(&indexing_history_name))? {
+        Some(indexing_history) => indexing_history,
+        None => env.create_database(Some(&indexing_history_name))?,
+    };
+
+    let main = Main { main };
+    let reader = env.read_txn()?;
+    let schema = main.schema(&reader)?;
+    reader.abort();
 
     Ok(Some(Index {
-        main: Main { main },
+        main,
         postings_lists: PostingsLists { postings_lists },
-        documents_fields: DocumentsFields { documents_fields },
+        documents_fields: DocumentsFields {
+            documents_fields,
+            cold_documents,
+            documents_accesses,
+        },
         documents_fields_counts: DocumentsFieldsCounts {
             documents_fields_counts,
         },
         synonyms: Synonyms { synonyms },
         docs_words: DocsWords { docs_words },
+        documents_hashes: DocumentsHashes {
+            documents_hashes,
+            hashes_documents,
+        },
+        documents_ids: DocumentsIds {
+            externals_internals,
+            internals_externals,
+        },
         updates: Updates { updates },
         updates_results: UpdatesResults { updates_results },
+        updates_replays: UpdatesReplays { updates_replays },
+        idempotency_keys: IdempotencyKeys {
+            idempotency_keys,
+            idempotency_keys_times,
+        },
+        indexing_history: IndexingHistory { indexing_history },
         updates_notifier,
+        processed_updates: ProcessedUpdates::default(),
+        update_callback: IndexCallback::default(),
+        customs_merge: CustomsMerge::default(),
+        customs_rename: CustomsRename::default(),
+        record_replays: Arc::new(AtomicBool::new(false)),
+        schema_snapshots: SchemaSnapshots::new(schema),
+        accesses: AccessLog::default(),
+        #[cfg(feature = "fault-injection")]
+        fault_injector: Default::default(),
     }))
 }
 
+/// Copies the settings of an index into another one, the documents and
+/// everything that is computed from them are not copied.
+pub fn copy_settings(writer: &mut heed::RwTxn, src: &Index, dst: &Index) -> MResult<()> {
+    if let Some(schema) = src.main.schema(writer)? {
+        dst.main.put_schema(writer, &schema)?;
+    }
+
+    let settings = IndexSettings::read(writer, src)?;
+    settings.write(writer, dst)
+}
+
 pub fn clear(writer: &mut heed::RwTxn, index: &Index) -> MResult<()> {
     // clear all the stores
     index.main.clear(writer)?;
@@ -388,7 +1018,12 @@ pub fn clear(writer: &mut heed::RwTxn, index: &Index) -> MResult<()> {
     index.documents_fields_counts.clear(writer)?;
     index.synonyms.clear(writer)?;
     index.docs_words.clear(writer)?;
+    index.documents_hashes.clear(writer)?;
+    index.documents_ids.clear(writer)?;
     index.updates.clear(writer)?;
     index.updates_results.clear(writer)?;
+    index.updates_replays.clear(writer)?;
+    index.idempotency_keys.clear(writer)?;
+    index.indexing_history.clear(writer)?;
     Ok(())
 }
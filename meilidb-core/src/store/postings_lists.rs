@@ -1,6 +1,7 @@
-use crate::DocIndex;
+use crate::{DocIndex, DocumentId};
 use heed::types::{ByteSlice, CowSlice};
 use heed::Result as ZResult;
+use meilidb_schema::SchemaAttr;
 use sdset::{Set, SetBuf};
 use std::borrow::Cow;
 
@@ -38,4 +39,56 @@ impl PostingsLists {
             None => Ok(None),
         }
     }
+
+    /// The postings of the word grouped by document and attribute.
+    pub fn postings<'txn>(
+        self,
+        reader: &'txn heed::RoTxn,
+        word: &[u8],
+    ) -> ZResult<PostingsIter<'txn>> {
+        let postings_list = self.postings_list(reader, word)?;
+        Ok(PostingsIter {
+            postings_list,
+            offset: 0,
+        })
+    }
+}
+
+/// The positions of a word in an attribute of a document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Posting {
+    pub document_id: DocumentId,
+    pub attribute: SchemaAttr,
+    /// The indexes of the word in the attribute, in ascending order.
+    pub word_indexes: Vec<u16>,
+}
+
+/// Iterates over the postings of a word in the documents order, the postings
+/// are the ones of the transaction the iterator has been created with.
+pub struct PostingsIter<'txn> {
+    postings_list: Option<Cow<'txn, Set<DocIndex>>>,
+    offset: usize,
+}
+
+impl<'txn> Iterator for PostingsIter<'txn> {
+    type Item = Posting;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let postings_list = self.postings_list.as_ref()?;
+        let doc_indexes = &postings_list[self.offset..];
+        let first = doc_indexes.first()?;
+
+        let group = doc_indexes.iter().take_while(|di| {
+            di.document_id == first.document_id && di.attribute == first.attribute
+        });
+
+        let word_indexes: Vec<_> = group.map(|di| di.word_index).collect();
+        self.offset += word_indexes.len();
+
+        Some(Posting {
+            document_id: first.document_id,
+            attribute: SchemaAttr(first.attribute),
+            word_indexes,
+        })
+    }
 }
@@ -18,6 +18,18 @@ impl Synonyms {
         self.synonyms.put(writer, word, bytes)
     }
 
+    /// Stores the alternatives of a word along with their penalties, a set of
+    /// alternatives is stored the same way, all of them without penalty.
+    pub fn put_weighted_synonyms(
+        self,
+        writer: &mut heed::RwTxn,
+        word: &[u8],
+        synonyms: &fst::Map,
+    ) -> ZResult<()> {
+        let bytes = synonyms.as_fst().as_bytes();
+        self.synonyms.put(writer, word, bytes)
+    }
+
     pub fn del_synonyms(self, writer: &mut heed::RwTxn, word: &[u8]) -> ZResult<bool> {
         self.synonyms.delete(writer, word)
     }
@@ -37,4 +49,16 @@ impl Synonyms {
             None => Ok(None),
         }
     }
+
+    pub fn weighted_synonyms(self, reader: &heed::RoTxn, word: &[u8]) -> ZResult<Option<fst::Map>> {
+        match self.synonyms.get(reader, word)? {
+            Some(bytes) => {
+                let len = bytes.len();
+                let bytes = Arc::new(bytes.to_owned());
+                let fst = fst::raw::Fst::from_shared_bytes(bytes, 0, len).unwrap();
+                Ok(Some(fst::Map::from(fst)))
+            }
+            None => Ok(None),
+        }
+    }
 }
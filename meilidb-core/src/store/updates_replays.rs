@@ -0,0 +1,44 @@
+use super::BEU64;
+use crate::update::UpdateReplay;
+use heed::types::{OwnedType, SerdeJson};
+use heed::Result as ZResult;
+
+/// The number of processed updates that can be replayed.
+pub const UPDATES_REPLAYS_LEN: usize = 10;
+
+/// The payloads of the last updates processed along with the state of the index
+/// they were applied on, by update id, the oldest ones are removed once full.
+#[derive(Copy, Clone)]
+pub struct UpdatesReplays {
+    pub(crate) updates_replays: heed::Database<OwnedType<BEU64>, SerdeJson<UpdateReplay>>,
+}
+
+impl UpdatesReplays {
+    pub fn push_replay(
+        self,
+        writer: &mut heed::RwTxn,
+        update_id: u64,
+        replay: &UpdateReplay,
+    ) -> ZResult<()> {
+        let key = BEU64::new(update_id);
+        self.updates_replays.put(writer, &key, replay)?;
+
+        let len = self.updates_replays.iter(writer)?.count();
+        for _ in UPDATES_REPLAYS_LEN..len {
+            if let Some((key, _)) = self.updates_replays.first(writer)? {
+                self.updates_replays.delete(writer, &key)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn replay(self, reader: &heed::RoTxn, update_id: u64) -> ZResult<Option<UpdateReplay>> {
+        let key = BEU64::new(update_id);
+        self.updates_replays.get(reader, &key)
+    }
+
+    pub fn clear(self, writer: &mut heed::RwTxn) -> ZResult<()> {
+        self.updates_replays.clear(writer)
+    }
+}
@@ -0,0 +1,167 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use meilidb_tokenizer::is_cjk;
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+
+use crate::error::UnsupportedOperation;
+
+/// A token filter written by the embedder, it returns the new form of the
+/// word or none to drop it, the word is then neither indexed nor searched.
+pub type CustomTokenFilter = dyn Fn(&str) -> Option<String> + Send + Sync;
+
+/// The custom token filters registered in the process, by name.
+static REGISTRY: OnceCell<RwLock<HashMap<String, Arc<CustomTokenFilter>>>> = OnceCell::new();
+
+fn registry() -> &'static RwLock<HashMap<String, Arc<CustomTokenFilter>>> {
+    REGISTRY.get_or_init(|| RwLock::new(HashMap::new()))
+}
+
+/// Registers a custom token filter under the given name, it replaces the filter already
+/// registered under this name. The indexes referring to it can only be searched and
+/// indexed once it is registered, it must therefore be registered on every startup.
+pub fn register_token_filter<F>(name: &str, filter: F)
+where
+    F: Fn(&str) -> Option<String> + Send + Sync + 'static,
+{
+    let mut registry = registry().write().unwrap();
+    registry.insert(name.to_owned(), Arc::new(filter));
+}
+
+/// The names of the custom token filters registered in the process.
+pub fn registered_token_filters() -> Vec<String> {
+    let registry = registry().read().unwrap();
+    let mut names: Vec<_> = registry.keys().cloned().collect();
+    names.sort_unstable();
+    names
+}
+
+/// A step of the analysis of the words, the filters of an index are applied in order
+/// to the words of the documents and to the words of the queries.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TokenFilter {
+    Lowercase,
+    /// Replaces the accented and non-latin characters by their ASCII form,
+    /// the words containing CJK characters are kept as they are.
+    AsciiFold,
+    /// Drops the words that have less than `min` or more than `max` characters.
+    Length {
+        #[serde(default)]
+        min: usize,
+        #[serde(default)]
+        max: Option<usize>,
+    },
+    /// A filter registered with `register_token_filter`.
+    Custom(String),
+}
+
+/// The normalization of `normalize_str`, the indexes that never defined their filters
+/// apply it but also index the words of the documents with their accents.
+pub fn default_token_filters() -> Vec<TokenFilter> {
+    vec![TokenFilter::Lowercase, TokenFilter::AsciiFold]
+}
+
+enum ResolvedFilter {
+    Lowercase,
+    AsciiFold,
+    Length { min: usize, max: Option<usize> },
+    Custom(Arc<CustomTokenFilter>),
+}
+
+/// The token filters of an index with the custom ones resolved from the registry.
+pub struct TokenFilterChain {
+    filters: Vec<ResolvedFilter>,
+}
+
+impl TokenFilterChain {
+    /// Returns an error if one of the custom filters is not registered.
+    pub fn resolve(filters: &[TokenFilter]) -> Result<TokenFilterChain, UnsupportedOperation> {
+        let registry = registry().read().unwrap();
+        let mut resolved = Vec::with_capacity(filters.len());
+
+        for filter in filters {
+            let filter = match filter {
+                TokenFilter::Lowercase => ResolvedFilter::Lowercase,
+                TokenFilter::AsciiFold => ResolvedFilter::AsciiFold,
+                TokenFilter::Length { min, max } => ResolvedFilter::Length {
+                    min: *min,
+                    max: *max,
+                },
+                TokenFilter::Custom(name) => match registry.get(name) {
+                    Some(filter) => ResolvedFilter::Custom(filter.clone()),
+                    None => return Err(UnsupportedOperation::UnknownTokenFilter(name.clone())),
+                },
+            };
+            resolved.push(filter);
+        }
+
+        Ok(TokenFilterChain { filters: resolved })
+    }
+
+    /// Applies the filters in order, returns none if one of them drops the word.
+    pub fn apply(&self, word: &str) -> Option<String> {
+        let mut word = word.to_owned();
+
+        for filter in &self.filters {
+            word = match filter {
+                ResolvedFilter::Lowercase => word.to_lowercase(),
+                ResolvedFilter::AsciiFold if word.contains(is_cjk) => word,
+                ResolvedFilter::AsciiFold => deunicode::deunicode_with_tofu(&word, ""),
+                ResolvedFilter::Length { min, max } => {
+                    let len = word.chars().count();
+                    if len < *min || max.map_or(false, |max| len > max) {
+                        return None;
+                    }
+                    word
+                }
+                ResolvedFilter::Custom(filter) => (filter)(&word)?,
+            };
+        }
+
+        if word.is_empty() {
+            None
+        } else {
+            Some(word)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn apply_in_order() {
+        register_token_filter("strip-plural", |word| {
+            Some(word.trim_end_matches('s').to_owned())
+        });
+        register_token_filter("drop-the", |word| match word {
+            "the" => None,
+            word => Some(word.to_owned()),
+        });
+
+        let filters: Vec<TokenFilter> = serde_json::from_str(
+            r#"["lowercase", "asciiFold", { "custom": "drop-the" }, { "custom": "strip-plural" }, { "length": { "min": 3 } }]"#,
+        )
+        .unwrap();
+
+        let chain = TokenFilterChain::resolve(&filters).unwrap();
+        assert_eq!(chain.apply("Crèmes"), Some("creme".to_string()));
+        assert_eq!(chain.apply("THE"), None);
+        assert_eq!(chain.apply("ss"), None);
+        assert_eq!(chain.apply("東京"), Some("東京".to_string()));
+
+        // the order of the filters matters
+        let filters = vec![
+            TokenFilter::Custom("drop-the".into()),
+            TokenFilter::Lowercase,
+        ];
+        let chain = TokenFilterChain::resolve(&filters).unwrap();
+        assert_eq!(chain.apply("THE"), Some("the".to_string()));
+
+        let filters = vec![TokenFilter::Custom("unknown".into())];
+        assert!(TokenFilterChain::resolve(&filters).is_err());
+    }
+}
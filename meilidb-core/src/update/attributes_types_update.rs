@@ -0,0 +1,46 @@
+use crate::serde::AttributesTypes;
+use crate::update::documents_addition::reindex_all_documents;
+use crate::update::{next_update_id, Update};
+use crate::{store, MResult};
+
+pub fn apply_attributes_types_update(
+    writer: &mut heed::RwTxn,
+    main_store: store::Main,
+    documents_fields_store: store::DocumentsFields,
+    documents_fields_counts_store: store::DocumentsFieldsCounts,
+    postings_lists_store: store::PostingsLists,
+    docs_words_store: store::DocsWords,
+    attributes_types: AttributesTypes,
+) -> MResult<()> {
+    let old_attributes_types = main_store.attributes_types(writer)?.unwrap_or_default();
+    main_store.put_attributes_types(writer, &attributes_types)?;
+
+    // the typed values are stored in the ranked map which is rebuilt by the reindexing
+    let need_full_reindexing = old_attributes_types != attributes_types;
+    if need_full_reindexing && main_store.schema(writer)?.is_some() {
+        reindex_all_documents(
+            writer,
+            main_store,
+            documents_fields_store,
+            documents_fields_counts_store,
+            postings_lists_store,
+            docs_words_store,
+        )?
+    }
+
+    Ok(())
+}
+
+pub fn push_attributes_types_update(
+    writer: &mut heed::RwTxn,
+    updates_store: store::Updates,
+    updates_results_store: store::UpdatesResults,
+    attributes_types: AttributesTypes,
+) -> MResult<u64> {
+    let last_update_id = next_update_id(writer, updates_store, updates_results_store)?;
+
+    let update = Update::attributes_types(attributes_types);
+    updates_store.put_update(writer, last_update_id, &update)?;
+
+    Ok(last_update_id)
+}
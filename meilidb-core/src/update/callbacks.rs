@@ -0,0 +1,270 @@
+use std::collections::{HashMap, VecDeque};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+
+use arc_swap::ArcSwapOption;
+
+use crate::database::BoxUpdateFn;
+use crate::update::ProcessedUpdateResult;
+
+/// The number of processed updates of an index waiting for the callbacks to be called,
+/// the following ones are coalesced with the last one waiting until the callbacks catch up.
+pub const UPDATE_CALLBACKS_QUEUE_LEN: usize = 1000;
+
+pub(crate) type ArcSwapFn = ArcSwapOption<BoxUpdateFn>;
+
+/// The callback of a single index, it is called after the one of the database.
+#[derive(Clone, Default)]
+pub(crate) struct IndexCallback {
+    inner: Arc<ArcSwapFn>,
+}
+
+impl IndexCallback {
+    pub(crate) fn set(&self, update_fn: BoxUpdateFn) {
+        self.inner.swap(Some(Arc::new(update_fn)));
+    }
+
+    pub(crate) fn clear(&self) {
+        self.inner.swap(None);
+    }
+}
+
+struct Dispatch {
+    index_uid: String,
+    index_callback: IndexCallback,
+    status: ProcessedUpdateResult,
+}
+
+#[derive(Default)]
+struct Queue {
+    dispatches: VecDeque<Dispatch>,
+    /// The number of dispatches waiting in the queue, by index.
+    pending: HashMap<String, usize>,
+    coalesced: usize,
+    closed: bool,
+}
+
+/// Calls the update callbacks on a dedicated thread, the update loops only enqueue
+/// the processed updates and are never blocked nor killed by a slow or panicking callback.
+///
+/// The thread stops once the dispatcher is closed, when the database is dropped,
+/// after having called the callbacks of the updates already enqueued.
+#[derive(Clone)]
+pub(crate) struct CallbacksDispatcher {
+    queue: Arc<(Mutex<Queue>, Condvar)>,
+    handle: Arc<Mutex<Option<JoinHandle<()>>>>,
+}
+
+impl CallbacksDispatcher {
+    pub(crate) fn spawn(database_callback: Arc<ArcSwapFn>) -> CallbacksDispatcher {
+        let queue = Arc::new((Mutex::new(Queue::default()), Condvar::new()));
+        let thread_queue = queue.clone();
+
+        let handle = thread::spawn(move || {
+            while let Some(dispatch) = next_dispatch(&thread_queue) {
+                let Dispatch {
+                    index_uid,
+                    index_callback,
+                    status,
+                } = dispatch;
+
+                if let Some(ref callback) = *database_callback.load() {
+                    call(callback, &index_uid, status.clone());
+                }
+
+                if let Some(ref callback) = *index_callback.inner.load() {
+                    call(callback, &index_uid, status);
+                }
+            }
+        });
+
+        CallbacksDispatcher {
+            queue,
+            handle: Arc::new(Mutex::new(Some(handle))),
+        }
+    }
+
+    /// Enqueues the processed update, it replaces the last update of the index
+    /// waiting in the queue if the callbacks are too far behind for this index.
+    pub(crate) fn dispatch(
+        &self,
+        index_uid: &str,
+        index_callback: &IndexCallback,
+        status: ProcessedUpdateResult,
+    ) {
+        let (lock, condvar) = &*self.queue;
+        let mut queue = lock.lock().unwrap();
+
+        if queue.closed {
+            log::error!(
+                "the update callbacks are stopped, the update {} of the index {} is not notified",
+                status.update_id,
+                index_uid
+            );
+            return;
+        }
+
+        let pending = queue.pending.get(index_uid).cloned().unwrap_or(0);
+        if pending >= UPDATE_CALLBACKS_QUEUE_LEN {
+            let last = queue
+                .dispatches
+                .iter_mut()
+                .rev()
+                .find(|dispatch| dispatch.index_uid == index_uid);
+
+            if let Some(last) = last {
+                log::warn!(
+                    "the update callbacks are too slow, the update {} of the index {} \
+                     is only notified with the update {}",
+                    last.status.update_id,
+                    index_uid,
+                    status.update_id
+                );
+                last.index_callback = index_callback.clone();
+                last.status = status;
+                queue.coalesced += 1;
+                return;
+            }
+        }
+
+        queue.dispatches.push_back(Dispatch {
+            index_uid: index_uid.to_owned(),
+            index_callback: index_callback.clone(),
+            status,
+        });
+        *queue.pending.entry(index_uid.to_owned()).or_insert(0) += 1;
+        condvar.notify_one();
+    }
+
+    /// The number of processed updates that have been coalesced with a following
+    /// update of the same index, their callbacks have not been called.
+    pub(crate) fn coalesced(&self) -> usize {
+        let (lock, _) = &*self.queue;
+        lock.lock().unwrap().coalesced
+    }
+
+    /// Calls the callbacks of the updates already enqueued and stops the thread,
+    /// the updates dispatched after are not notified.
+    pub(crate) fn close(&self) {
+        let (lock, condvar) = &*self.queue;
+        lock.lock().unwrap().closed = true;
+        condvar.notify_all();
+
+        let handle = self.handle.lock().unwrap().take();
+        if let Some(handle) = handle {
+            // a callback dropping the database must not wait for itself
+            if handle.thread().id() != thread::current().id() && handle.join().is_err() {
+                log::error!("the update callbacks thread panicked");
+            }
+        }
+    }
+}
+
+/// Waits for the next update to notify, none once the queue is closed and empty.
+fn next_dispatch(queue: &(Mutex<Queue>, Condvar)) -> Option<Dispatch> {
+    let (lock, condvar) = queue;
+    let mut queue = lock.lock().unwrap();
+
+    loop {
+        if let Some(dispatch) = queue.dispatches.pop_front() {
+            let remove = match queue.pending.get_mut(&dispatch.index_uid) {
+                Some(pending) => {
+                    *pending -= 1;
+                    *pending == 0
+                }
+                None => false,
+            };
+
+            if remove {
+                queue.pending.remove(&dispatch.index_uid);
+            }
+
+            return Some(dispatch);
+        }
+
+        if queue.closed {
+            return None;
+        }
+
+        queue = condvar.wait(queue).unwrap();
+    }
+}
+
+fn call(callback: &BoxUpdateFn, index_uid: &str, status: ProcessedUpdateResult) {
+    let update_id = status.update_id;
+    let result = panic::catch_unwind(AssertUnwindSafe(|| (callback)(index_uid, status)));
+    if result.is_err() {
+        log::error!(
+            "the update callback panicked on the update {} of the index {}",
+            update_id,
+            index_uid
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    use chrono::Utc;
+
+    use crate::update::UpdateType;
+
+    fn status(update_id: u64) -> ProcessedUpdateResult {
+        ProcessedUpdateResult {
+            update_id,
+            update_type: UpdateType::ClearAll,
+            error: None,
+            duplicates: Vec::new(),
+            collisions: Vec::new(),
+            coercions: None,
+            duration: 0.0,
+            enqueued_at: Utc::now(),
+            processed_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn coalesced_when_too_slow() {
+        let (started, wait_started) = mpsc::channel();
+        let started = Mutex::new(started);
+        let (release, blocked) = mpsc::channel::<()>();
+        let blocked = Mutex::new(blocked);
+        let (sender, receiver) = mpsc::channel();
+        let sender = Mutex::new(sender);
+
+        let update_fn: BoxUpdateFn = Box::new(move |_: &str, status: ProcessedUpdateResult| {
+            if status.update_id == 0 {
+                started.lock().unwrap().send(()).unwrap();
+                let _ = blocked.lock().unwrap().recv();
+            }
+            sender.lock().unwrap().send(status.update_id).unwrap();
+        });
+        let database_callback = Arc::new(ArcSwapFn::empty());
+        database_callback.swap(Some(Arc::new(update_fn)));
+
+        let dispatcher = CallbacksDispatcher::spawn(database_callback);
+        let index_callback = IndexCallback::default();
+
+        dispatcher.dispatch("test", &index_callback, status(0));
+        wait_started.recv().unwrap();
+
+        let last = UPDATE_CALLBACKS_QUEUE_LEN as u64 + 5;
+        for update_id in 1..=last {
+            dispatcher.dispatch("test", &index_callback, status(update_id));
+        }
+        assert_eq!(dispatcher.coalesced(), 5);
+
+        // the enqueued updates are all notified before the dispatcher is closed
+        drop(release);
+        dispatcher.close();
+        dispatcher.dispatch("test", &index_callback, status(last + 1));
+
+        let notified: Vec<_> = receiver.try_iter().collect();
+        let mut expected: Vec<_> = (0..UPDATE_CALLBACKS_QUEUE_LEN as u64).collect();
+        expected.push(last);
+        assert_eq!(notified, expected);
+    }
+}
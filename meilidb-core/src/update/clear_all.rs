@@ -1,5 +1,6 @@
+use crate::raw_indexer::AttributesLengths;
 use crate::update::{next_update_id, Update};
-use crate::{store, MResult, RankedMap};
+use crate::{store, GeoIndex, MResult, RankedMap};
 
 pub fn apply_clear_all(
     writer: &mut heed::RwTxn,
@@ -8,14 +9,20 @@ pub fn apply_clear_all(
     documents_fields_counts_store: store::DocumentsFieldsCounts,
     postings_lists_store: store::PostingsLists,
     docs_words_store: store::DocsWords,
+    documents_hashes_store: store::DocumentsHashes,
+    documents_ids_store: store::DocumentsIds,
 ) -> MResult<()> {
     main_store.put_words_fst(writer, &fst::Set::default())?;
     main_store.put_ranked_map(writer, &RankedMap::default())?;
+    main_store.put_geo_index(writer, &GeoIndex::default())?;
     main_store.put_number_of_documents(writer, |_| 0)?;
+    main_store.put_attributes_lengths(writer, &AttributesLengths::default())?;
     documents_fields_store.clear(writer)?;
     documents_fields_counts_store.clear(writer)?;
     postings_lists_store.clear(writer)?;
     docs_words_store.clear(writer)?;
+    documents_hashes_store.clear(writer)?;
+    documents_ids_store.clear(writer)?;
 
     Ok(())
 }
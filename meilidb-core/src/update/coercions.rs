@@ -0,0 +1,223 @@
+use std::collections::{BTreeMap, HashMap};
+
+use meilidb_schema::Schema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::raw_indexer::RawIndexer;
+use crate::serde::{AttributeType, AttributesTypes};
+use crate::DocumentId;
+
+/// The number of documents listed in a report,
+/// the fields of all the documents are counted.
+const MAX_REPORTED_DOCUMENTS: usize = 100;
+
+/// The fields of an added document that are not indexed as they are written.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DocumentCoercions {
+    pub identifier: String,
+    /// The fields whose values are converted to be sorted and filtered on,
+    /// like the numbers written as strings.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub coerced: Vec<String>,
+    /// The fields that are not attributes of the schema,
+    /// they are neither stored nor searchable.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dropped: Vec<String>,
+    /// The fields with more words than the indexer accepts,
+    /// the words past the limit are not searchable.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub truncated: Vec<String>,
+}
+
+impl DocumentCoercions {
+    pub fn is_empty(&self) -> bool {
+        self.coerced.is_empty() && self.dropped.is_empty() && self.truncated.is_empty()
+    }
+}
+
+/// The fields coerced, dropped or truncated by a documents addition.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CoercionReport {
+    /// The number of documents in which the field has been coerced, by field name.
+    pub coerced: BTreeMap<String, usize>,
+    /// The number of documents in which the field has been dropped, by field name.
+    pub dropped: BTreeMap<String, usize>,
+    /// The number of documents in which the field has been truncated, by field name.
+    pub truncated: BTreeMap<String, usize>,
+    /// The first documents that have some of their fields changed.
+    pub documents: Vec<DocumentCoercions>,
+    /// The fields whose values exceed the limits of the indexer, like the words limits.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+}
+
+impl CoercionReport {
+    pub fn is_empty(&self) -> bool {
+        self.documents.is_empty()
+    }
+
+    fn push(&mut self, document: DocumentCoercions) {
+        if document.is_empty() {
+            return;
+        }
+
+        let counts = vec![
+            (&mut self.coerced, &document.coerced),
+            (&mut self.dropped, &document.dropped),
+            (&mut self.truncated, &document.truncated),
+        ];
+
+        for (counts, fields) in counts {
+            for field in fields {
+                *counts.entry(field.clone()).or_insert(0) += 1;
+            }
+        }
+
+        if self.documents.len() < MAX_REPORTED_DOCUMENTS {
+            self.documents.push(document);
+        }
+    }
+
+    /// Adds the coercions of another report, of documents indexed in the same update.
+    pub(crate) fn merge(&mut self, other: CoercionReport) {
+        let counts = vec![
+            (&mut self.coerced, other.coerced),
+            (&mut self.dropped, other.dropped),
+            (&mut self.truncated, other.truncated),
+        ];
+
+        for (counts, other) in counts {
+            for (field, count) in other {
+                *counts.entry(field).or_insert(0) += count;
+            }
+        }
+
+        let remaining = MAX_REPORTED_DOCUMENTS.saturating_sub(self.documents.len());
+        self.documents
+            .extend(other.documents.into_iter().take(remaining));
+
+        for warning in other.warnings {
+            if !self.warnings.contains(&warning) {
+                self.warnings.push(warning);
+            }
+        }
+    }
+}
+
+/// Whether the value of a field must be converted to be sorted or filtered on.
+fn is_coerced(value: &Value, attribute_type: Option<AttributeType>, is_ranked: bool) -> bool {
+    match (attribute_type, value) {
+        (Some(AttributeType::Boolean), Value::String(_)) => true,
+        (Some(AttributeType::Aggregated(_)), Value::String(_)) => true,
+        (Some(AttributeType::Aggregated(_)), Value::Array(_)) => true,
+        (Some(_), _) => false,
+        (None, Value::Number(_)) => false,
+        (None, _) => is_ranked,
+    }
+}
+
+/// Lists the fields of a document that are not attributes of the schema, apart from
+/// the identifier, and the ones whose values are converted to be sorted and filtered on.
+pub(crate) fn document_coercions(
+    identifier: String,
+    document: &HashMap<String, Value>,
+    schema: &Schema,
+    attributes_types: &AttributesTypes,
+) -> DocumentCoercions {
+    let mut coercions = DocumentCoercions {
+        identifier,
+        ..DocumentCoercions::default()
+    };
+
+    for (name, value) in document {
+        match schema.attribute(name) {
+            Some(attribute) => {
+                let is_ranked = schema.props(attribute).is_ranked();
+                let attribute_type = attributes_types.get(name).cloned();
+                if is_coerced(value, attribute_type, is_ranked) {
+                    coercions.coerced.push(name.clone());
+                }
+            }
+            None => {
+                if name != schema.identifier_name() {
+                    coercions.dropped.push(name.clone());
+                }
+            }
+        }
+    }
+
+    coercions.coerced.sort_unstable();
+    coercions.dropped.sort_unstable();
+    coercions
+}
+
+/// Adds the attributes truncated by the indexer to the documents coercions, aggregates
+/// them and warns about the attributes whose values exceed their words limit.
+pub(crate) fn coercion_report(
+    schema: &Schema,
+    mut coercions: HashMap<DocumentId, DocumentCoercions>,
+    indexer: &RawIndexer,
+) -> CoercionReport {
+    for (document_id, attribute) in indexer.truncated() {
+        if let Some(coercions) = coercions.get_mut(document_id) {
+            let name = schema.attribute_name(*attribute).to_owned();
+            if !coercions.truncated.contains(&name) {
+                coercions.truncated.push(name);
+            }
+        }
+    }
+
+    let mut documents: Vec<_> = coercions.into_iter().map(|(_, c)| c).collect();
+    documents.sort_unstable_by(|a, b| a.identifier.cmp(&b.identifier));
+
+    let mut report = CoercionReport::default();
+    for document in documents {
+        report.push(document);
+    }
+
+    let mut lengths: Vec<_> = indexer
+        .lengths()
+        .iter()
+        .filter(|(_, stats)| stats.truncated != 0)
+        .map(|(attr, stats)| (schema.attribute_name(*attr), *attr, stats))
+        .collect();
+    lengths.sort_unstable_by_key(|(name, _, _)| *name);
+
+    for (name, attribute, stats) in lengths {
+        let warning = format!(
+            "the field {} exceeds the limit of {} words in {} values, the longest has {} words, \
+             the words past the limit are not searchable",
+            name,
+            indexer.attribute_word_limit(attribute),
+            stats.truncated,
+            stats.max_words,
+        );
+        report.warnings.push(warning);
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::serde::Aggregation;
+
+    #[test]
+    fn coerced_values() {
+        let boolean = Some(AttributeType::Boolean);
+        let date = Some(AttributeType::Date);
+        let average = Some(AttributeType::Aggregated(Aggregation::Avg));
+
+        assert!(is_coerced(&Value::from("yes"), boolean, false));
+        assert!(!is_coerced(&Value::from(true), boolean, false));
+        assert!(!is_coerced(&Value::from("2019-11-04"), date, false));
+        assert!(is_coerced(&Value::from(vec![4, 5]), average, true));
+        assert!(!is_coerced(&Value::from(4), average, true));
+        assert!(is_coerced(&Value::from("12"), None, true));
+        assert!(is_coerced(&Value::from(true), None, true));
+        assert!(!is_coerced(&Value::from(12), None, true));
+        assert!(!is_coerced(&Value::from("12"), None, false));
+    }
+}
@@ -0,0 +1,39 @@
+use std::collections::BTreeSet;
+
+use crate::automaton::normalize_str;
+use crate::store;
+use crate::update::{next_update_id, Update};
+use crate::MResult;
+
+pub fn apply_decompounding_update(
+    writer: &mut heed::RwTxn,
+    main_store: store::Main,
+    dictionary: BTreeSet<String>,
+) -> MResult<()> {
+    // words are matched against the normalized query words
+    let dictionary: BTreeSet<_> = dictionary
+        .iter()
+        .map(|word| normalize_str(word.trim()))
+        .filter(|word| !word.is_empty())
+        .collect();
+
+    let dictionary = fst::Set::from_iter(dictionary)?;
+
+    main_store.put_decompounding_fst(writer, &dictionary)?;
+
+    Ok(())
+}
+
+pub fn push_decompounding_update(
+    writer: &mut heed::RwTxn,
+    updates_store: store::Updates,
+    updates_results_store: store::UpdatesResults,
+    dictionary: BTreeSet<String>,
+) -> MResult<u64> {
+    let last_update_id = next_update_id(writer, updates_store, updates_results_store)?;
+
+    let update = Update::decompounding(dictionary);
+    updates_store.put_update(writer, last_update_id, &update)?;
+
+    Ok(last_update_id)
+}
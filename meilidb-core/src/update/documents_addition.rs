@@ -1,50 +1,158 @@
-use std::collections::HashMap;
+use std::cmp;
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
 
 use fst::{set::OpBuilder, SetBuilder};
+use meilidb_schema::{Schema, MAX_ATTRIBUTES};
 use sdset::{duo::Union, SetOperation};
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 
 use crate::database::{UpdateEvent, UpdateEventsEmitter};
-use crate::raw_indexer::RawIndexer;
-use crate::serde::{extract_document_id, serialize_value, Deserializer, Serializer};
+use crate::raw_indexer::{AttributesLengths, RawIndexer};
+use crate::serde::{
+    compute_content_hash, discover_attributes, extend_schema, extract_document_key,
+    flatten_document, infer_schema, serialize_value, Deserializer, DocumentKey, Serializer,
+};
 use crate::store;
-use crate::update::{apply_documents_deletion, next_update_id, Update};
-use crate::{Error, MResult, RankedMap};
+use crate::token_filters::TokenFilterChain;
+use crate::update::coercions::{coercion_report, document_coercions};
+use crate::update::{apply_documents_deletion, next_update_id, CoercionReport, Update};
+use crate::{DocumentId, Error, GeoPoint, LimitExceeded, MResult, RankedMap, GEO_FIELD};
+
+/// What to do with the added documents whose content is
+/// already indexed under another document identifier.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DuplicatesPolicy {
+    /// Index the documents without looking for duplicates.
+    Ignore,
+    /// Index the documents and list the duplicates in the update result.
+    Report,
+    /// List the duplicates in the update result but do not index them.
+    Skip,
+}
+
+impl Default for DuplicatesPolicy {
+    fn default() -> DuplicatesPolicy {
+        DuplicatesPolicy::Ignore
+    }
+}
+
+/// An added document whose content is the same as another document.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DuplicateDocument {
+    /// The identifier of the added document.
+    pub identifier: String,
+    /// The identifier of the document that has the same content.
+    pub duplicate_of: String,
+}
+
+/// How the identifiers of the added documents are verified, the documents sharing an
+/// identifier are indexed as usual, the last one replacing the others, but are reported.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum IdentifiersVerification {
+    /// The identifiers are not verified.
+    Ignore,
+    /// The identifiers shared by several documents of the addition are reported.
+    Addition,
+    /// The identifiers shared by several documents of the addition, or by
+    /// an added document and an already indexed one, are reported.
+    Indexed,
+}
+
+impl Default for IdentifiersVerification {
+    fn default() -> IdentifiersVerification {
+        IdentifiersVerification::Ignore
+    }
+}
+
+/// How the identifier of the added documents without one is generated,
+/// the identifiers are generated when the update is processed.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum IdentifiersGeneration {
+    /// The documents without an identifier are refused.
+    Disabled,
+    /// The identifier is a hash of the content of the document,
+    /// the documents with the same content therefore replace each other.
+    Content,
+    /// The identifier is a random UUID.
+    Uuid,
+}
+
+impl Default for IdentifiersGeneration {
+    fn default() -> IdentifiersGeneration {
+        IdentifiersGeneration::Disabled
+    }
+}
+
+/// An identifier shared by several added documents or by an added document and an indexed one.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IdentifierCollision {
+    pub identifier: String,
+    /// The number of added documents with this identifier.
+    pub occurrences: usize,
+    /// Whether a document with this identifier was already indexed.
+    pub indexed: bool,
+}
+
+/// The duplicates, the identifiers collisions, the coercions, the time spent
+/// to rebuild the words FST and the ids of the documents of a documents addition.
+pub(crate) type AdditionResult = (
+    Vec<DuplicateDocument>,
+    Vec<IdentifierCollision>,
+    CoercionReport,
+    Duration,
+    Vec<DocumentId>,
+);
 
 pub struct DocumentsAddition<D> {
+    main_store: store::Main,
     updates_store: store::Updates,
     updates_results_store: store::UpdatesResults,
     updates_notifier: UpdateEventsEmitter,
     documents: Vec<D>,
     is_partial: bool,
+    duplicates_policy: DuplicatesPolicy,
+    identifiers_verification: IdentifiersVerification,
+    identifiers_generation: IdentifiersGeneration,
 }
 
 impl<D> DocumentsAddition<D> {
     pub fn new(
+        main_store: store::Main,
         updates_store: store::Updates,
         updates_results_store: store::UpdatesResults,
         updates_notifier: UpdateEventsEmitter,
     ) -> DocumentsAddition<D> {
         DocumentsAddition {
+            main_store,
             updates_store,
             updates_results_store,
             updates_notifier,
             documents: Vec::new(),
             is_partial: false,
+            duplicates_policy: DuplicatesPolicy::default(),
+            identifiers_verification: IdentifiersVerification::default(),
+            identifiers_generation: IdentifiersGeneration::default(),
         }
     }
 
     pub fn new_partial(
+        main_store: store::Main,
         updates_store: store::Updates,
         updates_results_store: store::UpdatesResults,
         updates_notifier: UpdateEventsEmitter,
     ) -> DocumentsAddition<D> {
         DocumentsAddition {
+            main_store,
             updates_store,
             updates_results_store,
             updates_notifier,
             documents: Vec::new(),
             is_partial: true,
+            duplicates_policy: DuplicatesPolicy::default(),
+            identifiers_verification: IdentifiersVerification::default(),
+            identifiers_generation: IdentifiersGeneration::default(),
         }
     }
 
@@ -52,6 +160,18 @@ impl<D> DocumentsAddition<D> {
         self.documents.push(document);
     }
 
+    pub fn duplicates_policy(&mut self, policy: DuplicatesPolicy) {
+        self.duplicates_policy = policy;
+    }
+
+    pub fn identifiers_verification(&mut self, verification: IdentifiersVerification) {
+        self.identifiers_verification = verification;
+    }
+
+    pub fn identifiers_generation(&mut self, generation: IdentifiersGeneration) {
+        self.identifiers_generation = generation;
+    }
+
     pub fn finalize(self, writer: &mut heed::RwTxn) -> MResult<u64>
     where
         D: serde::Serialize,
@@ -59,10 +179,14 @@ impl<D> DocumentsAddition<D> {
         let _ = self.updates_notifier.send(UpdateEvent::NewUpdate);
         let update_id = push_documents_addition(
             writer,
+            self.main_store,
             self.updates_store,
             self.updates_results_store,
             self.documents,
             self.is_partial,
+            self.duplicates_policy,
+            self.identifiers_verification,
+            self.identifiers_generation,
         )?;
         Ok(update_id)
     }
@@ -76,10 +200,14 @@ impl<D> Extend<D> for DocumentsAddition<D> {
 
 pub fn push_documents_addition<D: serde::Serialize>(
     writer: &mut heed::RwTxn,
+    main_store: store::Main,
     updates_store: store::Updates,
     updates_results_store: store::UpdatesResults,
     addition: Vec<D>,
     is_partial: bool,
+    duplicates_policy: DuplicatesPolicy,
+    identifiers_verification: IdentifiersVerification,
+    identifiers_generation: IdentifiersGeneration,
 ) -> MResult<u64> {
     let mut values = Vec::with_capacity(addition.len());
     for add in addition {
@@ -88,12 +216,26 @@ pub fn push_documents_addition<D: serde::Serialize>(
         values.push(add);
     }
 
+    if let Some(limits) = main_store.update_limits(writer)? {
+        limits.check_documents(&values)?;
+    }
+
     let last_update_id = next_update_id(writer, updates_store, updates_results_store)?;
 
     let update = if is_partial {
-        Update::documents_partial(values)
+        Update::documents_partial(
+            values,
+            duplicates_policy,
+            identifiers_verification,
+            identifiers_generation,
+        )
     } else {
-        Update::documents_addition(values)
+        Update::documents_addition(
+            values,
+            duplicates_policy,
+            identifiers_verification,
+            identifiers_generation,
+        )
     };
 
     updates_store.put_update(writer, last_update_id, &update)?;
@@ -108,37 +250,82 @@ pub fn apply_documents_addition<'a, 'b>(
     documents_fields_counts_store: store::DocumentsFieldsCounts,
     postings_lists_store: store::PostingsLists,
     docs_words_store: store::DocsWords,
+    documents_hashes_store: store::DocumentsHashes,
+    documents_ids_store: store::DocumentsIds,
     addition: Vec<HashMap<String, serde_json::Value>>,
-) -> MResult<()> {
+    duplicates_policy: DuplicatesPolicy,
+    identifiers_verification: IdentifiersVerification,
+    identifiers_generation: IdentifiersGeneration,
+) -> MResult<AdditionResult> {
     let mut documents_additions = HashMap::new();
+    let mut documents_keys = Vec::new();
 
-    let schema = match main_store.schema(writer)? {
-        Some(schema) => schema,
-        None => return Err(Error::SchemaMissing),
-    };
+    let (addition, schema) =
+        prepare_addition(writer, main_store, addition, identifiers_generation)?;
 
     let identifier = schema.identifier_name();
+    let charset = main_store.document_id_charset(writer)?.unwrap_or_default();
 
     // 1. store documents ids for future deletion
-    for document in addition {
-        let document_id = match extract_document_id(identifier, &document)? {
-            Some(id) => id,
+    for mut document in addition {
+        generate_identifier(identifier, &mut document, identifiers_generation);
+
+        let key = match extract_document_key(identifier, &document)? {
+            Some(key) => {
+                key.validate(&charset)?;
+                key
+            }
             None => return Err(Error::MissingDocumentId),
         };
 
+        let document_id = key.document_id();
+        documents_keys.push((document_id, key));
         documents_additions.insert(document_id, document);
     }
 
-    // 2. remove the documents posting lists
+    let collisions = verify_identifiers(
+        writer,
+        documents_fields_store,
+        &documents_keys,
+        identifiers_verification,
+    )?;
+
+    // 2. look for the documents whose content is already indexed
+    let (documents_hashes, duplicates) = resolve_duplicates(
+        writer,
+        &schema,
+        documents_fields_store,
+        documents_hashes_store,
+        &documents_keys,
+        &mut documents_additions,
+        duplicates_policy,
+    )?;
+
+    let mut identifiers: HashMap<_, _> = documents_keys
+        .iter()
+        .map(|(id, key)| (*id, key.to_string()))
+        .collect();
+
+    // the documents sent several times are only stored once
+    let mut seen = HashSet::new();
+    let added_ids: Vec<_> = documents_keys
+        .iter()
+        .map(|(id, _)| *id)
+        .filter(|id| seen.insert(*id))
+        .collect();
+
+    // 3. remove the documents posting lists
     let number_of_inserted_documents = documents_additions.len();
     let documents_ids = documents_additions.iter().map(|(id, _)| *id).collect();
-    apply_documents_deletion(
+    let deletion_words_fst_duration = apply_documents_deletion(
         writer,
         main_store,
         documents_fields_store,
         documents_fields_counts_store,
         postings_lists_store,
         docs_words_store,
+        documents_hashes_store,
+        documents_ids_store,
         documents_ids,
     )?;
 
@@ -147,18 +334,27 @@ pub fn apply_documents_addition<'a, 'b>(
         None => RankedMap::default(),
     };
 
-    let stop_words = match main_store.stop_words_fst(writer)? {
-        Some(stop_words) => stop_words,
-        None => fst::Set::default(),
-    };
+    let attributes_types = main_store.attributes_types(writer)?.unwrap_or_default();
 
-    // 3. index the documents fields in the stores
-    let mut indexer = RawIndexer::new(stop_words);
+    // 4. index the documents fields and locations in the stores
+    let mut indexer = raw_indexer(writer, main_store, &schema)?;
+    let mut coercions = HashMap::new();
+    let mut geo_index = main_store.geo_index(writer)?.unwrap_or_default();
 
     for (document_id, document) in documents_additions {
+        if let Some(point) = document.get(GEO_FIELD).and_then(GeoPoint::from_value) {
+            geo_index.insert(document_id, point);
+        }
+
+        let identifier = identifiers.remove(&document_id).unwrap_or_default();
+        documents_ids_store.put_document_id(writer, &identifier, document_id)?;
+        let found = document_coercions(identifier, &document, &schema, &attributes_types);
+        coercions.insert(document_id, found);
+
         let serializer = Serializer {
             txn: writer,
             schema: &schema,
+            attributes_types: &attributes_types,
             document_store: documents_fields_store,
             document_fields_counts: documents_fields_counts_store,
             indexer: &mut indexer,
@@ -169,7 +365,16 @@ pub fn apply_documents_addition<'a, 'b>(
         document.serialize(serializer)?;
     }
 
-    write_documents_addition_index(
+    let report = coercion_report(&schema, coercions, &indexer);
+    merge_attributes_lengths(writer, main_store, &schema, &indexer)?;
+    main_store.put_geo_index(writer, &geo_index)?;
+
+    // 5. store the content hashes of the new documents
+    for (document_id, hash) in documents_hashes {
+        documents_hashes_store.put_document_hash(writer, document_id, hash)?;
+    }
+
+    let words_fst_duration = write_documents_addition_index(
         writer,
         main_store,
         postings_lists_store,
@@ -177,7 +382,16 @@ pub fn apply_documents_addition<'a, 'b>(
         &ranked_map,
         number_of_inserted_documents,
         indexer,
-    )
+    )?;
+
+    let words_fst_duration = deletion_words_fst_duration + words_fst_duration;
+    Ok((
+        duplicates,
+        collisions,
+        report,
+        words_fst_duration,
+        added_ids,
+    ))
 }
 
 pub fn apply_documents_partial_addition<'a, 'b>(
@@ -187,24 +401,39 @@ pub fn apply_documents_partial_addition<'a, 'b>(
     documents_fields_counts_store: store::DocumentsFieldsCounts,
     postings_lists_store: store::PostingsLists,
     docs_words_store: store::DocsWords,
+    documents_hashes_store: store::DocumentsHashes,
+    documents_ids_store: store::DocumentsIds,
     addition: Vec<HashMap<String, serde_json::Value>>,
-) -> MResult<()> {
+    duplicates_policy: DuplicatesPolicy,
+    identifiers_verification: IdentifiersVerification,
+    identifiers_generation: IdentifiersGeneration,
+) -> MResult<AdditionResult> {
     let mut documents_additions = HashMap::new();
+    let mut documents_keys = Vec::new();
 
-    let schema = match main_store.schema(writer)? {
-        Some(schema) => schema,
-        None => return Err(Error::SchemaMissing),
-    };
+    let (addition, schema) =
+        prepare_addition(writer, main_store, addition, identifiers_generation)?;
 
     let identifier = schema.identifier_name();
+    let charset = main_store.document_id_charset(writer)?.unwrap_or_default();
+
+    // the locations are kept even when the geo field is not in the schema
+    let old_geo_index = main_store.geo_index(writer)?.unwrap_or_default();
 
     // 1. store documents ids for future deletion
     for mut document in addition {
-        let document_id = match extract_document_id(identifier, &document)? {
-            Some(id) => id,
+        generate_identifier(identifier, &mut document, identifiers_generation);
+
+        let key = match extract_document_key(identifier, &document)? {
+            Some(key) => {
+                key.validate(&charset)?;
+                key
+            }
             None => return Err(Error::MissingDocumentId),
         };
 
+        let document_id = key.document_id();
+
         let mut deserializer = Deserializer {
             document_id,
             reader: writer,
@@ -222,19 +451,59 @@ pub fn apply_documents_partial_addition<'a, 'b>(
             }
         }
 
+        if !document.contains_key(GEO_FIELD) {
+            if let Some(point) = old_geo_index.get(document_id) {
+                document.insert(GEO_FIELD.to_owned(), point.to_value());
+            }
+        }
+
+        documents_keys.push((document_id, key));
         documents_additions.insert(document_id, document);
     }
 
-    // 2. remove the documents posting lists
+    let collisions = verify_identifiers(
+        writer,
+        documents_fields_store,
+        &documents_keys,
+        identifiers_verification,
+    )?;
+
+    // 2. look for the documents whose content is already indexed
+    let (documents_hashes, duplicates) = resolve_duplicates(
+        writer,
+        &schema,
+        documents_fields_store,
+        documents_hashes_store,
+        &documents_keys,
+        &mut documents_additions,
+        duplicates_policy,
+    )?;
+
+    let mut identifiers: HashMap<_, _> = documents_keys
+        .iter()
+        .map(|(id, key)| (*id, key.to_string()))
+        .collect();
+
+    // the documents sent several times are only stored once
+    let mut seen = HashSet::new();
+    let added_ids: Vec<_> = documents_keys
+        .iter()
+        .map(|(id, _)| *id)
+        .filter(|id| seen.insert(*id))
+        .collect();
+
+    // 3. remove the documents posting lists
     let number_of_inserted_documents = documents_additions.len();
     let documents_ids = documents_additions.iter().map(|(id, _)| *id).collect();
-    apply_documents_deletion(
+    let deletion_words_fst_duration = apply_documents_deletion(
         writer,
         main_store,
         documents_fields_store,
         documents_fields_counts_store,
         postings_lists_store,
         docs_words_store,
+        documents_hashes_store,
+        documents_ids_store,
         documents_ids,
     )?;
 
@@ -243,18 +512,27 @@ pub fn apply_documents_partial_addition<'a, 'b>(
         None => RankedMap::default(),
     };
 
-    let stop_words = match main_store.stop_words_fst(writer)? {
-        Some(stop_words) => stop_words,
-        None => fst::Set::default(),
-    };
+    let attributes_types = main_store.attributes_types(writer)?.unwrap_or_default();
 
-    // 3. index the documents fields in the stores
-    let mut indexer = RawIndexer::new(stop_words);
+    // 4. index the documents fields and locations in the stores
+    let mut indexer = raw_indexer(writer, main_store, &schema)?;
+    let mut coercions = HashMap::new();
+    let mut geo_index = main_store.geo_index(writer)?.unwrap_or_default();
 
     for (document_id, document) in documents_additions {
+        if let Some(point) = document.get(GEO_FIELD).and_then(GeoPoint::from_value) {
+            geo_index.insert(document_id, point);
+        }
+
+        let identifier = identifiers.remove(&document_id).unwrap_or_default();
+        documents_ids_store.put_document_id(writer, &identifier, document_id)?;
+        let found = document_coercions(identifier, &document, &schema, &attributes_types);
+        coercions.insert(document_id, found);
+
         let serializer = Serializer {
             txn: writer,
             schema: &schema,
+            attributes_types: &attributes_types,
             document_store: documents_fields_store,
             document_fields_counts: documents_fields_counts_store,
             indexer: &mut indexer,
@@ -265,7 +543,16 @@ pub fn apply_documents_partial_addition<'a, 'b>(
         document.serialize(serializer)?;
     }
 
-    write_documents_addition_index(
+    let report = coercion_report(&schema, coercions, &indexer);
+    merge_attributes_lengths(writer, main_store, &schema, &indexer)?;
+    main_store.put_geo_index(writer, &geo_index)?;
+
+    // 5. store the content hashes of the new documents
+    for (document_id, hash) in documents_hashes {
+        documents_hashes_store.put_document_hash(writer, document_id, hash)?;
+    }
+
+    let words_fst_duration = write_documents_addition_index(
         writer,
         main_store,
         postings_lists_store,
@@ -273,7 +560,350 @@ pub fn apply_documents_partial_addition<'a, 'b>(
         &ranked_map,
         number_of_inserted_documents,
         indexer,
-    )
+    )?;
+
+    let words_fst_duration = deletion_words_fst_duration + words_fst_duration;
+    Ok((
+        duplicates,
+        collisions,
+        report,
+        words_fst_duration,
+        added_ids,
+    ))
+}
+
+/// Flattens the nested objects of the added documents and discovers their new attributes
+/// when the index asks for it, the extended or inferred schema is stored.
+fn prepare_addition(
+    writer: &mut heed::RwTxn,
+    main_store: store::Main,
+    addition: Vec<HashMap<String, serde_json::Value>>,
+    identifiers_generation: IdentifiersGeneration,
+) -> MResult<(Vec<HashMap<String, serde_json::Value>>, Schema)> {
+    let flatten = main_store.flatten_documents(writer)?.unwrap_or(false);
+    let discovery = main_store.attributes_discovery(writer)?.unwrap_or(false);
+    let max_attributes = match main_store.max_attributes(writer)? {
+        Some(limit) => cmp::min(limit as usize, MAX_ATTRIBUTES),
+        None => MAX_ATTRIBUTES,
+    };
+
+    let addition: Vec<_> = if flatten {
+        addition.into_iter().map(flatten_document).collect()
+    } else {
+        addition
+    };
+
+    let (mut schema, mut updated) = match main_store.schema(writer)? {
+        Some(schema) => (schema, false),
+        None if discovery => {
+            // the attributes are sorted to always infer the same identifier
+            let mut attributes: Vec<_> = addition
+                .first()
+                .into_iter()
+                .flat_map(|document| document.keys().map(String::as_str))
+                .collect();
+            attributes.sort_unstable();
+
+            let default_identifier = match identifiers_generation {
+                IdentifiersGeneration::Disabled => None,
+                _ => Some("id"),
+            };
+
+            let found = attributes.len();
+            if found > max_attributes {
+                let limit = max_attributes;
+                return Err(Error::LimitExceeded(LimitExceeded::TooManyAttributes {
+                    limit,
+                    found,
+                }));
+            }
+
+            match infer_schema(attributes, default_identifier) {
+                Some(schema) => (schema, true),
+                None => return Err(Error::SchemaMissing),
+            }
+        }
+        None => return Err(Error::SchemaMissing),
+    };
+
+    if flatten {
+        if let Some(extended) = extend_schema(&schema, &addition, max_attributes)? {
+            schema = extended;
+            updated = true;
+        }
+    }
+
+    if discovery {
+        if let Some(extended) = discover_attributes(&schema, &addition, max_attributes)? {
+            schema = extended;
+            updated = true;
+        }
+    }
+
+    if updated {
+        main_store.put_schema(writer, &schema)?;
+    }
+
+    Ok((addition, schema))
+}
+
+/// The ids of the indexed documents an addition of these documents can replace, update,
+/// report as colliding or as duplicates, they are resolved without modifying the index.
+/// The documents without identifier and without a deterministic one generated are new.
+pub fn touched_documents_ids(
+    reader: &heed::RoTxn,
+    main_store: store::Main,
+    documents_hashes_store: store::DocumentsHashes,
+    addition: &[HashMap<String, serde_json::Value>],
+    identifiers_generation: IdentifiersGeneration,
+) -> MResult<Vec<DocumentId>> {
+    let schema = match main_store.schema(reader)? {
+        Some(schema) => schema,
+        None => return Ok(Vec::new()),
+    };
+
+    let flatten = main_store.flatten_documents(reader)?.unwrap_or(false);
+    let identifier = schema.identifier_name();
+
+    let mut documents_ids = Vec::new();
+    for document in addition {
+        let mut document = if flatten {
+            flatten_document(document.clone())
+        } else {
+            document.clone()
+        };
+
+        if identifiers_generation == IdentifiersGeneration::Content {
+            generate_identifier(identifier, &mut document, identifiers_generation);
+        }
+
+        // the identifiers that cannot be resolved make the addition itself fail
+        if let Ok(Some(key)) = extract_document_key(identifier, &document) {
+            documents_ids.push(key.document_id());
+        }
+
+        let hash = compute_content_hash(identifier, &document);
+        documents_ids.extend(documents_hashes_store.documents_with_hash(reader, hash)?);
+    }
+
+    documents_ids.sort_unstable();
+    documents_ids.dedup();
+
+    Ok(documents_ids)
+}
+
+/// Inserts a generated identifier in the document if it does not have one.
+fn generate_identifier(
+    identifier: &str,
+    document: &mut HashMap<String, serde_json::Value>,
+    generation: IdentifiersGeneration,
+) {
+    match document.get(identifier) {
+        None | Some(serde_json::Value::Null) => (),
+        Some(_) => return,
+    }
+
+    let generated = match generation {
+        IdentifiersGeneration::Disabled => return,
+        IdentifiersGeneration::Content => {
+            format!("{:016x}", compute_content_hash(identifier, document))
+        }
+        IdentifiersGeneration::Uuid => Uuid::new_v4().to_string(),
+    };
+
+    document.insert(identifier.to_string(), serde_json::Value::String(generated));
+}
+
+/// Lists the identifiers shared by several documents of the addition and, if asked, the ones
+/// of the already indexed documents, it must be called before the documents are replaced.
+fn verify_identifiers(
+    reader: &heed::RoTxn,
+    documents_fields_store: store::DocumentsFields,
+    documents_keys: &[(DocumentId, DocumentKey)],
+    verification: IdentifiersVerification,
+) -> MResult<Vec<IdentifierCollision>> {
+    if verification == IdentifiersVerification::Ignore {
+        return Ok(Vec::new());
+    }
+
+    let mut occurrences = HashMap::new();
+    let mut keys = Vec::new();
+    for (document_id, key) in documents_keys {
+        let count = occurrences.entry(*document_id).or_insert(0);
+        if *count == 0 {
+            keys.push((*document_id, key));
+        }
+        *count += 1;
+    }
+
+    let mut collisions = Vec::new();
+    for (document_id, key) in keys {
+        let occurrences = occurrences[&document_id];
+        let indexed = verification == IdentifiersVerification::Indexed
+            && documents_fields_store
+                .document_fields(reader, document_id)?
+                .next()
+                .is_some();
+
+        if occurrences > 1 || indexed {
+            collisions.push(IdentifierCollision {
+                identifier: key.to_string(),
+                occurrences,
+                indexed,
+            });
+        }
+    }
+
+    Ok(collisions)
+}
+
+/// Computes the content hashes of the added documents and, depending on the policy,
+/// finds the documents whose content is the same as another one.
+///
+/// A document is a duplicate when its content is already indexed under another identifier
+/// or when it appears earlier in the addition, the documents replaced by the addition are
+/// not considered. Skipped duplicates are removed from the addition.
+fn resolve_duplicates(
+    reader: &heed::RoTxn,
+    schema: &Schema,
+    documents_fields_store: store::DocumentsFields,
+    documents_hashes_store: store::DocumentsHashes,
+    documents_keys: &[(DocumentId, DocumentKey)],
+    documents_additions: &mut HashMap<DocumentId, HashMap<String, serde_json::Value>>,
+    duplicates_policy: DuplicatesPolicy,
+) -> MResult<(HashMap<DocumentId, u64>, Vec<DuplicateDocument>)> {
+    let identifier = schema.identifier_name();
+    let mut documents_hashes: HashMap<_, _> = documents_additions
+        .iter()
+        .map(|(id, document)| (*id, compute_content_hash(identifier, document)))
+        .collect();
+
+    if duplicates_policy == DuplicatesPolicy::Ignore {
+        return Ok((documents_hashes, Vec::new()));
+    }
+
+    let added_ids: HashSet<_> = documents_keys.iter().map(|(id, _)| *id).collect();
+    let mut visited = HashSet::new();
+    let mut originals = HashMap::new();
+    let mut duplicates = Vec::new();
+
+    for (document_id, key) in documents_keys {
+        // the same identifier can be found multiple times, only the last version is kept
+        if !visited.insert(*document_id) {
+            continue;
+        }
+
+        let hash = documents_hashes[document_id];
+        let duplicate_of = match originals.get(&hash) {
+            Some(original) => Some(original.clone()),
+            None => {
+                let indexed = documents_hashes_store
+                    .documents_with_hash(reader, hash)?
+                    .into_iter()
+                    .find(|id| !added_ids.contains(id));
+
+                match indexed {
+                    Some(id) => indexed_document_key(reader, schema, documents_fields_store, id)?,
+                    None => None,
+                }
+            }
+        };
+
+        match duplicate_of {
+            Some(duplicate_of) => {
+                if duplicates_policy == DuplicatesPolicy::Skip {
+                    documents_additions.remove(document_id);
+                    documents_hashes.remove(document_id);
+                }
+
+                duplicates.push(DuplicateDocument {
+                    identifier: key.to_string(),
+                    duplicate_of,
+                });
+            }
+            None => {
+                originals.insert(hash, key.to_string());
+            }
+        }
+    }
+
+    Ok((documents_hashes, duplicates))
+}
+
+fn indexed_document_key(
+    reader: &heed::RoTxn,
+    schema: &Schema,
+    documents_fields_store: store::DocumentsFields,
+    document_id: DocumentId,
+) -> MResult<Option<String>> {
+    let attribute = match schema.attribute(schema.identifier_name()) {
+        Some(attribute) => attribute,
+        None => return Ok(None),
+    };
+
+    match documents_fields_store.document_attribute(reader, document_id, attribute)? {
+        Some(bytes) => {
+            let value: serde_json::Value = serde_json::from_slice(&bytes)?;
+            let key = DocumentKey::from_value(&value).ok();
+            Ok(key.map(|key| key.to_string()))
+        }
+        None => Ok(None),
+    }
+}
+
+/// An indexer that skips the stop words and the attributes that are not searchable,
+/// it indexes at most the words limit of the attributes that have one and analyzes
+/// the words with the token filters of the index.
+fn raw_indexer(
+    reader: &heed::RoTxn,
+    main_store: store::Main,
+    schema: &Schema,
+) -> MResult<RawIndexer> {
+    let stop_words = match main_store.stop_words_fst(reader)? {
+        Some(stop_words) => stop_words,
+        None => fst::Set::default(),
+    };
+
+    let mut indexer = RawIndexer::new(stop_words);
+
+    if let Some(token_filters) = main_store.token_filters(reader)? {
+        indexer.set_token_filters(TokenFilterChain::resolve(&token_filters)?);
+    }
+
+    let words_limits = main_store.words_limits(reader)?.unwrap_or_default();
+    for (name, limit) in words_limits {
+        if let Some(attribute) = schema.attribute(&name) {
+            indexer.limit_attribute_words(attribute, limit);
+        }
+    }
+
+    if let Some(searchable_attributes) = main_store.searchable_attributes(reader)? {
+        let attributes = searchable_attributes
+            .iter()
+            .filter_map(|name| schema.attribute(name));
+        indexer.restrict_searchable_attributes(attributes);
+    }
+
+    Ok(indexer)
+}
+
+/// Adds the lengths of the values indexed to the ones of the index, they are
+/// reset when the documents are cleared or reindexed but not on deletions.
+fn merge_attributes_lengths(
+    writer: &mut heed::RwTxn,
+    main_store: store::Main,
+    schema: &Schema,
+    indexer: &RawIndexer,
+) -> MResult<()> {
+    let mut attributes_lengths = main_store.attributes_lengths(writer)?.unwrap_or_default();
+    for (attribute, stats) in indexer.lengths() {
+        let name = schema.attribute_name(*attribute).to_owned();
+        attributes_lengths.entry(name).or_default().merge(stats);
+    }
+
+    main_store.put_attributes_lengths(writer, &attributes_lengths)?;
+
+    Ok(())
 }
 
 pub fn reindex_all_documents(
@@ -289,6 +919,7 @@ pub fn reindex_all_documents(
         None => return Err(Error::SchemaMissing),
     };
 
+    let attributes_types = main_store.attributes_types(writer)?.unwrap_or_default();
     let mut ranked_map = RankedMap::default();
 
     // 1. retrieve all documents ids
@@ -302,24 +933,23 @@ pub fn reindex_all_documents(
     main_store.put_words_fst(writer, &fst::Set::default())?;
     main_store.put_ranked_map(writer, &ranked_map)?;
     main_store.put_number_of_documents(writer, |_| 0)?;
+    main_store.put_attributes_lengths(writer, &AttributesLengths::default())?;
     postings_lists_store.clear(writer)?;
     docs_words_store.clear(writer)?;
 
     // 3. re-index chunks of documents (otherwise we make the borrow checker unhappy)
     for documents_ids in documents_ids_to_reindex.chunks(100) {
-        let stop_words = match main_store.stop_words_fst(writer)? {
-            Some(stop_words) => stop_words,
-            None => fst::Set::default(),
-        };
-
         let number_of_inserted_documents = documents_ids.len();
-        let mut indexer = RawIndexer::new(stop_words);
+        let mut indexer = raw_indexer(writer, main_store, &schema)?;
         let mut ram_store = HashMap::new();
 
         for document_id in documents_ids {
+            // the fields are written back in the hot tier
+            documents_fields_store.thaw_document(writer, *document_id)?;
+
             for result in documents_fields_store.document_fields(writer, *document_id)? {
                 let (attr, bytes) = result?;
-                let value: serde_json::Value = serde_json::from_slice(bytes)?;
+                let value: serde_json::Value = serde_json::from_slice(&bytes)?;
                 ram_store.insert((document_id, attr), value);
             }
 
@@ -328,6 +958,7 @@ pub fn reindex_all_documents(
                     writer,
                     attr,
                     schema.props(attr),
+                    attributes_types.get(schema.attribute_name(attr)).cloned(),
                     *docid,
                     documents_fields_store,
                     documents_fields_counts_store,
@@ -339,6 +970,7 @@ pub fn reindex_all_documents(
         }
 
         // 4. write the new index in the main store
+        merge_attributes_lengths(writer, main_store, &schema, &indexer)?;
         write_documents_addition_index(
             writer,
             main_store,
@@ -361,7 +993,7 @@ pub fn write_documents_addition_index(
     ranked_map: &RankedMap,
     number_of_inserted_documents: usize,
     indexer: RawIndexer,
-) -> MResult<()> {
+) -> MResult<Duration> {
     let indexed = indexer.build();
     let mut delta_words_builder = SetBuilder::memory();
 
@@ -380,6 +1012,7 @@ pub fn write_documents_addition_index(
         docs_words_store.put_doc_words(writer, id, &words)?;
     }
 
+    let start = Instant::now();
     let delta_words = delta_words_builder
         .into_inner()
         .and_then(fst::Set::from_bytes)
@@ -402,9 +1035,11 @@ pub fn write_documents_addition_index(
         None => delta_words,
     };
 
+    let words_fst_duration = start.elapsed();
+
     main_store.put_words_fst(writer, &words)?;
     main_store.put_ranked_map(writer, ranked_map)?;
     main_store.put_number_of_documents(writer, |old| old + number_of_inserted_documents as u64)?;
 
-    Ok(())
+    Ok(words_fst_duration)
 }
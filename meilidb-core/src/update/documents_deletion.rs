@@ -1,16 +1,21 @@
 use std::collections::{BTreeSet, HashMap, HashSet};
+use std::time::{Duration, Instant};
 
 use fst::{SetBuilder, Streamer};
 use meilidb_schema::Schema;
 use sdset::{duo::DifferenceByKey, SetBuf, SetOperation};
 
 use crate::database::{UpdateEvent, UpdateEventsEmitter};
+use crate::filters::{CandidatesFilter, Filter};
 use crate::serde::extract_document_id;
 use crate::store;
 use crate::update::{next_update_id, Update};
 use crate::{DocumentId, Error, MResult, RankedMap};
 
 pub struct DocumentsDeletion {
+    main_store: store::Main,
+    documents_fields_store: store::DocumentsFields,
+    documents_fields_counts_store: store::DocumentsFieldsCounts,
     updates_store: store::Updates,
     updates_results_store: store::UpdatesResults,
     updates_notifier: UpdateEventsEmitter,
@@ -19,11 +24,17 @@ pub struct DocumentsDeletion {
 
 impl DocumentsDeletion {
     pub fn new(
+        main_store: store::Main,
+        documents_fields_store: store::DocumentsFields,
+        documents_fields_counts_store: store::DocumentsFieldsCounts,
         updates_store: store::Updates,
         updates_results_store: store::UpdatesResults,
         updates_notifier: UpdateEventsEmitter,
     ) -> DocumentsDeletion {
         DocumentsDeletion {
+            main_store,
+            documents_fields_store,
+            documents_fields_counts_store,
             updates_store,
             updates_results_store,
             updates_notifier,
@@ -50,6 +61,37 @@ impl DocumentsDeletion {
         Ok(())
     }
 
+    /// Scans the documents of the index and deletes the ones whose stored fields
+    /// match the filter, returns the ids of the matching documents.
+    pub fn delete_by_filter(
+        &mut self,
+        reader: &heed::RoTxn,
+        filter: &Filter,
+    ) -> MResult<Vec<DocumentId>> {
+        let candidates = CandidatesFilter::new(
+            reader,
+            Some(filter),
+            Vec::new(),
+            None,
+            None,
+            None,
+            self.main_store,
+            self.documents_fields_store,
+        )?;
+
+        let mut matching = Vec::new();
+        for document_id in self.documents_fields_counts_store.documents_ids(reader)? {
+            let document_id = document_id?;
+            if candidates.accepts(document_id) {
+                matching.push(document_id);
+            }
+        }
+
+        self.documents.extend_from_slice(&matching);
+
+        Ok(matching)
+    }
+
     pub fn finalize(self, writer: &mut heed::RwTxn) -> MResult<u64> {
         let _ = self.updates_notifier.send(UpdateEvent::NewUpdate);
         let update_id = push_documents_deletion(
@@ -89,8 +131,10 @@ pub fn apply_documents_deletion(
     documents_fields_counts_store: store::DocumentsFieldsCounts,
     postings_lists_store: store::PostingsLists,
     docs_words_store: store::DocsWords,
+    documents_hashes_store: store::DocumentsHashes,
+    documents_ids_store: store::DocumentsIds,
     deletion: Vec<DocumentId>,
-) -> MResult<()> {
+) -> MResult<Duration> {
     let idset = SetBuf::from_dirty(deletion);
 
     let schema = match main_store.schema(writer)? {
@@ -103,6 +147,8 @@ pub fn apply_documents_deletion(
         None => RankedMap::default(),
     };
 
+    let mut geo_index = main_store.geo_index(writer)?.unwrap_or_default();
+
     // collect the ranked attributes according to the schema
     let ranked_attrs: Vec<_> = schema
         .iter()
@@ -123,6 +169,7 @@ pub fn apply_documents_deletion(
         for ranked_attr in &ranked_attrs {
             ranked_map.remove(id, *ranked_attr);
         }
+        geo_index.remove(id);
 
         if let Some(words) = docs_words_store.doc_words(writer, id)? {
             let mut stream = words.stream();
@@ -164,8 +211,11 @@ pub fn apply_documents_deletion(
     let deleted_documents_len = deleted_documents.len() as u64;
     for id in deleted_documents {
         docs_words_store.del_doc_words(writer, id)?;
+        documents_hashes_store.del_document_hash(writer, id)?;
+        documents_ids_store.del_document_id(writer, id)?;
     }
 
+    let start = Instant::now();
     let removed_words = fst::Set::from_iter(removed_words).unwrap();
     let words = match main_store.words_fst(writer)? {
         Some(words_set) => {
@@ -184,9 +234,12 @@ pub fn apply_documents_deletion(
         None => fst::Set::default(),
     };
 
+    let words_fst_duration = start.elapsed();
+
     main_store.put_words_fst(writer, &words)?;
     main_store.put_ranked_map(writer, &ranked_map)?;
+    main_store.put_geo_index(writer, &geo_index)?;
     main_store.put_number_of_documents(writer, |old| old - deleted_documents_len)?;
 
-    Ok(())
+    Ok(words_fst_duration)
 }
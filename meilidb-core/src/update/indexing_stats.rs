@@ -0,0 +1,107 @@
+use std::collections::HashMap;
+use std::io;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::update::UpdateType;
+
+/// The statistics of a processed documents update, they are kept in
+/// the indexing history of the index once the update is successful.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexingStats {
+    pub update_id: u64,
+    #[serde(rename = "type")]
+    pub update_type: UpdateType,
+    pub number_of_documents: usize,
+    /// The size of the documents serialized in JSON, zero for the deletions.
+    pub bytes_indexed: u64,
+    pub documents_per_second: f64,
+    pub duration: f64,           // in seconds
+    pub words_fst_duration: f64, // in seconds
+    pub processed_at: DateTime<Utc>,
+}
+
+impl IndexingStats {
+    pub(crate) fn new(
+        update_id: u64,
+        update_type: UpdateType,
+        number_of_documents: usize,
+        bytes_indexed: u64,
+        duration: Duration,
+        words_fst_duration: Duration,
+    ) -> IndexingStats {
+        let duration = duration.as_secs_f64();
+        let documents_per_second = if duration > 0.0 {
+            number_of_documents as f64 / duration
+        } else {
+            0.0
+        };
+
+        IndexingStats {
+            update_id,
+            update_type,
+            number_of_documents,
+            bytes_indexed,
+            documents_per_second,
+            duration,
+            words_fst_duration: words_fst_duration.as_secs_f64(),
+            processed_at: Utc::now(),
+        }
+    }
+}
+
+struct BytesCounter(u64);
+
+impl io::Write for BytesCounter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// The number of bytes of the documents serialized in JSON, they are not written in memory.
+pub(crate) fn documents_bytes(documents: &[HashMap<String, serde_json::Value>]) -> u64 {
+    let mut counter = BytesCounter(0);
+    for document in documents {
+        // serializing a map of JSON values into a writer that never fails can not fail
+        let _ = serde_json::to_writer(&mut counter, document);
+    }
+    counter.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn counted_bytes() {
+        let document: HashMap<String, serde_json::Value> =
+            serde_json::from_value(json!({ "id": 1, "title": "hello" })).unwrap();
+        let expected = serde_json::to_vec(&document).unwrap().len() as u64;
+
+        assert_eq!(documents_bytes(&[document.clone(), document]), expected * 2);
+        assert_eq!(documents_bytes(&[]), 0);
+    }
+
+    #[test]
+    fn documents_per_second() {
+        let update_type = UpdateType::DocumentsAddition { number: 500 };
+        let stats = IndexingStats::new(
+            3,
+            update_type,
+            500,
+            1024,
+            Duration::from_millis(250),
+            Duration::from_millis(10),
+        );
+        assert_eq!(stats.documents_per_second, 2000.0);
+        assert_eq!(stats.words_fst_duration, 0.01);
+    }
+}
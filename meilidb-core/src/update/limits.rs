@@ -0,0 +1,127 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::error::LimitExceeded;
+
+/// The limits an index imposes on the documents updates, checked when they are enqueued.
+///
+/// A limit that is not defined is not enforced, sizes are
+/// computed on the JSON representation of the documents.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UpdateLimits {
+    pub max_documents_per_update: Option<usize>,
+    pub max_payload_bytes: Option<usize>,
+    pub max_field_bytes: Option<usize>,
+}
+
+impl UpdateLimits {
+    pub fn check_documents(
+        &self,
+        documents: &[HashMap<String, Value>],
+    ) -> Result<(), LimitExceeded> {
+        if let Some(limit) = self.max_documents_per_update {
+            if documents.len() > limit {
+                let found = documents.len();
+                return Err(LimitExceeded::TooManyDocuments { limit, found });
+            }
+        }
+
+        if self.max_payload_bytes.is_none() && self.max_field_bytes.is_none() {
+            return Ok(());
+        }
+
+        let mut payload_bytes = 0;
+        for document in documents {
+            for (field, value) in document {
+                let field_bytes = json_len(value);
+
+                if let Some(limit) = self.max_field_bytes {
+                    if field_bytes > limit {
+                        return Err(LimitExceeded::FieldTooLarge {
+                            limit,
+                            found: field_bytes,
+                            field: field.clone(),
+                        });
+                    }
+                }
+
+                payload_bytes += field.len() + field_bytes;
+            }
+        }
+
+        if let Some(limit) = self.max_payload_bytes {
+            if payload_bytes > limit {
+                let found = payload_bytes;
+                return Err(LimitExceeded::PayloadTooLarge { limit, found });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn json_len(value: &Value) -> usize {
+    // serializing a Value into memory can not fail
+    serde_json::to_vec(value).map_or(0, |bytes| bytes.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn documents(value: Value) -> Vec<HashMap<String, Value>> {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn no_limits() {
+        let documents = documents(json!([{ "id": 1, "title": "a".repeat(1000) }]));
+        assert!(UpdateLimits::default().check_documents(&documents).is_ok());
+    }
+
+    #[test]
+    fn too_many_documents() {
+        let limits = UpdateLimits {
+            max_documents_per_update: Some(1),
+            ..UpdateLimits::default()
+        };
+
+        let documents = documents(json!([{ "id": 1 }, { "id": 2 }]));
+        assert_matches!(
+            limits.check_documents(&documents),
+            Err(LimitExceeded::TooManyDocuments { limit: 1, found: 2 })
+        );
+    }
+
+    #[test]
+    fn too_large() {
+        let limits = UpdateLimits {
+            max_payload_bytes: Some(100),
+            max_field_bytes: Some(20),
+            ..UpdateLimits::default()
+        };
+
+        let field = documents(json!([{ "id": 1, "title": "a".repeat(30) }]));
+        assert_matches!(
+            limits.check_documents(&field),
+            Err(LimitExceeded::FieldTooLarge { ref field, .. }) if field == "title"
+        );
+
+        let payload = documents(json!([
+            { "id": 1, "title": "a".repeat(15) },
+            { "id": 2, "title": "b".repeat(15) },
+            { "id": 3, "title": "c".repeat(15) },
+            { "id": 4, "title": "d".repeat(15) },
+        ]));
+        assert_matches!(
+            limits.check_documents(&payload),
+            Err(LimitExceeded::PayloadTooLarge { limit: 100, .. })
+        );
+
+        let valid = documents(json!([{ "id": 1, "title": "a".repeat(15) }]));
+        assert!(limits.check_documents(&valid).is_ok());
+    }
+}
@@ -0,0 +1,495 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::PathBuf;
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use arc_swap::ArcSwapOption;
+use log::warn;
+use meilidb_schema::Schema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::criterion::RankingRule;
+use crate::error::UnsupportedOperation;
+use crate::update::documents_addition::AdditionResult;
+use crate::update::{apply_clear_all, apply_documents_addition, check_searchable};
+use crate::update::{next_update_id, CoercionReport, Update};
+use crate::{store, Error, MResult};
+
+/// The number of documents mapped and indexed at once by a migration.
+const MIGRATION_BATCH_SIZE: usize = 10_000;
+
+static MIGRATION_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+pub type BoxCustomsRenameFn = Box<
+    dyn Fn(&[u8], &BTreeMap<String, String>) -> Result<Vec<u8>, String> + Send + Sync + 'static,
+>;
+
+/// How the attributes named in the customs of an index follow the attributes renamed by
+/// a migration, the customs are opaque to the database and kept as is when none is set.
+#[derive(Clone, Default)]
+pub(crate) struct CustomsRename {
+    inner: Arc<ArcSwapOption<BoxCustomsRenameFn>>,
+}
+
+impl CustomsRename {
+    pub(crate) fn set(&self, rename_fn: BoxCustomsRenameFn) {
+        self.inner.swap(Some(Arc::new(rename_fn)));
+    }
+
+    fn rename(&self, customs: &[u8], renames: &BTreeMap<String, String>) -> MResult<Vec<u8>> {
+        match *self.inner.load() {
+            Some(ref rename_fn) => rename_fn(customs, renames).map_err(Error::CustomsRename),
+            None => Ok(customs.to_vec()),
+        }
+    }
+}
+
+/// The value of an attribute split into several attributes of the new schema.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct FieldSplit {
+    pub separator: String,
+    /// The attributes receiving the parts of the value in order, the last one receives the
+    /// remaining text, a value that is not a string is entirely given to the first one.
+    pub into: Vec<String>,
+}
+
+/// The values of several attributes merged into one attribute of the new schema.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct FieldMerge {
+    pub separator: String,
+    /// The attributes whose values are joined in order, the missing ones are skipped.
+    pub from: Vec<String>,
+}
+
+/// Describes how the documents of an index are rewritten for its new schema,
+/// the attributes not mentioned keep their name and value.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct FieldMapping {
+    /// The old name of the attributes associated with their new name.
+    #[serde(default)]
+    pub renames: BTreeMap<String, String>,
+    #[serde(default)]
+    pub splits: BTreeMap<String, FieldSplit>,
+    /// The new attributes associated with the attributes merged into them.
+    #[serde(default)]
+    pub merges: BTreeMap<String, FieldMerge>,
+    /// The values given to the attributes that are missing or null once the document is mapped.
+    #[serde(default)]
+    pub defaults: BTreeMap<String, Value>,
+}
+
+impl FieldMapping {
+    pub fn len(&self) -> usize {
+        self.renames.len() + self.splits.len() + self.merges.len() + self.defaults.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The attributes written by the mapping.
+    fn targets(&self) -> impl Iterator<Item = &String> {
+        let splits = self.splits.values().flat_map(|split| split.into.iter());
+        self.renames
+            .values()
+            .chain(splits)
+            .chain(self.merges.keys())
+            .chain(self.defaults.keys())
+    }
+
+    /// Returns an error if the mapping writes an attribute that is not in the schema.
+    pub fn validate(&self, schema: &Schema) -> Result<(), UnsupportedOperation> {
+        match self.targets().find(|name| schema.attribute(name).is_none()) {
+            Some(name) => Err(UnsupportedOperation::CannotMigrateToUnknownAttribute(
+                name.clone(),
+            )),
+            None => Ok(()),
+        }
+    }
+
+    pub fn map_document(&self, mut document: HashMap<String, Value>) -> HashMap<String, Value> {
+        let mut consumed: HashSet<&str> = HashSet::new();
+        consumed.extend(self.renames.keys().map(String::as_str));
+        consumed.extend(self.splits.keys().map(String::as_str));
+        for merge in self.merges.values() {
+            consumed.extend(merge.from.iter().map(String::as_str));
+        }
+
+        let mut mapped: HashMap<String, Value> = document
+            .iter()
+            .filter(|(name, _)| !consumed.contains(name.as_str()))
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect();
+
+        // the merged attributes are read before the others are moved
+        for (new, merge) in &self.merges {
+            let parts: Vec<_> = merge
+                .from
+                .iter()
+                .filter_map(|name| match document.get(name) {
+                    Some(Value::String(text)) => Some(text.clone()),
+                    Some(Value::Null) | None => None,
+                    Some(value) => Some(value.to_string()),
+                })
+                .collect();
+
+            if !parts.is_empty() {
+                mapped.insert(new.clone(), Value::String(parts.join(&merge.separator)));
+            }
+        }
+
+        for (old, new) in &self.renames {
+            if let Some(value) = document.remove(old) {
+                mapped.insert(new.clone(), value);
+            }
+        }
+
+        for (old, split) in &self.splits {
+            match document.remove(old) {
+                Some(Value::String(text)) => {
+                    let parts = text.splitn(split.into.len(), split.separator.as_str());
+                    for (name, part) in split.into.iter().zip(parts) {
+                        mapped.insert(name.clone(), Value::String(part.trim().to_string()));
+                    }
+                }
+                Some(Value::Null) | None => (),
+                Some(value) => {
+                    if let Some(name) = split.into.first() {
+                        mapped.insert(name.clone(), value);
+                    }
+                }
+            }
+        }
+
+        for (name, value) in &self.defaults {
+            match mapped.get(name) {
+                Some(Value::Null) | None => {
+                    mapped.insert(name.clone(), value.clone());
+                }
+                Some(_) => (),
+            }
+        }
+
+        mapped
+    }
+}
+
+/// Rewrites all the documents of the index for the new schema, the documents are read with
+/// the current schema, mapped and written by batches to a file, the index is then emptied and
+/// rebuilt from this file by batches. Everything is done in the update transaction, the
+/// searches see the previous index until the migrated one replaces it when it is committed.
+pub fn apply_migration(
+    writer: &mut heed::RwTxn,
+    index: &store::Index,
+    new_schema: Schema,
+    mapping: FieldMapping,
+) -> MResult<AdditionResult> {
+    let old_schema = match index.main.schema(writer)? {
+        Some(schema) => schema,
+        None => return Err(Error::SchemaMissing),
+    };
+
+    mapping.validate(&new_schema)?;
+
+    // the searchable attributes follow the renamed attributes
+    let searchable_attributes = index.main.searchable_attributes(writer)?.map(|attributes| {
+        let rename = |name: String| mapping.renames.get(&name).cloned().unwrap_or(name);
+        attributes.into_iter().map(rename).collect::<Vec<_>>()
+    });
+    check_searchable(
+        &new_schema,
+        searchable_attributes.as_ref().map(Vec::as_slice),
+    )?;
+
+    // 1. read and map the documents with the current schema, only
+    //    a batch of them is kept in memory before being written
+    let mut documents_ids = Vec::new();
+    for result in index.documents_fields_counts.documents_ids(writer)? {
+        documents_ids.push(result?);
+    }
+
+    let mut mapped_documents = MappedDocuments::create()?;
+    for batch in documents_ids.chunks(MIGRATION_BATCH_SIZE) {
+        for document_id in batch {
+            index.documents_fields.thaw_document(writer, *document_id)?;
+
+            let mut document = HashMap::new();
+            for result in index
+                .documents_fields
+                .document_fields(writer, *document_id)?
+            {
+                let (attr, bytes) = result?;
+                let value: Value = serde_json::from_slice(&bytes)?;
+                document.insert(old_schema.attribute_name(attr).to_string(), value);
+            }
+
+            mapped_documents.push(&mapping.map_document(document))?;
+        }
+    }
+
+    // 2. empty the index and replace its schema, the settings
+    //    referring to the renamed attributes follow them
+    apply_clear_all(
+        writer,
+        index.main,
+        index.documents_fields,
+        index.documents_fields_counts,
+        index.postings_lists,
+        index.docs_words,
+        index.documents_hashes,
+        index.documents_ids,
+    )?;
+
+    index.main.put_schema(writer, &new_schema)?;
+    rename_settings(writer, index, &mapping.renames)?;
+
+    // 3. index the mapped documents with the new schema by batches
+    let mut duplicates = Vec::new();
+    let mut collisions = Vec::new();
+    let mut report = CoercionReport::default();
+    let mut words_fst_duration = Duration::default();
+    let mut added_ids = Vec::new();
+
+    let mut lines = mapped_documents.reader()?.lines();
+    loop {
+        let mut batch = Vec::with_capacity(MIGRATION_BATCH_SIZE);
+        for line in lines.by_ref().take(MIGRATION_BATCH_SIZE) {
+            batch.push(serde_json::from_str(&line?)?);
+        }
+
+        if batch.is_empty() {
+            break;
+        }
+
+        let (found, shared, coercions, duration, ids) = apply_documents_addition(
+            writer,
+            index.main,
+            index.documents_fields,
+            index.documents_fields_counts,
+            index.postings_lists,
+            index.docs_words,
+            index.documents_hashes,
+            index.documents_ids,
+            batch,
+            Default::default(),
+            Default::default(),
+            Default::default(),
+        )?;
+
+        duplicates.extend(found);
+        collisions.extend(shared);
+        report.merge(coercions);
+        words_fst_duration += duration;
+        added_ids.extend(ids);
+    }
+
+    Ok((
+        duplicates,
+        collisions,
+        report,
+        words_fst_duration,
+        added_ids,
+    ))
+}
+
+/// Renames the attributes in the settings of the index that refer to them by name.
+fn rename_settings(
+    writer: &mut heed::RwTxn,
+    index: &store::Index,
+    renames: &BTreeMap<String, String>,
+) -> MResult<()> {
+    if renames.is_empty() {
+        return Ok(());
+    }
+
+    let rename = |name: String| renames.get(&name).cloned().unwrap_or(name);
+    let rename_rule = |rule: RankingRule| match rule {
+        RankingRule::Asc(name) => RankingRule::Asc(rename(name)),
+        RankingRule::Dsc(name) => RankingRule::Dsc(rename(name)),
+        rule => rule,
+    };
+
+    let main = index.main;
+
+    if let Some(attributes) = main.searchable_attributes(writer)? {
+        let attributes: Vec<_> = attributes.into_iter().map(rename).collect();
+        main.put_searchable_attributes(writer, &attributes)?;
+    }
+
+    if let Some(attributes) = main.displayed_attributes(writer)? {
+        let attributes: Vec<_> = attributes.into_iter().map(rename).collect();
+        main.put_displayed_attributes(writer, &attributes)?;
+    }
+
+    if let Some(rules) = main.criteria(writer)? {
+        let rules: Vec<_> = rules.into_iter().map(rename_rule).collect();
+        main.put_criteria(writer, &rules)?;
+    }
+
+    if let Some(profiles) = main.ranking_profiles(writer)? {
+        let profiles = profiles
+            .into_iter()
+            .map(|(name, rules)| (name, rules.into_iter().map(rename_rule).collect()))
+            .collect();
+        main.put_ranking_profiles(writer, &profiles)?;
+    }
+
+    if let Some(attributes_types) = main.attributes_types(writer)? {
+        let attributes_types = attributes_types
+            .into_iter()
+            .map(|(name, attribute_type)| (rename(name), attribute_type))
+            .collect();
+        main.put_attributes_types(writer, &attributes_types)?;
+    }
+
+    if let Some(words_limits) = main.words_limits(writer)? {
+        let words_limits = words_limits
+            .into_iter()
+            .map(|(name, limit)| (rename(name), limit))
+            .collect();
+        main.put_words_limits(writer, &words_limits)?;
+    }
+
+    if let Some(normalization) = main.facets_normalization(writer)? {
+        let normalization = normalization
+            .into_iter()
+            .map(|(name, policy)| (rename(name), policy))
+            .collect();
+        main.put_facets_normalization(writer, &normalization)?;
+    }
+
+    let customs = match main.customs(writer)? {
+        Some(customs) => Some(index.customs_rename.rename(customs, renames)?),
+        None => None,
+    };
+
+    if let Some(customs) = customs {
+        main.put_customs(writer, &customs)?;
+    }
+
+    Ok(())
+}
+
+/// The mapped documents of a migration written as JSON lines to a temporary file,
+/// the file is removed once the migration is done, even when it failed.
+struct MappedDocuments {
+    path: PathBuf,
+    file: BufWriter<File>,
+}
+
+impl MappedDocuments {
+    fn create() -> io::Result<MappedDocuments> {
+        let count = MIGRATION_COUNT.fetch_add(1, Ordering::SeqCst);
+        let name = format!("meilidb-migration-{}-{}", process::id(), count);
+        let path = std::env::temp_dir().join(name);
+
+        let file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)?;
+
+        Ok(MappedDocuments {
+            path,
+            file: BufWriter::new(file),
+        })
+    }
+
+    fn push(&mut self, document: &HashMap<String, Value>) -> MResult<()> {
+        serde_json::to_writer(&mut self.file, document)?;
+        self.file.write_all(b"\n")?;
+        Ok(())
+    }
+
+    fn reader(&mut self) -> io::Result<BufReader<File>> {
+        self.file.flush()?;
+        File::open(&self.path).map(BufReader::new)
+    }
+}
+
+impl Drop for MappedDocuments {
+    fn drop(&mut self) {
+        if let Err(e) = fs::remove_file(&self.path) {
+            warn!("could not remove the migration file {:?}; {}", self.path, e);
+        }
+    }
+}
+
+pub fn push_migration(
+    writer: &mut heed::RwTxn,
+    updates_store: store::Updates,
+    updates_results_store: store::UpdatesResults,
+    new_schema: Schema,
+    mapping: FieldMapping,
+) -> MResult<u64> {
+    let last_update_id = next_update_id(writer, updates_store, updates_results_store)?;
+
+    let update = Update::migration(new_schema, mapping);
+    updates_store.put_update(writer, last_update_id, &update)?;
+
+    Ok(last_update_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn document(value: Value) -> HashMap<String, Value> {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn map_document() {
+        let mapping: FieldMapping = serde_json::from_value(json!({
+            "renames": { "title": "name" },
+            "splits": { "author": { "separator": ",", "into": ["last_name", "first_name"] } },
+            "merges": { "place": { "separator": ", ", "from": ["city", "country"] } },
+            "defaults": { "language": "en", "name": "untitled" },
+        }))
+        .unwrap();
+
+        let mapped = mapping.map_document(document(json!({
+            "id": 1,
+            "title": "Foundation",
+            "author": "Asimov, Isaac",
+            "city": "New York",
+            "country": "USA",
+        })));
+
+        let expected = document(json!({
+            "id": 1,
+            "name": "Foundation",
+            "last_name": "Asimov",
+            "first_name": "Isaac",
+            "place": "New York, USA",
+            "language": "en",
+        }));
+
+        assert_eq!(mapped, expected);
+
+        let mapped = mapping.map_document(document(json!({
+            "id": 2,
+            "author": 42,
+            "country": "France",
+            "language": null,
+        })));
+
+        let expected = document(json!({
+            "id": 2,
+            "name": "untitled",
+            "last_name": 42,
+            "place": "France",
+            "language": "en",
+        }));
+
+        assert_eq!(mapped, expected);
+    }
+}
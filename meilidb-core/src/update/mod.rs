@@ -1,24 +1,64 @@
+mod attributes_types_update;
+mod callbacks;
 mod clear_all;
+mod coercions;
 mod customs_update;
+mod decompounding_update;
 mod documents_addition;
 mod documents_deletion;
+mod indexing_stats;
+mod limits;
+mod migration;
+mod processed_updates;
+mod replay;
 mod schema_update;
+mod searchable_attributes_update;
+mod settings_patch;
 mod stop_words_addition;
 mod stop_words_deletion;
 mod synonyms_addition;
 mod synonyms_deletion;
+mod token_filters_update;
+mod words_limits_update;
 
+pub use self::attributes_types_update::{
+    apply_attributes_types_update, push_attributes_types_update,
+};
+pub use self::callbacks::UPDATE_CALLBACKS_QUEUE_LEN;
+pub(crate) use self::callbacks::{ArcSwapFn, CallbacksDispatcher, IndexCallback};
 pub use self::clear_all::{apply_clear_all, push_clear_all};
+pub use self::coercions::{CoercionReport, DocumentCoercions};
 pub use self::customs_update::{apply_customs_update, push_customs_update};
+pub use self::decompounding_update::{apply_decompounding_update, push_decompounding_update};
 pub use self::documents_addition::{
     apply_documents_addition, apply_documents_partial_addition, DocumentsAddition,
+    DuplicateDocument, DuplicatesPolicy, IdentifierCollision, IdentifiersGeneration,
+    IdentifiersVerification,
 };
 pub use self::documents_deletion::{apply_documents_deletion, DocumentsDeletion};
-pub use self::schema_update::{apply_schema_update, push_schema_update};
+pub use self::indexing_stats::IndexingStats;
+pub use self::limits::UpdateLimits;
+pub(crate) use self::migration::CustomsRename;
+pub use self::migration::{
+    apply_migration, push_migration, BoxCustomsRenameFn, FieldMapping, FieldMerge, FieldSplit,
+};
+pub use self::processed_updates::{ProcessedUpdates, UpdateProcessed};
+pub use self::replay::{replay_update, UpdateReplay};
+pub use self::schema_update::{apply_schema_update, check_searchable, push_schema_update};
+pub use self::searchable_attributes_update::{
+    apply_searchable_attributes_update, push_searchable_attributes_update,
+};
+pub(crate) use self::settings_patch::CustomsMerge;
+pub use self::settings_patch::{
+    apply_settings_patch, push_settings_patch, BoxCustomsMergeFn, SettingsPatch,
+    SettingsPatchContent,
+};
 pub use self::stop_words_addition::{apply_stop_words_addition, StopWordsAddition};
 pub use self::stop_words_deletion::{apply_stop_words_deletion, StopWordsDeletion};
-pub use self::synonyms_addition::{apply_synonyms_addition, SynonymsAddition};
+pub use self::synonyms_addition::{apply_synonyms_addition, SynonymsAddition, MAX_SYNONYM_PENALTY};
 pub use self::synonyms_deletion::{apply_synonyms_deletion, SynonymsDeletion};
+pub use self::token_filters_update::{apply_token_filters_update, push_token_filters_update};
+pub use self::words_limits_update::{apply_words_limits_update, push_words_limits_update};
 
 use std::cmp;
 use std::collections::{BTreeMap, BTreeSet, HashMap};
@@ -27,18 +67,26 @@ use std::time::Instant;
 use chrono::{DateTime, Utc};
 use heed::Result as ZResult;
 use log::debug;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 
+use crate::raw_indexer::WordsLimits;
+use crate::serde::AttributesTypes;
+use crate::token_filters::TokenFilter;
 use crate::{store, DocumentId, MResult};
 use meilidb_schema::Schema;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Update {
+    #[serde(deserialize_with = "deserialize_update_data")]
     data: UpdateData,
     enqueued_at: DateTime<Utc>,
 }
 
 impl Update {
+    pub(crate) fn data(&self) -> &UpdateData {
+        &self.data
+    }
+
     fn clear_all() -> Update {
         Update {
             data: UpdateData::ClearAll,
@@ -60,16 +108,36 @@ impl Update {
         }
     }
 
-    fn documents_addition(data: Vec<HashMap<String, serde_json::Value>>) -> Update {
+    fn documents_addition(
+        data: Vec<HashMap<String, serde_json::Value>>,
+        duplicates_policy: DuplicatesPolicy,
+        identifiers_verification: IdentifiersVerification,
+        identifiers_generation: IdentifiersGeneration,
+    ) -> Update {
         Update {
-            data: UpdateData::DocumentsAddition(data),
+            data: UpdateData::DocumentsAddition {
+                documents: data,
+                duplicates_policy,
+                identifiers_verification,
+                identifiers_generation,
+            },
             enqueued_at: Utc::now(),
         }
     }
 
-    fn documents_partial(data: Vec<HashMap<String, serde_json::Value>>) -> Update {
+    fn documents_partial(
+        data: Vec<HashMap<String, serde_json::Value>>,
+        duplicates_policy: DuplicatesPolicy,
+        identifiers_verification: IdentifiersVerification,
+        identifiers_generation: IdentifiersGeneration,
+    ) -> Update {
         Update {
-            data: UpdateData::DocumentsPartial(data),
+            data: UpdateData::DocumentsPartial {
+                documents: data,
+                duplicates_policy,
+                identifiers_verification,
+                identifiers_generation,
+            },
             enqueued_at: Utc::now(),
         }
     }
@@ -81,9 +149,9 @@ impl Update {
         }
     }
 
-    fn synonyms_addition(data: BTreeMap<String, Vec<String>>) -> Update {
+    fn synonyms_addition(data: BTreeMap<String, Vec<(String, u8)>>) -> Update {
         Update {
-            data: UpdateData::SynonymsAddition(data),
+            data: UpdateData::WeightedSynonymsAddition(data),
             enqueued_at: Utc::now(),
         }
     }
@@ -108,6 +176,55 @@ impl Update {
             enqueued_at: Utc::now(),
         }
     }
+
+    fn decompounding(data: BTreeSet<String>) -> Update {
+        Update {
+            data: UpdateData::Decompounding(data),
+            enqueued_at: Utc::now(),
+        }
+    }
+
+    fn attributes_types(data: AttributesTypes) -> Update {
+        Update {
+            data: UpdateData::AttributesTypes(data),
+            enqueued_at: Utc::now(),
+        }
+    }
+
+    fn settings_patch(data: SettingsPatchContent) -> Update {
+        Update {
+            data: UpdateData::SettingsPatch(data),
+            enqueued_at: Utc::now(),
+        }
+    }
+
+    fn words_limits(data: WordsLimits) -> Update {
+        Update {
+            data: UpdateData::WordsLimits(data),
+            enqueued_at: Utc::now(),
+        }
+    }
+
+    fn token_filters(data: Vec<TokenFilter>) -> Update {
+        Update {
+            data: UpdateData::TokenFilters(data),
+            enqueued_at: Utc::now(),
+        }
+    }
+
+    fn searchable_attributes(data: Option<Vec<String>>) -> Update {
+        Update {
+            data: UpdateData::SearchableAttributes(data),
+            enqueued_at: Utc::now(),
+        }
+    }
+
+    fn migration(schema: Schema, mapping: FieldMapping) -> Update {
+        Update {
+            data: UpdateData::Migration(schema, mapping),
+            enqueued_at: Utc::now(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -115,13 +232,80 @@ pub enum UpdateData {
     ClearAll,
     Schema(Schema),
     Customs(Vec<u8>),
-    DocumentsAddition(Vec<HashMap<String, serde_json::Value>>),
-    DocumentsPartial(Vec<HashMap<String, serde_json::Value>>),
+    DocumentsAddition {
+        documents: Vec<HashMap<String, serde_json::Value>>,
+        #[serde(default)]
+        duplicates_policy: DuplicatesPolicy,
+        #[serde(default)]
+        identifiers_verification: IdentifiersVerification,
+        #[serde(default)]
+        identifiers_generation: IdentifiersGeneration,
+    },
+    DocumentsPartial {
+        documents: Vec<HashMap<String, serde_json::Value>>,
+        #[serde(default)]
+        duplicates_policy: DuplicatesPolicy,
+        #[serde(default)]
+        identifiers_verification: IdentifiersVerification,
+        #[serde(default)]
+        identifiers_generation: IdentifiersGeneration,
+    },
     DocumentsDeletion(Vec<DocumentId>),
     SynonymsAddition(BTreeMap<String, Vec<String>>),
+    WeightedSynonymsAddition(BTreeMap<String, Vec<(String, u8)>>),
     SynonymsDeletion(BTreeMap<String, Option<Vec<String>>>),
     StopWordsAddition(BTreeSet<String>),
     StopWordsDeletion(BTreeSet<String>),
+    Decompounding(BTreeSet<String>),
+    AttributesTypes(AttributesTypes),
+    SettingsPatch(SettingsPatchContent),
+    WordsLimits(WordsLimits),
+    SearchableAttributes(Option<Vec<String>>),
+    Migration(Schema, FieldMapping),
+    TokenFilters(Vec<TokenFilter>),
+}
+
+/// The documents updates enqueued by the previous versions, only the documents were stored.
+#[derive(Deserialize)]
+enum PreviousUpdateData {
+    DocumentsAddition(Vec<HashMap<String, serde_json::Value>>),
+    DocumentsPartial(Vec<HashMap<String, serde_json::Value>>),
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredUpdateData {
+    Current(UpdateData),
+    Previous(PreviousUpdateData),
+}
+
+/// Reads the updates enqueued by this version and the previous ones, the missing
+/// options of the previous documents updates take their default values.
+fn deserialize_update_data<'de, D>(deserializer: D) -> Result<UpdateData, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let data = match StoredUpdateData::deserialize(deserializer)? {
+        StoredUpdateData::Current(data) => data,
+        StoredUpdateData::Previous(PreviousUpdateData::DocumentsAddition(documents)) => {
+            UpdateData::DocumentsAddition {
+                documents,
+                duplicates_policy: DuplicatesPolicy::default(),
+                identifiers_verification: IdentifiersVerification::default(),
+                identifiers_generation: IdentifiersGeneration::default(),
+            }
+        }
+        StoredUpdateData::Previous(PreviousUpdateData::DocumentsPartial(documents)) => {
+            UpdateData::DocumentsPartial {
+                documents,
+                duplicates_policy: DuplicatesPolicy::default(),
+                identifiers_verification: IdentifiersVerification::default(),
+                identifiers_generation: IdentifiersGeneration::default(),
+            }
+        }
+    };
+
+    Ok(data)
 }
 
 impl UpdateData {
@@ -130,11 +314,11 @@ impl UpdateData {
             UpdateData::ClearAll => UpdateType::ClearAll,
             UpdateData::Schema(_) => UpdateType::Schema,
             UpdateData::Customs(_) => UpdateType::Customs,
-            UpdateData::DocumentsAddition(addition) => UpdateType::DocumentsAddition {
-                number: addition.len(),
+            UpdateData::DocumentsAddition { documents, .. } => UpdateType::DocumentsAddition {
+                number: documents.len(),
             },
-            UpdateData::DocumentsPartial(addition) => UpdateType::DocumentsPartial {
-                number: addition.len(),
+            UpdateData::DocumentsPartial { documents, .. } => UpdateType::DocumentsPartial {
+                number: documents.len(),
             },
             UpdateData::DocumentsDeletion(deletion) => UpdateType::DocumentsDeletion {
                 number: deletion.len(),
@@ -142,6 +326,9 @@ impl UpdateData {
             UpdateData::SynonymsAddition(addition) => UpdateType::SynonymsAddition {
                 number: addition.len(),
             },
+            UpdateData::WeightedSynonymsAddition(addition) => UpdateType::SynonymsAddition {
+                number: addition.len(),
+            },
             UpdateData::SynonymsDeletion(deletion) => UpdateType::SynonymsDeletion {
                 number: deletion.len(),
             },
@@ -151,6 +338,27 @@ impl UpdateData {
             UpdateData::StopWordsDeletion(deletion) => UpdateType::StopWordsDeletion {
                 number: deletion.len(),
             },
+            UpdateData::Decompounding(dictionary) => UpdateType::Decompounding {
+                number: dictionary.len(),
+            },
+            UpdateData::AttributesTypes(types) => UpdateType::AttributesTypes {
+                number: types.len(),
+            },
+            UpdateData::SettingsPatch(patch) => UpdateType::SettingsPatch {
+                number: patch.len(),
+            },
+            UpdateData::WordsLimits(limits) => UpdateType::WordsLimits {
+                number: limits.len(),
+            },
+            UpdateData::SearchableAttributes(attributes) => UpdateType::SearchableAttributes {
+                number: attributes.as_ref().map_or(0, Vec::len),
+            },
+            UpdateData::Migration(_, mapping) => UpdateType::Migration {
+                number: mapping.len(),
+            },
+            UpdateData::TokenFilters(filters) => UpdateType::TokenFilters {
+                number: filters.len(),
+            },
         }
     }
 }
@@ -168,6 +376,13 @@ pub enum UpdateType {
     SynonymsDeletion { number: usize },
     StopWordsAddition { number: usize },
     StopWordsDeletion { number: usize },
+    Decompounding { number: usize },
+    AttributesTypes { number: usize },
+    SettingsPatch { number: usize },
+    WordsLimits { number: usize },
+    SearchableAttributes { number: usize },
+    Migration { number: usize },
+    TokenFilters { number: usize },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -177,6 +392,12 @@ pub struct ProcessedUpdateResult {
     pub update_type: UpdateType,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub error: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub duplicates: Vec<DuplicateDocument>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub collisions: Vec<IdentifierCollision>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub coercions: Option<CoercionReport>,
     pub duration: f64, // in seconds
     pub enqueued_at: DateTime<Utc>,
     pub processed_at: DateTime<Utc>,
@@ -240,15 +461,23 @@ pub fn next_update_id(
     Ok(new_update_id)
 }
 
+/// Applies the update and returns its result along with the ids
+/// of the documents added or updated by it.
 pub fn update_task<'a, 'b>(
     writer: &'a mut heed::RwTxn<'b>,
     index: store::Index,
     update_id: u64,
     update: Update,
-) -> MResult<ProcessedUpdateResult> {
+) -> MResult<(ProcessedUpdateResult, Vec<DocumentId>)> {
     debug!("Processing update number {}", update_id);
 
     let Update { enqueued_at, data } = update;
+    let mut documents_ids = Vec::new();
+    let mut duplicates = Vec::new();
+    let mut collisions = Vec::new();
+    let mut coercions = None;
+    // the number of documents, their size and the time spent to rebuild the words FST
+    let mut indexing = None;
 
     let (update_type, result, duration) = match data {
         UpdateData::ClearAll => {
@@ -262,6 +491,8 @@ pub fn update_task<'a, 'b>(
                 index.documents_fields_counts,
                 index.postings_lists,
                 index.docs_words,
+                index.documents_hashes,
+                index.documents_ids,
             );
 
             (update_type, result, start.elapsed())
@@ -290,11 +521,18 @@ pub fn update_task<'a, 'b>(
 
             (update_type, result, start.elapsed())
         }
-        UpdateData::DocumentsAddition(documents) => {
+        UpdateData::DocumentsAddition {
+            documents,
+            duplicates_policy,
+            identifiers_verification,
+            identifiers_generation,
+        } => {
+            let number_of_documents = documents.len();
+            let bytes_indexed = indexing_stats::documents_bytes(&documents);
             let start = Instant::now();
 
             let update_type = UpdateType::DocumentsAddition {
-                number: documents.len(),
+                number: number_of_documents,
             };
 
             let result = apply_documents_addition(
@@ -304,16 +542,37 @@ pub fn update_task<'a, 'b>(
                 index.documents_fields_counts,
                 index.postings_lists,
                 index.docs_words,
+                index.documents_hashes,
+                index.documents_ids,
                 documents,
-            );
+                duplicates_policy,
+                identifiers_verification,
+                identifiers_generation,
+            )
+            .map(|(found, shared, report, words_fst_duration, ids)| {
+                duplicates = found;
+                documents_ids = ids;
+                collisions = shared;
+                if !report.is_empty() {
+                    coercions = Some(report);
+                }
+                indexing = Some((number_of_documents, bytes_indexed, words_fst_duration));
+            });
 
             (update_type, result, start.elapsed())
         }
-        UpdateData::DocumentsPartial(documents) => {
+        UpdateData::DocumentsPartial {
+            documents,
+            duplicates_policy,
+            identifiers_verification,
+            identifiers_generation,
+        } => {
+            let number_of_documents = documents.len();
+            let bytes_indexed = indexing_stats::documents_bytes(&documents);
             let start = Instant::now();
 
             let update_type = UpdateType::DocumentsPartial {
-                number: documents.len(),
+                number: number_of_documents,
             };
 
             let result = apply_documents_partial_addition(
@@ -323,16 +582,31 @@ pub fn update_task<'a, 'b>(
                 index.documents_fields_counts,
                 index.postings_lists,
                 index.docs_words,
+                index.documents_hashes,
+                index.documents_ids,
                 documents,
-            );
+                duplicates_policy,
+                identifiers_verification,
+                identifiers_generation,
+            )
+            .map(|(found, shared, report, words_fst_duration, ids)| {
+                duplicates = found;
+                documents_ids = ids;
+                collisions = shared;
+                if !report.is_empty() {
+                    coercions = Some(report);
+                }
+                indexing = Some((number_of_documents, bytes_indexed, words_fst_duration));
+            });
 
             (update_type, result, start.elapsed())
         }
         UpdateData::DocumentsDeletion(documents) => {
+            let number_of_documents = documents.len();
             let start = Instant::now();
 
             let update_type = UpdateType::DocumentsDeletion {
-                number: documents.len(),
+                number: number_of_documents,
             };
 
             let result = apply_documents_deletion(
@@ -342,8 +616,13 @@ pub fn update_task<'a, 'b>(
                 index.documents_fields_counts,
                 index.postings_lists,
                 index.docs_words,
+                index.documents_hashes,
+                index.documents_ids,
                 documents,
-            );
+            )
+            .map(|words_fst_duration| {
+                indexing = Some((number_of_documents, 0, words_fst_duration));
+            });
 
             (update_type, result, start.elapsed())
         }
@@ -354,6 +633,27 @@ pub fn update_task<'a, 'b>(
                 number: synonyms.len(),
             };
 
+            // the updates enqueued before the synonyms penalties
+            // existed are applied without any penalty
+            let synonyms = synonyms
+                .into_iter()
+                .map(|(synonym, alternatives)| {
+                    let alternatives = alternatives.into_iter().map(|a| (a, 0)).collect();
+                    (synonym, alternatives)
+                })
+                .collect();
+
+            let result = apply_synonyms_addition(writer, index.main, index.synonyms, synonyms);
+
+            (update_type, result, start.elapsed())
+        }
+        UpdateData::WeightedSynonymsAddition(synonyms) => {
+            let start = Instant::now();
+
+            let update_type = UpdateType::SynonymsAddition {
+                number: synonyms.len(),
+            };
+
             let result = apply_synonyms_addition(writer, index.main, index.synonyms, synonyms);
 
             (update_type, result, start.elapsed())
@@ -398,6 +698,125 @@ pub fn update_task<'a, 'b>(
                 stop_words,
             );
 
+            (update_type, result, start.elapsed())
+        }
+        UpdateData::Decompounding(dictionary) => {
+            let start = Instant::now();
+
+            let update_type = UpdateType::Decompounding {
+                number: dictionary.len(),
+            };
+
+            let result = apply_decompounding_update(writer, index.main, dictionary);
+
+            (update_type, result, start.elapsed())
+        }
+        UpdateData::AttributesTypes(attributes_types) => {
+            let start = Instant::now();
+
+            let update_type = UpdateType::AttributesTypes {
+                number: attributes_types.len(),
+            };
+
+            let result = apply_attributes_types_update(
+                writer,
+                index.main,
+                index.documents_fields,
+                index.documents_fields_counts,
+                index.postings_lists,
+                index.docs_words,
+                attributes_types,
+            );
+
+            (update_type, result, start.elapsed())
+        }
+        UpdateData::SettingsPatch(patch) => {
+            let start = Instant::now();
+
+            let update_type = UpdateType::SettingsPatch {
+                number: patch.len(),
+            };
+
+            let result = apply_settings_patch(writer, &index, patch);
+
+            (update_type, result, start.elapsed())
+        }
+        UpdateData::WordsLimits(words_limits) => {
+            let start = Instant::now();
+
+            let update_type = UpdateType::WordsLimits {
+                number: words_limits.len(),
+            };
+
+            let result = apply_words_limits_update(
+                writer,
+                index.main,
+                index.documents_fields,
+                index.documents_fields_counts,
+                index.postings_lists,
+                index.docs_words,
+                words_limits,
+            );
+
+            (update_type, result, start.elapsed())
+        }
+        UpdateData::TokenFilters(token_filters) => {
+            let start = Instant::now();
+
+            let update_type = UpdateType::TokenFilters {
+                number: token_filters.len(),
+            };
+
+            let result = apply_token_filters_update(
+                writer,
+                index.main,
+                index.documents_fields,
+                index.documents_fields_counts,
+                index.postings_lists,
+                index.docs_words,
+                token_filters,
+            );
+
+            (update_type, result, start.elapsed())
+        }
+        UpdateData::SearchableAttributes(attributes) => {
+            let start = Instant::now();
+
+            let update_type = UpdateType::SearchableAttributes {
+                number: attributes.as_ref().map_or(0, Vec::len),
+            };
+
+            let result = apply_searchable_attributes_update(
+                writer,
+                index.main,
+                index.documents_fields,
+                index.documents_fields_counts,
+                index.postings_lists,
+                index.docs_words,
+                attributes,
+            );
+
+            (update_type, result, start.elapsed())
+        }
+        UpdateData::Migration(schema, mapping) => {
+            let start = Instant::now();
+
+            let update_type = UpdateType::Migration {
+                number: mapping.len(),
+            };
+
+            let number_of_documents = index.main.number_of_documents(writer)? as usize;
+            let result = apply_migration(writer, &index, schema, mapping).map(
+                |(found, shared, report, words_fst_duration, _)| {
+                    duplicates = found;
+                    collisions = shared;
+                    if !report.is_empty() {
+                        coercions = Some(report);
+                    }
+                    indexing = Some((number_of_documents, 0, words_fst_duration));
+                },
+            );
+
             (update_type, result, start.elapsed())
         }
     };
@@ -407,14 +826,118 @@ pub fn update_task<'a, 'b>(
         update_id, update_type, result
     );
 
+    // the statistics are written in the nested transaction,
+    // they are discarded along with the unsuccessful updates
+    if let Some((number_of_documents, bytes_indexed, words_fst_duration)) = indexing {
+        let stats = IndexingStats::new(
+            update_id,
+            update_type.clone(),
+            number_of_documents,
+            bytes_indexed,
+            duration,
+            words_fst_duration,
+        );
+        index
+            .indexing_history
+            .push_stats(writer, update_id, &stats)?;
+    }
+
     let status = ProcessedUpdateResult {
         update_id,
         update_type,
         error: result.map_err(|e| e.to_string()).err(),
+        duplicates,
+        collisions,
+        coercions,
         duration: duration.as_secs_f64(),
         enqueued_at,
         processed_at: Utc::now(),
     };
 
-    Ok(status)
+    Ok((status, documents_ids))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_previous_documents_updates() {
+        // an addition enqueued by the previous versions
+        let json = r#"{
+            "data": { "DocumentsAddition": [{ "id": 1, "title": "Alien" }] },
+            "enqueued_at": "2019-11-01T00:00:00Z"
+        }"#;
+
+        let update: Update = serde_json::from_str(json).unwrap();
+        match update.data() {
+            UpdateData::DocumentsAddition {
+                documents,
+                duplicates_policy,
+                identifiers_verification,
+                identifiers_generation,
+            } => {
+                assert_eq!(documents.len(), 1);
+                assert_eq!(documents[0]["title"], "Alien");
+                assert_eq!(*duplicates_policy, DuplicatesPolicy::Ignore);
+                assert_eq!(*identifiers_verification, IdentifiersVerification::Ignore);
+                assert_eq!(*identifiers_generation, IdentifiersGeneration::Disabled);
+            }
+            data => panic!("unexpected update data {:?}", data),
+        }
+
+        let json = r#"{
+            "data": { "DocumentsPartial": [{ "id": 1 }, { "id": 2 }] },
+            "enqueued_at": "2019-11-01T00:00:00Z"
+        }"#;
+
+        let update: Update = serde_json::from_str(json).unwrap();
+        assert_matches!(
+            update.data().update_type(),
+            UpdateType::DocumentsPartial { number: 2 }
+        );
+
+        // the other updates did not change
+        let json = r#"{ "data": "ClearAll", "enqueued_at": "2019-11-01T00:00:00Z" }"#;
+        let update: Update = serde_json::from_str(json).unwrap();
+        assert_matches!(update.data().update_type(), UpdateType::ClearAll);
+    }
+
+    #[test]
+    fn documents_updates_roundtrip() {
+        let documents: Vec<HashMap<String, serde_json::Value>> =
+            vec![serde_json::from_str(r#"{ "id": 1 }"#).unwrap()];
+        let update = Update::documents_addition(
+            documents,
+            DuplicatesPolicy::Skip,
+            IdentifiersVerification::Indexed,
+            IdentifiersGeneration::Uuid,
+        );
+
+        let json = serde_json::to_string(&update).unwrap();
+        let update: Update = serde_json::from_str(&json).unwrap();
+        assert_matches!(
+            update.data(),
+            UpdateData::DocumentsAddition {
+                duplicates_policy: DuplicatesPolicy::Skip,
+                identifiers_verification: IdentifiersVerification::Indexed,
+                identifiers_generation: IdentifiersGeneration::Uuid,
+                ..
+            }
+        );
+
+        // the options missing from a stored update take their default values
+        let json = r#"{
+            "data": { "DocumentsAddition": { "documents": [] } },
+            "enqueued_at": "2019-11-01T00:00:00Z"
+        }"#;
+        let update: Update = serde_json::from_str(json).unwrap();
+        assert_matches!(
+            update.data(),
+            UpdateData::DocumentsAddition {
+                duplicates_policy: DuplicatesPolicy::Ignore,
+                ..
+            }
+        );
+    }
 }
@@ -0,0 +1,122 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Condvar, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::time::{Duration, Instant};
+
+#[derive(Default)]
+struct State {
+    last_update_id: Option<u64>,
+    wakers: Vec<Waker>,
+}
+
+impl State {
+    fn is_processed(&self, update_id: u64) -> bool {
+        self.last_update_id.map_or(false, |last| update_id <= last)
+    }
+}
+
+/// Notifies the waiters of an index each time its update loop has processed an update.
+///
+/// The updates are processed in order, an update is processed as soon
+/// as its id is lower than or equal to the last processed one.
+#[derive(Clone, Default)]
+pub struct ProcessedUpdates {
+    inner: Arc<(Mutex<State>, Condvar)>,
+}
+
+impl ProcessedUpdates {
+    pub(crate) fn notify(&self, update_id: u64) {
+        let (state, condvar) = &*self.inner;
+        let mut state = state.lock().unwrap();
+
+        state.last_update_id = state.last_update_id.max(Some(update_id));
+        for waker in state.wakers.drain(..) {
+            waker.wake();
+        }
+
+        condvar.notify_all();
+    }
+
+    /// Blocks until the update has been processed or the timeout is reached,
+    /// returns `false` if the timeout has been reached first.
+    pub fn wait(&self, update_id: u64, timeout: Duration) -> bool {
+        let (state, condvar) = &*self.inner;
+        let deadline = Instant::now() + timeout;
+
+        let mut state = state.lock().unwrap();
+        while !state.is_processed(update_id) {
+            let now = Instant::now();
+            if now >= deadline {
+                return false;
+            }
+
+            let (guard, _) = condvar.wait_timeout(state, deadline - now).unwrap();
+            state = guard;
+        }
+
+        true
+    }
+
+    pub fn processed(&self, update_id: u64) -> UpdateProcessed {
+        UpdateProcessed {
+            processed_updates: self.clone(),
+            update_id,
+        }
+    }
+}
+
+/// A future that resolves once the update has been processed.
+pub struct UpdateProcessed {
+    processed_updates: ProcessedUpdates,
+    update_id: u64,
+}
+
+impl Future for UpdateProcessed {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+        let (state, _) = &*self.processed_updates.inner;
+        let mut state = state.lock().unwrap();
+
+        if state.is_processed(self.update_id) {
+            return Poll::Ready(());
+        }
+
+        if !state.wakers.iter().any(|w| w.will_wake(cx.waker())) {
+            state.wakers.push(cx.waker().clone());
+        }
+
+        Poll::Pending
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn wait_timeout() {
+        let processed_updates = ProcessedUpdates::default();
+        processed_updates.notify(2);
+
+        assert!(processed_updates.wait(1, Duration::from_millis(10)));
+        assert!(processed_updates.wait(2, Duration::from_millis(10)));
+        assert!(!processed_updates.wait(3, Duration::from_millis(10)));
+    }
+
+    #[test]
+    fn wait_notified() {
+        let processed_updates = ProcessedUpdates::default();
+
+        let notifier = processed_updates.clone();
+        let handle = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(10));
+            notifier.notify(0);
+        });
+
+        assert!(processed_updates.wait(0, Duration::from_secs(10)));
+        handle.join().unwrap();
+    }
+}
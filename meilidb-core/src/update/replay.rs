@@ -0,0 +1,221 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::documents_addition::touched_documents_ids;
+use super::{apply_documents_addition, update_task, ProcessedUpdateResult, Update, UpdateData};
+use crate::operations_log::stored_documents;
+use crate::{store, DocumentId, MResult};
+use meilidb_schema::Schema;
+
+const REPLAY_MAP_SIZE: usize = 1024 * 1024 * 1024; // 1GiB
+
+static REPLAY_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// What is kept of a processed update to be able to apply it again,
+/// the payload and the state of the index it was applied on.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateReplay {
+    pub update: Update,
+    pub schema: Option<Schema>,
+    /// The documents the update replaced, updated, deleted or compared
+    /// the added documents with, as they were stored before it was applied.
+    #[serde(default)]
+    pub documents: Vec<HashMap<String, Value>>,
+}
+
+impl UpdateReplay {
+    pub fn new(
+        reader: &heed::RoTxn,
+        index: &store::Index,
+        update: Update,
+    ) -> MResult<UpdateReplay> {
+        let schema = index.main.schema(reader)?;
+
+        let documents = match &schema {
+            Some(_) => {
+                let documents_ids = touched_documents_ids_of(reader, index, update.data())?;
+                stored_documents(reader, index, &documents_ids)?
+            }
+            None => Vec::new(),
+        };
+
+        Ok(UpdateReplay {
+            update,
+            schema,
+            documents,
+        })
+    }
+}
+
+/// The documents updates only depend on the documents they modify or are compared with,
+/// the other updates are replayed without documents.
+fn touched_documents_ids_of(
+    reader: &heed::RoTxn,
+    index: &store::Index,
+    data: &UpdateData,
+) -> MResult<Vec<DocumentId>> {
+    match data {
+        UpdateData::DocumentsAddition {
+            documents,
+            identifiers_generation,
+            ..
+        }
+        | UpdateData::DocumentsPartial {
+            documents,
+            identifiers_generation,
+            ..
+        } => touched_documents_ids(
+            reader,
+            index.main,
+            index.documents_hashes,
+            documents,
+            *identifiers_generation,
+        ),
+        UpdateData::DocumentsDeletion(documents_ids) => Ok(documents_ids.clone()),
+        _ => Ok(Vec::new()),
+    }
+}
+
+/// Applies the update again on a scratch index stored in a temporary directory.
+/// The scratch index has the recorded schema, the current indexing settings of the
+/// index and the recorded documents, as they were stored before the update was applied.
+pub fn replay_update(
+    reader: &heed::RoTxn,
+    index: &store::Index,
+    update_id: u64,
+) -> MResult<Option<ProcessedUpdateResult>> {
+    let replay = match index.updates_replays.replay(reader, update_id)? {
+        Some(replay) => replay,
+        None => return Ok(None),
+    };
+
+    let path = scratch_path();
+    fs::create_dir_all(&path)?;
+
+    let result = replay_in(&path, reader, index, update_id, replay);
+
+    if let Err(e) = fs::remove_dir_all(&path) {
+        warn!("could not remove the replay directory {:?}; {}", path, e);
+    }
+
+    result.map(Some)
+}
+
+fn scratch_path() -> PathBuf {
+    let count = REPLAY_COUNT.fetch_add(1, Ordering::SeqCst);
+    let name = format!("meilidb-replay-{}-{}", process::id(), count);
+    std::env::temp_dir().join(name)
+}
+
+fn replay_in(
+    path: &Path,
+    reader: &heed::RoTxn,
+    index: &store::Index,
+    update_id: u64,
+    replay: UpdateReplay,
+) -> MResult<ProcessedUpdateResult> {
+    let env = heed::EnvOpenOptions::new()
+        .map_size(REPLAY_MAP_SIZE)
+        .max_dbs(100)
+        .open(path)?;
+
+    // the scratch index never receives updates through the update loop
+    let (sender, _receiver) = crossbeam_channel::unbounded();
+    let mut scratch = store::create(&env, "replay", sender)?;
+    scratch.customs_merge = index.customs_merge.clone();
+    scratch.customs_rename = index.customs_rename.clone();
+
+    let mut writer = env.write_txn()?;
+    copy_indexing_settings(reader, index, &mut writer, &scratch)?;
+
+    if let Some(schema) = &replay.schema {
+        scratch.main.put_schema(&mut writer, schema)?;
+    }
+
+    if !replay.documents.is_empty() {
+        apply_documents_addition(
+            &mut writer,
+            scratch.main,
+            scratch.documents_fields,
+            scratch.documents_fields_counts,
+            scratch.postings_lists,
+            scratch.docs_words,
+            scratch.documents_hashes,
+            scratch.documents_ids,
+            replay.documents,
+            Default::default(),
+            Default::default(),
+            Default::default(),
+        )?;
+    }
+
+    let result = update_task(&mut writer, scratch, update_id, replay.update);
+    writer.abort();
+
+    result.map(|(status, _)| status)
+}
+
+/// Copies the settings used when indexing from an index of another environment.
+fn copy_indexing_settings(
+    reader: &heed::RoTxn,
+    src: &store::Index,
+    writer: &mut heed::RwTxn,
+    dst: &store::Index,
+) -> MResult<()> {
+    if let Some(customs) = src.main.customs(reader)? {
+        dst.main.put_customs(writer, customs)?;
+    }
+
+    if let Some(stop_words) = src.main.stop_words_fst(reader)? {
+        dst.main.put_stop_words_fst(writer, &stop_words)?;
+    }
+
+    if let Some(decompounding) = src.main.decompounding_fst(reader)? {
+        dst.main.put_decompounding_fst(writer, &decompounding)?;
+    }
+
+    if let Some(strip_units) = src.main.strip_units(reader)? {
+        dst.main.put_strip_units(writer, strip_units)?;
+    }
+
+    if let Some(flatten) = src.main.flatten_documents(reader)? {
+        dst.main.put_flatten_documents(writer, flatten)?;
+    }
+
+    if let Some(discovery) = src.main.attributes_discovery(reader)? {
+        dst.main.put_attributes_discovery(writer, discovery)?;
+    }
+
+    if let Some(attributes_types) = src.main.attributes_types(reader)? {
+        dst.main.put_attributes_types(writer, &attributes_types)?;
+    }
+
+    if let Some(words_limits) = src.main.words_limits(reader)? {
+        dst.main.put_words_limits(writer, &words_limits)?;
+    }
+
+    if let Some(token_filters) = src.main.token_filters(reader)? {
+        dst.main.put_token_filters(writer, &token_filters)?;
+    }
+
+    if let Some(attributes) = src.main.searchable_attributes(reader)? {
+        dst.main.put_searchable_attributes(writer, &attributes)?;
+    }
+
+    if let Some(limits) = src.main.update_limits(reader)? {
+        dst.main.put_update_limits(writer, &limits)?;
+    }
+
+    if let Some(charset) = src.main.document_id_charset(reader)? {
+        dst.main.put_document_id_charset(writer, &charset)?;
+    }
+
+    Ok(())
+}
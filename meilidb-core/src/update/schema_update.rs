@@ -2,7 +2,24 @@ use meilidb_schema::{Diff, Schema};
 
 use crate::update::documents_addition::reindex_all_documents;
 use crate::update::{next_update_id, Update};
-use crate::{error::UnsupportedOperation, store, MResult};
+use crate::{error::UnsupportedOperation, store, Error, MResult};
+
+/// Refuses the schemas whose documents can never be found because none of their indexed
+/// attributes is searchable, the searchable attributes restrict them when they are given.
+pub fn check_searchable(schema: &Schema, searchable_attributes: Option<&[String]>) -> MResult<()> {
+    let is_searchable =
+        |name: &str| searchable_attributes.map_or(true, |names| names.iter().any(|n| n == name));
+
+    if schema
+        .iter()
+        .any(|(name, _, props)| props.is_indexed() && is_searchable(name))
+    {
+        return Ok(());
+    }
+
+    let attributes = schema.iter().map(|(name, _, _)| name.to_string()).collect();
+    Err(Error::NothingSearchable(attributes))
+}
 
 pub fn apply_schema_update(
     writer: &mut heed::RwTxn,
@@ -44,6 +61,12 @@ pub fn apply_schema_update(
         }
     }
 
+    let searchable_attributes = main_store.searchable_attributes(writer)?;
+    check_searchable(
+        new_schema,
+        searchable_attributes.as_ref().map(Vec::as_slice),
+    )?;
+
     main_store.put_schema(writer, new_schema)?;
 
     if need_full_reindexing {
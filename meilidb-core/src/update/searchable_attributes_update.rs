@@ -0,0 +1,61 @@
+use crate::update::documents_addition::reindex_all_documents;
+use crate::update::{check_searchable, next_update_id, Update};
+use crate::{store, MResult};
+
+pub fn apply_searchable_attributes_update(
+    writer: &mut heed::RwTxn,
+    main_store: store::Main,
+    documents_fields_store: store::DocumentsFields,
+    documents_fields_counts_store: store::DocumentsFieldsCounts,
+    postings_lists_store: store::PostingsLists,
+    docs_words_store: store::DocsWords,
+    searchable_attributes: Option<Vec<String>>,
+) -> MResult<()> {
+    if let Some(schema) = main_store.schema(writer)? {
+        check_searchable(&schema, searchable_attributes.as_ref().map(Vec::as_slice))?;
+    }
+
+    let old_searchable_attributes = main_store.searchable_attributes(writer)?;
+    match &searchable_attributes {
+        Some(attributes) => main_store.put_searchable_attributes(writer, attributes)?,
+        None => {
+            main_store.delete_searchable_attributes(writer)?;
+        }
+    }
+
+    // only the order changed, the attributes are indexed as they were
+    let sorted = |attributes: Option<Vec<String>>| {
+        attributes.map(|mut attributes| {
+            attributes.sort_unstable();
+            attributes
+        })
+    };
+
+    let need_full_reindexing = sorted(old_searchable_attributes) != sorted(searchable_attributes);
+    if need_full_reindexing && main_store.schema(writer)?.is_some() {
+        reindex_all_documents(
+            writer,
+            main_store,
+            documents_fields_store,
+            documents_fields_counts_store,
+            postings_lists_store,
+            docs_words_store,
+        )?
+    }
+
+    Ok(())
+}
+
+pub fn push_searchable_attributes_update(
+    writer: &mut heed::RwTxn,
+    updates_store: store::Updates,
+    updates_results_store: store::UpdatesResults,
+    searchable_attributes: Option<Vec<String>>,
+) -> MResult<u64> {
+    let last_update_id = next_update_id(writer, updates_store, updates_results_store)?;
+
+    let update = Update::searchable_attributes(searchable_attributes);
+    updates_store.put_update(writer, last_update_id, &update)?;
+
+    Ok(last_update_id)
+}
@@ -0,0 +1,292 @@
+use std::cmp;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+
+use arc_swap::ArcSwapOption;
+use fst::Streamer;
+use serde::{Deserialize, Serialize};
+
+use crate::automaton::normalize_str;
+use crate::database::{UpdateEvent, UpdateEventsEmitter};
+use crate::update::{
+    apply_customs_update, apply_stop_words_addition, apply_stop_words_deletion,
+    apply_synonyms_addition, apply_synonyms_deletion, next_update_id, Update, MAX_SYNONYM_PENALTY,
+};
+use crate::{store, Error, MResult};
+
+pub type BoxCustomsMergeFn =
+    Box<dyn Fn(Option<&[u8]>, &[u8]) -> Result<Vec<u8>, String> + Send + Sync + 'static>;
+
+/// How the customs of a settings patch are merged into the current customs of the index,
+/// the customs are opaque to the database and replaced as a whole when no merge is set.
+#[derive(Clone, Default)]
+pub(crate) struct CustomsMerge {
+    inner: Arc<ArcSwapOption<BoxCustomsMergeFn>>,
+}
+
+impl CustomsMerge {
+    pub(crate) fn set(&self, merge_fn: BoxCustomsMergeFn) {
+        self.inner.swap(Some(Arc::new(merge_fn)));
+    }
+
+    fn merge(&self, current: Option<&[u8]>, patch: &[u8]) -> MResult<Vec<u8>> {
+        match *self.inner.load() {
+            Some(ref merge_fn) => merge_fn(current, patch).map_err(Error::CustomsMerge),
+            None => Ok(patch.to_vec()),
+        }
+    }
+}
+
+/// The changes of a settings patch, they are applied to the settings
+/// as they are when the update is processed, not when it is enqueued.
+///
+/// The same content can be enqueued to many indexes with `Database::apply_settings_to`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SettingsPatchContent {
+    /// Merged into the current customs when the patch is processed.
+    pub customs: Option<Vec<u8>>,
+    pub stop_words_addition: BTreeSet<String>,
+    pub stop_words_deletion: BTreeSet<String>,
+    pub synonyms_addition: BTreeMap<String, Vec<(String, u8)>>,
+    pub synonyms_deletion: BTreeMap<String, Option<Vec<String>>>,
+}
+
+impl SettingsPatchContent {
+    /// The number of changes in this patch.
+    pub fn len(&self) -> usize {
+        usize::from(self.customs.is_some())
+            + self.stop_words_addition.len()
+            + self.stop_words_deletion.len()
+            + self.synonyms_addition.len()
+            + self.synonyms_deletion.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The customs are merged into the current ones with the merge set on the database.
+    pub fn merge_customs(&mut self, customs: Vec<u8>) {
+        self.customs = Some(customs);
+    }
+
+    pub fn add_stop_word<S: AsRef<str>>(&mut self, stop_word: S) {
+        let stop_word = normalize_str(stop_word.as_ref());
+        self.stop_words_addition.insert(stop_word);
+    }
+
+    pub fn delete_stop_word<S: AsRef<str>>(&mut self, stop_word: S) {
+        let stop_word = normalize_str(stop_word.as_ref());
+        self.stop_words_deletion.insert(stop_word);
+    }
+
+    /// Adds alternatives to the ones the synonym already has.
+    pub fn add_synonym<S, T, I>(&mut self, synonym: S, alternatives: I)
+    where
+        S: AsRef<str>,
+        T: AsRef<str>,
+        I: IntoIterator<Item = T>,
+    {
+        let alternatives = alternatives.into_iter().map(|s| (s, 0));
+        self.add_weighted_synonym(synonym, alternatives)
+    }
+
+    /// Adds alternatives along with a penalty to the ones the synonym already has,
+    /// the given penalty replaces the one of an alternative the synonym already has.
+    pub fn add_weighted_synonym<S, T, I>(&mut self, synonym: S, alternatives: I)
+    where
+        S: AsRef<str>,
+        T: AsRef<str>,
+        I: IntoIterator<Item = (T, u8)>,
+    {
+        let synonym = normalize_str(synonym.as_ref());
+        let alternatives = alternatives.into_iter().map(|(s, penalty)| {
+            let penalty = cmp::min(penalty, MAX_SYNONYM_PENALTY);
+            (s.as_ref().to_lowercase(), penalty)
+        });
+        self.synonyms_addition
+            .entry(synonym)
+            .or_insert_with(Vec::new)
+            .extend(alternatives);
+    }
+
+    pub fn delete_all_alternatives_of<S: AsRef<str>>(&mut self, synonym: S) {
+        let synonym = normalize_str(synonym.as_ref());
+        self.synonyms_deletion.insert(synonym, None);
+    }
+
+    pub fn delete_specific_alternatives_of<S, T, I>(&mut self, synonym: S, alternatives: I)
+    where
+        S: AsRef<str>,
+        T: AsRef<str>,
+        I: IntoIterator<Item = T>,
+    {
+        let synonym = normalize_str(synonym.as_ref());
+        let value = self.synonyms_deletion.entry(synonym).or_insert(None);
+        let alternatives = alternatives.into_iter().map(|s| s.as_ref().to_lowercase());
+        match value {
+            Some(v) => v.extend(alternatives),
+            None => *value = Some(alternatives.collect()),
+        }
+    }
+}
+
+/// Changes some of the settings in a single update, the stop words and the synonyms
+/// that are not mentioned are left untouched, unlike with the whole lists replacement.
+pub struct SettingsPatch {
+    updates_store: store::Updates,
+    updates_results_store: store::UpdatesResults,
+    updates_notifier: UpdateEventsEmitter,
+    content: SettingsPatchContent,
+}
+
+impl SettingsPatch {
+    pub fn new(
+        updates_store: store::Updates,
+        updates_results_store: store::UpdatesResults,
+        updates_notifier: UpdateEventsEmitter,
+    ) -> SettingsPatch {
+        SettingsPatch {
+            updates_store,
+            updates_results_store,
+            updates_notifier,
+            content: SettingsPatchContent::default(),
+        }
+    }
+
+    pub fn merge_customs(&mut self, customs: Vec<u8>) {
+        self.content.merge_customs(customs)
+    }
+
+    pub fn add_stop_word<S: AsRef<str>>(&mut self, stop_word: S) {
+        self.content.add_stop_word(stop_word)
+    }
+
+    pub fn delete_stop_word<S: AsRef<str>>(&mut self, stop_word: S) {
+        self.content.delete_stop_word(stop_word)
+    }
+
+    /// Adds alternatives to the ones the synonym already has.
+    pub fn add_synonym<S, T, I>(&mut self, synonym: S, alternatives: I)
+    where
+        S: AsRef<str>,
+        T: AsRef<str>,
+        I: IntoIterator<Item = T>,
+    {
+        self.content.add_synonym(synonym, alternatives)
+    }
+
+    pub fn add_weighted_synonym<S, T, I>(&mut self, synonym: S, alternatives: I)
+    where
+        S: AsRef<str>,
+        T: AsRef<str>,
+        I: IntoIterator<Item = (T, u8)>,
+    {
+        self.content.add_weighted_synonym(synonym, alternatives)
+    }
+
+    pub fn delete_all_alternatives_of<S: AsRef<str>>(&mut self, synonym: S) {
+        self.content.delete_all_alternatives_of(synonym)
+    }
+
+    pub fn delete_specific_alternatives_of<S, T, I>(&mut self, synonym: S, alternatives: I)
+    where
+        S: AsRef<str>,
+        T: AsRef<str>,
+        I: IntoIterator<Item = T>,
+    {
+        self.content
+            .delete_specific_alternatives_of(synonym, alternatives)
+    }
+
+    pub fn finalize(self, writer: &mut heed::RwTxn) -> MResult<u64> {
+        let _ = self.updates_notifier.send(UpdateEvent::NewUpdate);
+        let update_id = push_settings_patch(
+            writer,
+            self.updates_store,
+            self.updates_results_store,
+            self.content,
+        )?;
+        Ok(update_id)
+    }
+}
+
+pub fn push_settings_patch(
+    writer: &mut heed::RwTxn,
+    updates_store: store::Updates,
+    updates_results_store: store::UpdatesResults,
+    patch: SettingsPatchContent,
+) -> MResult<u64> {
+    let last_update_id = next_update_id(writer, updates_store, updates_results_store)?;
+
+    let update = Update::settings_patch(patch);
+    updates_store.put_update(writer, last_update_id, &update)?;
+
+    Ok(last_update_id)
+}
+
+/// Applies the removals before the additions, a stop word or an alternative
+/// that is both removed and added by the same patch is therefore kept.
+pub fn apply_settings_patch(
+    writer: &mut heed::RwTxn,
+    index: &store::Index,
+    patch: SettingsPatchContent,
+) -> MResult<()> {
+    if let Some(customs) = patch.customs {
+        let current = index.main.customs(writer)?;
+        let customs = index.customs_merge.merge(current, &customs)?;
+        apply_customs_update(writer, index.main, &customs)?;
+    }
+
+    if !patch.stop_words_deletion.is_empty() {
+        apply_stop_words_deletion(
+            writer,
+            index.main,
+            index.documents_fields,
+            index.documents_fields_counts,
+            index.postings_lists,
+            index.docs_words,
+            patch.stop_words_deletion,
+        )?;
+    }
+
+    if !patch.stop_words_addition.is_empty() {
+        apply_stop_words_addition(
+            writer,
+            index.main,
+            index.postings_lists,
+            patch.stop_words_addition,
+        )?;
+    }
+
+    if !patch.synonyms_deletion.is_empty() {
+        apply_synonyms_deletion(writer, index.main, index.synonyms, patch.synonyms_deletion)?;
+    }
+
+    if !patch.synonyms_addition.is_empty() {
+        // the alternatives are added to the existing ones instead of replacing them,
+        // the penalties given by the patch replace the ones of the existing alternatives
+        let mut addition = patch.synonyms_addition;
+        for (synonym, alternatives) in addition.iter_mut() {
+            if let Some(existing) = index
+                .synonyms
+                .weighted_synonyms(writer, synonym.as_bytes())?
+            {
+                let patched: BTreeSet<_> = alternatives.iter().map(|(a, _)| a.clone()).collect();
+                let mut stream = existing.stream();
+                while let Some((alternative, penalty)) = stream.next() {
+                    let alternative = String::from_utf8_lossy(alternative).into_owned();
+                    if patched.contains(&alternative) {
+                        continue;
+                    }
+                    let penalty = cmp::min(penalty, u64::from(MAX_SYNONYM_PENALTY)) as u8;
+                    alternatives.push((alternative, penalty));
+                }
+            }
+        }
+
+        apply_synonyms_addition(writer, index.main, index.synonyms, addition)?;
+    }
+
+    Ok(())
+}
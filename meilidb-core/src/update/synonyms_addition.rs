@@ -1,7 +1,7 @@
+use std::cmp;
 use std::collections::BTreeMap;
 
-use fst::{set::OpBuilder, SetBuilder};
-use sdset::SetBuf;
+use fst::{set::OpBuilder, MapBuilder, SetBuilder};
 
 use crate::automaton::normalize_str;
 use crate::database::{UpdateEvent, UpdateEventsEmitter};
@@ -12,9 +12,13 @@ pub struct SynonymsAddition {
     updates_store: store::Updates,
     updates_results_store: store::UpdatesResults,
     updates_notifier: UpdateEventsEmitter,
-    synonyms: BTreeMap<String, Vec<String>>,
+    synonyms: BTreeMap<String, Vec<(String, u8)>>,
 }
 
+/// The highest penalty an alternative can have, a match of an
+/// alternative is considered as having this number of typos.
+pub const MAX_SYNONYM_PENALTY: u8 = 3;
+
 impl SynonymsAddition {
     pub fn new(
         updates_store: store::Updates,
@@ -34,9 +38,26 @@ impl SynonymsAddition {
         S: AsRef<str>,
         T: AsRef<str>,
         I: IntoIterator<Item = T>,
+    {
+        let alternatives = alternatives.into_iter().map(|s| (s, 0));
+        self.add_weighted_synonym(synonym, alternatives)
+    }
+
+    /// Adds alternatives along with a penalty, the matches of an alternative
+    /// with a penalty rank as if they had this number of typos, which makes
+    /// them rank after the matches of the original word and of the alternatives
+    /// without penalty. The penalty is capped to [`MAX_SYNONYM_PENALTY`].
+    pub fn add_weighted_synonym<S, T, I>(&mut self, synonym: S, alternatives: I)
+    where
+        S: AsRef<str>,
+        T: AsRef<str>,
+        I: IntoIterator<Item = (T, u8)>,
     {
         let synonym = normalize_str(synonym.as_ref());
-        let alternatives = alternatives.into_iter().map(|s| s.as_ref().to_lowercase());
+        let alternatives = alternatives.into_iter().map(|(s, penalty)| {
+            let penalty = cmp::min(penalty, MAX_SYNONYM_PENALTY);
+            (s.as_ref().to_lowercase(), penalty)
+        });
         self.synonyms
             .entry(synonym)
             .or_insert_with(Vec::new)
@@ -59,7 +80,7 @@ pub fn push_synonyms_addition(
     writer: &mut heed::RwTxn,
     updates_store: store::Updates,
     updates_results_store: store::UpdatesResults,
-    addition: BTreeMap<String, Vec<String>>,
+    addition: BTreeMap<String, Vec<(String, u8)>>,
 ) -> MResult<u64> {
     let last_update_id = next_update_id(writer, updates_store, updates_results_store)?;
 
@@ -73,22 +94,29 @@ pub fn apply_synonyms_addition(
     writer: &mut heed::RwTxn,
     main_store: store::Main,
     synonyms_store: store::Synonyms,
-    addition: BTreeMap<String, Vec<String>>,
+    addition: BTreeMap<String, Vec<(String, u8)>>,
 ) -> MResult<()> {
     let mut synonyms_builder = SetBuilder::memory();
 
-    for (word, alternatives) in addition {
+    for (word, mut alternatives) in addition {
         synonyms_builder.insert(&word).unwrap();
 
+        // an alternative given multiple times keeps its lowest penalty
+        alternatives.sort_unstable();
+        alternatives.dedup_by(|(a, _), (b, _)| a == b);
+
         let alternatives = {
-            let alternatives = SetBuf::from_dirty(alternatives);
-            let mut alternatives_builder = SetBuilder::memory();
-            alternatives_builder.extend_iter(alternatives).unwrap();
+            let mut alternatives_builder = MapBuilder::memory();
+            for (alternative, penalty) in alternatives {
+                alternatives_builder
+                    .insert(alternative, u64::from(penalty))
+                    .unwrap();
+            }
             let bytes = alternatives_builder.into_inner().unwrap();
-            fst::Set::from_bytes(bytes).unwrap()
+            fst::Map::from_bytes(bytes).unwrap()
         };
 
-        synonyms_store.put_synonyms(writer, word.as_bytes(), &alternatives)?;
+        synonyms_store.put_weighted_synonyms(writer, word.as_bytes(), &alternatives)?;
     }
 
     let delta_synonyms = synonyms_builder
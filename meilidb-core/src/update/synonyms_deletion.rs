@@ -1,7 +1,7 @@
 use std::collections::BTreeMap;
 use std::iter::FromIterator;
 
-use fst::{set::OpBuilder, SetBuilder};
+use fst::{map, set::OpBuilder, MapBuilder, SetBuilder, Streamer};
 use sdset::SetBuf;
 
 use crate::automaton::normalize_str;
@@ -87,7 +87,8 @@ pub fn apply_synonyms_deletion(
     for (synonym, alternatives) in deletion {
         match alternatives {
             Some(alternatives) => {
-                let prev_alternatives = synonyms_store.synonyms(writer, synonym.as_bytes())?;
+                let prev_alternatives =
+                    synonyms_store.weighted_synonyms(writer, synonym.as_bytes())?;
                 let prev_alternatives = match prev_alternatives {
                     Some(alternatives) => alternatives,
                     None => continue,
@@ -95,31 +96,40 @@ pub fn apply_synonyms_deletion(
 
                 let delta_alternatives = {
                     let alternatives = SetBuf::from_dirty(alternatives);
-                    let mut builder = SetBuilder::memory();
-                    builder.extend_iter(alternatives).unwrap();
-                    builder.into_inner().and_then(fst::Set::from_bytes).unwrap()
+                    let mut builder = MapBuilder::memory();
+                    for alternative in alternatives {
+                        builder.insert(alternative, 0).unwrap();
+                    }
+                    builder.into_inner().and_then(fst::Map::from_bytes).unwrap()
                 };
 
-                let op = OpBuilder::new()
+                // the remaining alternatives keep their penalties
+                let mut op = map::OpBuilder::new()
                     .add(prev_alternatives.stream())
                     .add(delta_alternatives.stream())
                     .difference();
 
                 let (alternatives, empty_alternatives) = {
-                    let mut builder = SetBuilder::memory();
-                    let len = builder.get_ref().len();
-                    builder.extend_stream(op).unwrap();
-                    let is_empty = len == builder.get_ref().len();
+                    let mut builder = MapBuilder::memory();
+                    let mut count = 0;
+                    while let Some((alternative, values)) = op.next() {
+                        builder.insert(alternative, values[0].value).unwrap();
+                        count += 1;
+                    }
                     let bytes = builder.into_inner().unwrap();
-                    let alternatives = fst::Set::from_bytes(bytes).unwrap();
+                    let alternatives = fst::Map::from_bytes(bytes).unwrap();
 
-                    (alternatives, is_empty)
+                    (alternatives, count == 0)
                 };
 
                 if empty_alternatives {
                     delete_whole_synonym_builder.insert(synonym.as_bytes())?;
                 } else {
-                    synonyms_store.put_synonyms(writer, synonym.as_bytes(), &alternatives)?;
+                    synonyms_store.put_weighted_synonyms(
+                        writer,
+                        synonym.as_bytes(),
+                        &alternatives,
+                    )?;
                 }
             }
             None => {
@@ -0,0 +1,50 @@
+use crate::token_filters::{TokenFilter, TokenFilterChain};
+use crate::update::documents_addition::reindex_all_documents;
+use crate::update::{next_update_id, Update};
+use crate::{store, MResult};
+
+pub fn apply_token_filters_update(
+    writer: &mut heed::RwTxn,
+    main_store: store::Main,
+    documents_fields_store: store::DocumentsFields,
+    documents_fields_counts_store: store::DocumentsFieldsCounts,
+    postings_lists_store: store::PostingsLists,
+    docs_words_store: store::DocsWords,
+    token_filters: Vec<TokenFilter>,
+) -> MResult<()> {
+    // the custom filters must be registered before the index refers to them
+    TokenFilterChain::resolve(&token_filters)?;
+
+    let old_token_filters = main_store.token_filters(writer)?;
+    main_store.put_token_filters(writer, &token_filters)?;
+
+    // the words are indexed as they were analyzed, they must
+    // all be analyzed again from the stored fields
+    let need_full_reindexing = old_token_filters.as_ref() != Some(&token_filters);
+    if need_full_reindexing && main_store.schema(writer)?.is_some() {
+        reindex_all_documents(
+            writer,
+            main_store,
+            documents_fields_store,
+            documents_fields_counts_store,
+            postings_lists_store,
+            docs_words_store,
+        )?
+    }
+
+    Ok(())
+}
+
+pub fn push_token_filters_update(
+    writer: &mut heed::RwTxn,
+    updates_store: store::Updates,
+    updates_results_store: store::UpdatesResults,
+    token_filters: Vec<TokenFilter>,
+) -> MResult<u64> {
+    let last_update_id = next_update_id(writer, updates_store, updates_results_store)?;
+
+    let update = Update::token_filters(token_filters);
+    updates_store.put_update(writer, last_update_id, &update)?;
+
+    Ok(last_update_id)
+}
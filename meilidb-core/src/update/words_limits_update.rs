@@ -0,0 +1,47 @@
+use crate::raw_indexer::WordsLimits;
+use crate::update::documents_addition::reindex_all_documents;
+use crate::update::{next_update_id, Update};
+use crate::{store, MResult};
+
+pub fn apply_words_limits_update(
+    writer: &mut heed::RwTxn,
+    main_store: store::Main,
+    documents_fields_store: store::DocumentsFields,
+    documents_fields_counts_store: store::DocumentsFieldsCounts,
+    postings_lists_store: store::PostingsLists,
+    docs_words_store: store::DocsWords,
+    words_limits: WordsLimits,
+) -> MResult<()> {
+    let old_words_limits = main_store.words_limits(writer)?.unwrap_or_default();
+    main_store.put_words_limits(writer, &words_limits)?;
+
+    // the documents are stored entirely, the words that were
+    // past the old limits can be indexed again from the stored fields
+    let need_full_reindexing = old_words_limits != words_limits;
+    if need_full_reindexing && main_store.schema(writer)?.is_some() {
+        reindex_all_documents(
+            writer,
+            main_store,
+            documents_fields_store,
+            documents_fields_counts_store,
+            postings_lists_store,
+            docs_words_store,
+        )?
+    }
+
+    Ok(())
+}
+
+pub fn push_words_limits_update(
+    writer: &mut heed::RwTxn,
+    updates_store: store::Updates,
+    updates_results_store: store::UpdatesResults,
+    words_limits: WordsLimits,
+) -> MResult<u64> {
+    let last_update_id = next_update_id(writer, updates_store, updates_results_store)?;
+
+    let update = Update::words_limits(words_limits);
+    updates_store.put_update(writer, last_update_id, &update)?;
+
+    Ok(last_update_id)
+}
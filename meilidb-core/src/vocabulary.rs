@@ -0,0 +1,370 @@
+use std::io::Write;
+use std::mem;
+use std::str::FromStr;
+
+use fst::Streamer;
+use serde::Serialize;
+use slice_group_by::GroupBy;
+
+use crate::{store, DocIndex, MResult};
+
+/// The number of words listed by the postings statistics, the ones with the largest lists.
+const LARGEST_WORDS: usize = 10;
+
+/// The formats in which the vocabulary of an index can be exported.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum VocabularyFormat {
+    /// A `word,document_frequency,average_position` header followed by one line per word.
+    Csv,
+    /// A JSON array of objects with the `word`, `documentFrequency` and `averagePosition` keys.
+    Json,
+}
+
+impl FromStr for VocabularyFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<VocabularyFormat, String> {
+        match s {
+            "csv" => Ok(VocabularyFormat::Csv),
+            "json" => Ok(VocabularyFormat::Json),
+            other => Err(format!("unknown vocabulary format {:?}", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WordStats {
+    pub word: String,
+    /// The number of documents in which the word appears.
+    pub document_frequency: usize,
+    /// The average index of the word in the attributes it appears in.
+    pub average_position: f64,
+}
+
+/// The statistics of the postings lists of the words matching a pattern.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PostingsStats {
+    /// The number of words matching the pattern.
+    pub words: usize,
+    /// The number of postings of all the words.
+    pub postings: usize,
+    pub min_postings: usize,
+    pub max_postings: usize,
+    pub average_postings: f64,
+    /// The number of bytes the postings lists take in the store.
+    pub total_bytes: usize,
+    /// The words with the largest postings lists, the largest first.
+    pub largest: Vec<WordPostings>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct WordPostings {
+    pub word: String,
+    pub postings: usize,
+}
+
+/// Whether the word matches the pattern, a `*` matches any sequence of bytes.
+fn matches_pattern(pattern: &[u8], word: &[u8]) -> bool {
+    let (mut p, mut w) = (0, 0);
+    let mut backtrack = None;
+
+    while w < word.len() {
+        if p < pattern.len() && pattern[p] == b'*' {
+            backtrack = Some((p, w));
+            p += 1;
+        } else if p < pattern.len() && pattern[p] == word[w] {
+            p += 1;
+            w += 1;
+        } else if let Some((star, matched)) = backtrack {
+            p = star + 1;
+            w = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|b| *b == b'*')
+}
+
+/// Computes the statistics of the postings lists of the words matching the pattern,
+/// a pattern without `*` is a prefix. Only the words starting with the part of the
+/// pattern before the first `*` are read from the words fst.
+pub fn postings_stats(
+    reader: &heed::RoTxn,
+    index: &store::Index,
+    pattern: &str,
+) -> MResult<PostingsStats> {
+    let pattern = if pattern.contains('*') {
+        pattern.to_owned()
+    } else {
+        format!("{}*", pattern)
+    };
+    let prefix = pattern.split('*').next().unwrap_or_default().as_bytes();
+
+    let words = index.main.words_fst(reader)?.unwrap_or_default();
+
+    let mut stats = PostingsStats {
+        words: 0,
+        postings: 0,
+        min_postings: 0,
+        max_postings: 0,
+        average_postings: 0.0,
+        total_bytes: 0,
+        largest: Vec::new(),
+    };
+
+    let mut stream = words.range().ge(prefix).into_stream();
+    while let Some(word) = stream.next() {
+        if !word.starts_with(prefix) {
+            break;
+        }
+
+        if !matches_pattern(pattern.as_bytes(), word) {
+            continue;
+        }
+
+        let postings = match index.postings_lists.postings_list(reader, word)? {
+            Some(postings_list) => postings_list.len(),
+            None => continue,
+        };
+
+        if stats.words == 0 || postings < stats.min_postings {
+            stats.min_postings = postings;
+        }
+        stats.max_postings = stats.max_postings.max(postings);
+        stats.words += 1;
+        stats.postings += postings;
+        stats.total_bytes += postings * mem::size_of::<DocIndex>();
+
+        stats.largest.push(WordPostings {
+            word: String::from_utf8_lossy(word).into_owned(),
+            postings,
+        });
+        if stats.largest.len() > LARGEST_WORDS {
+            // the sort is stable, the words with the same number of postings stay sorted
+            stats.largest.sort_by(|a, b| b.postings.cmp(&a.postings));
+            stats.largest.truncate(LARGEST_WORDS);
+        }
+    }
+
+    stats.largest.sort_by(|a, b| b.postings.cmp(&a.postings));
+    if stats.words != 0 {
+        stats.average_postings = stats.postings as f64 / stats.words as f64;
+    }
+
+    Ok(stats)
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains(|c| c == ',' || c == '"' || c == '\n' || c == '\r') {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+/// Writes the statistics of the words of the index in the given format, the words
+/// are streamed from the words fst one at a time and written in lexicographic order.
+///
+/// Returns the number of words written.
+pub fn export_vocabulary<W: Write>(
+    reader: &heed::RoTxn,
+    index: &store::Index,
+    mut writer: W,
+    format: VocabularyFormat,
+) -> MResult<usize> {
+    let words = index.main.words_fst(reader)?.unwrap_or_default();
+
+    match format {
+        VocabularyFormat::Csv => writeln!(writer, "word,document_frequency,average_position")?,
+        VocabularyFormat::Json => write!(writer, "[")?,
+    }
+
+    let mut count = 0;
+    let mut stream = words.stream();
+    while let Some(word) = stream.next() {
+        let postings_list = match index.postings_lists.postings_list(reader, word)? {
+            Some(postings_list) => postings_list,
+            None => continue,
+        };
+
+        if postings_list.is_empty() {
+            continue;
+        }
+
+        // the postings lists are ordered by document id
+        let document_frequency = postings_list.linear_group_by_key(|d| d.document_id).count();
+        let positions_sum: u64 = postings_list.iter().map(|d| u64::from(d.word_index)).sum();
+        let average_position = positions_sum as f64 / postings_list.len() as f64;
+
+        let stats = WordStats {
+            word: String::from_utf8_lossy(word).into_owned(),
+            document_frequency,
+            average_position,
+        };
+
+        match format {
+            VocabularyFormat::Csv => writeln!(
+                writer,
+                "{},{},{}",
+                csv_field(&stats.word),
+                stats.document_frequency,
+                stats.average_position
+            )?,
+            VocabularyFormat::Json => {
+                if count != 0 {
+                    write!(writer, ",")?;
+                }
+                serde_json::to_writer(&mut writer, &stats)?;
+            }
+        }
+
+        count += 1;
+    }
+
+    if let VocabularyFormat::Json = format {
+        writeln!(writer, "]")?;
+    }
+
+    writer.flush()?;
+
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::database::Database;
+    use crate::{DocIndex, DocumentId};
+    use sdset::SetBuf;
+
+    fn doc_index(document_id: u64, word_index: u16) -> DocIndex {
+        DocIndex {
+            document_id: DocumentId(document_id),
+            attribute: 0,
+            word_index,
+            char_index: 0,
+            char_length: 0,
+        }
+    }
+
+    #[test]
+    fn export_csv_and_json() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let database = Database::open_or_create(dir.path()).unwrap();
+        let env = &database.env;
+        let index = database.create_index("test").unwrap();
+
+        let mut writer = env.write_txn().unwrap();
+
+        let words = fst::Set::from_iter(vec!["hello", "wo,rld"]).unwrap();
+        index.main.put_words_fst(&mut writer, &words).unwrap();
+
+        let hello = SetBuf::from_dirty(vec![doc_index(0, 0), doc_index(0, 4), doc_index(1, 2)]);
+        let world = SetBuf::from_dirty(vec![doc_index(1, 1)]);
+        let postings_lists = index.postings_lists;
+        postings_lists
+            .put_postings_list(&mut writer, b"hello", &hello)
+            .unwrap();
+        postings_lists
+            .put_postings_list(&mut writer, b"wo,rld", &world)
+            .unwrap();
+
+        writer.commit().unwrap();
+
+        let reader = env.read_txn().unwrap();
+
+        let mut csv = Vec::new();
+        let count = export_vocabulary(&reader, &index, &mut csv, VocabularyFormat::Csv).unwrap();
+        assert_eq!(count, 2);
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "word,document_frequency,average_position\nhello,2,2\n\"wo,rld\",1,1\n"
+        );
+
+        let mut json = Vec::new();
+        export_vocabulary(&reader, &index, &mut json, VocabularyFormat::Json).unwrap();
+        let value: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!([
+                { "word": "hello", "documentFrequency": 2, "averagePosition": 2.0 },
+                { "word": "wo,rld", "documentFrequency": 1, "averagePosition": 1.0 },
+            ])
+        );
+    }
+
+    #[test]
+    fn words_patterns() {
+        assert!(matches_pattern(b"he*", b"hello"));
+        assert!(matches_pattern(b"*llo", b"hello"));
+        assert!(matches_pattern(b"h*l*o", b"hello"));
+        assert!(matches_pattern(b"*", b""));
+        assert!(!matches_pattern(b"he*x", b"hello"));
+        assert!(!matches_pattern(b"hello", b"hell"));
+    }
+
+    #[test]
+    fn postings_statistics() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let database = Database::open_or_create(dir.path()).unwrap();
+        let env = &database.env;
+        let index = database.create_index("test").unwrap();
+
+        let mut writer = env.write_txn().unwrap();
+
+        let words = fst::Set::from_iter(vec!["aGVsbG8", "hello", "help", "world"]).unwrap();
+        index.main.put_words_fst(&mut writer, &words).unwrap();
+
+        let postings_lists = index.postings_lists;
+        let lists = vec![
+            ("aGVsbG8", SetBuf::from_dirty(vec![doc_index(0, 0)])),
+            (
+                "hello",
+                SetBuf::from_dirty(vec![doc_index(0, 1), doc_index(1, 2)]),
+            ),
+            (
+                "help",
+                SetBuf::from_dirty(vec![doc_index(0, 3), doc_index(1, 0), doc_index(2, 0)]),
+            ),
+            ("world", SetBuf::from_dirty(vec![doc_index(1, 1)])),
+        ];
+        for (word, list) in &lists {
+            postings_lists
+                .put_postings_list(&mut writer, word.as_bytes(), list)
+                .unwrap();
+        }
+
+        writer.commit().unwrap();
+
+        let reader = env.read_txn().unwrap();
+
+        let stats = postings_stats(&reader, &index, "hel").unwrap();
+        assert_eq!(stats.words, 2);
+        assert_eq!(stats.postings, 5);
+        assert_eq!(stats.min_postings, 2);
+        assert_eq!(stats.max_postings, 3);
+        assert_eq!(stats.average_postings, 2.5);
+        assert_eq!(stats.total_bytes, 5 * mem::size_of::<DocIndex>());
+        let largest: Vec<_> = stats.largest.iter().map(|w| w.word.as_str()).collect();
+        assert_eq!(largest, vec!["help", "hello"]);
+
+        let stats = postings_stats(&reader, &index, "*l*").unwrap();
+        assert_eq!(stats.words, 3);
+
+        let stats = postings_stats(&reader, &index, "").unwrap();
+        assert_eq!(stats.words, 4);
+        assert_eq!(stats.postings, 7);
+
+        let stats = postings_stats(&reader, &index, "x").unwrap();
+        assert_eq!(stats.words, 0);
+        assert_eq!(stats.min_postings, 0);
+        assert_eq!(stats.average_postings, 0.0);
+    }
+}
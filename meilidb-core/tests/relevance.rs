@@ -0,0 +1,179 @@
+//! Checks the ranking of the documents of small datasets against golden files.
+//!
+//! Every directory in `tests/relevance` is a dataset with a `schema.toml`, a
+//! `documents.json` and a `golden.json` file which gives, for each query, the
+//! identifiers of the documents in the expected order.
+//!
+//! When a change of the criteria or of the tokenizer is expected to change the
+//! ranking, run the tests with `MEILIDB_UPDATE_GOLDEN=1` to rewrite the golden
+//! files and review the diff of the orderings before committing it.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use meilidb_core::{Database, UpdateStatus};
+use meilidb_schema::Schema;
+use serde_json::Value;
+
+const UPDATE_GOLDEN_VAR: &str = "MEILIDB_UPDATE_GOLDEN";
+
+/// The number of documents returned for each query.
+const QUERY_LIMIT: usize = 20;
+
+type Golden = BTreeMap<String, Vec<String>>;
+
+fn datasets_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/relevance")
+}
+
+fn read_json<T: serde::de::DeserializeOwned>(path: &Path) -> T {
+    let file = fs::File::open(path).unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+    serde_json::from_reader(file).unwrap_or_else(|e| panic!("{}: {}", path.display(), e))
+}
+
+/// Indexes the documents of the dataset and returns the identifiers
+/// of the documents found for each query of the golden file.
+fn rankings(dataset: &Path, queries: impl Iterator<Item = String>) -> Golden {
+    let schema = fs::read_to_string(dataset.join("schema.toml")).unwrap();
+    let schema: Schema = toml::from_str(&schema).unwrap();
+    let documents: Vec<Value> = read_json(&dataset.join("documents.json"));
+
+    let dir = tempfile::tempdir().unwrap();
+    let database = Database::open_or_create(dir.path()).unwrap();
+    let env = &database.env;
+    let index = database.create_index("relevance").unwrap();
+
+    let mut writer = env.write_txn().unwrap();
+    index.schema_update(&mut writer, schema.clone()).unwrap();
+    writer.commit().unwrap();
+
+    let mut additions = index.documents_addition();
+    for document in documents {
+        additions.update_document(document);
+    }
+
+    let mut writer = env.write_txn().unwrap();
+    let update_id = additions.finalize(&mut writer).unwrap();
+    writer.commit().unwrap();
+    assert!(index.wait_for_update(update_id, Duration::from_secs(10)));
+
+    let reader = env.read_txn().unwrap();
+    match index.update_status(&reader, update_id).unwrap() {
+        Some(UpdateStatus::Processed { content }) => {
+            if let Some(error) = content.error {
+                panic!("{}: {}", dataset.display(), error);
+            }
+        }
+        status => panic!("{}: unexpected status {:?}", dataset.display(), status),
+    }
+
+    let identifier = schema.attribute(schema.identifier_name()).unwrap();
+
+    let mut rankings = Golden::new();
+    for query in queries {
+        let documents = index
+            .query_builder()
+            .query(&reader, &query, 0..QUERY_LIMIT)
+            .unwrap();
+
+        let identifiers = documents
+            .into_iter()
+            .map(|document| {
+                let value: Value = index
+                    .document_attribute(&reader, document.id, identifier)
+                    .unwrap()
+                    .unwrap();
+                match value {
+                    Value::String(s) => s,
+                    value => value.to_string(),
+                }
+            })
+            .collect();
+
+        rankings.insert(query, identifiers);
+    }
+
+    rankings
+}
+
+/// Describes the differences between the expected and the actual orderings of a query.
+fn ranking_diff(query: &str, expected: &[String], actual: &[String]) -> Option<String> {
+    if expected == actual {
+        return None;
+    }
+
+    let mut diff = format!("  query {:?}\n", query);
+    for rank in 0..expected.len().max(actual.len()) {
+        let expected = expected.get(rank).map(String::as_str).unwrap_or("-");
+        let actual = actual.get(rank).map(String::as_str).unwrap_or("-");
+        let marker = if expected == actual { " " } else { "!" };
+        diff.push_str(&format!(
+            "  {} #{:<3} expected {:<12} found {}\n",
+            marker,
+            rank + 1,
+            expected,
+            actual
+        ));
+    }
+
+    Some(diff)
+}
+
+#[test]
+fn golden_rankings() {
+    let update_golden = std::env::var_os(UPDATE_GOLDEN_VAR).is_some();
+
+    let mut datasets: Vec<_> = fs::read_dir(datasets_dir())
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.is_dir())
+        .collect();
+    datasets.sort();
+    assert!(!datasets.is_empty(), "no relevance datasets found");
+
+    let mut report = String::new();
+
+    for dataset in datasets {
+        let golden_path = dataset.join("golden.json");
+        let golden: Golden = read_json(&golden_path);
+        let actual = rankings(&dataset, golden.keys().cloned());
+
+        if update_golden {
+            let mut json = serde_json::to_string_pretty(&actual).unwrap();
+            json.push('\n');
+            fs::write(&golden_path, json).unwrap();
+            continue;
+        }
+
+        let name = dataset.file_name().unwrap().to_string_lossy();
+        for (query, expected) in &golden {
+            if let Some(diff) = ranking_diff(query, expected, &actual[query]) {
+                report.push_str(&format!("dataset {}\n{}", name, diff));
+            }
+        }
+    }
+
+    assert!(
+        report.is_empty(),
+        "the rankings differ from the golden files, run with {}=1 \
+         to update them if the changes are expected:\n{}",
+        UPDATE_GOLDEN_VAR,
+        report
+    );
+}
+
+#[test]
+fn diff_of_rankings() {
+    let ids = |ids: &[&str]| ids.iter().map(ToString::to_string).collect::<Vec<_>>();
+
+    assert_eq!(
+        ranking_diff("hobbit", &ids(&["1", "2"]), &ids(&["1", "2"])),
+        None
+    );
+
+    let diff = ranking_diff("hobbit", &ids(&["1", "2"]), &ids(&["2", "1", "3"])).unwrap();
+    assert!(diff.contains("! #1   expected 1            found 2"));
+    assert!(diff.contains("! #3   expected -            found 3"));
+}
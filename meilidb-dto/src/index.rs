@@ -0,0 +1,313 @@
+use std::collections::HashSet;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::schema::SchemaBody;
+
+/// Free-form informations attached to an index, like a description, an owner or tags.
+pub type IndexMetadata = serde_json::Map<String, Value>;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexResponse {
+    pub name: String,
+    pub uid: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<IndexMetadata>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// The query string of the indexes listing, the indexes are sorted by uid.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct IndexesQuery {
+    /// Only lists the indexes whose uid or name starts with this prefix, case insensitively.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub search: Option<String>,
+    /// Only lists the indexes whose metadata matches a `key:value` filter.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offset: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct IndexCreateRequest {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uid: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub schema: Option<SchemaBody>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<IndexMetadata>,
+    /// Appends the new attributes of the added documents to the schema, which is
+    /// inferred from the first documents when none is given.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attributes_discovery: Option<bool>,
+}
+
+/// A new index created with the settings of an existing one but without its documents.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct IndexCloneRequest {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uid: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexCreateResponse {
+    pub name: String,
+    pub uid: String,
+    pub schema: Option<SchemaBody>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub update_id: Option<u64>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct UpdateIndexRequest {
+    pub name: String,
+    /// Replaces the metadata of the index when given.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<IndexMetadata>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateIndexResponse {
+    pub name: String,
+    pub uid: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<IndexMetadata>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexUpdateResponse {
+    pub update_id: u64,
+}
+
+/// The documents to fetch in a single read, the identifiers can be strings or numbers.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct FetchDocumentsRequest {
+    pub ids: Vec<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attributes_to_retrieve: Option<HashSet<String>>,
+}
+
+/// The filter expression selecting the documents to delete, like `genre = "horror"`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct DeleteByFilterRequest {
+    pub filter: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use serde_json::json;
+
+    #[test]
+    fn index_response() {
+        let response = IndexResponse {
+            name: "Movies".to_string(),
+            uid: "movies".to_string(),
+            metadata: None,
+            created_at: Utc.ymd(2019, 11, 4).and_hms(10, 28, 47),
+            updated_at: Utc.ymd(2019, 11, 5).and_hms(8, 0, 0),
+        };
+
+        let value = json!({
+            "name": "Movies",
+            "uid": "movies",
+            "createdAt": "2019-11-04T10:28:47Z",
+            "updatedAt": "2019-11-05T08:00:00Z",
+        });
+
+        assert_eq!(serde_json::to_value(&response).unwrap(), value);
+        assert_eq!(
+            serde_json::from_value::<IndexResponse>(value).unwrap(),
+            response
+        );
+    }
+
+    #[test]
+    fn index_response_metadata() {
+        let value = json!({
+            "name": "Movies",
+            "uid": "movies",
+            "metadata": {
+                "description": "All the movies",
+                "owner": "catalog",
+                "tags": ["films", "cinema"],
+            },
+            "createdAt": "2019-11-04T10:28:47Z",
+            "updatedAt": "2019-11-05T08:00:00Z",
+        });
+
+        let response = serde_json::from_value::<IndexResponse>(value.clone()).unwrap();
+        let metadata = response.metadata.as_ref().unwrap();
+        assert_eq!(metadata["owner"], json!("catalog"));
+        assert_eq!(serde_json::to_value(&response).unwrap(), value);
+    }
+
+    #[test]
+    fn indexes_query() {
+        let query = IndexesQuery {
+            search: Some("mov".to_string()),
+            limit: Some(10),
+            ..IndexesQuery::default()
+        };
+
+        let value = json!({ "search": "mov", "limit": 10 });
+
+        assert_eq!(serde_json::to_value(&query).unwrap(), value);
+        assert_eq!(
+            serde_json::from_value::<IndexesQuery>(value).unwrap(),
+            query
+        );
+
+        let unknown = json!({ "search": "mov", "sort": "name" });
+        assert!(serde_json::from_value::<IndexesQuery>(unknown).is_err());
+    }
+
+    #[test]
+    fn index_create_request() {
+        let request = IndexCreateRequest {
+            name: "Movies".to_string(),
+            ..IndexCreateRequest::default()
+        };
+
+        let value = json!({ "name": "Movies" });
+
+        assert_eq!(serde_json::to_value(&request).unwrap(), value);
+        assert_eq!(
+            serde_json::from_value::<IndexCreateRequest>(value).unwrap(),
+            request
+        );
+
+        let unknown = json!({ "name": "Movies", "identifier": "id" });
+        assert!(serde_json::from_value::<IndexCreateRequest>(unknown).is_err());
+
+        let value = json!({ "name": "Movies", "attributesDiscovery": true });
+        let request = serde_json::from_value::<IndexCreateRequest>(value).unwrap();
+        assert_eq!(request.attributes_discovery, Some(true));
+    }
+
+    #[test]
+    fn index_clone_request() {
+        let request = IndexCloneRequest {
+            name: "Movies Copy".to_string(),
+            uid: Some("movies-copy".to_string()),
+        };
+
+        let value = json!({ "name": "Movies Copy", "uid": "movies-copy" });
+
+        assert_eq!(serde_json::to_value(&request).unwrap(), value);
+        assert_eq!(
+            serde_json::from_value::<IndexCloneRequest>(value).unwrap(),
+            request
+        );
+
+        let unknown = json!({ "name": "Movies Copy", "schema": {} });
+        assert!(serde_json::from_value::<IndexCloneRequest>(unknown).is_err());
+    }
+
+    #[test]
+    fn index_create_response() {
+        let response = IndexCreateResponse {
+            name: "Movies".to_string(),
+            uid: "movies".to_string(),
+            schema: None,
+            update_id: None,
+            created_at: Utc.ymd(2019, 11, 4).and_hms(10, 28, 47),
+            updated_at: Utc.ymd(2019, 11, 4).and_hms(10, 28, 47),
+        };
+
+        let value = json!({
+            "name": "Movies",
+            "uid": "movies",
+            "schema": null,
+            "createdAt": "2019-11-04T10:28:47Z",
+            "updatedAt": "2019-11-04T10:28:47Z",
+        });
+
+        assert_eq!(serde_json::to_value(&response).unwrap(), value);
+        assert_eq!(
+            serde_json::from_value::<IndexCreateResponse>(value).unwrap(),
+            response
+        );
+    }
+
+    #[test]
+    fn update_index() {
+        let request = UpdateIndexRequest {
+            name: "Films".to_string(),
+            metadata: None,
+        };
+        let value = json!({ "name": "Films" });
+
+        assert_eq!(serde_json::to_value(&request).unwrap(), value);
+        assert_eq!(
+            serde_json::from_value::<UpdateIndexRequest>(value).unwrap(),
+            request
+        );
+    }
+
+    #[test]
+    fn index_update_response() {
+        let response = IndexUpdateResponse { update_id: 42 };
+        let value = json!({ "updateId": 42 });
+
+        assert_eq!(serde_json::to_value(&response).unwrap(), value);
+        assert_eq!(
+            serde_json::from_value::<IndexUpdateResponse>(value).unwrap(),
+            response
+        );
+    }
+
+    #[test]
+    fn fetch_documents_request() {
+        let request = FetchDocumentsRequest {
+            ids: vec![json!(12), json!("batman")],
+            ..FetchDocumentsRequest::default()
+        };
+
+        let value = json!({ "ids": [12, "batman"] });
+
+        assert_eq!(serde_json::to_value(&request).unwrap(), value);
+        assert_eq!(
+            serde_json::from_value::<FetchDocumentsRequest>(value).unwrap(),
+            request
+        );
+    }
+
+    #[test]
+    fn delete_by_filter_request() {
+        let request = DeleteByFilterRequest {
+            filter: "genre = horror".to_string(),
+        };
+
+        let value = json!({ "filter": "genre = horror" });
+
+        assert_eq!(serde_json::to_value(&request).unwrap(), value);
+        assert_eq!(
+            serde_json::from_value::<DeleteByFilterRequest>(value).unwrap(),
+            request
+        );
+    }
+}
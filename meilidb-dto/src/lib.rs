@@ -0,0 +1,21 @@
+//! The request and response bodies of the HTTP API.
+//!
+//! These types are shared by the HTTP server and the client, any change
+//! to their serialized form is a breaking change of the public API.
+
+mod index;
+mod search;
+mod settings;
+mod update;
+mod usage;
+
+pub mod schema;
+
+pub use self::index::*;
+pub use self::schema::{
+    FieldMapping, FieldMerge, FieldProperties, FieldSplit, MigrationBody, SchemaBody,
+};
+pub use self::search::*;
+pub use self::settings::*;
+pub use self::update::*;
+pub use self::usage::*;
@@ -1,8 +1,9 @@
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
 
 use indexmap::IndexMap;
 use meilidb_schema::{Schema, SchemaBuilder, SchemaProps};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -14,7 +15,7 @@ pub enum FieldProperties {
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
-pub struct SchemaBody(IndexMap<String, HashSet<FieldProperties>>);
+pub struct SchemaBody(pub IndexMap<String, HashSet<FieldProperties>>);
 
 impl From<Schema> for SchemaBody {
     fn from(value: Schema) -> SchemaBody {
@@ -74,6 +75,43 @@ impl Into<Schema> for SchemaBody {
     }
 }
 
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct FieldSplit {
+    pub separator: String,
+    pub into: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct FieldMerge {
+    pub separator: String,
+    pub from: Vec<String>,
+}
+
+/// How the documents are rewritten by a migration, the attributes not mentioned are kept.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct FieldMapping {
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub renames: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub splits: BTreeMap<String, FieldSplit>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub merges: BTreeMap<String, FieldMerge>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub defaults: BTreeMap<String, Value>,
+}
+
+/// The new schema of an index and how the documents are rewritten for it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct MigrationBody {
+    pub schema: SchemaBody,
+    #[serde(default)]
+    pub mapping: FieldMapping,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -115,4 +153,24 @@ mod tests {
 
         assert_eq!(schema_from_body, schema_from_builder);
     }
+
+    #[test]
+    fn migration_body() {
+        let value = serde_json::json!({
+            "schema": { "id": ["identifier", "displayed"], "name": ["indexed", "displayed"] },
+            "mapping": {
+                "renames": { "title": "name" },
+                "splits": { "author": { "separator": ",", "into": ["last", "first"] } },
+            },
+        });
+
+        let body: MigrationBody = serde_json::from_value(value.clone()).unwrap();
+        assert_eq!(body.mapping.renames["title"], "name");
+        assert_eq!(body.mapping.splits["author"].into, vec!["last", "first"]);
+        assert!(body.mapping.merges.is_empty());
+        assert_eq!(serde_json::to_value(&body).unwrap(), value);
+
+        let unknown = serde_json::json!({ "schema": {}, "mapping": { "drops": ["title"] } });
+        assert!(serde_json::from_value::<MigrationBody>(unknown).is_err());
+    }
 }
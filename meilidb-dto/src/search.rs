@@ -0,0 +1,627 @@
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+pub type Document = IndexMap<String, Value>;
+pub type HighlightInfos = HashMap<String, Value>;
+pub type MatchesInfos = HashMap<String, Vec<MatchPosition>>;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct SearchQuery {
+    pub q: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offset: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attributes_to_retrieve: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attributes_to_search_in: Option<String>,
+    /// The only attributes whose words match the query and are highlighted,
+    /// unlike `attributesToSearchIn` the ranking order of the attributes is kept.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub restrict_searchable_attributes: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attributes_to_crop: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub crop_length: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attributes_to_highlight: Option<String>,
+    /// The text inserted before the highlighted matches, `<em>` by default.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub highlight_pre_tag: Option<String>,
+    /// The text inserted after the highlighted matches, `</em>` by default.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub highlight_post_tag: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub filters: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub required_attribute: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub documents_ids: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub around_lat_lng: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub around_radius: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub matches: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub patterns: Option<bool>,
+    /// Whether the last query word matches the words it is a prefix of, the other
+    /// words never do, the setting of the index is used when not given.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prefix: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub diversity_attribute: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub diversity_strength: Option<usize>,
+    /// The name of the ranking profile of the index to sort the documents with.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ranking_profile: Option<String>,
+    /// HTML-escapes the formatted fields, the setting of the index is used when not given.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub escape_html: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fields_order: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hits_key: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub show_processing_time: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub show_query: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub debug: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub show_buckets: Option<bool>,
+    /// The attributes, separated by commas, whose values are counted among
+    /// the best ranked documents matching the query.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub facets: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct SearchMultiBody {
+    pub indexes: HashSet<String>,
+    pub query: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offset: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attributes_to_retrieve: Option<HashSet<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attributes_to_search_in: Option<HashSet<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub restrict_searchable_attributes: Option<HashSet<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attributes_to_crop: Option<HashMap<String, usize>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attributes_to_highlight: Option<HashSet<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub highlight_pre_tag: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub highlight_post_tag: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub filters: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub matches: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prefix: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub escape_html: Option<bool>,
+    /// Keeps only the best-ranked hit of the hits that have the same value for this
+    /// attribute in all of the indexes, e.g. the identifier to remove the syndicated documents.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dedup_attribute: Option<String>,
+    /// The time given to the search of each index, the indexes that do not answer
+    /// in time are excluded from the results instead of delaying the response.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub index_timeout_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub struct MatchPosition {
+    pub start: usize,
+    pub length: usize,
+}
+
+impl Ord for MatchPosition {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match self.start.cmp(&other.start) {
+            Ordering::Equal => self.length.cmp(&other.length),
+            _ => self.start.cmp(&other.start),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SearchHit<T = Document> {
+    #[serde(flatten)]
+    pub document: T,
+    #[serde(
+        rename = "_formatted",
+        default,
+        skip_serializing_if = "IndexMap::is_empty"
+    )]
+    pub formatted: IndexMap<String, Value>,
+    #[serde(
+        rename = "_matchesInfo",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub matches_info: Option<MatchesInfos>,
+    /// Why the hit has been returned and ranked where it is, returned
+    /// when the search is made in debug mode.
+    #[serde(rename = "_explain", default, skip_serializing_if = "Option::is_none")]
+    pub explain: Option<HitExplanation>,
+}
+
+/// The way the word searched has been derived from the query.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum MatchOrigin {
+    Query,
+    Synonym,
+    Decompounding,
+    Split,
+    Concatenation,
+}
+
+/// A word of the hit matched by a word searched for the query.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MatchExplanation {
+    /// The word searched, it is a word of the query or a word derived from it.
+    pub query: String,
+    pub origin: MatchOrigin,
+    /// The word of the document.
+    pub word: String,
+    pub typos: u8,
+    /// The penalty of the synonym alternative the searched word comes from.
+    pub penalty: u8,
+    pub attribute: String,
+    pub word_index: u16,
+    pub is_exact: bool,
+}
+
+/// The value a ranking rule compares the hit with.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RankingScore {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub score: Option<serde_json::Number>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HitExplanation {
+    /// The values of the ranking rules for the hit, in the order of the ranking rules.
+    pub ranking: Vec<RankingScore>,
+    pub matches: Vec<MatchExplanation>,
+}
+
+/// A change the engine made to the words of the query to search the hits.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum QueryRewrite {
+    /// A query word that is a stop word, it matches no hit.
+    StopWord {
+        word: String,
+    },
+    /// A query word removed by the token filters or past the limit of query words.
+    Ignored {
+        word: String,
+    },
+    Synonym {
+        query: Vec<String>,
+        words: Vec<String>,
+        penalty: u8,
+    },
+    Decompounding {
+        query: String,
+        words: Vec<String>,
+    },
+    Split {
+        query: String,
+        words: Vec<String>,
+    },
+    Concatenation {
+        query: Vec<String>,
+        word: String,
+    },
+    /// A word of the index matched by a query word with typos.
+    Typo {
+        query: String,
+        word: String,
+        typos: u8,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchResult<T = Document> {
+    pub hits: Vec<SearchHit<T>>,
+    pub offset: usize,
+    pub limit: usize,
+    pub processing_time_ms: usize,
+    pub query: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub processing_time_breakdown: Option<ProcessingTimeBreakdown>,
+    /// The offsets in the hits at which a ranking bucket starts, the hits of
+    /// a bucket are considered equal by all of the ranking rules.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub buckets: Option<Vec<usize>>,
+    /// The values of the requested facets, by attribute, along with
+    /// the number of best ranked documents that have them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub facets: Option<HashMap<String, Vec<FacetCount>>>,
+    /// The ranking rules that could not be applied as expected, like the ones
+    /// on attributes that some of the documents have no ranked value for.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+    /// The changes made to the words of the query, listed in debug mode.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rewrites: Vec<QueryRewrite>,
+}
+
+/// A value of a facet, displayed as one of the values of the documents
+/// it was counted for, and the number of documents that have it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FacetCount {
+    pub value: String,
+    pub count: usize,
+}
+
+/// The time spent in each phase of a search, in milliseconds,
+/// returned when the search is made in debug mode.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProcessingTimeBreakdown {
+    pub parse_ms: f64,
+    pub candidates_ms: f64,
+    pub ranking_ms: f64,
+    pub formatting_ms: f64,
+    pub serialization_ms: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchMultiBodyResponse<T = Document> {
+    pub hits: HashMap<String, Vec<SearchHit<T>>>,
+    pub offset: usize,
+    pub hits_per_page: usize,
+    pub processing_time_ms: usize,
+    pub query: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub excluded_indexes: Vec<ExcludedIndex>,
+}
+
+/// Why an index has been excluded from the results of a multi-index search.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ExclusionReason {
+    /// The search of the index failed internally.
+    Error,
+    /// The search of the index did not answer in time.
+    Timeout,
+    /// The index failed or timed out too many times in a row, it is
+    /// not searched until its circuit breaker lets a search through.
+    CircuitOpen,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExcludedIndex {
+    pub index_uid: String,
+    pub reason: ExclusionReason,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn search_query() {
+        let query = SearchQuery {
+            q: "batman".to_string(),
+            limit: Some(5),
+            attributes_to_highlight: Some("title,overview".to_string()),
+            highlight_pre_tag: Some("<mark>".to_string()),
+            highlight_post_tag: Some("</mark>".to_string()),
+            escape_html: Some(true),
+            ..SearchQuery::default()
+        };
+
+        let value = json!({
+            "q": "batman",
+            "limit": 5,
+            "attributesToHighlight": "title,overview",
+            "highlightPreTag": "<mark>",
+            "highlightPostTag": "</mark>",
+            "escapeHtml": true,
+        });
+
+        assert_eq!(serde_json::to_value(&query).unwrap(), value);
+        assert_eq!(serde_json::from_value::<SearchQuery>(value).unwrap(), query);
+
+        let unknown = json!({ "q": "batman", "page": 2 });
+        assert!(serde_json::from_value::<SearchQuery>(unknown).is_err());
+    }
+
+    #[test]
+    fn search_multi_body() {
+        let mut indexes = HashSet::new();
+        indexes.insert("*".to_string());
+
+        let body = SearchMultiBody {
+            indexes,
+            query: "batman".to_string(),
+            matches: Some(true),
+            ..SearchMultiBody::default()
+        };
+
+        let value = json!({
+            "indexes": ["*"],
+            "query": "batman",
+            "matches": true,
+        });
+
+        assert_eq!(serde_json::to_value(&body).unwrap(), value);
+        assert_eq!(
+            serde_json::from_value::<SearchMultiBody>(value).unwrap(),
+            body
+        );
+    }
+
+    #[test]
+    fn search_multi_body_response_excluded_indexes() {
+        let response: SearchMultiBodyResponse = SearchMultiBodyResponse {
+            hits: HashMap::new(),
+            offset: 0,
+            hits_per_page: 20,
+            processing_time_ms: 12,
+            query: "batman".to_string(),
+            excluded_indexes: vec![ExcludedIndex {
+                index_uid: "movies".to_string(),
+                reason: ExclusionReason::CircuitOpen,
+            }],
+        };
+
+        let value = json!({
+            "hits": {},
+            "offset": 0,
+            "hitsPerPage": 20,
+            "processingTimeMs": 12,
+            "query": "batman",
+            "excludedIndexes": [{ "indexUid": "movies", "reason": "circuitOpen" }],
+        });
+
+        assert_eq!(serde_json::to_value(&response).unwrap(), value);
+        assert_eq!(
+            serde_json::from_value::<SearchMultiBodyResponse>(value).unwrap(),
+            response
+        );
+    }
+
+    #[test]
+    fn processing_time_breakdown() {
+        let breakdown = ProcessingTimeBreakdown {
+            parse_ms: 0.5,
+            candidates_ms: 1.25,
+            ranking_ms: 2.0,
+            formatting_ms: 0.75,
+            serialization_ms: 0.25,
+        };
+
+        let value = json!({
+            "parseMs": 0.5,
+            "candidatesMs": 1.25,
+            "rankingMs": 2.0,
+            "formattingMs": 0.75,
+            "serializationMs": 0.25,
+        });
+
+        assert_eq!(serde_json::to_value(&breakdown).unwrap(), value);
+        assert_eq!(
+            serde_json::from_value::<ProcessingTimeBreakdown>(value).unwrap(),
+            breakdown
+        );
+    }
+
+    #[test]
+    fn hit_explanation() {
+        let explanation = HitExplanation {
+            ranking: vec![
+                RankingScore {
+                    name: "SumOfTypos".to_string(),
+                    score: Some(1.into()),
+                },
+                RankingScore {
+                    name: "Custom".to_string(),
+                    score: None,
+                },
+            ],
+            matches: vec![MatchExplanation {
+                query: "hello".to_string(),
+                origin: MatchOrigin::Synonym,
+                word: "helo".to_string(),
+                typos: 1,
+                penalty: 0,
+                attribute: "title".to_string(),
+                word_index: 0,
+                is_exact: false,
+            }],
+        };
+
+        let value = json!({
+            "ranking": [{ "name": "SumOfTypos", "score": 1 }, { "name": "Custom" }],
+            "matches": [{
+                "query": "hello",
+                "origin": "synonym",
+                "word": "helo",
+                "typos": 1,
+                "penalty": 0,
+                "attribute": "title",
+                "wordIndex": 0,
+                "isExact": false,
+            }],
+        });
+
+        assert_eq!(serde_json::to_value(&explanation).unwrap(), value);
+        assert_eq!(
+            serde_json::from_value::<HitExplanation>(value).unwrap(),
+            explanation
+        );
+    }
+
+    #[test]
+    fn match_position_ordering() {
+        let a = MatchPosition {
+            start: 0,
+            length: 4,
+        };
+        let b = MatchPosition {
+            start: 0,
+            length: 6,
+        };
+        let c = MatchPosition {
+            start: 3,
+            length: 1,
+        };
+
+        let mut positions = vec![c.clone(), b.clone(), a.clone()];
+        positions.sort_unstable();
+
+        assert_eq!(positions, vec![a, b, c]);
+    }
+
+    #[test]
+    fn search_result() {
+        let mut document = IndexMap::new();
+        document.insert("id".to_string(), json!(12));
+        document.insert("title".to_string(), json!("Batman"));
+
+        let mut formatted = IndexMap::new();
+        formatted.insert("title".to_string(), json!("<em>Batman</em>"));
+
+        let mut matches_info = HashMap::new();
+        matches_info.insert(
+            "title".to_string(),
+            vec![MatchPosition {
+                start: 0,
+                length: 6,
+            }],
+        );
+
+        let result = SearchResult {
+            hits: vec![
+                SearchHit {
+                    document: document.clone(),
+                    formatted,
+                    matches_info: Some(matches_info),
+                    explain: None,
+                },
+                SearchHit {
+                    document,
+                    formatted: IndexMap::new(),
+                    matches_info: None,
+                    explain: None,
+                },
+            ],
+            offset: 0,
+            limit: 20,
+            processing_time_ms: 2,
+            query: "batman".to_string(),
+            processing_time_breakdown: None,
+            buckets: None,
+            facets: None,
+            warnings: Vec::new(),
+            rewrites: Vec::new(),
+        };
+
+        let value = json!({
+            "hits": [
+                {
+                    "id": 12,
+                    "title": "Batman",
+                    "_formatted": { "title": "<em>Batman</em>" },
+                    "_matchesInfo": { "title": [{ "start": 0, "length": 6 }] },
+                },
+                {
+                    "id": 12,
+                    "title": "Batman",
+                },
+            ],
+            "offset": 0,
+            "limit": 20,
+            "processingTimeMs": 2,
+            "query": "batman",
+        });
+
+        assert_eq!(serde_json::to_value(&result).unwrap(), value);
+        assert_eq!(
+            serde_json::from_value::<SearchResult>(value).unwrap(),
+            result
+        );
+    }
+
+    #[test]
+    fn search_result_buckets() {
+        let result: SearchResult = SearchResult {
+            hits: Vec::new(),
+            offset: 0,
+            limit: 20,
+            processing_time_ms: 2,
+            query: "batman".to_string(),
+            processing_time_breakdown: None,
+            buckets: Some(vec![0, 3]),
+            facets: None,
+            warnings: vec!["2 of the 5 documents have no value for the ranking rule rank".into()],
+            rewrites: Vec::new(),
+        };
+
+        let value = serde_json::to_value(&result).unwrap();
+        assert_eq!(value["buckets"], json!([0, 3]));
+        assert_eq!(value["warnings"].as_array().map(Vec::len), Some(1));
+        assert_eq!(
+            serde_json::from_value::<SearchResult>(value).unwrap(),
+            result
+        );
+    }
+
+    #[test]
+    fn query_rewrites() {
+        let rewrites = vec![
+            QueryRewrite::StopWord {
+                word: "the".to_string(),
+            },
+            QueryRewrite::Typo {
+                query: "batmn".to_string(),
+                word: "batman".to_string(),
+                typos: 1,
+            },
+        ];
+
+        let value = json!([
+            { "type": "stopWord", "word": "the" },
+            { "type": "typo", "query": "batmn", "word": "batman", "typos": 1 },
+        ]);
+
+        assert_eq!(serde_json::to_value(&rewrites).unwrap(), value);
+        assert_eq!(
+            serde_json::from_value::<Vec<QueryRewrite>>(value).unwrap(),
+            rewrites
+        );
+    }
+}
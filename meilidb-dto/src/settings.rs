@@ -0,0 +1,703 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use serde::{Deserialize, Serialize};
+
+use crate::schema::SchemaBody;
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct SettingBody {
+    pub ranking_order: Option<RankingOrder>,
+    pub distinct_field: Option<DistinctField>,
+    pub ranking_rules: Option<RankingRules>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RankingOrdering {
+    Asc,
+    Dsc,
+}
+
+pub type RankingOrder = Vec<String>;
+pub type DistinctField = String;
+pub type RankingRules = HashMap<String, RankingOrdering>;
+
+/// A proposed settings update, the components that are not given are left untouched.
+///
+/// The stop words, the synonyms and the decompounding dictionary
+/// are the complete lists the index would end up with.
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct SettingsDiffRequest {
+    pub ranking_order: Option<RankingOrder>,
+    pub distinct_field: Option<DistinctField>,
+    pub ranking_rules: Option<RankingRules>,
+    pub schema: Option<SchemaBody>,
+    pub stop_words: Option<BTreeSet<String>>,
+    pub synonyms: Option<BTreeMap<String, Vec<String>>>,
+    pub decompounding: Option<BTreeSet<String>>,
+}
+
+/// Words added to and removed from the current stop words.
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct StopWordsPatch {
+    #[serde(default)]
+    pub add: BTreeSet<String>,
+    #[serde(default)]
+    pub remove: BTreeSet<String>,
+}
+
+/// An alternative added by a synonyms patch, either a simple word or a word along with a
+/// penalty making its matches rank after the ones of the original word, from 0 to 3.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum PatchAlternative {
+    Word(String),
+    Weighted { word: String, penalty: u8 },
+}
+
+impl PatchAlternative {
+    pub fn weighted(&self) -> (&str, u8) {
+        match self {
+            PatchAlternative::Word(word) => (word, 0),
+            PatchAlternative::Weighted { word, penalty } => (word, *penalty),
+        }
+    }
+}
+
+/// Alternatives added to and removed from the current synonyms,
+/// a synonym removed with an empty list loses all its alternatives.
+/// The penalty of an added alternative replaces the one it already had.
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct SynonymsPatch {
+    #[serde(default)]
+    pub add: BTreeMap<String, Vec<PatchAlternative>>,
+    #[serde(default)]
+    pub remove: BTreeMap<String, Vec<String>>,
+}
+
+/// A partial settings update, the settings that are not given are left untouched
+/// and the stop words and synonyms patches are applied to the lists as they are
+/// when the update is processed.
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct SettingsPatchRequest {
+    pub ranking_order: Option<RankingOrder>,
+    pub distinct_field: Option<DistinctField>,
+    pub ranking_rules: Option<RankingRules>,
+    pub stop_words: Option<StopWordsPatch>,
+    pub synonyms: Option<SynonymsPatch>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SettingEffect {
+    /// The change is taken into account as soon as the update is processed.
+    Immediate,
+    /// All the documents of the index must be reindexed.
+    Reindexing,
+    /// The update would be refused.
+    Rejected,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SettingChange {
+    pub component: String,
+    pub effect: SettingEffect,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SettingsDiff {
+    pub changes: Vec<SettingChange>,
+    pub unchanged: Vec<String>,
+    pub requires_reindexing: bool,
+}
+
+/// The limits an index imposes on the documents updates it accepts,
+/// a limit that is not given is not enforced.
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct UpdateLimitsBody {
+    pub max_documents_per_update: Option<usize>,
+    pub max_payload_bytes: Option<usize>,
+    pub max_field_bytes: Option<usize>,
+    /// The number of attributes the schema can have once extended with
+    /// the attributes discovered or flattened from the documents.
+    #[serde(default)]
+    pub max_attributes: Option<usize>,
+}
+
+/// How the numbers of the queries are matched, "1,000" always matches "1000"
+/// and "10kg" matches "10" when the units are stripped from the queries.
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct NumbersSettingsBody {
+    #[serde(default)]
+    pub strip_units: bool,
+}
+
+/// Whether the last word of the queries matches the words it is a prefix of, to search
+/// as the users type, the searches can override it with `prefix`, it is enabled by default.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct PrefixSearchSettingsBody {
+    pub prefix: bool,
+}
+
+/// The characters allowed in the string identifiers of the added documents, any character
+/// but the control ones by default. The integers and UUIDs identifiers are always accepted.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "charset")]
+pub enum DocumentIdCharsetBody {
+    Any,
+    /// The ASCII alphanumeric characters along with the extra characters given.
+    Alphanumeric {
+        #[serde(default)]
+        extra: String,
+    },
+}
+
+/// Whether the formatted fields of the search results are HTML-escaped before the
+/// `<em>` highlight tags are inserted, the searches can override it with `escapeHtml`.
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct HighlightingSettingsBody {
+    #[serde(default)]
+    pub escape_html: bool,
+}
+
+/// Whether the nested objects of the added documents are flattened into dotted attributes,
+/// e.g. `address.city`, the new attributes whose root is in the schema are added to it.
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct FlatteningSettingsBody {
+    #[serde(default)]
+    pub flatten_documents: bool,
+}
+
+/// How deep the search results can be paginated, the offset and limit of the searches
+/// are bounded by `maxTotalHits`, the pagination is not bounded when it is not given.
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct PaginationSettingsBody {
+    pub max_total_hits: Option<u64>,
+}
+
+/// How long the queries can be, the words following `maxQueryWords` are only highlighted
+/// and the least important automatons past `maxAutomatons` are not used to search.
+/// The default limits are used for the ones that are not given.
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct QueryLimitsBody {
+    pub max_query_words: Option<usize>,
+    pub max_automatons: Option<usize>,
+}
+
+/// The number of typos allowed in the query words from a word length in bytes,
+/// the lengths must be increasing and the number of typos never decrease.
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct TyposBody {
+    pub thresholds: Vec<TyposThresholdBody>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct TyposThresholdBody {
+    pub min_word_length: usize,
+    pub max_typos: u8,
+}
+
+/// The documents that have not been retrieved for `idleSecs` seconds are moved to the
+/// cold tier where they are compressed and slower to retrieve, the cold tier is
+/// disabled and all the documents are moved back to the hot tier when it is not given.
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ColdTierBody {
+    pub idle_secs: Option<u64>,
+}
+
+/// The soft limits of the number of searches of an index, the searches are never refused,
+/// the responses of the ones past a limit are annotated with a warning header.
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct QueryQuotaBody {
+    pub max_searches_per_hour: Option<u64>,
+    pub max_searches_per_day: Option<u64>,
+}
+
+/// How the values of an attribute are interpreted to be sorted and filtered on,
+/// the documents are returned with their original values.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AttributeType {
+    /// ISO 8601 dates, like "2019-11-04" or "2019-11-04T10:28:47Z".
+    Date,
+    /// Booleans and "true"/"false", "yes"/"no" or "1"/"0" strings.
+    Boolean,
+    /// Arrays of `{ "word", "position", "start", "end" }` objects tokenized by the caller,
+    /// `start` and `end` being char offsets in the original text, the words are indexed
+    /// as they are given and are neither sorted nor filtered on.
+    Tokens,
+    /// Numbers or arrays of numbers, like ratings or prices, sorted and filtered
+    /// on with a single value, written `{ "aggregated": "avg" }`.
+    Aggregated(Aggregation),
+}
+
+/// How the numbers of an array are reduced to the value sorted and filtered on.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Aggregation {
+    Min,
+    Max,
+    Sum,
+    Avg,
+}
+
+pub type AttributesTypes = BTreeMap<String, AttributeType>;
+
+/// How the values of a facet are normalized before being counted, the values that are equal
+/// once normalized are counted together under the most frequent of their original values.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FacetNormalization {
+    /// The values are counted as they are given.
+    Verbatim,
+    /// The leading and trailing whitespaces are removed.
+    Trim,
+    /// The values are trimmed and lowercased.
+    Lowercase,
+    /// The values are trimmed, lowercased and their accents are removed.
+    UnicodeFold,
+}
+
+/// The normalization of the facets, by attribute name,
+/// the values of the other attributes are counted verbatim.
+pub type FacetsNormalization = BTreeMap<String, FacetNormalization>;
+
+/// The maximum number of words indexed in the attributes, by attribute name,
+/// the documents are stored and returned with all their words.
+pub type WordsLimits = BTreeMap<String, usize>;
+
+/// A step of the analysis of the words of the documents and of the queries,
+/// the token filters of an index are applied in order.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TokenFilter {
+    Lowercase,
+    AsciiFold,
+    /// Drops the words that have less than `min` or more than `max` characters.
+    Length {
+        #[serde(default)]
+        min: usize,
+        #[serde(default)]
+        max: Option<usize>,
+    },
+    /// A filter registered by the program embedding the engine, by name.
+    Custom(String),
+}
+
+/// The only attributes that are indexed, their order defines the priority of the attributes
+/// in the ranking, all the indexed attributes of the schema are searched when it is not given.
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct SearchableAttributesBody {
+    pub searchable_attributes: Option<Vec<String>>,
+}
+
+/// The only attributes returned in the documents, all the displayed
+/// attributes of the schema are returned when it is not given.
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct DisplayedAttributesBody {
+    pub displayed_attributes: Option<Vec<String>>,
+}
+
+/// How the search responses of an index are shaped, to match the JSON expected
+/// by the clients, the parameters that are not given keep the default shape.
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ResponseFormat {
+    /// The attributes placed first in the hits, in this order.
+    pub fields_order: Option<Vec<String>>,
+    /// The name of the field containing the hits, "hits" by default.
+    pub hits_key: Option<String>,
+    pub show_processing_time: Option<bool>,
+    pub show_query: Option<bool>,
+}
+
+impl ResponseFormat {
+    /// Returns this format with the parameters given by the other one replaced.
+    pub fn overridden_by(self, other: ResponseFormat) -> ResponseFormat {
+        ResponseFormat {
+            fields_order: other.fields_order.or(self.fields_order),
+            hits_key: other.hits_key.or(self.hits_key),
+            show_processing_time: other.show_processing_time.or(self.show_processing_time),
+            show_query: other.show_query.or(self.show_query),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn pagination_settings() {
+        let body = PaginationSettingsBody {
+            max_total_hits: Some(1000),
+        };
+        let value = json!({ "maxTotalHits": 1000 });
+
+        assert_eq!(serde_json::to_value(&body).unwrap(), value);
+        assert_eq!(
+            serde_json::from_value::<PaginationSettingsBody>(value).unwrap(),
+            body
+        );
+        assert_eq!(
+            serde_json::from_value::<PaginationSettingsBody>(json!({})).unwrap(),
+            PaginationSettingsBody::default()
+        );
+    }
+
+    #[test]
+    fn document_id_charset() {
+        let body = DocumentIdCharsetBody::Alphanumeric {
+            extra: "-_".to_string(),
+        };
+        let value = json!({ "charset": "alphanumeric", "extra": "-_" });
+
+        assert_eq!(serde_json::to_value(&body).unwrap(), value);
+        assert_eq!(
+            serde_json::from_value::<DocumentIdCharsetBody>(value).unwrap(),
+            body
+        );
+        assert_eq!(
+            serde_json::from_value::<DocumentIdCharsetBody>(json!({ "charset": "any" })).unwrap(),
+            DocumentIdCharsetBody::Any
+        );
+    }
+
+    #[test]
+    fn searchable_and_displayed_attributes() {
+        let body = SearchableAttributesBody {
+            searchable_attributes: Some(vec!["title".to_string(), "overview".to_string()]),
+        };
+        let value = json!({ "searchableAttributes": ["title", "overview"] });
+
+        assert_eq!(serde_json::to_value(&body).unwrap(), value);
+        assert_eq!(
+            serde_json::from_value::<SearchableAttributesBody>(value).unwrap(),
+            body
+        );
+
+        let value = json!({ "displayedAttributes": null });
+        assert_eq!(
+            serde_json::from_value::<DisplayedAttributesBody>(value).unwrap(),
+            DisplayedAttributesBody::default()
+        );
+    }
+
+    #[test]
+    fn query_limits() {
+        let body = QueryLimitsBody {
+            max_query_words: Some(10),
+            max_automatons: Some(100),
+        };
+        let value = json!({ "maxQueryWords": 10, "maxAutomatons": 100 });
+
+        assert_eq!(serde_json::to_value(&body).unwrap(), value);
+        assert_eq!(
+            serde_json::from_value::<QueryLimitsBody>(value).unwrap(),
+            body
+        );
+        assert_eq!(
+            serde_json::from_value::<QueryLimitsBody>(json!({})).unwrap(),
+            QueryLimitsBody::default()
+        );
+    }
+
+    #[test]
+    fn typos() {
+        let body = TyposBody {
+            thresholds: vec![
+                TyposThresholdBody {
+                    min_word_length: 0,
+                    max_typos: 0,
+                },
+                TyposThresholdBody {
+                    min_word_length: 5,
+                    max_typos: 1,
+                },
+            ],
+        };
+        let value = json!({
+            "thresholds": [
+                { "minWordLength": 0, "maxTypos": 0 },
+                { "minWordLength": 5, "maxTypos": 1 },
+            ]
+        });
+
+        assert_eq!(serde_json::to_value(&body).unwrap(), value);
+        assert_eq!(serde_json::from_value::<TyposBody>(value).unwrap(), body);
+    }
+
+    #[test]
+    fn query_quota() {
+        let body = QueryQuotaBody {
+            max_searches_per_hour: Some(1000),
+            max_searches_per_day: None,
+        };
+        let value = json!({ "maxSearchesPerHour": 1000, "maxSearchesPerDay": null });
+
+        assert_eq!(serde_json::to_value(&body).unwrap(), value);
+        assert_eq!(
+            serde_json::from_value::<QueryQuotaBody>(value).unwrap(),
+            body
+        );
+        assert_eq!(
+            serde_json::from_value::<QueryQuotaBody>(json!({})).unwrap(),
+            QueryQuotaBody::default()
+        );
+    }
+
+    #[test]
+    fn cold_tier() {
+        let body = ColdTierBody {
+            idle_secs: Some(86400),
+        };
+        let value = json!({ "idleSecs": 86400 });
+
+        assert_eq!(serde_json::to_value(&body).unwrap(), value);
+        assert_eq!(serde_json::from_value::<ColdTierBody>(value).unwrap(), body);
+        assert_eq!(
+            serde_json::from_value::<ColdTierBody>(json!({})).unwrap(),
+            ColdTierBody::default()
+        );
+    }
+
+    #[test]
+    fn attributes_types() {
+        let mut types = AttributesTypes::new();
+        types.insert("release_date".to_string(), AttributeType::Date);
+        types.insert("adult".to_string(), AttributeType::Boolean);
+        types.insert("citation".to_string(), AttributeType::Tokens);
+        types.insert(
+            "ratings".to_string(),
+            AttributeType::Aggregated(Aggregation::Avg),
+        );
+
+        let value = json!({
+            "release_date": "date",
+            "adult": "boolean",
+            "citation": "tokens",
+            "ratings": { "aggregated": "avg" },
+        });
+
+        assert_eq!(serde_json::to_value(&types).unwrap(), value);
+        assert_eq!(
+            serde_json::from_value::<AttributesTypes>(value).unwrap(),
+            types
+        );
+
+        let unknown = json!({ "rank": "integer" });
+        assert!(serde_json::from_value::<AttributesTypes>(unknown).is_err());
+    }
+
+    #[test]
+    fn facets_normalization() {
+        let mut normalization = FacetsNormalization::new();
+        normalization.insert("genre".to_string(), FacetNormalization::UnicodeFold);
+        normalization.insert("color".to_string(), FacetNormalization::Trim);
+
+        let value = json!({ "genre": "unicodeFold", "color": "trim" });
+
+        assert_eq!(serde_json::to_value(&normalization).unwrap(), value);
+        assert_eq!(
+            serde_json::from_value::<FacetsNormalization>(value).unwrap(),
+            normalization
+        );
+
+        let unknown = json!({ "genre": "uppercase" });
+        assert!(serde_json::from_value::<FacetsNormalization>(unknown).is_err());
+    }
+
+    #[test]
+    fn token_filters() {
+        let filters = vec![
+            TokenFilter::Lowercase,
+            TokenFilter::AsciiFold,
+            TokenFilter::Length { min: 2, max: None },
+            TokenFilter::Custom("stemmer".to_string()),
+        ];
+
+        let value = json!([
+            "lowercase",
+            "asciiFold",
+            { "length": { "min": 2, "max": null } },
+            { "custom": "stemmer" },
+        ]);
+
+        assert_eq!(serde_json::to_value(&filters).unwrap(), value);
+        assert_eq!(
+            serde_json::from_value::<Vec<TokenFilter>>(value).unwrap(),
+            filters
+        );
+
+        let value = json!([{ "length": { "max": 20 } }]);
+        assert_eq!(
+            serde_json::from_value::<Vec<TokenFilter>>(value).unwrap(),
+            vec![TokenFilter::Length {
+                min: 0,
+                max: Some(20)
+            }]
+        );
+
+        assert!(serde_json::from_value::<Vec<TokenFilter>>(json!(["stem"])).is_err());
+    }
+
+    #[test]
+    fn setting_body() {
+        let mut ranking_rules = HashMap::new();
+        ranking_rules.insert("release_date".to_string(), RankingOrdering::Dsc);
+        ranking_rules.insert("rank".to_string(), RankingOrdering::Asc);
+
+        let settings = SettingBody {
+            ranking_order: Some(vec![
+                "_sum_of_typos".to_string(),
+                "release_date".to_string(),
+            ]),
+            distinct_field: Some("movie_id".to_string()),
+            ranking_rules: Some(ranking_rules),
+        };
+
+        let value = json!({
+            "rankingOrder": ["_sum_of_typos", "release_date"],
+            "distinctField": "movie_id",
+            "rankingRules": { "release_date": "dsc", "rank": "asc" },
+        });
+
+        assert_eq!(serde_json::to_value(&settings).unwrap(), value);
+        assert_eq!(
+            serde_json::from_value::<SettingBody>(value).unwrap(),
+            settings
+        );
+    }
+
+    #[test]
+    fn settings_diff() {
+        let diff = SettingsDiff {
+            changes: vec![
+                SettingChange {
+                    component: "rankingOrder".to_string(),
+                    effect: SettingEffect::Immediate,
+                    reason: None,
+                },
+                SettingChange {
+                    component: "stopWords".to_string(),
+                    effect: SettingEffect::Reindexing,
+                    reason: Some("1 stop word removed".to_string()),
+                },
+            ],
+            unchanged: vec!["synonyms".to_string()],
+            requires_reindexing: true,
+        };
+
+        let value = json!({
+            "changes": [
+                { "component": "rankingOrder", "effect": "immediate" },
+                { "component": "stopWords", "effect": "reindexing", "reason": "1 stop word removed" },
+            ],
+            "unchanged": ["synonyms"],
+            "requiresReindexing": true,
+        });
+
+        assert_eq!(serde_json::to_value(&diff).unwrap(), value);
+        assert_eq!(serde_json::from_value::<SettingsDiff>(value).unwrap(), diff);
+    }
+
+    #[test]
+    fn settings_patch_request() {
+        let value = json!({
+            "stopWords": { "add": ["of"], "remove": ["a"] },
+            "synonyms": { "add": { "nyc": ["big apple", { "word": "new york", "penalty": 2 }] } },
+        });
+
+        let request = serde_json::from_value::<SettingsPatchRequest>(value).unwrap();
+        let stop_words = request.stop_words.unwrap();
+        assert!(stop_words.add.contains("of"));
+        assert!(stop_words.remove.contains("a"));
+
+        let synonyms = request.synonyms.unwrap();
+        assert_eq!(
+            synonyms.add["nyc"],
+            vec![
+                PatchAlternative::Word("big apple".to_string()),
+                PatchAlternative::Weighted {
+                    word: "new york".to_string(),
+                    penalty: 2
+                },
+            ]
+        );
+        assert_eq!(synonyms.add["nyc"][1].weighted(), ("new york", 2));
+        assert!(synonyms.remove.is_empty());
+        assert!(request.ranking_order.is_none());
+
+        let unknown = json!({ "stopWords": { "replace": ["the"] } });
+        assert!(serde_json::from_value::<SettingsPatchRequest>(unknown).is_err());
+    }
+
+    #[test]
+    fn empty_setting_body() {
+        let value = json!({
+            "rankingOrder": null,
+            "distinctField": null,
+            "rankingRules": null,
+        });
+
+        assert_eq!(
+            serde_json::to_value(&SettingBody::default()).unwrap(),
+            value
+        );
+        assert_eq!(
+            serde_json::from_value::<SettingBody>(json!({})).unwrap(),
+            SettingBody::default()
+        );
+    }
+
+    #[test]
+    fn response_format_override() {
+        let index_format = ResponseFormat {
+            fields_order: Some(vec!["title".to_string()]),
+            hits_key: Some("results".to_string()),
+            show_processing_time: Some(false),
+            show_query: None,
+        };
+
+        let request_format = ResponseFormat {
+            hits_key: Some("documents".to_string()),
+            show_query: Some(false),
+            ..ResponseFormat::default()
+        };
+
+        let format = index_format.overridden_by(request_format);
+
+        assert_eq!(
+            format,
+            ResponseFormat {
+                fields_order: Some(vec!["title".to_string()]),
+                hits_key: Some("documents".to_string()),
+                show_processing_time: Some(false),
+                show_query: Some(false),
+            }
+        );
+    }
+}
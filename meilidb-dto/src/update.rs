@@ -0,0 +1,396 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+// These types mirror the update statuses returned by meilidb-core,
+// they must be kept in sync with the ones defined in the update module.
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "name")]
+pub enum UpdateType {
+    ClearAll,
+    Schema,
+    Customs,
+    DocumentsAddition { number: usize },
+    DocumentsPartial { number: usize },
+    DocumentsDeletion { number: usize },
+    SynonymsAddition { number: usize },
+    SynonymsDeletion { number: usize },
+    StopWordsAddition { number: usize },
+    StopWordsDeletion { number: usize },
+    Decompounding { number: usize },
+    AttributesTypes { number: usize },
+    SettingsPatch { number: usize },
+    WordsLimits { number: usize },
+    SearchableAttributes { number: usize },
+    Migration { number: usize },
+    TokenFilters { number: usize },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DuplicateDocument {
+    pub identifier: String,
+    pub duplicate_of: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IdentifierCollision {
+    pub identifier: String,
+    pub occurrences: usize,
+    pub indexed: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DocumentCoercions {
+    pub identifier: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub coerced: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dropped: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub truncated: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CoercionReport {
+    pub coerced: BTreeMap<String, usize>,
+    pub dropped: BTreeMap<String, usize>,
+    pub truncated: BTreeMap<String, usize>,
+    pub documents: Vec<DocumentCoercions>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProcessedUpdateResult {
+    pub update_id: u64,
+    #[serde(rename = "type")]
+    pub update_type: UpdateType,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub duplicates: Vec<DuplicateDocument>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub collisions: Vec<IdentifierCollision>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub coercions: Option<CoercionReport>,
+    pub duration: f64, // in seconds
+    pub enqueued_at: DateTime<Utc>,
+    pub processed_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EnqueuedUpdateResult {
+    pub update_id: u64,
+    pub update_type: UpdateType,
+    pub enqueued_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "status")]
+pub enum UpdateStatus {
+    Enqueued {
+        #[serde(flatten)]
+        content: EnqueuedUpdateResult,
+    },
+    Processed {
+        #[serde(flatten)]
+        content: ProcessedUpdateResult,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use serde_json::json;
+
+    #[test]
+    fn update_types() {
+        let types = vec![
+            (UpdateType::ClearAll, json!({ "name": "ClearAll" })),
+            (UpdateType::Schema, json!({ "name": "Schema" })),
+            (UpdateType::Customs, json!({ "name": "Customs" })),
+            (
+                UpdateType::DocumentsAddition { number: 1 },
+                json!({ "name": "DocumentsAddition", "number": 1 }),
+            ),
+            (
+                UpdateType::DocumentsPartial { number: 2 },
+                json!({ "name": "DocumentsPartial", "number": 2 }),
+            ),
+            (
+                UpdateType::DocumentsDeletion { number: 3 },
+                json!({ "name": "DocumentsDeletion", "number": 3 }),
+            ),
+            (
+                UpdateType::SynonymsAddition { number: 4 },
+                json!({ "name": "SynonymsAddition", "number": 4 }),
+            ),
+            (
+                UpdateType::SynonymsDeletion { number: 5 },
+                json!({ "name": "SynonymsDeletion", "number": 5 }),
+            ),
+            (
+                UpdateType::StopWordsAddition { number: 6 },
+                json!({ "name": "StopWordsAddition", "number": 6 }),
+            ),
+            (
+                UpdateType::StopWordsDeletion { number: 7 },
+                json!({ "name": "StopWordsDeletion", "number": 7 }),
+            ),
+            (
+                UpdateType::Decompounding { number: 8 },
+                json!({ "name": "Decompounding", "number": 8 }),
+            ),
+            (
+                UpdateType::AttributesTypes { number: 2 },
+                json!({ "name": "AttributesTypes", "number": 2 }),
+            ),
+            (
+                UpdateType::SettingsPatch { number: 3 },
+                json!({ "name": "SettingsPatch", "number": 3 }),
+            ),
+            (
+                UpdateType::WordsLimits { number: 1 },
+                json!({ "name": "WordsLimits", "number": 1 }),
+            ),
+            (
+                UpdateType::SearchableAttributes { number: 2 },
+                json!({ "name": "SearchableAttributes", "number": 2 }),
+            ),
+            (
+                UpdateType::Migration { number: 3 },
+                json!({ "name": "Migration", "number": 3 }),
+            ),
+            (
+                UpdateType::TokenFilters { number: 4 },
+                json!({ "name": "TokenFilters", "number": 4 }),
+            ),
+        ];
+
+        for (update_type, value) in types {
+            assert_eq!(serde_json::to_value(&update_type).unwrap(), value);
+            assert_eq!(
+                serde_json::from_value::<UpdateType>(value).unwrap(),
+                update_type
+            );
+        }
+    }
+
+    #[test]
+    fn enqueued_update_status() {
+        let status = UpdateStatus::Enqueued {
+            content: EnqueuedUpdateResult {
+                update_id: 12,
+                update_type: UpdateType::DocumentsAddition { number: 4 },
+                enqueued_at: Utc.ymd(2019, 11, 4).and_hms(10, 28, 47),
+            },
+        };
+
+        let value = json!({
+            "status": "enqueued",
+            "update_id": 12,
+            "update_type": { "name": "DocumentsAddition", "number": 4 },
+            "enqueued_at": "2019-11-04T10:28:47Z",
+        });
+
+        assert_eq!(serde_json::to_value(&status).unwrap(), value);
+        assert_eq!(
+            serde_json::from_value::<UpdateStatus>(value).unwrap(),
+            status
+        );
+    }
+
+    #[test]
+    fn processed_update_status() {
+        let status = UpdateStatus::Processed {
+            content: ProcessedUpdateResult {
+                update_id: 13,
+                update_type: UpdateType::Customs,
+                error: Some("invalid settings".to_string()),
+                duplicates: Vec::new(),
+                collisions: Vec::new(),
+                coercions: None,
+                duration: 0.5,
+                enqueued_at: Utc.ymd(2019, 11, 4).and_hms(10, 28, 47),
+                processed_at: Utc.ymd(2019, 11, 4).and_hms(10, 28, 48),
+            },
+        };
+
+        let value = json!({
+            "status": "processed",
+            "update_id": 13,
+            "type": { "name": "Customs" },
+            "error": "invalid settings",
+            "duration": 0.5,
+            "enqueued_at": "2019-11-04T10:28:47Z",
+            "processed_at": "2019-11-04T10:28:48Z",
+        });
+
+        assert_eq!(serde_json::to_value(&status).unwrap(), value);
+        assert_eq!(
+            serde_json::from_value::<UpdateStatus>(value).unwrap(),
+            status
+        );
+    }
+
+    #[test]
+    fn processed_update_with_duplicates() {
+        let status = UpdateStatus::Processed {
+            content: ProcessedUpdateResult {
+                update_id: 14,
+                update_type: UpdateType::DocumentsAddition { number: 2 },
+                error: None,
+                duplicates: vec![DuplicateDocument {
+                    identifier: "2".to_string(),
+                    duplicate_of: "1".to_string(),
+                }],
+                collisions: Vec::new(),
+                coercions: None,
+                duration: 0.5,
+                enqueued_at: Utc.ymd(2019, 11, 4).and_hms(10, 28, 47),
+                processed_at: Utc.ymd(2019, 11, 4).and_hms(10, 28, 48),
+            },
+        };
+
+        let value = json!({
+            "status": "processed",
+            "update_id": 14,
+            "type": { "name": "DocumentsAddition", "number": 2 },
+            "duplicates": [{ "identifier": "2", "duplicate_of": "1" }],
+            "duration": 0.5,
+            "enqueued_at": "2019-11-04T10:28:47Z",
+            "processed_at": "2019-11-04T10:28:48Z",
+        });
+
+        assert_eq!(serde_json::to_value(&status).unwrap(), value);
+        assert_eq!(
+            serde_json::from_value::<UpdateStatus>(value).unwrap(),
+            status
+        );
+    }
+
+    #[test]
+    fn processed_update_with_collisions() {
+        let status = UpdateStatus::Processed {
+            content: ProcessedUpdateResult {
+                update_id: 16,
+                update_type: UpdateType::DocumentsAddition { number: 3 },
+                error: None,
+                duplicates: Vec::new(),
+                collisions: vec![IdentifierCollision {
+                    identifier: "1".to_string(),
+                    occurrences: 2,
+                    indexed: true,
+                }],
+                coercions: None,
+                duration: 0.5,
+                enqueued_at: Utc.ymd(2019, 11, 4).and_hms(10, 28, 47),
+                processed_at: Utc.ymd(2019, 11, 4).and_hms(10, 28, 48),
+            },
+        };
+
+        let value = json!({
+            "status": "processed",
+            "update_id": 16,
+            "type": { "name": "DocumentsAddition", "number": 3 },
+            "collisions": [{ "identifier": "1", "occurrences": 2, "indexed": true }],
+            "duration": 0.5,
+            "enqueued_at": "2019-11-04T10:28:47Z",
+            "processed_at": "2019-11-04T10:28:48Z",
+        });
+
+        assert_eq!(serde_json::to_value(&status).unwrap(), value);
+        assert_eq!(
+            serde_json::from_value::<UpdateStatus>(value).unwrap(),
+            status
+        );
+    }
+
+    #[test]
+    fn processed_update_with_coercions() {
+        let mut dropped = BTreeMap::new();
+        dropped.insert("unknown".to_string(), 1);
+
+        let status = UpdateStatus::Processed {
+            content: ProcessedUpdateResult {
+                update_id: 15,
+                update_type: UpdateType::DocumentsAddition { number: 1 },
+                error: None,
+                duplicates: Vec::new(),
+                collisions: Vec::new(),
+                coercions: Some(CoercionReport {
+                    coerced: BTreeMap::new(),
+                    dropped,
+                    truncated: BTreeMap::new(),
+                    documents: vec![DocumentCoercions {
+                        identifier: "1".to_string(),
+                        coerced: Vec::new(),
+                        dropped: vec!["unknown".to_string()],
+                        truncated: Vec::new(),
+                    }],
+                    warnings: Vec::new(),
+                }),
+                duration: 0.5,
+                enqueued_at: Utc.ymd(2019, 11, 4).and_hms(10, 28, 47),
+                processed_at: Utc.ymd(2019, 11, 4).and_hms(10, 28, 48),
+            },
+        };
+
+        let value = json!({
+            "status": "processed",
+            "update_id": 15,
+            "type": { "name": "DocumentsAddition", "number": 1 },
+            "coercions": {
+                "coerced": {},
+                "dropped": { "unknown": 1 },
+                "truncated": {},
+                "documents": [{ "identifier": "1", "dropped": ["unknown"] }],
+            },
+            "duration": 0.5,
+            "enqueued_at": "2019-11-04T10:28:47Z",
+            "processed_at": "2019-11-04T10:28:48Z",
+        });
+
+        assert_eq!(serde_json::to_value(&status).unwrap(), value);
+        assert_eq!(
+            serde_json::from_value::<UpdateStatus>(value).unwrap(),
+            status
+        );
+    }
+
+    #[test]
+    fn coercion_report_warnings() {
+        let mut truncated = BTreeMap::new();
+        truncated.insert("body".to_string(), 1);
+
+        let report = CoercionReport {
+            coerced: BTreeMap::new(),
+            dropped: BTreeMap::new(),
+            truncated,
+            documents: vec![DocumentCoercions {
+                identifier: "1".to_string(),
+                coerced: Vec::new(),
+                dropped: Vec::new(),
+                truncated: vec!["body".to_string()],
+            }],
+            warnings: vec!["the field body exceeds the limit of 2 words in 1 values".to_string()],
+        };
+
+        let value = json!({
+            "coerced": {},
+            "dropped": {},
+            "truncated": { "body": 1 },
+            "documents": [{ "identifier": "1", "truncated": ["body"] }],
+            "warnings": ["the field body exceeds the limit of 2 words in 1 values"],
+        });
+
+        assert_eq!(serde_json::to_value(&report).unwrap(), value);
+        assert_eq!(
+            serde_json::from_value::<CoercionReport>(value).unwrap(),
+            report
+        );
+    }
+}
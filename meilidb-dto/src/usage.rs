@@ -0,0 +1,68 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::QueryQuotaBody;
+
+/// The number of searches made in the current hour and in the last 24 hours,
+/// the hours are the ones of the UTC clock.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageBody {
+    pub searches_this_hour: u64,
+    pub searches_last_day: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexUsageResponse {
+    #[serde(flatten)]
+    pub usage: UsageBody,
+    pub quota: QueryQuotaBody,
+    /// Whether one of the limits of the quota is exceeded.
+    pub exceeded: bool,
+}
+
+/// The searches of all the indexes and of all the keys, the keys are listed by their
+/// identifier. The searches made with the master key or without any key are only
+/// counted by index.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageResponse {
+    pub indexes: BTreeMap<String, UsageBody>,
+    pub keys: BTreeMap<String, UsageBody>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn index_usage_response() {
+        let response = IndexUsageResponse {
+            usage: UsageBody {
+                searches_this_hour: 12,
+                searches_last_day: 340,
+            },
+            quota: QueryQuotaBody {
+                max_searches_per_hour: Some(10),
+                max_searches_per_day: None,
+            },
+            exceeded: true,
+        };
+
+        let value = json!({
+            "searchesThisHour": 12,
+            "searchesLastDay": 340,
+            "quota": { "maxSearchesPerHour": 10, "maxSearchesPerDay": null },
+            "exceeded": true,
+        });
+
+        assert_eq!(serde_json::to_value(&response).unwrap(), value);
+        assert_eq!(
+            serde_json::from_value::<IndexUsageResponse>(value).unwrap(),
+            response
+        );
+    }
+}
@@ -0,0 +1,191 @@
+use chrono::{DateTime, Utc};
+use heed::types::{SerdeBincode, Str};
+use meilidb_core::MResult;
+use serde::{Deserialize, Serialize};
+
+use crate::data::Data;
+
+const AUDIT_EVENT_PREFIX_KEY: &str = "_audit_event_";
+const NEXT_AUDIT_EVENT_ID_KEY: &str = "next-audit-event-id";
+
+// the number of most recent events kept in the common store
+const MAX_AUDIT_EVENTS: u64 = 10_000;
+
+type SerdeAuditEvent = SerdeBincode<AuditEvent>;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AuditAction {
+    IndexCreation,
+    IndexUpdate,
+    IndexDeletion,
+    SchemaUpdate,
+    SettingsUpdate,
+    SynonymsUpdate,
+    StopWordsUpdate,
+    DecompoundingUpdate,
+    DocumentsAddition,
+    DocumentsDeletion,
+    DocumentsClear,
+    KeyCreation,
+    KeyUpdate,
+    KeyDeletion,
+}
+
+/// An administrative operation, the acting key is only known when the
+/// operation was made with a token and not with the master key.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEvent {
+    pub event_id: u64,
+    pub action: AuditAction,
+    pub index: Option<String>,
+    /// The identifier of the acting key, not the key itself.
+    pub key_id: Option<String>,
+    pub timestamp: DateTime<Utc>,
+}
+
+fn audit_event_key(event_id: u64) -> String {
+    // the ids are padded to be iterated in order
+    format!("{}{:020}", AUDIT_EVENT_PREFIX_KEY, event_id)
+}
+
+impl Data {
+    /// Appends an event to the audit log, it must be called in the
+    /// write transaction of the operation to only log committed ones.
+    /// The oldest events are removed to keep the log bounded.
+    pub fn push_audit_event(
+        &self,
+        writer: &mut heed::RwTxn,
+        action: AuditAction,
+        index: Option<String>,
+        key_id: Option<String>,
+    ) -> MResult<()> {
+        let common_store = self.db.common_store();
+
+        let event_id = common_store
+            .get::<Str, SerdeBincode<u64>>(writer, NEXT_AUDIT_EVENT_ID_KEY)?
+            .unwrap_or(0);
+
+        let event = AuditEvent {
+            event_id,
+            action,
+            index,
+            key_id,
+            timestamp: Utc::now(),
+        };
+
+        let event_key = audit_event_key(event_id);
+        common_store.put::<Str, SerdeAuditEvent>(writer, &event_key, &event)?;
+        common_store.put::<Str, SerdeBincode<u64>>(
+            writer,
+            NEXT_AUDIT_EVENT_ID_KEY,
+            &(event_id + 1),
+        )?;
+
+        // the ids are contiguous, all the events before this one are expired
+        if let Some(first_kept) = (event_id + 1).checked_sub(MAX_AUDIT_EVENTS) {
+            let expired_keys: Vec<String> = {
+                let iter = common_store
+                    .prefix_iter::<Str, SerdeAuditEvent>(writer, AUDIT_EVENT_PREFIX_KEY)?;
+                let mut expired_keys = Vec::new();
+                for result in iter {
+                    let (key, event) = result?;
+                    if event.event_id >= first_kept {
+                        break;
+                    }
+                    expired_keys.push(key.to_owned());
+                }
+                expired_keys
+            };
+
+            for key in expired_keys {
+                common_store.delete::<Str>(writer, &key)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the audit events from the oldest to the most recent one.
+    pub fn audit_events(
+        &self,
+        reader: &heed::RoTxn,
+        offset: usize,
+        limit: usize,
+    ) -> MResult<Vec<AuditEvent>> {
+        let iter = self
+            .db
+            .common_store()
+            .prefix_iter::<Str, SerdeAuditEvent>(reader, AUDIT_EVENT_PREFIX_KEY)?;
+
+        let mut events = Vec::new();
+        for result in iter.skip(offset).take(limit) {
+            let (_, event) = result?;
+            events.push(event);
+        }
+
+        Ok(events)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::token::key_id;
+
+    #[test]
+    fn audit_event_keys_are_ordered() {
+        assert!(audit_event_key(9) < audit_event_key(10));
+        assert!(audit_event_key(99) < audit_event_key(100));
+    }
+
+    #[test]
+    fn stored_audit_events() {
+        let (_dir, data) = Data::temporary();
+
+        let env = &data.db.env;
+        let mut writer = env.write_txn().unwrap();
+        data.push_audit_event(&mut writer, AuditAction::KeyCreation, None, None)
+            .unwrap();
+        let index = Some(String::from("movies"));
+        data.push_audit_event(&mut writer, AuditAction::IndexCreation, index, None)
+            .unwrap();
+        writer.commit().unwrap();
+
+        let reader = env.read_txn().unwrap();
+        let events = data.audit_events(&reader, 0, 20).unwrap();
+        let events: Vec<_> = events
+            .iter()
+            .map(|e| (e.event_id, e.action, e.index.as_ref().map(String::as_str)))
+            .collect();
+
+        assert_eq!(
+            events,
+            vec![
+                (0, AuditAction::KeyCreation, None),
+                (1, AuditAction::IndexCreation, Some("movies")),
+            ]
+        );
+    }
+
+    #[test]
+    fn bounded_audit_log() {
+        let (_dir, data) = Data::temporary();
+
+        let env = &data.db.env;
+        let mut writer = env.write_txn().unwrap();
+        for _ in 0..MAX_AUDIT_EVENTS + 3 {
+            let key = Some(key_id("ABCDEFGH"));
+            data.push_audit_event(&mut writer, AuditAction::KeyUpdate, None, key)
+                .unwrap();
+        }
+        writer.commit().unwrap();
+
+        let reader = env.read_txn().unwrap();
+        let events = data.audit_events(&reader, 0, usize::max_value()).unwrap();
+        assert_eq!(events.len() as u64, MAX_AUDIT_EVENTS);
+        assert_eq!(events[0].event_id, 3);
+        assert_eq!(events[0].key_id, Some(key_id("ABCDEFGH")));
+    }
+}
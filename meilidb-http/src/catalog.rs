@@ -0,0 +1,128 @@
+use std::collections::BTreeMap;
+use std::sync::RwLock;
+
+use meilidb_core::{Database, Index, MResult};
+use meilidb_dto::IndexResponse;
+
+/// The names, dates and metadata of the indexes kept in memory and sorted by uid,
+/// the indexes are listed without reading the main store of each of them.
+///
+/// The entry of an index must be refreshed every time one of these informations
+/// is written, the indexes without a name or dates are not listed.
+#[derive(Default)]
+pub struct IndexesCatalog {
+    entries: RwLock<BTreeMap<String, IndexResponse>>,
+}
+
+impl IndexesCatalog {
+    pub fn load(db: &Database) -> MResult<IndexesCatalog> {
+        let catalog = IndexesCatalog::default();
+
+        let reader = db.env.read_txn()?;
+        for uid in db.indexes_uids() {
+            catalog.refresh_with(&reader, db, &uid)?;
+        }
+
+        Ok(catalog)
+    }
+
+    /// Reads again the informations of the index, removes it if it does not exist anymore.
+    pub fn refresh(&self, db: &Database, uid: &str) -> MResult<()> {
+        let reader = db.env.read_txn()?;
+        self.refresh_with(&reader, db, uid)
+    }
+
+    fn refresh_with(&self, reader: &heed::RoTxn, db: &Database, uid: &str) -> MResult<()> {
+        let entry = match db.open_index(uid) {
+            Some(index) => read_entry(reader, &index, uid)?,
+            None => None,
+        };
+
+        let mut entries = self.entries.write().unwrap();
+        match entry {
+            Some(entry) => entries.insert(uid.to_owned(), entry),
+            None => entries.remove(uid),
+        };
+
+        Ok(())
+    }
+
+    pub fn remove(&self, uid: &str) {
+        self.entries.write().unwrap().remove(uid);
+    }
+
+    /// The indexes sorted by uid.
+    pub fn indexes(&self) -> Vec<IndexResponse> {
+        self.entries.read().unwrap().values().cloned().collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.read().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.read().unwrap().is_empty()
+    }
+}
+
+fn read_entry(reader: &heed::RoTxn, index: &Index, uid: &str) -> MResult<Option<IndexResponse>> {
+    let name = index.main.name(reader)?;
+    let created_at = index.main.created_at(reader)?;
+    let updated_at = index.main.updated_at(reader)?;
+
+    match (name, created_at, updated_at) {
+        (Some(name), Some(created_at), Some(updated_at)) => {
+            let metadata = index.main.metadata(reader)?;
+            Ok(Some(IndexResponse {
+                name,
+                uid: uid.to_owned(),
+                metadata,
+                created_at,
+                updated_at,
+            }))
+        }
+        _ => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn refreshed_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = Database::open_or_create(dir.path()).unwrap();
+
+        let unnamed = db.create_index("unnamed").unwrap();
+        let movies = db.create_index("movies").unwrap();
+        let books = db.create_index("books").unwrap();
+
+        let mut writer = db.env.write_txn().unwrap();
+        for (index, name) in &[(&movies, "Movies"), (&books, "Books")] {
+            index.main.put_name(&mut writer, name).unwrap();
+            index.main.put_created_at(&mut writer).unwrap();
+            index.main.put_updated_at(&mut writer).unwrap();
+        }
+        unnamed.main.put_created_at(&mut writer).unwrap();
+        writer.commit().unwrap();
+
+        let catalog = IndexesCatalog::load(&db).unwrap();
+        let uids: Vec<_> = catalog.indexes().into_iter().map(|i| i.uid).collect();
+        assert_eq!(uids, vec!["books", "movies"]);
+
+        let mut writer = db.env.write_txn().unwrap();
+        movies.main.put_name(&mut writer, "Films").unwrap();
+        writer.commit().unwrap();
+
+        // the catalog is not read again until the entry is refreshed
+        assert_eq!(catalog.indexes()[1].name, "Movies");
+        catalog.refresh(&db, "movies").unwrap();
+        assert_eq!(catalog.indexes()[1].name, "Films");
+
+        db.delete_index("books").unwrap();
+        catalog.refresh(&db, "books").unwrap();
+        let uids: Vec<_> = catalog.indexes().into_iter().map(|i| i.uid).collect();
+        assert_eq!(uids, vec!["movies"]);
+    }
+}
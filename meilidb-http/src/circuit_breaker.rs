@@ -0,0 +1,164 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Default)]
+struct IndexCircuit {
+    consecutive_failures: usize,
+    open_until: Option<Instant>,
+}
+
+/// Excludes from the multi-index searches the indexes whose searches failed internally or
+/// timed out too many times in a row, the searches rejected because of the request never count. Once the cooldown is elapsed a single search is let through,
+/// the circuit is closed if it succeeds and opened for another cooldown otherwise.
+pub struct CircuitBreaker {
+    failure_threshold: usize,
+    cooldown: Duration,
+    circuits: Mutex<HashMap<String, IndexCircuit>>,
+}
+
+impl CircuitBreaker {
+    /// A threshold of zero disables the circuit breaker, all the indexes are always searched.
+    pub fn new(failure_threshold: usize, cooldown: Duration) -> CircuitBreaker {
+        CircuitBreaker {
+            failure_threshold,
+            cooldown,
+            circuits: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Whether the index can be searched, it must be followed by a call
+    /// to `record_success` or `record_failure` when it is allowed.
+    pub fn allows(&self, index_uid: &str) -> bool {
+        self.allows_at(index_uid, Instant::now())
+    }
+
+    pub fn record_success(&self, index_uid: &str) {
+        self.circuits.lock().unwrap().remove(index_uid);
+    }
+
+    pub fn record_failure(&self, index_uid: &str) {
+        self.record_failure_at(index_uid, Instant::now())
+    }
+
+    /// Forgets the failures of a deleted index.
+    pub fn forget(&self, index_uid: &str) {
+        self.circuits.lock().unwrap().remove(index_uid);
+    }
+
+    /// The indexes currently excluded from the multi-index searches.
+    pub fn open_circuits(&self) -> Vec<String> {
+        let now = Instant::now();
+        let circuits = self.circuits.lock().unwrap();
+        let mut uids: Vec<_> = circuits
+            .iter()
+            .filter(|(_, circuit)| circuit.open_until.map_or(false, |until| now < until))
+            .map(|(uid, _)| uid.clone())
+            .collect();
+        uids.sort();
+        uids
+    }
+
+    fn allows_at(&self, index_uid: &str, now: Instant) -> bool {
+        let mut circuits = self.circuits.lock().unwrap();
+        let circuit = match circuits.get_mut(index_uid) {
+            Some(circuit) => circuit,
+            None => return true,
+        };
+
+        match circuit.open_until {
+            Some(until) if now < until => false,
+            Some(_) => {
+                // the other searches are excluded while the trial search is running
+                circuit.open_until = Some(now + self.cooldown);
+                true
+            }
+            None => true,
+        }
+    }
+
+    fn record_failure_at(&self, index_uid: &str, now: Instant) {
+        if self.failure_threshold == 0 {
+            return;
+        }
+
+        let mut circuits = self.circuits.lock().unwrap();
+        let circuit = circuits.entry(index_uid.to_owned()).or_default();
+        circuit.consecutive_failures += 1;
+        if circuit.consecutive_failures >= self.failure_threshold {
+            circuit.open_until = Some(now + self.cooldown);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn open_after_consecutive_failures() {
+        let breaker = CircuitBreaker::new(3, Duration::from_secs(30));
+        let now = Instant::now();
+
+        breaker.record_failure_at("movies", now);
+        breaker.record_failure_at("movies", now);
+        breaker.record_success("movies");
+        breaker.record_failure_at("movies", now);
+        breaker.record_failure_at("movies", now);
+        assert!(breaker.allows_at("movies", now));
+
+        breaker.record_failure_at("movies", now);
+        assert!(!breaker.allows_at("movies", now));
+        assert!(breaker.allows_at("books", now));
+        assert_eq!(breaker.open_circuits(), vec!["movies".to_string()]);
+    }
+
+    #[test]
+    fn single_trial_after_cooldown() {
+        let cooldown = Duration::from_secs(30);
+        let breaker = CircuitBreaker::new(1, cooldown);
+        let now = Instant::now();
+
+        breaker.record_failure_at("movies", now);
+        assert!(!breaker.allows_at("movies", now + cooldown / 2));
+
+        // only one search is let through once the cooldown is elapsed
+        let later = now + cooldown;
+        assert!(breaker.allows_at("movies", later));
+        assert!(!breaker.allows_at("movies", later));
+
+        // the trial failed, the circuit is opened for another cooldown
+        breaker.record_failure_at("movies", later);
+        assert!(!breaker.allows_at("movies", later + cooldown / 2));
+
+        let much_later = later + cooldown;
+        assert!(breaker.allows_at("movies", much_later));
+        breaker.record_success("movies");
+        assert!(breaker.allows_at("movies", much_later));
+        assert!(breaker.allows_at("movies", much_later));
+    }
+
+    #[test]
+    fn disabled_breaker() {
+        let breaker = CircuitBreaker::new(0, Duration::from_secs(30));
+        let now = Instant::now();
+
+        for _ in 0..10 {
+            breaker.record_failure_at("movies", now);
+        }
+        assert!(breaker.allows_at("movies", now));
+    }
+
+    #[test]
+    fn forget_deleted_index() {
+        let breaker = CircuitBreaker::new(1, Duration::from_secs(30));
+        let now = Instant::now();
+
+        breaker.record_failure_at("movies", now);
+        assert!(!breaker.allows_at("movies", now));
+
+        breaker.forget("movies");
+        assert!(breaker.allows_at("movies", now));
+        assert!(breaker.open_circuits().is_empty());
+    }
+}
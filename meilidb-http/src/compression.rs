@@ -0,0 +1,397 @@
+use std::io::{self, Read};
+use std::mem;
+
+use async_compression::stream::{BrotliEncoder, GzipEncoder};
+use bytes::Bytes;
+use futures::future::BoxFuture;
+use futures::stream::{self, StreamExt};
+use http::header::{HeaderValue, ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_LENGTH, VARY};
+use http_service::Body;
+use tide::middleware::{Middleware, Next};
+use tide::response::IntoResponse;
+use tide::{Context, Response};
+
+use crate::error::ResponseError;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Encoding {
+    Brotli,
+    Gzip,
+}
+
+impl Encoding {
+    fn as_str(self) -> &'static str {
+        match self {
+            Encoding::Brotli => "br",
+            Encoding::Gzip => "gzip",
+        }
+    }
+}
+
+/// The encoding to compress a response with according to the `Accept-Encoding` header,
+/// brotli is preferred over gzip when both are accepted with the same weight.
+pub fn preferred_encoding(accept_encoding: &str) -> Option<Encoding> {
+    let mut preferred: Option<(Encoding, f32)> = None;
+
+    for item in accept_encoding.split(',') {
+        let mut parts = item.split(';');
+        let name = parts.next().unwrap_or_default().trim().to_lowercase();
+        let weight = parts
+            .filter_map(|param| {
+                let param = param.trim();
+                if param.starts_with("q=") {
+                    param[2..].trim().parse::<f32>().ok()
+                } else {
+                    None
+                }
+            })
+            .next()
+            .unwrap_or(1.0);
+
+        let encoding = match name.as_str() {
+            "br" => Encoding::Brotli,
+            "gzip" | "x-gzip" => Encoding::Gzip,
+            _ => continue,
+        };
+
+        if weight <= 0.0 {
+            continue;
+        }
+
+        let better = match preferred {
+            Some((current, current_weight)) => {
+                weight > current_weight
+                    || (weight == current_weight
+                        && encoding == Encoding::Brotli
+                        && current != Encoding::Brotli)
+            }
+            None => true,
+        };
+
+        if better {
+            preferred = Some((encoding, weight));
+        }
+    }
+
+    preferred.map(|(encoding, _)| encoding)
+}
+
+/// Compresses the responses when the client accepts it, the responses smaller than
+/// the minimum size are sent uncompressed as the compression would not pay off.
+pub struct Compression {
+    min_size: usize,
+}
+
+impl Compression {
+    pub fn new(min_size: usize) -> Compression {
+        Compression { min_size }
+    }
+}
+
+/// Reads the body until the minimum size is reached, the body is returned as it is
+/// if it is smaller, compressed otherwise.
+async fn compress_body(mut body: Body, min_size: usize, encoding: Encoding) -> (Body, bool) {
+    let mut prefix: Vec<io::Result<Bytes>> = Vec::new();
+    let mut prefix_len = 0;
+
+    while prefix_len < min_size {
+        match body.next().await {
+            Some(Ok(chunk)) => {
+                prefix_len += chunk.len();
+                prefix.push(Ok(chunk));
+            }
+            Some(Err(e)) => {
+                prefix.push(Err(e));
+                return (Body::from_stream(stream::iter(prefix)), false);
+            }
+            None => return (Body::from_stream(stream::iter(prefix)), false),
+        }
+    }
+
+    let stream = stream::iter(prefix).chain(body);
+    let body = match encoding {
+        Encoding::Brotli => Body::from_stream(BrotliEncoder::new(stream, 5)),
+        Encoding::Gzip => {
+            let level = flate2::Compression::default();
+            Body::from_stream(GzipEncoder::new(stream, level))
+        }
+    };
+
+    (body, true)
+}
+
+impl<State: Send + Sync + 'static> Middleware<State> for Compression {
+    fn handle<'a>(&'a self, cx: Context<State>, next: Next<'a, State>) -> BoxFuture<'a, Response> {
+        Box::pin(async move {
+            let encoding = cx
+                .request()
+                .headers()
+                .get(ACCEPT_ENCODING)
+                .and_then(|value| value.to_str().ok())
+                .and_then(preferred_encoding);
+
+            let response = next.run(cx).await;
+
+            let encoding = match encoding {
+                Some(encoding) if !response.headers().contains_key(CONTENT_ENCODING) => encoding,
+                _ => return response,
+            };
+
+            let (mut parts, body) = response.into_parts();
+            parts
+                .headers
+                .append(VARY, HeaderValue::from_static("Accept-Encoding"));
+
+            let (body, compressed) = compress_body(body, self.min_size, encoding).await;
+            if compressed {
+                parts.headers.remove(CONTENT_LENGTH);
+                parts.headers.insert(
+                    CONTENT_ENCODING,
+                    HeaderValue::from_static(encoding.as_str()),
+                );
+            }
+
+            http::Response::from_parts(parts, body)
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum DecompressionError {
+    Invalid(String),
+    TooLarge(usize),
+}
+
+/// Reads the compressed body, the bodies larger than the maximum size are refused
+/// as they cannot be smaller once decompressed.
+async fn read_body(mut body: Body, max_size: usize) -> Result<Vec<u8>, DecompressionError> {
+    let mut bytes = Vec::new();
+
+    while let Some(chunk) = body.next().await {
+        let chunk = chunk.map_err(|e| DecompressionError::Invalid(e.to_string()))?;
+        bytes.extend_from_slice(&chunk);
+        if bytes.len() > max_size {
+            return Err(DecompressionError::TooLarge(max_size));
+        }
+    }
+
+    Ok(bytes)
+}
+
+/// The encodings the request bodies can be sent with.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum BodyEncoding {
+    Identity,
+    Gzip,
+    Deflate,
+    Unsupported,
+}
+
+fn body_encoding(content_encoding: &str) -> BodyEncoding {
+    match content_encoding.trim().to_lowercase().as_str() {
+        "" | "identity" => BodyEncoding::Identity,
+        "gzip" | "x-gzip" => BodyEncoding::Gzip,
+        "deflate" => BodyEncoding::Deflate,
+        _ => BodyEncoding::Unsupported,
+    }
+}
+
+/// Decompresses a gzipped or deflated body, the decompression stops
+/// as soon as the maximum size is exceeded.
+fn decompress(
+    compressed: &[u8],
+    encoding: BodyEncoding,
+    max_size: usize,
+) -> Result<Vec<u8>, DecompressionError> {
+    let decoder: Box<dyn Read + '_> = match encoding {
+        BodyEncoding::Gzip => Box::new(flate2::read::GzDecoder::new(compressed)),
+        BodyEncoding::Deflate => Box::new(flate2::read::ZlibDecoder::new(compressed)),
+        BodyEncoding::Identity | BodyEncoding::Unsupported => return Ok(compressed.to_vec()),
+    };
+
+    let mut decompressed = Vec::new();
+    decoder
+        .take(max_size as u64 + 1)
+        .read_to_end(&mut decompressed)
+        .map_err(|e| DecompressionError::Invalid(e.to_string()))?;
+
+    if decompressed.len() > max_size {
+        return Err(DecompressionError::TooLarge(max_size));
+    }
+
+    Ok(decompressed)
+}
+
+/// Decompresses the gzipped and deflated requests bodies, like the documents payloads,
+/// the bodies larger than the maximum size once decompressed are refused as well as
+/// the bodies sent with any other encoding.
+pub struct Decompression {
+    max_size: usize,
+}
+
+impl Decompression {
+    pub fn new(max_size: usize) -> Decompression {
+        Decompression { max_size }
+    }
+}
+
+impl<State: Send + Sync + 'static> Middleware<State> for Decompression {
+    fn handle<'a>(
+        &'a self,
+        mut cx: Context<State>,
+        next: Next<'a, State>,
+    ) -> BoxFuture<'a, Response> {
+        Box::pin(async move {
+            let encoding = match cx.request().headers().get(CONTENT_ENCODING) {
+                Some(value) => value
+                    .to_str()
+                    .map_or(BodyEncoding::Unsupported, body_encoding),
+                None => BodyEncoding::Identity,
+            };
+
+            match encoding {
+                BodyEncoding::Identity => return next.run(cx).await,
+                BodyEncoding::Unsupported => {
+                    let message = "the payload must be sent uncompressed, gzipped or deflated";
+                    return ResponseError::unsupported_encoding(message).into_response();
+                }
+                BodyEncoding::Gzip | BodyEncoding::Deflate => (),
+            }
+
+            let body = mem::replace(cx.request_mut().body_mut(), Body::empty());
+            let result = match read_body(body, self.max_size).await {
+                Ok(compressed) => decompress(&compressed, encoding, self.max_size),
+                Err(e) => Err(e),
+            };
+
+            let decompressed = match result {
+                Ok(decompressed) => decompressed,
+                Err(DecompressionError::TooLarge(max_size)) => {
+                    let message = format!("the decompressed payload exceeds {} bytes", max_size);
+                    return ResponseError::payload_too_large(message).into_response();
+                }
+                Err(DecompressionError::Invalid(e)) => {
+                    let message = format!("invalid compressed payload; {}", e);
+                    return ResponseError::bad_request(message).into_response();
+                }
+            };
+
+            let request = cx.request_mut();
+            request.headers_mut().remove(CONTENT_ENCODING);
+            request
+                .headers_mut()
+                .insert(CONTENT_LENGTH, HeaderValue::from(decompressed.len()));
+            *request.body_mut() = Body::from(decompressed);
+
+            next.run(cx).await
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accept_encoding_negotiation() {
+        assert_eq!(preferred_encoding("gzip"), Some(Encoding::Gzip));
+        assert_eq!(
+            preferred_encoding("gzip, deflate, br"),
+            Some(Encoding::Brotli)
+        );
+        assert_eq!(preferred_encoding("br;q=0.5, gzip"), Some(Encoding::Gzip));
+        assert_eq!(
+            preferred_encoding("br;q=0, gzip;q=0.1"),
+            Some(Encoding::Gzip)
+        );
+        assert_eq!(preferred_encoding("identity, deflate"), None);
+        assert_eq!(preferred_encoding("gzip;q=0"), None);
+        assert_eq!(preferred_encoding(""), None);
+    }
+
+    #[test]
+    fn small_bodies_are_not_compressed() {
+        let body = Body::from(b"small".to_vec());
+        let (body, compressed) =
+            futures::executor::block_on(compress_body(body, 1024, Encoding::Gzip));
+        assert!(!compressed);
+
+        let bytes = futures::executor::block_on(body.into_vec()).unwrap();
+        assert_eq!(bytes, b"small");
+    }
+
+    #[test]
+    fn large_bodies_are_compressed() {
+        let content = "meilidb ".repeat(1000).into_bytes();
+        let body = Body::from(content.clone());
+        let (body, compressed) =
+            futures::executor::block_on(compress_body(body, 1024, Encoding::Gzip));
+        assert!(compressed);
+
+        let bytes = futures::executor::block_on(body.into_vec()).unwrap();
+        assert!(bytes.len() < content.len());
+
+        let mut decoder = flate2::read::GzDecoder::new(&bytes[..]);
+        let mut decompressed = Vec::new();
+        io::Read::read_to_end(&mut decoder, &mut decompressed).unwrap();
+        assert_eq!(decompressed, content);
+    }
+
+    fn gzip(content: &[u8]) -> Vec<u8> {
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        io::Write::write_all(&mut encoder, content).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn gzipped_documents_are_decompressed() {
+        let documents = serde_json::json!([
+            { "id": 1, "title": "Carol" },
+            { "id": 2, "title": "Wonder Woman" },
+        ]);
+        let content = serde_json::to_vec(&documents).unwrap();
+        let body = Body::from(gzip(&content));
+
+        let compressed = futures::executor::block_on(read_body(body, 1024)).unwrap();
+        let decompressed = decompress(&compressed, BodyEncoding::Gzip, 1024).unwrap();
+        let parsed: serde_json::Value = serde_json::from_slice(&decompressed).unwrap();
+        assert_eq!(parsed, documents);
+
+        let mut encoder =
+            flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+        io::Write::write_all(&mut encoder, &content).unwrap();
+        let compressed = encoder.finish().unwrap();
+        let decompressed = decompress(&compressed, BodyEncoding::Deflate, 1024).unwrap();
+        assert_eq!(decompressed, content);
+    }
+
+    #[test]
+    fn content_encoding_negotiation() {
+        assert_eq!(body_encoding("gzip"), BodyEncoding::Gzip);
+        assert_eq!(body_encoding(" X-Gzip "), BodyEncoding::Gzip);
+        assert_eq!(body_encoding("deflate"), BodyEncoding::Deflate);
+        assert_eq!(body_encoding("identity"), BodyEncoding::Identity);
+        assert_eq!(body_encoding("br"), BodyEncoding::Unsupported);
+        assert_eq!(body_encoding("gzip, br"), BodyEncoding::Unsupported);
+    }
+
+    #[test]
+    fn large_decompressed_bodies_are_refused() {
+        let content = "meilidb ".repeat(1000).into_bytes();
+        let compressed = gzip(&content);
+        assert!(compressed.len() < 1024);
+
+        let result = decompress(&compressed, BodyEncoding::Gzip, 1024);
+        assert_eq!(result, Err(DecompressionError::TooLarge(1024)));
+
+        let body = Body::from(content);
+        let result = futures::executor::block_on(read_body(body, 1024));
+        assert_eq!(result, Err(DecompressionError::TooLarge(1024)));
+
+        let result = decompress(b"not gzipped", BodyEncoding::Gzip, 1024);
+        match result {
+            Err(DecompressionError::Invalid(_)) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+}
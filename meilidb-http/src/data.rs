@@ -1,6 +1,8 @@
 use std::collections::HashMap;
 use std::ops::Deref;
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 
 use chrono::{DateTime, Utc};
 use heed::types::{SerdeBincode, Str};
@@ -8,8 +10,13 @@ use log::error;
 use meilidb_core::{Database, Error as MError, MResult};
 use sysinfo::Pid;
 
+use crate::catalog::IndexesCatalog;
+use crate::circuit_breaker::CircuitBreaker;
+use crate::diagnostics::InFlightQueries;
 use crate::option::Opt;
 use crate::routes::index::index_update_callback;
+use crate::routes::setting::{merge_customs, rename_customs};
+use crate::usage::UsageMeter;
 
 const LAST_UPDATE_KEY: &str = "last-update";
 
@@ -34,6 +41,12 @@ pub struct DataInner {
     pub db_path: String,
     pub api_key: Option<String>,
     pub server_pid: Pid,
+    pub catalog: Arc<IndexesCatalog>,
+    pub usage: Arc<UsageMeter>,
+    pub queries: Arc<InFlightQueries>,
+    pub diagnostics_dir: PathBuf,
+    pub query_memory_limit: Option<usize>,
+    pub circuit_breaker: Arc<CircuitBreaker>,
 }
 
 impl DataInner {
@@ -105,20 +118,39 @@ impl Data {
         let db_path = opt.db_path.clone();
         let api_key = opt.api_key.clone();
         let server_pid = sysinfo::get_current_pid().unwrap();
+        let diagnostics_dir = PathBuf::from(opt.diagnostics_dir.clone());
 
         let db = Arc::new(Database::open_or_create(opt.db_path.clone()).unwrap());
+        db.set_customs_merge(Box::new(merge_customs));
+        db.set_customs_rename(Box::new(rename_customs));
+        db.set_operations_log(opt.operations_log).unwrap();
+        db.set_updates_replays(opt.updates_replays);
+        let catalog = Arc::new(IndexesCatalog::load(&db).unwrap());
+        let usage = Arc::new(UsageMeter::load(&db).unwrap());
+        let circuit_breaker = CircuitBreaker::new(
+            opt.multi_search_failure_threshold,
+            Duration::from_secs(opt.multi_search_cooldown_sec),
+        );
 
         let inner_data = DataInner {
             db: db.clone(),
             db_path,
             api_key,
             server_pid,
+            catalog,
+            usage,
+            queries: Arc::new(InFlightQueries::default()),
+            diagnostics_dir,
+            query_memory_limit: opt.query_memory_limit,
+            circuit_breaker: Arc::new(circuit_breaker),
         };
 
         let data = Data {
             inner: Arc::new(inner_data),
         };
 
+        data.fail_interrupted_imports().unwrap();
+
         let callback_context = data.clone();
         db.set_update_callback(Box::new(move |index_uid, status| {
             index_update_callback(&index_uid, &callback_context, status);
@@ -127,3 +159,17 @@ impl Data {
         data
     }
 }
+
+#[cfg(test)]
+impl Data {
+    /// Opens a database with the default options in a temporary directory,
+    /// the directory is removed when the returned guard is dropped.
+    pub(crate) fn temporary() -> (tempfile::TempDir, Data) {
+        use structopt::StructOpt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("db");
+        let opt = Opt::from_iter(&["meilidb", "--db-path", db_path.to_str().unwrap()]);
+        (dir, Data::new(opt))
+    }
+}
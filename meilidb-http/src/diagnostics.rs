@@ -0,0 +1,216 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use chrono::{DateTime, Utc};
+use meilidb_core::{dfa_cache_len, MResult, UpdateStatus, MAP_SIZE};
+use serde::Serialize;
+
+use crate::data::Data;
+use crate::snapshot::SnapshotResult;
+
+const DIAGNOSTICS_FILE_PREFIX: &str = "diagnostics-";
+const DIAGNOSTICS_FILE_EXTENSION: &str = "json";
+
+/// A search that is currently being processed.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueryTrace {
+    pub index_uid: String,
+    pub query: String,
+    pub started_at: DateTime<Utc>,
+}
+
+/// The searches being processed, they are listed in the diagnostics
+/// to find the ones that take too long or hold a reader for too long.
+#[derive(Default)]
+pub struct InFlightQueries {
+    next_id: AtomicU64,
+    queries: Mutex<HashMap<u64, QueryTrace>>,
+}
+
+impl InFlightQueries {
+    /// Registers a search, it is listed until the returned guard is dropped.
+    pub fn start(&self, index_uid: &str, query: &str) -> InFlightQuery {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let trace = QueryTrace {
+            index_uid: index_uid.to_owned(),
+            query: query.to_owned(),
+            started_at: Utc::now(),
+        };
+
+        self.queries.lock().unwrap().insert(id, trace);
+        InFlightQuery { queries: self, id }
+    }
+
+    /// The searches being processed, from the oldest to the most recent one.
+    pub fn traces(&self) -> Vec<QueryTrace> {
+        let mut traces: Vec<_> = self.queries.lock().unwrap().values().cloned().collect();
+        traces.sort_by_key(|trace| trace.started_at);
+        traces
+    }
+}
+
+pub struct InFlightQuery<'a> {
+    queries: &'a InFlightQueries,
+    id: u64,
+}
+
+impl Drop for InFlightQuery<'_> {
+    fn drop(&mut self) {
+        self.queries.queries.lock().unwrap().remove(&self.id);
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexDiagnostics {
+    pub current_update_id: Option<u64>,
+    pub enqueued_updates: Vec<UpdateStatus>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotsDiagnostics {
+    pub last_success: Option<DateTime<Utc>>,
+    pub results: Vec<SnapshotResult>,
+}
+
+/// The sizes of the environment, the reader table of LMDB is not exposed
+/// by heed, the in-flight queries are the readers opened by the searches.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LmdbDiagnostics {
+    pub map_size: usize,
+    pub data_size: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CachesDiagnostics {
+    pub dfa_cache_len: usize,
+    pub catalog_len: usize,
+    pub usage_counters_len: usize,
+}
+
+/// The state of the server written to a file on demand, to be sent to the support.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Diagnostics {
+    pub generated_at: DateTime<Utc>,
+    pub read_only: bool,
+    pub indexes: BTreeMap<String, IndexDiagnostics>,
+    pub snapshots: SnapshotsDiagnostics,
+    pub lmdb: LmdbDiagnostics,
+    pub caches: CachesDiagnostics,
+    pub in_flight_queries: Vec<QueryTrace>,
+    pub open_circuits: Vec<String>,
+}
+
+impl Data {
+    pub fn diagnostics(&self) -> MResult<Diagnostics> {
+        let reader = self.db.env.read_txn()?;
+
+        let mut indexes = BTreeMap::new();
+        for index_uid in self.db.indexes_uids() {
+            let index = match self.db.open_index(&index_uid) {
+                Some(index) => index,
+                None => continue,
+            };
+
+            let current_update_id = index.current_update_id(&reader)?;
+            let enqueued_updates = index
+                .all_updates_status(&reader)?
+                .into_iter()
+                .filter(|status| match status {
+                    UpdateStatus::Enqueued { .. } => true,
+                    UpdateStatus::Processed { .. } => false,
+                })
+                .collect();
+
+            let diagnostics = IndexDiagnostics {
+                current_update_id,
+                enqueued_updates,
+            };
+            indexes.insert(index_uid, diagnostics);
+        }
+
+        let snapshots = SnapshotsDiagnostics {
+            last_success: self.last_snapshot_success(&reader)?,
+            results: self.snapshots_results(&reader)?,
+        };
+
+        let data_size = fs::metadata(Path::new(&self.db_path).join("data.mdb"))
+            .map(|metadata| metadata.len())
+            .ok();
+
+        let caches = CachesDiagnostics {
+            dfa_cache_len: dfa_cache_len(),
+            catalog_len: self.catalog.len(),
+            usage_counters_len: self.usage.len(),
+        };
+
+        Ok(Diagnostics {
+            generated_at: Utc::now(),
+            read_only: self.db.is_read_only(),
+            indexes,
+            snapshots,
+            lmdb: LmdbDiagnostics {
+                map_size: MAP_SIZE,
+                data_size,
+            },
+            caches,
+            in_flight_queries: self.queries.traces(),
+            open_circuits: self.circuit_breaker.open_circuits(),
+        })
+    }
+}
+
+fn diagnostics_path(dir: &Path, datetime: DateTime<Utc>) -> PathBuf {
+    let name = format!(
+        "{}{}.{}",
+        DIAGNOSTICS_FILE_PREFIX,
+        datetime.format("%Y%m%d%H%M%S%3f"),
+        DIAGNOSTICS_FILE_EXTENSION,
+    );
+    dir.join(name)
+}
+
+/// Writes the diagnostics in a new file of the directory and returns its path.
+pub fn write_diagnostics(diagnostics: &Diagnostics, dir: &Path) -> io::Result<PathBuf> {
+    fs::create_dir_all(dir)?;
+
+    let path = diagnostics_path(dir, diagnostics.generated_at);
+    let file = File::create(&path)?;
+    serde_json::to_writer_pretty(file, diagnostics)
+        .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn in_flight_queries() {
+        let queries = InFlightQueries::default();
+
+        let first = queries.start("movies", "star wars");
+        {
+            let _second = queries.start("books", "dune");
+            assert_eq!(queries.traces().len(), 2);
+        }
+
+        let traces = queries.traces();
+        assert_eq!(traces.len(), 1);
+        assert_eq!(traces[0].index_uid, "movies");
+        assert_eq!(traces[0].query, "star wars");
+
+        drop(first);
+        assert!(queries.traces().is_empty());
+    }
+}
@@ -0,0 +1,87 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+use log::{info, warn};
+use sysinfo::{DiskExt, System, SystemExt};
+
+use crate::data::Data;
+
+#[derive(Debug, Clone)]
+pub struct DiskPolicy {
+    /// The free space in bytes under which the database becomes read-only.
+    pub min_free_space: u64,
+    pub interval: Duration,
+}
+
+/// The available space of the disk on which the path is, the disk
+/// is the one with the longest mount point containing the path.
+fn available_space(system: &System, path: &Path) -> Option<u64> {
+    system
+        .get_disks()
+        .iter()
+        .filter(|disk| path.starts_with(disk.get_mount_point()))
+        .max_by_key(|disk| disk.get_mount_point().as_os_str().len())
+        .map(|disk| disk.get_available_space())
+}
+
+/// Whether the database must be read-only, a space that cannot be measured
+/// does not change the current mode.
+fn must_be_read_only(available: Option<u64>, policy: &DiskPolicy, read_only: bool) -> bool {
+    match available {
+        Some(available) => available < policy.min_free_space,
+        None => read_only,
+    }
+}
+
+/// Spawns a thread that periodically checks the free space of the database volume,
+/// the database is switched to read-only when it is too low and is made writable
+/// again when enough space is freed.
+pub fn schedule_disk_checks(data: Data, policy: DiskPolicy) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        let db_path =
+            fs::canonicalize(&data.db_path).unwrap_or_else(|_| PathBuf::from(&data.db_path));
+        let mut system = System::new();
+
+        loop {
+            system.refresh_disks();
+
+            let available = available_space(&system, &db_path);
+            let was_read_only = data.db.is_read_only();
+            let read_only = must_be_read_only(available, &policy, was_read_only);
+
+            if read_only != was_read_only {
+                data.db.set_read_only(read_only);
+                if read_only {
+                    warn!(
+                        "only {} bytes left on the database disk, the database is now read-only",
+                        available.unwrap_or(0)
+                    );
+                } else {
+                    info!("enough disk space has been freed, the database is writable again");
+                }
+            }
+
+            thread::sleep(policy.interval);
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_only_threshold() {
+        let policy = DiskPolicy {
+            min_free_space: 1024,
+            interval: Duration::from_secs(1),
+        };
+
+        assert!(must_be_read_only(Some(512), &policy, false));
+        assert!(!must_be_read_only(Some(2048), &policy, true));
+        assert!(must_be_read_only(None, &policy, true));
+        assert!(!must_be_read_only(None, &policy, false));
+    }
+}
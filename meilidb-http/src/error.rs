@@ -19,7 +19,10 @@ pub enum ResponseError {
     BadParameter(String, String),
     OpenIndex(String),
     CreateIndex(String),
+    PayloadTooLarge(String),
+    UnsupportedEncoding(String),
     Maintenance,
+    ReadOnly,
 }
 
 impl ResponseError {
@@ -62,6 +65,14 @@ impl ResponseError {
     pub fn create_index(message: impl Display) -> ResponseError {
         ResponseError::CreateIndex(message.to_string())
     }
+
+    pub fn payload_too_large(message: impl Display) -> ResponseError {
+        ResponseError::PayloadTooLarge(message.to_string())
+    }
+
+    pub fn unsupported_encoding(message: impl Display) -> ResponseError {
+        ResponseError::UnsupportedEncoding(message.to_string())
+    }
 }
 
 impl IntoResponse for ResponseError {
@@ -105,10 +116,20 @@ impl IntoResponse for ResponseError {
                 format!("Impossible to open index; {}", err),
                 StatusCode::BAD_REQUEST,
             ),
+            ResponseError::PayloadTooLarge(err) => error(err, StatusCode::PAYLOAD_TOO_LARGE),
+            ResponseError::UnsupportedEncoding(err) => {
+                error(err, StatusCode::UNSUPPORTED_MEDIA_TYPE)
+            }
             ResponseError::Maintenance => error(
                 String::from("Server is in maintenance, please try again later"),
                 StatusCode::SERVICE_UNAVAILABLE,
             ),
+            ResponseError::ReadOnly => error(
+                String::from(
+                    "The database is read-only because of a low disk space, please try again later",
+                ),
+                StatusCode::INSUFFICIENT_STORAGE,
+            ),
         }
     }
 }
@@ -0,0 +1,173 @@
+use std::borrow::Cow;
+use std::io;
+use std::vec;
+
+use bytes::Bytes;
+use http::header::CONTENT_TYPE;
+use http::StatusCode;
+use http_service::Body;
+use serde::Serialize;
+use serde_json::Value;
+use tide::Response;
+
+/// The size from which the serialized pieces are sent to the client.
+const CHUNK_SIZE: usize = 16 * 1024;
+
+/// The depth up to which the arrays and objects are split into pieces, it is enough
+/// to serialize the hits of the searches one by one, even for the multi-index ones.
+const MAX_SPLIT_DEPTH: usize = 2;
+
+enum Piece<T> {
+    Raw(Cow<'static, str>),
+    Item(T),
+}
+
+/// Serializes a JSON body piece by piece, only when the previous chunk has been sent,
+/// the whole body is never written in memory at once.
+pub struct JsonChunks<T> {
+    pieces: vec::IntoIter<Piece<T>>,
+}
+
+impl<T: Serialize> JsonChunks<T> {
+    pub fn array(items: Vec<T>) -> JsonChunks<T> {
+        let mut pieces = Vec::with_capacity(items.len() * 2 + 1);
+
+        pieces.push(Piece::Raw(Cow::Borrowed("[")));
+        for (i, item) in items.into_iter().enumerate() {
+            if i != 0 {
+                pieces.push(Piece::Raw(Cow::Borrowed(",")));
+            }
+            pieces.push(Piece::Item(item));
+        }
+        pieces.push(Piece::Raw(Cow::Borrowed("]")));
+
+        JsonChunks {
+            pieces: pieces.into_iter(),
+        }
+    }
+}
+
+impl JsonChunks<Value> {
+    pub fn value(value: Value) -> JsonChunks<Value> {
+        let mut pieces = Vec::new();
+        split_value(value, 0, &mut pieces);
+
+        JsonChunks {
+            pieces: pieces.into_iter(),
+        }
+    }
+}
+
+fn split_value(value: Value, depth: usize, pieces: &mut Vec<Piece<Value>>) {
+    if depth > MAX_SPLIT_DEPTH {
+        pieces.push(Piece::Item(value));
+        return;
+    }
+
+    match value {
+        Value::Array(values) => {
+            pieces.push(Piece::Raw(Cow::Borrowed("[")));
+            for (i, value) in values.into_iter().enumerate() {
+                if i != 0 {
+                    pieces.push(Piece::Raw(Cow::Borrowed(",")));
+                }
+                split_value(value, depth + 1, pieces);
+            }
+            pieces.push(Piece::Raw(Cow::Borrowed("]")));
+        }
+        Value::Object(map) => {
+            pieces.push(Piece::Raw(Cow::Borrowed("{")));
+            for (i, (key, value)) in map.into_iter().enumerate() {
+                let separator = if i == 0 { "" } else { "," };
+                let key = Value::String(key).to_string();
+                pieces.push(Piece::Raw(Cow::Owned(format!("{}{}:", separator, key))));
+                split_value(value, depth + 1, pieces);
+            }
+            pieces.push(Piece::Raw(Cow::Borrowed("}")));
+        }
+        value => pieces.push(Piece::Item(value)),
+    }
+}
+
+impl<T: Serialize> Iterator for JsonChunks<T> {
+    type Item = serde_json::Result<Vec<u8>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut chunk = Vec::new();
+
+        while chunk.len() < CHUNK_SIZE {
+            match self.pieces.next() {
+                Some(Piece::Raw(raw)) => chunk.extend_from_slice(raw.as_bytes()),
+                Some(Piece::Item(item)) => {
+                    if let Err(e) = serde_json::to_writer(&mut chunk, &item) {
+                        return Some(Err(e));
+                    }
+                }
+                None => break,
+            }
+        }
+
+        if chunk.is_empty() {
+            None
+        } else {
+            Some(Ok(chunk))
+        }
+    }
+}
+
+/// A JSON response sent with a chunked transfer encoding, the chunks
+/// are serialized as the client connection is ready to receive them.
+pub fn json_stream<T>(chunks: JsonChunks<T>) -> Response
+where
+    T: Serialize + Send + 'static,
+{
+    let chunks = chunks.map(|result| match result {
+        Ok(chunk) => Ok(Bytes::from(chunk)),
+        Err(e) => Err(io::Error::new(io::ErrorKind::Other, e)),
+    });
+
+    http::Response::builder()
+        .status(StatusCode::OK)
+        .header(CONTENT_TYPE, "application/json")
+        .body(Body::from_stream(futures::stream::iter(chunks)))
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn concat<T: Serialize>(chunks: JsonChunks<T>) -> String {
+        let bytes: Vec<u8> = chunks.map(Result::unwrap).flatten().collect();
+        String::from_utf8(bytes).unwrap()
+    }
+
+    #[test]
+    fn chunked_value() {
+        let hits: Vec<_> = (0..2000)
+            .map(|i| json!({ "id": i, "title": "Marvin the paranoid android" }))
+            .collect();
+        let value = json!({
+            "hits": hits,
+            "nested": { "movies": [{ "id": 1 }], "empty": [] },
+            "query": "mar\"vin",
+            "limit": 2000,
+        });
+
+        let expected = value.to_string();
+        let chunks = JsonChunks::value(value);
+        assert!(chunks.pieces.len() > 2000);
+        assert_eq!(concat(chunks), expected);
+    }
+
+    #[test]
+    fn chunked_array() {
+        let documents = vec![Some(json!({ "id": 1 })), None, Some(json!({ "id": 3 }))];
+        let expected = serde_json::to_string(&documents).unwrap();
+        assert_eq!(concat(JsonChunks::array(documents)), expected);
+
+        let empty: Vec<Value> = Vec::new();
+        assert_eq!(concat(JsonChunks::array(empty)), "[]");
+    }
+}
@@ -1,29 +1,48 @@
-use crate::routes::setting::{RankingOrdering, SettingBody};
+use crate::models::token::{match_wildcard, retain_allowed_attributes, Wildcard};
 use indexmap::IndexMap;
-use log::error;
+use log::{error, warn};
 use meilidb_core::criterion::*;
+use meilidb_core::serde::DocumentKey;
 use meilidb_core::Highlight;
-use meilidb_core::{Index, RankedMap};
+use meilidb_core::{AutomatonOrigin, Explanation, Number, QueryRewrite as CoreQueryRewrite};
+use meilidb_core::{DocumentRetrieval, GeoPoint, Index, PatternOptions, RankedMap};
+use meilidb_core::{FacetValues, QueryResult};
+use meilidb_dto::{
+    FacetCount, HitExplanation, MatchExplanation, MatchOrigin, MatchPosition, MatchesInfos,
+    ProcessingTimeBreakdown, QueryRewrite, RankingOrdering, RankingScore, SearchHit, SearchResult,
+    SettingBody,
+};
 use meilidb_schema::{Schema, SchemaAttr};
-use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::cmp::Ordering;
+use std::cmp;
+use std::collections::hash_map::DefaultHasher;
 use std::collections::{HashMap, HashSet};
 use std::convert::From;
 use std::error;
 use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::Range;
 use std::time::{Duration, Instant};
 
+pub const DEFAULT_HIGHLIGHT_PRE_TAG: &str = "<em>";
+pub const DEFAULT_HIGHLIGHT_POST_TAG: &str = "</em>";
+pub const MAX_DIVERSITY_STRENGTH: usize = 100;
+/// The number of best ranked documents the values of the facets are counted among,
+/// unless the requested hits go further.
+pub const FACETS_DOCUMENTS_LIMIT: usize = 1000;
+
 #[derive(Debug)]
 pub enum Error {
     SearchDocuments(String),
     RetrieveDocument(u64, String),
-    DocumentNotFound(u64),
     CropFieldWrongType(String),
     AttributeNotFoundOnDocument(String),
     AttributeNotFoundOnSchema(String),
     MissingFilterValue,
     UnknownFilteredAttribute,
+    InvalidGeoPoint(String),
+    UnknownRankingProfile(String),
+    InvalidDiversityStrength(usize),
     Internal(String),
 }
 
@@ -40,7 +59,6 @@ impl fmt::Display for Error {
                 "impossible to retrieve the document with id: {}; {}",
                 id, err
             ),
-            DocumentNotFound(id) => write!(f, "document {} not found", id),
             CropFieldWrongType(field) => {
                 write!(f, "the field {} cannot be cropped it's not a string", field)
             }
@@ -52,6 +70,17 @@ impl fmt::Display for Error {
             UnknownFilteredAttribute => {
                 f.write_str("a filter is specifying an unknown schema attribute")
             }
+            InvalidGeoPoint(point) => write!(
+                f,
+                "{} is not a valid location, it must be a latitude and a longitude like 48.85,2.35",
+                point
+            ),
+            UnknownRankingProfile(name) => write!(f, "the ranking profile {} does not exist", name),
+            InvalidDiversityStrength(strength) => write!(
+                f,
+                "the diversity strength {} is invalid, it must be between 1 and {}",
+                strength, MAX_DIVERSITY_STRENGTH
+            ),
             Internal(err) => write!(f, "internal error; {}", err),
         }
     }
@@ -77,10 +106,26 @@ impl IndexSearchExt for Index {
             attributes_to_crop: None,
             attributes_to_retrieve: None,
             attributes_to_search_in: None,
+            restrict_searchable_attributes: None,
             attributes_to_highlight: None,
+            highlight_pre_tag: DEFAULT_HIGHLIGHT_PRE_TAG.to_string(),
+            highlight_post_tag: DEFAULT_HIGHLIGHT_POST_TAG.to_string(),
             filters: None,
+            required_attribute: None,
+            documents_ids: None,
+            around: None,
             timeout: Duration::from_millis(30),
+            memory_limit: None,
             matches: false,
+            patterns: false,
+            prefix: None,
+            diversity: None,
+            ranking_profile: None,
+            escape_html: None,
+            allowed_attributes: None,
+            debug: false,
+            buckets: false,
+            facets: None,
         }
     }
 }
@@ -93,10 +138,26 @@ pub struct SearchBuilder<'a> {
     attributes_to_crop: Option<HashMap<String, usize>>,
     attributes_to_retrieve: Option<HashSet<String>>,
     attributes_to_search_in: Option<HashSet<String>>,
+    restrict_searchable_attributes: Option<HashSet<String>>,
     attributes_to_highlight: Option<HashSet<String>>,
+    highlight_pre_tag: String,
+    highlight_post_tag: String,
     filters: Option<String>,
+    required_attribute: Option<String>,
+    documents_ids: Option<Vec<String>>,
+    around: Option<(String, Option<u64>)>,
     timeout: Duration,
+    memory_limit: Option<usize>,
     matches: bool,
+    patterns: bool,
+    prefix: Option<bool>,
+    diversity: Option<(String, usize)>,
+    ranking_profile: Option<String>,
+    escape_html: Option<bool>,
+    allowed_attributes: Option<Vec<Wildcard>>,
+    debug: bool,
+    buckets: bool,
+    facets: Option<Vec<String>>,
 }
 
 impl<'a> SearchBuilder<'a> {
@@ -137,30 +198,130 @@ impl<'a> SearchBuilder<'a> {
         self
     }
 
+    pub fn restrict_searchable_attributes(&mut self, value: HashSet<String>) -> &SearchBuilder {
+        self.restrict_searchable_attributes = Some(value);
+        self
+    }
+
     pub fn attributes_to_highlight(&mut self, value: HashSet<String>) -> &SearchBuilder {
         self.attributes_to_highlight = Some(value);
         self
     }
 
+    /// The text inserted before the highlighted matches, it is never HTML-escaped.
+    pub fn highlight_pre_tag(&mut self, value: String) -> &SearchBuilder {
+        self.highlight_pre_tag = value;
+        self
+    }
+
+    /// The text inserted after the highlighted matches, it is never HTML-escaped.
+    pub fn highlight_post_tag(&mut self, value: String) -> &SearchBuilder {
+        self.highlight_post_tag = value;
+        self
+    }
+
     pub fn filters(&mut self, value: String) -> &SearchBuilder {
         self.filters = Some(value);
         self
     }
 
+    /// Returns only the documents where one of the query words matches in the attribute.
+    pub fn required_attribute(&mut self, value: String) -> &SearchBuilder {
+        self.required_attribute = Some(value);
+        self
+    }
+
+    /// Searches only in the documents with these identifiers.
+    pub fn documents_ids(&mut self, value: Vec<String>) -> &SearchBuilder {
+        self.documents_ids = Some(value);
+        self
+    }
+
+    /// Sorts the documents by their distance to the location, a latitude and a longitude
+    /// separated by a comma, and keeps only the ones closer than the radius in meters.
+    pub fn around(&mut self, lat_lng: String, radius: Option<u64>) -> &SearchBuilder {
+        self.around = Some((lat_lng, radius));
+        self
+    }
+
     pub fn timeout(&mut self, value: Duration) -> &SearchBuilder {
         self.timeout = value;
         self
     }
 
+    /// Bounds the memory used to buffer the matches, the rest is spilled to disk.
+    pub fn memory_limit(&mut self, bytes: usize) -> &SearchBuilder {
+        self.memory_limit = Some(bytes);
+        self
+    }
+
     pub fn get_matches(&mut self) -> &SearchBuilder {
         self.matches = true;
         self
     }
 
+    pub fn patterns(&mut self) -> &SearchBuilder {
+        self.patterns = true;
+        self
+    }
+
+    /// Overrides the prefix search setting of the index for this search.
+    pub fn prefix(&mut self, value: bool) -> &SearchBuilder {
+        self.prefix = Some(value);
+        self
+    }
+
+    /// Avoids consecutive results sharing the same value for the given attribute,
+    /// up to `strength` following results can be promoted to break a series,
+    /// the strength must be between 1 and `MAX_DIVERSITY_STRENGTH`.
+    pub fn diversity(&mut self, attribute: String, strength: usize) -> &SearchBuilder {
+        self.diversity = Some((attribute, strength));
+        self
+    }
+
+    /// Sorts the documents with the ranking rules of this profile of the index
+    /// instead of the ones of the settings.
+    pub fn ranking_profile(&mut self, name: String) -> &SearchBuilder {
+        self.ranking_profile = Some(name);
+        self
+    }
+
+    /// Overrides the HTML escaping setting of the index for this search.
+    pub fn escape_html(&mut self, value: bool) -> &SearchBuilder {
+        self.escape_html = Some(value);
+        self
+    }
+
+    /// Reports the time spent in each phase of the search in the result.
+    pub fn debug(&mut self) -> &SearchBuilder {
+        self.debug = true;
+        self
+    }
+
+    /// Returns the offsets in the hits at which the ranking buckets start.
+    pub fn buckets(&mut self) -> &SearchBuilder {
+        self.buckets = true;
+        self
+    }
+
+    /// Counts the values of these attributes among the best ranked documents, the booleans
+    /// are counted under `true` and `false` and each value of an array is counted.
+    pub fn facets(&mut self, attributes: Vec<String>) -> &SearchBuilder {
+        self.facets = Some(attributes);
+        self
+    }
+
+    /// Restricts the attributes that can be retrieved and highlighted
+    /// to the ones matching the given wildcard patterns.
+    pub fn allowed_attributes(&mut self, value: Vec<Wildcard>) -> &SearchBuilder {
+        self.allowed_attributes = Some(value);
+        self
+    }
+
     pub fn search(&self, reader: &heed::RoTxn) -> Result<SearchResult, Error> {
-        let schema = self.index.main.schema(reader);
-        let schema = schema.map_err(|e| Error::Internal(e.to_string()))?;
-        let schema = match schema {
+        // the same schema is used from the query to the documents reconstruction
+        let snapshot = self.index.schema_snapshot();
+        let schema = match snapshot.schema() {
             Some(schema) => schema,
             None => return Err(Error::Internal(String::from("missing schema"))),
         };
@@ -170,9 +331,34 @@ impl<'a> SearchBuilder<'a> {
         let ranked_map = ranked_map.unwrap_or_default();
 
         let start = Instant::now();
+        let mut warnings = Vec::new();
+
+        let around = match &self.around {
+            Some((lat_lng, radius)) => match parse_lat_lng(lat_lng) {
+                Some(point) => Some((point, *radius)),
+                None => return Err(Error::InvalidGeoPoint(lat_lng.clone())),
+            },
+            None => None,
+        };
+
+        let geo_index = match around {
+            Some(_) => self.index.main.geo_index(reader),
+            None => Ok(None),
+        };
+        let geo_index = geo_index.map_err(|e| Error::Internal(e.to_string()))?;
+        let geo_index = geo_index.unwrap_or_default();
+        let distance = around.map(|(origin, _)| SortByDistance::new(&geo_index, origin));
 
         // Change criteria
-        let mut query_builder = match self.get_criteria(reader, &ranked_map, &schema)? {
+        let criteria = match &self.ranking_profile {
+            Some(name) => {
+                let criteria =
+                    self.profile_criteria(reader, name, &ranked_map, schema, distance)?;
+                Some(criteria)
+            }
+            None => self.get_criteria(reader, &ranked_map, schema, distance, &mut warnings)?,
+        };
+        let mut query_builder = match criteria {
             Some(criteria) => self.index.query_builder_with_criteria(criteria),
             None => self.index.query_builder(),
         };
@@ -184,25 +370,46 @@ impl<'a> SearchBuilder<'a> {
             }
         }
 
+        // Restrict the matches to some of the searchable fields
+        if let Some(fields) = &self.restrict_searchable_attributes {
+            let attributes = fields.iter().filter_map(|f| schema.attribute(f));
+            query_builder.restrict_searchable_attributes(attributes);
+        }
+
         if let Some(filters) = &self.filters {
             let mut split = filters.split(':');
             match (split.next(), split.next()) {
                 (Some(_), None) | (Some(_), Some("")) => return Err(Error::MissingFilterValue),
-                (Some(attr), Some(value)) => {
+                (Some(attr_name), Some(value)) => {
                     let ref_reader = reader;
                     let ref_index = &self.index;
-                    let value = value.trim().to_lowercase();
+                    let ref_ranked_map = &ranked_map;
 
-                    let attr = match schema.attribute(attr) {
+                    let attr = match schema.attribute(attr_name) {
                         Some(attr) => attr,
                         None => return Err(Error::UnknownFilteredAttribute),
                     };
 
+                    // typed attributes are filtered on their canonical values
+                    let attributes_types = self.index.main.attributes_types(reader);
+                    let attributes_types = attributes_types
+                        .map_err(|e| Error::Internal(e.to_string()))?
+                        .unwrap_or_default();
+                    let typed_value = attributes_types
+                        .get(attr_name)
+                        .and_then(|t| t.parse_str(value));
+
+                    let value = value.trim().to_lowercase();
+
                     query_builder.with_filter(move |id| {
                         let attr = attr;
                         let index = ref_index;
                         let reader = ref_reader;
 
+                        if let Some(typed_value) = typed_value {
+                            return ref_ranked_map.get(id, attr) == Some(typed_value);
+                        }
+
                         match index.document_attribute::<Value>(reader, id, attr) {
                             Ok(Some(Value::String(s))) => s.to_lowercase() == value,
                             Ok(Some(Value::Bool(b))) => {
@@ -219,13 +426,119 @@ impl<'a> SearchBuilder<'a> {
             }
         }
 
+        if let Some((origin, Some(radius))) = around {
+            query_builder.within_radius(origin.lat, origin.lng, radius as f64);
+        }
+
+        if let Some(attribute) = &self.required_attribute {
+            match schema.attribute(attribute) {
+                Some(attr) => query_builder.with_required_attribute(attr),
+                None => return Err(Error::AttributeNotFoundOnSchema(attribute.clone())),
+            }
+        }
+
+        if let Some(documents_ids) = &self.documents_ids {
+            // the identifiers are resolved like the identifiers of the indexed documents
+            let ids = documents_ids
+                .iter()
+                .map(|id| DocumentKey::String(id.trim().to_owned()).document_id());
+            query_builder.restrict_documents(ids);
+        }
+
         query_builder.with_fetch_timeout(self.timeout);
 
-        let docs =
-            query_builder.query(reader, &self.query, self.offset..(self.offset + self.limit));
+        if self.debug {
+            query_builder.explain();
+        }
+
+        if let Some(bytes) = self.memory_limit {
+            query_builder.with_memory_limit(bytes);
+        }
+
+        if self.patterns {
+            query_builder.with_patterns(PatternOptions::default());
+        }
+
+        if let Some(prefix) = self.prefix {
+            query_builder.with_prefix(prefix);
+        }
+
+        if let Some((attribute, strength)) = &self.diversity {
+            if *strength == 0 || *strength > MAX_DIVERSITY_STRENGTH {
+                return Err(Error::InvalidDiversityStrength(*strength));
+            }
+
+            let ref_reader = reader;
+            let ref_index = &self.index;
+
+            let attr = match schema.attribute(attribute) {
+                Some(attr) => attr,
+                None => return Err(Error::AttributeNotFoundOnSchema(attribute.clone())),
+            };
+
+            let diversity =
+                move |id| match ref_index.document_attribute::<Value>(ref_reader, id, attr) {
+                    Ok(Some(value)) => {
+                        let mut hasher = DefaultHasher::new();
+                        value.to_string().hash(&mut hasher);
+                        Some(hasher.finish())
+                    }
+                    _ => None,
+                };
+
+            query_builder.with_diversity(diversity, *strength);
+        }
+
+        // only the best ranked document of each value of the distinct field is returned
+        if let Some(attribute) = self.distinct_attribute(reader, schema, &mut warnings)? {
+            query_builder.with_distinct_attribute(attribute, 1);
+        }
+
+        let setup_time = start.elapsed();
+        let hits_range = self.offset..(self.offset + self.limit);
+
+        // the facets are counted among the best ranked documents, the hits are taken from them
+        let range = match self.facets {
+            Some(_) => 0..cmp::max(hits_range.end, FACETS_DOCUMENTS_LIMIT),
+            None => hits_range.clone(),
+        };
+
+        let mut result = query_builder
+            .query_with_buckets(reader, &self.query, range)
+            .map_err(|e| Error::SearchDocuments(e.to_string()))?;
+
+        let facets = match &self.facets {
+            Some(attributes) => {
+                let facets = self.facets_counts(reader, schema, attributes, &result)?;
+                retain_range(&mut result, hits_range);
+                Some(facets)
+            }
+            None => None,
+        };
+
+        let timings = result.timings;
+        let rewrites = result.rewrites.into_iter().map(query_rewrite).collect();
+        let buckets = if self.buckets {
+            Some(result.buckets_starts)
+        } else {
+            None
+        };
+
+        let escape_html = match self.escape_html {
+            Some(escape_html) => escape_html,
+            None => {
+                let escape_html = self.index.main.html_escaping(reader);
+                let escape_html = escape_html.map_err(|e| Error::Internal(e.to_string()))?;
+                escape_html.unwrap_or(false)
+            }
+        };
 
+        let formatting_start = Instant::now();
         let mut hits = Vec::with_capacity(self.limit);
-        for doc in docs.map_err(|e| Error::SearchDocuments(e.to_string()))? {
+        let mut explanations = result.explanations.into_iter();
+        for doc in result.documents {
+            let explanation = explanations.next();
+
             // retrieve the content of document in kv store
             let mut fields: Option<HashSet<&str>> = None;
             if let Some(attributes_to_retrieve) = &self.attributes_to_retrieve {
@@ -236,167 +549,474 @@ impl<'a> SearchBuilder<'a> {
                 fields = Some(set);
             }
 
-            let document: IndexMap<String, Value> = self
+            let retrieval = self
                 .index
-                .document(reader, fields.as_ref(), doc.id)
-                .map_err(|e| Error::RetrieveDocument(doc.id.0, e.to_string()))?
-                .ok_or(Error::DocumentNotFound(doc.id.0))?;
+                .retrieve_document(reader, &snapshot, fields.as_ref(), doc.id)
+                .map_err(|e| Error::RetrieveDocument(doc.id.0, e.to_string()))?;
+
+            // a hit is never returned without its document
+            let mut document: IndexMap<String, Value> = match retrieval {
+                DocumentRetrieval::Found(document) => document,
+                DocumentRetrieval::Gone => {
+                    warnings.push(format!("the document {} has been deleted", doc.id.0));
+                    continue;
+                }
+            };
 
             let mut formatted = document.clone();
             let mut matches = doc.highlights.clone();
 
             // Crops fields if needed
             if let Some(fields) = &self.attributes_to_crop {
-                crop_document(&mut formatted, &mut matches, &schema, fields);
+                crop_document(&mut formatted, &mut matches, schema, fields);
             }
 
             // Transform to readable matches
-            let matches = calculate_matches(matches, self.attributes_to_retrieve.clone(), &schema);
-
-            if !self.matches {
-                if let Some(attributes_to_highlight) = &self.attributes_to_highlight {
-                    formatted = calculate_highlights(&formatted, &matches, attributes_to_highlight);
+            let matches = calculate_matches(matches, self.attributes_to_retrieve.clone(), schema);
+
+            match &self.attributes_to_highlight {
+                Some(attributes_to_highlight) if !self.matches => {
+                    formatted = calculate_highlights(
+                        &formatted,
+                        &matches,
+                        attributes_to_highlight,
+                        (&self.highlight_pre_tag, &self.highlight_post_tag),
+                        escape_html,
+                    );
                 }
+                _ if escape_html => formatted.values_mut().for_each(escape_value),
+                _ => (),
             }
 
-            let matches_info = if self.matches { Some(matches) } else { None };
+            let mut matches_info = if self.matches { Some(matches) } else { None };
+            let mut explain = explanation.map(|e| hit_explanation(e, schema));
+
+            // Restricted attributes must never be serialized
+            if let Some(patterns) = &self.allowed_attributes {
+                retain_allowed_attributes(patterns, &mut document);
+                retain_allowed_attributes(patterns, &mut formatted);
+                if let Some(matches_info) = &mut matches_info {
+                    matches_info.retain(|attribute, _| {
+                        patterns.iter().any(|p| match_wildcard(p, attribute))
+                    });
+                }
+                if let Some(explain) = &mut explain {
+                    explain
+                        .matches
+                        .retain(|m| patterns.iter().any(|p| match_wildcard(p, &m.attribute)));
+                }
+            }
 
             let hit = SearchHit {
                 document,
                 formatted,
                 matches_info,
+                explain,
             };
 
             hits.push(hit);
         }
 
+        let formatting_time = formatting_start.elapsed();
         let time_ms = start.elapsed().as_millis() as usize;
 
+        // the serialization happens once the response is shaped, it is measured by the caller
+        let processing_time_breakdown = if self.debug {
+            Some(ProcessingTimeBreakdown {
+                parse_ms: duration_ms(setup_time + timings.parse),
+                candidates_ms: duration_ms(timings.candidates),
+                ranking_ms: duration_ms(timings.ranking),
+                formatting_ms: duration_ms(formatting_time),
+                serialization_ms: 0.0,
+            })
+        } else {
+            None
+        };
+
         let results = SearchResult {
             hits,
             offset: self.offset,
             limit: self.limit,
             processing_time_ms: time_ms,
             query: self.query.to_string(),
+            processing_time_breakdown,
+            buckets,
+            facets,
+            warnings,
+            rewrites,
         };
 
         Ok(results)
     }
 
+    /// Counts the values of the stored fields of the attributes among the documents
+    /// of the result, the attributes that cannot be retrieved are refused.
+    fn facets_counts(
+        &self,
+        reader: &heed::RoTxn,
+        schema: &Schema,
+        attributes: &[String],
+        result: &QueryResult,
+    ) -> Result<HashMap<String, Vec<FacetCount>>, Error> {
+        let mut facets = HashMap::new();
+
+        let normalization = self
+            .index
+            .main
+            .facets_normalization(reader)
+            .map_err(|e| Error::Internal(e.to_string()))?
+            .unwrap_or_default();
+
+        for attribute in attributes {
+            let allowed = match &self.allowed_attributes {
+                Some(patterns) => patterns.iter().any(|p| match_wildcard(p, attribute)),
+                None => true,
+            };
+
+            let attr = match schema.attribute(attribute) {
+                Some(attr) if allowed => attr,
+                _ => return Err(Error::AttributeNotFoundOnSchema(attribute.clone())),
+            };
+
+            let policy = normalization.get(attribute).cloned().unwrap_or_default();
+            let mut values = FacetValues::new(policy);
+            for document in &result.documents {
+                let value = self
+                    .index
+                    .document_attribute::<Value>(reader, document.id, attr)
+                    .map_err(|e| Error::Internal(e.to_string()))?;
+
+                if let Some(value) = value {
+                    values.insert_value(&value);
+                }
+            }
+
+            let counts = values
+                .iter()
+                .map(|(value, count)| FacetCount {
+                    value: value.to_owned(),
+                    count,
+                })
+                .collect();
+
+            facets.insert(attribute.clone(), counts);
+        }
+
+        Ok(facets)
+    }
+
+    /// The criteria of the ranking rules of a profile of the index, the distance
+    /// is placed after them when the documents are sorted around a location.
+    pub fn profile_criteria(
+        &self,
+        reader: &heed::RoTxn,
+        name: &str,
+        ranked_map: &'a RankedMap,
+        schema: &Schema,
+        distance: Option<SortByDistance<'a>>,
+    ) -> Result<Criteria<'a>, Error> {
+        let profiles = self
+            .index
+            .main
+            .ranking_profiles(reader)
+            .map_err(|e| Error::Internal(e.to_string()))?;
+
+        let rules = match profiles.as_ref().and_then(|profiles| profiles.get(name)) {
+            Some(rules) => rules,
+            None => return Err(Error::UnknownRankingProfile(name.to_owned())),
+        };
+
+        let mut builder = RankingRule::criteria_builder(rules, ranked_map, schema);
+        if let Some(distance) = distance {
+            builder.push(distance);
+        }
+
+        builder.push(DocumentId);
+        Ok(builder.build())
+    }
+
+    fn current_settings(&self, reader: &heed::RoTxn) -> Result<SettingBody, Error> {
+        let customs = self
+            .index
+            .main
+            .customs(reader)
+            .map_err(|e| Error::Internal(e.to_string()))?;
+
+        match customs {
+            Some(bytes) => bincode::deserialize(bytes).map_err(|e| Error::Internal(e.to_string())),
+            None => Ok(SettingBody::default()),
+        }
+    }
+
+    /// The distinct field of the settings of the index, a field that is
+    /// not in the schema is ignored and a warning is pushed instead.
+    fn distinct_attribute(
+        &self,
+        reader: &heed::RoTxn,
+        schema: &Schema,
+        warnings: &mut Vec<String>,
+    ) -> Result<Option<SchemaAttr>, Error> {
+        let distinct_field = match self.current_settings(reader)?.distinct_field {
+            Some(distinct_field) => distinct_field,
+            None => return Ok(None),
+        };
+
+        match schema.attribute(&distinct_field) {
+            Some(attribute) => Ok(Some(attribute)),
+            None => {
+                let warning = format!(
+                    "the distinct field {} is not in the schema, the results are not deduplicated",
+                    distinct_field
+                );
+                warnings.push(warning);
+                Ok(None)
+            }
+        }
+    }
+
+    /// The criteria of the ranking rules of the index, the custom rules that cannot be
+    /// applied are skipped and the reason is pushed to the warnings of the search.
     pub fn get_criteria(
         &self,
         reader: &heed::RoTxn,
         ranked_map: &'a RankedMap,
         schema: &Schema,
+        mut distance: Option<SortByDistance<'a>>,
+        warnings: &mut Vec<String>,
     ) -> Result<Option<Criteria<'a>>, Error> {
-        let current_settings = match self.index.main.customs(reader).unwrap() {
-            Some(bytes) => bincode::deserialize(bytes).unwrap(),
-            None => SettingBody::default(),
-        };
+        let current_settings = self.current_settings(reader)?;
 
         let ranking_rules = &current_settings.ranking_rules;
         let ranking_order = &current_settings.ranking_order;
 
-        if let Some(ranking_rules) = ranking_rules {
-            let mut builder = CriteriaBuilder::with_capacity(7 + ranking_rules.len());
-            if let Some(ranking_rules_order) = ranking_order {
-                for rule in ranking_rules_order {
-                    match rule.as_str() {
-                        "_sum_of_typos" => builder.push(SumOfTypos),
-                        "_number_of_words" => builder.push(NumberOfWords),
-                        "_word_proximity" => builder.push(WordsProximity),
-                        "_sum_of_words_attribute" => builder.push(SumOfWordsAttribute),
-                        "_sum_of_words_position" => builder.push(SumOfWordsPosition),
-                        "_exact" => builder.push(Exact),
-                        _ => {
-                            let order = match ranking_rules.get(rule.as_str()) {
-                                Some(o) => o,
-                                None => continue,
-                            };
-
-                            let custom_ranking = match order {
-                                RankingOrdering::Asc => {
-                                    SortByAttr::lower_is_better(&ranked_map, &schema, &rule)
-                                        .unwrap()
-                                }
-                                RankingOrdering::Dsc => {
-                                    SortByAttr::higher_is_better(&ranked_map, &schema, &rule)
-                                        .unwrap()
-                                }
-                            };
-
-                            builder.push(custom_ranking);
-                        }
-                    }
+        let ranking_rules = match ranking_rules {
+            Some(ranking_rules) => ranking_rules,
+            None => match distance {
+                Some(distance) => {
+                    let criteria = CriteriaBuilder::with_capacity(8)
+                        .add(SumOfTypos)
+                        .add(NumberOfWords)
+                        .add(WordsProximity)
+                        .add(SumOfWordsAttribute)
+                        .add(SumOfWordsPosition)
+                        .add(Exact)
+                        .add(distance)
+                        .add(DocumentId)
+                        .build();
+                    return Ok(Some(criteria));
                 }
-                builder.push(DocumentId);
-                return Ok(Some(builder.build()));
-            } else {
-                builder.push(SumOfTypos);
-                builder.push(NumberOfWords);
-                builder.push(WordsProximity);
-                builder.push(SumOfWordsAttribute);
-                builder.push(SumOfWordsPosition);
-                builder.push(Exact);
-                for (rule, order) in ranking_rules.iter() {
-                    let custom_ranking = match order {
-                        RankingOrdering::Asc => {
-                            SortByAttr::lower_is_better(&ranked_map, &schema, &rule).unwrap()
+                None => return Ok(None),
+            },
+        };
+
+        let number_of_documents = self
+            .index
+            .main
+            .number_of_documents(reader)
+            .map_err(|e| Error::Internal(e.to_string()))?;
+
+        let attributes_types = self
+            .index
+            .main
+            .attributes_types(reader)
+            .map_err(|e| Error::Internal(e.to_string()))?
+            .unwrap_or_default();
+
+        let mut sort_by = |rule: &str, order: &RankingOrdering| {
+            // the attributes of the types that are not sortable have no value in the ranked map
+            if let Some(attribute_type) = attributes_types.get(rule) {
+                if !attribute_type.is_sortable() {
+                    let warning = format!(
+                        "the ranking rule {} is ignored; the attribute is of the {:?} type",
+                        rule, attribute_type
+                    );
+                    warn!("{}", warning);
+                    warnings.push(warning);
+                    return None;
+                }
+            }
+
+            custom_ranking(
+                ranked_map,
+                schema,
+                rule,
+                order,
+                number_of_documents,
+                warnings,
+            )
+        };
+
+        let mut builder = CriteriaBuilder::with_capacity(7 + ranking_rules.len());
+        if let Some(ranking_rules_order) = ranking_order {
+            for rule in ranking_rules_order {
+                match rule.as_str() {
+                    "_sum_of_typos" => builder.push(SumOfTypos),
+                    "_number_of_words" => builder.push(NumberOfWords),
+                    "_word_proximity" => builder.push(WordsProximity),
+                    "_sum_of_words_attribute" => builder.push(SumOfWordsAttribute),
+                    "_sum_of_words_position" => builder.push(SumOfWordsPosition),
+                    "_exact" => builder.push(Exact),
+                    "_exactness" => builder.push(Exactness),
+                    "_geo_distance" => {
+                        if let Some(distance) = distance.take() {
+                            builder.push(distance);
                         }
-                        RankingOrdering::Dsc => {
-                            SortByAttr::higher_is_better(&ranked_map, &schema, &rule).unwrap()
+                    }
+                    _ => {
+                        let order = match ranking_rules.get(rule.as_str()) {
+                            Some(o) => o,
+                            None => continue,
+                        };
+
+                        if let Some(criterion) = sort_by(rule, order) {
+                            builder.push(criterion);
                         }
-                    };
-                    builder.push(custom_ranking);
+                    }
+                }
+            }
+        } else {
+            builder.push(SumOfTypos);
+            builder.push(NumberOfWords);
+            builder.push(WordsProximity);
+            builder.push(SumOfWordsAttribute);
+            builder.push(SumOfWordsPosition);
+            builder.push(Exact);
+            for (rule, order) in ranking_rules.iter() {
+                if let Some(criterion) = sort_by(rule, order) {
+                    builder.push(criterion);
                 }
-                builder.push(DocumentId);
-                return Ok(Some(builder.build()));
             }
         }
 
-        Ok(None)
+        // the distance is the last criterion when the ranking order does not place it
+        if let Some(distance) = distance {
+            builder.push(distance);
+        }
+
+        builder.push(DocumentId);
+        Ok(Some(builder.build()))
     }
 }
 
-#[derive(Debug, Clone, Eq, PartialEq, PartialOrd, Serialize, Deserialize)]
-pub struct MatchPosition {
-    pub start: usize,
-    pub length: usize,
+/// Sorts by the values of the attribute in the ranked map, the documents without a value are
+/// placed after the other ones. The rules on attributes that are not in the schema or not
+/// ranked, like after a schema change, are skipped instead of failing the search.
+fn custom_ranking<'a>(
+    ranked_map: &'a RankedMap,
+    schema: &Schema,
+    rule: &str,
+    order: &RankingOrdering,
+    number_of_documents: u64,
+    warnings: &mut Vec<String>,
+) -> Option<SortByAttr<'a>> {
+    let result = match order {
+        RankingOrdering::Asc => SortByAttr::lower_is_better(ranked_map, schema, rule),
+        RankingOrdering::Dsc => SortByAttr::higher_is_better(ranked_map, schema, rule),
+    };
+
+    let criterion = match result {
+        Ok(criterion) => criterion,
+        Err(e) => {
+            let warning = format!("the ranking rule {} is ignored; {}", rule, e);
+            warn!("{}", warning);
+            warnings.push(warning);
+            return None;
+        }
+    };
+
+    // the attribute is ranked, it has been checked by the criterion
+    let attribute = schema.attribute(rule)?;
+    let with_value = ranked_map.attribute_len(attribute) as u64;
+    if let Some(warning) = missing_values_warning(rule, with_value, number_of_documents) {
+        warn!("{}", warning);
+        warnings.push(warning);
+    }
+
+    Some(criterion)
 }
 
-impl Ord for MatchPosition {
-    fn cmp(&self, other: &Self) -> Ordering {
-        match self.start.cmp(&other.start) {
-            Ordering::Equal => self.length.cmp(&other.length),
-            _ => self.start.cmp(&other.start),
-        }
+fn missing_values_warning(rule: &str, with_value: u64, number_of_documents: u64) -> Option<String> {
+    if with_value >= number_of_documents {
+        return None;
     }
+
+    if with_value == 0 {
+        return Some(format!(
+            "no document has a value for the ranking rule {}, the documents \
+             may need to be reindexed to compute the ranked values",
+            rule
+        ));
+    }
+
+    Some(format!(
+        "{} of the {} documents have no value for the ranking rule {}, \
+         they are placed after the other ones",
+        number_of_documents - with_value,
+        number_of_documents,
+        rule
+    ))
+}
+
+/// Parses a location given as a latitude and a longitude separated by a comma.
+fn parse_lat_lng(lat_lng: &str) -> Option<GeoPoint> {
+    let mut split = lat_lng.split(',');
+    let lat = split.next()?.trim().parse().ok()?;
+    let lng = split.next()?.trim().parse().ok()?;
+    match split.next() {
+        Some(_) => None,
+        None => GeoPoint::new(lat, lng),
+    }
+}
+
+pub fn duration_ms(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
 }
 
-pub type HighlightInfos = HashMap<String, Value>;
-pub type MatchesInfos = HashMap<String, Vec<MatchPosition>>;
-// pub type RankingInfos = HashMap<String, u64>;
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct SearchHit {
-    #[serde(flatten)]
-    pub document: IndexMap<String, Value>,
-    #[serde(rename = "_formatted", skip_serializing_if = "IndexMap::is_empty")]
-    pub formatted: IndexMap<String, Value>,
-    #[serde(rename = "_matchesInfo", skip_serializing_if = "Option::is_none")]
-    pub matches_info: Option<MatchesInfos>,
+/// Keeps the documents of the range, along with what describes them,
+/// the first document kept starts a new bucket.
+fn retain_range(result: &mut QueryResult, range: Range<usize>) {
+    let end = cmp::min(range.end, result.documents.len());
+    let start = cmp::min(range.start, end);
+
+    result.documents = result.documents.drain(start..end).collect();
+    if result.explanations.len() >= end {
+        result.explanations = result.explanations.drain(start..end).collect();
+    }
+    if result.decisive_criteria.len() >= end {
+        result.decisive_criteria = result.decisive_criteria.drain(start..end).collect();
+    }
+
+    let mut buckets_starts = Vec::new();
+    if start < end {
+        buckets_starts.push(0);
+    }
+    let following = result
+        .buckets_starts
+        .iter()
+        .filter(|s| **s > start && **s < end);
+    buckets_starts.extend(following.map(|s| s - start));
+    result.buckets_starts = buckets_starts;
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
-pub struct SearchResult {
-    pub hits: Vec<SearchHit>,
-    pub offset: usize,
-    pub limit: usize,
-    pub processing_time_ms: usize,
-    pub query: String,
-    // pub parsed_query: String,
-    // pub params: Option<String>,
+/// The start of the window of `context * 2` characters that contains the most matches,
+/// the windows considered are centered on each of the matches, the first one wins the ties.
+fn best_crop_start(matches: &[Highlight], context: usize) -> usize {
+    let length = context * 2;
+    let fits = |start: usize, m: &Highlight| {
+        let m_start = m.char_index as usize;
+        m_start >= start && m_start + m.char_length as usize <= start + length
+    };
+
+    let mut best = None;
+    for m in matches {
+        let start = (m.char_index as usize).saturating_sub(context);
+        let count = matches.iter().filter(|m| fits(start, m)).count();
+        if best.map_or(true, |(best_count, _)| count > best_count) {
+            best = Some((count, start));
+        }
+    }
+
+    best.map_or(0, |(_, start)| start)
 }
 
 fn crop_text(
@@ -404,14 +1024,18 @@ fn crop_text(
     matches: impl IntoIterator<Item = Highlight>,
     context: usize,
 ) -> (String, Vec<Highlight>) {
-    let mut matches = matches.into_iter().peekable();
+    let matches: Vec<_> = matches.into_iter().collect();
+    let length = context * 2;
 
-    let char_index = matches.peek().map(|m| m.char_index as usize).unwrap_or(0);
-    let start = char_index.saturating_sub(context);
-    let text = text.chars().skip(start).take(context * 2).collect();
+    let start = best_crop_start(&matches, context);
+    let text = text.chars().skip(start).take(length).collect();
 
     let matches = matches
-        .take_while(|m| (m.char_index as usize) + (m.char_length as usize) <= start + (context * 2))
+        .into_iter()
+        .filter(|m| {
+            let m_start = m.char_index as usize;
+            m_start >= start && m_start + (m.char_length as usize) <= start + length
+        })
         .map(|match_| Highlight {
             char_index: match_.char_index - start as u16,
             ..match_
@@ -488,10 +1112,118 @@ fn calculate_matches(
     matches_result
 }
 
+/// Names the attributes of the matches and the numbers of the criteria of an explanation.
+fn hit_explanation(explanation: Explanation, schema: &Schema) -> HitExplanation {
+    let ranking = explanation
+        .criteria
+        .into_iter()
+        .map(|criterion| RankingScore {
+            name: criterion.name,
+            score: criterion.score.and_then(json_number),
+        })
+        .collect();
+
+    let matches = explanation
+        .matches
+        .into_iter()
+        .map(|m| MatchExplanation {
+            query: m.query,
+            origin: match m.origin {
+                AutomatonOrigin::Query => MatchOrigin::Query,
+                AutomatonOrigin::Synonym => MatchOrigin::Synonym,
+                AutomatonOrigin::Decompounding => MatchOrigin::Decompounding,
+                AutomatonOrigin::Split => MatchOrigin::Split,
+                AutomatonOrigin::Concatenation => MatchOrigin::Concatenation,
+            },
+            word: m.word,
+            typos: m.typos,
+            penalty: m.penalty,
+            attribute: schema.attribute_name(m.attribute).to_string(),
+            word_index: m.word_index,
+            is_exact: m.is_exact,
+        })
+        .collect();
+
+    HitExplanation { ranking, matches }
+}
+
+fn query_rewrite(rewrite: CoreQueryRewrite) -> QueryRewrite {
+    match rewrite {
+        CoreQueryRewrite::StopWord { word } => QueryRewrite::StopWord { word },
+        CoreQueryRewrite::Ignored { word } => QueryRewrite::Ignored { word },
+        CoreQueryRewrite::Synonym {
+            query,
+            words,
+            penalty,
+        } => QueryRewrite::Synonym {
+            query,
+            words,
+            penalty,
+        },
+        CoreQueryRewrite::Decompounding { query, words } => {
+            QueryRewrite::Decompounding { query, words }
+        }
+        CoreQueryRewrite::Split { query, words } => QueryRewrite::Split { query, words },
+        CoreQueryRewrite::Concatenation { query, word } => {
+            QueryRewrite::Concatenation { query, word }
+        }
+        CoreQueryRewrite::Typo { query, word, typos } => QueryRewrite::Typo { query, word, typos },
+    }
+}
+
+/// The floats that are not finite have no JSON representation.
+fn json_number(number: Number) -> Option<serde_json::Number> {
+    match number {
+        Number::Unsigned(n) => Some(n.into()),
+        Number::Signed(n) => Some(n.into()),
+        Number::Float(n) => serde_json::Number::from_f64(n.0),
+    }
+}
+
+/// The characters that must be escaped to insert a text in an HTML document.
+fn escape_html_char(c: char) -> Option<&'static str> {
+    match c {
+        '&' => Some("&amp;"),
+        '<' => Some("&lt;"),
+        '>' => Some("&gt;"),
+        '"' => Some("&quot;"),
+        '\'' => Some("&#x27;"),
+        _ => None,
+    }
+}
+
+fn push_text(output: &mut String, text: &[char], escape_html: bool) {
+    for c in text {
+        match escape_html_char(*c).filter(|_| escape_html) {
+            Some(escaped) => output.push_str(escaped),
+            None => output.push(*c),
+        }
+    }
+}
+
+/// HTML-escapes the strings of the value, the ones of the arrays and objects too.
+fn escape_value(value: &mut Value) {
+    match value {
+        Value::String(string) => {
+            let chars: Vec<_> = string.chars().collect();
+            let mut escaped = String::with_capacity(string.len());
+            push_text(&mut escaped, &chars, true);
+            *string = escaped;
+        }
+        Value::Array(values) => values.iter_mut().for_each(escape_value),
+        Value::Object(object) => object.values_mut().for_each(escape_value),
+        _ => (),
+    }
+}
+
+/// Surrounds the matches with the pre and post tags, the text is HTML-escaped
+/// before the tags are inserted when asked to.
 fn calculate_highlights(
     document: &IndexMap<String, Value>,
     matches: &MatchesInfos,
     attributes_to_highlight: &HashSet<String>,
+    (pre_tag, post_tag): (&str, &str),
+    escape_html: bool,
 ) -> IndexMap<String, Value> {
     let mut highlight_result = IndexMap::new();
 
@@ -506,17 +1238,17 @@ fn calculate_highlights(
                         let before = value.get(index..m.start);
                         let highlighted = value.get(m.start..(m.start + m.length));
                         if let (Some(before), Some(highlighted)) = (before, highlighted) {
-                            highlighted_value.extend(before);
-                            highlighted_value.push_str("<em>");
-                            highlighted_value.extend(highlighted);
-                            highlighted_value.push_str("</em>");
+                            push_text(&mut highlighted_value, before, escape_html);
+                            highlighted_value.push_str(pre_tag);
+                            push_text(&mut highlighted_value, highlighted, escape_html);
+                            highlighted_value.push_str(post_tag);
                             index = m.start + m.length;
                         } else {
                             error!("value: {:?}; index: {:?}, match: {:?}", value, index, m);
                         }
                     }
                 }
-                highlighted_value.extend(value[index..].iter());
+                push_text(&mut highlighted_value, &value[index..], escape_html);
                 highlight_result.insert(attribute.to_string(), Value::String(highlighted_value));
             };
         }
@@ -529,6 +1261,181 @@ fn calculate_highlights(
 mod tests {
     use super::*;
 
+    const TAGS: (&str, &str) = (DEFAULT_HIGHLIGHT_PRE_TAG, DEFAULT_HIGHLIGHT_POST_TAG);
+
+    #[test]
+    fn retained_range() {
+        let documents = (0..6)
+            .map(|id| meilidb_core::Document {
+                id: meilidb_core::DocumentId(id),
+                highlights: Vec::new(),
+            })
+            .collect();
+
+        let mut result = QueryResult {
+            documents,
+            buckets_starts: vec![0, 2, 5],
+            decisive_criteria: vec![None; 6],
+            explanations: Vec::new(),
+            rewrites: Vec::new(),
+            timings: Default::default(),
+        };
+
+        retain_range(&mut result, 1..4);
+        let ids: Vec<_> = result.documents.iter().map(|d| d.id.0).collect();
+        assert_eq!(ids, vec![1, 2, 3]);
+        assert_eq!(result.buckets_starts, vec![0, 1]);
+        assert_eq!(result.decisive_criteria.len(), 3);
+
+        retain_range(&mut result, 5..10);
+        assert!(result.documents.is_empty());
+        assert!(result.buckets_starts.is_empty());
+    }
+
+    #[test]
+    fn missing_ranked_values() {
+        assert_eq!(missing_values_warning("rank", 10, 10), None);
+        assert_eq!(missing_values_warning("rank", 0, 0), None);
+
+        let warning = missing_values_warning("rank", 7, 10).unwrap();
+        assert!(warning.starts_with("3 of the 10 documents"));
+
+        let warning = missing_values_warning("rank", 0, 10).unwrap();
+        assert!(warning.contains("reindexed"));
+    }
+
+    #[test]
+    fn lat_lng() {
+        assert_eq!(parse_lat_lng("48.85, 2.35"), GeoPoint::new(48.85, 2.35));
+        assert_eq!(parse_lat_lng("48.85"), None);
+        assert_eq!(parse_lat_lng("48.85,2.35,1"), None);
+        assert_eq!(parse_lat_lng("120,2.35"), None);
+    }
+
+    #[test]
+    fn escaped_highlights() {
+        let data = r#"{ "title": "<b>Tom & Jerry</b>", "year": 1940, "tags": ["<i>"] }"#;
+        let mut document: IndexMap<String, Value> = serde_json::from_str(data).unwrap();
+
+        let mut attributes_to_highlight = HashSet::new();
+        attributes_to_highlight.insert("title".to_string());
+
+        let mut matches = HashMap::new();
+        matches.insert(
+            "title".to_string(),
+            vec![MatchPosition {
+                start: 3,
+                length: 3,
+            }],
+        );
+
+        let result =
+            super::calculate_highlights(&document, &matches, &attributes_to_highlight, TAGS, true);
+        assert_eq!(
+            result["title"],
+            "&lt;b&gt;<em>Tom</em> &amp; Jerry&lt;/b&gt;"
+        );
+
+        // the tags are never escaped
+        let tags = ("<mark class=\"hit\">", "</mark>");
+        let result =
+            super::calculate_highlights(&document, &matches, &attributes_to_highlight, tags, true);
+        assert_eq!(
+            result["title"],
+            "&lt;b&gt;<mark class=\"hit\">Tom</mark> &amp; Jerry&lt;/b&gt;"
+        );
+
+        document.values_mut().for_each(escape_value);
+        assert_eq!(document["title"], "&lt;b&gt;Tom &amp; Jerry&lt;/b&gt;");
+        assert_eq!(document["year"], 1940);
+        assert_eq!(document["tags"][0], "&lt;i&gt;");
+    }
+
+    #[test]
+    fn matches_infos() {
+        use meilidb_schema::{SchemaBuilder, SchemaProps};
+
+        let props = SchemaProps {
+            displayed: true,
+            indexed: true,
+            ranked: false,
+        };
+
+        let mut builder = SchemaBuilder::with_identifier("id");
+        let title = builder.new_attribute("title", props);
+        let overview = builder.new_attribute("overview", props);
+        let schema = builder.build();
+
+        let highlight = |attribute: SchemaAttr, char_index, char_length| Highlight {
+            attribute: attribute.0,
+            char_index,
+            char_length,
+        };
+
+        // the same word matched by several query words is only listed once
+        let matches = vec![
+            highlight(overview, 12, 6),
+            highlight(title, 4, 5),
+            highlight(overview, 0, 3),
+            highlight(overview, 12, 6),
+        ];
+
+        let infos = calculate_matches(matches.clone(), None, &schema);
+        assert_eq!(infos.len(), 2);
+        assert_eq!(
+            infos["title"],
+            vec![MatchPosition {
+                start: 4,
+                length: 5
+            }]
+        );
+        assert_eq!(
+            infos["overview"],
+            vec![
+                MatchPosition {
+                    start: 0,
+                    length: 3
+                },
+                MatchPosition {
+                    start: 12,
+                    length: 6
+                },
+            ]
+        );
+
+        // only the retrieved attributes are described
+        let mut attributes_to_retrieve = HashSet::new();
+        attributes_to_retrieve.insert("title".to_string());
+        let infos = calculate_matches(matches, Some(attributes_to_retrieve), &schema);
+        assert_eq!(infos.keys().collect::<Vec<_>>(), vec!["title"]);
+    }
+
+    #[test]
+    fn crop_around_best_window() {
+        let text = "one two three four five six seven eight nine ten";
+        let highlight = |char_index, char_length| Highlight {
+            attribute: 0,
+            char_index,
+            char_length,
+        };
+
+        // the window around "nine" also contains "eight", the one around "one" does not
+        let matches = vec![highlight(0, 3), highlight(34, 5), highlight(40, 4)];
+        let (cropped, matches) = crop_text(text, matches, 8);
+        assert_eq!(cropped, "n eight nine ten");
+        assert_eq!(matches, vec![highlight(2, 5), highlight(8, 4)]);
+
+        // the first match wins the ties
+        let matches = vec![highlight(4, 3), highlight(40, 4)];
+        let (cropped, matches) = crop_text(text, matches, 8);
+        assert_eq!(cropped, "one two three fo");
+        assert_eq!(matches, vec![highlight(4, 3)]);
+
+        let (cropped, matches) = crop_text(text, Vec::new(), 4);
+        assert_eq!(cropped, "one two ");
+        assert!(matches.is_empty());
+    }
+
     #[test]
     fn calculate_highlights() {
         let data = r#"{
@@ -556,7 +1463,8 @@ mod tests {
             length: 9,
         });
         matches.insert("description".to_string(), m);
-        let result = super::calculate_highlights(&document, &matches, &attributes_to_highlight);
+        let result =
+            super::calculate_highlights(&document, &matches, &attributes_to_highlight, TAGS, false);
 
         let mut result_expected = IndexMap::new();
         result_expected.insert(
@@ -1,2 +1,3 @@
+pub mod json_stream;
 pub mod meilidb;
 pub mod tide;
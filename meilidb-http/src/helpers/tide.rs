@@ -1,11 +1,18 @@
+use crate::audit::AuditAction;
 use crate::error::{ResponseError, SResult};
 use crate::models::token::*;
 use crate::Data;
 use chrono::Utc;
 use heed::types::{SerdeBincode, Str};
+use http::StatusCode;
+use meilidb_core::serde::DocumentKey;
 use meilidb_core::Index;
+use meilidb_dto::IndexUpdateResponse;
 use serde_json::Value;
-use tide::Context;
+use tide::response::IntoResponse;
+use tide::{Context, Response};
+
+const IDEMPOTENCY_KEY_HEADER: &str = "Idempotency-Key";
 
 pub trait ContextExt {
     fn is_allowed(&self, acl: ACL) -> SResult<()>;
@@ -13,10 +20,36 @@ pub trait ContextExt {
     fn url_param(&self, name: &str) -> Result<String, ResponseError>;
     fn index(&self) -> Result<Index, ResponseError>;
     fn identifier(&self) -> Result<String, ResponseError>;
+    fn allowed_attributes(&self) -> SResult<Option<Vec<Wildcard>>>;
+    fn idempotency_key(&self) -> SResult<Option<String>>;
+    fn replayed_update(&self, reader: &heed::RoTxn, index: &Index) -> SResult<Option<Response>>;
+    fn register_update(
+        &self,
+        writer: &mut heed::RwTxn,
+        index: &Index,
+        update_id: u64,
+    ) -> SResult<()>;
+    fn acting_key(&self) -> Option<String>;
+    fn audit(&self, writer: &mut heed::RwTxn, action: AuditAction) -> SResult<()>;
+    fn audit_index(
+        &self,
+        writer: &mut heed::RwTxn,
+        action: AuditAction,
+        index_uid: &str,
+    ) -> SResult<()>;
 }
 
 impl ContextExt for Context<Data> {
     fn is_allowed(&self, acl: ACL) -> SResult<()> {
+        // nothing can be written while the disk space is low
+        let write_acl = match acl {
+            ACL::IndexesWrite | ACL::DocumentsWrite | ACL::SettingsWrite => true,
+            _ => false,
+        };
+        if write_acl && self.state().db.is_read_only() {
+            return Err(ResponseError::ReadOnly);
+        }
+
         let api_key = match &self.state().api_key {
             Some(api_key) => api_key,
             None => return Ok(()),
@@ -103,16 +136,131 @@ impl ContextExt for Context<Data> {
     }
 
     fn identifier(&self) -> Result<String, ResponseError> {
-        let name = self
+        let value = self
             .param::<Value>("identifier")
-            .as_ref()
-            .map(meilidb_core::serde::value_to_string)
-            .map_err(|e| ResponseError::bad_parameter("identifier", e))?
-            .ok_or(ResponseError::bad_parameter(
-                "identifier",
-                "missing parameter",
-            ))?;
-
-        Ok(name)
+            .map_err(|e| ResponseError::bad_parameter("identifier", e))?;
+
+        let key = DocumentKey::from_value(&value)
+            .map_err(|e| ResponseError::bad_parameter("identifier", e))?;
+
+        Ok(key.to_string())
+    }
+
+    fn allowed_attributes(&self) -> SResult<Option<Vec<Wildcard>>> {
+        let api_key = match &self.state().api_key {
+            Some(api_key) => api_key,
+            None => return Ok(None),
+        };
+
+        // routes that are not protected can be requested without any key
+        let user_api_key = match self.header("X-Meili-API-Key") {
+            Ok(user_api_key) => user_api_key,
+            Err(_) => return Ok(None),
+        };
+
+        if user_api_key == *api_key {
+            return Ok(None);
+        }
+
+        let db = &self.state().db;
+        let env = &db.env;
+        let reader = env.read_txn().map_err(ResponseError::internal)?;
+
+        let attributes = db
+            .common_store()
+            .get::<Str, SerdeBincode<Vec<Wildcard>>>(&reader, &token_attributes_key(&user_api_key))
+            .map_err(ResponseError::internal)?;
+
+        Ok(attributes)
+    }
+
+    fn idempotency_key(&self) -> SResult<Option<String>> {
+        match self.headers().get(IDEMPOTENCY_KEY_HEADER) {
+            Some(key) => {
+                let key = key
+                    .to_str()
+                    .map_err(|e| ResponseError::bad_parameter(IDEMPOTENCY_KEY_HEADER, e))?;
+                Ok(Some(key.to_string()))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the status of the update previously enqueued with the
+    /// idempotency key of this request, if any, instead of enqueuing it again.
+    fn replayed_update(&self, reader: &heed::RoTxn, index: &Index) -> SResult<Option<Response>> {
+        let key = match self.idempotency_key()? {
+            Some(key) => key,
+            None => return Ok(None),
+        };
+
+        let update_id = match index
+            .idempotency_keys
+            .update_id(reader, &key)
+            .map_err(ResponseError::internal)?
+        {
+            Some(update_id) => update_id,
+            None => return Ok(None),
+        };
+
+        let status = index
+            .update_status(reader, update_id)
+            .map_err(ResponseError::internal)?;
+
+        let response = match status {
+            Some(status) => tide::response::json(status),
+            None => tide::response::json(IndexUpdateResponse { update_id }),
+        };
+
+        Ok(Some(response.with_status(StatusCode::OK).into_response()))
+    }
+
+    /// Associates the idempotency key of this request, if any, with the enqueued update.
+    fn register_update(
+        &self,
+        writer: &mut heed::RwTxn,
+        index: &Index,
+        update_id: u64,
+    ) -> SResult<()> {
+        if let Some(key) = self.idempotency_key()? {
+            index
+                .idempotency_keys
+                .put_update_id(writer, &key, update_id)
+                .map_err(ResponseError::internal)?;
+        }
+        Ok(())
+    }
+
+    /// The identifier of the token used by this request, the keys themselves are never
+    /// returned and the master key is not identified.
+    fn acting_key(&self) -> Option<String> {
+        let api_key = self.state().api_key.as_ref()?;
+        let user_api_key = self.header("X-Meili-API-Key").ok()?;
+
+        if user_api_key == *api_key {
+            None
+        } else {
+            Some(key_id(&user_api_key))
+        }
+    }
+
+    /// Records the operation in the audit log, along with the index of the route if any.
+    fn audit(&self, writer: &mut heed::RwTxn, action: AuditAction) -> SResult<()> {
+        let index_uid = self.param::<String>("index").ok();
+        self.state()
+            .push_audit_event(writer, action, index_uid, self.acting_key())
+            .map_err(ResponseError::internal)
+    }
+
+    fn audit_index(
+        &self,
+        writer: &mut heed::RwTxn,
+        action: AuditAction,
+        index_uid: &str,
+    ) -> SResult<()> {
+        let index_uid = Some(index_uid.to_string());
+        self.state()
+            .push_audit_event(writer, action, index_uid, self.acting_key())
+            .map_err(ResponseError::internal)
     }
 }
@@ -1,8 +1,19 @@
+pub mod audit;
+pub mod catalog;
+pub mod circuit_breaker;
+pub mod compression;
 pub mod data;
+pub mod diagnostics;
+pub mod disk;
 pub mod error;
 pub mod helpers;
 pub mod models;
 pub mod option;
+pub mod response_format;
 pub mod routes;
+pub mod sample;
+pub mod snapshot;
+pub mod tiering;
+pub mod usage;
 
 pub use self::data::Data;
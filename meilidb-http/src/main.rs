@@ -1,4 +1,8 @@
+#[cfg(feature = "analytics")]
 use std::env::VarError::NotPresent;
+use std::path::PathBuf;
+use std::time::Duration;
+#[cfg(feature = "analytics")]
 use std::{env, thread};
 
 use http::header::HeaderValue;
@@ -8,14 +12,20 @@ use structopt::StructOpt;
 use tide::middleware::{CorsMiddleware, CorsOrigin};
 use tide_log::RequestLogger;
 
+use meilidb_http::compression::{Compression, Decompression};
 use meilidb_http::data::Data;
+use meilidb_http::disk::{schedule_disk_checks, DiskPolicy};
 use meilidb_http::option::Opt;
 use meilidb_http::routes;
 use meilidb_http::routes::index::index_update_callback;
+use meilidb_http::snapshot::{schedule_snapshots, SnapshotPolicy};
+use meilidb_http::tiering::schedule_tiering;
+use meilidb_http::usage::schedule_usage_flushes;
 
+#[cfg(feature = "analytics")]
 mod analytics;
 
-#[cfg(target_os = "linux")]
+#[cfg(all(target_os = "linux", feature = "jemalloc"))]
 #[global_allocator]
 static ALLOC: jemallocator::Jemalloc = jemallocator::Jemalloc;
 
@@ -25,8 +35,11 @@ pub fn main() -> Result<(), MainError> {
     let opt = Opt::from_args();
     let data = Data::new(opt.clone());
 
-    if env::var("MEILI_NO_ANALYTICS") == Err(NotPresent) {
-        thread::spawn(|| analytics::analytics_sender());
+    #[cfg(feature = "analytics")]
+    {
+        if env::var("MEILI_NO_ANALYTICS") == Err(NotPresent) {
+            thread::spawn(|| analytics::analytics_sender());
+        }
     }
 
     let data_cloned = data.clone();
@@ -34,6 +47,29 @@ pub fn main() -> Result<(), MainError> {
         index_update_callback(name, &data_cloned, status);
     }));
 
+    if let Some(snapshot_dir) = opt.snapshot_dir.clone() {
+        let policy = SnapshotPolicy {
+            dir: PathBuf::from(snapshot_dir),
+            interval: Duration::from_secs(opt.snapshot_interval_sec),
+            retention: opt.snapshot_retention,
+        };
+        schedule_snapshots(data.clone(), policy);
+    }
+
+    if opt.min_free_disk_space > 0 {
+        let policy = DiskPolicy {
+            min_free_space: opt.min_free_disk_space,
+            interval: Duration::from_secs(opt.disk_check_interval_sec),
+        };
+        schedule_disk_checks(data.clone(), policy);
+    }
+
+    let interval = Duration::from_secs(opt.usage_flush_interval_sec);
+    schedule_usage_flushes(data.clone(), interval);
+
+    let interval = Duration::from_secs(opt.tiering_interval_sec);
+    schedule_tiering(data.clone(), interval);
+
     let mut app = tide::App::with_state(data);
 
     app.middleware(
@@ -42,8 +78,8 @@ pub fn main() -> Result<(), MainError> {
             .allow_methods(HeaderValue::from_static("GET, POST, OPTIONS")),
     );
     app.middleware(RequestLogger::new());
-    app.middleware(tide_compression::Compression::new());
-    app.middleware(tide_compression::Decompression::new());
+    app.middleware(Compression::new(opt.compression_min_size));
+    app.middleware(Decompression::new(opt.decompressed_payload_max_size));
 
     routes::load_routes(&mut app);
 
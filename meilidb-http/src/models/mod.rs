@@ -1,3 +1,2 @@
-pub mod schema;
 pub mod token;
 pub mod update_operation;
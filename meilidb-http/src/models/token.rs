@@ -1,7 +1,14 @@
+use std::hash::Hasher;
+
 use chrono::{DateTime, Utc};
+use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
+use siphasher::sip::SipHasher;
 
 pub const TOKEN_PREFIX_KEY: &str = "_token_";
+/// The attributes restriction of a token is stored apart from the token, this way
+/// the tokens stored by the previous versions can still be read.
+pub const TOKEN_ATTRIBUTES_PREFIX_KEY: &str = "_attributes_token_";
 
 #[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -32,6 +39,29 @@ pub struct Token {
     pub revoked: bool,
 }
 
+/// A token along with the attributes it is restricted to, as returned by the routes.
+#[derive(Debug, Clone, Serialize)]
+pub struct TokenView {
+    /// The identifier of the key, it can be shown without disclosing the key.
+    pub id: String,
+    #[serde(flatten)]
+    pub token: Token,
+    /// The attributes that can be retrieved and highlighted with this token,
+    /// all the attributes are allowed when there is no restriction.
+    pub attributes: Option<Vec<Wildcard>>,
+}
+
+/// A stable identifier of an API key, it is logged and stored in place of the key.
+pub fn key_id(key: &str) -> String {
+    let mut hasher = SipHasher::new();
+    hasher.write(key.as_bytes());
+    format!("{:016x}", hasher.finish())
+}
+
+pub fn token_attributes_key(key: &str) -> String {
+    format!("{}{}", TOKEN_ATTRIBUTES_PREFIX_KEY, key)
+}
+
 fn cleanup_wildcard(input: &str) -> (bool, &str, bool) {
     let first = input.chars().next().filter(|&c| c == '*').is_some();
     let last = input.chars().last().filter(|&c| c == '*').is_some();
@@ -51,6 +81,11 @@ pub fn match_wildcard(pattern: &str, input: &str) -> bool {
     }
 }
 
+/// Removes the attributes that do not match any of the allowed patterns.
+pub fn retain_allowed_attributes<V>(patterns: &[Wildcard], attributes: &mut IndexMap<String, V>) {
+    attributes.retain(|attribute, _| patterns.iter().any(|p| match_wildcard(p, attribute)));
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -69,4 +104,64 @@ mod tests {
         assert!(match_wildcard("*ab*", "ab"));
         assert!(match_wildcard("*😆*", "ab😆dsa"));
     }
+
+    #[test]
+    fn key_ids() {
+        let id = key_id("ABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789ABCD");
+        assert_eq!(id.len(), 16);
+        assert_eq!(id, key_id("ABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789ABCD"));
+        assert_ne!(id, key_id("ABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789ABCE"));
+    }
+
+    #[test]
+    fn decode_previous_tokens() {
+        // the layout of the tokens stored by the previous versions
+        #[derive(Serialize)]
+        struct PreviousToken {
+            key: String,
+            description: String,
+            acl: Vec<ACL>,
+            indexes: Vec<Wildcard>,
+            created_at: DateTime<Utc>,
+            updated_at: DateTime<Utc>,
+            expires_at: DateTime<Utc>,
+            revoked: bool,
+        }
+
+        let now = Utc::now();
+        let previous = PreviousToken {
+            key: String::from("ABCDEF"),
+            description: String::from("search only"),
+            acl: vec![ACL::DocumentsRead],
+            indexes: vec![String::from("movies*")],
+            created_at: now,
+            updated_at: now,
+            expires_at: now,
+            revoked: false,
+        };
+
+        let bytes = bincode::serialize(&previous).unwrap();
+        let token: Token = bincode::deserialize(&bytes).unwrap();
+
+        assert_eq!(token.key, "ABCDEF");
+        assert_eq!(token.acl, vec![ACL::DocumentsRead]);
+        assert_eq!(token.indexes, vec![String::from("movies*")]);
+        assert_eq!(token.expires_at, now);
+        assert!(!token.revoked);
+    }
+
+    #[test]
+    fn test_retain_allowed_attributes() {
+        let mut attributes = IndexMap::new();
+        attributes.insert(String::from("title"), 1);
+        attributes.insert(String::from("private_notes"), 2);
+        attributes.insert(String::from("private_price"), 3);
+        attributes.insert(String::from("overview"), 4);
+
+        let patterns = vec![String::from("title"), String::from("over*")];
+        retain_allowed_attributes(&patterns, &mut attributes);
+
+        let attributes: Vec<_> = attributes.keys().map(String::as_str).collect();
+        assert_eq!(attributes, vec!["title", "overview"]);
+    }
 }
@@ -1,3 +1,5 @@
+use std::str::FromStr;
+
 use structopt::StructOpt;
 
 #[derive(Debug, Clone, StructOpt)]
@@ -17,4 +19,124 @@ pub struct Opt {
     /// Do not send analytics to Meili.
     #[structopt(long, env = "MEILI_NO_ANALYTICS")]
     pub no_analytics: bool,
+
+    /// The directory where the automatic snapshots of the database are stored,
+    /// automatic snapshots are disabled when not specified.
+    #[structopt(long, env = "MEILI_SNAPSHOT_DIR")]
+    pub snapshot_dir: Option<String>,
+
+    /// The interval in seconds between two automatic snapshots.
+    #[structopt(
+        long,
+        env = "MEILI_SNAPSHOT_INTERVAL_SEC",
+        default_value = "86400",
+        parse(try_from_str = parse_non_zero)
+    )]
+    pub snapshot_interval_sec: u64,
+
+    /// The number of automatic snapshots kept in the snapshot directory.
+    #[structopt(
+        long,
+        env = "MEILI_SNAPSHOT_RETENTION",
+        default_value = "5",
+        parse(try_from_str = parse_non_zero)
+    )]
+    pub snapshot_retention: usize,
+
+    /// The free space in bytes under which the database becomes read-only,
+    /// the updates are rejected until enough space is freed, 0 disables the check.
+    #[structopt(long, env = "MEILI_MIN_FREE_DISK_SPACE", default_value = "104857600")]
+    pub min_free_disk_space: u64,
+
+    /// The interval in seconds between two checks of the free disk space.
+    #[structopt(long, env = "MEILI_DISK_CHECK_INTERVAL_SEC", default_value = "10")]
+    pub disk_check_interval_sec: u64,
+
+    /// The interval in seconds between two writes of the searches usage counters.
+    #[structopt(long, env = "MEILI_USAGE_FLUSH_INTERVAL_SEC", default_value = "60")]
+    pub usage_flush_interval_sec: u64,
+
+    /// The interval in seconds between two moves of the documents between the hot
+    /// and the cold tiers, of the indexes that have cold tier settings.
+    #[structopt(long, env = "MEILI_TIERING_INTERVAL_SEC", default_value = "3600")]
+    pub tiering_interval_sec: u64,
+
+    /// The directory where the diagnostics dumps requested by the administrators are written.
+    #[structopt(
+        long,
+        env = "MEILI_DIAGNOSTICS_DIR",
+        default_value = "/tmp/meilidb-diagnostics"
+    )]
+    pub diagnostics_dir: String,
+
+    /// The memory in bytes a search can use to buffer the matches of the query words,
+    /// the matches exceeding it are spilled to a temporary file. Unbounded when not specified.
+    #[structopt(long, env = "MEILI_QUERY_MEMORY_LIMIT")]
+    pub query_memory_limit: Option<usize>,
+
+    /// The size in bytes from which the responses are compressed when the client accepts
+    /// brotli or gzip, the smaller responses are sent uncompressed.
+    #[structopt(long, env = "MEILI_COMPRESSION_MIN_SIZE", default_value = "1024")]
+    pub compression_min_size: usize,
+
+    /// The maximum size in bytes of the gzipped requests bodies once decompressed,
+    /// the larger bodies are refused.
+    #[structopt(
+        long,
+        env = "MEILI_DECOMPRESSED_PAYLOAD_MAX_SIZE",
+        default_value = "104857600"
+    )]
+    pub decompressed_payload_max_size: usize,
+
+    /// The number of consecutive failures or timeouts of the searches of an index after which
+    /// it is excluded from the multi-index searches, 0 never excludes the indexes.
+    #[structopt(
+        long,
+        env = "MEILI_MULTI_SEARCH_FAILURE_THRESHOLD",
+        default_value = "5"
+    )]
+    pub multi_search_failure_threshold: usize,
+
+    /// The time in seconds an index is excluded from the multi-index searches
+    /// before a search is tried on it again.
+    #[structopt(long, env = "MEILI_MULTI_SEARCH_COOLDOWN_SEC", default_value = "30")]
+    pub multi_search_cooldown_sec: u64,
+
+    /// Log the operations applied to the indexes, they are listed with `/operations`
+    /// for the external consumers, the log is kept but stops growing once disabled.
+    #[structopt(long, env = "MEILI_OPERATIONS_LOG")]
+    pub operations_log: bool,
+
+    /// Record the last processed updates of the indexes, along with the documents they
+    /// modify, to be able to replay them with `/indexes/:index/updates/:update_id/replay`.
+    #[structopt(long, env = "MEILI_UPDATES_REPLAYS")]
+    pub updates_replays: bool,
+}
+
+/// Parses the options that cannot be zero, like the interval between two snapshots.
+fn parse_non_zero<T>(s: &str) -> Result<T, String>
+where
+    T: FromStr + Default + PartialEq,
+    T::Err: ToString,
+{
+    let value = s.parse::<T>().map_err(|e| e.to_string())?;
+    if value == T::default() {
+        return Err("the value must be greater than 0".to_string());
+    }
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snapshot_options_are_not_zero() {
+        let opt = Opt::from_iter_safe(&["meilidb", "--snapshot-retention", "2"]).unwrap();
+        assert_eq!(opt.snapshot_retention, 2);
+        assert_eq!(opt.snapshot_interval_sec, 86400);
+
+        assert!(Opt::from_iter_safe(&["meilidb", "--snapshot-retention", "0"]).is_err());
+        assert!(Opt::from_iter_safe(&["meilidb", "--snapshot-interval-sec", "0"]).is_err());
+    }
 }
@@ -0,0 +1,191 @@
+use heed::types::{SerdeBincode, Str};
+use indexmap::IndexMap;
+use meilidb_core::MResult;
+use meilidb_dto::{ResponseFormat, SearchResult};
+use serde_json::{json, Map, Value};
+
+use crate::data::Data;
+
+const RESPONSE_FORMAT_PREFIX_KEY: &str = "_response_format_";
+
+fn response_format_key(index_uid: &str) -> String {
+    format!("{}{}", RESPONSE_FORMAT_PREFIX_KEY, index_uid)
+}
+
+impl Data {
+    pub fn response_format(
+        &self,
+        reader: &heed::RoTxn,
+        index_uid: &str,
+    ) -> MResult<ResponseFormat> {
+        let key = response_format_key(index_uid);
+        let format = self
+            .db
+            .common_store()
+            .get::<Str, SerdeBincode<ResponseFormat>>(reader, &key)?;
+
+        Ok(format.unwrap_or_default())
+    }
+
+    pub fn put_response_format(
+        &self,
+        writer: &mut heed::RwTxn,
+        index_uid: &str,
+        format: &ResponseFormat,
+    ) -> MResult<()> {
+        let key = response_format_key(index_uid);
+        self.db
+            .common_store()
+            .put::<Str, SerdeBincode<ResponseFormat>>(writer, &key, format)?;
+
+        Ok(())
+    }
+
+    pub fn delete_response_format(&self, writer: &mut heed::RwTxn, index_uid: &str) -> MResult<()> {
+        let key = response_format_key(index_uid);
+        self.db.common_store().delete::<Str>(writer, &key)?;
+
+        Ok(())
+    }
+}
+
+/// Moves the given fields first, in this order, the other fields keep their order.
+fn reorder_fields(document: &mut IndexMap<String, Value>, fields_order: &[String]) {
+    let rank = |field: &String| {
+        fields_order
+            .iter()
+            .position(|f| f == field)
+            .unwrap_or(fields_order.len())
+    };
+
+    document.sort_by(|a, _, b, _| rank(a).cmp(&rank(b)));
+}
+
+/// Builds the search response envelope following the given format.
+pub fn format_search_result(mut result: SearchResult, format: &ResponseFormat) -> Value {
+    if let Some(fields_order) = &format.fields_order {
+        for hit in &mut result.hits {
+            reorder_fields(&mut hit.document, fields_order);
+            reorder_fields(&mut hit.formatted, fields_order);
+        }
+    }
+
+    let hits_key = format
+        .hits_key
+        .clone()
+        .unwrap_or_else(|| "hits".to_string());
+
+    let mut envelope = Map::new();
+    envelope.insert(hits_key, json!(result.hits));
+    envelope.insert("offset".to_string(), json!(result.offset));
+    envelope.insert("limit".to_string(), json!(result.limit));
+
+    if format.show_processing_time.unwrap_or(true) {
+        let processing_time_ms = json!(result.processing_time_ms);
+        envelope.insert("processingTimeMs".to_string(), processing_time_ms);
+    }
+
+    if format.show_query.unwrap_or(true) {
+        envelope.insert("query".to_string(), json!(result.query));
+    }
+
+    if let Some(buckets) = &result.buckets {
+        envelope.insert("buckets".to_string(), json!(buckets));
+    }
+
+    if let Some(facets) = &result.facets {
+        envelope.insert("facets".to_string(), json!(facets));
+    }
+
+    if !result.warnings.is_empty() {
+        envelope.insert("warnings".to_string(), json!(result.warnings));
+    }
+
+    if !result.rewrites.is_empty() {
+        envelope.insert("rewrites".to_string(), json!(result.rewrites));
+    }
+
+    Value::Object(envelope)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use meilidb_dto::SearchHit;
+
+    fn search_result() -> SearchResult {
+        let mut document = IndexMap::new();
+        document.insert("id".to_string(), json!(12));
+        document.insert("title".to_string(), json!("Batman"));
+        document.insert("overview".to_string(), json!("Gotham"));
+
+        SearchResult {
+            hits: vec![SearchHit {
+                document,
+                formatted: IndexMap::new(),
+                matches_info: None,
+                explain: None,
+            }],
+            offset: 0,
+            limit: 20,
+            processing_time_ms: 2,
+            query: "batman".to_string(),
+            processing_time_breakdown: None,
+            buckets: None,
+            facets: None,
+            warnings: Vec::new(),
+            rewrites: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn default_format() {
+        let result = search_result();
+        let value = format_search_result(result.clone(), &ResponseFormat::default());
+
+        assert_eq!(value, serde_json::to_value(&result).unwrap());
+    }
+
+    #[test]
+    fn custom_format() {
+        let format = ResponseFormat {
+            fields_order: Some(vec!["title".to_string(), "unknown".to_string()]),
+            hits_key: Some("results".to_string()),
+            show_processing_time: Some(false),
+            show_query: Some(false),
+        };
+
+        let value = format_search_result(search_result(), &format);
+
+        let keys: Vec<_> = value.as_object().unwrap().keys().collect();
+        assert_eq!(keys, vec!["results", "offset", "limit"]);
+
+        let hit = value["results"][0].as_object().unwrap();
+        let fields: Vec<_> = hit.keys().collect();
+        assert_eq!(fields, vec!["title", "id", "overview"]);
+    }
+
+    #[test]
+    fn buckets_are_formatted() {
+        let mut result = search_result();
+        result.buckets = Some(vec![0, 1]);
+
+        let value = format_search_result(result.clone(), &ResponseFormat::default());
+        assert_eq!(value, serde_json::to_value(&result).unwrap());
+        assert_eq!(value["buckets"], json!([0, 1]));
+
+        let format = ResponseFormat {
+            hits_key: Some("results".to_string()),
+            show_query: Some(false),
+            ..ResponseFormat::default()
+        };
+
+        let value = format_search_result(result, &format);
+        let keys: Vec<_> = value.as_object().unwrap().keys().collect();
+        assert_eq!(
+            keys,
+            vec!["results", "offset", "limit", "processingTimeMs", "buckets"]
+        );
+        assert_eq!(value["buckets"], json!([0, 1]));
+    }
+}
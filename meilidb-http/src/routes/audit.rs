@@ -0,0 +1,33 @@
+use serde::Deserialize;
+use tide::querystring::ContextExt as QSContextExt;
+use tide::{Context, Response};
+
+use crate::error::{ResponseError, SResult};
+use crate::helpers::tide::ContextExt;
+use crate::models::token::ACL::*;
+use crate::Data;
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct AuditQuery {
+    offset: Option<usize>,
+    limit: Option<usize>,
+}
+
+pub async fn list(ctx: Context<Data>) -> SResult<Response> {
+    ctx.is_allowed(Admin)?;
+
+    let query: AuditQuery = ctx.url_query().unwrap_or_default();
+    let offset = query.offset.unwrap_or(0);
+    let limit = query.limit.unwrap_or(20);
+
+    let env = &ctx.state().db.env;
+    let reader = env.read_txn().map_err(ResponseError::internal)?;
+
+    let events = ctx
+        .state()
+        .audit_events(&reader, offset, limit)
+        .map_err(ResponseError::internal)?;
+
+    Ok(tide::response::json(events))
+}
@@ -0,0 +1,61 @@
+use std::collections::BTreeSet;
+
+use http::StatusCode;
+use meilidb_dto::IndexUpdateResponse;
+use tide::response::IntoResponse;
+use tide::{Context, Response};
+
+use crate::audit::AuditAction;
+use crate::error::{ResponseError, SResult};
+use crate::helpers::tide::ContextExt;
+use crate::models::token::ACL::*;
+use crate::Data;
+
+pub async fn list(ctx: Context<Data>) -> SResult<Response> {
+    ctx.is_allowed(SettingsRead)?;
+    let index = ctx.index()?;
+
+    let env = &ctx.state().db.env;
+    let reader = env.read_txn().map_err(ResponseError::internal)?;
+
+    let decompounding_fst = index
+        .main
+        .decompounding_fst(&reader)
+        .map_err(ResponseError::internal)?;
+
+    let dictionary = decompounding_fst
+        .unwrap_or_default()
+        .stream()
+        .into_strs()
+        .map_err(ResponseError::internal)?;
+
+    Ok(tide::response::json(dictionary))
+}
+
+pub async fn update(mut ctx: Context<Data>) -> SResult<Response> {
+    ctx.is_allowed(SettingsWrite)?;
+    let index = ctx.index()?;
+
+    let data: BTreeSet<String> = ctx.body_json().await.map_err(ResponseError::bad_request)?;
+
+    let env = &ctx.state().db.env;
+    let mut writer = env.write_txn().map_err(ResponseError::internal)?;
+
+    if let Some(response) = ctx.replayed_update(&writer, &index)? {
+        return Ok(response);
+    }
+
+    let update_id = index
+        .decompounding_update(&mut writer, data)
+        .map_err(ResponseError::internal)?;
+
+    ctx.register_update(&mut writer, &index, update_id)?;
+    ctx.audit(&mut writer, AuditAction::DecompoundingUpdate)?;
+
+    writer.commit().map_err(ResponseError::internal)?;
+
+    let response_body = IndexUpdateResponse { update_id };
+    Ok(tide::response::json(response_body)
+        .with_status(StatusCode::ACCEPTED)
+        .into_response())
+}
@@ -0,0 +1,23 @@
+use serde_json::json;
+use tide::{Context, Response};
+
+use crate::diagnostics::write_diagnostics;
+use crate::error::{ResponseError, SResult};
+use crate::helpers::tide::ContextExt;
+use crate::models::token::ACL::*;
+use crate::Data;
+
+/// Writes the update queues, the snapshots, the caches and the searches being processed
+/// in a new file of the diagnostics directory and returns the path of this file.
+pub async fn dump(ctx: Context<Data>) -> SResult<Response> {
+    ctx.is_allowed(Admin)?;
+
+    let data = ctx.state();
+    let diagnostics = data.diagnostics().map_err(ResponseError::internal)?;
+    let path =
+        write_diagnostics(&diagnostics, &data.diagnostics_dir).map_err(ResponseError::internal)?;
+
+    Ok(tide::response::json(
+        json!({ "path": path.display().to_string() }),
+    ))
+}
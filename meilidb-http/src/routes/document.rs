@@ -1,15 +1,31 @@
 use std::collections::{BTreeSet, HashSet};
-
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::PathBuf;
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use futures::stream::StreamExt;
+use http::header::CONTENT_TYPE;
 use http::StatusCode;
 use indexmap::IndexMap;
-use serde::{Deserialize, Serialize};
+use log::warn;
+use meilidb_core::serde::{csv_documents, infer_schema, CsvOptions, DocumentKey};
+use meilidb_core::{
+    DuplicatesPolicy, Filter, IdentifiersGeneration, IdentifiersVerification, Index,
+};
+use meilidb_dto::{DeleteByFilterRequest, FetchDocumentsRequest, IndexUpdateResponse};
+use serde::Deserialize;
 use serde_json::Value;
 use tide::querystring::ContextExt as QSContextExt;
 use tide::response::IntoResponse;
 use tide::{Context, Response};
 
+use crate::audit::AuditAction;
 use crate::error::{ResponseError, SResult};
+use crate::helpers::json_stream::{json_stream, JsonChunks};
 use crate::helpers::tide::ContextExt;
+use crate::models::token::retain_allowed_attributes;
 use crate::models::token::ACL::*;
 use crate::Data;
 
@@ -24,11 +40,15 @@ pub async fn get_document(ctx: Context<Data>) -> SResult<Response> {
     let env = &ctx.state().db.env;
     let reader = env.read_txn().map_err(ResponseError::internal)?;
 
-    let response = index
+    let mut response = index
         .document::<IndexMap<String, Value>>(&reader, None, document_id)
         .map_err(ResponseError::internal)?
         .ok_or(ResponseError::document_not_found(&identifier))?;
 
+    if let Some(allowed_attributes) = ctx.allowed_attributes()? {
+        retain_allowed_attributes(&allowed_attributes, &mut response);
+    }
+
     if response.is_empty() {
         return Err(ResponseError::document_not_found(identifier));
     }
@@ -36,15 +56,10 @@ pub async fn get_document(ctx: Context<Data>) -> SResult<Response> {
     Ok(tide::response::json(response))
 }
 
-#[derive(Default, Serialize)]
-#[serde(rename_all = "camelCase")]
-pub struct IndexUpdateResponse {
-    pub update_id: u64,
-}
-
 pub async fn delete_document(ctx: Context<Data>) -> SResult<Response> {
     ctx.is_allowed(DocumentsWrite)?;
 
+    let index_uid = ctx.url_param("index")?;
     let index = ctx.index()?;
     let identifier = ctx.identifier()?;
     let document_id = meilidb_core::serde::compute_document_id(identifier.clone());
@@ -52,12 +67,24 @@ pub async fn delete_document(ctx: Context<Data>) -> SResult<Response> {
     let env = &ctx.state().db.env;
     let mut writer = env.write_txn().map_err(ResponseError::internal)?;
 
+    if let Some(response) = ctx.replayed_update(&writer, &index)? {
+        return Ok(response);
+    }
+
     let mut documents_deletion = index.documents_deletion();
     documents_deletion.delete_document_by_id(document_id);
     let update_id = documents_deletion
         .finalize(&mut writer)
         .map_err(ResponseError::internal)?;
 
+    let keys: HashSet<_> = Some(identifier).into_iter().collect();
+    ctx.state()
+        .sample_documents_deletion(&mut writer, &index_uid, &keys)
+        .map_err(ResponseError::internal)?;
+
+    ctx.register_update(&mut writer, &index, update_id)?;
+    ctx.audit(&mut writer, AuditAction::DocumentsDeletion)?;
+
     writer.commit().map_err(ResponseError::internal)?;
 
     let response_body = IndexUpdateResponse { update_id };
@@ -66,12 +93,62 @@ pub async fn delete_document(ctx: Context<Data>) -> SResult<Response> {
         .into_response())
 }
 
+/// Returns the documents in the order of the given identifiers,
+/// the documents that are not found are returned as nulls.
+pub async fn fetch_documents(mut ctx: Context<Data>) -> SResult<Response> {
+    ctx.is_allowed(DocumentsRead)?;
+
+    let body: FetchDocumentsRequest = ctx.body_json().await.map_err(ResponseError::bad_request)?;
+    let index = ctx.index()?;
+
+    let mut documents_ids = Vec::with_capacity(body.ids.len());
+    for identifier in &body.ids {
+        let key = DocumentKey::from_value(identifier).map_err(ResponseError::bad_request)?;
+        documents_ids.push(key.document_id());
+    }
+
+    let attributes = body.attributes_to_retrieve.as_ref().map(|attributes| {
+        attributes
+            .iter()
+            .map(String::as_str)
+            .collect::<HashSet<_>>()
+    });
+
+    let allowed_attributes = ctx.allowed_attributes()?;
+
+    let env = &ctx.state().db.env;
+    let reader = env.read_txn().map_err(ResponseError::internal)?;
+
+    let mut response_body = Vec::with_capacity(documents_ids.len());
+    for document_id in documents_ids {
+        let document: Option<IndexMap<String, Value>> = index
+            .document(&reader, attributes.as_ref(), document_id)
+            .map_err(ResponseError::internal)?;
+
+        let document = document.and_then(|mut document| {
+            if let Some(allowed_attributes) = &allowed_attributes {
+                retain_allowed_attributes(allowed_attributes, &mut document);
+            }
+            if document.is_empty() {
+                None
+            } else {
+                Some(document)
+            }
+        });
+
+        response_body.push(document);
+    }
+
+    Ok(json_stream(JsonChunks::array(response_body)))
+}
+
 #[derive(Default, Deserialize)]
 #[serde(rename_all = "camelCase", deny_unknown_fields)]
 struct BrowseQuery {
     offset: Option<usize>,
     limit: Option<usize>,
     attributes_to_retrieve: Option<String>,
+    raw: Option<bool>,
 }
 
 pub async fn get_all_documents(ctx: Context<Data>) -> SResult<Response> {
@@ -80,6 +157,12 @@ pub async fn get_all_documents(ctx: Context<Data>) -> SResult<Response> {
     let index = ctx.index()?;
     let query: BrowseQuery = ctx.url_query().unwrap_or(BrowseQuery::default());
 
+    // the raw documents contain the attributes that are not displayed
+    let raw = query.raw.unwrap_or(false);
+    if raw {
+        ctx.is_allowed(Admin)?;
+    }
+
     let offset = query.offset.unwrap_or(0);
     let limit = query.limit.unwrap_or(20);
 
@@ -99,7 +182,13 @@ pub async fn get_all_documents(ctx: Context<Data>) -> SResult<Response> {
 
     let mut response_body = Vec::<IndexMap<String, Value>>::new();
 
-    if let Some(attributes) = query.attributes_to_retrieve {
+    if raw {
+        for document_id in documents_ids {
+            if let Ok(Some(document)) = index.raw_document(&reader, document_id) {
+                response_body.push(document.into_iter().collect());
+            }
+        }
+    } else if let Some(attributes) = query.attributes_to_retrieve {
         let attributes = attributes.split(',').collect::<HashSet<&str>>();
         for document_id in documents_ids {
             if let Ok(Some(document)) = index.document(&reader, Some(&attributes), document_id) {
@@ -114,69 +203,398 @@ pub async fn get_all_documents(ctx: Context<Data>) -> SResult<Response> {
         }
     }
 
-    Ok(tide::response::json(response_body))
+    if let Some(allowed_attributes) = ctx.allowed_attributes()? {
+        for document in &mut response_body {
+            retain_allowed_attributes(&allowed_attributes, document);
+        }
+    }
+
+    Ok(json_stream(JsonChunks::array(response_body)))
+}
+
+/// Infers a schema from a document, an `id` identifier is added
+/// when none is found and the identifiers are generated.
+fn infered_schema(
+    document: &IndexMap<String, Value>,
+    generation: IdentifiersGeneration,
+) -> Option<meilidb_schema::Schema> {
+    let default_identifier = match generation {
+        IdentifiersGeneration::Disabled => None,
+        _ => Some("id"),
+    };
+    infer_schema(document.keys().map(String::as_str), default_identifier)
+}
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct UpdateDocumentsQuery {
+    duplicates: Option<String>,
+    verify_identifiers: Option<String>,
+    generate_identifiers: Option<String>,
+    csv_delimiter: Option<String>,
+    csv_types: Option<String>,
+}
+
+/// How the documents of an addition request are indexed.
+#[derive(Debug, Copy, Clone)]
+struct AdditionOptions {
+    is_partial: bool,
+    duplicates_policy: DuplicatesPolicy,
+    identifiers_verification: IdentifiersVerification,
+    identifiers_generation: IdentifiersGeneration,
+}
+
+impl UpdateDocumentsQuery {
+    fn addition_options(&self, is_partial: bool) -> SResult<AdditionOptions> {
+        let duplicates_policy = match self.duplicates.as_ref().map(String::as_str) {
+            None | Some("ignore") => DuplicatesPolicy::Ignore,
+            Some("report") => DuplicatesPolicy::Report,
+            Some("skip") => DuplicatesPolicy::Skip,
+            Some(other) => {
+                return Err(ResponseError::bad_request(format!(
+                    "invalid duplicates policy {:?}, expected ignore, report or skip",
+                    other
+                )))
+            }
+        };
+
+        let identifiers_verification = match self.verify_identifiers.as_ref().map(String::as_str) {
+            None | Some("ignore") => IdentifiersVerification::Ignore,
+            Some("addition") => IdentifiersVerification::Addition,
+            Some("indexed") => IdentifiersVerification::Indexed,
+            Some(other) => {
+                return Err(ResponseError::bad_request(format!(
+                    "invalid identifiers verification {:?}, expected ignore, addition or indexed",
+                    other
+                )))
+            }
+        };
+
+        let identifiers_generation = match self.generate_identifiers.as_ref().map(String::as_str) {
+            None | Some("disabled") => IdentifiersGeneration::Disabled,
+            Some("content") => IdentifiersGeneration::Content,
+            Some("uuid") => IdentifiersGeneration::Uuid,
+            Some(other) => {
+                return Err(ResponseError::bad_request(format!(
+                    "invalid identifiers generation {:?}, expected disabled, content or uuid",
+                    other
+                )))
+            }
+        };
+
+        Ok(AdditionOptions {
+            is_partial,
+            duplicates_policy,
+            identifiers_verification,
+            identifiers_generation,
+        })
+    }
+
+    fn csv_options(&self) -> SResult<CsvOptions> {
+        let mut options = CsvOptions::default();
+
+        if let Some(delimiter) = &self.csv_delimiter {
+            match delimiter.as_bytes() {
+                [byte] => options.delimiter = *byte,
+                _ => {
+                    return Err(ResponseError::bad_request(format!(
+                        "invalid csv delimiter {:?}, expected a single ascii character",
+                        delimiter
+                    )))
+                }
+            }
+        }
+
+        if let Some(types) = &self.csv_types {
+            options.types = CsvOptions::parse_types(types).map_err(ResponseError::bad_request)?;
+        }
+
+        Ok(options)
+    }
+}
+
+/// The number of documents of the updates enqueued once a stream of documents is read.
+const STREAM_BATCH_SIZE: usize = 1000;
+
+static SPOOLED_STREAMS: AtomicUsize = AtomicUsize::new(0);
+
+type Document = IndexMap<String, Value>;
+
+/// The documents of a stream written as JSON lines to a temporary file while the
+/// stream is read, the file is removed once the documents are enqueued or refused.
+struct SpooledDocuments {
+    path: PathBuf,
+    file: BufWriter<File>,
+    len: usize,
 }
 
-fn infered_schema(document: &IndexMap<String, Value>) -> Option<meilidb_schema::Schema> {
-    use meilidb_schema::{SchemaBuilder, DISPLAYED, INDEXED};
+impl SpooledDocuments {
+    fn create() -> io::Result<SpooledDocuments> {
+        let count = SPOOLED_STREAMS.fetch_add(1, Ordering::SeqCst);
+        let name = format!("meilidb-stream-{}-{}", process::id(), count);
+        let path = std::env::temp_dir().join(name);
+
+        let file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)?;
+
+        Ok(SpooledDocuments {
+            path,
+            file: BufWriter::new(file),
+            len: 0,
+        })
+    }
+
+    fn push(&mut self, document: &Document) -> io::Result<()> {
+        serde_json::to_writer(&mut self.file, document)?;
+        self.file.write_all(b"\n")?;
+        self.len += 1;
+        Ok(())
+    }
+
+    /// The spooled documents in the order they were pushed.
+    fn documents(&mut self) -> io::Result<impl Iterator<Item = io::Result<Document>>> {
+        self.file.flush()?;
+        let reader = BufReader::new(File::open(&self.path)?);
+        Ok(reader.lines().map(|line| {
+            let line = line?;
+            serde_json::from_str(&line).map_err(io::Error::from)
+        }))
+    }
+}
 
-    let mut identifier = None;
-    for key in document.keys() {
-        if identifier.is_none() && key.to_lowercase().contains("id") {
-            identifier = Some(key);
+impl Drop for SpooledDocuments {
+    fn drop(&mut self) {
+        if let Err(e) = fs::remove_file(&self.path) {
+            warn!("could not remove the stream file {:?}; {}", self.path, e);
         }
     }
+}
+
+/// Splits the chunks of a body into the documents of its lines,
+/// the chunks do not need to end on a line boundary.
+#[derive(Default)]
+struct NdjsonDecoder {
+    buffer: Vec<u8>,
+    line_number: usize,
+}
 
-    match identifier {
-        Some(identifier) => {
-            let mut builder = SchemaBuilder::with_identifier(identifier);
-            for key in document.keys() {
-                builder.new_attribute(key, DISPLAYED | INDEXED);
+impl NdjsonDecoder {
+    fn decode(&mut self, chunk: &[u8], documents: &mut Vec<Document>) -> Result<(), String> {
+        self.buffer.extend_from_slice(chunk);
+
+        while let Some(position) = self.buffer.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=position).collect();
+            self.line_number += 1;
+
+            if line.iter().all(u8::is_ascii_whitespace) {
+                continue;
+            }
+
+            match serde_json::from_slice(&line) {
+                Ok(document) => documents.push(document),
+                Err(e) => {
+                    return Err(format!(
+                        "invalid document at line {}; {}",
+                        self.line_number, e
+                    ))
+                }
             }
-            Some(builder.build())
         }
-        None => None,
+
+        Ok(())
+    }
+
+    /// Decodes the last line, it does not need to end with a new line.
+    fn finish(&mut self, documents: &mut Vec<Document>) -> Result<(), String> {
+        self.decode(b"\n", documents)
     }
 }
 
-async fn update_multiple_documents(mut ctx: Context<Data>, is_partial: bool) -> SResult<Response> {
-    ctx.is_allowed(DocumentsWrite)?;
+fn has_content_type(ctx: &Context<Data>, content_type: &str) -> bool {
+    ctx.request()
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map_or(false, |value| value.starts_with(content_type))
+}
 
-    let data: Vec<IndexMap<String, Value>> =
-        ctx.body_json().await.map_err(ResponseError::bad_request)?;
-    let index = ctx.index()?;
+fn is_ndjson(ctx: &Context<Data>) -> bool {
+    has_content_type(ctx, "application/x-ndjson")
+}
 
-    let env = &ctx.state().db.env;
-    let mut writer = env.write_txn().map_err(ResponseError::internal)?;
+fn is_csv(ctx: &Context<Data>) -> bool {
+    has_content_type(ctx, "text/csv")
+}
+
+/// Enqueues the documents addition, the schema is inferred from
+/// the first document if the index does not have one yet.
+fn enqueue_documents(
+    ctx: &Context<Data>,
+    index: &Index,
+    writer: &mut heed::RwTxn,
+    data: Vec<Document>,
+    options: AdditionOptions,
+) -> SResult<u64> {
+    let current_schema = index.main.schema(writer).map_err(ResponseError::internal)?;
+
+    // an index must have a schema to be sampled
+    if let Some(schema) = &current_schema {
+        let index_uid = ctx.url_param("index")?;
+        ctx.state()
+            .sample_documents_addition(
+                writer,
+                &index_uid,
+                schema.identifier_name(),
+                &data,
+                options.is_partial,
+            )
+            .map_err(ResponseError::internal)?;
+    }
 
-    let current_schema = index
-        .main
-        .schema(&writer)
-        .map_err(ResponseError::internal)?;
     if current_schema.is_none() {
-        match data.first().and_then(infered_schema) {
+        let generation = options.identifiers_generation;
+        match data
+            .first()
+            .and_then(|document| infered_schema(document, generation))
+        {
             Some(schema) => {
                 index
-                    .schema_update(&mut writer, schema)
+                    .schema_update(writer, schema)
                     .map_err(ResponseError::internal)?;
             }
             None => return Err(ResponseError::bad_request("Could not infer a schema")),
         }
     }
 
-    let mut document_addition = if is_partial {
+    let mut document_addition = if options.is_partial {
         index.documents_partial_addition()
     } else {
         index.documents_addition()
     };
 
+    document_addition.duplicates_policy(options.duplicates_policy);
+    document_addition.identifiers_verification(options.identifiers_verification);
+    document_addition.identifiers_generation(options.identifiers_generation);
+
     for document in data {
         document_addition.update_document(document);
     }
 
-    let update_id = document_addition
-        .finalize(&mut writer)
-        .map_err(ResponseError::internal)?;
+    let update_id = document_addition.finalize(writer).map_err(|e| match e {
+        meilidb_core::Error::LimitExceeded(limit) => ResponseError::payload_too_large(limit),
+        e => ResponseError::internal(e),
+    })?;
+
+    ctx.register_update(writer, index, update_id)?;
+    ctx.audit(writer, AuditAction::DocumentsAddition)?;
+
+    Ok(update_id)
+}
+
+/// Reads the documents of the body line by line and enqueues them by batches, the body is
+/// never entirely buffered in memory but spooled to a temporary file, the batches are only
+/// enqueued, all at once, when every line of the body is valid. The updates are processed
+/// in order, the identifier of the last one is returned and the idempotency key, if any,
+/// is associated with it.
+async fn update_documents_stream(
+    mut ctx: Context<Data>,
+    options: AdditionOptions,
+) -> SResult<Response> {
+    let index = ctx.index()?;
+    let env = ctx.state().db.env.clone();
+
+    {
+        let reader = env.read_txn().map_err(ResponseError::internal)?;
+        if let Some(response) = ctx.replayed_update(&reader, &index)? {
+            return Ok(response);
+        }
+    }
+
+    let mut body = ctx.take_body();
+    let mut decoder = NdjsonDecoder::default();
+    let mut spool = SpooledDocuments::create().map_err(ResponseError::internal)?;
+    let mut decoded = Vec::new();
+    let mut end_of_body = false;
+
+    while !end_of_body {
+        let result = match body.next().await {
+            Some(chunk) => {
+                let chunk = chunk.map_err(ResponseError::bad_request)?;
+                decoder.decode(&chunk, &mut decoded)
+            }
+            None => {
+                end_of_body = true;
+                decoder.finish(&mut decoded)
+            }
+        };
+        result.map_err(ResponseError::bad_request)?;
+
+        for document in decoded.drain(..) {
+            spool.push(&document).map_err(ResponseError::internal)?;
+        }
+    }
+
+    if spool.len == 0 {
+        return Err(ResponseError::bad_request(
+            "the body does not contain any document",
+        ));
+    }
+
+    // the batches are enqueued in the same transaction, none of them is if one is refused
+    let mut writer = env.write_txn().map_err(ResponseError::internal)?;
+    let mut batch = Vec::with_capacity(STREAM_BATCH_SIZE);
+    let mut update_id = 0;
+
+    let mut documents = spool
+        .documents()
+        .map_err(ResponseError::internal)?
+        .peekable();
+    while let Some(document) = documents.next() {
+        batch.push(document.map_err(ResponseError::internal)?);
+
+        if batch.len() >= STREAM_BATCH_SIZE || documents.peek().is_none() {
+            let batch = std::mem::replace(&mut batch, Vec::with_capacity(STREAM_BATCH_SIZE));
+            update_id = enqueue_documents(&ctx, &index, &mut writer, batch, options)?;
+        }
+    }
+
+    writer.commit().map_err(ResponseError::internal)?;
+
+    let response_body = IndexUpdateResponse { update_id };
+    Ok(tide::response::json(response_body)
+        .with_status(StatusCode::ACCEPTED)
+        .into_response())
+}
+
+async fn update_multiple_documents(mut ctx: Context<Data>, is_partial: bool) -> SResult<Response> {
+    ctx.is_allowed(DocumentsWrite)?;
+
+    let query: UpdateDocumentsQuery = ctx.url_query().unwrap_or_default();
+    let options = query.addition_options(is_partial)?;
+
+    if is_ndjson(&ctx) {
+        return update_documents_stream(ctx, options).await;
+    }
+
+    let data: Vec<Document> = if is_csv(&ctx) {
+        let options = query.csv_options()?;
+        let body = ctx.body_bytes().await.map_err(ResponseError::bad_request)?;
+        csv_documents(&body[..], &options).map_err(ResponseError::bad_request)?
+    } else {
+        ctx.body_json().await.map_err(ResponseError::bad_request)?
+    };
+    let index = ctx.index()?;
+
+    let env = &ctx.state().db.env;
+    let mut writer = env.write_txn().map_err(ResponseError::internal)?;
+
+    if let Some(response) = ctx.replayed_update(&writer, &index)? {
+        return Ok(response);
+    }
+
+    let update_id = enqueue_documents(&ctx, &index, &mut writer, data, options)?;
 
     writer.commit().map_err(ResponseError::internal)?;
 
@@ -186,10 +604,14 @@ async fn update_multiple_documents(mut ctx: Context<Data>, is_partial: bool) ->
         .into_response())
 }
 
+/// The documents sent replace the stored ones entirely, the fields missing
+/// from them are removed.
 pub async fn add_or_replace_multiple_documents(ctx: Context<Data>) -> SResult<Response> {
     update_multiple_documents(ctx, false).await
 }
 
+/// The documents sent are merged with the stored ones field by field,
+/// the fields missing from them are kept as they are.
 pub async fn add_or_update_multiple_documents(ctx: Context<Data>) -> SResult<Response> {
     update_multiple_documents(ctx, true).await
 }
@@ -198,17 +620,23 @@ pub async fn delete_multiple_documents(mut ctx: Context<Data>) -> SResult<Respon
     ctx.is_allowed(DocumentsWrite)?;
 
     let data: Vec<Value> = ctx.body_json().await.map_err(ResponseError::bad_request)?;
+    let index_uid = ctx.url_param("index")?;
     let index = ctx.index()?;
 
     let env = &ctx.state().db.env;
     let mut writer = env.write_txn().map_err(ResponseError::internal)?;
 
+    if let Some(response) = ctx.replayed_update(&writer, &index)? {
+        return Ok(response);
+    }
+
     let mut documents_deletion = index.documents_deletion();
+    let mut keys = HashSet::new();
 
     for identifier in data {
-        if let Some(identifier) = meilidb_core::serde::value_to_string(&identifier) {
-            documents_deletion
-                .delete_document_by_id(meilidb_core::serde::compute_document_id(identifier));
+        if let Ok(key) = DocumentKey::from_value(&identifier) {
+            documents_deletion.delete_document_by_id(key.document_id());
+            keys.insert(key.to_string());
         }
     }
 
@@ -216,6 +644,55 @@ pub async fn delete_multiple_documents(mut ctx: Context<Data>) -> SResult<Respon
         .finalize(&mut writer)
         .map_err(ResponseError::internal)?;
 
+    ctx.state()
+        .sample_documents_deletion(&mut writer, &index_uid, &keys)
+        .map_err(ResponseError::internal)?;
+
+    ctx.register_update(&mut writer, &index, update_id)?;
+    ctx.audit(&mut writer, AuditAction::DocumentsDeletion)?;
+
+    writer.commit().map_err(ResponseError::internal)?;
+
+    let response_body = IndexUpdateResponse { update_id };
+    Ok(tide::response::json(response_body)
+        .with_status(StatusCode::ACCEPTED)
+        .into_response())
+}
+
+pub async fn delete_documents_by_filter(mut ctx: Context<Data>) -> SResult<Response> {
+    ctx.is_allowed(DocumentsWrite)?;
+
+    let body: DeleteByFilterRequest = ctx.body_json().await.map_err(ResponseError::bad_request)?;
+    let filter: Filter = body.filter.parse().map_err(ResponseError::bad_request)?;
+    let index_uid = ctx.url_param("index")?;
+    let index = ctx.index()?;
+
+    let env = &ctx.state().db.env;
+    let mut writer = env.write_txn().map_err(ResponseError::internal)?;
+
+    if let Some(response) = ctx.replayed_update(&writer, &index)? {
+        return Ok(response);
+    }
+
+    let mut documents_deletion = index.documents_deletion();
+    let documents_ids = match documents_deletion.delete_by_filter(&writer, &filter) {
+        Ok(documents_ids) => documents_ids,
+        Err(meilidb_core::Error::Filter(e)) => return Err(ResponseError::bad_request(e)),
+        Err(e) => return Err(ResponseError::internal(e)),
+    };
+
+    let update_id = documents_deletion
+        .finalize(&mut writer)
+        .map_err(ResponseError::internal)?;
+
+    let documents_ids: HashSet<_> = documents_ids.into_iter().collect();
+    ctx.state()
+        .sample_documents_deletion_by_ids(&mut writer, &index_uid, &documents_ids)
+        .map_err(ResponseError::internal)?;
+
+    ctx.register_update(&mut writer, &index, update_id)?;
+    ctx.audit(&mut writer, AuditAction::DocumentsDeletion)?;
+
     writer.commit().map_err(ResponseError::internal)?;
 
     let response_body = IndexUpdateResponse { update_id };
@@ -227,13 +704,25 @@ pub async fn delete_multiple_documents(mut ctx: Context<Data>) -> SResult<Respon
 pub async fn clear_all_documents(ctx: Context<Data>) -> SResult<Response> {
     ctx.is_allowed(DocumentsWrite)?;
 
+    let index_uid = ctx.url_param("index")?;
     let index = ctx.index()?;
 
     let env = &ctx.state().db.env;
     let mut writer = env.write_txn().map_err(ResponseError::internal)?;
+
+    if let Some(response) = ctx.replayed_update(&writer, &index)? {
+        return Ok(response);
+    }
     let update_id = index
         .clear_all(&mut writer)
         .map_err(ResponseError::internal)?;
+
+    ctx.state()
+        .sample_clear_all(&mut writer, &index_uid)
+        .map_err(ResponseError::internal)?;
+    ctx.register_update(&mut writer, &index, update_id)?;
+    ctx.audit(&mut writer, AuditAction::DocumentsClear)?;
+
     writer.commit().map_err(ResponseError::internal)?;
 
     let response_body = IndexUpdateResponse { update_id };
@@ -241,3 +730,75 @@ pub async fn clear_all_documents(ctx: Context<Data>) -> SResult<Response> {
         .with_status(StatusCode::ACCEPTED)
         .into_response())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ndjson_decoder() {
+        let mut decoder = NdjsonDecoder::default();
+        let mut documents = Vec::new();
+
+        decoder
+            .decode(b"{\"id\": 1, \"title\": \"al", &mut documents)
+            .unwrap();
+        assert!(documents.is_empty());
+
+        decoder
+            .decode(b"ien\"}\n\n{\"id\": 2}\n{\"id\"", &mut documents)
+            .unwrap();
+        assert_eq!(documents.len(), 2);
+        assert_eq!(documents[0]["title"], "alien");
+
+        decoder.decode(b": 3}", &mut documents).unwrap();
+        decoder.finish(&mut documents).unwrap();
+        assert_eq!(documents.len(), 3);
+        assert_eq!(documents[2]["id"], 3);
+
+        let mut decoder = NdjsonDecoder::default();
+        let error = decoder
+            .decode(b"{\"id\": 1}\nnot json\n", &mut documents)
+            .unwrap_err();
+        assert!(error.starts_with("invalid document at line 2"));
+    }
+
+    #[test]
+    fn spooled_documents() {
+        let mut spool = SpooledDocuments::create().unwrap();
+        let path = spool.path.clone();
+
+        let documents: Vec<Document> = vec![
+            serde_json::from_str(r#"{ "id": 1, "title": "alien" }"#).unwrap(),
+            serde_json::from_str(r#"{ "id": 2, "title": "aliens" }"#).unwrap(),
+        ];
+        for document in &documents {
+            spool.push(document).unwrap();
+        }
+        assert_eq!(spool.len, 2);
+
+        let spooled: Vec<_> = spool.documents().unwrap().map(Result::unwrap).collect();
+        assert_eq!(spooled, documents);
+
+        drop(spool);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn infered_schema_identifier() {
+        let mut document = Document::new();
+        document.insert("title".to_string(), Value::from("alien"));
+
+        assert!(infered_schema(&document, IdentifiersGeneration::Disabled).is_none());
+
+        let schema = infered_schema(&document, IdentifiersGeneration::Uuid).unwrap();
+        assert_eq!(schema.identifier_name(), "id");
+        assert!(schema.attribute("id").is_some());
+        assert!(schema.attribute("title").is_some());
+
+        document.insert("movie_id".to_string(), Value::from(1));
+        let schema = infered_schema(&document, IdentifiersGeneration::Uuid).unwrap();
+        assert_eq!(schema.identifier_name(), "movie_id");
+        assert!(schema.attribute("id").is_none());
+    }
+}
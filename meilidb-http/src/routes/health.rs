@@ -3,13 +3,21 @@ use crate::helpers::tide::ContextExt;
 use crate::models::token::ACL::*;
 use crate::Data;
 
+use chrono::{DateTime, Utc};
 use heed::types::{Str, Unit};
-use serde::Deserialize;
-use tide::Context;
+use serde::{Deserialize, Serialize};
+use tide::{Context, Response};
 
 const UNHEALTHY_KEY: &str = "_is_unhealthy";
 
-pub async fn get_health(ctx: Context<Data>) -> SResult<()> {
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct HealthResponse {
+    last_snapshot_at: Option<DateTime<Utc>>,
+    read_only: bool,
+}
+
+pub async fn get_health(ctx: Context<Data>) -> SResult<Response> {
     let db = &ctx.state().db;
     let env = &db.env;
     let reader = env.read_txn().map_err(ResponseError::internal)?;
@@ -20,7 +28,18 @@ pub async fn get_health(ctx: Context<Data>) -> SResult<()> {
         return Err(ResponseError::Maintenance);
     }
 
-    Ok(())
+    let last_snapshot_at = ctx
+        .state()
+        .last_snapshot_success(&reader)
+        .map_err(ResponseError::internal)?;
+
+    let read_only = ctx.state().db.is_read_only();
+
+    let response = HealthResponse {
+        last_snapshot_at,
+        read_only,
+    };
+    Ok(tide::response::json(response))
 }
 
 pub async fn set_healthy(ctx: Context<Data>) -> SResult<()> {
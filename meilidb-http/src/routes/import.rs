@@ -0,0 +1,390 @@
+use std::thread;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use heed::types::{SerdeBincode, Str};
+use http::StatusCode;
+use indexmap::IndexMap;
+use isahc::prelude::*;
+use log::error;
+use meilidb_core::{Index, IndexSettings, MResult};
+use meilidb_dto::{QueryQuotaBody, ResponseFormat};
+use meilidb_schema::SchemaBuilder;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tide::response::IntoResponse;
+use tide::{Context, Response};
+
+use crate::error::{ResponseError, SResult};
+use crate::helpers::tide::ContextExt;
+use crate::models::token::ACL::*;
+use crate::Data;
+
+const IMPORT_PREFIX_KEY: &str = "_import_";
+const DEFAULT_BATCH_SIZE: usize = 1000;
+const REMOTE_REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct ImportRequest {
+    url: String,
+    api_key: Option<String>,
+    source_index: Option<String>,
+    batch_size: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ImportStatus {
+    Importing,
+    Imported,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportProgress {
+    pub url: String,
+    pub source_index: String,
+    pub status: ImportStatus,
+    pub documents_imported: usize,
+    pub updates_ids: Vec<u64>,
+    pub error: Option<String>,
+    pub started_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+fn import_key(index_uid: &str) -> String {
+    format!("{}{}", IMPORT_PREFIX_KEY, index_uid)
+}
+
+impl Data {
+    pub fn import_progress(
+        &self,
+        reader: &heed::RoTxn,
+        index_uid: &str,
+    ) -> MResult<Option<ImportProgress>> {
+        let progress = self
+            .db
+            .common_store()
+            .get::<Str, SerdeBincode<ImportProgress>>(reader, &import_key(index_uid))?;
+
+        Ok(progress)
+    }
+
+    pub fn put_import_progress(
+        &self,
+        writer: &mut heed::RwTxn,
+        index_uid: &str,
+        progress: &ImportProgress,
+    ) -> MResult<()> {
+        self.db
+            .common_store()
+            .put::<Str, SerdeBincode<ImportProgress>>(writer, &import_key(index_uid), progress)?;
+
+        Ok(())
+    }
+
+    /// Marks the imports left in progress by a previous run as failed,
+    /// they were interrupted and would prevent any new import of their index.
+    pub fn fail_interrupted_imports(&self) -> MResult<()> {
+        let env = &self.db.env;
+        let mut writer = env.write_txn()?;
+
+        let mut interrupted = Vec::new();
+        let common_store = self.db.common_store();
+        for result in common_store
+            .prefix_iter::<Str, SerdeBincode<ImportProgress>>(&writer, IMPORT_PREFIX_KEY)?
+        {
+            let (key, progress) = result?;
+            if progress.status == ImportStatus::Importing {
+                interrupted.push((key[IMPORT_PREFIX_KEY.len()..].to_owned(), progress));
+            }
+        }
+
+        for (index_uid, mut progress) in interrupted {
+            progress.status = ImportStatus::Failed;
+            progress.error = Some("the import was interrupted by a restart".to_string());
+            progress.updated_at = Utc::now();
+            self.put_import_progress(&mut writer, &index_uid, &progress)?;
+        }
+
+        writer.commit()?;
+
+        Ok(())
+    }
+}
+
+fn put_progress(data: &Data, index_uid: &str, progress: &ImportProgress) -> Result<(), String> {
+    let env = &data.db.env;
+    let mut writer = env.write_txn().map_err(|e| e.to_string())?;
+    data.put_import_progress(&mut writer, index_uid, progress)
+        .map_err(|e| e.to_string())?;
+    writer.commit().map_err(|e| e.to_string())
+}
+
+struct RemoteIndex {
+    url: String,
+    api_key: Option<String>,
+    index_uid: String,
+}
+
+impl RemoteIndex {
+    fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T, String> {
+        let url = format!(
+            "{}/indexes/{}{}",
+            self.url.trim_end_matches('/'),
+            self.index_uid,
+            path
+        );
+
+        let mut builder = Request::get(&url);
+        builder.timeout(REMOTE_REQUEST_TIMEOUT);
+        if let Some(api_key) = &self.api_key {
+            builder.header("X-Meili-API-Key", api_key.as_str());
+        }
+
+        let request = builder.body(()).map_err(|e| e.to_string())?;
+        let response = request.send().map_err(|e| e.to_string())?;
+        let status = response.status();
+        let body = response.into_body().text().map_err(|e| e.to_string())?;
+
+        if !status.is_success() {
+            return Err(format!("{} returned {}: {}", url, status, body));
+        }
+
+        serde_json::from_str(&body).map_err(|e| e.to_string())
+    }
+}
+
+fn import_index(
+    data: &Data,
+    index: &Index,
+    remote: &RemoteIndex,
+    batch_size: usize,
+    progress: &mut ImportProgress,
+    index_uid: &str,
+) -> Result<(), String> {
+    let env = &data.db.env;
+
+    // import the schema and the settings first, documents can't be indexed without them
+    let schema = remote.get::<SchemaBuilder>("/schema?raw=true")?.build();
+    let settings = remote.get::<IndexSettings>("/settings/all")?;
+    let response_format = remote.get::<ResponseFormat>("/settings/response-format")?;
+    let query_quota = remote.get::<QueryQuotaBody>("/settings/query-quota")?;
+
+    let mut writer = env.write_txn().map_err(|e| e.to_string())?;
+
+    let current_schema = index.main.schema(&writer).map_err(|e| e.to_string())?;
+    if current_schema.is_none() {
+        let update_id = index
+            .schema_update(&mut writer, schema)
+            .map_err(|e| e.to_string())?;
+        progress.updates_ids.push(update_id);
+    }
+
+    // the settings are written directly, they are in place
+    // before any of the imported documents is indexed
+    settings
+        .write(&mut writer, index)
+        .map_err(|e| e.to_string())?;
+
+    data.put_response_format(&mut writer, index_uid, &response_format)
+        .map_err(|e| e.to_string())?;
+    data.put_query_quota(&mut writer, index_uid, &query_quota)
+        .map_err(|e| e.to_string())?;
+
+    writer.commit().map_err(|e| e.to_string())?;
+    progress.updated_at = Utc::now();
+    put_progress(data, index_uid, progress)?;
+
+    // then browse all the remote documents batch by batch
+    let mut offset = 0;
+    loop {
+        // the raw documents contain the attributes that are not displayed
+        let path = format!("/documents?offset={}&limit={}&raw=true", offset, batch_size);
+        let documents = remote.get::<Vec<IndexMap<String, Value>>>(&path)?;
+        if documents.is_empty() {
+            break;
+        }
+
+        let number_of_documents = documents.len();
+
+        let mut writer = env.write_txn().map_err(|e| e.to_string())?;
+        let mut documents_addition = index.documents_addition();
+        for document in documents {
+            documents_addition.update_document(document);
+        }
+        let update_id = documents_addition
+            .finalize(&mut writer)
+            .map_err(|e| e.to_string())?;
+        writer.commit().map_err(|e| e.to_string())?;
+
+        offset += number_of_documents;
+        progress.documents_imported += number_of_documents;
+        progress.updates_ids.push(update_id);
+        progress.updated_at = Utc::now();
+        put_progress(data, index_uid, progress)?;
+
+        if number_of_documents < batch_size {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+pub async fn import(mut ctx: Context<Data>) -> SResult<Response> {
+    ctx.is_allowed(IndexesWrite)?;
+    ctx.is_allowed(DocumentsWrite)?;
+
+    let body = ctx
+        .body_json::<ImportRequest>()
+        .await
+        .map_err(ResponseError::bad_request)?;
+
+    let index_uid = ctx.url_param("index")?;
+    let index = ctx.index()?;
+
+    let batch_size = body.batch_size.unwrap_or(DEFAULT_BATCH_SIZE).max(1);
+    let remote = RemoteIndex {
+        url: body.url,
+        api_key: body.api_key,
+        index_uid: body.source_index.unwrap_or_else(|| index_uid.clone()),
+    };
+
+    let mut progress = ImportProgress {
+        url: remote.url.clone(),
+        source_index: remote.index_uid.clone(),
+        status: ImportStatus::Importing,
+        documents_imported: 0,
+        updates_ids: Vec::new(),
+        error: None,
+        started_at: Utc::now(),
+        updated_at: Utc::now(),
+    };
+
+    // the check and the write are made in the same transaction,
+    // two concurrent imports of the same index cannot both start
+    let env = &ctx.state().db.env;
+    let mut writer = env.write_txn().map_err(ResponseError::internal)?;
+    let previous = ctx
+        .state()
+        .import_progress(&writer, &index_uid)
+        .map_err(ResponseError::internal)?;
+
+    if let Some(ImportProgress {
+        status: ImportStatus::Importing,
+        ..
+    }) = previous
+    {
+        writer.abort();
+        return Err(ResponseError::bad_request(
+            "an import is already in progress for this index",
+        ));
+    }
+
+    ctx.state()
+        .put_import_progress(&mut writer, &index_uid, &progress)
+        .map_err(ResponseError::internal)?;
+    writer.commit().map_err(ResponseError::internal)?;
+
+    let data = ctx.state().clone();
+    let response_body = progress.clone();
+    thread::spawn(move || {
+        let result = import_index(
+            &data,
+            &index,
+            &remote,
+            batch_size,
+            &mut progress,
+            &index_uid,
+        );
+
+        match result {
+            Ok(()) => progress.status = ImportStatus::Imported,
+            Err(e) => {
+                error!("Impossible to import the index {}; {}", index_uid, e);
+                progress.status = ImportStatus::Failed;
+                progress.error = Some(e);
+            }
+        }
+
+        progress.updated_at = Utc::now();
+        if let Err(e) = put_progress(&data, &index_uid, &progress) {
+            error!("Impossible to save the import progress; {}", e);
+        }
+    });
+
+    Ok(tide::response::json(response_body)
+        .with_status(StatusCode::ACCEPTED)
+        .into_response())
+}
+
+pub async fn get_import_status(ctx: Context<Data>) -> SResult<Response> {
+    ctx.is_allowed(IndexesRead)?;
+
+    let index_uid = ctx.url_param("index")?;
+
+    let env = &ctx.state().db.env;
+    let reader = env.read_txn().map_err(ResponseError::internal)?;
+
+    let progress = ctx
+        .state()
+        .import_progress(&reader, &index_uid)
+        .map_err(ResponseError::internal)?
+        .ok_or_else(|| ResponseError::not_found("no import for this index"))?;
+
+    Ok(tide::response::json(progress))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stored_import_progress() {
+        let (_dir, data) = Data::temporary();
+
+        let progress = |error: Option<&str>| ImportProgress {
+            url: "http://localhost:7700".to_string(),
+            source_index: "movies".to_string(),
+            status: ImportStatus::Importing,
+            documents_imported: 1000,
+            updates_ids: vec![0, 1],
+            error: error.map(ToOwned::to_owned),
+            started_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+
+        let env = &data.db.env;
+        let mut writer = env.write_txn().unwrap();
+        data.put_import_progress(&mut writer, "movies", &progress(None))
+            .unwrap();
+        data.put_import_progress(&mut writer, "series", &progress(Some("timeout")))
+            .unwrap();
+        writer.commit().unwrap();
+
+        let reader = env.read_txn().unwrap();
+        let movies = data.import_progress(&reader, "movies").unwrap().unwrap();
+        assert_eq!(movies.status, ImportStatus::Importing);
+        assert_eq!(movies.updates_ids, vec![0, 1]);
+        assert_eq!(movies.error, None);
+
+        let series = data.import_progress(&reader, "series").unwrap().unwrap();
+        assert_eq!(series.error, Some("timeout".to_string()));
+
+        assert!(data.import_progress(&reader, "books").unwrap().is_none());
+        drop(reader);
+
+        // the imports left in progress are failed on startup
+        data.fail_interrupted_imports().unwrap();
+
+        let reader = env.read_txn().unwrap();
+        let movies = data.import_progress(&reader, "movies").unwrap().unwrap();
+        assert_eq!(movies.status, ImportStatus::Failed);
+        assert!(movies.error.is_some());
+        assert_eq!(movies.documents_imported, 1000);
+    }
+}
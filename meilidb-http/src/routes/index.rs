@@ -1,20 +1,25 @@
 use chrono::{DateTime, Utc};
 use http::StatusCode;
 use log::error;
-use meilidb_core::ProcessedUpdateResult;
+use meilidb_core::{check_searchable, ProcessedUpdateResult};
+use meilidb_dto::{
+    IndexCloneRequest, IndexCreateRequest, IndexCreateResponse, IndexMetadata, IndexResponse,
+    IndexUpdateResponse, IndexesQuery, MigrationBody, SchemaBody, UpdateIndexRequest,
+    UpdateIndexResponse,
+};
 use meilidb_schema::{Schema, SchemaBuilder};
 use rand::seq::SliceRandom;
-use serde::{Deserialize, Serialize};
-use serde_json::json;
+use serde::Deserialize;
+use serde_json::{json, Value};
 use tide::querystring::ContextExt as QSContextExt;
 use tide::response::IntoResponse;
 use tide::{Context, Response};
 
+use crate::audit::AuditAction;
 use crate::error::{ResponseError, SResult};
 use crate::helpers::tide::ContextExt;
-use crate::models::schema::SchemaBody;
 use crate::models::token::ACL::*;
-use crate::routes::document::IndexUpdateResponse;
+use crate::sample::sample_index_uid;
 use crate::Data;
 
 fn generate_uid() -> String {
@@ -26,62 +31,64 @@ fn generate_uid() -> String {
         .collect()
 }
 
-pub async fn list_indexes(ctx: Context<Data>) -> SResult<Response> {
-    ctx.is_allowed(IndexesRead)?;
+/// Whether the uid or the name of the index starts with the prefix, case insensitively.
+fn name_or_uid_matches(index: &IndexResponse, prefix: &str) -> bool {
+    let prefix = prefix.to_lowercase();
+    index.uid.to_lowercase().starts_with(&prefix) || index.name.to_lowercase().starts_with(&prefix)
+}
 
-    let indexes_uids = ctx.state().db.indexes_uids();
+/// Whether the metadata matches a `key:value` filter, the value of the key must be equal
+/// to the given one or be an array containing it, a filter without value only checks
+/// that the key exists.
+fn metadata_matches(metadata: Option<&IndexMetadata>, filter: &str) -> bool {
+    let (key, expected) = match filter.find(':') {
+        Some(pos) => (&filter[..pos], Some(&filter[pos + 1..])),
+        None => (filter, None),
+    };
 
-    let env = &ctx.state().db.env;
-    let reader = env.read_txn().map_err(ResponseError::internal)?;
+    let value = match metadata.and_then(|m| m.get(key)) {
+        Some(value) => value,
+        None => return false,
+    };
 
-    let mut response_body = Vec::new();
-
-    for index_uid in indexes_uids {
-        let index = ctx.state().db.open_index(&index_uid);
-
-        match index {
-            Some(index) => {
-                let name = index
-                    .main
-                    .name(&reader)
-                    .map_err(ResponseError::internal)?
-                    .ok_or(ResponseError::internal("'name' not found"))?;
-                let created_at = index
-                    .main
-                    .created_at(&reader)
-                    .map_err(ResponseError::internal)?
-                    .ok_or(ResponseError::internal("'created_at' date not found"))?;
-                let updated_at = index
-                    .main
-                    .updated_at(&reader)
-                    .map_err(ResponseError::internal)?
-                    .ok_or(ResponseError::internal("'updated_at' date not found"))?;
-
-                let index_reponse = IndexResponse {
-                    name,
-                    uid: index_uid,
-                    created_at,
-                    updated_at,
-                };
-                response_body.push(index_reponse);
-            }
-            None => error!(
-                "Index {} is referenced in the indexes list but cannot be found",
-                index_uid
-            ),
-        }
-    }
+    let equals = |value: &Value, expected: &str| match value {
+        Value::String(s) => s == expected,
+        Value::Number(_) | Value::Bool(_) => value.to_string() == expected,
+        _ => false,
+    };
 
-    Ok(tide::response::json(response_body))
+    match (value, expected) {
+        (_, None) => true,
+        (Value::Array(values), Some(expected)) => values.iter().any(|v| equals(v, expected)),
+        (value, Some(expected)) => equals(value, expected),
+    }
 }
 
-#[derive(Debug, Serialize)]
-#[serde(rename_all = "camelCase")]
-struct IndexResponse {
-    name: String,
-    uid: String,
-    created_at: DateTime<Utc>,
-    updated_at: DateTime<Utc>,
+pub async fn list_indexes(ctx: Context<Data>) -> SResult<Response> {
+    ctx.is_allowed(IndexesRead)?;
+
+    let query: IndexesQuery = ctx.url_query().unwrap_or_default();
+    let offset = query.offset.unwrap_or(0);
+    let limit = query.limit.unwrap_or(usize::max_value());
+
+    let response_body: Vec<_> = ctx
+        .state()
+        .catalog
+        .indexes()
+        .into_iter()
+        .filter(|index| match &query.search {
+            Some(prefix) => name_or_uid_matches(index, prefix),
+            None => true,
+        })
+        .filter(|index| match &query.metadata {
+            Some(filter) => metadata_matches(index.metadata.as_ref(), filter),
+            None => true,
+        })
+        .skip(offset)
+        .take(limit)
+        .collect();
+
+    Ok(tide::response::json(response_body))
 }
 
 pub async fn get_index(ctx: Context<Data>) -> SResult<Response> {
@@ -108,10 +115,15 @@ pub async fn get_index(ctx: Context<Data>) -> SResult<Response> {
         .updated_at(&reader)
         .map_err(ResponseError::internal)?
         .ok_or(ResponseError::internal("'updated_at' date not found"))?;
+    let metadata = index
+        .main
+        .metadata(&reader)
+        .map_err(ResponseError::internal)?;
 
     let response_body = IndexResponse {
         name,
         uid,
+        metadata,
         created_at,
         updated_at,
     };
@@ -119,26 +131,6 @@ pub async fn get_index(ctx: Context<Data>) -> SResult<Response> {
     Ok(tide::response::json(response_body))
 }
 
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase", deny_unknown_fields)]
-struct IndexCreateRequest {
-    name: String,
-    uid: Option<String>,
-    schema: Option<SchemaBody>,
-}
-
-#[derive(Debug, Serialize)]
-#[serde(rename_all = "camelCase")]
-struct IndexCreateResponse {
-    name: String,
-    uid: String,
-    schema: Option<SchemaBody>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    update_id: Option<u64>,
-    created_at: DateTime<Utc>,
-    updated_at: DateTime<Utc>,
-}
-
 pub async fn create_index(mut ctx: Context<Data>) -> SResult<Response> {
     ctx.is_allowed(IndexesWrite)?;
 
@@ -147,6 +139,12 @@ pub async fn create_index(mut ctx: Context<Data>) -> SResult<Response> {
         .await
         .map_err(ResponseError::bad_request)?;
 
+    // the documents of a schema without indexed attributes could never be found
+    let schema: Option<Schema> = body.schema.clone().map(Into::into);
+    if let Some(schema) = &schema {
+        check_searchable(schema, None).map_err(ResponseError::bad_request)?;
+    }
+
     let db = &ctx.state().db;
 
     let uid = match body.uid {
@@ -180,7 +178,20 @@ pub async fn create_index(mut ctx: Context<Data>) -> SResult<Response> {
         .put_updated_at(&mut writer)
         .map_err(ResponseError::internal)?;
 
-    let schema: Option<Schema> = body.schema.clone().map(Into::into);
+    if let Some(metadata) = &body.metadata {
+        created_index
+            .main
+            .put_metadata(&mut writer, metadata)
+            .map_err(ResponseError::internal)?;
+    }
+
+    if let Some(discovery) = body.attributes_discovery {
+        created_index
+            .main
+            .put_attributes_discovery(&mut writer, discovery)
+            .map_err(ResponseError::internal)?;
+    }
+
     let mut response_update_id = None;
     if let Some(schema) = schema {
         let update_id = created_index
@@ -189,7 +200,13 @@ pub async fn create_index(mut ctx: Context<Data>) -> SResult<Response> {
         response_update_id = Some(update_id)
     }
 
+    ctx.audit_index(&mut writer, AuditAction::IndexCreation, &uid)?;
+
     writer.commit().map_err(ResponseError::internal)?;
+    ctx.state()
+        .catalog
+        .refresh(db, &uid)
+        .map_err(ResponseError::internal)?;
 
     let response_body = IndexCreateResponse {
         name: body.name,
@@ -205,19 +222,86 @@ pub async fn create_index(mut ctx: Context<Data>) -> SResult<Response> {
         .into_response())
 }
 
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase", deny_unknown_fields)]
-struct UpdateIndexRequest {
-    name: String,
-}
+/// Creates a new index with the settings of this one, its documents are not copied.
+pub async fn clone_index(mut ctx: Context<Data>) -> SResult<Response> {
+    ctx.is_allowed(IndexesWrite)?;
+
+    let body = ctx
+        .body_json::<IndexCloneRequest>()
+        .await
+        .map_err(ResponseError::bad_request)?;
+
+    let source_uid = ctx.url_param("index")?;
+    let db = &ctx.state().db;
+
+    let uid = match body.uid {
+        Some(uid) => uid,
+        None => loop {
+            let uid = generate_uid();
+            if db.open_index(&uid).is_none() {
+                break uid;
+            }
+        },
+    };
+
+    let cloned_index = match db.clone_index_settings(&source_uid, &uid) {
+        Ok(index) => index,
+        Err(meilidb_core::Error::IndexNotFound) => {
+            return Err(ResponseError::index_not_found(source_uid))
+        }
+        Err(e) => return Err(ResponseError::create_index(e)),
+    };
+
+    let env = &db.env;
+    let mut writer = env.write_txn().map_err(ResponseError::internal)?;
+
+    cloned_index
+        .main
+        .put_name(&mut writer, &body.name)
+        .map_err(ResponseError::internal)?;
+    cloned_index
+        .main
+        .put_created_at(&mut writer)
+        .map_err(ResponseError::internal)?;
+    cloned_index
+        .main
+        .put_updated_at(&mut writer)
+        .map_err(ResponseError::internal)?;
+
+    // the shape of the search responses is a setting of the http server
+    let format = ctx
+        .state()
+        .response_format(&writer, &source_uid)
+        .map_err(ResponseError::internal)?;
+    ctx.state()
+        .put_response_format(&mut writer, &uid, &format)
+        .map_err(ResponseError::internal)?;
+
+    let schema = cloned_index
+        .main
+        .schema(&writer)
+        .map_err(ResponseError::internal)?;
+
+    ctx.audit_index(&mut writer, AuditAction::IndexCreation, &uid)?;
+
+    writer.commit().map_err(ResponseError::internal)?;
+    ctx.state()
+        .catalog
+        .refresh(db, &uid)
+        .map_err(ResponseError::internal)?;
+
+    let response_body = IndexCreateResponse {
+        name: body.name,
+        uid,
+        schema: schema.map(SchemaBody::from),
+        update_id: None,
+        created_at: Utc::now(),
+        updated_at: Utc::now(),
+    };
 
-#[derive(Debug, Serialize)]
-#[serde(rename_all = "camelCase")]
-struct UpdateIndexResponse {
-    name: String,
-    uid: String,
-    created_at: DateTime<Utc>,
-    updated_at: DateTime<Utc>,
+    Ok(tide::response::json(response_body)
+        .with_status(StatusCode::CREATED)
+        .into_response())
 }
 
 pub async fn update_index(mut ctx: Context<Data>) -> SResult<Response> {
@@ -241,12 +325,25 @@ pub async fn update_index(mut ctx: Context<Data>) -> SResult<Response> {
         .put_name(&mut writer, &body.name)
         .map_err(ResponseError::internal)?;
 
+    if let Some(metadata) = &body.metadata {
+        index
+            .main
+            .put_metadata(&mut writer, metadata)
+            .map_err(ResponseError::internal)?;
+    }
+
     index
         .main
         .put_updated_at(&mut writer)
         .map_err(ResponseError::internal)?;
 
+    ctx.audit(&mut writer, AuditAction::IndexUpdate)?;
+
     writer.commit().map_err(ResponseError::internal)?;
+    ctx.state()
+        .catalog
+        .refresh(db, &index_uid)
+        .map_err(ResponseError::internal)?;
     let reader = env.read_txn().map_err(ResponseError::internal)?;
 
     let created_at = index
@@ -259,10 +356,15 @@ pub async fn update_index(mut ctx: Context<Data>) -> SResult<Response> {
         .updated_at(&reader)
         .map_err(ResponseError::internal)?
         .ok_or(ResponseError::internal("'updated_at' date not found"))?;
+    let metadata = index
+        .main
+        .metadata(&reader)
+        .map_err(ResponseError::internal)?;
 
     let response_body = UpdateIndexResponse {
         name: body.name,
         uid: index_uid,
+        metadata,
         created_at,
         updated_at,
     };
@@ -333,10 +435,94 @@ pub async fn update_schema(mut ctx: Context<Data>) -> SResult<Response> {
         .open_index(&index_uid)
         .ok_or(ResponseError::index_not_found(index_uid))?;
 
+    if let Some(response) = ctx.replayed_update(&writer, &index)? {
+        return Ok(response);
+    }
+
+    let searchable_attributes = index
+        .main
+        .searchable_attributes(&writer)
+        .map_err(ResponseError::internal)?;
+    check_searchable(&schema, searchable_attributes.as_ref().map(Vec::as_slice))
+        .map_err(ResponseError::bad_request)?;
+
     let update_id = index
         .schema_update(&mut writer, schema.clone())
         .map_err(ResponseError::internal)?;
 
+    ctx.register_update(&mut writer, &index, update_id)?;
+    ctx.audit(&mut writer, AuditAction::SchemaUpdate)?;
+
+    writer.commit().map_err(ResponseError::internal)?;
+
+    let response_body = IndexUpdateResponse { update_id };
+    Ok(tide::response::json(response_body)
+        .with_status(StatusCode::ACCEPTED)
+        .into_response())
+}
+
+/// Rewrites the documents of the index for a new schema, unlike the schema updates
+/// the attributes can be renamed, removed, reordered, split and merged.
+pub async fn migrate_schema(mut ctx: Context<Data>) -> SResult<Response> {
+    ctx.is_allowed(IndexesWrite)?;
+
+    let index_uid = ctx.url_param("index")?;
+
+    let body: MigrationBody = ctx.body_json().await.map_err(ResponseError::bad_request)?;
+    let schema: Schema = body.schema.into();
+    let mapping = body.mapping;
+
+    let mapping = meilidb_core::FieldMapping {
+        renames: mapping.renames,
+        splits: mapping
+            .splits
+            .into_iter()
+            .map(|(old, split)| {
+                let split = meilidb_core::FieldSplit {
+                    separator: split.separator,
+                    into: split.into,
+                };
+                (old, split)
+            })
+            .collect(),
+        merges: mapping
+            .merges
+            .into_iter()
+            .map(|(new, merge)| {
+                let merge = meilidb_core::FieldMerge {
+                    separator: merge.separator,
+                    from: merge.from,
+                };
+                (new, merge)
+            })
+            .collect(),
+        defaults: mapping.defaults,
+    };
+
+    // the unknown attributes are refused before the migration is enqueued
+    mapping
+        .validate(&schema)
+        .map_err(ResponseError::bad_request)?;
+
+    let db = &ctx.state().db;
+    let env = &db.env;
+    let mut writer = env.write_txn().map_err(ResponseError::internal)?;
+
+    let index = db
+        .open_index(&index_uid)
+        .ok_or(ResponseError::index_not_found(index_uid))?;
+
+    if let Some(response) = ctx.replayed_update(&writer, &index)? {
+        return Ok(response);
+    }
+
+    let update_id = index
+        .migrate_to(&mut writer, schema, mapping)
+        .map_err(ResponseError::internal)?;
+
+    ctx.register_update(&mut writer, &index, update_id)?;
+    ctx.audit(&mut writer, AuditAction::SchemaUpdate)?;
+
     writer.commit().map_err(ResponseError::internal)?;
 
     let response_body = IndexUpdateResponse { update_id };
@@ -372,6 +558,35 @@ pub async fn get_update_status(ctx: Context<Data>) -> SResult<Response> {
     Ok(response)
 }
 
+/// Applies again a processed update on a scratch index and returns the result
+/// it would have now, to debug the updates without modifying the index.
+pub async fn replay_update(ctx: Context<Data>) -> SResult<Response> {
+    ctx.is_allowed(Admin)?;
+
+    let env = &ctx.state().db.env;
+    let reader = env.read_txn().map_err(ResponseError::internal)?;
+
+    let update_id = ctx
+        .param::<u64>("update_id")
+        .map_err(|e| ResponseError::bad_parameter("update_id", e))?;
+
+    let index = ctx.index()?;
+    let result = index
+        .replay_update(&reader, update_id)
+        .map_err(ResponseError::internal)?;
+
+    let response = match result {
+        Some(result) => tide::response::json(result)
+            .with_status(StatusCode::OK)
+            .into_response(),
+        None => tide::response::json(json!({ "message": "update cannot be replayed" }))
+            .with_status(StatusCode::NOT_FOUND)
+            .into_response(),
+    };
+
+    Ok(response)
+}
+
 pub async fn get_all_updates_status(ctx: Context<Data>) -> SResult<Response> {
     ctx.is_allowed(IndexesRead)?;
 
@@ -394,11 +609,41 @@ pub async fn delete_index(ctx: Context<Data>) -> SResult<StatusCode> {
     ctx.is_allowed(IndexesWrite)?;
     let index_uid = ctx.url_param("index")?;
 
+    // the sample index of an index does not outlive it
+    let env = &ctx.state().db.env;
+    let mut writer = env.write_txn().map_err(ResponseError::internal)?;
+    let sampled = ctx
+        .state()
+        .delete_sample(&mut writer, &index_uid)
+        .map_err(ResponseError::internal)?;
+    ctx.state()
+        .delete_response_format(&mut writer, &index_uid)
+        .map_err(ResponseError::internal)?;
+    ctx.state()
+        .delete_query_quota(&mut writer, &index_uid)
+        .map_err(ResponseError::internal)?;
+    ctx.state()
+        .delete_index_usage(&mut writer, &index_uid)
+        .map_err(ResponseError::internal)?;
+    ctx.audit(&mut writer, AuditAction::IndexDeletion)?;
+    writer.commit().map_err(ResponseError::internal)?;
+    ctx.state().circuit_breaker.forget(&index_uid);
+
+    if sampled {
+        let sample_uid = sample_index_uid(&index_uid);
+        ctx.state()
+            .db
+            .delete_index(&sample_uid)
+            .map_err(ResponseError::internal)?;
+        ctx.state().catalog.remove(&sample_uid);
+    }
+
     let found = ctx
         .state()
         .db
         .delete_index(&index_uid)
         .map_err(ResponseError::internal)?;
+    ctx.state().catalog.remove(&index_uid);
 
     if found {
         Ok(StatusCode::NO_CONTENT)
@@ -437,5 +682,52 @@ pub fn index_update_callback(index_uid: &str, data: &Data, status: ProcessedUpda
         if let Err(e) = writer.commit() {
             error!("Impossible to get write_txn; {}", e);
         }
+
+        if let Err(e) = data.catalog.refresh(&data.db, index_uid) {
+            error!("Impossible to refresh the indexes catalog; {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn name_or_uid_filters() {
+        let index = IndexResponse {
+            name: "Movies".to_string(),
+            uid: "catalog-movies".to_string(),
+            metadata: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+
+        assert!(name_or_uid_matches(&index, "mov"));
+        assert!(name_or_uid_matches(&index, "Catalog"));
+        assert!(name_or_uid_matches(&index, ""));
+
+        assert!(!name_or_uid_matches(&index, "movies-"));
+        assert!(!name_or_uid_matches(&index, "ovies"));
+    }
+
+    #[test]
+    fn metadata_filters() {
+        let metadata = json!({
+            "owner": "catalog",
+            "tags": ["films", "cinema"],
+            "public": true,
+        });
+        let metadata = metadata.as_object();
+
+        assert!(metadata_matches(metadata, "owner:catalog"));
+        assert!(metadata_matches(metadata, "tags:cinema"));
+        assert!(metadata_matches(metadata, "public:true"));
+        assert!(metadata_matches(metadata, "owner"));
+
+        assert!(!metadata_matches(metadata, "owner:search"));
+        assert!(!metadata_matches(metadata, "tags:series"));
+        assert!(!metadata_matches(metadata, "description"));
+        assert!(!metadata_matches(None, "owner:catalog"));
     }
 }
@@ -7,12 +7,30 @@ use serde::{Deserialize, Serialize};
 use tide::response::IntoResponse;
 use tide::{Context, Response};
 
+use crate::audit::AuditAction;
 use crate::error::{ResponseError, SResult};
 use crate::helpers::tide::ContextExt;
 use crate::models::token::ACL::*;
 use crate::models::token::*;
 use crate::Data;
 
+fn token_view(
+    reader: &heed::RoTxn,
+    common_store: heed::PolyDatabase,
+    token: Token,
+) -> SResult<TokenView> {
+    let attributes = common_store
+        .get::<Str, SerdeBincode<Vec<Wildcard>>>(reader, &token_attributes_key(&token.key))
+        .map_err(ResponseError::internal)?;
+
+    let id = key_id(&token.key);
+    Ok(TokenView {
+        id,
+        token,
+        attributes,
+    })
+}
+
 fn generate_api_key() -> String {
     let mut rng = rand::thread_rng();
     let sample = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
@@ -31,7 +49,7 @@ pub async fn list(ctx: Context<Data>) -> SResult<Response> {
 
     let common_store = db.common_store();
 
-    let mut response: Vec<Token> = Vec::new();
+    let mut response: Vec<TokenView> = Vec::new();
 
     let iter = common_store
         .prefix_iter::<Str, SerdeBincode<Token>>(&reader, TOKEN_PREFIX_KEY)
@@ -39,7 +57,7 @@ pub async fn list(ctx: Context<Data>) -> SResult<Response> {
 
     for result in iter {
         let (_, token) = result.map_err(ResponseError::internal)?;
-        response.push(token);
+        response.push(token_view(&reader, common_store, token)?);
     }
 
     Ok(tide::response::json(response))
@@ -64,7 +82,8 @@ pub async fn get(ctx: Context<Data>) -> SResult<Response> {
             token_key
         )))?;
 
-    Ok(tide::response::json(token_config))
+    let response = token_view(&reader, db.common_store(), token_config)?;
+    Ok(tide::response::json(response))
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -73,6 +92,8 @@ pub struct CreatedRequest {
     description: String,
     acl: Vec<ACL>,
     indexes: Vec<Wildcard>,
+    #[serde(default)]
+    attributes: Option<Vec<Wildcard>>,
     #[serde(with = "ts_seconds")]
     expires_at: DateTime<Utc>,
 }
@@ -104,9 +125,24 @@ pub async fn create(mut ctx: Context<Data>) -> SResult<Response> {
         .put::<Str, SerdeBincode<Token>>(&mut writer, &token_key, &token_definition)
         .map_err(ResponseError::internal)?;
 
+    if let Some(attributes) = &data.attributes {
+        let attributes_key = token_attributes_key(&token_definition.key);
+        db.common_store()
+            .put::<Str, SerdeBincode<Vec<Wildcard>>>(&mut writer, &attributes_key, attributes)
+            .map_err(ResponseError::internal)?;
+    }
+
+    ctx.audit(&mut writer, AuditAction::KeyCreation)?;
+
     writer.commit().map_err(ResponseError::internal)?;
 
-    Ok(tide::response::json(token_definition)
+    let response = TokenView {
+        id: key_id(&token_definition.key),
+        token: token_definition,
+        attributes: data.attributes,
+    };
+
+    Ok(tide::response::json(response)
         .with_status(StatusCode::CREATED)
         .into_response())
 }
@@ -117,6 +153,7 @@ pub struct UpdatedRequest {
     description: Option<String>,
     acl: Option<Vec<ACL>>,
     indexes: Option<Vec<Wildcard>>,
+    attributes: Option<Vec<Wildcard>>,
     expires_at: Option<DateTime<Utc>>,
     revoked: Option<bool>,
 }
@@ -156,6 +193,16 @@ pub async fn update(mut ctx: Context<Data>) -> SResult<Response> {
         token_config.indexes = indexes;
     }
 
+    if let Some(attributes) = &data.attributes {
+        common_store
+            .put::<Str, SerdeBincode<Vec<Wildcard>>>(
+                &mut writer,
+                &token_attributes_key(&request_key),
+                attributes,
+            )
+            .map_err(ResponseError::internal)?;
+    }
+
     if let Some(expires_at) = data.expires_at {
         token_config.expires_at = expires_at;
     }
@@ -170,9 +217,13 @@ pub async fn update(mut ctx: Context<Data>) -> SResult<Response> {
         .put::<Str, SerdeBincode<Token>>(&mut writer, &token_key, &token_config)
         .map_err(ResponseError::internal)?;
 
+    let response = token_view(&writer, common_store, token_config)?;
+
+    ctx.audit(&mut writer, AuditAction::KeyUpdate)?;
+
     writer.commit().map_err(ResponseError::internal)?;
 
-    Ok(tide::response::json(token_config)
+    Ok(tide::response::json(response)
         .with_status(StatusCode::OK)
         .into_response())
 }
@@ -193,6 +244,12 @@ pub async fn delete(ctx: Context<Data>) -> SResult<StatusCode> {
         .delete::<Str>(&mut writer, &token_key)
         .map_err(ResponseError::internal)?;
 
+    common_store
+        .delete::<Str>(&mut writer, &token_attributes_key(&request_key))
+        .map_err(ResponseError::internal)?;
+
+    ctx.audit(&mut writer, AuditAction::KeyDeletion)?;
+
     writer.commit().map_err(ResponseError::internal)?;
 
     Ok(StatusCode::NO_CONTENT)
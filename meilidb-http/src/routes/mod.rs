@@ -1,14 +1,22 @@
 use crate::data::Data;
 
+pub mod audit;
+pub mod decompounding;
+pub mod diagnostics;
 pub mod document;
 pub mod health;
+pub mod import;
 pub mod index;
 pub mod key;
+pub mod operations;
+pub mod sample;
 pub mod search;
 pub mod setting;
+pub mod snapshot;
 pub mod stats;
 pub mod stop_words;
 pub mod synonym;
+pub mod usage;
 
 pub fn load_routes(app: &mut tide::App<Data>) {
     app.at("").nest(|router| {
@@ -27,6 +35,7 @@ pub fn load_routes(app: &mut tide::App<Data>) {
                     router.at("/").get(index::get_all_updates_status);
 
                     router.at("/:update_id").get(index::get_update_status);
+                    router.at("/:update_id/replay").post(index::replay_update);
                 });
 
                 router
@@ -35,17 +44,21 @@ pub fn load_routes(app: &mut tide::App<Data>) {
                     .put(index::update_index)
                     .delete(index::delete_index);
 
+                router.at("/clone").post(index::clone_index);
+
                 router
                     .at("/schema")
                     .get(index::get_index_schema)
                     .put(index::update_schema);
+                router.at("/schema/migrate").post(index::migrate_schema);
 
                 router.at("/documents").nest(|router| {
                     router
                         .at("/")
                         .get(document::get_all_documents)
                         .post(document::add_or_replace_multiple_documents)
-                        .put(document::add_or_update_multiple_documents)
+                        .put(document::add_or_replace_multiple_documents)
+                        .patch(document::add_or_update_multiple_documents)
                         .delete(document::clear_all_documents);
 
                     router.at("/:identifier").nest(|router| {
@@ -58,6 +71,12 @@ pub fn load_routes(app: &mut tide::App<Data>) {
                     router
                         .at("/delete")
                         .post(document::delete_multiple_documents);
+
+                    router
+                        .at("/delete-by-filter")
+                        .post(document::delete_documents_by_filter);
+
+                    router.at("/fetch").post(document::fetch_documents);
                 });
 
                 router.at("/synonyms").nest(|router| {
@@ -84,10 +103,138 @@ pub fn load_routes(app: &mut tide::App<Data>) {
                         .delete(stop_words::delete);
                 });
 
+                router
+                    .at("/decompounding")
+                    .get(decompounding::list)
+                    .post(decompounding::update);
+
                 router
                     .at("/settings")
                     .get(setting::get)
-                    .post(setting::update);
+                    .post(setting::update)
+                    .patch(setting::patch);
+
+                router.at("/settings/all").get(setting::get_all);
+
+                router.at("/settings/diff").post(setting::diff);
+
+                router
+                    .at("/settings/limits")
+                    .get(setting::get_limits)
+                    .post(setting::update_limits);
+
+                router
+                    .at("/settings/numbers")
+                    .get(setting::get_numbers)
+                    .post(setting::update_numbers);
+
+                router
+                    .at("/settings/prefix-search")
+                    .get(setting::get_prefix_search)
+                    .post(setting::update_prefix_search);
+
+                router
+                    .at("/settings/document-id-charset")
+                    .get(setting::get_document_id_charset)
+                    .post(setting::update_document_id_charset);
+
+                router
+                    .at("/settings/highlighting")
+                    .get(setting::get_highlighting)
+                    .post(setting::update_highlighting);
+
+                router
+                    .at("/settings/facets-normalization")
+                    .get(setting::get_facets_normalization)
+                    .post(setting::update_facets_normalization);
+
+                router
+                    .at("/settings/flattening")
+                    .get(setting::get_flattening)
+                    .post(setting::update_flattening);
+
+                router
+                    .at("/settings/pagination")
+                    .get(setting::get_pagination)
+                    .post(setting::update_pagination);
+
+                router
+                    .at("/settings/query-limits")
+                    .get(setting::get_query_limits)
+                    .post(setting::update_query_limits);
+
+                router
+                    .at("/settings/query-quota")
+                    .get(setting::get_query_quota)
+                    .post(setting::update_query_quota);
+
+                router
+                    .at("/settings/typos")
+                    .get(setting::get_typos)
+                    .post(setting::update_typos);
+
+                router
+                    .at("/settings/criteria")
+                    .get(setting::get_criteria)
+                    .post(setting::update_criteria);
+
+                router
+                    .at("/settings/ranking-profiles")
+                    .get(setting::get_ranking_profiles)
+                    .post(setting::update_ranking_profiles);
+
+                router
+                    .at("/settings/cold-tier")
+                    .get(setting::get_cold_tier)
+                    .post(setting::update_cold_tier);
+
+                router
+                    .at("/settings/frozen-words")
+                    .get(setting::get_frozen_words)
+                    .post(setting::update_frozen_words);
+
+                router.at("/usage").get(usage::get_index_usage);
+
+                router
+                    .at("/settings/attributes-types")
+                    .get(setting::get_attributes_types)
+                    .post(setting::update_attributes_types);
+
+                router
+                    .at("/settings/words-limits")
+                    .get(setting::get_words_limits)
+                    .post(setting::update_words_limits);
+
+                router
+                    .at("/settings/token-filters")
+                    .get(setting::get_token_filters)
+                    .post(setting::update_token_filters);
+
+                router
+                    .at("/settings/searchable-attributes")
+                    .get(setting::get_searchable_attributes)
+                    .post(setting::update_searchable_attributes);
+
+                router
+                    .at("/settings/displayed-attributes")
+                    .get(setting::get_displayed_attributes)
+                    .post(setting::update_displayed_attributes);
+
+                router
+                    .at("/settings/response-format")
+                    .get(setting::get_response_format)
+                    .post(setting::update_response_format);
+
+                router
+                    .at("/sample")
+                    .get(sample::get)
+                    .post(sample::create)
+                    .delete(sample::delete);
+
+                router
+                    .at("/import")
+                    .get(import::get_import_status)
+                    .post(import::import);
             });
         });
 
@@ -111,8 +258,20 @@ pub fn load_routes(app: &mut tide::App<Data>) {
             .put(health::change_healthyness)
             .delete(health::set_unhealthy);
 
+        router.at("/snapshots").get(snapshot::list);
+        router.at("/audit").get(audit::list);
+        router
+            .at("/operations")
+            .get(operations::list)
+            .delete(operations::truncate);
+        router.at("/diagnostics").post(diagnostics::dump);
+        router.at("/usage").get(usage::get_usage);
+
         router.at("/stats").get(stats::get_stats);
         router.at("/stats/:index").get(stats::index_stat);
+        router
+            .at("/stats/:index/postings")
+            .get(stats::postings_stats);
         router.at("/version").get(stats::get_version);
         router.at("/sys-info").get(stats::get_sys_info);
         router
@@ -0,0 +1,60 @@
+use serde::Deserialize;
+use serde_json::json;
+use tide::querystring::ContextExt as QSContextExt;
+use tide::{Context, Response};
+
+use crate::error::{ResponseError, SResult};
+use crate::helpers::tide::ContextExt;
+use crate::models::token::ACL::*;
+use crate::Data;
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct OperationsQuery {
+    since: Option<u64>,
+    limit: Option<usize>,
+}
+
+pub async fn list(ctx: Context<Data>) -> SResult<Response> {
+    ctx.is_allowed(Admin)?;
+
+    // a consumer given an invalid sequence must not restart from the first operation
+    let query: OperationsQuery = match ctx.uri().query() {
+        Some(_) => ctx
+            .url_query()
+            .map_err(|_| ResponseError::bad_request("invalid since or limit parameter"))?,
+        None => OperationsQuery::default(),
+    };
+    let since = query.since.unwrap_or(0);
+    let limit = query.limit.unwrap_or(100);
+
+    let operations = ctx
+        .state()
+        .db
+        .operations_since(since, limit)
+        .map_err(ResponseError::internal)?;
+
+    Ok(tide::response::json(operations))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct TruncateQuery {
+    up_to: u64,
+}
+
+pub async fn truncate(ctx: Context<Data>) -> SResult<Response> {
+    ctx.is_allowed(Admin)?;
+
+    let query: TruncateQuery = ctx
+        .url_query()
+        .map_err(|_| ResponseError::bad_request("the upTo parameter is missing"))?;
+
+    let removed = ctx
+        .state()
+        .db
+        .truncate_operations(query.up_to)
+        .map_err(ResponseError::internal)?;
+
+    Ok(tide::response::json(json!({ "removed": removed })))
+}
@@ -0,0 +1,227 @@
+use http::StatusCode;
+use indexmap::IndexMap;
+use meilidb_core::serde::DocumentKey;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tide::response::IntoResponse;
+use tide::{Context, Response};
+
+use crate::error::{ResponseError, SResult};
+use crate::helpers::tide::ContextExt;
+use crate::models::token::ACL::*;
+use crate::sample::{copy_index_settings, sample_index_uid, Sample};
+use crate::Data;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct SampleRequest {
+    size: usize,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SampleResponse {
+    sample_uid: String,
+    size: usize,
+    number_of_documents: usize,
+    seen_documents: u64,
+}
+
+impl SampleResponse {
+    fn new(index_uid: &str, sample: &Sample) -> SampleResponse {
+        SampleResponse {
+            sample_uid: sample_index_uid(index_uid),
+            size: sample.size,
+            number_of_documents: sample.slots.len(),
+            seen_documents: sample.seen,
+        }
+    }
+}
+
+pub async fn get(ctx: Context<Data>) -> SResult<Response> {
+    ctx.is_allowed(IndexesRead)?;
+
+    let index_uid = ctx.url_param("index")?;
+
+    let env = &ctx.state().db.env;
+    let reader = env.read_txn().map_err(ResponseError::internal)?;
+
+    let sample = ctx
+        .state()
+        .sample(&reader, &index_uid)
+        .map_err(ResponseError::internal)?
+        .ok_or_else(|| ResponseError::not_found("no sample for this index"))?;
+
+    Ok(tide::response::json(SampleResponse::new(
+        &index_uid, &sample,
+    )))
+}
+
+/// Creates the sample index of an index, a shadow index holding a bounded
+/// random subset of its documents along with a copy of its settings.
+///
+/// The sample index is a regular index, settings changes can be tried
+/// on it and previewed with searches before being applied to the full index.
+pub async fn create(mut ctx: Context<Data>) -> SResult<Response> {
+    ctx.is_allowed(IndexesWrite)?;
+
+    let body = ctx
+        .body_json::<SampleRequest>()
+        .await
+        .map_err(ResponseError::bad_request)?;
+
+    if body.size == 0 {
+        return Err(ResponseError::bad_request(
+            "the sample size must be greater than zero",
+        ));
+    }
+
+    let index_uid = ctx.url_param("index")?;
+    let index = ctx.index()?;
+    let db = &ctx.state().db;
+
+    let env = &db.env;
+    let reader = env.read_txn().map_err(ResponseError::internal)?;
+
+    if ctx
+        .state()
+        .sample(&reader, &index_uid)
+        .map_err(ResponseError::internal)?
+        .is_some()
+    {
+        return Err(ResponseError::bad_request(
+            "this index already has a sample",
+        ));
+    }
+
+    let schema = index
+        .main
+        .schema(&reader)
+        .map_err(ResponseError::internal)?
+        .ok_or_else(|| ResponseError::bad_request("the index has no schema"))?;
+
+    // sample the documents already indexed, following Algorithm R
+    let mut sample = Sample::new(body.size);
+    let mut documents_ids = Vec::with_capacity(body.size);
+    let mut rng = rand::thread_rng();
+
+    let all_documents_ids = index
+        .documents_fields_counts
+        .documents_ids(&reader)
+        .map_err(ResponseError::internal)?;
+
+    for (seen, result) in all_documents_ids.enumerate() {
+        let document_id = result.map_err(ResponseError::internal)?;
+        if documents_ids.len() < body.size {
+            documents_ids.push(document_id);
+        } else {
+            let position = rng.gen_range(0, seen + 1);
+            if position < body.size {
+                documents_ids[position] = document_id;
+            }
+        }
+        sample.seen += 1;
+    }
+
+    let identifier = schema.identifier_name();
+    let mut documents = Vec::with_capacity(documents_ids.len());
+    for document_id in documents_ids {
+        let document = index
+            .document::<IndexMap<String, Value>>(&reader, None, document_id)
+            .map_err(ResponseError::internal)?;
+
+        if let Some(document) = document {
+            let key = document
+                .get(identifier)
+                .and_then(|key| DocumentKey::from_value(key).ok());
+
+            if let Some(key) = key {
+                sample.slots.push(key.to_string());
+                documents.push(document);
+            }
+        }
+    }
+
+    reader.abort();
+
+    let sample_uid = sample_index_uid(&index_uid);
+    let sample_index = db
+        .create_index(&sample_uid)
+        .map_err(ResponseError::create_index)?;
+
+    let mut writer = env.write_txn().map_err(ResponseError::internal)?;
+
+    let name = index
+        .main
+        .name(&writer)
+        .map_err(ResponseError::internal)?
+        .unwrap_or_else(|| index_uid.clone());
+
+    sample_index
+        .main
+        .put_name(&mut writer, &format!("{} (sample)", name))
+        .map_err(ResponseError::internal)?;
+    sample_index
+        .main
+        .put_created_at(&mut writer)
+        .map_err(ResponseError::internal)?;
+    sample_index
+        .main
+        .put_updated_at(&mut writer)
+        .map_err(ResponseError::internal)?;
+
+    copy_index_settings(&mut writer, &index, &sample_index).map_err(ResponseError::internal)?;
+
+    if !documents.is_empty() {
+        let mut documents_addition = sample_index.documents_addition();
+        for document in documents {
+            documents_addition.update_document(document);
+        }
+        documents_addition
+            .finalize(&mut writer)
+            .map_err(ResponseError::internal)?;
+    }
+
+    ctx.state()
+        .put_sample(&mut writer, &index_uid, &sample)
+        .map_err(ResponseError::internal)?;
+
+    writer.commit().map_err(ResponseError::internal)?;
+    ctx.state()
+        .catalog
+        .refresh(db, &sample_uid)
+        .map_err(ResponseError::internal)?;
+
+    let response_body = SampleResponse::new(&index_uid, &sample);
+    Ok(tide::response::json(response_body)
+        .with_status(StatusCode::CREATED)
+        .into_response())
+}
+
+pub async fn delete(ctx: Context<Data>) -> SResult<StatusCode> {
+    ctx.is_allowed(IndexesWrite)?;
+
+    let index_uid = ctx.url_param("index")?;
+
+    let env = &ctx.state().db.env;
+    let mut writer = env.write_txn().map_err(ResponseError::internal)?;
+    let found = ctx
+        .state()
+        .delete_sample(&mut writer, &index_uid)
+        .map_err(ResponseError::internal)?;
+    writer.commit().map_err(ResponseError::internal)?;
+
+    if !found {
+        return Ok(StatusCode::NOT_FOUND);
+    }
+
+    let sample_uid = sample_index_uid(&index_uid);
+    ctx.state()
+        .db
+        .delete_index(&sample_uid)
+        .map_err(ResponseError::internal)?;
+    ctx.state().catalog.remove(&sample_uid);
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -1,34 +1,26 @@
-use std::collections::HashMap;
-use std::collections::HashSet;
-use std::time::Duration;
+use std::collections::{HashMap, HashSet};
+use std::sync::{mpsc, Arc};
+use std::time::{Duration, Instant};
 
+use http::header::HeaderValue;
 use meilidb_core::Index;
-use rayon::iter::{IntoParallelIterator, ParallelIterator};
-use serde::{Deserialize, Serialize};
+use meilidb_dto::{
+    ExcludedIndex, ExclusionReason, ResponseFormat, SearchHit, SearchMultiBody,
+    SearchMultiBodyResponse, SearchQuery, SearchResult,
+};
+use serde_json::json;
 use tide::querystring::ContextExt as QSContextExt;
 use tide::{Context, Response};
 
 use crate::error::{ResponseError, SResult};
-use crate::helpers::meilidb::{Error, IndexSearchExt, SearchHit};
+use crate::helpers::json_stream::{json_stream, JsonChunks};
+use crate::helpers::meilidb::{duration_ms, Error, IndexSearchExt};
 use crate::helpers::tide::ContextExt;
+use crate::models::token::Wildcard;
+use crate::response_format::format_search_result;
+use crate::usage::QUOTA_WARNING_HEADER;
 use crate::Data;
 
-#[derive(Deserialize)]
-#[serde(rename_all = "camelCase", deny_unknown_fields)]
-struct SearchQuery {
-    q: String,
-    offset: Option<usize>,
-    limit: Option<usize>,
-    attributes_to_retrieve: Option<String>,
-    attributes_to_search_in: Option<String>,
-    attributes_to_crop: Option<String>,
-    crop_length: Option<usize>,
-    attributes_to_highlight: Option<String>,
-    filters: Option<String>,
-    timeout_ms: Option<u64>,
-    matches: Option<bool>,
-}
-
 pub async fn search_with_url_query(ctx: Context<Data>) -> SResult<Response> {
     // ctx.is_allowed(DocumentsRead)?;
 
@@ -48,6 +40,10 @@ pub async fn search_with_url_query(ctx: Context<Data>) -> SResult<Response> {
 
     let mut search_builder = index.new_search(query.q.clone());
 
+    if let Some(allowed_attributes) = ctx.allowed_attributes()? {
+        search_builder.allowed_attributes(allowed_attributes);
+    }
+
     if let Some(offset) = query.offset {
         search_builder.offset(offset);
     }
@@ -65,6 +61,13 @@ pub async fn search_with_url_query(ctx: Context<Data>) -> SResult<Response> {
             search_builder.add_attribute_to_search_in(attr.to_string());
         }
     }
+    if let Some(restrict_searchable_attributes) = query.restrict_searchable_attributes {
+        let attributes = restrict_searchable_attributes
+            .split(',')
+            .map(|attr| attr.to_string())
+            .collect();
+        search_builder.restrict_searchable_attributes(attributes);
+    }
     if let Some(attributes_to_crop) = query.attributes_to_crop {
         let crop_length = query.crop_length.unwrap_or(200);
         if attributes_to_crop == "*" {
@@ -95,53 +98,141 @@ pub async fn search_with_url_query(ctx: Context<Data>) -> SResult<Response> {
         search_builder.attributes_to_highlight(attributes_to_highlight);
     }
 
+    if let Some(pre_tag) = query.highlight_pre_tag {
+        search_builder.highlight_pre_tag(pre_tag);
+    }
+    if let Some(post_tag) = query.highlight_post_tag {
+        search_builder.highlight_post_tag(post_tag);
+    }
+
     if let Some(filters) = query.filters {
         search_builder.filters(filters);
     }
 
+    if let Some(required_attribute) = query.required_attribute {
+        search_builder.required_attribute(required_attribute);
+    }
+
+    if let Some(documents_ids) = query.documents_ids {
+        let documents_ids = documents_ids.split(',').map(ToString::to_string).collect();
+        search_builder.documents_ids(documents_ids);
+    }
+
+    if let Some(around_lat_lng) = query.around_lat_lng {
+        search_builder.around(around_lat_lng, query.around_radius);
+    }
+
     if let Some(timeout_ms) = query.timeout_ms {
         search_builder.timeout(Duration::from_millis(timeout_ms));
     }
 
+    if let Some(bytes) = ctx.state().query_memory_limit {
+        search_builder.memory_limit(bytes);
+    }
+
     if let Some(matches) = query.matches {
         if matches {
             search_builder.get_matches();
         }
     }
 
-    let response = match search_builder.search(&reader) {
+    if let Some(patterns) = query.patterns {
+        if patterns {
+            search_builder.patterns();
+        }
+    }
+    if let Some(prefix) = query.prefix {
+        search_builder.prefix(prefix);
+    }
+
+    if query.debug == Some(true) {
+        search_builder.debug();
+    }
+
+    if query.show_buckets == Some(true) {
+        search_builder.buckets();
+    }
+
+    if let Some(facets) = query.facets {
+        let facets = facets.split(',').map(ToString::to_string).collect();
+        search_builder.facets(facets);
+    }
+
+    if let Some(ranking_profile) = query.ranking_profile {
+        search_builder.ranking_profile(ranking_profile);
+    }
+
+    if let Some(escape_html) = query.escape_html {
+        search_builder.escape_html(escape_html);
+    }
+
+    match (query.diversity_attribute, query.diversity_strength) {
+        (Some(diversity_attribute), strength) => {
+            let strength = strength.unwrap_or(3);
+            search_builder.diversity(diversity_attribute, strength);
+        }
+        (None, Some(_)) => {
+            return Err(ResponseError::bad_request(
+                "diversityStrength requires a diversityAttribute",
+            ))
+        }
+        (None, None) => (),
+    }
+
+    let index_uid = ctx.url_param("index")?;
+    let trace = ctx.state().queries.start(&index_uid, &query.q);
+
+    let mut response = match search_builder.search(&reader) {
         Ok(response) => response,
         Err(Error::Internal(message)) => return Err(ResponseError::Internal(message)),
         Err(others) => return Err(ResponseError::bad_request(others)),
     };
+    drop(trace);
+
+    let acting_key = ctx.acting_key();
+    let quota_warning = ctx
+        .state()
+        .record_search(&reader, &index_uid, acting_key.as_ref().map(String::as_str))
+        .map_err(ResponseError::internal)?;
+
+    let index_format = ctx
+        .state()
+        .response_format(&reader, &index_uid)
+        .map_err(ResponseError::internal)?;
+
+    let request_format = ResponseFormat {
+        fields_order: query
+            .fields_order
+            .map(|fields| fields.split(',').map(ToString::to_string).collect()),
+        hits_key: query.hits_key,
+        show_processing_time: query.show_processing_time,
+        show_query: query.show_query,
+    };
 
-    Ok(tide::response::json(response))
-}
+    let format = index_format.overridden_by(request_format);
+
+    let breakdown = response.processing_time_breakdown.take();
+    let start = Instant::now();
+    let mut body = format_search_result(response, &format);
+
+    if let Some(mut breakdown) = breakdown {
+        breakdown.serialization_ms = duration_ms(start.elapsed());
+        body["processingTimeBreakdown"] = json!(breakdown);
+    }
 
-#[derive(Clone, Deserialize)]
-#[serde(rename_all = "camelCase", deny_unknown_fields)]
-struct SearchMultiBody {
-    indexes: HashSet<String>,
-    query: String,
-    offset: Option<usize>,
-    limit: Option<usize>,
-    attributes_to_retrieve: Option<HashSet<String>>,
-    attributes_to_search_in: Option<HashSet<String>>,
-    attributes_to_crop: Option<HashMap<String, usize>>,
-    attributes_to_highlight: Option<HashSet<String>>,
-    filters: Option<String>,
-    timeout_ms: Option<u64>,
-    matches: Option<bool>,
+    let mut response = json_stream(JsonChunks::value(body));
+    if let Some(warning) = quota_warning {
+        add_quota_warning(&mut response, &warning);
+    }
+
+    Ok(response)
 }
 
-#[derive(Debug, Clone, Serialize)]
-#[serde(rename_all = "camelCase")]
-struct SearchMultiBodyResponse {
-    hits: HashMap<String, Vec<SearchHit>>,
-    offset: usize,
-    hits_per_page: usize,
-    processing_time_ms: usize,
-    query: String,
+/// Annotates the response of a search made past the quota of the index, it is still served.
+fn add_quota_warning(response: &mut Response, warning: &str) {
+    if let Ok(value) = HeaderValue::from_str(warning) {
+        response.headers_mut().append(QUOTA_WARNING_HEADER, value);
+    }
 }
 
 pub async fn search_multi_index(mut ctx: Context<Data>) -> SResult<Response> {
@@ -172,74 +263,324 @@ pub async fn search_multi_index(mut ctx: Context<Data>) -> SResult<Response> {
 
     let offset = offset;
     let count = count;
-    let db = &ctx.state().db;
-    let par_body = body.clone();
-    let responses_per_index: Vec<SResult<_>> = index_list
-        .into_par_iter()
-        .map(move |index_uid| {
-            let index: Index = db
-                .open_index(&index_uid)
-                .ok_or(ResponseError::index_not_found(&index_uid))?;
-
-            let mut search_builder = index.new_search(par_body.query.clone());
-
-            search_builder.offset(offset);
-            search_builder.limit(count);
-
-            if let Some(attributes_to_retrieve) = par_body.attributes_to_retrieve.clone() {
-                search_builder.attributes_to_retrieve(attributes_to_retrieve);
-            }
-            if let Some(attributes_to_search_in) = par_body.attributes_to_search_in.clone() {
-                search_builder.attributes_to_search_in(attributes_to_search_in);
-            }
-            if let Some(attributes_to_crop) = par_body.attributes_to_crop.clone() {
-                search_builder.attributes_to_crop(attributes_to_crop);
-            }
-            if let Some(attributes_to_highlight) = par_body.attributes_to_highlight.clone() {
-                search_builder.attributes_to_highlight(attributes_to_highlight);
-            }
-            if let Some(filters) = par_body.filters.clone() {
-                search_builder.filters(filters);
-            }
-            if let Some(timeout_ms) = par_body.timeout_ms {
-                search_builder.timeout(Duration::from_millis(timeout_ms));
+    let allowed_attributes = ctx.allowed_attributes()?;
+    let acting_key = ctx.acting_key();
+    let data = ctx.state().clone();
+    let breaker = &data.circuit_breaker;
+    let par_body = Arc::new(body.clone());
+
+    let mut excluded_indexes = Vec::new();
+    let mut pending = HashSet::new();
+    let (sender, receiver) = mpsc::channel();
+
+    // the unknown indexes are rejected like in a single index search,
+    // they are never tracked by the circuit breaker
+    for index_uid in &index_list {
+        if data.db.open_index(index_uid).is_none() {
+            return Err(ResponseError::index_not_found(index_uid));
+        }
+    }
+
+    for index_uid in index_list {
+        if !breaker.allows(&index_uid) {
+            excluded_indexes.push(ExcludedIndex {
+                index_uid,
+                reason: ExclusionReason::CircuitOpen,
+            });
+            continue;
+        }
+
+        let data = data.clone();
+        let body = par_body.clone();
+        let allowed_attributes = allowed_attributes.clone();
+        let acting_key = acting_key.clone();
+        let sender = sender.clone();
+        pending.insert(index_uid.clone());
+
+        // the searches that time out keep running, their results are ignored
+        rayon::spawn(move || {
+            let result = search_index(
+                &data,
+                &index_uid,
+                &body,
+                (offset, count),
+                allowed_attributes,
+                acting_key.as_ref().map(String::as_str),
+            );
+            let _ = sender.send((index_uid, result));
+        });
+    }
+
+    drop(sender);
+
+    let deadline = body
+        .index_timeout_ms
+        .map(|timeout_ms| Instant::now() + Duration::from_millis(timeout_ms));
+
+    let mut hits_per_index = Vec::new();
+
+    let mut max_query_time = 0;
+    let mut quota_warnings = Vec::new();
+
+    while !pending.is_empty() {
+        let received = match deadline {
+            Some(deadline) => {
+                let timeout = deadline.saturating_duration_since(Instant::now());
+                receiver.recv_timeout(timeout).ok()
             }
-            if let Some(matches) = par_body.matches {
-                if matches {
-                    search_builder.get_matches();
+            None => receiver.recv().ok(),
+        };
+
+        let (index_uid, result) = match received {
+            Some(received) => received,
+            None => break,
+        };
+
+        pending.remove(&index_uid);
+
+        match result {
+            Ok((response, quota_warning)) => {
+                breaker.record_success(&index_uid);
+                if let Some(warning) = quota_warning {
+                    quota_warnings.push(format!("{}: {}", index_uid, warning));
+                }
+                if response.processing_time_ms > max_query_time {
+                    max_query_time = response.processing_time_ms;
                 }
+                hits_per_index.push((index_uid, response.hits));
             }
+            Err(ResponseError::Internal(_)) => {
+                breaker.record_failure(&index_uid);
+                excluded_indexes.push(ExcludedIndex {
+                    index_uid,
+                    reason: ExclusionReason::Error,
+                });
+            }
+            // the errors caused by the request are not failures of the index
+            Err(error) => return Err(error),
+        }
+    }
 
-            let env = &db.env;
-            let reader = env.read_txn().map_err(ResponseError::internal)?;
-
-            let response = search_builder
-                .search(&reader)
-                .map_err(ResponseError::internal)?;
-            Ok((index_uid, response))
-        })
-        .collect();
+    for index_uid in pending {
+        breaker.record_failure(&index_uid);
+        excluded_indexes.push(ExcludedIndex {
+            index_uid,
+            reason: ExclusionReason::Timeout,
+        });
+    }
 
-    let mut hits_map = HashMap::new();
+    excluded_indexes.sort_by(|a, b| a.index_uid.cmp(&b.index_uid));
 
-    let mut max_query_time = 0;
+    if let Some(attribute) = &body.dedup_attribute {
+        dedup_hits(&mut hits_per_index, attribute);
 
-    for response in responses_per_index {
-        if let Ok((index_uid, response)) = response {
-            if response.processing_time_ms > max_query_time {
-                max_query_time = response.processing_time_ms;
+        // the attribute was only retrieved to deduplicate the hits
+        let requested = body.attributes_to_retrieve.as_ref();
+        if requested.map_or(false, |attributes| !attributes.contains(attribute)) {
+            for (_, hits) in &mut hits_per_index {
+                for hit in hits {
+                    hit.document.shift_remove(attribute);
+                }
             }
-            hits_map.insert(index_uid, response.hits);
         }
     }
 
+    let hits_map: HashMap<_, _> = hits_per_index.into_iter().collect();
+
     let response = SearchMultiBodyResponse {
         hits: hits_map,
         offset,
         hits_per_page: count,
         processing_time_ms: max_query_time,
         query: body.query,
+        excluded_indexes,
+    };
+
+    let response = serde_json::to_value(response).map_err(ResponseError::internal)?;
+    let mut response = json_stream(JsonChunks::value(response));
+    for warning in quota_warnings {
+        add_quota_warning(&mut response, &warning);
+    }
+
+    Ok(response)
+}
+
+/// Searches one of the indexes of a multi-index search.
+fn search_index(
+    data: &Data,
+    index_uid: &str,
+    body: &SearchMultiBody,
+    (offset, count): (usize, usize),
+    allowed_attributes: Option<Vec<Wildcard>>,
+    acting_key: Option<&str>,
+) -> SResult<(SearchResult, Option<String>)> {
+    let db = &data.db;
+    let index: Index = db
+        .open_index(index_uid)
+        .ok_or(ResponseError::index_not_found(index_uid))?;
+
+    let mut search_builder = index.new_search(body.query.clone());
+
+    search_builder.offset(offset);
+    search_builder.limit(count);
+
+    if let Some(allowed_attributes) = allowed_attributes {
+        search_builder.allowed_attributes(allowed_attributes);
+    }
+
+    if let Some(mut attributes_to_retrieve) = body.attributes_to_retrieve.clone() {
+        // the hits are deduplicated with the value of the attribute
+        if let Some(attribute) = &body.dedup_attribute {
+            attributes_to_retrieve.insert(attribute.clone());
+        }
+        search_builder.attributes_to_retrieve(attributes_to_retrieve);
+    }
+    if let Some(attributes_to_search_in) = body.attributes_to_search_in.clone() {
+        search_builder.attributes_to_search_in(attributes_to_search_in);
+    }
+    if let Some(restrict_searchable_attributes) = body.restrict_searchable_attributes.clone() {
+        search_builder.restrict_searchable_attributes(restrict_searchable_attributes);
+    }
+    if let Some(attributes_to_crop) = body.attributes_to_crop.clone() {
+        search_builder.attributes_to_crop(attributes_to_crop);
+    }
+    if let Some(attributes_to_highlight) = body.attributes_to_highlight.clone() {
+        search_builder.attributes_to_highlight(attributes_to_highlight);
+    }
+    if let Some(pre_tag) = body.highlight_pre_tag.clone() {
+        search_builder.highlight_pre_tag(pre_tag);
+    }
+    if let Some(post_tag) = body.highlight_post_tag.clone() {
+        search_builder.highlight_post_tag(post_tag);
+    }
+    if let Some(filters) = body.filters.clone() {
+        search_builder.filters(filters);
+    }
+    if let Some(timeout_ms) = body.timeout_ms {
+        search_builder.timeout(Duration::from_millis(timeout_ms));
+    }
+    if let Some(bytes) = data.query_memory_limit {
+        search_builder.memory_limit(bytes);
+    }
+    if let Some(matches) = body.matches {
+        if matches {
+            search_builder.get_matches();
+        }
+    }
+    if let Some(prefix) = body.prefix {
+        search_builder.prefix(prefix);
+    }
+    if let Some(escape_html) = body.escape_html {
+        search_builder.escape_html(escape_html);
+    }
+
+    let env = &db.env;
+    let reader = env.read_txn().map_err(ResponseError::internal)?;
+
+    let trace = data.queries.start(index_uid, &body.query);
+    let response = match search_builder.search(&reader) {
+        Ok(response) => response,
+        Err(Error::Internal(message)) => return Err(ResponseError::Internal(message)),
+        Err(others) => return Err(ResponseError::bad_request(others)),
     };
+    drop(trace);
+
+    let quota_warning = data
+        .record_search(&reader, index_uid, acting_key)
+        .map_err(ResponseError::internal)?;
+
+    Ok((response, quota_warning))
+}
+
+/// Removes the hits that have the same value for the attribute as a better-ranked hit
+/// of any of the indexes, a hit is better ranked when it is closer to the start of the
+/// hits of its index, the ties are broken by the order of the indexes uids. The hits
+/// without a value for the attribute are kept. Returns the number of hits removed.
+fn dedup_hits(hits_per_index: &mut [(String, Vec<SearchHit>)], attribute: &str) -> usize {
+    hits_per_index.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    let max_len = hits_per_index.iter().map(|(_, hits)| hits.len()).max();
+    let mut kept: Vec<Vec<bool>> = hits_per_index
+        .iter()
+        .map(|(_, hits)| vec![true; hits.len()])
+        .collect();
+    let mut seen = HashSet::new();
+    let mut removed = 0;
+
+    for rank in 0..max_len.unwrap_or(0) {
+        for (i, (_, hits)) in hits_per_index.iter().enumerate() {
+            let value = match hits.get(rank).and_then(|h| h.document.get(attribute)) {
+                Some(value) if !value.is_null() => value,
+                _ => continue,
+            };
+
+            if !seen.insert(value.to_string()) {
+                kept[i][rank] = false;
+                removed += 1;
+            }
+        }
+    }
+
+    for ((_, hits), kept) in hits_per_index.iter_mut().zip(kept) {
+        let mut kept = kept.into_iter();
+        hits.retain(|_| kept.next().unwrap_or(true));
+    }
 
-    Ok(tide::response::json(response))
+    removed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indexmap::IndexMap;
+
+    fn hits(ids: &[u64]) -> Vec<SearchHit> {
+        ids.iter()
+            .map(|id| {
+                let mut document = IndexMap::new();
+                document.insert("id".to_string(), json!(id));
+                SearchHit {
+                    document,
+                    formatted: IndexMap::new(),
+                    matches_info: None,
+                    explain: None,
+                }
+            })
+            .collect()
+    }
+
+    fn ids(hits: &[SearchHit]) -> Vec<u64> {
+        hits.iter()
+            .map(|hit| hit.document["id"].as_u64().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn dedup_hits_keeps_the_best_ranked() {
+        let mut hits_per_index = vec![
+            ("news".to_string(), hits(&[3, 1, 2])),
+            ("blog".to_string(), hits(&[1, 4, 3])),
+            ("feed".to_string(), hits(&[5])),
+        ];
+
+        assert_eq!(dedup_hits(&mut hits_per_index, "id"), 2);
+
+        let results: Vec<_> = hits_per_index
+            .iter()
+            .map(|(uid, hits)| (uid.as_str(), ids(hits)))
+            .collect();
+
+        // the "1" of the blog and the "3" of the news are ranked first
+        assert_eq!(
+            results,
+            vec![
+                ("blog", vec![1, 4]),
+                ("feed", vec![5]),
+                ("news", vec![3, 2]),
+            ]
+        );
+
+        // the hits without the attribute are all kept
+        assert_eq!(dedup_hits(&mut hits_per_index, "title"), 0);
+    }
 }
@@ -1,34 +1,109 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet};
 
 use http::StatusCode;
-use serde::{Deserialize, Serialize};
+use meilidb_core::criterion::{RankingProfiles, RankingRule};
+use meilidb_core::serde::{
+    Aggregation as CoreAggregation, AttributeType as CoreAttributeType, DocumentIdCharset,
+};
+use meilidb_core::{
+    check_searchable, default_token_filters, normalize_str, ColdTierSettings,
+    FacetNormalization as CoreFacetNormalization, Index, IndexSettings, QueryLimits,
+    TokenFilter as CoreTokenFilter, TokenFilterChain, TyposTable, TyposThreshold, UpdateLimits,
+};
+use meilidb_dto::{
+    Aggregation, AttributeType, AttributesTypes, ColdTierBody, DisplayedAttributesBody,
+    DocumentIdCharsetBody, FacetNormalization, FacetsNormalization, FlatteningSettingsBody,
+    HighlightingSettingsBody, IndexUpdateResponse, NumbersSettingsBody, PaginationSettingsBody,
+    PatchAlternative, PrefixSearchSettingsBody, QueryLimitsBody, QueryQuotaBody, ResponseFormat,
+    SearchableAttributesBody, SettingBody, SettingChange, SettingEffect, SettingsDiff,
+    SettingsDiffRequest, TokenFilter, TyposBody, TyposThresholdBody, UpdateLimitsBody, WordsLimits,
+};
+use meilidb_schema::{Diff, Schema};
 use tide::response::IntoResponse;
 use tide::{Context, Response};
 
+use crate::audit::AuditAction;
 use crate::error::{ResponseError, SResult};
 use crate::helpers::tide::ContextExt;
 use crate::models::token::ACL::*;
-use crate::routes::document::IndexUpdateResponse;
 use crate::Data;
 
-#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase", deny_unknown_fields)]
-pub struct SettingBody {
-    pub ranking_order: Option<RankingOrder>,
-    pub distinct_field: Option<DistinctField>,
-    pub ranking_rules: Option<RankingRules>,
+/// Rejects the ranking rules sorting by attributes whose type is not sortable, like the
+/// booleans that are not stored in the ranked map.
+fn check_sortable<'a, I>(index: &Index, reader: &heed::RoTxn, attributes: I) -> SResult<()>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let attributes_types = index
+        .main
+        .attributes_types(reader)
+        .map_err(ResponseError::internal)?
+        .unwrap_or_default();
+
+    for name in attributes {
+        match attributes_types.get(name) {
+            Some(attribute_type) if !attribute_type.is_sortable() => {
+                return Err(ResponseError::bad_request(format!(
+                    "the attribute {} is of the {:?} type, it cannot be used to sort the documents",
+                    name, attribute_type
+                )));
+            }
+            _ => (),
+        }
+    }
+
+    Ok(())
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
-#[serde(rename_all = "lowercase")]
-pub enum RankingOrdering {
-    Asc,
-    Dsc,
+/// Merges the settings of a settings patch into the current ones, it is set on the
+/// database to be called when the patch is processed and not when it is enqueued.
+pub fn merge_customs(current: Option<&[u8]>, patch: &[u8]) -> Result<Vec<u8>, String> {
+    let mut settings: SettingBody = match current {
+        Some(bytes) => bincode::deserialize(bytes).map_err(|e| e.to_string())?,
+        None => SettingBody::default(),
+    };
+    let patch: SettingBody = bincode::deserialize(patch).map_err(|e| e.to_string())?;
+
+    if patch.ranking_order.is_some() {
+        settings.ranking_order = patch.ranking_order;
+    }
+
+    if patch.distinct_field.is_some() {
+        settings.distinct_field = patch.distinct_field;
+    }
+
+    if patch.ranking_rules.is_some() {
+        settings.ranking_rules = patch.ranking_rules;
+    }
+
+    bincode::serialize(&settings).map_err(|e| e.to_string())
 }
 
-pub type RankingOrder = Vec<String>;
-pub type DistinctField = String;
-pub type RankingRules = HashMap<String, RankingOrdering>;
+/// Renames the attributes of the settings, it is set on the database to be
+/// called when a migration renames the attributes of an index.
+pub fn rename_customs(
+    customs: &[u8],
+    renames: &BTreeMap<String, String>,
+) -> Result<Vec<u8>, String> {
+    let mut settings: SettingBody = bincode::deserialize(customs).map_err(|e| e.to_string())?;
+    let rename = |name: String| renames.get(&name).cloned().unwrap_or(name);
+
+    if let Some(ranking_order) = settings.ranking_order.take() {
+        settings.ranking_order = Some(ranking_order.into_iter().map(rename).collect());
+    }
+
+    settings.distinct_field = settings.distinct_field.take().map(rename);
+
+    if let Some(ranking_rules) = settings.ranking_rules.take() {
+        let ranking_rules = ranking_rules
+            .into_iter()
+            .map(|(name, ordering)| (rename(name), ordering))
+            .collect();
+        settings.ranking_rules = Some(ranking_rules);
+    }
+
+    bincode::serialize(&settings).map_err(|e| e.to_string())
+}
 
 pub async fn get(ctx: Context<Data>) -> SResult<Response> {
     ctx.is_allowed(SettingsRead)?;
@@ -45,6 +120,19 @@ pub async fn get(ctx: Context<Data>) -> SResult<Response> {
     Ok(tide::response::json(settings))
 }
 
+/// Returns every setting of an index, in the form used to import an index into another one.
+pub async fn get_all(ctx: Context<Data>) -> SResult<Response> {
+    ctx.is_allowed(SettingsRead)?;
+    let index = ctx.index()?;
+
+    let env = &ctx.state().db.env;
+    let reader = env.read_txn().map_err(ResponseError::internal)?;
+
+    let settings = IndexSettings::read(&reader, &index).map_err(ResponseError::internal)?;
+
+    Ok(tide::response::json(settings))
+}
+
 pub async fn update(mut ctx: Context<Data>) -> SResult<Response> {
     ctx.is_allowed(SettingsWrite)?;
 
@@ -55,6 +143,10 @@ pub async fn update(mut ctx: Context<Data>) -> SResult<Response> {
     let env = &ctx.state().db.env;
     let mut writer = env.write_txn().map_err(ResponseError::internal)?;
 
+    if let Some(response) = ctx.replayed_update(&writer, &index)? {
+        return Ok(response);
+    }
+
     let mut current_settings = match index.main.customs(&writer).unwrap() {
         Some(bytes) => bincode::deserialize(bytes).unwrap(),
         None => SettingBody::default(),
@@ -69,6 +161,7 @@ pub async fn update(mut ctx: Context<Data>) -> SResult<Response> {
     }
 
     if let Some(ranking_rules) = settings.ranking_rules {
+        check_sortable(&index, &writer, ranking_rules.keys().map(String::as_str))?;
         current_settings.ranking_rules = Some(ranking_rules);
     }
 
@@ -78,6 +171,87 @@ pub async fn update(mut ctx: Context<Data>) -> SResult<Response> {
         .customs_update(&mut writer, bytes)
         .map_err(ResponseError::internal)?;
 
+    ctx.register_update(&mut writer, &index, update_id)?;
+    ctx.audit(&mut writer, AuditAction::SettingsUpdate)?;
+
+    writer.commit().map_err(ResponseError::internal)?;
+
+    let response_body = IndexUpdateResponse { update_id };
+    Ok(tide::response::json(response_body)
+        .with_status(StatusCode::ACCEPTED)
+        .into_response())
+}
+
+/// Changes only the given settings, the stop words and the synonyms are patched when the
+/// update is processed, two patches enqueued concurrently therefore never overwrite each other.
+pub async fn patch(mut ctx: Context<Data>) -> SResult<Response> {
+    ctx.is_allowed(SettingsWrite)?;
+
+    let request: SettingsPatchRequest =
+        ctx.body_json().await.map_err(ResponseError::bad_request)?;
+
+    let index = ctx.index()?;
+
+    let env = &ctx.state().db.env;
+    let mut writer = env.write_txn().map_err(ResponseError::internal)?;
+
+    if let Some(response) = ctx.replayed_update(&writer, &index)? {
+        return Ok(response);
+    }
+
+    let mut patch = index.settings_patch();
+
+    let customs_changed = request.ranking_order.is_some()
+        || request.distinct_field.is_some()
+        || request.ranking_rules.is_some();
+
+    if customs_changed {
+        if let Some(ranking_rules) = &request.ranking_rules {
+            check_sortable(&index, &writer, ranking_rules.keys().map(String::as_str))?;
+        }
+
+        // only the given settings are set, they are merged into the
+        // current ones when the update is processed by `merge_customs`
+        let settings = SettingBody {
+            ranking_order: request.ranking_order,
+            distinct_field: request.distinct_field,
+            ranking_rules: request.ranking_rules,
+        };
+
+        let bytes = bincode::serialize(&settings).map_err(ResponseError::internal)?;
+        patch.merge_customs(bytes);
+    }
+
+    if let Some(stop_words) = request.stop_words {
+        for stop_word in stop_words.add {
+            patch.add_stop_word(stop_word);
+        }
+        for stop_word in stop_words.remove {
+            patch.delete_stop_word(stop_word);
+        }
+    }
+
+    if let Some(synonyms) = request.synonyms {
+        for (synonym, alternatives) in synonyms.add {
+            let alternatives = alternatives.iter().map(PatchAlternative::weighted);
+            patch.add_weighted_synonym(synonym, alternatives);
+        }
+        for (synonym, alternatives) in synonyms.remove {
+            if alternatives.is_empty() {
+                patch.delete_all_alternatives_of(synonym);
+            } else {
+                patch.delete_specific_alternatives_of(synonym, alternatives);
+            }
+        }
+    }
+
+    let update_id = patch
+        .finalize(&mut writer)
+        .map_err(ResponseError::internal)?;
+
+    ctx.register_update(&mut writer, &index, update_id)?;
+    ctx.audit(&mut writer, AuditAction::SettingsUpdate)?;
+
     writer.commit().map_err(ResponseError::internal)?;
 
     let response_body = IndexUpdateResponse { update_id };
@@ -85,3 +259,1509 @@ pub async fn update(mut ctx: Context<Data>) -> SResult<Response> {
         .with_status(StatusCode::ACCEPTED)
         .into_response())
 }
+
+pub async fn get_limits(ctx: Context<Data>) -> SResult<Response> {
+    ctx.is_allowed(SettingsRead)?;
+    let index = ctx.index()?;
+
+    let env = &ctx.state().db.env;
+    let reader = env.read_txn().map_err(ResponseError::internal)?;
+
+    let limits = index
+        .main
+        .update_limits(&reader)
+        .map_err(ResponseError::internal)?
+        .unwrap_or_default();
+
+    let max_attributes = index
+        .main
+        .max_attributes(&reader)
+        .map_err(ResponseError::internal)?;
+
+    let response_body = UpdateLimitsBody {
+        max_documents_per_update: limits.max_documents_per_update,
+        max_payload_bytes: limits.max_payload_bytes,
+        max_field_bytes: limits.max_field_bytes,
+        max_attributes: max_attributes.map(|limit| limit as usize),
+    };
+
+    Ok(tide::response::json(response_body))
+}
+
+/// Replaces the update limits of an index, they are not enqueued like the other
+/// settings as they must be enforced on the updates that are enqueued after them.
+pub async fn update_limits(mut ctx: Context<Data>) -> SResult<Response> {
+    ctx.is_allowed(SettingsWrite)?;
+
+    let body: UpdateLimitsBody = ctx.body_json().await.map_err(ResponseError::bad_request)?;
+
+    let index = ctx.index()?;
+
+    let env = &ctx.state().db.env;
+    let mut writer = env.write_txn().map_err(ResponseError::internal)?;
+
+    let limits = UpdateLimits {
+        max_documents_per_update: body.max_documents_per_update,
+        max_payload_bytes: body.max_payload_bytes,
+        max_field_bytes: body.max_field_bytes,
+    };
+
+    index
+        .main
+        .put_update_limits(&mut writer, &limits)
+        .map_err(ResponseError::internal)?;
+
+    // stored apart to keep the previously stored limits readable
+    let result = match body.max_attributes {
+        Some(limit) => index.main.put_max_attributes(&mut writer, limit as u64),
+        None => index.main.delete_max_attributes(&mut writer).map(drop),
+    };
+    result.map_err(ResponseError::internal)?;
+
+    ctx.audit(&mut writer, AuditAction::SettingsUpdate)?;
+
+    writer.commit().map_err(ResponseError::internal)?;
+
+    Ok(tide::response::json(body))
+}
+
+pub async fn get_numbers(ctx: Context<Data>) -> SResult<Response> {
+    ctx.is_allowed(SettingsRead)?;
+    let index = ctx.index()?;
+
+    let env = &ctx.state().db.env;
+    let reader = env.read_txn().map_err(ResponseError::internal)?;
+
+    let strip_units = index
+        .main
+        .strip_units(&reader)
+        .map_err(ResponseError::internal)?
+        .unwrap_or(false);
+
+    Ok(tide::response::json(NumbersSettingsBody { strip_units }))
+}
+
+/// Numbers are indexed under all their notations, this setting
+/// only changes the queries and is therefore applied immediately.
+pub async fn update_numbers(mut ctx: Context<Data>) -> SResult<Response> {
+    ctx.is_allowed(SettingsWrite)?;
+
+    let body: NumbersSettingsBody = ctx.body_json().await.map_err(ResponseError::bad_request)?;
+
+    let index = ctx.index()?;
+
+    let env = &ctx.state().db.env;
+    let mut writer = env.write_txn().map_err(ResponseError::internal)?;
+
+    index
+        .main
+        .put_strip_units(&mut writer, body.strip_units)
+        .map_err(ResponseError::internal)?;
+
+    ctx.audit(&mut writer, AuditAction::SettingsUpdate)?;
+
+    writer.commit().map_err(ResponseError::internal)?;
+
+    Ok(tide::response::json(body))
+}
+
+pub async fn get_prefix_search(ctx: Context<Data>) -> SResult<Response> {
+    ctx.is_allowed(SettingsRead)?;
+    let index = ctx.index()?;
+
+    let env = &ctx.state().db.env;
+    let reader = env.read_txn().map_err(ResponseError::internal)?;
+
+    let prefix = index
+        .main
+        .prefix_search(&reader)
+        .map_err(ResponseError::internal)?
+        .unwrap_or(true);
+
+    Ok(tide::response::json(PrefixSearchSettingsBody { prefix }))
+}
+
+/// The prefixes of the query words are searched in the words of
+/// the index, this setting is therefore applied immediately.
+pub async fn update_prefix_search(mut ctx: Context<Data>) -> SResult<Response> {
+    ctx.is_allowed(SettingsWrite)?;
+
+    let body: PrefixSearchSettingsBody =
+        ctx.body_json().await.map_err(ResponseError::bad_request)?;
+
+    let index = ctx.index()?;
+
+    let env = &ctx.state().db.env;
+    let mut writer = env.write_txn().map_err(ResponseError::internal)?;
+
+    index
+        .main
+        .put_prefix_search(&mut writer, body.prefix)
+        .map_err(ResponseError::internal)?;
+
+    ctx.audit(&mut writer, AuditAction::SettingsUpdate)?;
+
+    writer.commit().map_err(ResponseError::internal)?;
+
+    Ok(tide::response::json(body))
+}
+
+pub async fn get_document_id_charset(ctx: Context<Data>) -> SResult<Response> {
+    ctx.is_allowed(SettingsRead)?;
+    let index = ctx.index()?;
+
+    let env = &ctx.state().db.env;
+    let reader = env.read_txn().map_err(ResponseError::internal)?;
+
+    let charset = index
+        .main
+        .document_id_charset(&reader)
+        .map_err(ResponseError::internal)?
+        .unwrap_or_default();
+
+    let body = match charset {
+        DocumentIdCharset::Any => DocumentIdCharsetBody::Any,
+        DocumentIdCharset::Alphanumeric { extra } => DocumentIdCharsetBody::Alphanumeric { extra },
+    };
+
+    Ok(tide::response::json(body))
+}
+
+/// The identifiers are validated when the documents are added, this setting
+/// only applies to the next additions and is therefore applied immediately.
+pub async fn update_document_id_charset(mut ctx: Context<Data>) -> SResult<Response> {
+    ctx.is_allowed(SettingsWrite)?;
+
+    let body: DocumentIdCharsetBody = ctx.body_json().await.map_err(ResponseError::bad_request)?;
+
+    let index = ctx.index()?;
+
+    let charset = match body.clone() {
+        DocumentIdCharsetBody::Any => DocumentIdCharset::Any,
+        DocumentIdCharsetBody::Alphanumeric { extra } => DocumentIdCharset::Alphanumeric { extra },
+    };
+
+    let env = &ctx.state().db.env;
+    let mut writer = env.write_txn().map_err(ResponseError::internal)?;
+
+    index
+        .main
+        .put_document_id_charset(&mut writer, &charset)
+        .map_err(ResponseError::internal)?;
+
+    ctx.audit(&mut writer, AuditAction::SettingsUpdate)?;
+
+    writer.commit().map_err(ResponseError::internal)?;
+
+    Ok(tide::response::json(body))
+}
+
+pub async fn get_highlighting(ctx: Context<Data>) -> SResult<Response> {
+    ctx.is_allowed(SettingsRead)?;
+    let index = ctx.index()?;
+
+    let env = &ctx.state().db.env;
+    let reader = env.read_txn().map_err(ResponseError::internal)?;
+
+    let escape_html = index
+        .main
+        .html_escaping(&reader)
+        .map_err(ResponseError::internal)?
+        .unwrap_or(false);
+
+    Ok(tide::response::json(HighlightingSettingsBody {
+        escape_html,
+    }))
+}
+
+/// The formatted fields are escaped when the search results are
+/// built, this setting is therefore applied immediately.
+pub async fn update_highlighting(mut ctx: Context<Data>) -> SResult<Response> {
+    ctx.is_allowed(SettingsWrite)?;
+
+    let body: HighlightingSettingsBody =
+        ctx.body_json().await.map_err(ResponseError::bad_request)?;
+
+    let index = ctx.index()?;
+
+    let env = &ctx.state().db.env;
+    let mut writer = env.write_txn().map_err(ResponseError::internal)?;
+
+    index
+        .main
+        .put_html_escaping(&mut writer, body.escape_html)
+        .map_err(ResponseError::internal)?;
+
+    ctx.audit(&mut writer, AuditAction::SettingsUpdate)?;
+
+    writer.commit().map_err(ResponseError::internal)?;
+
+    Ok(tide::response::json(body))
+}
+
+pub async fn get_facets_normalization(ctx: Context<Data>) -> SResult<Response> {
+    ctx.is_allowed(SettingsRead)?;
+    let index = ctx.index()?;
+
+    let env = &ctx.state().db.env;
+    let reader = env.read_txn().map_err(ResponseError::internal)?;
+
+    let normalization: FacetsNormalization = index
+        .main
+        .facets_normalization(&reader)
+        .map_err(ResponseError::internal)?
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(name, policy)| {
+            let policy = match policy {
+                CoreFacetNormalization::Verbatim => FacetNormalization::Verbatim,
+                CoreFacetNormalization::Trim => FacetNormalization::Trim,
+                CoreFacetNormalization::Lowercase => FacetNormalization::Lowercase,
+                CoreFacetNormalization::UnicodeFold => FacetNormalization::UnicodeFold,
+            };
+            (name, policy)
+        })
+        .collect();
+
+    Ok(tide::response::json(normalization))
+}
+
+/// The facets are counted when the search results are built,
+/// this setting is therefore applied immediately.
+pub async fn update_facets_normalization(mut ctx: Context<Data>) -> SResult<Response> {
+    ctx.is_allowed(SettingsWrite)?;
+
+    let body: FacetsNormalization = ctx.body_json().await.map_err(ResponseError::bad_request)?;
+
+    let index = ctx.index()?;
+
+    let normalization = body
+        .iter()
+        .map(|(name, policy)| {
+            let policy = match policy {
+                FacetNormalization::Verbatim => CoreFacetNormalization::Verbatim,
+                FacetNormalization::Trim => CoreFacetNormalization::Trim,
+                FacetNormalization::Lowercase => CoreFacetNormalization::Lowercase,
+                FacetNormalization::UnicodeFold => CoreFacetNormalization::UnicodeFold,
+            };
+            (name.clone(), policy)
+        })
+        .collect();
+
+    let env = &ctx.state().db.env;
+    let mut writer = env.write_txn().map_err(ResponseError::internal)?;
+
+    index
+        .main
+        .put_facets_normalization(&mut writer, &normalization)
+        .map_err(ResponseError::internal)?;
+
+    ctx.audit(&mut writer, AuditAction::SettingsUpdate)?;
+
+    writer.commit().map_err(ResponseError::internal)?;
+
+    Ok(tide::response::json(body))
+}
+
+pub async fn get_flattening(ctx: Context<Data>) -> SResult<Response> {
+    ctx.is_allowed(SettingsRead)?;
+    let index = ctx.index()?;
+
+    let env = &ctx.state().db.env;
+    let reader = env.read_txn().map_err(ResponseError::internal)?;
+
+    let flatten_documents = index
+        .main
+        .flatten_documents(&reader)
+        .map_err(ResponseError::internal)?
+        .unwrap_or(false);
+
+    Ok(tide::response::json(FlatteningSettingsBody {
+        flatten_documents,
+    }))
+}
+
+/// Only the documents added after the update are flattened,
+/// the already indexed documents are kept as they are.
+pub async fn update_flattening(mut ctx: Context<Data>) -> SResult<Response> {
+    ctx.is_allowed(SettingsWrite)?;
+
+    let body: FlatteningSettingsBody = ctx.body_json().await.map_err(ResponseError::bad_request)?;
+
+    let index = ctx.index()?;
+
+    let env = &ctx.state().db.env;
+    let mut writer = env.write_txn().map_err(ResponseError::internal)?;
+
+    index
+        .main
+        .put_flatten_documents(&mut writer, body.flatten_documents)
+        .map_err(ResponseError::internal)?;
+
+    ctx.audit(&mut writer, AuditAction::SettingsUpdate)?;
+
+    writer.commit().map_err(ResponseError::internal)?;
+
+    Ok(tide::response::json(body))
+}
+
+pub async fn get_pagination(ctx: Context<Data>) -> SResult<Response> {
+    ctx.is_allowed(SettingsRead)?;
+    let index = ctx.index()?;
+
+    let env = &ctx.state().db.env;
+    let reader = env.read_txn().map_err(ResponseError::internal)?;
+
+    let max_total_hits = index
+        .main
+        .max_total_hits(&reader)
+        .map_err(ResponseError::internal)?;
+
+    Ok(tide::response::json(PaginationSettingsBody {
+        max_total_hits,
+    }))
+}
+
+/// The pagination bound is enforced when the searches are executed,
+/// it is therefore applied immediately.
+pub async fn update_pagination(mut ctx: Context<Data>) -> SResult<Response> {
+    ctx.is_allowed(SettingsWrite)?;
+
+    let body: PaginationSettingsBody = ctx.body_json().await.map_err(ResponseError::bad_request)?;
+
+    let index = ctx.index()?;
+
+    let env = &ctx.state().db.env;
+    let mut writer = env.write_txn().map_err(ResponseError::internal)?;
+
+    match body.max_total_hits {
+        Some(max_total_hits) => index
+            .main
+            .put_max_total_hits(&mut writer, max_total_hits)
+            .map_err(ResponseError::internal)?,
+        None => {
+            index
+                .main
+                .delete_max_total_hits(&mut writer)
+                .map_err(ResponseError::internal)?;
+        }
+    }
+
+    ctx.audit(&mut writer, AuditAction::SettingsUpdate)?;
+
+    writer.commit().map_err(ResponseError::internal)?;
+
+    Ok(tide::response::json(body))
+}
+
+pub async fn get_query_limits(ctx: Context<Data>) -> SResult<Response> {
+    ctx.is_allowed(SettingsRead)?;
+    let index = ctx.index()?;
+
+    let env = &ctx.state().db.env;
+    let reader = env.read_txn().map_err(ResponseError::internal)?;
+
+    let limits = index
+        .main
+        .query_limits(&reader)
+        .map_err(ResponseError::internal)?
+        .unwrap_or_default();
+
+    Ok(tide::response::json(QueryLimitsBody {
+        max_query_words: Some(limits.max_query_words),
+        max_automatons: Some(limits.max_automatons),
+    }))
+}
+
+/// The query limits are enforced when the searches are executed,
+/// they are therefore applied immediately.
+pub async fn update_query_limits(mut ctx: Context<Data>) -> SResult<Response> {
+    ctx.is_allowed(SettingsWrite)?;
+
+    let body: QueryLimitsBody = ctx.body_json().await.map_err(ResponseError::bad_request)?;
+
+    let defaults = QueryLimits::default();
+    let limits = QueryLimits {
+        max_query_words: body.max_query_words.unwrap_or(defaults.max_query_words),
+        max_automatons: body.max_automatons.unwrap_or(defaults.max_automatons),
+    };
+
+    if limits.max_query_words == 0 || limits.max_automatons == 0 {
+        return Err(ResponseError::bad_request(
+            "the query limits must be greater than zero",
+        ));
+    }
+
+    let index = ctx.index()?;
+
+    let env = &ctx.state().db.env;
+    let mut writer = env.write_txn().map_err(ResponseError::internal)?;
+
+    index
+        .main
+        .put_query_limits(&mut writer, &limits)
+        .map_err(ResponseError::internal)?;
+
+    ctx.audit(&mut writer, AuditAction::SettingsUpdate)?;
+
+    writer.commit().map_err(ResponseError::internal)?;
+
+    Ok(tide::response::json(QueryLimitsBody {
+        max_query_words: Some(limits.max_query_words),
+        max_automatons: Some(limits.max_automatons),
+    }))
+}
+
+fn typos_body(table: &TyposTable) -> TyposBody {
+    let thresholds = table
+        .thresholds()
+        .iter()
+        .map(|t| TyposThresholdBody {
+            min_word_length: t.min_word_len,
+            max_typos: t.max_typos,
+        })
+        .collect();
+
+    TyposBody { thresholds }
+}
+
+pub async fn get_typos(ctx: Context<Data>) -> SResult<Response> {
+    ctx.is_allowed(SettingsRead)?;
+    let index = ctx.index()?;
+
+    let env = &ctx.state().db.env;
+    let reader = env.read_txn().map_err(ResponseError::internal)?;
+
+    let table = index
+        .main
+        .typos_table(&reader)
+        .map_err(ResponseError::internal)?
+        .unwrap_or_default();
+
+    Ok(tide::response::json(typos_body(&table)))
+}
+
+pub async fn get_criteria(ctx: Context<Data>) -> SResult<Response> {
+    ctx.is_allowed(SettingsRead)?;
+    let index = ctx.index()?;
+
+    let env = &ctx.state().db.env;
+    let reader = env.read_txn().map_err(ResponseError::internal)?;
+
+    let rules = index
+        .main
+        .criteria(&reader)
+        .map_err(ResponseError::internal)?
+        .unwrap_or_else(RankingRule::default_rules);
+
+    let rules: Vec<_> = rules.iter().map(ToString::to_string).collect();
+    Ok(tide::response::json(rules))
+}
+
+/// The criteria are built from the rules when the searches are executed, they are therefore
+/// applied immediately. The ranking rules and order of the settings take precedence over
+/// them and an empty list of rules restores the default criteria.
+pub async fn update_criteria(mut ctx: Context<Data>) -> SResult<Response> {
+    ctx.is_allowed(SettingsWrite)?;
+
+    let body: Vec<String> = ctx.body_json().await.map_err(ResponseError::bad_request)?;
+
+    let mut rules = Vec::with_capacity(body.len());
+    for rule in body {
+        let rule: RankingRule = rule.parse().map_err(ResponseError::bad_request)?;
+        rules.push(rule);
+    }
+
+    let index = ctx.index()?;
+
+    let env = &ctx.state().db.env;
+    let mut writer = env.write_txn().map_err(ResponseError::internal)?;
+    check_sortable(
+        &index,
+        &writer,
+        rules.iter().filter_map(RankingRule::attribute),
+    )?;
+
+    if rules.is_empty() {
+        index
+            .main
+            .delete_criteria(&mut writer)
+            .map_err(ResponseError::internal)?;
+        rules = RankingRule::default_rules();
+    } else {
+        index
+            .main
+            .put_criteria(&mut writer, &rules)
+            .map_err(ResponseError::internal)?;
+    }
+
+    ctx.audit(&mut writer, AuditAction::SettingsUpdate)?;
+
+    writer.commit().map_err(ResponseError::internal)?;
+
+    let rules: Vec<_> = rules.iter().map(ToString::to_string).collect();
+    Ok(tide::response::json(rules))
+}
+
+fn ranking_profiles_body(profiles: &RankingProfiles) -> BTreeMap<String, Vec<String>> {
+    profiles
+        .iter()
+        .map(|(name, rules)| {
+            let rules = rules.iter().map(ToString::to_string).collect();
+            (name.clone(), rules)
+        })
+        .collect()
+}
+
+pub async fn get_ranking_profiles(ctx: Context<Data>) -> SResult<Response> {
+    ctx.is_allowed(SettingsRead)?;
+    let index = ctx.index()?;
+
+    let env = &ctx.state().db.env;
+    let reader = env.read_txn().map_err(ResponseError::internal)?;
+
+    let profiles = index
+        .main
+        .ranking_profiles(&reader)
+        .map_err(ResponseError::internal)?
+        .unwrap_or_default();
+
+    Ok(tide::response::json(ranking_profiles_body(&profiles)))
+}
+
+/// Replaces the named ranking rules that the searches can select with the `rankingProfile`
+/// parameter, they are applied immediately and an empty object removes all of them.
+pub async fn update_ranking_profiles(mut ctx: Context<Data>) -> SResult<Response> {
+    ctx.is_allowed(SettingsWrite)?;
+
+    let body: BTreeMap<String, Vec<String>> =
+        ctx.body_json().await.map_err(ResponseError::bad_request)?;
+
+    let mut profiles = RankingProfiles::new();
+    for (name, rules) in body {
+        if rules.is_empty() {
+            return Err(ResponseError::bad_request(format!(
+                "the ranking profile {} must have at least one rule",
+                name
+            )));
+        }
+
+        let mut parsed = Vec::with_capacity(rules.len());
+        for rule in rules {
+            let rule: RankingRule = rule.parse().map_err(ResponseError::bad_request)?;
+            parsed.push(rule);
+        }
+        profiles.insert(name, parsed);
+    }
+
+    let index = ctx.index()?;
+
+    let env = &ctx.state().db.env;
+    let mut writer = env.write_txn().map_err(ResponseError::internal)?;
+    let attributes = profiles
+        .values()
+        .flatten()
+        .filter_map(RankingRule::attribute);
+    check_sortable(&index, &writer, attributes)?;
+
+    if profiles.is_empty() {
+        index
+            .main
+            .delete_ranking_profiles(&mut writer)
+            .map_err(ResponseError::internal)?;
+    } else {
+        index
+            .main
+            .put_ranking_profiles(&mut writer, &profiles)
+            .map_err(ResponseError::internal)?;
+    }
+
+    ctx.audit(&mut writer, AuditAction::SettingsUpdate)?;
+
+    writer.commit().map_err(ResponseError::internal)?;
+
+    Ok(tide::response::json(ranking_profiles_body(&profiles)))
+}
+
+pub async fn get_cold_tier(ctx: Context<Data>) -> SResult<Response> {
+    ctx.is_allowed(SettingsRead)?;
+    let index = ctx.index()?;
+
+    let env = &ctx.state().db.env;
+    let reader = env.read_txn().map_err(ResponseError::internal)?;
+
+    let settings = index
+        .main
+        .cold_tier(&reader)
+        .map_err(ResponseError::internal)?;
+
+    Ok(tide::response::json(ColdTierBody {
+        idle_secs: settings.map(|s| s.idle_secs),
+    }))
+}
+
+/// The documents are moved between the tiers by the next tiering,
+/// the documents retrieved since the last one are still considered idle.
+pub async fn update_cold_tier(mut ctx: Context<Data>) -> SResult<Response> {
+    ctx.is_allowed(SettingsWrite)?;
+
+    let body: ColdTierBody = ctx.body_json().await.map_err(ResponseError::bad_request)?;
+
+    if body.idle_secs == Some(0) {
+        return Err(ResponseError::bad_request(
+            "the idle period of the cold tier must be greater than zero",
+        ));
+    }
+
+    let index = ctx.index()?;
+
+    let env = &ctx.state().db.env;
+    let mut writer = env.write_txn().map_err(ResponseError::internal)?;
+
+    match body.idle_secs {
+        Some(idle_secs) => index
+            .main
+            .put_cold_tier(&mut writer, &ColdTierSettings { idle_secs })
+            .map_err(ResponseError::internal)?,
+        None => {
+            index
+                .main
+                .delete_cold_tier(&mut writer)
+                .map_err(ResponseError::internal)?;
+        }
+    }
+
+    ctx.audit(&mut writer, AuditAction::SettingsUpdate)?;
+
+    writer.commit().map_err(ResponseError::internal)?;
+
+    Ok(tide::response::json(body))
+}
+
+pub async fn get_frozen_words(ctx: Context<Data>) -> SResult<Response> {
+    ctx.is_allowed(SettingsRead)?;
+    let index = ctx.index()?;
+
+    let env = &ctx.state().db.env;
+    let reader = env.read_txn().map_err(ResponseError::internal)?;
+
+    let frozen_words = index
+        .frozen_words(&reader)
+        .map_err(ResponseError::internal)?;
+
+    Ok(tide::response::json(frozen_words))
+}
+
+pub async fn update_frozen_words(mut ctx: Context<Data>) -> SResult<Response> {
+    ctx.is_allowed(SettingsWrite)?;
+
+    let words: BTreeSet<String> = ctx.body_json().await.map_err(ResponseError::bad_request)?;
+
+    let index = ctx.index()?;
+
+    let env = &ctx.state().db.env;
+    let mut writer = env.write_txn().map_err(ResponseError::internal)?;
+
+    index
+        .put_frozen_words(&mut writer, words)
+        .map_err(ResponseError::internal)?;
+
+    let frozen_words = index
+        .frozen_words(&writer)
+        .map_err(ResponseError::internal)?;
+
+    ctx.audit(&mut writer, AuditAction::SettingsUpdate)?;
+
+    writer.commit().map_err(ResponseError::internal)?;
+
+    Ok(tide::response::json(frozen_words))
+}
+
+/// The typos are allowed when the query automatons are built,
+/// the table is therefore applied immediately.
+pub async fn update_typos(mut ctx: Context<Data>) -> SResult<Response> {
+    ctx.is_allowed(SettingsWrite)?;
+
+    let body: TyposBody = ctx.body_json().await.map_err(ResponseError::bad_request)?;
+
+    let thresholds = body
+        .thresholds
+        .into_iter()
+        .map(|t| TyposThreshold {
+            min_word_len: t.min_word_length,
+            max_typos: t.max_typos,
+        })
+        .collect();
+
+    let table = TyposTable::new(thresholds).map_err(ResponseError::bad_request)?;
+
+    let index = ctx.index()?;
+
+    let env = &ctx.state().db.env;
+    let mut writer = env.write_txn().map_err(ResponseError::internal)?;
+
+    index
+        .main
+        .put_typos_table(&mut writer, &table)
+        .map_err(ResponseError::internal)?;
+
+    ctx.audit(&mut writer, AuditAction::SettingsUpdate)?;
+
+    writer.commit().map_err(ResponseError::internal)?;
+
+    Ok(tide::response::json(typos_body(&table)))
+}
+
+pub async fn get_attributes_types(ctx: Context<Data>) -> SResult<Response> {
+    ctx.is_allowed(SettingsRead)?;
+    let index = ctx.index()?;
+
+    let env = &ctx.state().db.env;
+    let reader = env.read_txn().map_err(ResponseError::internal)?;
+
+    let attributes_types: AttributesTypes = index
+        .main
+        .attributes_types(&reader)
+        .map_err(ResponseError::internal)?
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(name, attribute_type)| {
+            let attribute_type = match attribute_type {
+                CoreAttributeType::Date => AttributeType::Date,
+                CoreAttributeType::Boolean => AttributeType::Boolean,
+                CoreAttributeType::Tokens => AttributeType::Tokens,
+                CoreAttributeType::Aggregated(aggregation) => {
+                    AttributeType::Aggregated(match aggregation {
+                        CoreAggregation::Min => Aggregation::Min,
+                        CoreAggregation::Max => Aggregation::Max,
+                        CoreAggregation::Sum => Aggregation::Sum,
+                        CoreAggregation::Avg => Aggregation::Avg,
+                    })
+                }
+            };
+            (name, attribute_type)
+        })
+        .collect();
+
+    Ok(tide::response::json(attributes_types))
+}
+
+/// Replaces the types of the attributes, the documents are reindexed
+/// to compute the typed values used to sort and filter on them.
+pub async fn update_attributes_types(mut ctx: Context<Data>) -> SResult<Response> {
+    ctx.is_allowed(SettingsWrite)?;
+
+    let body: AttributesTypes = ctx.body_json().await.map_err(ResponseError::bad_request)?;
+
+    let index = ctx.index()?;
+
+    let env = &ctx.state().db.env;
+    let mut writer = env.write_txn().map_err(ResponseError::internal)?;
+
+    if let Some(response) = ctx.replayed_update(&writer, &index)? {
+        return Ok(response);
+    }
+
+    let attributes_types = body
+        .into_iter()
+        .map(|(name, attribute_type)| {
+            let attribute_type = match attribute_type {
+                AttributeType::Date => CoreAttributeType::Date,
+                AttributeType::Boolean => CoreAttributeType::Boolean,
+                AttributeType::Tokens => CoreAttributeType::Tokens,
+                AttributeType::Aggregated(aggregation) => {
+                    CoreAttributeType::Aggregated(match aggregation {
+                        Aggregation::Min => CoreAggregation::Min,
+                        Aggregation::Max => CoreAggregation::Max,
+                        Aggregation::Sum => CoreAggregation::Sum,
+                        Aggregation::Avg => CoreAggregation::Avg,
+                    })
+                }
+            };
+            (name, attribute_type)
+        })
+        .collect();
+
+    let update_id = index
+        .attributes_types_update(&mut writer, attributes_types)
+        .map_err(ResponseError::internal)?;
+
+    ctx.register_update(&mut writer, &index, update_id)?;
+    ctx.audit(&mut writer, AuditAction::SettingsUpdate)?;
+
+    writer.commit().map_err(ResponseError::internal)?;
+
+    let response_body = IndexUpdateResponse { update_id };
+    Ok(tide::response::json(response_body)
+        .with_status(StatusCode::ACCEPTED)
+        .into_response())
+}
+
+fn fst_strings(fst: Option<fst::Set>) -> Result<Vec<String>, ResponseError> {
+    match fst {
+        Some(fst) => fst.stream().into_strs().map_err(ResponseError::internal),
+        None => Ok(Vec::new()),
+    }
+}
+
+fn normalize_words(words: &BTreeSet<String>) -> BTreeSet<String> {
+    words
+        .iter()
+        .map(|word| normalize_str(word.trim()))
+        .filter(|word| !word.is_empty())
+        .collect()
+}
+
+fn schema_change(current: Option<&Schema>, proposed: &Schema) -> Option<SettingChange> {
+    let current = match current {
+        Some(current) => current,
+        None => {
+            return Some(SettingChange {
+                component: "schema".to_string(),
+                effect: SettingEffect::Immediate,
+                reason: Some("the index has no schema yet".to_string()),
+            })
+        }
+    };
+
+    let mut effect = None;
+    let mut reasons = Vec::new();
+
+    for diff in meilidb_schema::diff(current, proposed) {
+        match diff {
+            Diff::IdentChange { old, new } => {
+                effect = Some(SettingEffect::Rejected);
+                reasons.push(format!(
+                    "the identifier cannot change from {} to {}",
+                    old, new
+                ));
+            }
+            Diff::AttrMove { name, .. } => {
+                effect = Some(SettingEffect::Rejected);
+                reasons.push(format!("the {} attribute cannot be moved", name));
+            }
+            Diff::RemovedAttr { name, .. } => {
+                effect = Some(SettingEffect::Rejected);
+                reasons.push(format!("the {} attribute cannot be removed", name));
+            }
+            Diff::NewAttr { name, pos, .. } => {
+                if pos < current.number_of_attributes() {
+                    effect = Some(SettingEffect::Rejected);
+                    reasons.push(format!(
+                        "the {} attribute can only be introduced at the end",
+                        name
+                    ));
+                } else {
+                    effect = effect.or(Some(SettingEffect::Immediate));
+                }
+            }
+            Diff::AttrPropsChange { name, old, new } => {
+                if old.indexed != new.indexed || old.ranked != new.ranked {
+                    if effect != Some(SettingEffect::Rejected) {
+                        effect = Some(SettingEffect::Reindexing);
+                    }
+                    reasons.push(format!(
+                        "the {} attribute changes its indexed or ranked property",
+                        name
+                    ));
+                } else {
+                    effect = effect.or(Some(SettingEffect::Immediate));
+                }
+            }
+        }
+    }
+
+    effect.map(|effect| SettingChange {
+        component: "schema".to_string(),
+        effect,
+        reason: if reasons.is_empty() {
+            None
+        } else {
+            Some(reasons.join(", "))
+        },
+    })
+}
+
+fn stop_words_change(
+    current: &BTreeSet<String>,
+    proposed: &BTreeSet<String>,
+) -> Option<SettingChange> {
+    if current == proposed {
+        return None;
+    }
+
+    // removing stop words makes their postings lists reappear, this is a full reindexing
+    let removed = current.difference(proposed).count();
+    if removed != 0 {
+        Some(SettingChange {
+            component: "stopWords".to_string(),
+            effect: SettingEffect::Reindexing,
+            reason: Some(format!("{} stop words removed", removed)),
+        })
+    } else {
+        Some(SettingChange {
+            component: "stopWords".to_string(),
+            effect: SettingEffect::Immediate,
+            reason: None,
+        })
+    }
+}
+
+pub async fn get_words_limits(ctx: Context<Data>) -> SResult<Response> {
+    ctx.is_allowed(SettingsRead)?;
+    let index = ctx.index()?;
+
+    let env = &ctx.state().db.env;
+    let reader = env.read_txn().map_err(ResponseError::internal)?;
+
+    let words_limits: WordsLimits = index
+        .main
+        .words_limits(&reader)
+        .map_err(ResponseError::internal)?
+        .unwrap_or_default();
+
+    Ok(tide::response::json(words_limits))
+}
+
+/// Replaces the maximum number of words indexed in the attributes,
+/// the documents are reindexed from their stored fields.
+pub async fn update_words_limits(mut ctx: Context<Data>) -> SResult<Response> {
+    ctx.is_allowed(SettingsWrite)?;
+
+    let words_limits: WordsLimits = ctx.body_json().await.map_err(ResponseError::bad_request)?;
+
+    let index = ctx.index()?;
+
+    let env = &ctx.state().db.env;
+    let mut writer = env.write_txn().map_err(ResponseError::internal)?;
+
+    if let Some(response) = ctx.replayed_update(&writer, &index)? {
+        return Ok(response);
+    }
+
+    let update_id = index
+        .words_limits_update(&mut writer, words_limits)
+        .map_err(ResponseError::internal)?;
+
+    ctx.register_update(&mut writer, &index, update_id)?;
+    ctx.audit(&mut writer, AuditAction::SettingsUpdate)?;
+
+    writer.commit().map_err(ResponseError::internal)?;
+
+    let response_body = IndexUpdateResponse { update_id };
+    Ok(tide::response::json(response_body)
+        .with_status(StatusCode::ACCEPTED)
+        .into_response())
+}
+
+/// The token filters of the index, the default ones if the index never defined them.
+pub async fn get_token_filters(ctx: Context<Data>) -> SResult<Response> {
+    ctx.is_allowed(SettingsRead)?;
+    let index = ctx.index()?;
+
+    let env = &ctx.state().db.env;
+    let reader = env.read_txn().map_err(ResponseError::internal)?;
+
+    let token_filters: Vec<TokenFilter> = index
+        .main
+        .token_filters(&reader)
+        .map_err(ResponseError::internal)?
+        .unwrap_or_else(default_token_filters)
+        .into_iter()
+        .map(|filter| match filter {
+            CoreTokenFilter::Lowercase => TokenFilter::Lowercase,
+            CoreTokenFilter::AsciiFold => TokenFilter::AsciiFold,
+            CoreTokenFilter::Length { min, max } => TokenFilter::Length { min, max },
+            CoreTokenFilter::Custom(name) => TokenFilter::Custom(name),
+        })
+        .collect();
+
+    Ok(tide::response::json(token_filters))
+}
+
+/// Replaces the token filters of the index, the custom filters must be registered
+/// by the program embedding the engine. The documents are reindexed.
+pub async fn update_token_filters(mut ctx: Context<Data>) -> SResult<Response> {
+    ctx.is_allowed(SettingsWrite)?;
+
+    let body: Vec<TokenFilter> = ctx.body_json().await.map_err(ResponseError::bad_request)?;
+
+    let token_filters: Vec<_> = body
+        .into_iter()
+        .map(|filter| match filter {
+            TokenFilter::Lowercase => CoreTokenFilter::Lowercase,
+            TokenFilter::AsciiFold => CoreTokenFilter::AsciiFold,
+            TokenFilter::Length { min, max } => CoreTokenFilter::Length { min, max },
+            TokenFilter::Custom(name) => CoreTokenFilter::Custom(name),
+        })
+        .collect();
+
+    TokenFilterChain::resolve(&token_filters).map_err(ResponseError::bad_request)?;
+
+    let index = ctx.index()?;
+
+    let env = &ctx.state().db.env;
+    let mut writer = env.write_txn().map_err(ResponseError::internal)?;
+
+    if let Some(response) = ctx.replayed_update(&writer, &index)? {
+        return Ok(response);
+    }
+
+    let update_id = index
+        .token_filters_update(&mut writer, token_filters)
+        .map_err(ResponseError::internal)?;
+
+    ctx.register_update(&mut writer, &index, update_id)?;
+    ctx.audit(&mut writer, AuditAction::SettingsUpdate)?;
+
+    writer.commit().map_err(ResponseError::internal)?;
+
+    let response_body = IndexUpdateResponse { update_id };
+    Ok(tide::response::json(response_body)
+        .with_status(StatusCode::ACCEPTED)
+        .into_response())
+}
+
+pub async fn get_searchable_attributes(ctx: Context<Data>) -> SResult<Response> {
+    ctx.is_allowed(SettingsRead)?;
+    let index = ctx.index()?;
+
+    let env = &ctx.state().db.env;
+    let reader = env.read_txn().map_err(ResponseError::internal)?;
+
+    let searchable_attributes = index
+        .main
+        .searchable_attributes(&reader)
+        .map_err(ResponseError::internal)?;
+
+    Ok(tide::response::json(SearchableAttributesBody {
+        searchable_attributes,
+    }))
+}
+
+/// Replaces the searchable attributes, the documents are reindexed from their stored
+/// fields when the set of attributes changes. The attributes that are not in the schema
+/// are ignored and all the indexed attributes are searchable again when it is not given.
+pub async fn update_searchable_attributes(mut ctx: Context<Data>) -> SResult<Response> {
+    ctx.is_allowed(SettingsWrite)?;
+
+    let body: SearchableAttributesBody =
+        ctx.body_json().await.map_err(ResponseError::bad_request)?;
+
+    let index = ctx.index()?;
+
+    let env = &ctx.state().db.env;
+    let mut writer = env.write_txn().map_err(ResponseError::internal)?;
+
+    if let Some(response) = ctx.replayed_update(&writer, &index)? {
+        return Ok(response);
+    }
+
+    if let Some(schema) = index
+        .main
+        .schema(&writer)
+        .map_err(ResponseError::internal)?
+    {
+        let searchable_attributes = body.searchable_attributes.as_ref().map(Vec::as_slice);
+        check_searchable(&schema, searchable_attributes).map_err(ResponseError::bad_request)?;
+    }
+
+    let update_id = index
+        .searchable_attributes_update(&mut writer, body.searchable_attributes)
+        .map_err(ResponseError::internal)?;
+
+    ctx.register_update(&mut writer, &index, update_id)?;
+    ctx.audit(&mut writer, AuditAction::SettingsUpdate)?;
+
+    writer.commit().map_err(ResponseError::internal)?;
+
+    let response_body = IndexUpdateResponse { update_id };
+    Ok(tide::response::json(response_body)
+        .with_status(StatusCode::ACCEPTED)
+        .into_response())
+}
+
+pub async fn get_displayed_attributes(ctx: Context<Data>) -> SResult<Response> {
+    ctx.is_allowed(SettingsRead)?;
+    let index = ctx.index()?;
+
+    let env = &ctx.state().db.env;
+    let reader = env.read_txn().map_err(ResponseError::internal)?;
+
+    let displayed_attributes = index
+        .main
+        .displayed_attributes(&reader)
+        .map_err(ResponseError::internal)?;
+
+    Ok(tide::response::json(DisplayedAttributesBody {
+        displayed_attributes,
+    }))
+}
+
+/// The documents are immediately returned with the new displayed attributes, only the
+/// attributes displayed by the schema can be returned, whatever the settings are.
+pub async fn update_displayed_attributes(mut ctx: Context<Data>) -> SResult<Response> {
+    ctx.is_allowed(SettingsWrite)?;
+
+    let body: DisplayedAttributesBody =
+        ctx.body_json().await.map_err(ResponseError::bad_request)?;
+
+    let index = ctx.index()?;
+
+    let env = &ctx.state().db.env;
+    let mut writer = env.write_txn().map_err(ResponseError::internal)?;
+
+    match &body.displayed_attributes {
+        Some(attributes) => {
+            index
+                .main
+                .put_displayed_attributes(&mut writer, attributes)
+                .map_err(ResponseError::internal)?;
+        }
+        None => {
+            index
+                .main
+                .delete_displayed_attributes(&mut writer)
+                .map_err(ResponseError::internal)?;
+        }
+    }
+
+    ctx.audit(&mut writer, AuditAction::SettingsUpdate)?;
+
+    writer.commit().map_err(ResponseError::internal)?;
+
+    Ok(tide::response::json(body))
+}
+
+pub async fn get_response_format(ctx: Context<Data>) -> SResult<Response> {
+    ctx.is_allowed(SettingsRead)?;
+    let index_uid = ctx.url_param("index")?;
+
+    let env = &ctx.state().db.env;
+    let reader = env.read_txn().map_err(ResponseError::internal)?;
+
+    let format = ctx
+        .state()
+        .response_format(&reader, &index_uid)
+        .map_err(ResponseError::internal)?;
+
+    Ok(tide::response::json(format))
+}
+
+/// Replaces the default shape of the search responses of an index,
+/// it only concerns the responses and is therefore applied immediately.
+pub async fn update_response_format(mut ctx: Context<Data>) -> SResult<Response> {
+    ctx.is_allowed(SettingsWrite)?;
+
+    let body: ResponseFormat = ctx.body_json().await.map_err(ResponseError::bad_request)?;
+
+    // the format can only be set on existing indexes
+    ctx.index()?;
+    let index_uid = ctx.url_param("index")?;
+
+    let env = &ctx.state().db.env;
+    let mut writer = env.write_txn().map_err(ResponseError::internal)?;
+
+    ctx.state()
+        .put_response_format(&mut writer, &index_uid, &body)
+        .map_err(ResponseError::internal)?;
+
+    ctx.audit(&mut writer, AuditAction::SettingsUpdate)?;
+
+    writer.commit().map_err(ResponseError::internal)?;
+
+    Ok(tide::response::json(body))
+}
+
+pub async fn get_query_quota(ctx: Context<Data>) -> SResult<Response> {
+    ctx.is_allowed(SettingsRead)?;
+    let index_uid = ctx.url_param("index")?;
+
+    let env = &ctx.state().db.env;
+    let reader = env.read_txn().map_err(ResponseError::internal)?;
+
+    let quota = ctx
+        .state()
+        .query_quota(&reader, &index_uid)
+        .map_err(ResponseError::internal)?;
+
+    Ok(tide::response::json(quota))
+}
+
+/// Replaces the soft limits of the number of searches of an index, the searches
+/// are only annotated when a limit is exceeded, it is therefore applied immediately.
+pub async fn update_query_quota(mut ctx: Context<Data>) -> SResult<Response> {
+    ctx.is_allowed(SettingsWrite)?;
+
+    let body: QueryQuotaBody = ctx.body_json().await.map_err(ResponseError::bad_request)?;
+
+    // the quota can only be set on existing indexes
+    ctx.index()?;
+    let index_uid = ctx.url_param("index")?;
+
+    let env = &ctx.state().db.env;
+    let mut writer = env.write_txn().map_err(ResponseError::internal)?;
+
+    ctx.state()
+        .put_query_quota(&mut writer, &index_uid, &body)
+        .map_err(ResponseError::internal)?;
+
+    ctx.audit(&mut writer, AuditAction::SettingsUpdate)?;
+
+    writer.commit().map_err(ResponseError::internal)?;
+
+    Ok(tide::response::json(body))
+}
+
+pub async fn diff(mut ctx: Context<Data>) -> SResult<Response> {
+    ctx.is_allowed(SettingsRead)?;
+
+    let proposed: SettingsDiffRequest =
+        ctx.body_json().await.map_err(ResponseError::bad_request)?;
+
+    let index = ctx.index()?;
+
+    let env = &ctx.state().db.env;
+    let reader = env.read_txn().map_err(ResponseError::internal)?;
+
+    let current_settings: SettingBody = match index
+        .main
+        .customs(&reader)
+        .map_err(ResponseError::internal)?
+    {
+        Some(bytes) => bincode::deserialize(bytes).map_err(ResponseError::internal)?,
+        None => SettingBody::default(),
+    };
+
+    let mut diff = SettingsDiff::default();
+    let mut push = |component: &str, change: Option<SettingChange>| match change {
+        Some(change) => diff.changes.push(change),
+        None => diff.unchanged.push(component.to_string()),
+    };
+
+    let immediate = |component: &str, changed: bool| {
+        if changed {
+            Some(SettingChange {
+                component: component.to_string(),
+                effect: SettingEffect::Immediate,
+                reason: None,
+            })
+        } else {
+            None
+        }
+    };
+
+    if let Some(ranking_order) = &proposed.ranking_order {
+        let changed = current_settings.ranking_order.as_ref() != Some(ranking_order);
+        push("rankingOrder", immediate("rankingOrder", changed));
+    }
+
+    if let Some(distinct_field) = &proposed.distinct_field {
+        let changed = current_settings.distinct_field.as_ref() != Some(distinct_field);
+        push("distinctField", immediate("distinctField", changed));
+    }
+
+    if let Some(ranking_rules) = &proposed.ranking_rules {
+        let changed = current_settings.ranking_rules.as_ref() != Some(ranking_rules);
+        push("rankingRules", immediate("rankingRules", changed));
+    }
+
+    if let Some(schema) = &proposed.schema {
+        let current = index
+            .main
+            .schema(&reader)
+            .map_err(ResponseError::internal)?;
+        let proposed: Schema = schema.clone().into();
+        push("schema", schema_change(current.as_ref(), &proposed));
+    }
+
+    if let Some(stop_words) = &proposed.stop_words {
+        let stop_words_fst = index
+            .main
+            .stop_words_fst(&reader)
+            .map_err(ResponseError::internal)?;
+        let current: BTreeSet<_> = fst_strings(stop_words_fst)?.into_iter().collect();
+        push(
+            "stopWords",
+            stop_words_change(&current, &normalize_words(stop_words)),
+        );
+    }
+
+    if let Some(synonyms) = &proposed.synonyms {
+        let synonyms_fst = index
+            .main
+            .synonyms_fst(&reader)
+            .map_err(ResponseError::internal)?;
+
+        let mut current = BTreeMap::new();
+        for synonym in fst_strings(synonyms_fst)? {
+            let alternatives = index
+                .synonyms
+                .synonyms(&reader, synonym.as_bytes())
+                .map_err(ResponseError::internal)?;
+            let alternatives: BTreeSet<_> = fst_strings(alternatives)?.into_iter().collect();
+            current.insert(synonym, alternatives);
+        }
+
+        let proposed: BTreeMap<_, _> = synonyms
+            .iter()
+            .map(|(synonym, alternatives)| {
+                let alternatives = alternatives.iter().map(|s| s.to_lowercase()).collect();
+                (normalize_str(synonym), alternatives)
+            })
+            .collect();
+
+        push("synonyms", immediate("synonyms", current != proposed));
+    }
+
+    if let Some(decompounding) = &proposed.decompounding {
+        let decompounding_fst = index
+            .main
+            .decompounding_fst(&reader)
+            .map_err(ResponseError::internal)?;
+        let current: BTreeSet<_> = fst_strings(decompounding_fst)?.into_iter().collect();
+        let changed = current != normalize_words(decompounding);
+        push("decompounding", immediate("decompounding", changed));
+    }
+
+    diff.requires_reindexing = diff
+        .changes
+        .iter()
+        .any(|change| change.effect == SettingEffect::Reindexing);
+
+    Ok(tide::response::json(diff))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use meilidb_dto::RankingOrdering;
+    use meilidb_schema::{SchemaBuilder, SchemaProps};
+    use std::collections::HashMap;
+
+    fn schema(ranked: bool) -> Schema {
+        let mut builder = SchemaBuilder::with_identifier("id");
+        builder.new_attribute(
+            "id",
+            SchemaProps {
+                indexed: false,
+                displayed: true,
+                ranked: false,
+            },
+        );
+        builder.new_attribute(
+            "title",
+            SchemaProps {
+                indexed: true,
+                displayed: true,
+                ranked,
+            },
+        );
+        builder.build()
+    }
+
+    #[test]
+    fn merged_customs() {
+        let current = SettingBody {
+            ranking_order: Some(vec!["typo".to_string()]),
+            distinct_field: Some("brand".to_string()),
+            ranking_rules: None,
+        };
+        let current = bincode::serialize(&current).unwrap();
+
+        let patch = SettingBody {
+            distinct_field: Some("color".to_string()),
+            ..SettingBody::default()
+        };
+        let patch = bincode::serialize(&patch).unwrap();
+
+        let merged = merge_customs(Some(&current), &patch).unwrap();
+        let merged: SettingBody = bincode::deserialize(&merged).unwrap();
+        assert_eq!(merged.ranking_order, Some(vec!["typo".to_string()]));
+        assert_eq!(merged.distinct_field, Some("color".to_string()));
+
+        let merged = merge_customs(None, &patch).unwrap();
+        let merged: SettingBody = bincode::deserialize(&merged).unwrap();
+        assert_eq!(merged.ranking_order, None);
+        assert_eq!(merged.distinct_field, Some("color".to_string()));
+
+        assert!(merge_customs(Some(b"\xff"), &patch).is_err());
+    }
+
+    #[test]
+    fn renamed_customs() {
+        let mut ranking_rules = HashMap::new();
+        ranking_rules.insert("date".to_string(), RankingOrdering::Dsc);
+
+        let customs = SettingBody {
+            ranking_order: Some(vec!["typo".to_string(), "date".to_string()]),
+            distinct_field: Some("brand".to_string()),
+            ranking_rules: Some(ranking_rules),
+        };
+        let customs = bincode::serialize(&customs).unwrap();
+
+        let mut renames = BTreeMap::new();
+        renames.insert("date".to_string(), "released_at".to_string());
+        renames.insert("brand".to_string(), "maker".to_string());
+
+        let renamed = rename_customs(&customs, &renames).unwrap();
+        let renamed: SettingBody = bincode::deserialize(&renamed).unwrap();
+        assert_eq!(
+            renamed.ranking_order,
+            Some(vec!["typo".to_string(), "released_at".to_string()])
+        );
+        assert_eq!(renamed.distinct_field, Some("maker".to_string()));
+        let ranking_rules = renamed.ranking_rules.unwrap();
+        assert_eq!(
+            ranking_rules.get("released_at"),
+            Some(&RankingOrdering::Dsc)
+        );
+        assert!(!ranking_rules.contains_key("date"));
+    }
+
+    #[test]
+    fn schema_changes() {
+        let current = schema(false);
+
+        assert_eq!(schema_change(Some(&current), &schema(false)), None);
+
+        let change = schema_change(Some(&current), &schema(true)).unwrap();
+        assert_eq!(change.effect, SettingEffect::Reindexing);
+
+        let mut builder = SchemaBuilder::with_identifier("title");
+        builder.new_attribute(
+            "id",
+            SchemaProps {
+                indexed: false,
+                displayed: true,
+                ranked: false,
+            },
+        );
+        builder.new_attribute(
+            "title",
+            SchemaProps {
+                indexed: true,
+                displayed: true,
+                ranked: false,
+            },
+        );
+        let change = schema_change(Some(&current), &builder.build()).unwrap();
+        assert_eq!(change.effect, SettingEffect::Rejected);
+    }
+
+    #[test]
+    fn stop_words_changes() {
+        let words = |words: &[&str]| words.iter().map(|s| s.to_string()).collect::<BTreeSet<_>>();
+
+        assert_eq!(
+            stop_words_change(&words(&["a", "the"]), &words(&["a", "the"])),
+            None
+        );
+
+        let added = stop_words_change(&words(&["a"]), &words(&["a", "the"])).unwrap();
+        assert_eq!(added.effect, SettingEffect::Immediate);
+
+        let removed = stop_words_change(&words(&["a", "the"]), &words(&["the", "of"])).unwrap();
+        assert_eq!(removed.effect, SettingEffect::Reindexing);
+    }
+}
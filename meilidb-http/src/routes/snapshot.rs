@@ -0,0 +1,20 @@
+use tide::{Context, Response};
+
+use crate::error::{ResponseError, SResult};
+use crate::helpers::tide::ContextExt;
+use crate::models::token::ACL::*;
+use crate::Data;
+
+pub async fn list(ctx: Context<Data>) -> SResult<Response> {
+    ctx.is_allowed(Admin)?;
+
+    let env = &ctx.state().db.env;
+    let reader = env.read_txn().map_err(ResponseError::internal)?;
+
+    let results = ctx
+        .state()
+        .snapshots_results(&reader)
+        .map_err(ResponseError::internal)?;
+
+    Ok(tide::response::json(results))
+}
@@ -2,9 +2,12 @@ use std::collections::HashMap;
 
 use chrono::{DateTime, Utc};
 use log::error;
+use meilidb_core::raw_indexer::AttributesLengths;
+use meilidb_core::{IndexingStats, TierStats};
 use pretty_bytes::converter::convert;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use sysinfo::{NetworkExt, Pid, ProcessExt, ProcessorExt, System, SystemExt};
+use tide::querystring::ContextExt as QSContextExt;
 use tide::{Context, Response};
 use walkdir::WalkDir;
 
@@ -19,6 +22,34 @@ struct IndexStatsResponse {
     number_of_documents: u64,
     is_indexing: bool,
     fields_frequency: HashMap<String, usize>,
+    /// The statistics of the last documents updates, only listed by the stats of an index.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    indexing_history: Option<Vec<IndexingStats>>,
+    /// The number of documents in each tier, only listed by the stats of an index.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tiers: Option<TiersResponse>,
+    /// The distribution of the number of words in the values of the attributes,
+    /// only listed by the stats of an index.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    attributes_lengths: Option<AttributesLengths>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TiersResponse {
+    hot_documents: u64,
+    cold_documents: u64,
+    cold_size: u64,
+}
+
+impl From<TierStats> for TiersResponse {
+    fn from(stats: TierStats) -> TiersResponse {
+        TiersResponse {
+            hot_documents: stats.hot_documents,
+            cold_documents: stats.cold_documents,
+            cold_size: stats.cold_size,
+        }
+    }
 }
 
 pub async fn index_stat(ctx: Context<Data>) -> SResult<Response> {
@@ -46,14 +77,54 @@ pub async fn index_stat(ctx: Context<Data>) -> SResult<Response> {
         .map_err(ResponseError::internal)?
         .ok_or(ResponseError::internal("'is_indexing' date not found"))?;
 
+    let indexing_history = index
+        .indexing_history(&reader)
+        .map_err(ResponseError::internal)?;
+
+    let tiers = index.tier_stats(&reader).map_err(ResponseError::internal)?;
+
+    let attributes_lengths = index
+        .main
+        .attributes_lengths(&reader)
+        .map_err(ResponseError::internal)?
+        .unwrap_or_default();
+
     let response = IndexStatsResponse {
         number_of_documents,
         is_indexing,
         fields_frequency,
+        indexing_history: Some(indexing_history),
+        tiers: Some(tiers.into()),
+        attributes_lengths: Some(attributes_lengths),
     };
     Ok(tide::response::json(response))
 }
 
+#[derive(Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PostingsStatsQuery {
+    pattern: Option<String>,
+}
+
+/// The statistics of the postings lists of the words matching the `pattern`
+/// query parameter, a `*` matches any sequence of characters and a pattern
+/// without one is a prefix, all the words are considered by default.
+pub async fn postings_stats(ctx: Context<Data>) -> SResult<Response> {
+    ctx.is_allowed(Admin)?;
+    let index = ctx.index()?;
+    let query: PostingsStatsQuery = ctx.url_query().unwrap_or_default();
+    let pattern = query.pattern.unwrap_or_default();
+
+    let env = &ctx.state().db.env;
+    let reader = env.read_txn().map_err(ResponseError::internal)?;
+
+    let stats = index
+        .postings_stats(&reader, &pattern)
+        .map_err(ResponseError::internal)?;
+
+    Ok(tide::response::json(stats))
+}
+
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
 struct StatsResult {
@@ -98,6 +169,9 @@ pub async fn get_stats(ctx: Context<Data>) -> SResult<Response> {
                     number_of_documents,
                     is_indexing,
                     fields_frequency,
+                    indexing_history: None,
+                    tiers: None,
+                    attributes_lengths: None,
                 };
                 index_list.insert(index_uid, response);
             }
@@ -1,11 +1,12 @@
 use http::StatusCode;
+use meilidb_dto::IndexUpdateResponse;
 use tide::response::IntoResponse;
 use tide::{Context, Response};
 
+use crate::audit::AuditAction;
 use crate::error::{ResponseError, SResult};
 use crate::helpers::tide::ContextExt;
 use crate::models::token::ACL::*;
-use crate::routes::document::IndexUpdateResponse;
 use crate::Data;
 
 pub async fn list(ctx: Context<Data>) -> SResult<Response> {
@@ -38,6 +39,10 @@ pub async fn add(mut ctx: Context<Data>) -> SResult<Response> {
     let env = &ctx.state().db.env;
     let mut writer = env.write_txn().map_err(ResponseError::internal)?;
 
+    if let Some(response) = ctx.replayed_update(&writer, &index)? {
+        return Ok(response);
+    }
+
     let mut stop_words_addition = index.stop_words_addition();
     for stop_word in data {
         stop_words_addition.add_stop_word(stop_word);
@@ -47,6 +52,9 @@ pub async fn add(mut ctx: Context<Data>) -> SResult<Response> {
         .finalize(&mut writer)
         .map_err(ResponseError::internal)?;
 
+    ctx.register_update(&mut writer, &index, update_id)?;
+    ctx.audit(&mut writer, AuditAction::StopWordsUpdate)?;
+
     writer.commit().map_err(ResponseError::internal)?;
 
     let response_body = IndexUpdateResponse { update_id };
@@ -64,6 +72,10 @@ pub async fn delete(mut ctx: Context<Data>) -> SResult<Response> {
     let env = &ctx.state().db.env;
     let mut writer = env.write_txn().map_err(ResponseError::internal)?;
 
+    if let Some(response) = ctx.replayed_update(&writer, &index)? {
+        return Ok(response);
+    }
+
     let mut stop_words_deletion = index.stop_words_deletion();
     for stop_word in data {
         stop_words_deletion.delete_stop_word(stop_word);
@@ -73,6 +85,9 @@ pub async fn delete(mut ctx: Context<Data>) -> SResult<Response> {
         .finalize(&mut writer)
         .map_err(ResponseError::internal)?;
 
+    ctx.register_update(&mut writer, &index, update_id)?;
+    ctx.audit(&mut writer, AuditAction::StopWordsUpdate)?;
+
     writer.commit().map_err(ResponseError::internal)?;
 
     let response_body = IndexUpdateResponse { update_id };
@@ -1,32 +1,100 @@
 use std::collections::HashMap;
 
+use fst::Streamer;
 use http::StatusCode;
+use meilidb_core::Index;
+use meilidb_dto::IndexUpdateResponse;
 use serde::{Deserialize, Serialize};
 use tide::response::IntoResponse;
 use tide::{Context, Response};
 
+use crate::audit::AuditAction;
 use crate::error::{ResponseError, SResult};
 use crate::helpers::tide::ContextExt;
 use crate::models::token::ACL::*;
-use crate::routes::document::IndexUpdateResponse;
 use crate::Data;
 
 #[derive(Clone, Serialize, Deserialize)]
 #[serde(untagged)]
 pub enum Synonym {
     OneWay(SynonymOneWay),
-    MultiWay { synonyms: Vec<String> },
+    MultiWay { synonyms: Vec<Alternative> },
 }
 
 #[derive(Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct SynonymOneWay {
     pub input: String,
-    pub synonyms: Vec<String>,
+    pub synonyms: Vec<Alternative>,
+}
+
+/// An alternative, either a simple word or a word along with a penalty making
+/// its matches rank after the ones of the original word, from 0 to 3.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Alternative {
+    Word(String),
+    Weighted(WeightedAlternative),
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct WeightedAlternative {
+    pub word: String,
+    pub penalty: u8,
+}
+
+impl Alternative {
+    fn word(&self) -> &str {
+        match self {
+            Alternative::Word(word) => word,
+            Alternative::Weighted(alternative) => &alternative.word,
+        }
+    }
+
+    fn weighted(&self) -> (&str, u8) {
+        match self {
+            Alternative::Word(word) => (word, 0),
+            Alternative::Weighted(alternative) => (&alternative.word, alternative.penalty),
+        }
+    }
 }
 
 pub type Synonyms = Vec<Synonym>;
 
+/// The alternatives of a synonym, the ones without penalty are written as simple words.
+fn alternatives(
+    index: &Index,
+    reader: &heed::RoTxn,
+    synonym: &str,
+) -> SResult<Option<Vec<Alternative>>> {
+    let alternatives = index
+        .synonyms
+        .weighted_synonyms(reader, synonym.as_bytes())
+        .map_err(ResponseError::internal)?;
+
+    let alternatives = match alternatives {
+        Some(alternatives) => alternatives,
+        None => return Ok(None),
+    };
+
+    let mut list = Vec::new();
+    let mut stream = alternatives.stream();
+    while let Some((word, penalty)) = stream.next() {
+        let word = String::from_utf8_lossy(word).into_owned();
+        let alternative = match penalty {
+            0 => Alternative::Word(word),
+            penalty => Alternative::Weighted(WeightedAlternative {
+                word,
+                penalty: penalty as u8,
+            }),
+        };
+        list.push(alternative);
+    }
+
+    Ok(Some(list))
+}
+
 pub async fn list(ctx: Context<Data>) -> SResult<Response> {
     ctx.is_allowed(SettingsRead)?;
     let index = ctx.index()?;
@@ -44,17 +112,10 @@ pub async fn list(ctx: Context<Data>) -> SResult<Response> {
 
     let mut response = HashMap::new();
 
-    let index_synonyms = &index.synonyms;
-
     for synonym in synonyms_list {
-        let alternative_list = index_synonyms
-            .synonyms(&reader, synonym.as_bytes())
-            .unwrap()
-            .unwrap()
-            .stream()
-            .into_strs()
-            .unwrap();
-        response.insert(synonym, alternative_list);
+        if let Some(alternatives) = alternatives(&index, &reader, &synonym)? {
+            response.insert(synonym, alternatives);
+        }
     }
 
     Ok(tide::response::json(response))
@@ -68,14 +129,8 @@ pub async fn get(ctx: Context<Data>) -> SResult<Response> {
     let env = &ctx.state().db.env;
     let reader = env.read_txn().map_err(ResponseError::internal)?;
 
-    let synonym_list = index
-        .synonyms
-        .synonyms(&reader, synonym.as_bytes())
-        .unwrap()
-        .unwrap()
-        .stream()
-        .into_strs()
-        .unwrap();
+    let synonym_list = alternatives(&index, &reader, &synonym)?
+        .ok_or_else(|| ResponseError::not_found(format!("synonym {} not found", synonym)))?;
 
     Ok(tide::response::json(synonym_list))
 }
@@ -90,17 +145,23 @@ pub async fn create(mut ctx: Context<Data>) -> SResult<Response> {
     let env = &ctx.state().db.env;
     let mut writer = env.write_txn().map_err(ResponseError::internal)?;
 
+    if let Some(response) = ctx.replayed_update(&writer, &index)? {
+        return Ok(response);
+    }
+
     let mut synonyms_addition = index.synonyms_addition();
 
     match data.clone() {
         Synonym::OneWay(content) => {
-            synonyms_addition.add_synonym(content.input, content.synonyms.into_iter())
+            let alternatives = content.synonyms.iter().map(Alternative::weighted);
+            synonyms_addition.add_weighted_synonym(content.input, alternatives)
         }
         Synonym::MultiWay { mut synonyms } => {
             if synonyms.len() > 1 {
                 for _ in 0..synonyms.len() {
                     let (first, elems) = synonyms.split_first().unwrap();
-                    synonyms_addition.add_synonym(first, elems.iter());
+                    let alternatives = elems.iter().map(Alternative::weighted);
+                    synonyms_addition.add_weighted_synonym(first.word(), alternatives);
                     synonyms.rotate_left(1);
                 }
             }
@@ -111,6 +172,9 @@ pub async fn create(mut ctx: Context<Data>) -> SResult<Response> {
         .finalize(&mut writer)
         .map_err(ResponseError::internal)?;
 
+    ctx.register_update(&mut writer, &index, update_id)?;
+    ctx.audit(&mut writer, AuditAction::SynonymsUpdate)?;
+
     writer.commit().map_err(ResponseError::internal)?;
 
     let response_body = IndexUpdateResponse { update_id };
@@ -123,17 +187,25 @@ pub async fn update(mut ctx: Context<Data>) -> SResult<Response> {
     ctx.is_allowed(SettingsWrite)?;
     let synonym = ctx.url_param("synonym")?;
     let index = ctx.index()?;
-    let data: Vec<String> = ctx.body_json().await.map_err(ResponseError::bad_request)?;
+    let data: Vec<Alternative> = ctx.body_json().await.map_err(ResponseError::bad_request)?;
 
     let env = &ctx.state().db.env;
     let mut writer = env.write_txn().map_err(ResponseError::internal)?;
 
+    if let Some(response) = ctx.replayed_update(&writer, &index)? {
+        return Ok(response);
+    }
+
     let mut synonyms_addition = index.synonyms_addition();
-    synonyms_addition.add_synonym(synonym.clone(), data.clone().into_iter());
+    let alternatives = data.iter().map(Alternative::weighted);
+    synonyms_addition.add_weighted_synonym(synonym.clone(), alternatives);
     let update_id = synonyms_addition
         .finalize(&mut writer)
         .map_err(ResponseError::internal)?;
 
+    ctx.register_update(&mut writer, &index, update_id)?;
+    ctx.audit(&mut writer, AuditAction::SynonymsUpdate)?;
+
     writer.commit().map_err(ResponseError::internal)?;
 
     let response_body = IndexUpdateResponse { update_id };
@@ -150,12 +222,19 @@ pub async fn delete(ctx: Context<Data>) -> SResult<Response> {
     let env = &ctx.state().db.env;
     let mut writer = env.write_txn().map_err(ResponseError::internal)?;
 
+    if let Some(response) = ctx.replayed_update(&writer, &index)? {
+        return Ok(response);
+    }
+
     let mut synonyms_deletion = index.synonyms_deletion();
     synonyms_deletion.delete_all_alternatives_of(synonym);
     let update_id = synonyms_deletion
         .finalize(&mut writer)
         .map_err(ResponseError::internal)?;
 
+    ctx.register_update(&mut writer, &index, update_id)?;
+    ctx.audit(&mut writer, AuditAction::SynonymsUpdate)?;
+
     writer.commit().map_err(ResponseError::internal)?;
 
     let response_body = IndexUpdateResponse { update_id };
@@ -174,17 +253,23 @@ pub async fn batch_write(mut ctx: Context<Data>) -> SResult<Response> {
     let env = &ctx.state().db.env;
     let mut writer = env.write_txn().map_err(ResponseError::internal)?;
 
+    if let Some(response) = ctx.replayed_update(&writer, &index)? {
+        return Ok(response);
+    }
+
     let mut synonyms_addition = index.synonyms_addition();
     for raw in data {
         match raw {
             Synonym::OneWay(content) => {
-                synonyms_addition.add_synonym(content.input, content.synonyms.into_iter())
+                let alternatives = content.synonyms.iter().map(Alternative::weighted);
+                synonyms_addition.add_weighted_synonym(content.input, alternatives)
             }
             Synonym::MultiWay { mut synonyms } => {
                 if synonyms.len() > 1 {
                     for _ in 0..synonyms.len() {
                         let (first, elems) = synonyms.split_first().unwrap();
-                        synonyms_addition.add_synonym(first, elems.iter());
+                        let alternatives = elems.iter().map(Alternative::weighted);
+                        synonyms_addition.add_weighted_synonym(first.word(), alternatives);
                         synonyms.rotate_left(1);
                     }
                 }
@@ -195,6 +280,9 @@ pub async fn batch_write(mut ctx: Context<Data>) -> SResult<Response> {
         .finalize(&mut writer)
         .map_err(ResponseError::internal)?;
 
+    ctx.register_update(&mut writer, &index, update_id)?;
+    ctx.audit(&mut writer, AuditAction::SynonymsUpdate)?;
+
     writer.commit().map_err(ResponseError::internal)?;
 
     let response_body = IndexUpdateResponse { update_id };
@@ -210,6 +298,10 @@ pub async fn clear(ctx: Context<Data>) -> SResult<Response> {
     let env = &ctx.state().db.env;
     let mut writer = env.write_txn().map_err(ResponseError::internal)?;
 
+    if let Some(response) = ctx.replayed_update(&writer, &index)? {
+        return Ok(response);
+    }
+
     let synonyms_fst = index
         .main
         .synonyms_fst(&writer)
@@ -226,6 +318,9 @@ pub async fn clear(ctx: Context<Data>) -> SResult<Response> {
         .finalize(&mut writer)
         .map_err(ResponseError::internal)?;
 
+    ctx.register_update(&mut writer, &index, update_id)?;
+    ctx.audit(&mut writer, AuditAction::SynonymsUpdate)?;
+
     writer.commit().map_err(ResponseError::internal)?;
 
     let response_body = IndexUpdateResponse { update_id };
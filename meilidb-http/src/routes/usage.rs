@@ -0,0 +1,40 @@
+use meilidb_dto::IndexUsageResponse;
+use tide::{Context, Response};
+
+use crate::error::{ResponseError, SResult};
+use crate::helpers::tide::ContextExt;
+use crate::models::token::ACL::*;
+use crate::usage::quota_warning;
+use crate::Data;
+
+/// The searches of the index in the current hour and in the last day, with its quota.
+pub async fn get_index_usage(ctx: Context<Data>) -> SResult<Response> {
+    ctx.is_allowed(IndexesRead)?;
+
+    // the usage of the deleted indexes is only listed by the global route
+    ctx.index()?;
+    let index_uid = ctx.url_param("index")?;
+
+    let env = &ctx.state().db.env;
+    let reader = env.read_txn().map_err(ResponseError::internal)?;
+
+    let quota = ctx
+        .state()
+        .query_quota(&reader, &index_uid)
+        .map_err(ResponseError::internal)?;
+    let usage = ctx.state().usage.index_usage(&index_uid);
+    let exceeded = quota_warning(&quota, &usage).is_some();
+
+    Ok(tide::response::json(IndexUsageResponse {
+        usage,
+        quota,
+        exceeded,
+    }))
+}
+
+/// The searches of all the indexes and of all the keys.
+pub async fn get_usage(ctx: Context<Data>) -> SResult<Response> {
+    ctx.is_allowed(Admin)?;
+
+    Ok(tide::response::json(ctx.state().usage.usage()))
+}
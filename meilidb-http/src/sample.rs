@@ -0,0 +1,468 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::mem;
+
+use heed::types::{SerdeBincode, Str};
+use indexmap::IndexMap;
+use meilidb_core::serde::DocumentKey;
+use meilidb_core::{DocumentId, Index, MResult};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::data::Data;
+
+const SAMPLE_PREFIX_KEY: &str = "_sample_";
+const SAMPLE_INDEX_SUFFIX: &str = "-sample";
+
+fn sample_key(index_uid: &str) -> String {
+    format!("{}{}", SAMPLE_PREFIX_KEY, index_uid)
+}
+
+pub fn sample_index_uid(index_uid: &str) -> String {
+    format!("{}{}", index_uid, SAMPLE_INDEX_SUFFIX)
+}
+
+/// A bounded subset of the documents of an index, maintained in a shadow index.
+///
+/// The documents are selected by reservoir sampling (Algorithm R), every document
+/// seen by the index has the same probability to be part of the sample whatever
+/// the order in which the documents were added.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Sample {
+    pub size: usize,
+    /// The number of documents offered to the reservoir.
+    pub seen: u64,
+    /// The identifiers of the documents currently in the sample.
+    pub slots: Vec<String>,
+}
+
+/// The outcome of offering a batch of documents to the reservoir.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct SampleChanges {
+    /// The identifiers that must be added to (or updated in) the sample index.
+    pub kept: HashSet<String>,
+    /// The identifiers that must be removed from the sample index.
+    pub evicted: HashSet<String>,
+}
+
+impl Sample {
+    pub fn new(size: usize) -> Sample {
+        Sample {
+            size,
+            seen: 0,
+            slots: Vec::with_capacity(size),
+        }
+    }
+
+    /// Offers the given documents to the reservoir. Documents that are already part
+    /// of the sample are always kept, they are updates of the sampled documents.
+    pub fn offer<R: Rng, I>(&mut self, keys: I, rng: &mut R) -> SampleChanges
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let mut positions: HashMap<_, _> = self
+            .slots
+            .iter()
+            .cloned()
+            .enumerate()
+            .map(|(i, key)| (key, i))
+            .collect();
+
+        let mut changes = SampleChanges::default();
+        let mut inserted = HashSet::new();
+
+        for key in keys {
+            if positions.contains_key(&key) {
+                changes.kept.insert(key);
+                continue;
+            }
+
+            self.seen += 1;
+
+            if self.slots.len() < self.size {
+                positions.insert(key.clone(), self.slots.len());
+                self.slots.push(key.clone());
+                inserted.insert(key.clone());
+                changes.kept.insert(key);
+                continue;
+            }
+
+            let position = rng.gen_range(0, self.seen);
+            if position < self.size as u64 {
+                let position = position as usize;
+                let evicted = mem::replace(&mut self.slots[position], key.clone());
+                positions.remove(&evicted);
+                positions.insert(key.clone(), position);
+
+                // a document inserted earlier in this batch is not yet in the sample index
+                changes.kept.remove(&evicted);
+                if !inserted.remove(&evicted) {
+                    changes.evicted.insert(evicted);
+                }
+
+                inserted.insert(key.clone());
+                changes.kept.insert(key);
+            }
+        }
+
+        changes
+    }
+
+    /// Removes the given documents from the sample, returns the removed ones.
+    pub fn remove(&mut self, keys: &HashSet<String>) -> HashSet<String> {
+        let mut removed = HashSet::new();
+        self.slots.retain(|key| {
+            if keys.contains(key) {
+                removed.insert(key.clone());
+                false
+            } else {
+                true
+            }
+        });
+        removed
+    }
+}
+
+impl Data {
+    pub fn sample(&self, reader: &heed::RoTxn, index_uid: &str) -> MResult<Option<Sample>> {
+        let sample = self
+            .db
+            .common_store()
+            .get::<Str, SerdeBincode<Sample>>(reader, &sample_key(index_uid))?;
+        Ok(sample)
+    }
+
+    pub fn put_sample(
+        &self,
+        writer: &mut heed::RwTxn,
+        index_uid: &str,
+        sample: &Sample,
+    ) -> MResult<()> {
+        self.db.common_store().put::<Str, SerdeBincode<Sample>>(
+            writer,
+            &sample_key(index_uid),
+            sample,
+        )?;
+        Ok(())
+    }
+
+    pub fn delete_sample(&self, writer: &mut heed::RwTxn, index_uid: &str) -> MResult<bool> {
+        let deleted = self
+            .db
+            .common_store()
+            .delete::<Str>(writer, &sample_key(index_uid))?;
+        Ok(deleted)
+    }
+
+    fn sample_and_index(
+        &self,
+        reader: &heed::RoTxn,
+        index_uid: &str,
+    ) -> MResult<Option<(Sample, Index)>> {
+        let sample = match self.sample(reader, index_uid)? {
+            Some(sample) => sample,
+            None => return Ok(None),
+        };
+
+        match self.db.open_index(sample_index_uid(index_uid)) {
+            Some(index) => Ok(Some((sample, index))),
+            None => Ok(None),
+        }
+    }
+
+    /// Forwards the documents added to an index to its sample index, if any.
+    pub fn sample_documents_addition(
+        &self,
+        writer: &mut heed::RwTxn,
+        index_uid: &str,
+        identifier: &str,
+        documents: &[IndexMap<String, Value>],
+        is_partial: bool,
+    ) -> MResult<()> {
+        let (mut sample, index) = match self.sample_and_index(writer, index_uid)? {
+            Some(value) => value,
+            None => return Ok(()),
+        };
+
+        let keyed: Vec<_> = documents
+            .iter()
+            .filter_map(|document| {
+                let key = document.get(identifier)?;
+                let key = DocumentKey::from_value(key).ok()?;
+                Some((key.to_string(), document))
+            })
+            .collect();
+
+        let keys = keyed.iter().map(|(key, _)| key.clone());
+        let changes = sample.offer(keys, &mut rand::thread_rng());
+
+        if !changes.evicted.is_empty() {
+            let mut documents_deletion = index.documents_deletion();
+            for key in &changes.evicted {
+                let key = DocumentKey::String(key.clone());
+                documents_deletion.delete_document_by_id(key.document_id());
+            }
+            documents_deletion.finalize(writer)?;
+        }
+
+        if !changes.kept.is_empty() {
+            let mut documents_addition = if is_partial {
+                index.documents_partial_addition()
+            } else {
+                index.documents_addition()
+            };
+
+            for (key, document) in keyed {
+                if changes.kept.contains(&key) {
+                    documents_addition.update_document(document.clone());
+                }
+            }
+            documents_addition.finalize(writer)?;
+        }
+
+        self.put_sample(writer, index_uid, &sample)
+    }
+
+    /// Removes the documents deleted from an index from its sample index, if any.
+    pub fn sample_documents_deletion(
+        &self,
+        writer: &mut heed::RwTxn,
+        index_uid: &str,
+        keys: &HashSet<String>,
+    ) -> MResult<()> {
+        let (mut sample, index) = match self.sample_and_index(writer, index_uid)? {
+            Some(value) => value,
+            None => return Ok(()),
+        };
+
+        let removed = sample.remove(keys);
+        if !removed.is_empty() {
+            let mut documents_deletion = index.documents_deletion();
+            for key in removed {
+                let key = DocumentKey::String(key);
+                documents_deletion.delete_document_by_id(key.document_id());
+            }
+            documents_deletion.finalize(writer)?;
+        }
+
+        self.put_sample(writer, index_uid, &sample)
+    }
+
+    /// Removes the deleted documents from the sample, they are given by their internal ids.
+    pub fn sample_documents_deletion_by_ids(
+        &self,
+        writer: &mut heed::RwTxn,
+        index_uid: &str,
+        documents_ids: &HashSet<DocumentId>,
+    ) -> MResult<()> {
+        let sample = match self.sample(writer, index_uid)? {
+            Some(sample) => sample,
+            None => return Ok(()),
+        };
+
+        let keys = sample
+            .slots
+            .into_iter()
+            .filter(|key| documents_ids.contains(&DocumentKey::String(key.clone()).document_id()))
+            .collect();
+
+        self.sample_documents_deletion(writer, index_uid, &keys)
+    }
+
+    /// Empties the sample index of an index whose documents were all cleared.
+    pub fn sample_clear_all(&self, writer: &mut heed::RwTxn, index_uid: &str) -> MResult<()> {
+        let (sample, index) = match self.sample_and_index(writer, index_uid)? {
+            Some(value) => value,
+            None => return Ok(()),
+        };
+
+        index.clear_all(writer)?;
+        self.put_sample(writer, index_uid, &Sample::new(sample.size))
+    }
+}
+
+/// Copies the schema, the settings, the synonyms, the stop words, the decompounding
+/// dictionary, the attributes types, the words limits, the typos table, the frozen words,
+/// the criteria, the ranking profiles and the cold tier settings of an index into its
+/// sample index.
+pub fn copy_index_settings(
+    writer: &mut heed::RwTxn,
+    source: &Index,
+    target: &Index,
+) -> MResult<()> {
+    if let Some(schema) = source.main.schema(writer)? {
+        target.schema_update(writer, schema)?;
+    }
+
+    if let Some(customs) = source.main.customs(writer)? {
+        let customs = customs.to_vec();
+        target.customs_update(writer, customs)?;
+    }
+
+    if let Some(synonyms_fst) = source.main.synonyms_fst(writer)? {
+        let mut synonyms_addition = target.synonyms_addition();
+        for synonym in synonyms_fst.stream().into_strs()? {
+            let alternatives = match source
+                .synonyms
+                .weighted_synonyms(writer, synonym.as_bytes())?
+            {
+                Some(alternatives) => alternatives.stream().into_str_vec()?,
+                None => continue,
+            };
+            let alternatives = alternatives.into_iter().map(|(a, p)| (a, p as u8));
+            synonyms_addition.add_weighted_synonym(synonym, alternatives);
+        }
+        synonyms_addition.finalize(writer)?;
+    }
+
+    if let Some(stop_words_fst) = source.main.stop_words_fst(writer)? {
+        let mut stop_words_addition = target.stop_words_addition();
+        for stop_word in stop_words_fst.stream().into_strs()? {
+            stop_words_addition.add_stop_word(stop_word);
+        }
+        stop_words_addition.finalize(writer)?;
+    }
+
+    if let Some(decompounding_fst) = source.main.decompounding_fst(writer)? {
+        let dictionary: BTreeSet<_> = decompounding_fst
+            .stream()
+            .into_strs()?
+            .into_iter()
+            .collect();
+        target.decompounding_update(writer, dictionary)?;
+    }
+
+    if let Some(attributes_types) = source.main.attributes_types(writer)? {
+        target.attributes_types_update(writer, attributes_types)?;
+    }
+
+    if let Some(words_limits) = source.main.words_limits(writer)? {
+        target.words_limits_update(writer, words_limits)?;
+    }
+
+    if let Some(token_filters) = source.main.token_filters(writer)? {
+        target.token_filters_update(writer, token_filters)?;
+    }
+
+    if let Some(attributes) = source.main.searchable_attributes(writer)? {
+        target.searchable_attributes_update(writer, Some(attributes))?;
+    }
+
+    if let Some(attributes) = source.main.displayed_attributes(writer)? {
+        target.main.put_displayed_attributes(writer, &attributes)?;
+    }
+
+    if let Some(typos_table) = source.main.typos_table(writer)? {
+        target.main.put_typos_table(writer, &typos_table)?;
+    }
+
+    if let Some(frozen_words_fst) = source.main.frozen_words_fst(writer)? {
+        target
+            .main
+            .put_frozen_words_fst(writer, &frozen_words_fst)?;
+    }
+
+    if let Some(rules) = source.main.criteria(writer)? {
+        target.main.put_criteria(writer, &rules)?;
+    }
+
+    if let Some(profiles) = source.main.ranking_profiles(writer)? {
+        target.main.put_ranking_profiles(writer, &profiles)?;
+    }
+
+    if let Some(escape_html) = source.main.html_escaping(writer)? {
+        target.main.put_html_escaping(writer, escape_html)?;
+    }
+
+    if let Some(normalization) = source.main.facets_normalization(writer)? {
+        target
+            .main
+            .put_facets_normalization(writer, &normalization)?;
+    }
+
+    if let Some(prefix) = source.main.prefix_search(writer)? {
+        target.main.put_prefix_search(writer, prefix)?;
+    }
+
+    if let Some(flatten) = source.main.flatten_documents(writer)? {
+        target.main.put_flatten_documents(writer, flatten)?;
+    }
+
+    if let Some(discovery) = source.main.attributes_discovery(writer)? {
+        target.main.put_attributes_discovery(writer, discovery)?;
+    }
+
+    if let Some(settings) = source.main.cold_tier(writer)? {
+        target.main.put_cold_tier(writer, &settings)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn keys(range: std::ops::Range<usize>) -> Vec<String> {
+        range.map(|i| i.to_string()).collect()
+    }
+
+    #[test]
+    fn reservoir_is_bounded() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let mut sample = Sample::new(10);
+
+        let changes = sample.offer(keys(0..5), &mut rng);
+        assert_eq!(changes.kept.len(), 5);
+        assert!(changes.evicted.is_empty());
+
+        for start in (5..1000).step_by(50) {
+            let changes = sample.offer(keys(start..start + 50), &mut rng);
+            assert!(changes
+                .evicted
+                .iter()
+                .all(|key| !sample.slots.contains(key)));
+            assert!(changes.kept.iter().all(|key| sample.slots.contains(key)));
+        }
+
+        assert_eq!(sample.seen, 1005);
+        assert_eq!(sample.slots.len(), 10);
+
+        let unique: HashSet<_> = sample.slots.iter().collect();
+        assert_eq!(unique.len(), 10);
+    }
+
+    #[test]
+    fn sampled_documents_are_updated() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let mut sample = Sample::new(3);
+
+        sample.offer(keys(0..3), &mut rng);
+        let changes = sample.offer(vec!["1".to_string()], &mut rng);
+
+        assert_eq!(sample.seen, 3);
+        assert!(changes.kept.contains("1"));
+        assert!(changes.evicted.is_empty());
+    }
+
+    #[test]
+    fn removed_documents_free_slots() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let mut sample = Sample::new(3);
+
+        sample.offer(keys(0..3), &mut rng);
+
+        let to_remove: HashSet<_> = keys(1..5).into_iter().collect();
+        let removed = sample.remove(&to_remove);
+
+        let expected: HashSet<_> = keys(1..3).into_iter().collect();
+        assert_eq!(removed, expected);
+        assert_eq!(sample.slots, vec!["0".to_string()]);
+
+        let changes = sample.offer(keys(10..12), &mut rng);
+        assert_eq!(changes.kept.len(), 2);
+        assert_eq!(sample.slots.len(), 3);
+    }
+}
@@ -0,0 +1,265 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use heed::types::{SerdeBincode, Str};
+use log::{error, info};
+use meilidb_core::MResult;
+use serde::{Deserialize, Serialize};
+
+use crate::data::Data;
+
+const SNAPSHOTS_RESULTS_KEY: &str = "snapshots-results";
+const LAST_SNAPSHOT_SUCCESS_KEY: &str = "last-snapshot-success";
+const SNAPSHOT_FILE_PREFIX: &str = "snapshot-";
+const SNAPSHOT_FILE_EXTENSION: &str = "mdb";
+
+// the number of snapshot results kept in the common store
+const MAX_SNAPSHOTS_RESULTS: usize = 100;
+
+type SerdeDatetime = SerdeBincode<DateTime<Utc>>;
+type SerdeSnapshotsResults = SerdeBincode<Vec<SnapshotResult>>;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotResult {
+    pub snapshot_id: u64,
+    pub path: String,
+    pub error: Option<String>,
+    pub duration: f64, // in seconds
+    pub enqueued_at: DateTime<Utc>,
+    pub processed_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SnapshotPolicy {
+    pub dir: PathBuf,
+    pub interval: Duration,
+    pub retention: usize,
+}
+
+impl Data {
+    pub fn snapshots_results(&self, reader: &heed::RoTxn) -> MResult<Vec<SnapshotResult>> {
+        match self
+            .db
+            .common_store()
+            .get::<Str, SerdeSnapshotsResults>(reader, SNAPSHOTS_RESULTS_KEY)?
+        {
+            Some(results) => Ok(results),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    pub fn last_snapshot_success(&self, reader: &heed::RoTxn) -> MResult<Option<DateTime<Utc>>> {
+        match self
+            .db
+            .common_store()
+            .get::<Str, SerdeDatetime>(reader, LAST_SNAPSHOT_SUCCESS_KEY)?
+        {
+            Some(datetime) => Ok(Some(datetime)),
+            None => Ok(None),
+        }
+    }
+
+    fn push_snapshot_result(
+        &self,
+        writer: &mut heed::RwTxn,
+        result: SnapshotResult,
+    ) -> MResult<()> {
+        let common_store = self.db.common_store();
+
+        if result.error.is_none() {
+            common_store.put::<Str, SerdeDatetime>(
+                writer,
+                LAST_SNAPSHOT_SUCCESS_KEY,
+                &result.processed_at,
+            )?;
+        }
+
+        let mut results = self.snapshots_results(writer)?;
+        results.push(result);
+
+        let len = results.len();
+        if len > MAX_SNAPSHOTS_RESULTS {
+            results.drain(..len - MAX_SNAPSHOTS_RESULTS);
+        }
+
+        common_store.put::<Str, SerdeSnapshotsResults>(writer, SNAPSHOTS_RESULTS_KEY, &results)?;
+
+        Ok(())
+    }
+}
+
+fn snapshot_path(dir: &Path, datetime: DateTime<Utc>) -> PathBuf {
+    let name = format!(
+        "{}{}.{}",
+        SNAPSHOT_FILE_PREFIX,
+        datetime.format("%Y%m%d%H%M%S"),
+        SNAPSHOT_FILE_EXTENSION,
+    );
+    dir.join(name)
+}
+
+/// Removes the oldest snapshots of the directory, keeping only the `retention` most recent ones.
+fn apply_retention(dir: &Path, retention: usize) -> std::io::Result<()> {
+    let mut snapshots = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let is_snapshot = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .map_or(false, |name| {
+                name.starts_with(SNAPSHOT_FILE_PREFIX) && name.ends_with(SNAPSHOT_FILE_EXTENSION)
+            });
+
+        if is_snapshot {
+            snapshots.push(path);
+        }
+    }
+
+    // file names contain the creation datetime,
+    // the lexicographic order is the chronological one
+    snapshots.sort_unstable();
+
+    let len = snapshots.len();
+    for path in snapshots.into_iter().take(len.saturating_sub(retention)) {
+        info!("removing expired snapshot {}", path.display());
+        fs::remove_file(path)?;
+    }
+
+    Ok(())
+}
+
+fn take_snapshot(data: &Data, policy: &SnapshotPolicy, snapshot_id: u64) -> SnapshotResult {
+    let enqueued_at = Utc::now();
+    let start = Instant::now();
+    let path = snapshot_path(&policy.dir, enqueued_at);
+
+    let result = fs::create_dir_all(&policy.dir)
+        .map_err(|e| e.to_string())
+        .and_then(|_| data.db.backup_to_path(&path).map_err(|e| e.to_string()))
+        .and_then(|_| apply_retention(&policy.dir, policy.retention).map_err(|e| e.to_string()));
+
+    SnapshotResult {
+        snapshot_id,
+        path: path.display().to_string(),
+        error: result.err(),
+        duration: start.elapsed().as_secs_f64(),
+        enqueued_at,
+        processed_at: Utc::now(),
+    }
+}
+
+fn record_snapshot_result(data: &Data, result: SnapshotResult) -> MResult<()> {
+    let env = &data.db.env;
+    let mut writer = env.write_txn()?;
+    data.push_snapshot_result(&mut writer, result)?;
+    writer.commit()?;
+    Ok(())
+}
+
+/// Spawns a thread that periodically snapshots the whole database
+/// into the policy directory and records the outcome of every snapshot.
+pub fn schedule_snapshots(data: Data, policy: SnapshotPolicy) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        let mut snapshot_id = match data.db.env.read_txn() {
+            Ok(reader) => data
+                .snapshots_results(&reader)
+                .ok()
+                .and_then(|results| results.last().map(|r| r.snapshot_id + 1))
+                .unwrap_or(0),
+            Err(e) => {
+                error!("Impossible to get read_txn; {}", e);
+                0
+            }
+        };
+
+        loop {
+            thread::sleep(policy.interval);
+
+            let result = take_snapshot(&data, &policy, snapshot_id);
+            match &result.error {
+                Some(e) => error!("snapshot {} failed; {}", result.path, e),
+                None => info!(
+                    "snapshot {} created in {:.02}s",
+                    result.path, result.duration
+                ),
+            }
+
+            if let Err(e) = record_snapshot_result(&data, result) {
+                error!("Impossible to record the snapshot result; {}", e);
+            }
+
+            snapshot_id += 1;
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn stored_snapshots_results() {
+        let (_dir, data) = Data::temporary();
+
+        let result = |snapshot_id, error: Option<&str>| SnapshotResult {
+            snapshot_id,
+            path: format!("snapshot-{}.mdb", snapshot_id),
+            error: error.map(ToOwned::to_owned),
+            duration: 1.5,
+            enqueued_at: Utc.ymd(2019, 11, 1).and_hms(0, 0, 0),
+            processed_at: Utc.ymd(2019, 11, 1).and_hms(0, 0, 1),
+        };
+
+        let env = &data.db.env;
+        let mut writer = env.write_txn().unwrap();
+        data.push_snapshot_result(&mut writer, result(0, None))
+            .unwrap();
+        data.push_snapshot_result(&mut writer, result(1, Some("disk full")))
+            .unwrap();
+        writer.commit().unwrap();
+
+        let reader = env.read_txn().unwrap();
+        let results = data.snapshots_results(&reader).unwrap();
+        let results: Vec<_> = results
+            .iter()
+            .map(|r| (r.snapshot_id, r.error.as_ref().map(String::as_str)))
+            .collect();
+        assert_eq!(results, vec![(0, None), (1, Some("disk full"))]);
+
+        let last_success = data.last_snapshot_success(&reader).unwrap();
+        assert_eq!(last_success, Some(Utc.ymd(2019, 11, 1).and_hms(0, 0, 1)));
+    }
+
+    #[test]
+    fn retention_removes_oldest_snapshots() {
+        let dir = tempfile::tempdir().unwrap();
+
+        for second in 0..5 {
+            let datetime = Utc.ymd(2019, 11, 1).and_hms(0, 0, second);
+            fs::write(snapshot_path(dir.path(), datetime), b"").unwrap();
+        }
+        fs::write(dir.path().join("unrelated.txt"), b"").unwrap();
+
+        apply_retention(dir.path(), 2).unwrap();
+
+        let mut remaining: Vec<_> = fs::read_dir(dir.path())
+            .unwrap()
+            .map(|e| e.unwrap().file_name().into_string().unwrap())
+            .collect();
+        remaining.sort_unstable();
+
+        assert_eq!(
+            remaining,
+            vec![
+                "snapshot-20191101000003.mdb",
+                "snapshot-20191101000004.mdb",
+                "unrelated.txt",
+            ]
+        );
+    }
+}
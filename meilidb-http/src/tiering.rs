@@ -0,0 +1,49 @@
+use std::thread;
+use std::time::Duration;
+
+use chrono::Utc;
+use log::{error, info};
+use meilidb_core::MResult;
+
+use crate::data::Data;
+
+fn tier_index(data: &Data, index_uid: &str, now: u64) -> MResult<()> {
+    let index = match data.db.open_index(index_uid) {
+        Some(index) => index,
+        None => return Ok(()),
+    };
+
+    let env = &data.db.env;
+    let mut writer = env.write_txn()?;
+    let report = index.tier_documents(&mut writer, now)?;
+    writer.commit()?;
+
+    if report.moved_to_cold != 0 || report.moved_to_hot != 0 {
+        info!(
+            "index {}: {} documents moved to the cold tier, {} moved back to the hot tier",
+            index_uid, report.moved_to_cold, report.moved_to_hot
+        );
+    }
+
+    Ok(())
+}
+
+/// Spawns a thread that periodically moves the documents of every index
+/// between the hot and the cold tiers according to their cold tier settings.
+pub fn schedule_tiering(data: Data, interval: Duration) -> thread::JoinHandle<()> {
+    thread::spawn(move || loop {
+        thread::sleep(interval);
+
+        // the documents are moved once the database is writable again
+        if data.db.is_read_only() {
+            continue;
+        }
+
+        let now = Utc::now().timestamp() as u64;
+        for index_uid in data.db.indexes_uids() {
+            if let Err(e) = tier_index(&data, &index_uid, now) {
+                error!("Impossible to tier the documents of {}; {}", index_uid, e);
+            }
+        }
+    })
+}
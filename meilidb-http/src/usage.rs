@@ -0,0 +1,349 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use chrono::Utc;
+use heed::types::{SerdeBincode, Str};
+use log::error;
+use meilidb_core::{Database, MResult};
+use meilidb_dto::{QueryQuotaBody, UsageBody, UsageResponse};
+use serde::{Deserialize, Serialize};
+
+use crate::data::Data;
+
+const INDEX_USAGE_PREFIX_KEY: &str = "_index_usage_";
+const KEY_USAGE_PREFIX_KEY: &str = "_key_usage_";
+const QUERY_QUOTA_PREFIX_KEY: &str = "_query_quota_";
+
+/// The number of hours the searches are counted for.
+const HOURS_KEPT: i64 = 24;
+
+pub const QUOTA_WARNING_HEADER: &str = "X-Meili-Quota-Warning";
+
+type SerdeUsageCounter = SerdeBincode<UsageCounter>;
+
+fn current_hour() -> i64 {
+    Utc::now().timestamp() / 3600
+}
+
+fn query_quota_key(index_uid: &str) -> String {
+    format!("{}{}", QUERY_QUOTA_PREFIX_KEY, index_uid)
+}
+
+fn index_usage_key(index_uid: &str) -> String {
+    format!("{}{}", INDEX_USAGE_PREFIX_KEY, index_uid)
+}
+
+/// The number of searches made in each of the last hours,
+/// the hours are counted since the Unix epoch.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UsageCounter {
+    hours: BTreeMap<i64, u64>,
+}
+
+impl UsageCounter {
+    fn record(&mut self, hour: i64) {
+        *self.hours.entry(hour).or_insert(0) += 1;
+        // the hours that are too old to be counted are forgotten
+        self.hours = self.hours.split_off(&(hour - HOURS_KEPT + 1));
+    }
+
+    fn usage(&self, hour: i64) -> UsageBody {
+        let searches_this_hour = self.hours.get(&hour).cloned().unwrap_or(0);
+        let searches_last_day = self
+            .hours
+            .range(hour - HOURS_KEPT + 1..=hour)
+            .map(|(_, count)| count)
+            .sum();
+
+        UsageBody {
+            searches_this_hour,
+            searches_last_day,
+        }
+    }
+}
+
+#[derive(Default)]
+struct Counters {
+    indexes: HashMap<String, UsageCounter>,
+    keys: HashMap<String, UsageCounter>,
+    dirty: bool,
+}
+
+/// Counts the searches by index and by key identifier, the counters are kept in memory
+/// and periodically written to the common store to not slow down the searches.
+#[derive(Default)]
+pub struct UsageMeter {
+    counters: Mutex<Counters>,
+}
+
+impl UsageMeter {
+    pub fn load(db: &Database) -> MResult<UsageMeter> {
+        let mut counters = Counters::default();
+        let reader = db.env.read_txn()?;
+        let common_store = db.common_store();
+
+        for result in
+            common_store.prefix_iter::<Str, SerdeUsageCounter>(&reader, INDEX_USAGE_PREFIX_KEY)?
+        {
+            let (key, counter) = result?;
+            let index_uid = &key[INDEX_USAGE_PREFIX_KEY.len()..];
+            counters.indexes.insert(index_uid.to_owned(), counter);
+        }
+
+        for result in
+            common_store.prefix_iter::<Str, SerdeUsageCounter>(&reader, KEY_USAGE_PREFIX_KEY)?
+        {
+            let (key, counter) = result?;
+            let key_id = &key[KEY_USAGE_PREFIX_KEY.len()..];
+            counters.keys.insert(key_id.to_owned(), counter);
+        }
+
+        Ok(UsageMeter {
+            counters: Mutex::new(counters),
+        })
+    }
+
+    /// Forgets the searches of a deleted index, the counter is removed from
+    /// the common store in the transaction deleting the index.
+    fn remove_index(
+        &self,
+        writer: &mut heed::RwTxn,
+        db: &Database,
+        index_uid: &str,
+    ) -> MResult<()> {
+        self.counters.lock().unwrap().indexes.remove(index_uid);
+        db.common_store()
+            .delete::<Str>(writer, &index_usage_key(index_uid))?;
+        Ok(())
+    }
+
+    /// Counts a search of the index, made with the key of the given identifier if it is not
+    /// the master key, and returns the usage of the index including this search.
+    pub fn record(&self, index_uid: &str, key_id: Option<&str>) -> UsageBody {
+        let hour = current_hour();
+        let mut counters = self.counters.lock().unwrap();
+        counters.dirty = true;
+
+        if let Some(key_id) = key_id {
+            counters
+                .keys
+                .entry(key_id.to_owned())
+                .or_default()
+                .record(hour);
+        }
+
+        let counter = counters.indexes.entry(index_uid.to_owned()).or_default();
+        counter.record(hour);
+        counter.usage(hour)
+    }
+
+    pub fn index_usage(&self, index_uid: &str) -> UsageBody {
+        let counters = self.counters.lock().unwrap();
+        match counters.indexes.get(index_uid) {
+            Some(counter) => counter.usage(current_hour()),
+            None => UsageBody::default(),
+        }
+    }
+
+    pub fn usage(&self) -> UsageResponse {
+        let hour = current_hour();
+        let counters = self.counters.lock().unwrap();
+        let usages = |counters: &HashMap<String, UsageCounter>| {
+            counters
+                .iter()
+                .map(|(name, counter)| (name.clone(), counter.usage(hour)))
+                .collect()
+        };
+
+        UsageResponse {
+            indexes: usages(&counters.indexes),
+            keys: usages(&counters.keys),
+        }
+    }
+
+    /// The number of indexes and keys the searches are counted for.
+    pub fn len(&self) -> usize {
+        let counters = self.counters.lock().unwrap();
+        counters.indexes.len() + counters.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Writes the counters to the common store if searches were made since the last flush.
+    pub fn flush(&self, db: &Database) -> MResult<()> {
+        let (indexes, keys) = {
+            let mut counters = self.counters.lock().unwrap();
+            if !counters.dirty {
+                return Ok(());
+            }
+            counters.dirty = false;
+            (counters.indexes.clone(), counters.keys.clone())
+        };
+
+        let common_store = db.common_store();
+        let mut writer = db.env.write_txn()?;
+
+        let prefixes = vec![
+            (INDEX_USAGE_PREFIX_KEY, indexes),
+            (KEY_USAGE_PREFIX_KEY, keys),
+        ];
+
+        for (prefix, counters) in prefixes {
+            for (name, counter) in counters {
+                let key = format!("{}{}", prefix, name);
+                common_store.put::<Str, SerdeUsageCounter>(&mut writer, &key, &counter)?;
+            }
+        }
+
+        writer.commit()?;
+        Ok(())
+    }
+}
+
+/// The warning to return when one of the limits of the quota is exceeded.
+pub fn quota_warning(quota: &QueryQuotaBody, usage: &UsageBody) -> Option<String> {
+    match (quota.max_searches_per_hour, quota.max_searches_per_day) {
+        (Some(max), _) if usage.searches_this_hour > max => Some(format!(
+            "the quota of {} searches per hour is exceeded",
+            max
+        )),
+        (_, Some(max)) if usage.searches_last_day > max => {
+            Some(format!("the quota of {} searches per day is exceeded", max))
+        }
+        _ => None,
+    }
+}
+
+impl Data {
+    pub fn query_quota(&self, reader: &heed::RoTxn, index_uid: &str) -> MResult<QueryQuotaBody> {
+        let key = query_quota_key(index_uid);
+        let quota = self
+            .db
+            .common_store()
+            .get::<Str, SerdeBincode<QueryQuotaBody>>(reader, &key)?;
+
+        Ok(quota.unwrap_or_default())
+    }
+
+    pub fn put_query_quota(
+        &self,
+        writer: &mut heed::RwTxn,
+        index_uid: &str,
+        quota: &QueryQuotaBody,
+    ) -> MResult<()> {
+        let key = query_quota_key(index_uid);
+        self.db
+            .common_store()
+            .put::<Str, SerdeBincode<QueryQuotaBody>>(writer, &key, quota)?;
+
+        Ok(())
+    }
+
+    pub fn delete_query_quota(&self, writer: &mut heed::RwTxn, index_uid: &str) -> MResult<()> {
+        let key = query_quota_key(index_uid);
+        self.db.common_store().delete::<Str>(writer, &key)?;
+
+        Ok(())
+    }
+
+    pub fn delete_index_usage(&self, writer: &mut heed::RwTxn, index_uid: &str) -> MResult<()> {
+        self.usage.remove_index(writer, &self.db, index_uid)
+    }
+
+    /// Counts a search of the index and returns the quota warning of the index, if any.
+    pub fn record_search(
+        &self,
+        reader: &heed::RoTxn,
+        index_uid: &str,
+        key_id: Option<&str>,
+    ) -> MResult<Option<String>> {
+        let usage = self.usage.record(index_uid, key_id);
+        let quota = self.query_quota(reader, index_uid)?;
+        Ok(quota_warning(&quota, &usage))
+    }
+}
+
+/// Spawns a thread that periodically writes the usage counters to the database.
+pub fn schedule_usage_flushes(data: Data, interval: Duration) -> thread::JoinHandle<()> {
+    thread::spawn(move || loop {
+        thread::sleep(interval);
+
+        if let Err(e) = data.usage.flush(&data.db) {
+            error!("Impossible to write the usage counters; {}", e);
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::token::key_id;
+
+    #[test]
+    fn rolling_counter() {
+        let mut counter = UsageCounter::default();
+        counter.record(100);
+        counter.record(110);
+        counter.record(110);
+
+        let usage = counter.usage(110);
+        assert_eq!(usage.searches_this_hour, 2);
+        assert_eq!(usage.searches_last_day, 3);
+
+        // the searches of more than a day ago are not counted anymore
+        counter.record(124);
+        let usage = counter.usage(124);
+        assert_eq!(usage.searches_this_hour, 1);
+        assert_eq!(usage.searches_last_day, 3);
+        assert_eq!(counter.hours.len(), 2);
+    }
+
+    #[test]
+    fn quota_warnings() {
+        let quota = QueryQuotaBody {
+            max_searches_per_hour: Some(10),
+            max_searches_per_day: Some(100),
+        };
+
+        let usage = |searches_this_hour, searches_last_day| UsageBody {
+            searches_this_hour,
+            searches_last_day,
+        };
+
+        assert_eq!(quota_warning(&quota, &usage(10, 100)), None);
+        assert!(quota_warning(&quota, &usage(11, 11)).is_some());
+        assert!(quota_warning(&quota, &usage(1, 101)).is_some());
+        assert_eq!(
+            quota_warning(&QueryQuotaBody::default(), &usage(1000, 1000)),
+            None
+        );
+    }
+
+    #[test]
+    fn persisted_counters() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = Database::open_or_create(dir.path()).unwrap();
+
+        let reader_key = key_id("READERKEY");
+        let meter = UsageMeter::default();
+        meter.record("movies", Some(&reader_key));
+        meter.record("movies", None);
+        meter.flush(&db).unwrap();
+
+        let meter = UsageMeter::load(&db).unwrap();
+        assert_eq!(meter.index_usage("movies").searches_this_hour, 2);
+        assert_eq!(meter.usage().keys[&reader_key].searches_this_hour, 1);
+
+        let mut writer = db.env.write_txn().unwrap();
+        meter.remove_index(&mut writer, &db, "movies").unwrap();
+        writer.commit().unwrap();
+
+        let meter = UsageMeter::load(&db).unwrap();
+        assert_eq!(meter.index_usage("movies"), UsageBody::default());
+        assert_eq!(meter.usage().keys[&reader_key].searches_this_hour, 1);
+    }
+}
@@ -22,6 +22,10 @@ pub const RANKED: SchemaProps = SchemaProps {
     ranked: true,
 };
 
+/// The maximum number of attributes of a schema, the attributes are numbered with an `u16`
+/// and the maximum value is kept to mark the end of the attributes.
+pub const MAX_ATTRIBUTES: usize = u16::MAX as usize;
+
 #[derive(Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct SchemaProps {
     #[serde(default)]
@@ -191,6 +195,23 @@ impl Schema {
         name
     }
 
+    /// Returns a copy of the schema with the new attributes appended, the existing
+    /// attributes keep their numbers and the ones already known are ignored.
+    pub fn with_new_attributes<I, S>(&self, attributes: I) -> Schema
+    where
+        I: IntoIterator<Item = (S, SchemaProps)>,
+        S: Into<String>,
+    {
+        let mut builder = self.to_builder();
+        for (name, props) in attributes {
+            let name = name.into();
+            if !builder.attributes.contains_key(&name) {
+                builder.new_attribute(name, props);
+            }
+        }
+        builder.build()
+    }
+
     pub fn iter<'a>(&'a self) -> impl Iterator<Item = (&str, SchemaAttr, SchemaProps)> + 'a {
         self.inner.props.iter().map(move |(name, prop)| {
             let attr = self.inner.attrs.get(name).unwrap();
@@ -408,6 +429,24 @@ mod tests {
         assert_eq!(&differences, expected)
     }
 
+    #[test]
+    fn new_attributes() {
+        let mut builder = SchemaBuilder::with_identifier("id");
+        builder.new_attribute("alpha", DISPLAYED);
+        builder.new_attribute("beta", DISPLAYED | INDEXED);
+        let schema = builder.build();
+
+        let new = vec![("gamma", INDEXED), ("alpha", RANKED), ("delta", DISPLAYED)];
+        let extended = schema.with_new_attributes(new);
+
+        assert_eq!(extended.attribute("alpha"), Some(SchemaAttr(0)));
+        assert_eq!(extended.props(SchemaAttr(0)), DISPLAYED);
+        assert_eq!(extended.attribute("beta"), Some(SchemaAttr(1)));
+        assert_eq!(extended.attribute("gamma"), Some(SchemaAttr(2)));
+        assert_eq!(extended.attribute("delta"), Some(SchemaAttr(3)));
+        assert_eq!(extended.number_of_attributes(), 4);
+    }
+
     #[test]
     fn serialize_deserialize() -> bincode::Result<()> {
         let mut builder = SchemaBuilder::with_identifier("id");
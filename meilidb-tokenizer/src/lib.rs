@@ -1,3 +1,8 @@
+mod number;
+
+pub use self::number::{normalize_number, split_unit};
+
+use self::number::is_digits_separator;
 use self::SeparatorCategory::*;
 use deunicode::deunicode_char;
 use slice_group_by::StrGroupBy;
@@ -87,10 +92,68 @@ fn chars_count_index((n, _): (usize, usize), (i, c): (usize, char)) -> (usize, u
     (n + 1, i + c.len_utf8())
 }
 
+// returns the end of the number starting with the group of digits `string[..end]`,
+// the following groups are kept only when they are thousands groups of exactly three
+// digits, "1,000,000" is a single number but "3.14" and "8,10,12" are not
+fn number_end(string: &str, mut end: usize) -> usize {
+    // the first group of a number with thousands separators has at most three digits
+    if end > 3 {
+        return end;
+    }
+
+    loop {
+        let mut chars = string[end..].chars();
+        match (chars.next(), chars.next()) {
+            (Some(s), Some(d)) if is_digits_separator(s) && d.is_ascii_digit() => {
+                let start = end + s.len_utf8();
+                let mut groups = string[start..].split(|c: char| is_separator(c) || is_cjk(c));
+                let group = groups.next().unwrap_or("");
+                let digits = group
+                    .find(|c: char| !c.is_ascii_digit())
+                    .unwrap_or_else(|| group.len());
+
+                if digits != 3 {
+                    return end;
+                }
+
+                if digits == group.len() {
+                    end = start + digits;
+                    continue;
+                }
+
+                // a unit can directly follow the last group, e.g. "1,000kg"
+                return match split_unit(group) {
+                    Some(_) => start + group.len(),
+                    None => end,
+                };
+            }
+            _ => return end,
+        }
+    }
+}
+
 pub fn split_query_string(query: &str) -> impl Iterator<Item = &str> {
     Tokenizer::new(query).map(|t| t.word)
 }
 
+fn is_double_quote(c: char) -> bool {
+    c == '"' || deunicode_char(c) == Some("\"")
+}
+
+/// Splits a query into the parts that are between double quotes and the ones that are not,
+/// in order and along with whether they are quoted, a quote that is never closed is ignored.
+pub fn split_query_phrases(query: &str) -> Vec<(&str, bool)> {
+    let parts: Vec<_> = query.split(is_double_quote).collect();
+    let last = parts.len() - 1;
+
+    parts
+        .into_iter()
+        .enumerate()
+        .filter(|(_, part)| !part.is_empty())
+        .map(|(i, part)| (part, i % 2 == 1 && i < last))
+        .collect()
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub struct Token<'a> {
     pub word: &'a str,
@@ -125,9 +188,7 @@ impl<'a> Iterator for Tokenizer<'a> {
     type Item = Token<'a>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let mut iter = self.inner.linear_group_by(same_group_category).peekable();
-
-        while let (Some(string), next_string) = (iter.next(), iter.peek()) {
+        for string in self.inner.linear_group_by(same_group_category) {
             let (count, index) = string.char_indices().fold((0, 0), chars_count_index);
 
             if !is_str_word(string) {
@@ -141,13 +202,24 @@ impl<'a> Iterator for Tokenizer<'a> {
                 continue;
             }
 
+            // the thousands groups separated by commas, points or
+            // apostrophes are kept together, e.g. "1,000" or "1,000kg"
+            let (string, count, index) = if string.chars().all(|c| c.is_ascii_digit()) {
+                let end = number_end(self.inner, index);
+                let string = &self.inner[..end];
+                (string, string.chars().count(), end)
+            } else {
+                (string, count, index)
+            };
+
             let token = Token {
                 word: string,
                 word_index: self.word_index,
                 char_index: self.char_index,
             };
 
-            if next_string.filter(|s| is_str_word(s)).is_some() {
+            let next_char = self.inner[index..].chars().next();
+            if next_char.filter(|c| !is_separator(*c)).is_some() {
                 self.word_index += 1;
             }
 
@@ -501,4 +573,101 @@ mod tests {
         );
         assert_eq!(tokenizer.next(), None);
     }
+
+    #[test]
+    fn numbers() {
+        let mut tokenizer = Tokenizer::new("1,000 kg, 1.5kg et 3. 4");
+
+        assert_eq!(
+            tokenizer.next(),
+            Some(Token {
+                word: "1,000",
+                word_index: 0,
+                char_index: 0
+            })
+        );
+        assert_eq!(
+            tokenizer.next(),
+            Some(Token {
+                word: "kg",
+                word_index: 1,
+                char_index: 6
+            })
+        );
+        // a decimal separator is not a thousands separator
+        assert_eq!(
+            tokenizer.next(),
+            Some(Token {
+                word: "1",
+                word_index: 9,
+                char_index: 10
+            })
+        );
+        assert_eq!(
+            tokenizer.next(),
+            Some(Token {
+                word: "5kg",
+                word_index: 17,
+                char_index: 12
+            })
+        );
+        assert_eq!(
+            tokenizer.next(),
+            Some(Token {
+                word: "et",
+                word_index: 18,
+                char_index: 16
+            })
+        );
+        assert_eq!(
+            tokenizer.next(),
+            Some(Token {
+                word: "3",
+                word_index: 19,
+                char_index: 19
+            })
+        );
+        assert_eq!(
+            tokenizer.next(),
+            Some(Token {
+                word: "4",
+                word_index: 27,
+                char_index: 22
+            })
+        );
+        assert_eq!(tokenizer.next(), None);
+    }
+
+    #[test]
+    fn numbers_thousands_groups() {
+        let words = |s| Tokenizer::new(s).map(|t| t.word).collect::<Vec<_>>();
+
+        assert_eq!(
+            words("1,000,000 1'000 1.000kg"),
+            vec!["1,000,000", "1'000", "1.000kg"]
+        );
+        assert_eq!(words("pages 8,10,12"), vec!["pages", "8", "10", "12"]);
+        assert_eq!(words("pi 3.14"), vec!["pi", "3", "14"]);
+        assert_eq!(words("1,0000 12345,678"), vec!["1", "0000", "12345", "678"]);
+        assert_eq!(words("1,000abc"), vec!["1", "000abc"]);
+    }
+
+    #[test]
+    fn query_phrases() {
+        assert_eq!(
+            split_query_phrases("the \"new york\" times"),
+            vec![("the ", false), ("new york", true), (" times", false)]
+        );
+        assert_eq!(
+            split_query_phrases("\u{201c}new york\u{201d}"),
+            vec![("new york", true)]
+        );
+
+        // an unclosed quote is ignored
+        assert_eq!(
+            split_query_phrases("\"new york\" \"times"),
+            vec![("new york", true), (" ", false), ("times", false)]
+        );
+        assert_eq!(split_query_phrases("new york"), vec![("new york", false)]);
+    }
 }
@@ -0,0 +1,139 @@
+/// The simple units that can directly follow a number, e.g. "10kg".
+const UNITS: &[&str] = &[
+    "%", "b", "cl", "cm", "ft", "g", "gb", "ghz", "h", "hz", "in", "kb", "kg", "khz", "km", "kw",
+    "kwh", "l", "m", "mah", "mb", "mg", "mhz", "min", "ml", "mm", "ms", "px", "s", "t", "tb", "v",
+    "w", "wh",
+];
+
+/// Whether this character can separate the groups of digits of a number,
+/// either as a thousands separator or as a decimal separator.
+pub(crate) fn is_digits_separator(c: char) -> bool {
+    c == ',' || c == '.' || c == '\''
+}
+
+/// Normalizes a formatted number, the thousands separators are removed and the
+/// decimal separator is replaced by a point, e.g. "1,000.50" into "1000.5" and
+/// "1.000,5" into "1000.5". Returns `None` if the word is not a formatted number.
+pub fn normalize_number(word: &str) -> Option<String> {
+    if !word.starts_with(|c: char| c.is_ascii_digit())
+        || !word.ends_with(|c: char| c.is_ascii_digit())
+    {
+        return None;
+    }
+
+    let mut groups = Vec::new();
+    let mut separators = Vec::new();
+    for group in word.split(is_digits_separator) {
+        if group.is_empty() || !group.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        groups.push(group);
+    }
+    separators.extend(word.chars().filter(|c| is_digits_separator(*c)));
+
+    let last = *separators.last()?;
+    let first = groups[0];
+
+    // the last separator is a decimal one if it is not used as
+    // a thousands separator, "1,000.5", "1,5" but not "1,000,000"
+    let is_decimal = if last == '\'' {
+        false
+    } else if separators.iter().any(|s| *s != last) {
+        true
+    } else if separators.len() == 1 {
+        groups[1].len() != 3 || first.len() > 3 || first.starts_with('0')
+    } else {
+        false
+    };
+
+    let (integer, fraction) = if is_decimal {
+        let (fraction, integer) = groups.split_last()?;
+        let separators = &separators[..separators.len() - 1];
+        if separators.contains(&last) {
+            return None;
+        }
+        (integer, Some(fraction))
+    } else {
+        (&groups[..], None)
+    };
+
+    // the thousands separators must all be the same and separate groups of three digits
+    let (head, tail) = integer.split_first()?;
+    let thousands = separators.first().filter(|_| !tail.is_empty());
+    let valid_thousands = thousands.map_or(true, |t| {
+        head.len() <= 3
+            && tail.iter().all(|g| g.len() == 3)
+            && separators[..tail.len()].iter().all(|s| s == t)
+    });
+
+    if !valid_thousands {
+        return None;
+    }
+
+    let mut number = integer.concat();
+    if let Some(fraction) = fraction {
+        let fraction = fraction.trim_end_matches('0');
+        if !fraction.is_empty() {
+            number.push('.');
+            number.push_str(fraction);
+        }
+    }
+
+    Some(number)
+}
+
+/// Splits a word made of a number directly followed by a simple unit,
+/// e.g. "10kg" into "10" and "kg", the unit must be lowercase.
+pub fn split_unit(word: &str) -> Option<(&str, &str)> {
+    let index = word.find(|c: char| !c.is_ascii_digit() && !is_digits_separator(c))?;
+    let (number, unit) = word.split_at(index);
+
+    if number.ends_with(|c: char| c.is_ascii_digit()) && UNITS.contains(&unit) {
+        Some((number, unit))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn thousands_separators() {
+        assert_eq!(normalize_number("1,000"), Some("1000".to_string()));
+        assert_eq!(normalize_number("1.000"), Some("1000".to_string()));
+        assert_eq!(normalize_number("1'000"), Some("1000".to_string()));
+        assert_eq!(normalize_number("12,345,678"), Some("12345678".to_string()));
+        assert_eq!(normalize_number("1,000.50"), Some("1000.5".to_string()));
+        assert_eq!(normalize_number("1.000,50"), Some("1000.5".to_string()));
+    }
+
+    #[test]
+    fn decimal_separators() {
+        assert_eq!(normalize_number("1.5"), Some("1.5".to_string()));
+        assert_eq!(normalize_number("1,5"), Some("1.5".to_string()));
+        assert_eq!(normalize_number("0,500"), Some("0.5".to_string()));
+        assert_eq!(normalize_number("2.0"), Some("2".to_string()));
+    }
+
+    #[test]
+    fn not_numbers() {
+        assert_eq!(normalize_number("1000"), None);
+        assert_eq!(normalize_number("1.2.3"), None);
+        assert_eq!(normalize_number("1,00,000"), None);
+        assert_eq!(normalize_number("1,000.5.5"), None);
+        assert_eq!(normalize_number("v1.5"), None);
+        assert_eq!(normalize_number("1,"), None);
+    }
+
+    #[test]
+    fn units() {
+        assert_eq!(split_unit("10kg"), Some(("10", "kg")));
+        assert_eq!(split_unit("1,5l"), Some(("1,5", "l")));
+        assert_eq!(split_unit("50%"), Some(("50", "%")));
+        assert_eq!(split_unit("10"), None);
+        assert_eq!(split_unit("10abc"), None);
+        assert_eq!(split_unit("kg"), None);
+    }
+}